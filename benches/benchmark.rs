@@ -5,13 +5,18 @@ use rand_xoshiro::Xoshiro256PlusPlus;
 use rans::b64_decoder::{B64RansDecoder, B64RansDecoderMulti};
 use rans::b64_encoder::{B64RansEncoder, B64RansEncoderMulti};
 use rans::byte_decoder::{ByteRansDecoder, ByteRansDecoderMulti};
-use rans::byte_encoder::{ByteRansEncoder, ByteRansEncoderMulti};
+use rans::byte_encoder::{
+    ByteRansEncSymbol, ByteRansEncTable, ByteRansEncoder, ByteRansEncoderMulti,
+};
+use rans::model::{EncModel, Model};
+use rans::simd_encoder::{SimdRansEncSymbol, SimdRansEncoder};
 use rans::{
     RansDecSymbol, RansDecoder, RansDecoderMulti, RansEncSymbol, RansEncoder, RansEncoderMulti,
 };
 
 const TEST_DATA_LEN: usize = 256;
 const SCALE_BITS: u32 = 8;
+const BLOCK_LEN: usize = 1 << 16;
 
 lazy_static! {
     static ref BYTE_DATA_SINGLE: Vec<u8> = gen_data_single(ByteRansEncoder::new(1024));
@@ -82,6 +87,67 @@ where
     );
 }
 
+fn simd_encoder_encode_lanes(c: &mut Criterion) {
+    let symbols = get_enc_symbols::<SimdRansEncSymbol>();
+    let symbol_vals = get_symbols_vals();
+
+    c.bench_function(
+        &format!("SIMD encode {}syms 8 lanes one by one", TEST_DATA_LEN),
+        |b| {
+            b.iter_batched_ref(
+                || SimdRansEncoder::<8>::new(1024),
+                |encoder| {
+                    for i in 0..TEST_DATA_LEN {
+                        encoder.put_at(i % 8, &symbols[symbol_vals[i]]);
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        },
+    );
+    c.bench_function(&format!("SIMD encode {}syms 8 lanes", TEST_DATA_LEN), |b| {
+        b.iter_batched_ref(
+            || SimdRansEncoder::<8>::new(1024),
+            |encoder| {
+                for chunk in symbol_vals.chunks_exact(8) {
+                    let lanes: [usize; 8] = chunk.try_into().unwrap();
+                    encoder.put_symbols_all(&lanes.map(|i| &symbols[i]));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn byte_encoder_encode_block(c: &mut Criterion) {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(1337);
+    let counts: Vec<u32> = (0..256).map(|i| 1 + (i * i) % 97).collect();
+    let model = Model::from_counts(&counts, 12);
+    let data: Vec<u8> = (0..BLOCK_LEN).map(|_| rng.gen()).collect();
+
+    let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    c.bench_function(&format!("Byte encode {}syms by index", BLOCK_LEN), |b| {
+        b.iter_batched_ref(
+            || ByteRansEncoder::new(BLOCK_LEN * 2),
+            |encoder| {
+                for &byte in data.iter().rev() {
+                    encoder.put_index(usize::from(byte), &enc_model);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let table = ByteRansEncTable::new(&model);
+    c.bench_function(&format!("Byte encode {}syms block", BLOCK_LEN), |b| {
+        b.iter_batched_ref(
+            || ByteRansEncoder::new(BLOCK_LEN * 2),
+            |encoder| encoder.encode_block(&table, &data),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
 fn get_enc_symbols<T: RansEncSymbol>() -> [T; 8] {
     let s1 = T::new(0, 3, SCALE_BITS);
     let s2 = T::new(3, 10, SCALE_BITS);
@@ -219,6 +285,8 @@ criterion_group!(
     b64_encoder_encode_single,
     byte_encoder_encode_interleaved,
     b64_encoder_encode_interleaved,
+    simd_encoder_encode_lanes,
+    byte_encoder_encode_block,
     byte_decoder_decode_single,
     b64_decoder_decode_single,
     byte_decoder_decode_interleaved,
@@ -0,0 +1,180 @@
+#ifndef RANS_H
+#define RANS_H
+
+/* Generated with cbindgen from src/capi.rs; do not edit manually. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Maximum number of bits used for frequency quantization supported by the
+// encoder and the decoder of the C API.
+#define RANS_MAX_SCALE_BITS 16
+
+// Result of the fallible C API functions: [`RansStatus::Ok`] on success, or
+// the [`RansError`] that occurred.
+typedef enum RansStatus {
+  // The operation succeeded.
+  RANS_STATUS_OK = 0,
+  // See [`RansError::EmptyInput`].
+  RANS_STATUS_EMPTY_INPUT,
+  // See [`RansError::BufferOverflow`].
+  RANS_STATUS_BUFFER_OVERFLOW,
+  // See [`RansError::InvalidChannel`].
+  RANS_STATUS_INVALID_CHANNEL,
+  // See [`RansError::UnexpectedEof`].
+  RANS_STATUS_UNEXPECTED_EOF,
+  // See [`RansError::InvalidHeader`].
+  RANS_STATUS_INVALID_HEADER,
+  // See [`RansError::InvalidModel`].
+  RANS_STATUS_INVALID_MODEL,
+  // See [`RansError::ChecksumMismatch`].
+  RANS_STATUS_CHECKSUM_MISMATCH,
+  // See [`RansError::InvalidSymbol`].
+  RANS_STATUS_INVALID_SYMBOL,
+  // See [`RansError::VerificationFailed`].
+  RANS_STATUS_VERIFICATION_FAILED,
+  // See [`RansError::InputExhausted`].
+  RANS_STATUS_INPUT_EXHAUSTED,
+  // See [`RansError::InvalidMagic`].
+  RANS_STATUS_INVALID_MAGIC,
+  // See [`RansError::UnsupportedVersion`].
+  RANS_STATUS_UNSUPPORTED_VERSION,
+  // See [`RansError::ContentChecksumMismatch`].
+  RANS_STATUS_CONTENT_CHECKSUM_MISMATCH,
+} RansStatus;
+
+// Opaque single-stream byte-aligned rANS encoder, created with
+// [`rans_encoder_new()`] and destroyed with [`rans_encoder_free()`].
+typedef struct RansEncoderHandle RansEncoderHandle;
+
+// Opaque single-stream byte-aligned rANS decoder, created with
+// [`rans_decoder_new()`] and destroyed with [`rans_decoder_free()`].
+typedef struct RansDecoderHandle RansDecoderHandle;
+
+// Returns a static, NUL-terminated description of given status.
+const char *rans_status_message(RansStatus status);
+
+// Creates a new encoder that can contain `max_len` bytes of encoded data.
+// The returned encoder has to be destroyed with [`rans_encoder_free()`].
+RansEncoderHandle *rans_encoder_new(size_t max_len);
+
+// Destroys an encoder. Does nothing if `encoder` is null.
+//
+// # Safety
+// `encoder` must be null or a pointer returned by [`rans_encoder_new()`]
+// that hasn't been destroyed yet.
+void rans_encoder_free(RansEncoderHandle *encoder);
+
+// Discards all the data put into an encoder, so that it can be reused.
+//
+// # Safety
+// `encoder` must be a valid pointer returned by [`rans_encoder_new()`].
+void rans_encoder_reset(RansEncoderHandle *encoder);
+
+// Encodes a symbol with given cumulative frequency and frequency, quantized
+// to `scale_bits` bits. As with any rANS encoder, the symbols have to be put
+// in the reverse order of decoding.
+//
+// Returns [`RansStatus::InvalidSymbol`] if the symbol definition is invalid
+// (see [`RansError::InvalidSymbol`]), or [`RansStatus::BufferOverflow`] if
+// the encoder is full.
+//
+// # Safety
+// `encoder` must be a valid pointer returned by [`rans_encoder_new()`].
+RansStatus rans_encoder_put(RansEncoderHandle *encoder,
+                            uint32_t cum_freq,
+                            uint32_t freq,
+                            uint32_t scale_bits);
+
+// Flushes an encoder, writing its final state into the data.
+//
+// Returns [`RansStatus::BufferOverflow`] if the encoder is full.
+//
+// # Safety
+// `encoder` must be a valid pointer returned by [`rans_encoder_new()`].
+RansStatus rans_encoder_flush(RansEncoderHandle *encoder);
+
+// Returns a pointer to the data encoded so far, storing its length in
+// `*len`. The pointer stays valid until the encoder is modified or
+// destroyed.
+//
+// # Safety
+// `encoder` must be a valid pointer returned by [`rans_encoder_new()`], and
+// `len` must be valid for writes.
+const uint8_t *rans_encoder_data(const RansEncoderHandle *encoder, size_t *len);
+
+// Creates a new decoder of a copy of given data, storing it in `*decoder`.
+// The decoder has to be destroyed with [`rans_decoder_free()`].
+//
+// Returns [`RansStatus::EmptyInput`] if `len` is zero, or
+// [`RansStatus::UnexpectedEof`] if the data is too short to contain the
+// initial state.
+//
+// # Safety
+// `data` must be valid for reads of `len` bytes (or can be null if `len` is
+// zero), and `decoder` must be valid for writes.
+RansStatus rans_decoder_new(const uint8_t *data, size_t len, RansDecoderHandle **decoder);
+
+// Destroys a decoder. Does nothing if `decoder` is null.
+//
+// # Safety
+// `decoder` must be null or a pointer created by [`rans_decoder_new()`] that
+// hasn't been destroyed yet.
+void rans_decoder_free(RansDecoderHandle *decoder);
+
+// Stores the cumulative frequency of the next symbol, quantized to
+// `scale_bits` bits, in `*cum_freq`. The symbol whose range contains it has
+// to be passed to [`rans_decoder_advance()`] next.
+//
+// Returns [`RansStatus::InvalidSymbol`] if `scale_bits` is larger than
+// [`RANS_MAX_SCALE_BITS`].
+//
+// # Safety
+// `decoder` must be a valid pointer created by [`rans_decoder_new()`], and
+// `cum_freq` must be valid for writes.
+RansStatus rans_decoder_get(RansDecoderHandle *decoder, uint32_t scale_bits, uint32_t *cum_freq);
+
+// Advances a decoder past a symbol with given cumulative frequency and
+// frequency, quantized to `scale_bits` bits.
+//
+// Returns [`RansStatus::InvalidSymbol`] if the symbol definition is invalid
+// (see [`RansError::InvalidSymbol`]), or [`RansStatus::InputExhausted`] if
+// the data ended (see [`RansError::InputExhausted`]), in which case the
+// decoder shouldn't be used anymore.
+//
+// # Safety
+// `decoder` must be a valid pointer created by [`rans_decoder_new()`].
+RansStatus rans_decoder_advance(RansDecoderHandle *decoder,
+                                uint32_t cum_freq,
+                                uint32_t freq,
+                                uint32_t scale_bits);
+
+// Compresses a byte buffer with [`simple::compress()`], storing the
+// compressed data in `*output` and its length in `*output_len`. The output
+// has to be freed with [`rans_buffer_free()`].
+//
+// # Safety
+// `data` must be valid for reads of `len` bytes (or can be null if `len` is
+// zero), and `output` and `output_len` must be valid for writes.
+RansStatus rans_compress(const uint8_t *data, size_t len, uint8_t **output, size_t *output_len);
+
+// Decompresses data produced by [`rans_compress()`], storing the original
+// data in `*output` and its length in `*output_len`. The output has to be
+// freed with [`rans_buffer_free()`]. Nothing is stored on error.
+//
+// Returns any of the errors of [`simple::decompress()`].
+//
+// # Safety
+// `data` must be valid for reads of `len` bytes (or can be null if `len` is
+// zero), and `output` and `output_len` must be valid for writes.
+RansStatus rans_decompress(const uint8_t *data, size_t len, uint8_t **output, size_t *output_len);
+
+// Frees a buffer returned by [`rans_compress()`] or [`rans_decompress()`].
+// Does nothing if `data` is null.
+//
+// # Safety
+// `data` and `len` must be a buffer and its length returned by
+// [`rans_compress()`] or [`rans_decompress()`] that hasn't been freed yet.
+void rans_buffer_free(uint8_t *data, size_t len);
+
+#endif /* RANS_H */
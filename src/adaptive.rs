@@ -0,0 +1,546 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::decoder_input::DecoderInput;
+use crate::model::Model;
+use crate::{RansDecSymbol, RansDecoder, RansEncSymbol, RansEncoder, RansEncoderMulti, RansError};
+
+/// Default amount a symbol count is incremented by after each symbol.
+const DEFAULT_INCREMENT: u32 = 32;
+/// Default number of symbols after which the frequencies are recomputed.
+const DEFAULT_RESCALE_INTERVAL: u32 = 64;
+/// Sum of the counts above which all counts get halved, so that recent
+/// symbols have more weight than the old ones.
+const MAX_TOTAL: u32 = 1 << 16;
+/// Sum of the counts above which all counts get halved in quasi-static
+/// models, only to keep them from overflowing.
+const QUASI_STATIC_MAX_TOTAL: u32 = 1 << 30;
+
+/// Order-0 adaptive model.
+///
+/// The model starts with all symbols being equally probable and keeps track
+/// of how many times each symbol occurred so far. The frequencies used for
+/// coding are recomputed from these counts every `rescale_interval` symbols,
+/// so the model follows the statistics of the data as they change, without
+/// the need to transmit a frequency table. The encoder and the decoder update
+/// their models in exactly the same way, so they always stay in sync.
+///
+/// See [`Self::quasi_static()`] for a cheaper variant rebuilding the table
+/// rarely.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdaptiveModel {
+    counts: Vec<u32>,
+    total: u32,
+    model: Model,
+    increment: u32,
+    max_total: u32,
+    rescale_interval: u32,
+    since_rescale: u32,
+}
+
+impl AdaptiveModel {
+    /// Creates a new adaptive model of an alphabet of `num_symbols` symbols,
+    /// with frequencies normalized to `1 << scale_bits`.
+    ///
+    /// # Panics
+    /// Panics if `num_symbols` is zero or larger than `1 << scale_bits`, or
+    /// if `scale_bits` is larger than 16.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::AdaptiveModel;
+    ///
+    /// let model = AdaptiveModel::new(4, 8);
+    /// assert_eq!(model.model().freqs(), [64, 64, 64, 64]);
+    /// ```
+    #[must_use]
+    pub fn new(num_symbols: usize, scale_bits: u32) -> Self {
+        Self::with_params(
+            num_symbols,
+            scale_bits,
+            DEFAULT_INCREMENT,
+            DEFAULT_RESCALE_INTERVAL,
+        )
+    }
+
+    /// Creates a new adaptive model like [`Self::new()`], but with custom
+    /// adaptation parameters: `increment` is added to the count of each coded
+    /// symbol, and the frequencies are recomputed every `rescale_interval`
+    /// symbols. Larger increments make the model adapt faster; shorter
+    /// intervals make it follow the data more closely, at the cost of speed.
+    ///
+    /// # Panics
+    /// Panics if `num_symbols` is zero or larger than `1 << scale_bits`, if
+    /// `scale_bits` is larger than 16, or if `increment` or
+    /// `rescale_interval` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::AdaptiveModel;
+    ///
+    /// let mut model = AdaptiveModel::with_params(2, 4, 1, 1);
+    /// model.update(0);
+    /// assert_eq!(model.model().freqs(), [11, 5]);
+    /// ```
+    #[must_use]
+    pub fn with_params(
+        num_symbols: usize,
+        scale_bits: u32,
+        increment: u32,
+        rescale_interval: u32,
+    ) -> Self {
+        assert!(scale_bits <= 16, "scale_bits must not be larger than 16");
+        assert!(num_symbols > 0, "the alphabet must not be empty");
+        assert!(
+            num_symbols <= 1 << scale_bits,
+            "too many symbols for given scale_bits"
+        );
+        assert!(increment > 0, "increment must not be zero");
+        assert!(rescale_interval > 0, "rescale_interval must not be zero");
+
+        let counts = vec![1; num_symbols];
+        let model = Model::from_counts(&counts, scale_bits);
+        Self {
+            total: num_symbols as u32,
+            counts,
+            model,
+            increment: increment.min(MAX_TOTAL / 2),
+            max_total: MAX_TOTAL,
+            rescale_interval,
+            since_rescale: 0,
+        }
+    }
+
+    /// Creates a new quasi-static model: every symbol is counted once and
+    /// the counts are never aged, and the frequency table is only rebuilt
+    /// every `rescale_interval` symbols.
+    ///
+    /// Rebuilding the table is the expensive part of adapting the model, so
+    /// with a large interval this is much faster than a model following the
+    /// data closely, while still converging to the statistics of the data.
+    /// It works best for data with mostly stationary statistics.
+    ///
+    /// # Panics
+    /// Panics if `num_symbols` is zero or larger than `1 << scale_bits`, if
+    /// `scale_bits` is larger than 16, or if `rescale_interval` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::AdaptiveModel;
+    ///
+    /// let mut model = AdaptiveModel::quasi_static(2, 4, 3);
+    /// model.update(0);
+    /// model.update(0);
+    /// assert_eq!(model.model().freqs(), [8, 8]);
+    /// model.update(0);
+    /// assert_eq!(model.model().freqs(), [13, 3]);
+    /// ```
+    #[must_use]
+    pub fn quasi_static(num_symbols: usize, scale_bits: u32, rescale_interval: u32) -> Self {
+        Self {
+            max_total: QUASI_STATIC_MAX_TOTAL,
+            ..Self::with_params(num_symbols, scale_bits, 1, rescale_interval)
+        }
+    }
+
+    /// Returns the frequency table currently used for coding.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::AdaptiveModel;
+    ///
+    /// let model = AdaptiveModel::new(2, 4);
+    /// assert_eq!(model.model().freqs(), [8, 8]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Returns the number of symbols after which the frequency table is
+    /// rebuilt.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::AdaptiveModel;
+    ///
+    /// let model = AdaptiveModel::quasi_static(256, 12, 1024);
+    /// assert_eq!(model.rescale_interval(), 1024);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn rescale_interval(&self) -> u32 {
+        self.rescale_interval
+    }
+
+    /// Returns the number of [`Self::update()`] calls left until the
+    /// frequency table is rebuilt; the table returned by [`Self::model()`]
+    /// stays the same until then. This is fully determined by the number of
+    /// symbols coded so far, so the encoder and the decoder always rebuild
+    /// their tables at the same points, and can e.g. cache symbol tables
+    /// built from [`Self::model()`] in between.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::AdaptiveModel;
+    ///
+    /// let mut model = AdaptiveModel::quasi_static(4, 8, 3);
+    /// assert_eq!(model.symbols_until_rescale(), 3);
+    /// model.update(1);
+    /// model.update(1);
+    /// assert_eq!(model.symbols_until_rescale(), 1);
+    /// model.update(1);
+    /// assert_eq!(model.symbols_until_rescale(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn symbols_until_rescale(&self) -> u32 {
+        self.rescale_interval - self.since_rescale
+    }
+
+    /// Returns the number of symbols in the alphabet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::AdaptiveModel;
+    ///
+    /// let model = AdaptiveModel::new(3, 4);
+    /// assert_eq!(model.len(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Returns `true` if the alphabet is empty. This is never the case, as
+    /// models of empty alphabets can't be created.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::AdaptiveModel;
+    ///
+    /// let model = AdaptiveModel::new(3, 4);
+    /// assert!(!model.is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Updates the model after coding `symbol`. Returns `true` if the
+    /// frequency table has been rebuilt.
+    ///
+    /// # Panics
+    /// Panics if `symbol` is out of range of the alphabet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::AdaptiveModel;
+    ///
+    /// let mut model = AdaptiveModel::with_params(2, 4, 1, 2);
+    /// assert!(!model.update(1));
+    /// assert_eq!(model.model().freqs(), [8, 8]);
+    /// assert!(model.update(1));
+    /// assert_eq!(model.model().freqs(), [4, 12]);
+    /// ```
+    pub fn update(&mut self, symbol: usize) -> bool {
+        self.counts[symbol] += self.increment;
+        self.total += self.increment;
+        if self.total > self.max_total {
+            self.total = 0;
+            for count in &mut self.counts {
+                *count = (*count + 1) / 2;
+                self.total += *count;
+            }
+        }
+
+        self.since_rescale += 1;
+        if self.since_rescale >= self.rescale_interval {
+            self.since_rescale = 0;
+            self.model = Model::from_counts(&self.counts, self.model.scale_bits());
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Byte-aligned rANS encoder coding symbols with an [`AdaptiveModel`].
+///
+/// Since rANS decodes the symbols in the reverse order of encoding, the
+/// symbols are buffered and only get encoded (in reverse) when
+/// [`Self::flush()`] is called.
+#[derive(Debug)]
+pub struct AdaptiveEncoder {
+    encoder: ByteRansEncoder,
+    model: AdaptiveModel,
+    symbols: Vec<usize>,
+}
+
+impl AdaptiveEncoder {
+    /// Creates a new `AdaptiveEncoder` instance using given initial `model`.
+    /// The decoder has to be created with the same initial model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::{AdaptiveEncoder, AdaptiveModel};
+    ///
+    /// let encoder = AdaptiveEncoder::new(AdaptiveModel::new(256, 12));
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(model: AdaptiveModel) -> Self {
+        Self {
+            encoder: ByteRansEncoder::with_growable_buffer(),
+            model,
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Adds `symbol` to the data to be encoded.
+    ///
+    /// # Panics
+    /// Panics if `symbol` is out of range of the model's alphabet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::{AdaptiveEncoder, AdaptiveModel};
+    ///
+    /// let mut encoder = AdaptiveEncoder::new(AdaptiveModel::new(256, 12));
+    /// encoder.put(42);
+    /// ```
+    #[inline]
+    pub fn put(&mut self, symbol: usize) {
+        assert!(symbol < self.model.len(), "symbol out of range");
+        self.symbols.push(symbol);
+    }
+
+    /// Encodes all the symbols added so far and flushes the encoder. After
+    /// this, [`Self::data()`] returns the complete encoded data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::{AdaptiveEncoder, AdaptiveModel};
+    ///
+    /// let mut encoder = AdaptiveEncoder::new(AdaptiveModel::new(256, 12));
+    /// for byte in b"hello" {
+    ///     encoder.put(*byte as usize);
+    /// }
+    /// encoder.flush();
+    /// assert!(!encoder.data().is_empty());
+    /// ```
+    pub fn flush(&mut self) {
+        // Run the model forward to find out the frequencies the decoder will
+        // see, then encode them backwards
+        let scale_bits = self.model.model().scale_bits();
+        let mut ranges = Vec::with_capacity(self.symbols.len());
+        for &symbol in &self.symbols {
+            let model = self.model.model();
+            ranges.push((model.cum_freq(symbol), model.freq(symbol)));
+            self.model.update(symbol);
+        }
+
+        for &(cum_freq, freq) in ranges.iter().rev() {
+            self.encoder
+                .put(&ByteRansEncSymbol::new(cum_freq, freq, scale_bits));
+        }
+        self.encoder.flush();
+        self.symbols.clear();
+    }
+
+    /// Returns the encoded data. This is empty until [`Self::flush()`] is
+    /// called.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::{AdaptiveEncoder, AdaptiveModel};
+    ///
+    /// let mut encoder = AdaptiveEncoder::new(AdaptiveModel::new(256, 12));
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        self.encoder.data()
+    }
+}
+
+/// Byte-aligned rANS decoder decoding symbols with an [`AdaptiveModel`].
+#[derive(Debug)]
+pub struct AdaptiveDecoder<'a> {
+    decoder: ByteRansDecoder<'a>,
+    model: AdaptiveModel,
+}
+
+impl<'a> AdaptiveDecoder<'a> {
+    /// Creates a new `AdaptiveDecoder` instance with given `data`, using
+    /// given initial `model`, which has to be the same as the one passed to
+    /// the encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::{AdaptiveDecoder, AdaptiveModel};
+    ///
+    /// let decoder = AdaptiveDecoder::new([0, 0, 128, 0], AdaptiveModel::new(256, 12));
+    /// ```
+    #[must_use]
+    pub fn new<T: Into<DecoderInput<'a>>>(data: T, model: AdaptiveModel) -> Self {
+        Self {
+            decoder: ByteRansDecoder::new(data),
+            model,
+        }
+    }
+
+    /// Creates a new `AdaptiveDecoder` instance like [`Self::new()`],
+    /// checking that the data is long enough to contain the initial state.
+    ///
+    /// # Errors
+    /// Returns [`RansError::EmptyInput`] if `data` is empty, or
+    /// [`RansError::UnexpectedEof`] if it is too short to contain the initial
+    /// state.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::{AdaptiveDecoder, AdaptiveModel};
+    /// use rans::RansError;
+    ///
+    /// let result = AdaptiveDecoder::try_new([0, 0], AdaptiveModel::new(256, 12));
+    /// assert_eq!(result.unwrap_err(), RansError::UnexpectedEof);
+    /// ```
+    pub fn try_new<T: Into<DecoderInput<'a>>>(
+        data: T,
+        model: AdaptiveModel,
+    ) -> Result<Self, RansError> {
+        Ok(Self {
+            decoder: ByteRansDecoder::try_new(data)?,
+            model,
+        })
+    }
+
+    /// Decodes a single symbol and updates the model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::adaptive::{AdaptiveDecoder, AdaptiveEncoder, AdaptiveModel};
+    ///
+    /// let mut encoder = AdaptiveEncoder::new(AdaptiveModel::new(256, 12));
+    /// encoder.put(42);
+    /// encoder.put(42);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = AdaptiveDecoder::new(encoder.data(), AdaptiveModel::new(256, 12));
+    /// assert_eq!(decoder.decode(), 42);
+    /// assert_eq!(decoder.decode(), 42);
+    /// ```
+    pub fn decode(&mut self) -> usize {
+        let model = self.model.model();
+        let scale_bits = model.scale_bits();
+        let symbol = model.symbol_for(self.decoder.get(scale_bits));
+        let dec_symbol = ByteRansDecSymbol::new(model.cum_freq(symbol), model.freq(symbol));
+        self.decoder.advance(&dec_symbol, scale_bits);
+
+        self.model.update(symbol);
+        symbol
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::adaptive::{AdaptiveDecoder, AdaptiveEncoder, AdaptiveModel};
+
+    #[test]
+    fn test_model_halves_counts() {
+        let mut model = AdaptiveModel::with_params(2, 8, 1 << 14, 1);
+        for _ in 0..4 {
+            model.update(0);
+        }
+        assert!(model.total <= super::MAX_TOTAL);
+        assert_eq!(model.counts.iter().sum::<u32>(), model.total);
+        assert!(model.counts[1] >= 1);
+    }
+
+    #[test]
+    fn test_quasi_static_keeps_counts() {
+        let mut model = AdaptiveModel::quasi_static(2, 12, 4096);
+        for _ in 0..100_000 {
+            model.update(0);
+        }
+        assert_eq!(model.counts, [100_001, 1]);
+    }
+
+    #[test]
+    fn test_rescale_schedule() {
+        let mut model = AdaptiveModel::quasi_static(16, 12, 100);
+        for i in 0..1000_usize {
+            let expected = model.symbols_until_rescale() == 1;
+            let freqs = model.model().freqs().to_vec();
+            assert_eq!(model.update(i % 3), expected);
+            assert_eq!(model.model().freqs() != freqs, expected);
+        }
+    }
+
+    #[test]
+    fn test_quasi_static_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let symbols: Vec<usize> = (0..20000).map(|_| rng.gen_range(0..16)).collect();
+
+        let mut encoder = AdaptiveEncoder::new(AdaptiveModel::quasi_static(256, 12, 1024));
+        for &symbol in &symbols {
+            encoder.put(symbol);
+        }
+        encoder.flush();
+        // The model converges to the 4 bits per symbol of the data after the
+        // first few rebuilds
+        assert!(encoder.data().len() < 11000);
+
+        let mut decoder =
+            AdaptiveDecoder::new(encoder.data(), AdaptiveModel::quasi_static(256, 12, 1024));
+        for &symbol in &symbols {
+            assert_eq!(decoder.decode(), symbol);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        // The statistics change halfway through the data
+        let symbols: Vec<usize> = (0..20000)
+            .map(|i| {
+                if i < 10000 {
+                    rng.gen_range(0..4)
+                } else {
+                    rng.gen_range(200..256)
+                }
+            })
+            .collect();
+
+        let mut encoder = AdaptiveEncoder::new(AdaptiveModel::new(256, 12));
+        for &symbol in &symbols {
+            encoder.put(symbol);
+        }
+        encoder.flush();
+
+        let mut decoder = AdaptiveDecoder::new(encoder.data(), AdaptiveModel::new(256, 12));
+        for &symbol in &symbols {
+            assert_eq!(decoder.decode(), symbol);
+        }
+    }
+
+    #[test]
+    fn test_adapts_to_data() {
+        let mut encoder = AdaptiveEncoder::new(AdaptiveModel::new(256, 12));
+        for _ in 0..10000 {
+            encoder.put(7);
+        }
+        encoder.flush();
+
+        // A static uniform model would need a byte per symbol
+        assert!(encoder.data().len() < 500);
+    }
+}
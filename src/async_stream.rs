@@ -0,0 +1,758 @@
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::frame::RunLengthMode;
+use crate::model::Model;
+use crate::stream::{
+    check_symbol, decode_block, encode_block, invalid_data, read_model_record, symbol_to_byte,
+    write_model_record, DEFAULT_BLOCK_SIZE,
+};
+
+/// Size of the buffer the block data is read into.
+const READ_CHUNK_LEN: usize = 8192;
+
+/// Asynchronous counterpart of [`RansWriter`](crate::stream::RansWriter),
+/// writing to any [`AsyncWrite`] sink.
+///
+/// The stream format is the same, so the data can be read with both
+/// [`AsyncRansReader`] and [`RansReader`](crate::stream::RansReader). The
+/// symbols are buffered into blocks, and each full block is encoded and then
+/// written to the sink without blocking, with the writes being retried when
+/// the sink is ready.
+///
+/// Unlike the synchronous writer, the pending block is not written when the
+/// writer is dropped, so the writer has to be flushed, shut down or finished
+/// with [`Self::finish()`] first.
+///
+/// # Examples
+/// ```
+/// use rans::async_stream::{AsyncRansReader, AsyncRansWriter};
+/// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let mut writer = AsyncRansWriter::new(Vec::new());
+/// writer.write_all(b"hello, world").await.unwrap();
+/// let compressed = writer.finish().await.unwrap();
+///
+/// let mut reader = AsyncRansReader::new(compressed.as_slice());
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).await.unwrap();
+/// assert_eq!(decompressed, b"hello, world");
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncRansWriter<W: AsyncWrite + Unpin> {
+    inner: W,
+    model: Option<Model>,
+    block_size: usize,
+    run_length: RunLengthMode,
+    symbols: Vec<usize>,
+    /// Encoded blocks that haven't been written to the sink yet
+    pending: Vec<u8>,
+    /// Number of bytes at the start of `pending` already written
+    written: usize,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncRansWriter<W> {
+    /// Creates a new `AsyncRansWriter` instance compressing bytes written
+    /// into it with an order-0 model computed for each block and stored along
+    /// with it.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansWriter;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut writer = AsyncRansWriter::new(Vec::new());
+    /// writer.write_all(b"hello, world").await.unwrap();
+    /// let compressed = writer.finish().await.unwrap();
+    /// assert!(!compressed.is_empty());
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            model: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+            run_length: RunLengthMode::Never,
+            symbols: Vec::new(),
+            pending: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Creates a new `AsyncRansWriter` instance encoding symbols using given
+    /// fixed `model`, which is not stored in the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansWriter;
+    /// use rans::model::Model;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let model = Model::from_counts(&[3, 1, 1000], 12);
+    /// let mut writer = AsyncRansWriter::with_model(Vec::new(), model);
+    /// writer.write_symbols(&[0, 2, 2, 1]).await.unwrap();
+    /// let compressed = writer.finish().await.unwrap();
+    /// assert!(!compressed.is_empty());
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_model(inner: W, model: Model) -> Self {
+        Self {
+            model: Some(model),
+            ..Self::new(inner)
+        }
+    }
+
+    /// Sets the number of symbols in a single block. Larger blocks make the
+    /// per-block overhead smaller, at the cost of memory usage.
+    ///
+    /// # Panics
+    /// Panics if `block_size` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansWriter;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut writer = AsyncRansWriter::new(Vec::new()).with_block_size(4);
+    /// writer.write_all(b"abcdefghi").await.unwrap();
+    /// // Both full blocks have already been written
+    /// assert!(!writer.get_ref().is_empty());
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        assert!(block_size > 0, "block_size must not be zero");
+        self.block_size = block_size;
+        self
+    }
+
+    /// Sets whether runs of repeated symbols are coded as (symbol, run
+    /// length) pairs. See
+    /// [`RansWriter::with_run_length()`](crate::stream::RansWriter::with_run_length).
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansWriter;
+    /// use rans::frame::RunLengthMode;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut writer = AsyncRansWriter::new(Vec::new()).with_run_length(RunLengthMode::Auto);
+    /// let data: Vec<u8> = (0..100_000).map(|i| (i / 1000 % 2) as u8).collect();
+    /// writer.write_all(&data).await.unwrap();
+    /// let compressed = writer.finish().await.unwrap();
+    /// assert!(compressed.len() < 500);
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_run_length(mut self, run_length: RunLengthMode) -> Self {
+        self.run_length = run_length;
+        self
+    }
+
+    /// Returns a reference to the underlying sink.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansWriter;
+    ///
+    /// let writer = AsyncRansWriter::new(Vec::new());
+    /// assert!(writer.get_ref().is_empty());
+    /// ```
+    #[must_use]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Writes the pending block and switches to coding the following symbols
+    /// with `model`, storing it in the stream. See
+    /// [`RansWriter::set_model()`](crate::stream::RansWriter::set_model).
+    ///
+    /// # Errors
+    /// Returns any error returned by the underlying sink.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::{AsyncRansReader, AsyncRansWriter};
+    /// use rans::model::Model;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut writer = AsyncRansWriter::new(Vec::new());
+    /// writer.write_all(b"a").await.unwrap();
+    /// let model = Model::from_counts(&[1, 1000], 12);
+    /// writer.set_model(model.clone()).await.unwrap();
+    /// writer.write_symbols(&[1, 0]).await.unwrap();
+    /// let compressed = writer.finish().await.unwrap();
+    ///
+    /// let mut reader = AsyncRansReader::new(compressed.as_slice());
+    /// assert_eq!(reader.read_symbol().await.unwrap(), Some(b'a' as usize));
+    /// assert_eq!(reader.read_symbol().await.unwrap(), Some(1));
+    /// assert_eq!(reader.model(), Some(&model));
+    /// # }
+    /// ```
+    pub async fn set_model(&mut self, model: Model) -> io::Result<()> {
+        poll_fn(|cx| self.poll_write_pending(cx)).await?;
+        self.encode_block();
+        write_model_record(&model, &mut self.pending);
+        self.model = Some(model);
+        Ok(())
+    }
+
+    /// Adds a single symbol to the stream.
+    ///
+    /// # Errors
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] if the
+    /// symbol can't be coded with the writer's model (see
+    /// [`RansWriter::write_symbol()`](crate::stream::RansWriter::write_symbol)),
+    /// or any error returned by the underlying sink.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansWriter;
+    /// use rans::model::Model;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let model = Model::from_counts(&[1, 0, 1], 8);
+    /// let mut writer = AsyncRansWriter::with_model(Vec::new(), model);
+    /// assert!(writer.write_symbol(2).await.is_ok());
+    /// assert!(writer.write_symbol(1).await.is_err());
+    /// # }
+    /// ```
+    pub async fn write_symbol(&mut self, symbol: usize) -> io::Result<()> {
+        poll_fn(|cx| self.poll_write_pending(cx)).await?;
+        self.push_symbol(symbol)
+    }
+
+    /// Adds all the `symbols` to the stream.
+    ///
+    /// # Errors
+    /// Same as in the case of [`Self::write_symbol()`]. The symbols preceding
+    /// the invalid one are added to the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansWriter;
+    /// use rans::model::Model;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let model = Model::from_counts(&[1, 1, 1], 8);
+    /// let mut writer = AsyncRansWriter::with_model(Vec::new(), model);
+    /// writer.write_symbols(&[0, 1, 2]).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn write_symbols(&mut self, symbols: &[usize]) -> io::Result<()> {
+        for &symbol in symbols {
+            self.write_symbol(symbol).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending block, flushes the underlying sink and returns it.
+    ///
+    /// # Errors
+    /// Returns any error returned by the underlying sink.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansWriter;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut writer = AsyncRansWriter::new(Vec::new());
+    /// writer.write_all(b"hello").await.unwrap();
+    /// let compressed: Vec<u8> = writer.finish().await.unwrap();
+    /// assert!(!compressed.is_empty());
+    /// # }
+    /// ```
+    pub async fn finish(mut self) -> io::Result<W> {
+        poll_fn(|cx| Pin::new(&mut self).poll_flush(cx)).await?;
+        Ok(self.inner)
+    }
+
+    /// Adds a symbol to the current block, encoding the block if it's full.
+    fn push_symbol(&mut self, symbol: usize) -> io::Result<()> {
+        check_symbol(self.model.as_ref(), symbol)?;
+
+        self.symbols.push(symbol);
+        if self.symbols.len() >= self.block_size {
+            self.encode_block();
+        }
+        Ok(())
+    }
+
+    fn encode_block(&mut self) {
+        if !self.symbols.is_empty() {
+            encode_block(
+                self.model.as_ref(),
+                self.run_length,
+                &self.symbols,
+                &mut self.pending,
+            );
+            self.symbols.clear();
+        }
+    }
+
+    /// Writes all the encoded blocks to the sink.
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            let len =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending[self.written..]))?;
+            if len == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += len;
+        }
+
+        self.pending.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncRansWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+
+        // Stop at the end of a block, so that it's written before accepting
+        // any more data
+        let mut len = 0;
+        for &byte in buf {
+            if let Err(error) = this.push_symbol(usize::from(byte)) {
+                return Poll::Ready(if len > 0 { Ok(len) } else { Err(error) });
+            }
+            len += 1;
+            if !this.pending.is_empty() {
+                break;
+            }
+        }
+        Poll::Ready(Ok(len))
+    }
+
+    /// Writes the pending block, even if it's not full, and flushes the
+    /// underlying sink.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.encode_block();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Asynchronous counterpart of [`RansReader`](crate::stream::RansReader),
+/// reading from any [`AsyncRead`] source.
+///
+/// Reads the blocks written by [`AsyncRansWriter`] or
+/// [`RansWriter`](crate::stream::RansWriter) one at a time, without blocking
+/// when the data of a block is not available yet. The decoded data can be
+/// read either through the [`AsyncRead`] implementation (if the symbols are
+/// bytes), or symbol by symbol with [`Self::read_symbol()`]. Model switches
+/// are handled transparently, like in the synchronous reader.
+///
+/// The block headers and checksums are validated, but a checksum only
+/// protects against accidental corruption, so this should not be used for
+/// data coming from untrusted sources.
+#[derive(Debug)]
+pub struct AsyncRansReader<R: AsyncRead + Unpin> {
+    inner: R,
+    model: Option<Model>,
+    symbols: Vec<usize>,
+    pos: usize,
+    /// Length of the block being read, once its header has been read
+    block_len: Option<usize>,
+    /// Value and bit position of the partially read block length
+    partial_len: (u64, u32),
+    /// Data of the block read so far
+    block: Vec<u8>,
+    /// Whether the block being read is a serialized model to switch to
+    model_record: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRansReader<R> {
+    /// Creates a new `AsyncRansReader` instance reading a stream written by
+    /// a writer created with [`AsyncRansWriter::new()`], i.e. with the models
+    /// stored in the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::{AsyncRansReader, AsyncRansWriter};
+    /// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut writer = AsyncRansWriter::new(Vec::new());
+    /// writer.write_all(b"hello, world").await.unwrap();
+    /// let compressed = writer.finish().await.unwrap();
+    ///
+    /// let mut reader = AsyncRansReader::new(compressed.as_slice());
+    /// let mut decompressed = String::new();
+    /// reader.read_to_string(&mut decompressed).await.unwrap();
+    /// assert_eq!(decompressed, "hello, world");
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            model: None,
+            symbols: Vec::new(),
+            pos: 0,
+            block_len: None,
+            partial_len: (0, 0),
+            block: Vec::new(),
+            model_record: false,
+        }
+    }
+
+    /// Creates a new `AsyncRansReader` instance reading a stream written by
+    /// a writer created with [`AsyncRansWriter::with_model()`]. `model` has
+    /// to be the same as the one used by the writer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::{AsyncRansReader, AsyncRansWriter};
+    /// use rans::model::Model;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let model = Model::from_counts(&[3, 1, 1000], 12);
+    /// let mut writer = AsyncRansWriter::with_model(Vec::new(), model.clone());
+    /// writer.write_symbols(&[0, 2]).await.unwrap();
+    /// let compressed = writer.finish().await.unwrap();
+    ///
+    /// let mut reader = AsyncRansReader::with_model(compressed.as_slice(), model);
+    /// assert_eq!(reader.read_symbol().await.unwrap(), Some(0));
+    /// assert_eq!(reader.read_symbol().await.unwrap(), Some(2));
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_model(inner: R, model: Model) -> Self {
+        Self {
+            model: Some(model),
+            ..Self::new(inner)
+        }
+    }
+
+    /// Returns a reference to the underlying source.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansReader;
+    ///
+    /// let reader = AsyncRansReader::new([1, 2, 3].as_slice());
+    /// assert_eq!(reader.get_ref(), &[1, 2, 3]);
+    /// ```
+    #[must_use]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the fixed model the following blocks are decoded with. See
+    /// [`RansReader::model()`](crate::stream::RansReader::model).
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::AsyncRansReader;
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 2, 3], 8);
+    /// let reader = AsyncRansReader::with_model([].as_slice(), model.clone());
+    /// assert_eq!(reader.model(), Some(&model));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn model(&self) -> Option<&Model> {
+        self.model.as_ref()
+    }
+
+    /// Reads a single symbol. Returns `None` at the end of the stream.
+    ///
+    /// # Errors
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if a block is
+    /// malformed, [`io::ErrorKind::UnexpectedEof`] if the stream ends in the
+    /// middle of a block, or any error returned by the underlying source.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::async_stream::{AsyncRansReader, AsyncRansWriter};
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut writer = AsyncRansWriter::new(Vec::new());
+    /// writer.write_all(b"a").await.unwrap();
+    /// let compressed = writer.finish().await.unwrap();
+    ///
+    /// let mut reader = AsyncRansReader::new(compressed.as_slice());
+    /// assert_eq!(reader.read_symbol().await.unwrap(), Some(b'a' as usize));
+    /// assert_eq!(reader.read_symbol().await.unwrap(), None);
+    /// # }
+    /// ```
+    pub async fn read_symbol(&mut self) -> io::Result<Option<usize>> {
+        if !poll_fn(|cx| self.poll_fill(cx)).await? {
+            return Ok(None);
+        }
+
+        let symbol = self.symbols[self.pos];
+        self.pos += 1;
+        Ok(Some(symbol))
+    }
+
+    /// Makes sure there are decoded symbols available, reading the next
+    /// blocks if needed. Returns `false` at the end of the stream.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        while self.pos >= self.symbols.len() {
+            if !ready!(self.poll_read_block(cx))? {
+                return Poll::Ready(Ok(false));
+            }
+        }
+        Poll::Ready(Ok(true))
+    }
+
+    /// Reads and decodes the next block, switching the models on the way.
+    /// Returns `false` at the end of the stream.
+    fn poll_read_block(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        loop {
+            while self.block_len.is_none() {
+                let (value, shift) = self.partial_len;
+                if shift >= 64 {
+                    return Poll::Ready(Err(invalid_data("block length too long")));
+                }
+
+                let mut byte = [0];
+                let mut buf = ReadBuf::new(&mut byte);
+                ready!(Pin::new(&mut self.inner).poll_read(cx, &mut buf))?;
+                if buf.filled().is_empty() {
+                    return Poll::Ready(if shift == 0 && !self.model_record {
+                        Ok(false)
+                    } else {
+                        Err(io::ErrorKind::UnexpectedEof.into())
+                    });
+                }
+
+                let value = value | u64::from(byte[0] & 0x7f) << shift;
+                if byte[0] & 0x80 == 0 {
+                    let len = usize::try_from(value).map_err(|_| invalid_data("block too long"))?;
+                    self.partial_len = (0, 0);
+                    if len == 0 && !self.model_record {
+                        self.model_record = true;
+                    } else {
+                        self.block_len = Some(len);
+                        self.block.clear();
+                    }
+                } else {
+                    self.partial_len = (value, shift + 7);
+                }
+            }
+
+            // Don't trust the length for the allocation size
+            let len = self.block_len.unwrap_or_default();
+            let mut chunk = [0; READ_CHUNK_LEN];
+            while self.block.len() < len {
+                let chunk_len = (len - self.block.len()).min(READ_CHUNK_LEN);
+                let mut buf = ReadBuf::new(&mut chunk[..chunk_len]);
+                ready!(Pin::new(&mut self.inner).poll_read(cx, &mut buf))?;
+                if buf.filled().is_empty() {
+                    return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+                }
+                self.block.extend_from_slice(buf.filled());
+            }
+
+            self.block_len = None;
+            if self.model_record {
+                self.model_record = false;
+                self.model = Some(read_model_record(&self.block)?);
+            } else {
+                self.symbols = decode_block(self.model.as_ref(), &self.block)?;
+                self.pos = 0;
+                return Poll::Ready(Ok(true));
+            }
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncRansReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 || !ready!(this.poll_fill(cx))? {
+            return Poll::Ready(Ok(()));
+        }
+
+        let available = &this.symbols[this.pos..];
+        let count = available.len().min(buf.remaining());
+        for &symbol in &available[..count] {
+            buf.put_slice(&[symbol_to_byte(symbol)?]);
+        }
+        this.pos += count;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind, Read, Write};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::async_stream::{AsyncRansReader, AsyncRansWriter};
+    use crate::model::Model;
+    use crate::stream::{RansReader, RansWriter};
+
+    fn test_data() -> Vec<u8> {
+        (0..100_000_u32)
+            .map(|i| (i * 7 % 13 + i / 1000) as u8)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_same_as_sync() {
+        let data = test_data();
+
+        let mut writer = RansWriter::new(Vec::new()).with_block_size(10_000);
+        writer.write_all(&data).unwrap();
+        let expected = writer.finish().unwrap();
+
+        let mut writer = AsyncRansWriter::new(Vec::new()).with_block_size(10_000);
+        for chunk in data.chunks(3333) {
+            writer.write_all(chunk).await.unwrap();
+        }
+        let compressed = writer.finish().await.unwrap();
+        assert_eq!(compressed, expected);
+
+        let mut reader = AsyncRansReader::new(compressed.as_slice());
+        let mut decompressed = Vec::new();
+        reader.read_to_end(&mut decompressed).await.unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[tokio::test]
+    async fn test_duplex() {
+        let data = test_data();
+        let (client, server) = tokio::io::duplex(100);
+
+        let expected = data.clone();
+        let reader = tokio::spawn(async move {
+            let mut reader = AsyncRansReader::new(server);
+            let mut decompressed = Vec::new();
+            reader.read_to_end(&mut decompressed).await.unwrap();
+            assert_eq!(decompressed, expected);
+        });
+
+        let mut writer = AsyncRansWriter::new(client).with_block_size(4096);
+        writer.write_all(&data).await.unwrap();
+        writer.shutdown().await.unwrap();
+        drop(writer);
+        reader.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_symbols() {
+        let model = Model::from_counts(&[10, 0, 5, 1, 1000], 12);
+        let symbols: Vec<usize> = (0..5000).map(|i| [4, 4, 0, 4, 2, 4, 3][i % 7]).collect();
+
+        let mut writer =
+            AsyncRansWriter::with_model(Vec::new(), model.clone()).with_block_size(999);
+        writer.write_symbols(&symbols).await.unwrap();
+        assert_eq!(
+            writer.write_symbol(1).await.unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        let compressed = writer.finish().await.unwrap();
+
+        let mut reader = RansReader::with_model(compressed.as_slice(), model.clone());
+        let decoded: Vec<usize> = reader.symbols().collect::<Result<_, _>>().unwrap();
+        assert_eq!(decoded, symbols);
+
+        let mut reader = AsyncRansReader::with_model(compressed.as_slice(), model);
+        let mut decoded = Vec::new();
+        while let Some(symbol) = reader.read_symbol().await.unwrap() {
+            decoded.push(symbol);
+        }
+        assert_eq!(decoded, symbols);
+    }
+
+    #[tokio::test]
+    async fn test_model_switch() {
+        let data = test_data();
+        let first = Model::from_counts(&[1, 2, 3], 10);
+        let second = Model::from_counts(&[0, 0, 100, 1], 12);
+
+        let mut writer = RansWriter::new(Vec::new()).with_block_size(1000);
+        writer.write_all(&data[..2500]).unwrap();
+        writer.set_model(first.clone()).unwrap();
+        writer.write_symbols(&[0, 1, 2, 2]).unwrap();
+        writer.set_model(second.clone()).unwrap();
+        writer.write_symbols(&[3, 2, 2]).unwrap();
+        let expected = writer.finish().unwrap();
+
+        let mut writer = AsyncRansWriter::new(Vec::new()).with_block_size(1000);
+        writer.write_all(&data[..2500]).await.unwrap();
+        writer.set_model(first).await.unwrap();
+        writer.write_symbols(&[0, 1, 2, 2]).await.unwrap();
+        writer.set_model(second.clone()).await.unwrap();
+        writer.write_symbols(&[3, 2, 2]).await.unwrap();
+        let compressed = writer.finish().await.unwrap();
+        assert_eq!(compressed, expected);
+
+        let mut reader = AsyncRansReader::new(compressed.as_slice());
+        let mut decoded = Vec::new();
+        while let Some(symbol) = reader.read_symbol().await.unwrap() {
+            decoded.push(symbol);
+        }
+        let mut symbols: Vec<usize> = data[..2500].iter().map(|&x| x as usize).collect();
+        symbols.extend([0, 1, 2, 2, 3, 2, 2]);
+        assert_eq!(decoded, symbols);
+        assert_eq!(reader.model(), Some(&second));
+    }
+
+    #[tokio::test]
+    async fn test_truncated() {
+        let mut writer = RansWriter::new(Vec::new());
+        writer.write_all(&test_data()).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut expected = Vec::new();
+        let sync_error = RansReader::new(&compressed[..compressed.len() - 1])
+            .read_to_end(&mut expected)
+            .unwrap_err();
+
+        let mut reader = AsyncRansReader::new(&compressed[..compressed.len() - 1]);
+        let mut decompressed = Vec::new();
+        let error = reader.read_to_end(&mut decompressed).await.unwrap_err();
+        assert_eq!(error.kind(), sync_error.kind());
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
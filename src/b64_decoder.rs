@@ -1,23 +1,45 @@
-use std::mem::MaybeUninit;
+use std::hash::{Hash, Hasher};
+use std::mem::{align_of, size_of, size_of_val, MaybeUninit};
+use std::{ptr, slice};
 
-use crate::decoder::check_dec_pointer;
-use crate::mut_cow::MutCow;
-use crate::{RansDecSymbol, RansDecoder, RansDecoderMulti};
+use crate::b64_encoder::ByteOrder;
+use crate::backend::RANS64_L;
+use crate::decoder::{check_dec_pointer, DecSnapshot};
+use crate::decoder_input::DecoderInput;
+use crate::model::{DecModel, Model};
+use crate::{RansDecSymbol, RansDecoder, RansDecoderMulti, RansError};
 
 /// Multi-stream interleaved rANS decoder - 64-bit version.
+///
+/// The decoder is both [`Send`] and [`Sync`], so it can be moved to (or
+/// shared with) other threads.
 #[derive(Debug)]
 pub struct B64RansDecoderMulti<'a, const N: usize> {
-    states: [ryg_rans_sys::rans_64::Rans64State; N],
-    data: MutCow<'a, [u8]>,
+    states: [crate::backend::rans_64::Rans64State; N],
+    data: DecoderInput<'a>,
+    /// Copy of `data` aligned to 32-bit words, used instead of it if `data`
+    /// itself is not aligned and the rANS core can't read unaligned words.
+    aligned: Option<Box<[u32]>>,
     ptr: *mut u32,
+    model: Option<DecModel<B64RansDecSymbol>>,
 }
 
 /// Single-stream rANS decoder - 64-bit version.
 pub type B64RansDecoder<'a> = B64RansDecoderMulti<'a, 1>;
 
+// SAFETY: `ptr` always points into `data`, which is either owned by the decoder
+// and does not move along with it, or borrowed/shared immutably. The pointer is
+// only ever advanced by methods taking `&mut self`.
+unsafe impl<'a, const N: usize> Send for B64RansDecoderMulti<'a, N> {}
+unsafe impl<'a, const N: usize> Sync for B64RansDecoderMulti<'a, N> {}
+
 impl<'a, const N: usize> B64RansDecoderMulti<'a, N> {
     /// Creates a new `B64MultiRansDecoder` instance with given `data`.
     ///
+    /// The data is only ever read, so it can be borrowed (`&[u8]`), owned
+    /// (`Vec<u8>`) or shared between threads (`Arc<[u8]>`) - see
+    /// [`DecoderInput`] for all the supported types.
+    ///
     /// Note that most of the API is inside the [`RansDecoderMulti`] trait, so
     /// you probably want to `use rans::RansDecoderMulti`.
     ///
@@ -28,50 +50,486 @@ impl<'a, const N: usize> B64RansDecoderMulti<'a, N> {
     ///
     /// let mut decoder = B64RansDecoderMulti::<1>::new(vec![0]);
     /// assert_eq!(decoder.get_at(0, 4), 0);
+    ///
+    /// let data = [0, 0, 0, 128, 0, 0, 0, 0];
+    /// let mut decoder = B64RansDecoderMulti::<1>::new(&data[..]);
+    /// assert_eq!(decoder.get_at(0, 4), 0);
     /// ```
     #[must_use]
-    pub fn new<T: Into<MutCow<'a, [u8]>>>(data: T) -> Self {
-        let mut data = data.into();
+    pub fn new<T: Into<DecoderInput<'a>>>(data: T) -> Self {
+        let data = data.into();
         assert!(!data.is_empty());
 
+        // The states get initialized below
+        let mut decoder = Self {
+            states: [0; N],
+            aligned: aligned_copy(&data),
+            data,
+            ptr: ptr::null_mut(),
+            model: None,
+        };
+
+        // The input is never written to, the pointer only needs to be mutable
+        // to match the signatures of the underlying rANS implementation
+        let mut ptr = decoder.input().as_ptr() as *mut u32;
+        for state in &mut decoder.states {
+            unsafe {
+                crate::backend::rans_64::rans_64_dec_init(state, &mut ptr);
+            }
+        }
+        decoder.ptr = ptr;
+
+        decoder
+    }
+
+    /// Creates a new `B64RansDecoderMulti` instance reading given 32-bit
+    /// words, e.g. the buffer returned by
+    /// [`B64RansEncoderMulti::into_buffer()`](crate::b64_encoder::B64RansEncoderMulti::into_buffer).
+    ///
+    /// The words are always aligned, so unlike [`Self::new()`], this never
+    /// needs to copy the data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<1>::new(1024);
+    /// encoder.put_bits_at(0, 0x1234, 16);
+    /// encoder.flush_all();
+    /// let words = encoder.into_buffer();
+    ///
+    /// let mut decoder = B64RansDecoderMulti::<1>::from_words(&words);
+    /// assert_eq!(decoder.get_bits_at(0, 16), 0x1234);
+    /// ```
+    #[must_use]
+    pub fn from_words(words: &'a [u32]) -> Self {
+        let bytes =
+            unsafe { slice::from_raw_parts(words.as_ptr().cast::<u8>(), size_of_val(words)) };
+        Self::new(bytes)
+    }
+
+    /// Creates a new `B64RansDecoderMulti` instance reading data whose 32-bit
+    /// words are in little-endian byte order, as written by
+    /// [`B64RansEncoderMulti::new_le()`](crate::b64_encoder::B64RansEncoderMulti::new_le).
+    ///
+    /// On big-endian targets, the data is copied to swap the bytes of the
+    /// words.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    ///
+    /// let decoder = B64RansDecoderMulti::<1>::new_le([8, 7, 6, 5, 4, 3, 2, 1]);
+    /// assert_eq!(decoder.state_at(0), 0x0102_0304_0506_0708);
+    /// ```
+    #[must_use]
+    pub fn new_le<T: Into<DecoderInput<'a>>>(data: T) -> Self {
+        Self::with_byte_order(data, ByteOrder::Little)
+    }
+
+    /// Creates a new `B64RansDecoderMulti` instance reading data whose 32-bit
+    /// words are in big-endian byte order, as written by
+    /// [`B64RansEncoderMulti::new_be()`](crate::b64_encoder::B64RansEncoderMulti::new_be).
+    ///
+    /// On little-endian targets, the data is copied to swap the bytes of the
+    /// words.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    ///
+    /// let decoder = B64RansDecoderMulti::<1>::new_be([5, 6, 7, 8, 1, 2, 3, 4]);
+    /// assert_eq!(decoder.state_at(0), 0x0102_0304_0506_0708);
+    /// ```
+    #[must_use]
+    pub fn new_be<T: Into<DecoderInput<'a>>>(data: T) -> Self {
+        Self::with_byte_order(data, ByteOrder::Big)
+    }
+
+    /// Creates a new `B64RansDecoderMulti` instance reading data whose 32-bit
+    /// words are in given byte order. Unless it is the native one, the data
+    /// is copied to swap the bytes of the words.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    /// use rans::b64_encoder::{B64RansEncoderMulti, ByteOrder};
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<1>::with_byte_order(1024, ByteOrder::Big);
+    /// encoder.put_bits_at(0, 0xabc, 12);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = B64RansDecoderMulti::<1>::with_byte_order(encoder.data(), ByteOrder::Big);
+    /// assert_eq!(decoder.get_bits_at(0, 12), 0xabc);
+    /// ```
+    #[must_use]
+    pub fn with_byte_order<T: Into<DecoderInput<'a>>>(data: T, byte_order: ByteOrder) -> Self {
+        let data = data.into();
+        if byte_order.is_native() {
+            return Self::new(data);
+        }
+
+        let mut swapped = data.to_vec();
+        for word in swapped.chunks_exact_mut(size_of::<u32>()) {
+            word.reverse();
+        }
+        Self::new(swapped)
+    }
+
+    /// Creates a new `B64MultiRansDecoder` instance with given `data`,
+    /// checking that the data is long enough to contain the initial states
+    /// of all channels.
+    ///
+    /// # Errors
+    /// Returns [`RansError::EmptyInput`] if `data` is empty, or
+    /// [`RansError::UnexpectedEof`] if it is too short to contain the initial
+    /// states.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    /// use rans::{RansDecoderMulti, RansError};
+    ///
+    /// let mut decoder = B64RansDecoderMulti::<1>::try_new([0, 0, 0, 128, 0, 0, 0, 0]).unwrap();
+    /// assert_eq!(decoder.get_at(0, 4), 0);
+    /// assert_eq!(
+    ///     B64RansDecoderMulti::<1>::try_new(vec![]).unwrap_err(),
+    ///     RansError::EmptyInput
+    /// );
+    /// ```
+    pub fn try_new<T: Into<DecoderInput<'a>>>(data: T) -> Result<Self, RansError> {
+        let data = data.into();
+        if data.is_empty() {
+            return Err(RansError::EmptyInput);
+        }
+        if data.len() < N * size_of::<crate::backend::rans_64::Rans64State>() {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        Ok(Self::new(data))
+    }
+
+    /// Registers the model used by [`Self::decode_at()`], so that the
+    /// symbols don't need to be looked up and passed back to the decoder by
+    /// the caller. Replaces the previously registered model, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    /// use rans::model::Model;
+    ///
+    /// let mut decoder = B64RansDecoderMulti::<1>::new([2, 0, 0, 0, 2, 0, 0, 0]);
+    /// decoder.set_model(&Model::from_freqs(vec![2, 2], 2));
+    /// assert_eq!(decoder.decode_at(0), 1);
+    /// assert_eq!(decoder.decode_at(0), 0);
+    /// ```
+    pub fn set_model(&mut self, model: &Model) {
+        self.model = Some(DecModel::new(model));
+    }
+
+    /// Decodes a single symbol at given channel using the model registered
+    /// with [`Self::set_model()`] and returns its index.
+    ///
+    /// # Panics
+    /// Panics if no model has been registered.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    /// use rans::model::Model;
+    ///
+    /// let mut decoder = B64RansDecoderMulti::<1>::new([2, 0, 0, 0, 2, 0, 0, 0]);
+    /// decoder.set_model(&Model::from_freqs(vec![2, 2], 2));
+    /// assert_eq!(decoder.decode_at(0), 1);
+    /// assert_eq!(decoder.decode_at(0), 0);
+    /// ```
+    pub fn decode_at(&mut self, channel: usize) -> usize {
+        let model = self
+            .model
+            .take()
+            .expect("no model registered; use set_model() first");
+        let index = self.decode_symbol_at(channel, &model);
+        self.model = Some(model);
+        index
+    }
+
+    /// Returns the number of bytes of the data that haven't been read by
+    /// the decoder yet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoder;
+    ///
+    /// let decoder = B64RansDecoder::new([0, 0, 0, 128, 0, 0, 0, 0, 42]);
+    /// assert_eq!(decoder.bytes_remaining(), 1);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn bytes_remaining(&self) -> usize {
+        self.data.len() - self.position()
+    }
+
+    /// Returns the position of the decoder in the data, i.e. the number of
+    /// bytes read by the decoder so far.
+    ///
+    /// Once all the symbols are decoded, this is the length of the rANS
+    /// payload, so it can be used to find where the data following it
+    /// begins.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::{B64RansDecSymbol, B64RansDecoder};
+    /// use rans::b64_encoder::{B64RansEncSymbol, B64RansEncoder};
+    /// use rans::{RansDecSymbol, RansDecoder, RansEncSymbol, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = B64RansEncoder::new(1024);
+    /// encoder.put(&B64RansEncSymbol::new(1, 1, 12));
+    /// encoder.flush();
+    /// let mut data = encoder.data().to_vec();
+    /// let payload_len = data.len();
+    /// data.extend_from_slice(b"next section");
+    ///
+    /// let mut decoder = B64RansDecoder::new(data.as_slice());
+    /// assert_eq!(decoder.get(12), 1);
+    /// decoder.advance(&B64RansDecSymbol::new(1, 1), 12);
+    /// assert_eq!(decoder.position(), payload_len);
+    /// ```
+    #[doc(alias = "bytes_consumed")]
+    #[must_use]
+    #[inline]
+    pub fn position(&self) -> usize {
+        unsafe { (self.ptr as *const u8).offset_from(self.input().as_ptr()) as usize }
+    }
+
+    /// Returns whether all the symbols have been decoded, i.e. the whole data
+    /// has been read and all the channels are back in their initial states.
+    ///
+    /// This is exact for well-formed data, except that symbols with
+    /// probability of 1 don't change the state of the coder, so they can't
+    /// be detected.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::{B64RansDecSymbol, B64RansDecoder};
+    /// use rans::b64_encoder::{B64RansEncSymbol, B64RansEncoder};
+    /// use rans::{RansDecSymbol, RansDecoderMulti, RansEncSymbol, RansEncoderMulti};
+    ///
+    /// let mut encoder = B64RansEncoder::new(1024);
+    /// encoder.put_at(0, &B64RansEncSymbol::new(1, 1, 4));
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = B64RansDecoder::new(encoder.data());
+    /// assert!(!decoder.is_exhausted());
+    /// assert_eq!(decoder.get_at(0, 4), 1);
+    /// decoder.advance_at(0, &B64RansDecSymbol::new(1, 1), 4);
+    /// assert!(decoder.is_exhausted());
+    /// ```
+    #[must_use]
+    pub fn is_exhausted(&self) -> bool {
+        self.bytes_remaining() == 0 && self.states.iter().all(|&state| state == RANS64_L)
+    }
+
+    /// Saves this decoder's channel states and read position, so that they
+    /// can be restored later with [`Self::restore_state()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = B64RansDecoderMulti::<1>::new([2, 0, 0, 0, 2, 0, 0, 0]);
+    /// let snapshot = decoder.save_state();
+    /// assert_eq!(decoder.get_bits_at(0, 2), 2);
+    /// decoder.restore_state(&snapshot);
+    /// assert_eq!(decoder.get_bits_at(0, 2), 2);
+    /// ```
+    #[must_use]
+    pub fn save_state(&self) -> DecSnapshot<u64, N> {
+        DecSnapshot {
+            states: self.states,
+            pos: self.position(),
+        }
+    }
+
+    /// Restores this decoder's channel states and read position from given
+    /// snapshot.
+    ///
+    /// The snapshot must have been created by a decoder reading the same
+    /// data; otherwise, the decoder returns garbage.
+    ///
+    /// # Panics
+    /// Panics if the snapshot's position is past the end of the data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = B64RansDecoderMulti::<1>::new([2, 0, 0, 0, 2, 0, 0, 0]);
+    /// let snapshot = decoder.save_state();
+    /// assert_eq!(decoder.get_bits_at(0, 2), 2);
+    /// decoder.restore_state(&snapshot);
+    /// assert_eq!(decoder.get_bits_at(0, 2), 2);
+    /// ```
+    pub fn restore_state(&mut self, snapshot: &DecSnapshot<u64, N>) {
+        assert!(
+            snapshot.pos <= self.data.len(),
+            "snapshot position out of range"
+        );
+
+        self.states = snapshot.states;
         unsafe {
-            let mut ptr = data.as_mut_ptr() as *mut u32;
+            self.ptr = self.input().as_ptr().add(snapshot.pos) as *mut u32;
+        }
+    }
+
+    /// Re-initializes the states of all the channels from the data at the
+    /// current position.
+    ///
+    /// Once all the symbols of a block are decoded, this makes it possible to
+    /// decode another, independently flushed block that follows it in the
+    /// data with the same decoder, without copying the data.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the rest of the data is too
+    /// short to contain the initial states of all channels. The decoder is
+    /// left unchanged in such case.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoder;
+    /// use rans::{RansDecoder, RansError};
+    ///
+    /// let mut decoder = B64RansDecoder::new([0, 0, 0, 128, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0]);
+    /// assert_eq!(decoder.get(2), 0);
+    /// decoder.reinit_tail().unwrap();
+    /// assert_eq!(decoder.get(2), 2);
+    /// assert_eq!(decoder.reinit_tail(), Err(RansError::UnexpectedEof));
+    /// ```
+    pub fn reinit_tail(&mut self) -> Result<(), RansError> {
+        if self.bytes_remaining() < N * size_of::<crate::backend::rans_64::Rans64State>() {
+            return Err(RansError::UnexpectedEof);
+        }
 
-            #[allow(clippy::uninit_assumed_init)]
-            let mut states: [ryg_rans_sys::rans_64::Rans64State; N] =
-                MaybeUninit::uninit().assume_init();
-            for state in &mut states {
-                ryg_rans_sys::rans_64::rans_64_dec_init(state, &mut ptr);
+        for state in &mut self.states {
+            unsafe {
+                crate::backend::rans_64::rans_64_dec_init(state, &mut self.ptr);
             }
+        }
+        Ok(())
+    }
 
-            Self { states, data, ptr }
+    /// Returns the current rANS state of given channel.
+    ///
+    /// Together with [`Self::set_state_at()`], this makes it possible to hand
+    /// streams over between this crate and other rANS implementations.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    ///
+    /// let decoder = B64RansDecoderMulti::<1>::new([8, 7, 6, 5, 4, 3, 2, 1]);
+    /// assert_eq!(decoder.state_at(0), 0x0102_0304_0506_0708);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn state_at(&self, channel: usize) -> u64 {
+        self.states[channel]
+    }
+
+    /// Overwrites the rANS state of given channel.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = B64RansDecoderMulti::<1>::new([8, 7, 6, 5, 4, 3, 2, 1]);
+    /// decoder.set_state_at(0, 0x0000_0123);
+    /// assert_eq!(decoder.get_at(0, 8), 0x23);
+    /// ```
+    #[inline]
+    pub fn set_state_at(&mut self, channel: usize, state: u64) {
+        self.states[channel] = state;
+    }
+
+    /// Returns whether renormalizing given channel doesn't read past the end
+    /// of the data.
+    #[inline]
+    fn can_renorm(&self, channel: usize) -> bool {
+        self.states[channel] >= RANS64_L || size_of::<u32>() <= self.bytes_remaining()
+    }
+
+    /// Makes sure that renormalizing given channel doesn't read past the end
+    /// of the data, if the `checked` feature is enabled.
+    #[inline]
+    fn check_renorm(&self, channel: usize) {
+        if cfg!(feature = "checked") {
+            assert!(
+                self.can_renorm(channel),
+                "decoder read past the end of the data"
+            );
+        }
+    }
+
+    /// Returns the data the decoder reads from, i.e. the aligned copy of the
+    /// input, if there is one.
+    #[inline]
+    fn input(&self) -> &[u8] {
+        match &self.aligned {
+            Some(words) => unsafe {
+                slice::from_raw_parts(words.as_ptr().cast::<u8>(), self.data.len())
+            },
+            None => &self.data,
         }
     }
 
     #[inline]
     fn is_ptr_valid(&self) -> bool {
-        let range = self.data.as_ptr_range();
+        let range = self.input().as_ptr_range();
         let range_inclusive = range.start..=range.end;
         range_inclusive.contains(&(self.ptr as *const u8))
     }
 }
 
+/// Returns a copy of given data aligned to 32-bit words, if the data is not
+/// aligned and the rANS core can't read unaligned words.
+fn aligned_copy(data: &[u8]) -> Option<Box<[u32]>> {
+    if cfg!(feature = "pure-rust") || data.as_ptr() as usize % align_of::<u32>() == 0 {
+        return None;
+    }
+
+    let mut words = vec![0; (data.len() + size_of::<u32>() - 1) / size_of::<u32>()];
+    unsafe {
+        ptr::copy_nonoverlapping(data.as_ptr(), words.as_mut_ptr().cast::<u8>(), data.len());
+    }
+    Some(words.into_boxed_slice())
+}
+
 impl<const N: usize> RansDecoderMulti<N> for B64RansDecoderMulti<'_, N> {
     type Symbol = B64RansDecSymbol;
 
     #[inline]
     fn get_at(&mut self, channel: usize, scale_bits: u32) -> u32 {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
 
-        unsafe { ryg_rans_sys::rans_64::rans_64_dec_get(&mut self.states[channel], scale_bits) }
+        unsafe { crate::backend::rans_64::rans_64_dec_get(&mut self.states[channel], scale_bits) }
     }
 
     #[inline]
     fn advance_at(&mut self, channel: usize, symbol: &Self::Symbol, scale_bits: u32) {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
+        if cfg!(feature = "checked") {
+            self.advance_step_at(channel, symbol, scale_bits);
+            self.renorm_at(channel);
+            return;
+        }
 
         unsafe {
-            ryg_rans_sys::rans_64::rans_64_dec_advance_symbol(
+            crate::backend::rans_64::rans_64_dec_advance_symbol(
                 &mut self.states[channel],
                 &mut self.ptr,
                 &symbol.symbol,
@@ -84,10 +542,10 @@ impl<const N: usize> RansDecoderMulti<N> for B64RansDecoderMulti<'_, N> {
 
     #[inline]
     fn advance_step_at(&mut self, channel: usize, symbol: &Self::Symbol, scale_bits: u32) {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
 
         unsafe {
-            ryg_rans_sys::rans_64::rans_64_dec_advance_symbol_step(
+            crate::backend::rans_64::rans_64_dec_advance_symbol_step(
                 &mut self.states[channel],
                 &symbol.symbol,
                 scale_bits,
@@ -97,30 +555,128 @@ impl<const N: usize> RansDecoderMulti<N> for B64RansDecoderMulti<'_, N> {
 
     #[inline]
     fn renorm_at(&mut self, channel: usize) {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
+        self.check_renorm(channel);
 
         unsafe {
-            ryg_rans_sys::rans_64::rans_64_dec_renorm(&mut self.states[channel], &mut self.ptr);
+            crate::backend::rans_64::rans_64_dec_renorm(&mut self.states[channel], &mut self.ptr);
         }
 
         check_dec_pointer!(self);
     }
+
+    #[inline]
+    fn try_renorm_at(&mut self, channel: usize) -> Result<(), RansError> {
+        checked_assert!(channel < N);
+        if !self.can_renorm(channel) {
+            return Err(RansError::InputExhausted);
+        }
+
+        self.renorm_at(channel);
+        Ok(())
+    }
+
+    #[inline]
+    fn try_renorm_all(&mut self) -> Result<(), RansError> {
+        let words = self.states.iter().filter(|&&x| x < RANS64_L).count();
+        if words * size_of::<u32>() > self.bytes_remaining() {
+            return Err(RansError::InputExhausted);
+        }
+
+        self.renorm_all();
+        Ok(())
+    }
 }
 
 impl RansDecoder for B64RansDecoderMulti<'_, 1> {}
 
+impl B64RansDecoderMulti<'_, 1> {
+    /// Decodes a single symbol using the model registered with
+    /// [`Self::set_model()`] and returns its index. See
+    /// [`Self::decode_at()`].
+    ///
+    /// # Panics
+    /// Panics if no model has been registered.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoder;
+    /// use rans::model::Model;
+    ///
+    /// let mut decoder = B64RansDecoder::new([2, 0, 0, 0, 2, 0, 0, 0]);
+    /// decoder.set_model(&Model::from_freqs(vec![2, 2], 2));
+    /// assert_eq!(decoder.decode(), 1);
+    /// assert_eq!(decoder.decode(), 0);
+    /// ```
+    pub fn decode(&mut self) -> usize {
+        self.decode_at(0)
+    }
+}
+
 /// rANS decoder symbol - 64-bit version.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct B64RansDecSymbol {
-    symbol: ryg_rans_sys::rans_64::Rans64DecSymbol,
+    symbol: crate::backend::rans_64::Rans64DecSymbol,
+}
+
+impl B64RansDecSymbol {
+    /// Creates a new symbol like [`RansDecSymbol::new()`], but can be used in
+    /// constant expressions, e.g. to build static tables of symbols for
+    /// fixed models at compile time.
+    ///
+    /// Only available with the `pure-rust` feature, as the C implementation
+    /// can't be called at compile time.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecSymbol;
+    /// use rans::RansDecSymbol;
+    ///
+    /// static SYMBOLS: [B64RansDecSymbol; 2] = [
+    ///     B64RansDecSymbol::new_const(0, 3),
+    ///     B64RansDecSymbol::new_const(3, 1),
+    /// ];
+    /// assert_eq!(SYMBOLS[1].cum_freq(), 3);
+    /// assert_eq!(SYMBOLS[1], B64RansDecSymbol::new(3, 1));
+    /// ```
+    #[cfg(feature = "pure-rust")]
+    #[must_use]
+    pub const fn new_const(cum_freq: u32, freq: u32) -> Self {
+        Self {
+            symbol: crate::backend::rans_64::dec_symbol(cum_freq, freq),
+        }
+    }
+
+    /// Returns the fields of the symbol, used to implement the comparison
+    /// traits (the backend symbol types don't implement them).
+    #[inline]
+    fn key(&self) -> (u32, u32) {
+        (self.symbol.start, self.symbol.freq)
+    }
+}
+
+impl PartialEq for B64RansDecSymbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for B64RansDecSymbol {}
+
+impl Hash for B64RansDecSymbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
 }
 
 impl RansDecSymbol for B64RansDecSymbol {
+    const MAX_SCALE_BITS: u32 = 31;
+
     #[inline]
     fn new(cum_freq: u32, freq: u32) -> Self {
         unsafe {
             let mut symbol = MaybeUninit::uninit();
-            ryg_rans_sys::rans_64::rans_64_dec_symbol_init(symbol.as_mut_ptr(), cum_freq, freq);
+            crate::backend::rans_64::rans_64_dec_symbol_init(symbol.as_mut_ptr(), cum_freq, freq);
 
             Self {
                 symbol: symbol.assume_init(),
@@ -141,8 +697,15 @@ impl RansDecSymbol for B64RansDecSymbol {
 
 #[cfg(test)]
 mod tests {
-    use crate::b64_decoder::{B64RansDecoder, B64RansDecoderMulti};
+    use crate::b64_decoder::{B64RansDecSymbol, B64RansDecoder, B64RansDecoderMulti};
+    use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoder, B64RansEncoderMulti, ByteOrder};
     use crate::decoder::tests as dec_tests;
+    use crate::encoder::tests as enc_tests;
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::{
+        RansDecSymbol, RansDecoder, RansDecoderMulti, RansEncSymbol, RansEncoder, RansEncoderMulti,
+        RansError,
+    };
 
     #[test]
     fn test_decode_empty() {
@@ -158,6 +721,53 @@ mod tests {
         dec_tests::test_decode_two_symbols(decoder);
     }
 
+    #[test]
+    fn test_decode_bits() {
+        let data = enc_tests::encode_bits(B64RansEncoder::new(1024));
+        let decoder = B64RansDecoder::new(data);
+
+        dec_tests::test_decode_bits(decoder);
+    }
+
+    #[test]
+    fn test_state_matches_encoder() {
+        let mut encoder = B64RansEncoder::new(1024);
+        encoder.put_bits_at(0, 0xdead_beef, 32);
+        let state = encoder.state_at(0);
+        encoder.flush_all();
+
+        let decoder = B64RansDecoder::new(encoder.data());
+        assert_eq!(decoder.state_at(0), state);
+    }
+
+    #[test]
+    fn test_save_restore_state() {
+        let data = enc_tests::encode_bits(B64RansEncoder::new(1024));
+        let decoder = B64RansDecoder::new(data);
+
+        dec_tests::test_save_restore_state(
+            decoder,
+            B64RansDecoder::save_state,
+            B64RansDecoder::restore_state,
+        );
+    }
+
+    #[test]
+    fn test_decode_symbols_with_model() {
+        let decoder = B64RansDecoder::new([2, 0, 0, 0, 2, 0, 0, 0]);
+
+        dec_tests::test_decode_symbols_with_model(decoder);
+    }
+
+    #[test]
+    fn test_decode_with_registered_model() {
+        let mut decoder = B64RansDecoder::new([2, 0, 0, 0, 2, 0, 0, 0]);
+        decoder.set_model(&Model::from_freqs(vec![2, 2], 2));
+
+        assert_eq!(decoder.decode_at(0), 1);
+        assert_eq!(decoder.decode_at(0), 0);
+    }
+
     #[test]
     fn test_decode_symbols_clone() {
         let decoder = B64RansDecoder::new([2, 0, 0, 0, 2, 0, 0, 0]);
@@ -183,6 +793,176 @@ mod tests {
         dec_tests::test_decode_interleaved(decoder);
     }
 
+    #[test]
+    fn test_try_new() {
+        assert!(B64RansDecoder::try_new([0, 0, 0, 128, 0, 0, 0, 0]).is_ok());
+        assert_eq!(
+            B64RansDecoder::try_new(vec![]).unwrap_err(),
+            RansError::EmptyInput
+        );
+        assert_eq!(
+            B64RansDecoderMulti::<2>::try_new([0, 0, 0, 128, 0, 0, 0, 0]).unwrap_err(),
+            RansError::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_put_all_decode_n() {
+        dec_tests::test_put_all_decode_n(
+            B64RansEncoderMulti::<3>::new(1024),
+            B64RansDecoderMulti::<3>::new,
+        );
+    }
+
+    #[test]
+    fn test_decode_symbols_all() {
+        dec_tests::test_decode_symbols_all(
+            B64RansEncoderMulti::<3>::new(1024),
+            B64RansDecoderMulti::<3>::new,
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        dec_tests::test_sections(
+            B64RansEncoderMulti::<2>::new(1 << 16),
+            B64RansDecoderMulti::<2>::new,
+        );
+    }
+
+    #[test]
+    fn test_uniform() {
+        dec_tests::test_uniform(B64RansEncoder::new(1024), B64RansDecoder::new);
+    }
+
+    #[test]
+    fn test_is_exhausted() {
+        dec_tests::test_is_exhausted(
+            B64RansEncoderMulti::<2>::new(1024),
+            B64RansDecoderMulti::<2>::new,
+            B64RansDecoderMulti::<2>::is_exhausted,
+        );
+    }
+
+    #[test]
+    fn test_reinit_tail() {
+        dec_tests::test_reinit_tail(
+            || B64RansEncoderMulti::<2>::new(1 << 16),
+            B64RansDecoderMulti::<2>::new,
+            B64RansDecoderMulti::<2>::reinit_tail,
+        );
+    }
+
+    #[test]
+    fn test_try_advance_truncated() {
+        dec_tests::test_try_advance_truncated(
+            B64RansEncoderMulti::<2>::new(1 << 16),
+            B64RansDecoderMulti::<2>::new,
+            8,
+        );
+    }
+
+    #[test]
+    fn test_high_precision_round_trip() {
+        const SCALE_BITS: u32 = 31;
+        let rare_enc = B64RansEncSymbol::new(0, 1, SCALE_BITS);
+        let common_enc = B64RansEncSymbol::new(1, (1 << SCALE_BITS) - 1, SCALE_BITS);
+        let rare_dec = B64RansDecSymbol::new(0, 1);
+        let common_dec = B64RansDecSymbol::new(1, (1 << SCALE_BITS) - 1);
+        let symbols: Vec<bool> = (0..100_000).map(|i| i % 9973 == 0).collect();
+
+        let mut encoder = B64RansEncoder::new(1024);
+        for &rare in symbols.iter().rev() {
+            encoder.put(if rare { &rare_enc } else { &common_enc });
+        }
+        encoder.flush();
+        // Each rare symbol costs 31 bits; the common ones are almost free
+        assert!(encoder.len() <= 8 + 11 * 4);
+
+        let mut decoder = B64RansDecoder::new(encoder.data());
+        for &rare in &symbols {
+            let cum_freq = decoder.get(SCALE_BITS);
+            assert_eq!(cum_freq == 0, rare);
+            decoder.advance(if rare { &rare_dec } else { &common_dec }, SCALE_BITS);
+        }
+        assert!(decoder.is_exhausted());
+    }
+
+    #[test]
+    fn test_decode_unaligned() {
+        let data = enc_tests::encode_bits(B64RansEncoder::new(1024));
+        let mut buffer = vec![0; data.len() + 1];
+        let offset = if buffer.as_ptr() as usize % 4 == 0 {
+            1
+        } else {
+            0
+        };
+        buffer[offset..offset + data.len()].copy_from_slice(&data);
+        let unaligned = &buffer[offset..offset + data.len()];
+
+        dec_tests::test_decode_bits(B64RansDecoder::new(unaligned));
+        dec_tests::test_save_restore_state(
+            B64RansDecoder::new(unaligned),
+            B64RansDecoder::save_state,
+            B64RansDecoder::restore_state,
+        );
+    }
+
+    #[test]
+    fn test_decode_from_words() {
+        let mut encoder = B64RansEncoder::new(1024);
+        for &(value, num_bits) in enc_tests::BITS_DATA.iter().rev() {
+            encoder.put_bits(value, num_bits);
+        }
+        encoder.flush();
+        let words = encoder.into_buffer();
+
+        dec_tests::test_decode_bits(B64RansDecoder::from_words(&words));
+    }
+
+    #[test]
+    fn test_byte_orders() {
+        let model = Model::from_counts(&[12, 1, 40, 3, 7], 16);
+        let symbols: Vec<usize> = (0..1000).map(|i| (i * 7 + i / 5) % 5).collect();
+
+        let mut outputs = Vec::new();
+        for byte_order in [ByteOrder::Native, ByteOrder::Little, ByteOrder::Big] {
+            let mut encoder = B64RansEncoderMulti::<2>::with_byte_order(1 << 16, byte_order);
+            let enc_model = EncModel::<B64RansEncSymbol>::new(&model);
+            let refs: Vec<_> = symbols.iter().map(|&s| enc_model.symbol(s)).collect();
+            encoder.put_all(&refs);
+            encoder.flush_all();
+
+            let mut decoder = B64RansDecoderMulti::<2>::with_byte_order(encoder.data(), byte_order);
+            let dec_model = DecModel::<B64RansDecSymbol>::new(&model);
+            assert_eq!(decoder.decode_n(&dec_model, symbols.len()), symbols);
+            assert!(decoder.is_exhausted());
+            outputs.push(encoder.data().to_vec());
+        }
+
+        let swapped: Vec<u8> = outputs[1]
+            .chunks_exact(4)
+            .flat_map(|word| word.iter().rev().copied())
+            .collect();
+        assert_eq!(swapped, outputs[2]);
+        assert_ne!(outputs[1], outputs[2]);
+        if cfg!(target_endian = "little") {
+            assert_eq!(outputs[0], outputs[1]);
+        } else {
+            assert_eq!(outputs[0], outputs[2]);
+        }
+    }
+
+    #[test]
+    fn test_is_send_sync() {
+        dec_tests::test_is_send_sync::<B64RansDecoderMulti<'static, 2>>();
+    }
+
+    #[test]
+    fn test_symbol_eq_hash() {
+        dec_tests::test_symbol_eq_hash::<B64RansDecSymbol>();
+    }
+
     #[test]
     fn test_has_debug_output() {
         let decoder = B64RansDecoder::new([0, 0, 0, 128, 0, 0, 0, 0]);
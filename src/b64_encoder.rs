@@ -1,19 +1,103 @@
+use std::hash::{Hash, Hasher};
 use std::mem::MaybeUninit;
+use std::ops::Range;
 use std::slice;
 
-use crate::encoder::{check_enc_pointer, RansEncSymbol, RansEncoder, RansEncoderMulti};
+use crate::backend::RANS64_L;
+use crate::encoder::{
+    assert_scale_bits, check_enc_pointer, EncCheckpoint, RansEncSymbol, RansEncoder,
+    RansEncoderMulti,
+};
+use crate::lookup::AliasTable;
+use crate::RansError;
+
+/// Maximum number of bytes a single `put` can write into the buffer.
+pub(crate) const MAX_PUT_LEN: usize = 4;
+/// Number of bytes a single `flush` writes into the buffer.
+pub(crate) const FLUSH_LEN: usize = 8;
+/// Initial buffer size, in bytes, of encoders with a growable buffer.
+const INITIAL_GROWABLE_LEN: usize = 256;
+
+/// Byte order of the 32-bit words of the 64-bit rANS bitstream.
+///
+/// By default, the 64-bit coder writes the words in the native byte order of
+/// the target, so data encoded on a little-endian machine can't be decoded on
+/// a big-endian one. Use [`B64RansEncoderMulti::new_le()`] and
+/// [`B64RansDecoderMulti::new_le()`](crate::b64_decoder::B64RansDecoderMulti::new_le)
+/// (or their `_be` counterparts) to get a portable bitstream.
+///
+/// # Examples
+/// ```
+/// use rans::b64_encoder::ByteOrder;
+///
+/// assert_eq!(ByteOrder::default(), ByteOrder::Native);
+/// assert!(ByteOrder::Native.is_native());
+/// assert_ne!(ByteOrder::Little.is_native(), ByteOrder::Big.is_native());
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Native byte order of the target.
+    #[default]
+    Native,
+    /// Little-endian byte order.
+    Little,
+    /// Big-endian byte order.
+    Big,
+}
+
+impl ByteOrder {
+    /// Returns whether this byte order is the same as the target's native
+    /// one, i.e. whether the words can be used without swapping their bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::ByteOrder;
+    ///
+    /// assert_eq!(ByteOrder::Little.is_native(), cfg!(target_endian = "little"));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_native(self) -> bool {
+        match self {
+            Self::Native => true,
+            Self::Little => cfg!(target_endian = "little"),
+            Self::Big => cfg!(target_endian = "big"),
+        }
+    }
+}
 
 /// Multi-stream interleaved rANS encoder - 64-bit version.
+///
+/// This is the high-precision variant of the coder: the states are 64 bits
+/// wide and are renormalized 32 bits at a time, which allows up to 31 scale
+/// bits (see [`B64RansEncSymbol::MAX_SCALE_BITS`](RansEncSymbol::MAX_SCALE_BITS)),
+/// compared to 16 for the byte-aligned and 16-bit word coders. This makes it
+/// possible to represent probabilities as small as `2^-31`, so very skewed
+/// distributions are coded with almost no loss compared to exact arithmetic
+/// coding.
+///
+/// The encoder is both [`Send`] and [`Sync`], so it can be moved to (or
+/// shared with) other threads.
 #[derive(Debug)]
 pub struct B64RansEncoderMulti<const N: usize> {
-    states: [ryg_rans_sys::rans_64::Rans64State; N],
+    states: [crate::backend::rans_64::Rans64State; N],
     dst: Vec<u32>,
     ptr: *mut u32,
+    growable: bool,
+    byte_order: ByteOrder,
+    /// Length of the data when the current section started
+    section_start: usize,
 }
 
 /// Single-stream rANS encoder - 64-bit version.
 pub type B64RansEncoder = B64RansEncoderMulti<1>;
 
+// SAFETY: `ptr` always points into `dst`, which is owned by the encoder and
+// does not move along with it. The pointer is only
+// ever advanced by methods taking `&mut self`.
+unsafe impl<const N: usize> Send for B64RansEncoderMulti<N> {}
+unsafe impl<const N: usize> Sync for B64RansEncoderMulti<N> {}
+
 impl<const N: usize> B64RansEncoderMulti<N> {
     /// Creates a new `B64MultiRansEncoder` instance that can contain `max_len`
     /// bytes in the internal buffer.
@@ -33,23 +117,419 @@ impl<const N: usize> B64RansEncoderMulti<N> {
     pub fn new(max_len: usize) -> Self {
         debug_assert!(N > 0);
 
+        // The states get initialized by `reset()` below
+        let mut encoder = Self {
+            states: [0; N],
+            dst: vec![0; max_len / 4],
+            ptr: std::ptr::null_mut(),
+            growable: false,
+            section_start: 0,
+            byte_order: ByteOrder::Native,
+        };
+        encoder.reset();
+        encoder
+    }
+
+    /// Creates a new `B64RansEncoderMulti` instance that writes the 32-bit
+    /// words of the bitstream in little-endian byte order, regardless of the
+    /// target. The data has to be decoded with
+    /// [`B64RansDecoderMulti::new_le()`](crate::b64_decoder::B64RansDecoderMulti::new_le).
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<1>::new_le(1024);
+    /// encoder.set_state_at(0, 0x0102_0304_0506_0708);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data(), [8, 7, 6, 5, 4, 3, 2, 1]);
+    /// ```
+    #[must_use]
+    pub fn new_le(max_len: usize) -> Self {
+        Self::with_byte_order(max_len, ByteOrder::Little)
+    }
+
+    /// Creates a new `B64RansEncoderMulti` instance that writes the 32-bit
+    /// words of the bitstream in big-endian byte order, regardless of the
+    /// target. The data has to be decoded with
+    /// [`B64RansDecoderMulti::new_be()`](crate::b64_decoder::B64RansDecoderMulti::new_be).
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<1>::new_be(1024);
+    /// encoder.set_state_at(0, 0x0102_0304_0506_0708);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data(), [5, 6, 7, 8, 1, 2, 3, 4]);
+    /// ```
+    #[must_use]
+    pub fn new_be(max_len: usize) -> Self {
+        Self::with_byte_order(max_len, ByteOrder::Big)
+    }
+
+    /// Creates a new `B64RansEncoderMulti` instance that can contain `max_len`
+    /// bytes in the internal buffer and writes the 32-bit words of the
+    /// bitstream in given byte order.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::{B64RansEncoderMulti, ByteOrder};
+    ///
+    /// let encoder = B64RansEncoderMulti::<2>::with_byte_order(1024, ByteOrder::Big);
+    /// assert_eq!(encoder.byte_order(), ByteOrder::Big);
+    /// ```
+    #[must_use]
+    pub fn with_byte_order(max_len: usize, byte_order: ByteOrder) -> Self {
+        let mut encoder = Self::new(max_len);
+        encoder.byte_order = byte_order;
+        encoder
+    }
+
+    /// Returns the byte order the 32-bit words of the bitstream are written
+    /// in.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::{B64RansEncoderMulti, ByteOrder};
+    ///
+    /// assert_eq!(B64RansEncoderMulti::<1>::new(1024).byte_order(), ByteOrder::Native);
+    /// assert_eq!(B64RansEncoderMulti::<1>::new_le(1024).byte_order(), ByteOrder::Little);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    /// Creates a new `B64RansEncoderMulti` instance that uses given vector as
+    /// its internal buffer, so that the allocation can be reused across
+    /// encoders. The whole capacity of the vector is used and its contents
+    /// are overwritten.
+    ///
+    /// Use [`Self::into_buffer()`] to get the vector back.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<2>::with_buffer(Vec::with_capacity(256));
+    /// encoder.flush_all();
+    /// let buffer = encoder.into_buffer();
+    /// assert_eq!(buffer.len(), 4);
+    /// assert!(buffer.capacity() >= 256);
+    ///
+    /// let encoder = B64RansEncoderMulti::<2>::with_buffer(buffer);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn with_buffer(mut buffer: Vec<u32>) -> Self {
+        debug_assert!(N > 0);
+
+        buffer.resize(buffer.capacity(), 0);
+        // The states get initialized by `reset()` below
+        let mut encoder = Self {
+            states: [0; N],
+            dst: buffer,
+            ptr: std::ptr::null_mut(),
+            growable: false,
+            section_start: 0,
+            byte_order: ByteOrder::Native,
+        };
+        encoder.reset();
+        encoder
+    }
+
+    /// Consumes this encoder, returning its internal buffer with the encoded
+    /// data, as 32-bit words moved to the beginning. The buffer's capacity is
+    /// retained, so it can be passed to [`Self::with_buffer()`] again.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<2>::new(1024);
+    /// encoder.flush_all();
+    /// let len = encoder.len();
+    /// assert_eq!(encoder.into_buffer().len() * 4, len);
+    /// ```
+    #[must_use]
+    pub fn into_buffer(mut self) -> Vec<u32> {
+        let len = self.len() / 4;
+        let start = self.dst.len() - len;
+        self.dst.copy_within(start.., 0);
+        self.dst.truncate(len);
+        self.dst
+    }
+
+    /// Returns the encoded data as 32-bit words. This is the same data as
+    /// [`RansEncoderMulti::data()`], without the need to reinterpret the
+    /// bytes; it can be decoded with
+    /// [`B64RansDecoderMulti::from_words()`](crate::b64_decoder::B64RansDecoderMulti::from_words).
+    ///
+    /// If the encoder was created with a non-native [`ByteOrder`], the bytes
+    /// of the words are swapped accordingly.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<1>::new(1024);
+    /// encoder.set_state_at(0, 0x0102_0304_0506_0708);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data_words(), [0x0506_0708, 0x0102_0304]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn data_words(&self) -> &[u32] {
+        &self.dst[self.dst.len() - self.len() / 4..]
+    }
+
+    /// Consumes this encoder, returning the encoded data as 32-bit words.
+    /// See [`Self::data_words()`].
+    ///
+    /// This is the same as [`Self::into_buffer()`], so no data is copied
+    /// other than moving it to the beginning of the buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<2>::new(1024);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.into_words(), [0x8000_0000, 0, 0x8000_0000, 0]);
+    /// ```
+    #[must_use]
+    pub fn into_words(self) -> Vec<u32> {
+        self.into_buffer()
+    }
+
+    /// Creates a new `B64MultiRansEncoder` instance with a growable internal
+    /// buffer.
+    ///
+    /// Instead of being limited to a fixed size, the buffer is transparently
+    /// reallocated whenever the remaining space gets too small to hold the
+    /// next symbol. Already encoded data is preserved, so this is useful when
+    /// the size of the output cannot be estimated up front.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::{B64RansEncSymbol, B64RansEncoder};
+    /// use rans::{RansEncSymbol, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = B64RansEncoder::with_growable_buffer();
+    /// let symbol = B64RansEncSymbol::new(0, 1, 8);
+    /// for _ in 0..10000 {
+    ///     encoder.put(&symbol);
+    /// }
+    /// encoder.flush();
+    /// assert!(encoder.len() > 5000);
+    /// ```
+    #[must_use]
+    pub fn with_growable_buffer() -> Self {
+        let mut encoder = Self::new(INITIAL_GROWABLE_LEN);
+        encoder.growable = true;
+        encoder
+    }
+
+    /// Puts a symbol into given channel using given alias table instead of
+    /// a regular [`B64RansEncSymbol`]. The data has to be decoded with
+    /// [`RansDecoderMulti::decode_alias_at()`](crate::RansDecoderMulti::decode_alias_at)
+    /// using the same table.
+    ///
+    /// This is slower than [`RansEncoderMulti::put_at()`], as the encoder
+    /// symbol is computed on the fly.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoderMulti;
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<2>::new(1024);
+    /// encoder.put_alias_at(0, &table, 1);
+    /// encoder.put_alias_at(1, &table, 2);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = B64RansDecoderMulti::<2>::new(encoder.data());
+    /// assert_eq!(decoder.decode_alias_at(0, &table), 2);
+    /// assert_eq!(decoder.decode_alias_at(1, &table), 1);
+    /// ```
+    pub fn put_alias_at(&mut self, channel: usize, table: &AliasTable, symbol: usize) {
+        checked_assert!(channel < N);
+
+        // The slot the symbol ends up in depends on the state after
+        // renormalization, which only depends on the symbol's frequency
+        let freq = table.freq(symbol);
+        let x_max = ((RANS64_L >> table.scale_bits()) << 32) * u64::from(freq);
+        let mut x = self.states[channel];
+        if x >= x_max {
+            x >>= 32;
+        }
+        let offset = (x % u64::from(freq)) as u32;
+        let start = table.remap(symbol, offset) - offset;
+        self.put_at(
+            channel,
+            &B64RansEncSymbol::new(start, freq, table.scale_bits()),
+        );
+    }
+
+    /// Creates a checkpoint of this encoder's channel states and write
+    /// position, which can be restored later with [`Self::restore()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<2>::new(1024);
+    /// let checkpoint = encoder.checkpoint();
+    /// encoder.flush_all();
+    /// encoder.restore(&checkpoint);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn checkpoint(&self) -> EncCheckpoint<u64, N> {
+        EncCheckpoint {
+            states: self.states,
+            len: self.len(),
+        }
+    }
+
+    /// Restores this encoder's channel states and write position from given
+    /// checkpoint, discarding everything put into the encoder since.
+    ///
+    /// The checkpoint must have been created by this encoder, after its last
+    /// reset; otherwise, the encoder produces garbage.
+    ///
+    /// # Panics
+    /// Panics if the checkpoint's data is longer than this encoder's buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<2>::new(1024);
+    /// encoder.flush_at(0);
+    /// let checkpoint = encoder.checkpoint();
+    /// encoder.flush_at(1);
+    /// encoder.restore(&checkpoint);
+    /// assert_eq!(encoder.len(), checkpoint.data_len());
+    /// ```
+    pub fn restore(&mut self, checkpoint: &EncCheckpoint<u64, N>) {
+        assert!(
+            checkpoint.len <= self.dst.len() * 4,
+            "checkpoint does not fit in the buffer"
+        );
+
+        self.states = checkpoint.states;
+        self.section_start = self.section_start.min(checkpoint.len);
         unsafe {
-            #[allow(clippy::uninit_assumed_init)]
-            let states: [ryg_rans_sys::rans_64::Rans64State; N] =
-                MaybeUninit::uninit().assume_init();
+            self.ptr = self
+                .dst
+                .as_mut_ptr()
+                .add(self.dst.len() - checkpoint.len / 4);
+        }
+    }
+
+    /// Returns the current rANS state of given channel.
+    ///
+    /// Together with [`Self::set_state_at()`], this makes it possible to hand
+    /// streams over between this crate and other rANS implementations.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    ///
+    /// let encoder = B64RansEncoderMulti::<2>::new(1024);
+    /// assert_eq!(encoder.state_at(1), 0x8000_0000);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn state_at(&self, channel: usize) -> u64 {
+        self.states[channel]
+    }
+
+    /// Overwrites the rANS state of given channel.
+    ///
+    /// The state has to be normalized, i.e. within the range the encoder keeps
+    /// its states in between the symbols; otherwise, the encoder produces
+    /// garbage.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = B64RansEncoderMulti::<1>::new(1024);
+    /// encoder.set_state_at(0, 0x0102_0304_0506_0708);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data(), [8, 7, 6, 5, 4, 3, 2, 1]);
+    /// ```
+    #[inline]
+    pub fn set_state_at(&mut self, channel: usize, state: u64) {
+        self.states[channel] = state;
+    }
+
+    /// Makes sure there are at least `len` bytes left in the buffer, growing
+    /// it if this encoder has a growable buffer. Otherwise, panics if the
+    /// checks are enabled.
+    #[inline]
+    fn ensure_space(&mut self, len: usize) {
+        if self.growable && self.remaining_len() < len {
+            self.grow(len);
+        }
+        checked_assert!(self.remaining_len() >= len, "encoder buffer overflow");
+    }
 
-            let dst = vec![0; max_len / 4];
+    /// Swaps the bytes of the words written since the write position was
+    /// `old_ptr`, if the bitstream's byte order isn't the native one.
+    #[inline]
+    fn fix_byte_order(&mut self, old_ptr: *mut u32) {
+        if self.byte_order.is_native() {
+            return;
+        }
 
-            let mut encoder = Self {
-                states,
-                dst,
-                ptr: std::ptr::null_mut(),
-            };
-            encoder.reset();
-            encoder
+        let mut ptr = self.ptr;
+        while ptr < old_ptr {
+            unsafe {
+                *ptr = (*ptr).swap_bytes();
+                ptr = ptr.add(1);
+            }
+        }
+    }
+
+    #[cold]
+    fn grow(&mut self, additional: usize) {
+        // All lengths here are in 32-bit words
+        let used = self.dst.len() - self.remaining_len() / 4;
+        let additional = (additional + 3) / 4;
+        let new_len = (self.dst.len() * 2).max(self.dst.len() + additional);
+
+        let mut dst = vec![0; new_len];
+        dst[new_len - used..].copy_from_slice(&self.dst[self.dst.len() - used..]);
+        self.dst = dst;
+        unsafe {
+            self.ptr = self.dst.as_mut_ptr().add(new_len - used);
         }
     }
 
+    #[inline]
+    fn remaining_len(&self) -> usize {
+        unsafe { self.ptr.offset_from(self.dst.as_ptr()) as usize * 4 }
+    }
+
     #[inline]
     fn is_ptr_valid(&self) -> bool {
         let range = self.dst.as_ptr_range();
@@ -62,44 +542,89 @@ impl<const N: usize> RansEncoderMulti<N> for B64RansEncoderMulti<N> {
     type Symbol = B64RansEncSymbol;
 
     fn reset(&mut self) {
-        unsafe {
-            for state in &mut self.states {
-                ryg_rans_sys::rans_64::rans_64_enc_init(state);
-            }
+        self.clear();
+        self.section_start = 0;
 
+        unsafe {
             let mut ptr: *mut u32 = self.dst.as_mut_ptr();
             ptr = ptr.add(self.dst.capacity());
             self.ptr = ptr;
         }
     }
 
+    fn clear(&mut self) {
+        for state in &mut self.states {
+            unsafe {
+                crate::backend::rans_64::rans_64_enc_init(state);
+            }
+        }
+    }
+
     #[inline]
     fn put_at(&mut self, channel: usize, symbol: &Self::Symbol) {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
+        self.ensure_space(MAX_PUT_LEN);
 
+        let old_ptr = self.ptr;
         unsafe {
-            ryg_rans_sys::rans_64::rans_64_enc_put_symbol(
+            crate::backend::rans_64::rans_64_enc_put_symbol(
                 &mut self.states[channel],
                 &mut self.ptr,
                 &symbol.symbol,
                 symbol.scale_bits,
             );
         }
+        self.fix_byte_order(old_ptr);
 
         check_enc_pointer!(self);
     }
 
+    fn try_put_at(&mut self, channel: usize, symbol: &Self::Symbol) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+        if !self.growable && self.remaining_len() < MAX_PUT_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.put_at(channel, symbol);
+        Ok(())
+    }
+
     #[inline]
     fn flush_at(&mut self, channel: usize) {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
+        self.ensure_space(FLUSH_LEN);
 
+        let old_ptr = self.ptr;
         unsafe {
-            ryg_rans_sys::rans_64::rans_64_enc_flush(&mut self.states[channel], &mut self.ptr);
+            crate::backend::rans_64::rans_64_enc_flush(&mut self.states[channel], &mut self.ptr);
         }
+        self.fix_byte_order(old_ptr);
 
         check_enc_pointer!(self);
     }
 
+    fn try_flush_at(&mut self, channel: usize) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+        if !self.growable && self.remaining_len() < FLUSH_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.flush_at(channel);
+        Ok(())
+    }
+
+    fn flush_section(&mut self) -> Range<usize> {
+        self.flush_all();
+        self.clear();
+        let section = self.section_start..self.len();
+        self.section_start = self.len();
+        section
+    }
+
     #[inline]
     fn data(&self) -> &[u8] {
         unsafe {
@@ -109,24 +634,130 @@ impl<const N: usize> RansEncoderMulti<N> for B64RansEncoderMulti<N> {
             slice::from_raw_parts(self.ptr as *const u8, len * 4)
         }
     }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.dst.len() * 4
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        if self.remaining_len() < additional {
+            self.grow(additional);
+        }
+    }
 }
 
 impl RansEncoder for B64RansEncoderMulti<1> {}
 
+impl B64RansEncoderMulti<1> {
+    /// Puts a symbol using given alias table instead of a regular
+    /// [`B64RansEncSymbol`]. See [`Self::put_alias_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecoder;
+    /// use rans::b64_encoder::B64RansEncoder;
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    /// use rans::{RansDecoder, RansEncoderMulti};
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    ///
+    /// let mut encoder = B64RansEncoder::new(1024);
+    /// encoder.put_alias(&table, 0);
+    /// encoder.put_alias(&table, 2);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = B64RansDecoder::new(encoder.data());
+    /// assert_eq!(decoder.decode_alias(&table), 2);
+    /// assert_eq!(decoder.decode_alias(&table), 0);
+    /// ```
+    pub fn put_alias(&mut self, table: &AliasTable, symbol: usize) {
+        self.put_alias_at(0, table, symbol);
+    }
+}
+
 /// rANS encoder symbol - 64-bit version.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct B64RansEncSymbol {
-    symbol: ryg_rans_sys::rans_64::Rans64EncSymbol,
+    symbol: crate::backend::rans_64::Rans64EncSymbol,
     scale_bits: u32,
 }
 
+impl B64RansEncSymbol {
+    /// Creates a new symbol like [`RansEncSymbol::new()`], but can be used in
+    /// constant expressions, e.g. to build static tables of symbols for
+    /// fixed models at compile time.
+    ///
+    /// Only available with the `pure-rust` feature, as the C implementation
+    /// can't be called at compile time.
+    ///
+    /// # Panics
+    /// Panics if `scale_bits` is larger than 31. In a constant expression,
+    /// this is a compile-time error.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncSymbol;
+    /// use rans::RansEncSymbol;
+    ///
+    /// static SYMBOLS: [B64RansEncSymbol; 2] = [
+    ///     B64RansEncSymbol::new_const(0, 3, 2),
+    ///     B64RansEncSymbol::new_const(3, 1, 2),
+    /// ];
+    /// assert_eq!(SYMBOLS[1], B64RansEncSymbol::new(3, 1, 2));
+    /// ```
+    #[cfg(feature = "pure-rust")]
+    #[must_use]
+    pub const fn new_const(cum_freq: u32, freq: u32, scale_bits: u32) -> Self {
+        assert!(scale_bits <= 31, "scale_bits must not be larger than 31");
+
+        Self {
+            symbol: crate::backend::rans_64::enc_symbol(cum_freq, freq, scale_bits),
+            scale_bits,
+        }
+    }
+
+    /// Returns the fields of the symbol, used to implement the comparison
+    /// traits (the backend symbol types don't implement them).
+    #[inline]
+    fn key(&self) -> (u64, u32, u32, u32, u32, u32) {
+        let symbol = &self.symbol;
+        (
+            symbol.rcp_freq,
+            symbol.freq,
+            symbol.bias,
+            symbol.cmpl_freq,
+            symbol.rcp_shift,
+            self.scale_bits,
+        )
+    }
+}
+
+impl PartialEq for B64RansEncSymbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for B64RansEncSymbol {}
+
+impl Hash for B64RansEncSymbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
 impl RansEncSymbol for B64RansEncSymbol {
+    const MAX_SCALE_BITS: u32 = 31;
+
     #[inline]
-    #[must_use]
     fn new(cum_freq: u32, freq: u32, scale_bits: u32) -> Self {
+        assert_scale_bits(scale_bits, Self::MAX_SCALE_BITS);
+
         unsafe {
             let mut symbol = MaybeUninit::uninit();
-            ryg_rans_sys::rans_64::rans_64_enc_symbol_init(
+            crate::backend::rans_64::rans_64_enc_symbol_init(
                 symbol.as_mut_ptr(),
                 cum_freq,
                 freq,
@@ -139,11 +770,32 @@ impl RansEncSymbol for B64RansEncSymbol {
             }
         }
     }
+
+    #[inline]
+    fn cum_freq(&self) -> u32 {
+        // Symbols of frequency 1 have the bias adjusted, see
+        // `rans_64_enc_symbol_init()`
+        if self.symbol.freq == 1 {
+            self.symbol.bias - ((1 << self.scale_bits) - 1)
+        } else {
+            self.symbol.bias
+        }
+    }
+
+    #[inline]
+    fn freq(&self) -> u32 {
+        self.symbol.freq
+    }
+
+    #[inline]
+    fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::b64_encoder::{B64RansEncoder, B64RansEncoderMulti};
+    use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoder, B64RansEncoderMulti};
     use crate::encoder::tests as enc_tests;
 
     #[test]
@@ -170,6 +822,15 @@ mod tests {
         enc_tests::test_encode_and_reset(encoder, &data1, &data2);
     }
 
+    #[test]
+    fn test_encode_and_clear() {
+        enc_tests::test_encode_and_clear(
+            B64RansEncoder::new(1024),
+            B64RansEncoder::new(1024),
+            B64RansEncoder::new(1024),
+        );
+    }
+
     #[test]
     fn test_encode_two_symbols() {
         let encoder = B64RansEncoder::new(1024);
@@ -204,6 +865,98 @@ mod tests {
         enc_tests::encode_interleaved(encoder, &data);
     }
 
+    #[test]
+    fn test_encode_with_buffer() {
+        // Leftover garbage in the buffer must not matter
+        let mut buffer = vec![0xff; 256];
+        buffer.truncate(10);
+        let encoder = B64RansEncoderMulti::<2>::with_buffer(buffer);
+        let data = [108, 0, 0, 0, 128, 0, 0, 0, 0, 0, 0, 0, 128, 0, 0, 0];
+
+        let encoder = enc_tests::encode_interleaved(encoder, &data);
+        let buffer = encoder.into_buffer();
+        assert_eq!(buffer.len(), 4);
+        assert!(buffer.capacity() >= 256);
+    }
+
+    #[test]
+    fn test_data_words() {
+        let encoder = B64RansEncoderMulti::<2>::new(1024);
+        let data = [108, 0, 0, 0, 128, 0, 0, 0, 0, 0, 0, 0, 128, 0, 0, 0];
+
+        let encoder = enc_tests::encode_interleaved(encoder, &data);
+        let words: Vec<u8> = encoder
+            .data_words()
+            .iter()
+            .flat_map(|word| word.to_ne_bytes())
+            .collect();
+        assert_eq!(words, data);
+        assert_eq!(encoder.into_words().len(), 4);
+    }
+
+    #[test]
+    fn test_put_index() {
+        enc_tests::test_put_index(B64RansEncoder::new(1024), B64RansEncoder::new(1024));
+    }
+
+    #[test]
+    fn test_try_put_overflow() {
+        let encoder = B64RansEncoder::new(16);
+
+        enc_tests::test_try_put_overflow(encoder);
+    }
+
+    #[test]
+    fn test_try_flush_overflow() {
+        let encoder = B64RansEncoderMulti::<2>::new(12);
+
+        enc_tests::test_try_flush_overflow(encoder);
+    }
+
+    #[test]
+    fn test_encode_growable() {
+        let encoder = B64RansEncoderMulti::<2>::with_growable_buffer();
+        let reference = B64RansEncoderMulti::<2>::new(1 << 16);
+
+        enc_tests::test_encode_growable(encoder, reference);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let encoder = B64RansEncoderMulti::<2>::new(16);
+        let reference = B64RansEncoderMulti::<2>::new(1 << 16);
+
+        enc_tests::test_reserve(encoder, reference);
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let encoder = B64RansEncoderMulti::<2>::with_growable_buffer();
+        let reference = B64RansEncoderMulti::<2>::new(1 << 16);
+
+        enc_tests::test_checkpoint_restore(
+            encoder,
+            reference,
+            B64RansEncoderMulti::checkpoint,
+            B64RansEncoderMulti::restore,
+        );
+    }
+
+    #[test]
+    fn test_is_send_sync() {
+        enc_tests::test_is_send_sync::<B64RansEncoderMulti<2>>();
+    }
+
+    #[test]
+    fn test_symbol_accessors() {
+        enc_tests::test_symbol_accessors::<B64RansEncSymbol>();
+    }
+
+    #[test]
+    fn test_symbol_eq_hash() {
+        enc_tests::test_symbol_eq_hash::<B64RansEncSymbol>();
+    }
+
     #[test]
     fn test_has_debug_output() {
         let encoder = B64RansEncoder::new(1024);
@@ -0,0 +1,79 @@
+// rANS core implementations the encoders and decoders are built on top of.
+//
+// By default, this is the C implementation from
+// [ryg-rans-sys](https://github.com/m4tx/ryg-rans-sys/). When the
+// `pure-rust` feature is enabled, a port written in Rust is used instead,
+// which does not need a C compiler and produces bit-exact identical output.
+
+#[cfg(not(any(feature = "ffi", feature = "pure-rust")))]
+compile_error!("either the `ffi` or the `pure-rust` feature has to be enabled");
+
+/// Lower bound of the normalization interval of the byte-aligned coder.
+pub(crate) const RANS_BYTE_L: u32 = 1 << 23;
+/// Lower bound of the normalization interval of the 64-bit coder.
+pub(crate) const RANS64_L: u64 = 1 << 31;
+
+#[cfg(feature = "pure-rust")]
+pub(crate) mod rans_64;
+#[cfg(feature = "pure-rust")]
+pub(crate) mod rans_byte;
+
+#[cfg(all(feature = "ffi", not(feature = "pure-rust")))]
+pub(crate) use ryg_rans_sys::{rans_64, rans_byte};
+
+#[cfg(all(test, feature = "ffi", feature = "pure-rust"))]
+mod tests {
+    use std::mem::MaybeUninit;
+
+    #[test]
+    fn test_byte_enc_symbol_matches_ffi() {
+        for scale_bits in [2, 8, 12, 16] {
+            for freq in 1..=(1 << scale_bits) {
+                let start = (1 << scale_bits) - freq;
+                let symbol = super::rans_byte::enc_symbol(start, freq, scale_bits);
+                let ffi_symbol = unsafe {
+                    let mut symbol = MaybeUninit::uninit();
+                    ryg_rans_sys::rans_byte::rans_enc_symbol_init(
+                        symbol.as_mut_ptr(),
+                        start,
+                        freq,
+                        scale_bits,
+                    );
+                    symbol.assume_init()
+                };
+
+                assert_eq!(symbol.x_max, ffi_symbol.x_max);
+                assert_eq!(symbol.rcp_freq, ffi_symbol.rcp_freq);
+                assert_eq!(symbol.bias, ffi_symbol.bias);
+                assert_eq!(symbol.cmpl_freq, ffi_symbol.cmpl_freq);
+                assert_eq!(symbol.rcp_shift, ffi_symbol.rcp_shift);
+            }
+        }
+    }
+
+    #[test]
+    fn test_64_enc_symbol_matches_ffi() {
+        for scale_bits in [2u32, 8, 16, 31] {
+            for freq in (1..=(1u32 << scale_bits)).step_by(1 << scale_bits.saturating_sub(16)) {
+                let start = (1 << scale_bits) - freq;
+                let symbol = super::rans_64::enc_symbol(start, freq, scale_bits);
+                let ffi_symbol = unsafe {
+                    let mut symbol = MaybeUninit::uninit();
+                    ryg_rans_sys::rans_64::rans_64_enc_symbol_init(
+                        symbol.as_mut_ptr(),
+                        start,
+                        freq,
+                        scale_bits,
+                    );
+                    symbol.assume_init()
+                };
+
+                assert_eq!(symbol.rcp_freq, ffi_symbol.rcp_freq);
+                assert_eq!(symbol.freq, ffi_symbol.freq);
+                assert_eq!(symbol.bias, ffi_symbol.bias);
+                assert_eq!(symbol.cmpl_freq, ffi_symbol.cmpl_freq);
+                assert_eq!(symbol.rcp_shift, ffi_symbol.rcp_shift);
+            }
+        }
+    }
+}
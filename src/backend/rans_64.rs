@@ -0,0 +1,212 @@
+// Port of `rans64.h` from ryg_rans. The function signatures mirror the ones
+// exposed by `ryg_rans_sys::rans_64`, so that both can be used
+// interchangeably.
+
+use super::RANS64_L;
+
+pub(crate) type Rans64State = u64;
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Rans64EncSymbol {
+    /// Fixed-point reciprocal frequency
+    pub(crate) rcp_freq: u64,
+    /// Symbol frequency
+    pub(crate) freq: u32,
+    /// Bias
+    pub(crate) bias: u32,
+    /// Complement of frequency: (1 << scale_bits) - freq
+    pub(crate) cmpl_freq: u32,
+    /// Reciprocal shift
+    pub(crate) rcp_shift: u32,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Rans64DecSymbol {
+    /// Start of range
+    pub(crate) start: u32,
+    /// Symbol frequency
+    pub(crate) freq: u32,
+}
+
+#[inline]
+fn mul_hi(a: u64, b: u64) -> u64 {
+    ((u128::from(a) * u128::from(b)) >> 64) as u64
+}
+
+pub(crate) unsafe fn rans_64_enc_init(r: &mut Rans64State) {
+    *r = RANS64_L;
+}
+
+pub(crate) unsafe fn rans_64_enc_flush(r: &mut Rans64State, pptr: &mut *mut u32) {
+    let x = *r;
+
+    let ptr = pptr.sub(2);
+    ptr.write_unaligned(x as u32);
+    ptr.add(1).write_unaligned((x >> 32) as u32);
+    *pptr = ptr;
+}
+
+pub(crate) unsafe fn rans_64_dec_init(r: &mut Rans64State, pptr: &mut *mut u32) {
+    let ptr = *pptr;
+
+    let mut x = u64::from(ptr.read_unaligned());
+    x |= u64::from(ptr.add(1).read_unaligned()) << 32;
+    *pptr = ptr.add(2);
+    *r = x;
+}
+
+pub(crate) unsafe fn rans_64_dec_get(r: &mut Rans64State, scale_bits: u32) -> u32 {
+    (*r & ((1 << scale_bits) - 1)) as u32
+}
+
+/// Builds an encoder symbol for a symbol with cumulative frequency `start`
+/// and frequency `freq`, with all frequencies summing up to
+/// `1 << scale_bits`.
+pub(crate) const fn enc_symbol(start: u32, freq: u32, scale_bits: u32) -> Rans64EncSymbol {
+    debug_assert!(scale_bits <= 31);
+    debug_assert!(start <= (1 << scale_bits));
+    debug_assert!(freq <= (1 << scale_bits) - start);
+
+    // Say M := 1 << scale_bits.
+    //
+    // The original encoder does:
+    //   x_new = (x/freq)*M + start + (x%freq)
+    //
+    // The fast encoder does (schematically):
+    //   q     = mul_hi(x, rcp_freq) >> rcp_shift   (division)
+    //   r     = x - q*freq                         (remainder)
+    //   x_new = q*M + bias + r                     (new x)
+    // plugging in r into x_new yields:
+    //   x_new = bias + x + q*(M - freq)
+    //        =: bias + x + q*cmpl_freq             (*)
+    //
+    // and we can just precompute cmpl_freq. Now we just need to set up our
+    // parameters such that the original encoder and the fast encoder agree.
+    let cmpl_freq = (1 << scale_bits) - freq;
+    if freq < 2 {
+        // freq=0 symbols are never valid to encode, so it doesn't matter what
+        // we set our values to.
+        //
+        // freq=1 is tricky, since the reciprocal of 1 is 1; unfortunately,
+        // our fixed-point reciprocal approximation can only multiply by values
+        // smaller than 1.
+        //
+        // So we use the "next best thing": rcp_freq=~0, rcp_shift=0, and
+        // bias = start + M - 1 (see `rans_byte` for the derivation).
+        Rans64EncSymbol {
+            rcp_freq: !0,
+            freq,
+            bias: start + (1 << scale_bits) - 1,
+            cmpl_freq,
+            rcp_shift: 0,
+        }
+    } else {
+        // Alverson, "Integer Division using reciprocals"
+        // shift=ceil(log2(freq))
+        let mut shift = 0;
+        while freq > (1 << shift) {
+            shift += 1;
+        }
+
+        let freq_64 = freq as u64;
+        let x0 = freq_64 - 1;
+        let x1 = 1u64 << (shift + 31);
+
+        let t1 = x1 / freq_64;
+        let x0 = x0 + ((x1 % freq_64) << 32);
+        let t0 = x0 / freq_64;
+
+        Rans64EncSymbol {
+            rcp_freq: t0 + (t1 << 32),
+            freq,
+            bias: start,
+            cmpl_freq,
+            rcp_shift: shift - 1,
+        }
+    }
+}
+
+pub(crate) unsafe fn rans_64_enc_symbol_init(
+    s: *mut Rans64EncSymbol,
+    start: u32,
+    freq: u32,
+    scale_bits: u32,
+) {
+    s.write(enc_symbol(start, freq, scale_bits));
+}
+
+/// Builds a decoder symbol for a symbol with cumulative frequency `start`
+/// and frequency `freq`.
+pub(crate) const fn dec_symbol(start: u32, freq: u32) -> Rans64DecSymbol {
+    debug_assert!(start <= (1 << 31));
+    debug_assert!(freq <= (1 << 31) - start);
+
+    Rans64DecSymbol { start, freq }
+}
+
+pub(crate) unsafe fn rans_64_dec_symbol_init(s: *mut Rans64DecSymbol, start: u32, freq: u32) {
+    s.write(dec_symbol(start, freq));
+}
+
+pub(crate) unsafe fn rans_64_enc_put_symbol(
+    r: &mut Rans64State,
+    pptr: &mut *mut u32,
+    sym: &Rans64EncSymbol,
+    scale_bits: u32,
+) {
+    // can't encode symbol with freq=0
+    debug_assert!(sym.freq != 0);
+
+    // renormalize
+    let mut x = *r;
+    // this turns into a shift.
+    let x_max = ((RANS64_L >> scale_bits) << 32) * u64::from(sym.freq);
+    if x >= x_max {
+        let ptr = pptr.sub(1);
+        ptr.write_unaligned(x as u32);
+        *pptr = ptr;
+        x >>= 32;
+    }
+
+    // x = C(s,x)
+    let q = mul_hi(x, sym.rcp_freq) >> sym.rcp_shift;
+    *r = x
+        .wrapping_add(u64::from(sym.bias))
+        .wrapping_add(q.wrapping_mul(u64::from(sym.cmpl_freq)));
+}
+
+pub(crate) unsafe fn rans_64_dec_advance_symbol(
+    r: &mut Rans64State,
+    pptr: &mut *mut u32,
+    sym: &Rans64DecSymbol,
+    scale_bits: u32,
+) {
+    rans_64_dec_advance_symbol_step(r, sym, scale_bits);
+    rans_64_dec_renorm(r, pptr);
+}
+
+pub(crate) unsafe fn rans_64_dec_advance_symbol_step(
+    r: &mut Rans64State,
+    sym: &Rans64DecSymbol,
+    scale_bits: u32,
+) {
+    let mask = (1u64 << scale_bits) - 1;
+
+    let x = *r;
+    *r = u64::from(sym.freq)
+        .wrapping_mul(x >> scale_bits)
+        .wrapping_add(x & mask)
+        .wrapping_sub(u64::from(sym.start));
+}
+
+pub(crate) unsafe fn rans_64_dec_renorm(r: &mut Rans64State, pptr: &mut *mut u32) {
+    // renormalize
+    let mut x = *r;
+    if x < RANS64_L {
+        let ptr = *pptr;
+        x = (x << 32) | u64::from(ptr.read_unaligned());
+        *pptr = ptr.add(1);
+    }
+
+    *r = x;
+}
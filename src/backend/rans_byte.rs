@@ -0,0 +1,221 @@
+// Port of `rans_byte.h` from ryg_rans. The function signatures mirror the ones
+// exposed by `ryg_rans_sys::rans_byte`, so that both can be used
+// interchangeably.
+
+use super::RANS_BYTE_L;
+
+pub(crate) type RansState = u32;
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct RansEncSymbol {
+    /// (Exclusive) upper bound of pre-normalization interval
+    pub(crate) x_max: u32,
+    /// Fixed-point reciprocal frequency
+    pub(crate) rcp_freq: u32,
+    /// Bias
+    pub(crate) bias: u32,
+    /// Complement of frequency: (1 << scale_bits) - freq
+    pub(crate) cmpl_freq: u16,
+    /// Reciprocal shift
+    pub(crate) rcp_shift: u16,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct RansDecSymbol {
+    /// Start of range
+    pub(crate) start: u16,
+    /// Symbol frequency
+    pub(crate) freq: u16,
+}
+
+pub(crate) unsafe fn rans_enc_init(r: &mut RansState) {
+    *r = RANS_BYTE_L;
+}
+
+pub(crate) unsafe fn rans_enc_flush(r: &mut RansState, pptr: &mut *mut u8) {
+    let x = *r;
+    let ptr = pptr.sub(4);
+    ptr.copy_from_nonoverlapping(x.to_le_bytes().as_ptr(), 4);
+    *pptr = ptr;
+}
+
+pub(crate) unsafe fn rans_dec_init(r: &mut RansState, pptr: &mut *mut u8) {
+    let mut bytes = [0; 4];
+    bytes.as_mut_ptr().copy_from_nonoverlapping(*pptr, 4);
+    *r = u32::from_le_bytes(bytes);
+    *pptr = pptr.add(4);
+}
+
+pub(crate) unsafe fn rans_dec_get(r: &mut RansState, scale_bits: u32) -> u32 {
+    *r & ((1 << scale_bits) - 1)
+}
+
+/// Builds an encoder symbol for a symbol with cumulative frequency `start`
+/// and frequency `freq`, with all frequencies summing up to
+/// `1 << scale_bits`.
+pub(crate) const fn enc_symbol(start: u32, freq: u32, scale_bits: u32) -> RansEncSymbol {
+    debug_assert!(scale_bits <= 16);
+    debug_assert!(start <= (1 << scale_bits));
+    debug_assert!(freq <= (1 << scale_bits) - start);
+
+    // Say M := 1 << scale_bits.
+    //
+    // The original encoder does:
+    //   x_new = (x/freq)*M + start + (x%freq)
+    //
+    // The fast encoder does (schematically):
+    //   q     = mul_hi(x, rcp_freq) >> rcp_shift   (division)
+    //   r     = x - q*freq                         (remainder)
+    //   x_new = q*M + bias + r                     (new x)
+    // plugging in r into x_new yields:
+    //   x_new = bias + x + q*(M - freq)
+    //        =: bias + x + q*cmpl_freq             (*)
+    let x_max = ((RANS_BYTE_L >> scale_bits) << 8) * freq;
+    let cmpl_freq = ((1 << scale_bits) - freq) as u16;
+    if freq < 2 {
+        // freq=0 symbols are never valid to encode, so it doesn't matter what
+        // we set our values to.
+        //
+        // freq=1 is tricky, since the reciprocal of 1 is 1; unfortunately,
+        // our fixed-point reciprocal approximation can only multiply by values
+        // smaller than 1.
+        //
+        // So we use the "next best thing": rcp_freq=0xffffffff, rcp_shift=0.
+        // This gives:
+        //   q = mul_hi(x, rcp_freq) >> rcp_shift
+        //     = mul_hi(x, (1<<32) - 1)) >> 0
+        //     = floor(x - x/(2^32))
+        //     = x - 1 if 1 <= x < 2^32
+        // and we know that x>0 (x=0 is never in a valid normalization
+        // interval).
+        //
+        // So we now need to choose the other parameters such that
+        //   x_new = x*M + start
+        // plug it in:
+        //     x*M + start                   (desired result)
+        //   = bias + x + q*cmpl_freq        (*)
+        //   = bias + x + (x - 1)*(M - 1)    (plug in q=x-1, cmpl_freq)
+        //   = bias + 1 + (x - 1)*M
+        //   = x*M + (bias + 1 - M)
+        //
+        // so we have start = bias + 1 - M, or equivalently
+        //   bias = start + M - 1.
+        RansEncSymbol {
+            x_max,
+            rcp_freq: !0,
+            bias: start + (1 << scale_bits) - 1,
+            cmpl_freq,
+            rcp_shift: 0,
+        }
+    } else {
+        // Alverson, "Integer Division using reciprocals"
+        // shift=ceil(log2(freq))
+        let mut shift = 0;
+        while freq > (1 << shift) {
+            shift += 1;
+        }
+
+        RansEncSymbol {
+            x_max,
+            rcp_freq: (((1u64 << (shift + 31)) + freq as u64 - 1) / freq as u64) as u32,
+            bias: start,
+            cmpl_freq,
+            rcp_shift: (shift - 1) as u16,
+        }
+    }
+}
+
+pub(crate) unsafe fn rans_enc_symbol_init(
+    s: *mut RansEncSymbol,
+    start: u32,
+    freq: u32,
+    scale_bits: u32,
+) {
+    s.write(enc_symbol(start, freq, scale_bits));
+}
+
+/// Builds a decoder symbol for a symbol with cumulative frequency `start`
+/// and frequency `freq`.
+pub(crate) const fn dec_symbol(start: u32, freq: u32) -> RansDecSymbol {
+    debug_assert!(start <= (1 << 16));
+    debug_assert!(freq <= (1 << 16) - start);
+
+    RansDecSymbol {
+        start: start as u16,
+        freq: freq as u16,
+    }
+}
+
+pub(crate) unsafe fn rans_dec_symbol_init(s: *mut RansDecSymbol, start: u32, freq: u32) {
+    s.write(dec_symbol(start, freq));
+}
+
+pub(crate) unsafe fn rans_enc_put_symbol(
+    r: &mut RansState,
+    pptr: &mut *mut u8,
+    sym: &RansEncSymbol,
+) {
+    // can't encode symbol with freq=0
+    debug_assert!(sym.x_max != 0);
+
+    // renormalize
+    let mut x = *r;
+    let x_max = sym.x_max;
+    if x >= x_max {
+        let mut ptr = *pptr;
+        while x >= x_max {
+            ptr = ptr.sub(1);
+            *ptr = x as u8;
+            x >>= 8;
+        }
+        *pptr = ptr;
+    }
+
+    // x = C(s,x)
+    // NOTE: written this way so we get a 32-bit "multiply high" when
+    // available. If you're on a 64-bit platform with cheap multiplies
+    // (e.g. x64), just bake the +32 into rcp_shift.
+    let q = ((u64::from(x) * u64::from(sym.rcp_freq)) >> 32) as u32 >> sym.rcp_shift;
+    *r = x
+        .wrapping_add(sym.bias)
+        .wrapping_add(q.wrapping_mul(u32::from(sym.cmpl_freq)));
+}
+
+pub(crate) unsafe fn rans_dec_advance_symbol(
+    r: &mut RansState,
+    pptr: &mut *mut u8,
+    sym: &RansDecSymbol,
+    scale_bits: u32,
+) {
+    rans_dec_advance_symbol_step(r, sym, scale_bits);
+    rans_dec_renorm(r, pptr);
+}
+
+pub(crate) unsafe fn rans_dec_advance_symbol_step(
+    r: &mut RansState,
+    sym: &RansDecSymbol,
+    scale_bits: u32,
+) {
+    let mask = (1u32 << scale_bits) - 1;
+
+    let x = *r;
+    *r = u32::from(sym.freq)
+        .wrapping_mul(x >> scale_bits)
+        .wrapping_add(x & mask)
+        .wrapping_sub(u32::from(sym.start));
+}
+
+pub(crate) unsafe fn rans_dec_renorm(r: &mut RansState, pptr: &mut *mut u8) {
+    // renormalize
+    let mut x = *r;
+    if x < RANS_BYTE_L {
+        let mut ptr = *pptr;
+        while x < RANS_BYTE_L {
+            x = (x << 8) | u32::from(*ptr);
+            ptr = ptr.add(1);
+        }
+        *pptr = ptr;
+    }
+
+    *r = x;
+}
@@ -0,0 +1,479 @@
+use crate::binary::{BinRansDecoder, BinRansEncoder, BitModel};
+use crate::RansError;
+
+/// Binarizes `value` with the truncated unary code: `value` ones, followed by
+/// a terminating zero unless `value` equals `max`.
+///
+/// # Panics
+/// Panics if `value` is larger than `max`.
+///
+/// # Examples
+/// ```
+/// use rans::binarization::truncated_unary;
+///
+/// assert_eq!(truncated_unary(2, 4), [true, true, false]);
+/// assert_eq!(truncated_unary(4, 4), [true, true, true, true]);
+/// ```
+#[must_use]
+pub fn truncated_unary(value: u32, max: u32) -> Vec<bool> {
+    assert!(value <= max, "value must not be larger than max");
+
+    let mut bins = vec![true; value as usize];
+    if value < max {
+        bins.push(false);
+    }
+    bins
+}
+
+/// Binarizes `value` with the Golomb-Rice code with parameter `k`: the
+/// quotient `value >> k` in unary (ones terminated by a zero), followed by the
+/// `k` lowest bits of `value`, most significant bit first.
+///
+/// With `k` equal to 0, this is the plain unary code. Note that the prefix
+/// is `value >> k` bins long, so `k` should be chosen so that the quotients
+/// stay small.
+///
+/// # Panics
+/// Panics if `k` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::binarization::golomb_rice;
+///
+/// assert_eq!(golomb_rice(5, 1), [true, true, false, true]);
+/// assert_eq!(golomb_rice(2, 0), [true, true, false]);
+/// ```
+#[must_use]
+pub fn golomb_rice(value: u32, k: u32) -> Vec<bool> {
+    assert!(k <= 31, "k must not be larger than 31");
+
+    let mut bins = vec![true; (value >> k) as usize];
+    bins.push(false);
+    push_suffix(&mut bins, u64::from(value), k);
+    bins
+}
+
+/// Binarizes `value` with the `k`-th order exp-Golomb code, as used by CABAC
+/// for the suffixes of large values: each prefix one doubles the size of the
+/// range the value falls in, the prefix is terminated by a zero, and the
+/// suffix holds the offset of the value in its range, most significant bit
+/// first.
+///
+/// The code is at most `2 * (32 - k) + 1` bins long, which makes it suitable
+/// for values whose magnitude isn't known in advance.
+///
+/// # Panics
+/// Panics if `k` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::binarization::exp_golomb;
+///
+/// assert_eq!(exp_golomb(0, 0), [false]);
+/// assert_eq!(exp_golomb(1, 0), [true, false, false]);
+/// assert_eq!(exp_golomb(3, 0), [true, true, false, false, false]);
+/// assert_eq!(exp_golomb(3, 2), [false, true, true]);
+/// ```
+#[must_use]
+pub fn exp_golomb(value: u32, k: u32) -> Vec<bool> {
+    assert!(k <= 31, "k must not be larger than 31");
+
+    let mut bins = Vec::new();
+    let mut value = u64::from(value);
+    let mut k = k;
+    while value >= 1 << k {
+        bins.push(true);
+        value -= 1 << k;
+        k += 1;
+    }
+    bins.push(false);
+    push_suffix(&mut bins, value, k);
+    bins
+}
+
+/// Encodes `value` with the [`truncated_unary()`] code.
+///
+/// Bin `i` is coded with `models[i]`, or with the last model if there are
+/// fewer models than bins, so that the first (most informative) bins get
+/// their own contexts, like in CABAC. If `models` is empty, all the bins are
+/// coded as bypass bits.
+///
+/// # Panics
+/// Panics if `value` is larger than `max`.
+///
+/// # Examples
+/// ```
+/// use rans::binarization::{decode_truncated_unary, put_truncated_unary};
+/// use rans::binary::{BinRansDecoder, BinRansEncoder, BitModel};
+///
+/// let mut encoder = BinRansEncoder::new();
+/// let mut models = [BitModel::new(); 2];
+/// put_truncated_unary(&mut encoder, 3, 3, &mut models);
+/// put_truncated_unary(&mut encoder, 1, 3, &mut models);
+/// encoder.flush();
+///
+/// let mut decoder = BinRansDecoder::new(encoder.data());
+/// let mut models = [BitModel::new(); 2];
+/// assert_eq!(decode_truncated_unary(&mut decoder, 3, &mut models), 3);
+/// assert_eq!(decode_truncated_unary(&mut decoder, 3, &mut models), 1);
+/// ```
+pub fn put_truncated_unary(
+    encoder: &mut BinRansEncoder,
+    value: u32,
+    max: u32,
+    models: &mut [BitModel],
+) {
+    put_bins(encoder, &truncated_unary(value, max), models);
+}
+
+/// Decodes a value encoded with [`put_truncated_unary()`]. `max` and the
+/// initial state of `models` have to be the same as the ones used when
+/// encoding.
+///
+/// # Examples
+/// ```
+/// use rans::binarization::{decode_truncated_unary, put_truncated_unary};
+/// use rans::binary::{BinRansDecoder, BinRansEncoder};
+///
+/// let mut encoder = BinRansEncoder::new();
+/// put_truncated_unary(&mut encoder, 2, 5, &mut []);
+/// encoder.flush();
+///
+/// let mut decoder = BinRansDecoder::new(encoder.data());
+/// assert_eq!(decode_truncated_unary(&mut decoder, 5, &mut []), 2);
+/// ```
+pub fn decode_truncated_unary(
+    decoder: &mut BinRansDecoder<'_>,
+    max: u32,
+    models: &mut [BitModel],
+) -> u32 {
+    let mut value = 0;
+    while value < max && decode_prefix_bin(decoder, value as usize, models) {
+        value += 1;
+    }
+    value
+}
+
+/// Encodes `value` with the [`golomb_rice()`] code. The prefix bins are coded
+/// with `models` like in [`put_truncated_unary()`], and the suffix bits are
+/// coded as bypass bits.
+///
+/// # Panics
+/// Panics if `k` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::binarization::{decode_golomb_rice, put_golomb_rice};
+/// use rans::binary::{BinRansDecoder, BinRansEncoder, BitModel};
+///
+/// let mut encoder = BinRansEncoder::new();
+/// let mut models = [BitModel::new(); 3];
+/// put_golomb_rice(&mut encoder, 21, 3, &mut models);
+/// encoder.flush();
+///
+/// let mut decoder = BinRansDecoder::new(encoder.data());
+/// let mut models = [BitModel::new(); 3];
+/// assert_eq!(decode_golomb_rice(&mut decoder, 3, &mut models), Ok(21));
+/// ```
+pub fn put_golomb_rice(encoder: &mut BinRansEncoder, value: u32, k: u32, models: &mut [BitModel]) {
+    put_bins(encoder, &golomb_rice(value, k), models);
+}
+
+/// Decodes a value encoded with [`put_golomb_rice()`]. `k` and the initial
+/// state of `models` have to be the same as the ones used when encoding.
+///
+/// # Errors
+/// Returns [`RansError::InvalidSymbol`] if the decoded value doesn't fit in a
+/// `u32`, which means the data is corrupted.
+///
+/// # Panics
+/// Panics if `k` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::binarization::{decode_golomb_rice, put_golomb_rice};
+/// use rans::binary::{BinRansDecoder, BinRansEncoder};
+///
+/// let mut encoder = BinRansEncoder::new();
+/// put_golomb_rice(&mut encoder, 7, 2, &mut []);
+/// encoder.flush();
+///
+/// let mut decoder = BinRansDecoder::new(encoder.data());
+/// assert_eq!(decode_golomb_rice(&mut decoder, 2, &mut []), Ok(7));
+/// ```
+pub fn decode_golomb_rice(
+    decoder: &mut BinRansDecoder<'_>,
+    k: u32,
+    models: &mut [BitModel],
+) -> Result<u32, RansError> {
+    assert!(k <= 31, "k must not be larger than 31");
+
+    let max_quotient = u32::MAX >> k;
+    let mut quotient = 0;
+    while decode_prefix_bin(decoder, quotient as usize, models) {
+        if quotient == max_quotient {
+            return Err(RansError::InvalidSymbol);
+        }
+        quotient += 1;
+    }
+
+    Ok(quotient << k | decode_suffix(decoder, k) as u32)
+}
+
+/// Encodes `value` with the [`exp_golomb()`] code. The prefix bins are coded
+/// with `models` like in [`put_truncated_unary()`], and the suffix bits are
+/// coded as bypass bits.
+///
+/// # Panics
+/// Panics if `k` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::binarization::{decode_exp_golomb, put_exp_golomb};
+/// use rans::binary::{BinRansDecoder, BinRansEncoder, BitModel};
+///
+/// let mut encoder = BinRansEncoder::new();
+/// let mut models = [BitModel::new(); 4];
+/// put_exp_golomb(&mut encoder, 1000, 0, &mut models);
+/// put_exp_golomb(&mut encoder, u32::MAX, 0, &mut models);
+/// encoder.flush();
+///
+/// let mut decoder = BinRansDecoder::new(encoder.data());
+/// let mut models = [BitModel::new(); 4];
+/// assert_eq!(decode_exp_golomb(&mut decoder, 0, &mut models), Ok(1000));
+/// assert_eq!(decode_exp_golomb(&mut decoder, 0, &mut models), Ok(u32::MAX));
+/// ```
+pub fn put_exp_golomb(encoder: &mut BinRansEncoder, value: u32, k: u32, models: &mut [BitModel]) {
+    put_bins(encoder, &exp_golomb(value, k), models);
+}
+
+/// Decodes a value encoded with [`put_exp_golomb()`]. `k` and the initial
+/// state of `models` have to be the same as the ones used when encoding.
+///
+/// # Errors
+/// Returns [`RansError::InvalidSymbol`] if the decoded value doesn't fit in a
+/// `u32`, which means the data is corrupted.
+///
+/// # Panics
+/// Panics if `k` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::binarization::{decode_exp_golomb, put_exp_golomb};
+/// use rans::binary::{BinRansDecoder, BinRansEncoder};
+///
+/// let mut encoder = BinRansEncoder::new();
+/// put_exp_golomb(&mut encoder, 12, 1, &mut []);
+/// encoder.flush();
+///
+/// let mut decoder = BinRansDecoder::new(encoder.data());
+/// assert_eq!(decode_exp_golomb(&mut decoder, 1, &mut []), Ok(12));
+/// ```
+pub fn decode_exp_golomb(
+    decoder: &mut BinRansDecoder<'_>,
+    k: u32,
+    models: &mut [BitModel],
+) -> Result<u32, RansError> {
+    assert!(k <= 31, "k must not be larger than 31");
+
+    let mut offset = 0_u64;
+    let mut k = k;
+    let mut index = 0;
+    while decode_prefix_bin(decoder, index, models) {
+        if k == 32 {
+            return Err(RansError::InvalidSymbol);
+        }
+        offset += 1 << k;
+        k += 1;
+        index += 1;
+    }
+
+    u32::try_from(offset + decode_suffix(decoder, k)).map_err(|_| RansError::InvalidSymbol)
+}
+
+/// Appends the `bits` lowest bits of `value` to `bins`, most significant bit
+/// first.
+fn push_suffix(bins: &mut Vec<bool>, value: u64, bits: u32) {
+    bins.extend((0..bits).rev().map(|bit| value >> bit & 1 != 0));
+}
+
+/// Encodes `bins`, coding the bins up to and including the first zero (the
+/// prefix) with `models`, and the rest as bypass bits.
+fn put_bins(encoder: &mut BinRansEncoder, bins: &[bool], models: &mut [BitModel]) {
+    let prefix_len = bins
+        .iter()
+        .position(|&bin| !bin)
+        .map_or(bins.len(), |index| index + 1);
+
+    for (index, &bin) in bins[..prefix_len].iter().enumerate() {
+        match prefix_model(models, index) {
+            Some(model) => encoder.put_adaptive(bin, model),
+            None => encoder.put_bypass(bin),
+        }
+    }
+    for &bin in &bins[prefix_len..] {
+        encoder.put_bypass(bin);
+    }
+}
+
+fn decode_prefix_bin(
+    decoder: &mut BinRansDecoder<'_>,
+    index: usize,
+    models: &mut [BitModel],
+) -> bool {
+    match prefix_model(models, index) {
+        Some(model) => decoder.decode_adaptive(model),
+        None => decoder.decode_bypass(),
+    }
+}
+
+fn decode_suffix(decoder: &mut BinRansDecoder<'_>, bits: u32) -> u64 {
+    (0..bits).fold(0, |value, _| {
+        value << 1 | u64::from(decoder.decode_bypass())
+    })
+}
+
+/// Returns the model for the prefix bin at `index`: the last one is shared
+/// by all the remaining bins.
+fn prefix_model(models: &mut [BitModel], index: usize) -> Option<&mut BitModel> {
+    let last = models.len().checked_sub(1)?;
+    models.get_mut(index.min(last))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::binarization::{
+        decode_exp_golomb, decode_golomb_rice, decode_truncated_unary, exp_golomb, golomb_rice,
+        put_exp_golomb, put_golomb_rice, put_truncated_unary, truncated_unary,
+    };
+    use crate::binary::{BinRansDecoder, BinRansEncoder, BitModel};
+    use crate::RansError;
+
+    #[test]
+    fn test_code_lengths() {
+        for value in [0, 1, 2, 7, 8, 1000, u32::MAX >> 1, u32::MAX] {
+            for k in [0, 1, 4, 31] {
+                let ones = 63 - ((u64::from(value) + (1 << k)) >> k).leading_zeros();
+                assert_eq!(exp_golomb(value, k).len() as u32, 2 * ones + 1 + k);
+            }
+        }
+        for value in 0..100 {
+            assert_eq!(golomb_rice(value, 3).len() as u32, (value >> 3) + 4);
+            assert_eq!(truncated_unary(value, 99).len() as u32, (value + 1).min(99));
+        }
+    }
+
+    #[test]
+    fn test_codes_are_prefix_free() {
+        let codes: Vec<Vec<bool>> = (0..200).map(|value| exp_golomb(value, 1)).collect();
+        for (i, a) in codes.iter().enumerate() {
+            for b in &codes[i + 1..] {
+                assert!(!b.starts_with(a) && !a.starts_with(b));
+            }
+        }
+    }
+
+    fn round_trip(models: usize) {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let values: Vec<(u32, u32)> = (0..5000)
+            .map(|_| {
+                let k = rng.gen_range(0..6);
+                let magnitude = rng.gen_range(0..32);
+                (rng.gen_range(0..=u32::MAX >> magnitude), k)
+            })
+            .collect();
+
+        let mut encoder = BinRansEncoder::new();
+        let mut tu_models = vec![BitModel::new(); models];
+        let mut rice_models = vec![BitModel::new(); models];
+        let mut eg_models = vec![BitModel::new(); models];
+        for &(value, k) in &values {
+            put_truncated_unary(&mut encoder, value % 20, 19, &mut tu_models);
+            put_golomb_rice(&mut encoder, value % 256, k, &mut rice_models);
+            put_exp_golomb(&mut encoder, value, k, &mut eg_models);
+        }
+        encoder.flush();
+
+        let mut decoder = BinRansDecoder::new(encoder.data());
+        let mut tu_models = vec![BitModel::new(); models];
+        let mut rice_models = vec![BitModel::new(); models];
+        let mut eg_models = vec![BitModel::new(); models];
+        for &(value, k) in &values {
+            assert_eq!(
+                decode_truncated_unary(&mut decoder, 19, &mut tu_models),
+                value % 20
+            );
+            assert_eq!(
+                decode_golomb_rice(&mut decoder, k, &mut rice_models),
+                Ok(value % 256)
+            );
+            assert_eq!(
+                decode_exp_golomb(&mut decoder, k, &mut eg_models),
+                Ok(value)
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_trip_bypass() {
+        round_trip(0);
+    }
+
+    #[test]
+    fn test_round_trip_adaptive() {
+        round_trip(1);
+        round_trip(8);
+    }
+
+    #[test]
+    fn test_adaptive_prefix_compresses() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        let values: Vec<u32> = (0..10000)
+            .map(|_| if rng.gen_bool(0.9) { 0 } else { 1 })
+            .collect();
+
+        let size = |models: &mut [BitModel]| {
+            let mut encoder = BinRansEncoder::new();
+            for &value in &values {
+                put_exp_golomb(&mut encoder, value, 0, models);
+            }
+            encoder.flush();
+            encoder.data().len()
+        };
+
+        assert!(size(&mut [BitModel::new(); 2]) * 2 < size(&mut []));
+    }
+
+    #[test]
+    fn test_golomb_rice_overflow() {
+        let mut encoder = BinRansEncoder::new();
+        for bin in golomb_rice(2, 0) {
+            encoder.put_bypass(bin);
+        }
+        encoder.flush();
+
+        let mut decoder = BinRansDecoder::new(encoder.data());
+        assert_eq!(
+            decode_golomb_rice(&mut decoder, 31, &mut []),
+            Err(RansError::InvalidSymbol)
+        );
+    }
+
+    #[test]
+    fn test_exp_golomb_overflow() {
+        let mut encoder = BinRansEncoder::new();
+        for _ in 0..40 {
+            encoder.put_bypass(true);
+        }
+        encoder.flush();
+
+        let mut decoder = BinRansDecoder::new(encoder.data());
+        assert_eq!(
+            decode_exp_golomb(&mut decoder, 0, &mut []),
+            Err(RansError::InvalidSymbol)
+        );
+    }
+}
@@ -0,0 +1,537 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::decoder_input::DecoderInput;
+use crate::{RansDecSymbol, RansDecoder, RansEncSymbol, RansEncoder, RansEncoderMulti, RansError};
+
+/// Precision of the probabilities kept by [`BitModel`], in bits.
+pub const BIT_MODEL_BITS: u32 = 15;
+/// Default adaptation rate of [`BitModel`].
+const DEFAULT_RATE: u32 = 5;
+
+/// Adaptive probability of a single binary symbol.
+///
+/// The model keeps the probability of the bit being 0 with
+/// [`BIT_MODEL_BITS`] bits of precision, and moves it towards the coded bit
+/// by `1 / 2^rate` of the distance after each update. Lower rates adapt
+/// faster, higher rates give more precise estimates of stationary data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitModel {
+    prob: u32,
+    rate: u32,
+}
+
+impl BitModel {
+    /// Creates a new `BitModel` instance, with both bit values equally
+    /// probable and the default adaptation rate.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BitModel;
+    ///
+    /// let model = BitModel::new();
+    /// assert_eq!(model.probability(), 1 << 14);
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_rate(DEFAULT_RATE)
+    }
+
+    /// Creates a new `BitModel` instance like [`Self::new()`], but with a
+    /// custom adaptation `rate`.
+    ///
+    /// # Panics
+    /// Panics if `rate` is not between 1 and 14.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BitModel;
+    ///
+    /// let mut model = BitModel::with_rate(1);
+    /// model.update(false);
+    /// assert_eq!(model.probability(), 3 << 13);
+    /// ```
+    #[must_use]
+    pub fn with_rate(rate: u32) -> Self {
+        assert!(
+            (1..BIT_MODEL_BITS).contains(&rate),
+            "rate must be between 1 and 14"
+        );
+
+        Self {
+            prob: 1 << (BIT_MODEL_BITS - 1),
+            rate,
+        }
+    }
+
+    /// Returns the current probability of the bit being 0, scaled to
+    /// `1 << BIT_MODEL_BITS`. It's never 0 nor `1 << BIT_MODEL_BITS`, so
+    /// both bit values can always be coded.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BitModel;
+    ///
+    /// let mut model = BitModel::new();
+    /// model.update(true);
+    /// assert!(model.probability() < 1 << 14);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn probability(&self) -> u32 {
+        self.prob
+    }
+
+    /// Updates the model after coding `bit`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BitModel;
+    ///
+    /// let mut model = BitModel::new();
+    /// for _ in 0..100 {
+    ///     model.update(false);
+    /// }
+    /// assert!(model.probability() > 32000);
+    /// ```
+    #[inline]
+    pub fn update(&mut self, bit: bool) {
+        if bit {
+            self.prob -= self.prob >> self.rate;
+        } else {
+            self.prob += ((1 << BIT_MODEL_BITS) - self.prob) >> self.rate;
+        }
+    }
+}
+
+impl Default for BitModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Byte-aligned rANS encoder of binary symbols.
+///
+/// Each bit is coded with its own probability, either given explicitly or
+/// taken from a [`BitModel`]. Since rANS decodes the symbols in the reverse
+/// order of encoding, the bits are buffered and only get encoded (in
+/// reverse) when [`Self::flush()`] is called, so the bits can be put in the
+/// same order as they will be decoded.
+#[derive(Debug)]
+pub struct BinRansEncoder {
+    encoder: ByteRansEncoder,
+    symbols: Vec<ByteRansEncSymbol>,
+}
+
+impl BinRansEncoder {
+    /// Creates a new `BinRansEncoder` instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BinRansEncoder;
+    ///
+    /// let encoder = BinRansEncoder::new();
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            encoder: ByteRansEncoder::with_growable_buffer(),
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Adds `bit` to the data to be encoded, given the probability `prob` of
+    /// the bit being 0, scaled to `1 << scale_bits`.
+    ///
+    /// # Panics
+    /// Panics if `scale_bits` is larger than 16, or if `prob` is 0 or not
+    /// smaller than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BinRansEncoder;
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// encoder.put(false, 3000, 12);
+    /// ```
+    #[inline]
+    pub fn put(&mut self, bit: bool, prob: u32, scale_bits: u32) {
+        assert!(scale_bits <= 16, "scale_bits must not be larger than 16");
+        assert!(
+            prob > 0 && prob < 1 << scale_bits,
+            "prob must be between 1 and (1 << scale_bits) - 1"
+        );
+
+        let symbol = if bit {
+            ByteRansEncSymbol::new(prob, (1 << scale_bits) - prob, scale_bits)
+        } else {
+            ByteRansEncSymbol::new(0, prob, scale_bits)
+        };
+        self.symbols.push(symbol);
+    }
+
+    /// Adds `bit` to the data to be encoded using the probability from
+    /// `model`, and updates the model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::{BinRansEncoder, BitModel};
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// let mut model = BitModel::new();
+    /// encoder.put_adaptive(true, &mut model);
+    /// ```
+    #[inline]
+    pub fn put_adaptive(&mut self, bit: bool, model: &mut BitModel) {
+        self.put(bit, model.probability(), BIT_MODEL_BITS);
+        model.update(bit);
+    }
+
+    /// Adds `bit` to the data to be encoded with both bit values being
+    /// equally probable (a "bypass" bit), e.g. for the suffix bits of the
+    /// [`binarization`](crate::binarization) schemes.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BinRansEncoder;
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// encoder.put_bypass(true);
+    /// ```
+    #[inline]
+    pub fn put_bypass(&mut self, bit: bool) {
+        self.put(bit, 1, 1);
+    }
+
+    /// Encodes all the bits added so far and flushes the encoder. After
+    /// this, [`Self::data()`] returns the complete encoded data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BinRansEncoder;
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// encoder.put(true, 1 << 11, 12);
+    /// encoder.flush();
+    /// assert!(!encoder.data().is_empty());
+    /// ```
+    pub fn flush(&mut self) {
+        for symbol in self.symbols.iter().rev() {
+            self.encoder.put(symbol);
+        }
+        self.encoder.flush();
+        self.symbols.clear();
+    }
+
+    /// Returns the encoded data. This is empty until [`Self::flush()`] is
+    /// called.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BinRansEncoder;
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        self.encoder.data()
+    }
+}
+
+impl Default for BinRansEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Byte-aligned rANS decoder of binary symbols produced by
+/// [`BinRansEncoder`].
+#[derive(Debug)]
+pub struct BinRansDecoder<'a> {
+    decoder: ByteRansDecoder<'a>,
+}
+
+impl<'a> BinRansDecoder<'a> {
+    /// Creates a new `BinRansDecoder` instance with given `data`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BinRansDecoder;
+    ///
+    /// let decoder = BinRansDecoder::new([0, 0, 128, 0]);
+    /// ```
+    #[must_use]
+    pub fn new<T: Into<DecoderInput<'a>>>(data: T) -> Self {
+        Self {
+            decoder: ByteRansDecoder::new(data),
+        }
+    }
+
+    /// Creates a new `BinRansDecoder` instance like [`Self::new()`],
+    /// checking that the data is long enough to contain the initial state.
+    ///
+    /// # Errors
+    /// Returns [`RansError::EmptyInput`] if `data` is empty, or
+    /// [`RansError::UnexpectedEof`] if it is too short to contain the initial
+    /// state.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BinRansDecoder;
+    /// use rans::RansError;
+    ///
+    /// let result = BinRansDecoder::try_new([0, 0]);
+    /// assert_eq!(result.unwrap_err(), RansError::UnexpectedEof);
+    /// ```
+    pub fn try_new<T: Into<DecoderInput<'a>>>(data: T) -> Result<Self, RansError> {
+        Ok(Self {
+            decoder: ByteRansDecoder::try_new(data)?,
+        })
+    }
+
+    /// Decodes a single bit, given the probability `prob` of the bit being
+    /// 0, scaled to `1 << scale_bits`. The probability has to be the same as
+    /// the one used when encoding.
+    ///
+    /// # Panics
+    /// Panics if `scale_bits` is larger than 16, or if `prob` is 0 or not
+    /// smaller than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::{BinRansDecoder, BinRansEncoder};
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// encoder.put(true, 3000, 12);
+    /// encoder.put(false, 100, 12);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = BinRansDecoder::new(encoder.data());
+    /// assert!(decoder.decode(3000, 12));
+    /// assert!(!decoder.decode(100, 12));
+    /// ```
+    #[inline]
+    pub fn decode(&mut self, prob: u32, scale_bits: u32) -> bool {
+        let (bit, symbol) = self.lookup(prob, scale_bits);
+        self.decoder.advance(&symbol, scale_bits);
+        bit
+    }
+
+    /// Decodes a single bit like [`Self::decode()`], but checks that the data
+    /// doesn't end first, so that malformed or truncated data never makes the
+    /// decoder read past the end of it.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to decode the bit, in which case the decoder shouldn't be used
+    /// anymore.
+    ///
+    /// # Panics
+    /// Panics if `scale_bits` is larger than 16, or if `prob` is 0 or not
+    /// smaller than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::{BinRansDecoder, BinRansEncoder};
+    /// use rans::RansError;
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// encoder.put(true, 3000, 12);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = BinRansDecoder::new(encoder.data());
+    /// assert_eq!(decoder.try_decode(3000, 12), Ok(true));
+    /// assert_eq!(decoder.try_decode(1, 16), Err(RansError::InputExhausted));
+    /// ```
+    #[inline]
+    pub fn try_decode(&mut self, prob: u32, scale_bits: u32) -> Result<bool, RansError> {
+        let (bit, symbol) = self.lookup(prob, scale_bits);
+        self.decoder.try_advance(&symbol, scale_bits)?;
+        Ok(bit)
+    }
+
+    /// Decodes a single bit added with [`BinRansEncoder::put_bypass()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::{BinRansDecoder, BinRansEncoder};
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// encoder.put_bypass(true);
+    /// encoder.put_bypass(false);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = BinRansDecoder::new(encoder.data());
+    /// assert!(decoder.decode_bypass());
+    /// assert!(!decoder.decode_bypass());
+    /// ```
+    #[inline]
+    pub fn decode_bypass(&mut self) -> bool {
+        self.decode(1, 1)
+    }
+
+    /// Decodes a single bit added with [`BinRansEncoder::put_bypass()`],
+    /// checking that the data doesn't end first. See [`Self::try_decode()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to decode the bit.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::BinRansDecoder;
+    /// use rans::RansError;
+    ///
+    /// let mut decoder = BinRansDecoder::new([0, 0, 128, 0]);
+    /// assert_eq!(decoder.try_decode_bypass(), Err(RansError::InputExhausted));
+    /// ```
+    #[inline]
+    pub fn try_decode_bypass(&mut self) -> Result<bool, RansError> {
+        self.try_decode(1, 1)
+    }
+
+    /// Decodes a single bit using the probability from `model`, and updates
+    /// the model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::{BinRansDecoder, BinRansEncoder, BitModel};
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// let mut model = BitModel::new();
+    /// encoder.put_adaptive(true, &mut model);
+    /// encoder.put_adaptive(false, &mut model);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = BinRansDecoder::new(encoder.data());
+    /// let mut model = BitModel::new();
+    /// assert!(decoder.decode_adaptive(&mut model));
+    /// assert!(!decoder.decode_adaptive(&mut model));
+    /// ```
+    #[inline]
+    pub fn decode_adaptive(&mut self, model: &mut BitModel) -> bool {
+        let bit = self.decode(model.probability(), BIT_MODEL_BITS);
+        model.update(bit);
+        bit
+    }
+
+    /// Decodes a single bit using the probability from `model` like
+    /// [`Self::decode_adaptive()`], checking that the data doesn't end first.
+    /// The model is only updated if the bit is decoded successfully.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to decode the bit.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::binary::{BinRansDecoder, BinRansEncoder, BitModel};
+    /// use rans::RansError;
+    ///
+    /// let mut encoder = BinRansEncoder::new();
+    /// encoder.put_adaptive(true, &mut BitModel::new());
+    /// encoder.flush();
+    ///
+    /// let mut decoder = BinRansDecoder::new(encoder.data());
+    /// let mut model = BitModel::new();
+    /// assert_eq!(decoder.try_decode_adaptive(&mut model), Ok(true));
+    /// ```
+    #[inline]
+    pub fn try_decode_adaptive(&mut self, model: &mut BitModel) -> Result<bool, RansError> {
+        let bit = self.try_decode(model.probability(), BIT_MODEL_BITS)?;
+        model.update(bit);
+        Ok(bit)
+    }
+
+    /// Returns the bit at the current position, given the probability
+    /// `prob` of it being 0, along with the symbol to advance by.
+    #[inline]
+    fn lookup(&mut self, prob: u32, scale_bits: u32) -> (bool, ByteRansDecSymbol) {
+        assert!(scale_bits <= 16, "scale_bits must not be larger than 16");
+        assert!(
+            prob > 0 && prob < 1 << scale_bits,
+            "prob must be between 1 and (1 << scale_bits) - 1"
+        );
+
+        let bit = self.decoder.get(scale_bits) >= prob;
+        let symbol = if bit {
+            ByteRansDecSymbol::new(prob, (1 << scale_bits) - prob)
+        } else {
+            ByteRansDecSymbol::new(0, prob)
+        };
+        (bit, symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::binary::{BinRansDecoder, BinRansEncoder, BitModel, BIT_MODEL_BITS};
+
+    #[test]
+    fn test_model_stays_in_range() {
+        for rate in 1..BIT_MODEL_BITS {
+            let mut model = BitModel::with_rate(rate);
+            for _ in 0..1000 {
+                model.update(false);
+            }
+            assert!(model.probability() < 1 << BIT_MODEL_BITS);
+            for _ in 0..1000 {
+                model.update(true);
+            }
+            assert!(model.probability() > 0);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_static() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let bits: Vec<(bool, u32, u32)> = (0..10000)
+            .map(|_| {
+                let scale_bits = rng.gen_range(12..=15);
+                let prob = rng.gen_range(1..1 << scale_bits);
+                (rng.gen_range(0..1 << scale_bits) >= prob, prob, scale_bits)
+            })
+            .collect();
+
+        let mut encoder = BinRansEncoder::new();
+        for &(bit, prob, scale_bits) in &bits {
+            encoder.put(bit, prob, scale_bits);
+        }
+        encoder.flush();
+
+        let mut decoder = BinRansDecoder::new(encoder.data());
+        for &(bit, prob, scale_bits) in &bits {
+            assert_eq!(decoder.decode(prob, scale_bits), bit);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_adaptive() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        // Two contexts with very different statistics
+        let bits: Vec<bool> = (0..20000)
+            .map(|i| rng.gen_bool(if i % 2 == 0 { 0.05 } else { 0.7 }))
+            .collect();
+
+        let mut encoder = BinRansEncoder::new();
+        let mut models = [BitModel::new(), BitModel::with_rate(4)];
+        for (i, &bit) in bits.iter().enumerate() {
+            encoder.put_adaptive(bit, &mut models[i % 2]);
+        }
+        encoder.flush();
+        // Entropy of the data is about 0.58 bits per bit
+        assert!(encoder.data().len() < 20000 * 65 / 800);
+
+        let mut decoder = BinRansDecoder::new(encoder.data());
+        let mut models = [BitModel::new(), BitModel::with_rate(4)];
+        for (i, &bit) in bits.iter().enumerate() {
+            assert_eq!(decoder.decode_adaptive(&mut models[i % 2]), bit);
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use crate::binary::{BinRansDecoder, BinRansEncoder, BitModel};
+use crate::RansError;
+
+/// Number of significance contexts in every plane: the number of significant
+/// neighbours of a coefficient, 0, 1 or 2.
+const SIGNIFICANCE_CONTEXTS: usize = 3;
+/// Maximum number of planes of any [`Coefficient`] type.
+const MAX_PLANES: usize = 32;
+/// Number of bypass bits coding the number of planes of a block, which is
+/// between 0 and [`MAX_PLANES`] inclusive.
+const PLANE_COUNT_BITS: u32 = 6;
+
+/// Signed integer type of the coefficients coded by [`put_block()`] and
+/// [`decode_block()`]. Implemented for `i16` and `i32`.
+pub trait Coefficient: Copy {
+    /// Number of bit-planes needed for the magnitude of any value of the
+    /// type, including the magnitude of its minimum value.
+    const PLANES: u32;
+
+    /// Returns the absolute value of the coefficient.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::bitplane::Coefficient;
+    ///
+    /// assert_eq!(i16::MIN.magnitude(), 32768);
+    /// assert_eq!((-3_i32).magnitude(), 3);
+    /// ```
+    #[must_use]
+    fn magnitude(self) -> u32;
+
+    /// Returns whether the coefficient is negative.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::bitplane::Coefficient;
+    ///
+    /// assert!(Coefficient::is_negative(-3_i16));
+    /// assert!(!Coefficient::is_negative(0_i32));
+    /// ```
+    #[must_use]
+    fn is_negative(self) -> bool;
+
+    /// Creates a coefficient from its sign and magnitude, or returns `None`
+    /// if it is out of range of the type.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::bitplane::Coefficient;
+    ///
+    /// assert_eq!(i16::from_sign_magnitude(true, 32768), Some(i16::MIN));
+    /// assert_eq!(i16::from_sign_magnitude(false, 32768), None);
+    /// ```
+    #[must_use]
+    fn from_sign_magnitude(negative: bool, magnitude: u32) -> Option<Self>;
+}
+
+impl Coefficient for i16 {
+    const PLANES: u32 = 16;
+
+    #[inline]
+    fn magnitude(self) -> u32 {
+        u32::from(self.unsigned_abs())
+    }
+
+    #[inline]
+    fn is_negative(self) -> bool {
+        self < 0
+    }
+
+    #[inline]
+    fn from_sign_magnitude(negative: bool, magnitude: u32) -> Option<Self> {
+        let value = i32::try_from(magnitude).ok()?;
+        i16::try_from(if negative { -value } else { value }).ok()
+    }
+}
+
+impl Coefficient for i32 {
+    const PLANES: u32 = 32;
+
+    #[inline]
+    fn magnitude(self) -> u32 {
+        self.unsigned_abs()
+    }
+
+    #[inline]
+    fn is_negative(self) -> bool {
+        self < 0
+    }
+
+    #[inline]
+    fn from_sign_magnitude(negative: bool, magnitude: u32) -> Option<Self> {
+        let value = i64::from(magnitude);
+        i32::try_from(if negative { -value } else { value }).ok()
+    }
+}
+
+/// Adaptive bit models of a single bit-plane.
+#[derive(Debug, Clone, Copy, Default)]
+struct PlaneModels {
+    significance: [BitModel; SIGNIFICANCE_CONTEXTS],
+    refinement: BitModel,
+}
+
+/// Adaptive contexts of the bit-plane coder.
+///
+/// Every plane has its own models for the significance bits (the bits up to
+/// and including the most significant one of a coefficient), chosen by how
+/// many of the neighbouring coefficients are already significant, and for
+/// the refinement bits (the bits below the most significant one). The signs
+/// share a single model.
+///
+/// The contexts keep adapting across blocks, so the decoder has to start
+/// with contexts in the same state as the encoder, and code the blocks in the
+/// same order.
+#[derive(Debug, Clone)]
+pub struct BitplaneContexts {
+    planes: [PlaneModels; MAX_PLANES],
+    sign: BitModel,
+}
+
+impl BitplaneContexts {
+    /// Creates a new set of contexts, with all the bit values equally
+    /// probable.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::bitplane::BitplaneContexts;
+    ///
+    /// let contexts = BitplaneContexts::new();
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            planes: [PlaneModels::default(); MAX_PLANES],
+            sign: BitModel::new(),
+        }
+    }
+}
+
+impl Default for BitplaneContexts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encodes a block of coefficients, e.g. the DCT coefficients of an 8x8 block
+/// or a subband of a wavelet transform, one bit-plane at a time.
+///
+/// The number of planes needed for the largest magnitude in the block is
+/// coded first, as bypass bits. Then, starting with the most significant
+/// plane, every coefficient gets its bit of the plane coded: as a
+/// significance bit, followed by the sign if the bit is the most significant
+/// one of the coefficient, or as a refinement bit if the coefficient became
+/// significant in one of the previous planes. The length of the block is not
+/// coded.
+///
+/// The significance bits are coded in contexts depending on the neighbouring
+/// coefficients in the block, so the coefficients should be ordered so that
+/// the neighbours have similar magnitudes, e.g. in zigzag order for DCT
+/// blocks.
+///
+/// # Examples
+/// ```
+/// use rans::binary::{BinRansDecoder, BinRansEncoder};
+/// use rans::bitplane::{decode_block, put_block, BitplaneContexts};
+///
+/// let block: [i16; 8] = [-120, 33, 0, -7, 1, 0, 0, 0];
+///
+/// let mut encoder = BinRansEncoder::new();
+/// put_block(&mut encoder, &block, &mut BitplaneContexts::new());
+/// encoder.flush();
+///
+/// let mut decoder = BinRansDecoder::new(encoder.data());
+/// let mut decoded = [0_i16; 8];
+/// decode_block(&mut decoder, &mut decoded, &mut BitplaneContexts::new()).unwrap();
+/// assert_eq!(decoded, block);
+/// ```
+pub fn put_block<T: Coefficient>(
+    encoder: &mut BinRansEncoder,
+    block: &[T],
+    contexts: &mut BitplaneContexts,
+) {
+    let max_magnitude = block.iter().map(|&x| x.magnitude()).max().unwrap_or(0);
+    let planes = u32::BITS - max_magnitude.leading_zeros();
+    for bit in (0..PLANE_COUNT_BITS).rev() {
+        encoder.put_bypass(planes >> bit & 1 != 0);
+    }
+
+    let mut significant = vec![false; block.len()];
+    for plane in (0..planes).rev() {
+        let models = &mut contexts.planes[plane as usize];
+        for (index, &coefficient) in block.iter().enumerate() {
+            let bit = coefficient.magnitude() >> plane & 1 != 0;
+            if significant[index] {
+                encoder.put_adaptive(bit, &mut models.refinement);
+                continue;
+            }
+
+            let context = significant_neighbours(&significant, index);
+            encoder.put_adaptive(bit, &mut models.significance[context]);
+            if bit {
+                encoder.put_adaptive(coefficient.is_negative(), &mut contexts.sign);
+                significant[index] = true;
+            }
+        }
+    }
+}
+
+/// Decodes a block of coefficients encoded with [`put_block()`]. The block
+/// has to have the same length, and `contexts` have to be in the same state
+/// as the ones used when encoding.
+///
+/// # Errors
+/// Returns [`RansError::InputExhausted`] if the data ends before the whole
+/// block is decoded, or [`RansError::InvalidSymbol`] if the number of planes
+/// or any of the decoded coefficients is out of range of `T`, which means the
+/// data is corrupted. The contents of `block` are unspecified in both cases.
+///
+/// # Examples
+/// ```
+/// use rans::binary::{BinRansDecoder, BinRansEncoder};
+/// use rans::bitplane::{decode_block, put_block, BitplaneContexts};
+///
+/// let blocks: [[i32; 4]; 2] = [[70000, -5, 3, 0], [-70000, 6, 0, 1]];
+///
+/// let mut encoder = BinRansEncoder::new();
+/// let mut contexts = BitplaneContexts::new();
+/// for block in &blocks {
+///     put_block(&mut encoder, block, &mut contexts);
+/// }
+/// encoder.flush();
+///
+/// let mut decoder = BinRansDecoder::new(encoder.data());
+/// let mut contexts = BitplaneContexts::new();
+/// for block in &blocks {
+///     let mut decoded = [0; 4];
+///     decode_block(&mut decoder, &mut decoded, &mut contexts).unwrap();
+///     assert_eq!(&decoded, block);
+/// }
+/// ```
+pub fn decode_block<T: Coefficient>(
+    decoder: &mut BinRansDecoder<'_>,
+    block: &mut [T],
+    contexts: &mut BitplaneContexts,
+) -> Result<(), RansError> {
+    let mut planes = 0;
+    for _ in 0..PLANE_COUNT_BITS {
+        planes = planes << 1 | u32::from(decoder.try_decode_bypass()?);
+    }
+    if planes > T::PLANES {
+        return Err(RansError::InvalidSymbol);
+    }
+
+    let mut magnitudes = vec![0_u32; block.len()];
+    let mut negative = vec![false; block.len()];
+    let mut significant = vec![false; block.len()];
+    for plane in (0..planes).rev() {
+        let models = &mut contexts.planes[plane as usize];
+        for index in 0..block.len() {
+            if significant[index] {
+                let bit = decoder.try_decode_adaptive(&mut models.refinement)?;
+                magnitudes[index] |= u32::from(bit) << plane;
+                continue;
+            }
+
+            let context = significant_neighbours(&significant, index);
+            if decoder.try_decode_adaptive(&mut models.significance[context])? {
+                magnitudes[index] |= 1 << plane;
+                negative[index] = decoder.try_decode_adaptive(&mut contexts.sign)?;
+                significant[index] = true;
+            }
+        }
+    }
+
+    for ((coefficient, &negative), &magnitude) in block.iter_mut().zip(&negative).zip(&magnitudes) {
+        *coefficient =
+            T::from_sign_magnitude(negative, magnitude).ok_or(RansError::InvalidSymbol)?;
+    }
+    Ok(())
+}
+
+/// Returns the number of significant coefficients next to the one at
+/// `index`. The previous coefficient has already been coded in the current
+/// plane, and the next one only in the planes above it, but the decoder
+/// knows both at this point as well.
+fn significant_neighbours(significant: &[bool], index: usize) -> usize {
+    let previous = index > 0 && significant[index - 1];
+    let next = significant.get(index + 1).copied().unwrap_or(false);
+    usize::from(previous) + usize::from(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::binary::{BinRansDecoder, BinRansEncoder};
+    use crate::bitplane::{decode_block, put_block, BitplaneContexts, Coefficient};
+    use crate::RansError;
+
+    fn round_trip<T: Coefficient + PartialEq + std::fmt::Debug + Default>(
+        blocks: &[Vec<T>],
+    ) -> usize {
+        let mut encoder = BinRansEncoder::new();
+        let mut contexts = BitplaneContexts::new();
+        for block in blocks {
+            put_block(&mut encoder, block, &mut contexts);
+        }
+        encoder.flush();
+
+        let mut decoder = BinRansDecoder::new(encoder.data());
+        let mut contexts = BitplaneContexts::new();
+        for block in blocks {
+            let mut decoded = vec![T::default(); block.len()];
+            assert_eq!(
+                decode_block(&mut decoder, &mut decoded, &mut contexts),
+                Ok(())
+            );
+            assert_eq!(&decoded, block);
+        }
+
+        encoder.data().len()
+    }
+
+    #[test]
+    fn test_round_trip_extremes() {
+        round_trip::<i16>(&[vec![], vec![0; 64], vec![i16::MIN, i16::MAX, -1, 1, 0]]);
+        round_trip::<i32>(&[vec![], vec![0; 64], vec![i32::MIN, i32::MAX, -1, 1, 0]]);
+    }
+
+    #[test]
+    fn test_round_trip_decaying() {
+        // Coefficients getting smaller towards the end of the block, like
+        // DCT coefficients in zigzag order
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let blocks: Vec<Vec<i16>> = (0..200)
+            .map(|_| {
+                (0..64)
+                    .map(|i| {
+                        let scale = 1024 >> (i / 4);
+                        rng.gen_range(-scale..=scale)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let len = round_trip(&blocks);
+        // The entropy is a little over 4 bits per coefficient
+        assert!(len < 200 * 64 * 6 / 8);
+
+        let wide: Vec<Vec<i32>> = blocks
+            .iter()
+            .map(|block| block.iter().map(|&x| i32::from(x) << 8).collect())
+            .collect();
+        round_trip(&wide);
+    }
+
+    #[test]
+    fn test_decode_as_wider_type() {
+        let block: Vec<i16> = vec![i16::MIN, 1000, -3, 0, 7];
+        let mut encoder = BinRansEncoder::new();
+        put_block(&mut encoder, &block, &mut BitplaneContexts::new());
+        encoder.flush();
+
+        let mut decoder = BinRansDecoder::new(encoder.data());
+        let mut decoded = [0_i32; 5];
+        decode_block(&mut decoder, &mut decoded, &mut BitplaneContexts::new()).unwrap();
+        assert_eq!(decoded, [-32768, 1000, -3, 0, 7]);
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        // Too many planes for i16, and a magnitude fitting in 16 planes, but
+        // not in i16
+        for value in [1_i32 << 20, 40000] {
+            let mut encoder = BinRansEncoder::new();
+            put_block(&mut encoder, &[value], &mut BitplaneContexts::new());
+            encoder.flush();
+
+            let mut decoder = BinRansDecoder::new(encoder.data());
+            assert_eq!(
+                decode_block(&mut decoder, &mut [0_i16], &mut BitplaneContexts::new()),
+                Err(RansError::InvalidSymbol)
+            );
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(2);
+        let block: Vec<i32> = (0..256).map(|_| rng.gen_range(-5000..5000)).collect();
+        let mut encoder = BinRansEncoder::new();
+        put_block(&mut encoder, &block, &mut BitplaneContexts::new());
+        encoder.flush();
+        let data = encoder.data();
+
+        for cut in 1..=data.len() - 4 {
+            let mut decoder = BinRansDecoder::new(&data[..data.len() - cut]);
+            let mut decoded = vec![0; block.len()];
+            assert_eq!(
+                decode_block(&mut decoder, &mut decoded, &mut BitplaneContexts::new()),
+                Err(RansError::InputExhausted)
+            );
+        }
+    }
+}
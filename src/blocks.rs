@@ -0,0 +1,144 @@
+use crate::{simple, varint, RansError};
+
+/// Index of a container made of independently compressed blocks.
+///
+/// The container starts with the length of the uncompressed data, the
+/// (uncompressed) size of a block and the compressed lengths of all the
+/// blocks, all stored as LEB128 integers, followed by the compressed blocks.
+/// Every block except the last one contains exactly `block_size` bytes.
+#[derive(Debug, Clone)]
+pub(crate) struct BlockIndex {
+    len: usize,
+    block_size: usize,
+    /// Offsets of the blocks in the container, plus the end of the last one
+    offsets: Vec<usize>,
+}
+
+impl BlockIndex {
+    /// Writes a container of given compressed `blocks` into `output`.
+    pub(crate) fn write(output: &mut Vec<u8>, len: usize, block_size: usize, blocks: &[Vec<u8>]) {
+        varint::write_u64(output, len as u64);
+        varint::write_u64(output, block_size as u64);
+        for block in blocks {
+            varint::write_u64(output, block.len() as u64);
+        }
+        for block in blocks {
+            output.extend_from_slice(block);
+        }
+    }
+
+    /// Reads the index of the container stored in `data`, checking that all
+    /// the blocks are present.
+    pub(crate) fn read(data: &[u8]) -> Result<Self, RansError> {
+        let mut pos = 0;
+        let len = usize::try_from(varint::read_u64(data, &mut pos)?)
+            .map_err(|_| RansError::InvalidHeader)?;
+        let block_size = usize::try_from(varint::read_u64(data, &mut pos)?)
+            .map_err(|_| RansError::InvalidHeader)?;
+        if block_size == 0 {
+            return Err(RansError::InvalidHeader);
+        }
+
+        let num_blocks = (len + block_size - 1) / block_size;
+        // Every block length takes at least one byte
+        if num_blocks > data.len() - pos {
+            return Err(RansError::UnexpectedEof);
+        }
+        let mut block_lens = Vec::with_capacity(num_blocks);
+        for _ in 0..num_blocks {
+            block_lens.push(varint::read_u64(data, &mut pos)?);
+        }
+
+        let mut offsets = Vec::with_capacity(num_blocks + 1);
+        offsets.push(pos);
+        for block_len in block_lens {
+            pos = usize::try_from(block_len)
+                .ok()
+                .and_then(|block_len| pos.checked_add(block_len))
+                .ok_or(RansError::InvalidHeader)?;
+            offsets.push(pos);
+        }
+        if pos > data.len() {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        Ok(Self {
+            len,
+            block_size,
+            offsets,
+        })
+    }
+
+    /// Returns the length of the uncompressed data.
+    #[must_use]
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of bytes in a single (not last) block.
+    #[must_use]
+    pub(crate) fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Returns the number of blocks.
+    #[must_use]
+    pub(crate) fn num_blocks(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Returns the number of uncompressed bytes in given block.
+    #[must_use]
+    pub(crate) fn block_len(&self, block: usize) -> usize {
+        (self.len - block * self.block_size).min(self.block_size)
+    }
+
+    /// Returns the compressed data of given block.
+    #[must_use]
+    pub(crate) fn block_data<'a>(&self, data: &'a [u8], block: usize) -> &'a [u8] {
+        &data[self.offsets[block]..self.offsets[block + 1]]
+    }
+
+    /// Decompresses given block, checking that its length matches the index.
+    pub(crate) fn decompress_block(&self, data: &[u8], block: usize) -> Result<Vec<u8>, RansError> {
+        let decompressed = simple::decompress(self.block_data(data, block))?;
+        if decompressed.len() != self.block_len(block) {
+            return Err(RansError::InvalidHeader);
+        }
+        Ok(decompressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::BlockIndex;
+    use crate::RansError;
+
+    #[test]
+    fn test_round_trip() {
+        let blocks = vec![vec![1, 2, 3], vec![], vec![4]];
+        let mut data = Vec::new();
+        BlockIndex::write(&mut data, 25, 10, &blocks);
+
+        let index = BlockIndex::read(&data).unwrap();
+        assert_eq!(index.num_blocks(), 3);
+        assert_eq!(index.block_len(0), 10);
+        assert_eq!(index.block_len(2), 5);
+        for (i, block) in blocks.iter().enumerate() {
+            assert_eq!(index.block_data(&data, i), block);
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut data = Vec::new();
+        BlockIndex::write(&mut data, 25, 10, &[vec![1], vec![2], vec![3]]);
+
+        for len in 0..data.len() {
+            assert_eq!(
+                BlockIndex::read(&data[..len]).unwrap_err(),
+                RansError::UnexpectedEof
+            );
+        }
+    }
+}
@@ -1,23 +1,40 @@
-use std::mem::MaybeUninit;
+use std::hash::{Hash, Hasher};
+use std::mem::{size_of, MaybeUninit};
 
-use crate::decoder::check_dec_pointer;
-use crate::mut_cow::MutCow;
-use crate::{RansDecSymbol, RansDecoder, RansDecoderMulti};
+use crate::backend::RANS_BYTE_L;
+use crate::decoder::{check_dec_pointer, DecSnapshot};
+use crate::decoder_input::DecoderInput;
+use crate::model::{DecModel, Model};
+use crate::{RansDecSymbol, RansDecoder, RansDecoderMulti, RansError};
 
 /// Multi-stream interleaved rANS decoder - byte-aligned version.
+///
+/// The decoder is both [`Send`] and [`Sync`], so it can be moved to (or
+/// shared with) other threads.
 #[derive(Debug)]
 pub struct ByteRansDecoderMulti<'a, const N: usize> {
-    states: [ryg_rans_sys::rans_byte::RansState; N],
-    data: MutCow<'a, [u8]>,
+    states: [crate::backend::rans_byte::RansState; N],
+    data: DecoderInput<'a>,
     ptr: *mut u8,
+    model: Option<DecModel<ByteRansDecSymbol>>,
 }
 
 /// Single-stream rANS decoder - byte-aligned version.
 pub type ByteRansDecoder<'a> = ByteRansDecoderMulti<'a, 1>;
 
+// SAFETY: `ptr` always points into `data`, which is either owned by the decoder
+// and does not move along with it, or borrowed/shared immutably. The pointer is
+// only ever advanced by methods taking `&mut self`.
+unsafe impl<'a, const N: usize> Send for ByteRansDecoderMulti<'a, N> {}
+unsafe impl<'a, const N: usize> Sync for ByteRansDecoderMulti<'a, N> {}
+
 impl<'a, const N: usize> ByteRansDecoderMulti<'a, N> {
     /// Creates a new `ByteMultiRansDecoder` instance with given `data`.
     ///
+    /// The data is only ever read, so it can be borrowed (`&[u8]`), owned
+    /// (`Vec<u8>`) or shared between threads (`Arc<[u8]>`) - see
+    /// [`DecoderInput`] for all the supported types.
+    ///
     /// Note that most of the API is inside the [`RansDecoderMulti`] trait, so
     /// you probably want to `use rans::RansDecoderMulti`.
     ///
@@ -28,23 +45,386 @@ impl<'a, const N: usize> ByteRansDecoderMulti<'a, N> {
     ///
     /// let mut decoder = ByteRansDecoderMulti::<1>::new(vec![0]);
     /// assert_eq!(decoder.get_at(0, 4), 0);
+    ///
+    /// let data = [0, 0, 128, 0];
+    /// let mut decoder = ByteRansDecoderMulti::<1>::new(&data[..]);
+    /// assert_eq!(decoder.get_at(0, 4), 0);
     /// ```
     #[must_use]
-    pub fn new<T: Into<MutCow<'a, [u8]>>>(data: T) -> Self {
-        let mut data = data.into();
+    pub fn new<T: Into<DecoderInput<'a>>>(data: T) -> Self {
+        let data = data.into();
         assert!(!data.is_empty());
 
+        // The input is never written to, the pointer only needs to be mutable
+        // to match the signatures of the underlying rANS implementation
+        let mut ptr = data.as_ptr() as *mut u8;
+        let mut states = [0; N];
+        for state in &mut states {
+            unsafe {
+                crate::backend::rans_byte::rans_dec_init(state, &mut ptr);
+            }
+        }
+
+        Self {
+            states,
+            data,
+            ptr,
+            model: None,
+        }
+    }
+
+    /// Creates a new `ByteMultiRansDecoder` instance with given `data`,
+    /// checking that the data is long enough to contain the initial states
+    /// of all channels.
+    ///
+    /// # Errors
+    /// Returns [`RansError::EmptyInput`] if `data` is empty, or
+    /// [`RansError::UnexpectedEof`] if it is too short to contain the initial
+    /// states.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::{RansDecoderMulti, RansError};
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<1>::try_new([0, 0, 128, 0]).unwrap();
+    /// assert_eq!(decoder.get_at(0, 4), 0);
+    /// assert_eq!(
+    ///     ByteRansDecoderMulti::<1>::try_new(vec![]).unwrap_err(),
+    ///     RansError::EmptyInput
+    /// );
+    /// ```
+    pub fn try_new<T: Into<DecoderInput<'a>>>(data: T) -> Result<Self, RansError> {
+        let data = data.into();
+        if data.is_empty() {
+            return Err(RansError::EmptyInput);
+        }
+        if data.len() < N * size_of::<crate::backend::rans_byte::RansState>() {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        Ok(Self::new(data))
+    }
+
+    /// Registers the model used by [`Self::decode_at()`], so that the
+    /// symbols don't need to be looked up and passed back to the decoder by
+    /// the caller. Replaces the previously registered model, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::model::Model;
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]);
+    /// decoder.set_model(&Model::from_freqs(vec![2, 2], 2));
+    /// assert_eq!(decoder.decode_at(0), 1);
+    /// assert_eq!(decoder.decode_at(1), 0);
+    /// ```
+    pub fn set_model(&mut self, model: &Model) {
+        self.model = Some(DecModel::new(model));
+    }
+
+    /// Decodes a single symbol at given channel using the model registered
+    /// with [`Self::set_model()`] and returns its index.
+    ///
+    /// # Panics
+    /// Panics if no model has been registered.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::model::Model;
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]);
+    /// decoder.set_model(&Model::from_freqs(vec![2, 2], 2));
+    /// assert_eq!(decoder.decode_at(0), 1);
+    /// assert_eq!(decoder.decode_at(1), 0);
+    /// ```
+    pub fn decode_at(&mut self, channel: usize) -> usize {
+        let model = self
+            .model
+            .take()
+            .expect("no model registered; use set_model() first");
+        let index = self.decode_symbol_at(channel, &model);
+        self.model = Some(model);
+        index
+    }
+
+    /// Returns the number of bytes of the data that haven't been read by
+    /// the decoder yet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    ///
+    /// let decoder = ByteRansDecoder::new([0, 0, 128, 0, 42]);
+    /// assert_eq!(decoder.bytes_remaining(), 1);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn bytes_remaining(&self) -> usize {
+        self.data.len() - self.position()
+    }
+
+    /// Returns the position of the decoder in the data, i.e. the number of
+    /// bytes read by the decoder so far.
+    ///
+    /// Once all the symbols are decoded, this is the length of the rANS
+    /// payload, so it can be used to find where the data following it
+    /// begins.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::{RansDecSymbol, RansDecoder, RansEncSymbol, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put(&ByteRansEncSymbol::new(1, 1, 12));
+    /// encoder.flush();
+    /// let mut data = encoder.data().to_vec();
+    /// let payload_len = data.len();
+    /// data.extend_from_slice(b"next section");
+    ///
+    /// let mut decoder = ByteRansDecoder::new(data.as_slice());
+    /// assert_eq!(decoder.get(12), 1);
+    /// decoder.advance(&ByteRansDecSymbol::new(1, 1), 12);
+    /// assert_eq!(decoder.position(), payload_len);
+    /// assert_eq!(&data[decoder.position()..], b"next section");
+    /// ```
+    #[doc(alias = "bytes_consumed")]
+    #[must_use]
+    #[inline]
+    pub fn position(&self) -> usize {
+        unsafe { self.ptr.offset_from(self.data.as_ptr()) as usize }
+    }
+
+    /// Returns whether all the symbols have been decoded, i.e. the whole data
+    /// has been read and all the channels are back in their initial states.
+    ///
+    /// This is exact for well-formed data, except that symbols with
+    /// probability of 1 don't change the state of the coder, so they can't
+    /// be detected.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::{RansDecSymbol, RansDecoderMulti, RansEncSymbol, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put_at(0, &ByteRansEncSymbol::new(1, 1, 4));
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoder::new(encoder.data());
+    /// assert!(!decoder.is_exhausted());
+    /// assert_eq!(decoder.get_at(0, 4), 1);
+    /// decoder.advance_at(0, &ByteRansDecSymbol::new(1, 1), 4);
+    /// assert!(decoder.is_exhausted());
+    /// ```
+    #[must_use]
+    pub fn is_exhausted(&self) -> bool {
+        self.bytes_remaining() == 0 && self.states.iter().all(|&state| state == RANS_BYTE_L)
+    }
+
+    /// Saves this decoder's channel states and read position, so that they
+    /// can be restored later with [`Self::restore_state()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<1>::new([11, 0, 0, 8]);
+    /// let snapshot = decoder.save_state();
+    /// assert_eq!(decoder.get_bits_at(0, 4), 0b1011);
+    /// decoder.restore_state(&snapshot);
+    /// assert_eq!(decoder.get_bits_at(0, 4), 0b1011);
+    /// ```
+    #[must_use]
+    pub fn save_state(&self) -> DecSnapshot<u32, N> {
+        DecSnapshot {
+            states: self.states,
+            pos: self.position(),
+        }
+    }
+
+    /// Restores this decoder's channel states and read position from given
+    /// snapshot.
+    ///
+    /// The snapshot must have been created by a decoder reading the same
+    /// data; otherwise, the decoder returns garbage.
+    ///
+    /// # Panics
+    /// Panics if the snapshot's position is past the end of the data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<1>::new([11, 0, 0, 8]);
+    /// let snapshot = decoder.save_state();
+    /// assert_eq!(decoder.get_bits_at(0, 4), 0b1011);
+    /// decoder.restore_state(&snapshot);
+    /// assert_eq!(decoder.get_bits_at(0, 4), 0b1011);
+    /// ```
+    pub fn restore_state(&mut self, snapshot: &DecSnapshot<u32, N>) {
+        assert!(
+            snapshot.pos <= self.data.len(),
+            "snapshot position out of range"
+        );
+
+        self.states = snapshot.states;
         unsafe {
-            let mut ptr = data.as_mut_ptr();
+            self.ptr = self.data.as_ptr().add(snapshot.pos) as *mut u8;
+        }
+    }
+
+    /// Re-initializes the states of all the channels from the data at the
+    /// current position.
+    ///
+    /// Once all the symbols of a block are decoded, this makes it possible to
+    /// decode another, independently flushed block that follows it in the
+    /// data with the same decoder, without copying the data.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the rest of the data is too
+    /// short to contain the initial states of all channels. The decoder is
+    /// left unchanged in such case.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::{RansDecSymbol, RansDecoder, RansEncSymbol, RansEncoder, RansEncoderMulti};
+    /// use rans::RansError;
+    ///
+    /// let mut data = Vec::new();
+    /// for cum_freq in [1, 2] {
+    ///     let mut encoder = ByteRansEncoder::new(1024);
+    ///     encoder.put(&ByteRansEncSymbol::new(cum_freq, 1, 4));
+    ///     encoder.flush();
+    ///     data.extend_from_slice(encoder.data());
+    /// }
+    ///
+    /// let mut decoder = ByteRansDecoder::new(data.as_slice());
+    /// assert_eq!(decoder.get(4), 1);
+    /// decoder.advance(&ByteRansDecSymbol::new(1, 1), 4);
+    /// decoder.reinit_tail().unwrap();
+    /// assert_eq!(decoder.get(4), 2);
+    /// decoder.advance(&ByteRansDecSymbol::new(2, 1), 4);
+    /// assert_eq!(decoder.reinit_tail(), Err(RansError::UnexpectedEof));
+    /// ```
+    pub fn reinit_tail(&mut self) -> Result<(), RansError> {
+        if self.bytes_remaining() < N * size_of::<crate::backend::rans_byte::RansState>() {
+            return Err(RansError::UnexpectedEof);
+        }
 
-            #[allow(clippy::uninit_assumed_init)]
-            let mut states: [ryg_rans_sys::rans_byte::RansState; N] =
-                MaybeUninit::uninit().assume_init();
-            for state in &mut states {
-                ryg_rans_sys::rans_byte::rans_dec_init(state, &mut ptr);
+        for state in &mut self.states {
+            unsafe {
+                crate::backend::rans_byte::rans_dec_init(state, &mut self.ptr);
             }
+        }
+        Ok(())
+    }
+
+    /// Returns the current rANS state of given channel.
+    ///
+    /// Together with [`Self::set_state_at()`], this makes it possible to hand
+    /// streams over between this crate and other rANS implementations.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    ///
+    /// let decoder = ByteRansDecoderMulti::<1>::new([4, 3, 2, 1]);
+    /// assert_eq!(decoder.state_at(0), 0x0102_0304);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn state_at(&self, channel: usize) -> u32 {
+        self.states[channel]
+    }
 
-            Self { states, data, ptr }
+    /// Overwrites the rANS state of given channel.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<1>::new([4, 3, 2, 1]);
+    /// decoder.set_state_at(0, 0x0000_0123);
+    /// assert_eq!(decoder.get_at(0, 8), 0x23);
+    /// ```
+    #[inline]
+    pub fn set_state_at(&mut self, channel: usize, state: u32) {
+        self.states[channel] = state;
+    }
+
+    /// Returns whether the state of given channel is within the range of
+    /// states produced by the encoder, i.e. `[L, L << 8)`. Only then are the
+    /// state updates guaranteed not to overflow.
+    #[inline]
+    pub(crate) fn is_state_valid(&self, channel: usize) -> bool {
+        (RANS_BYTE_L..RANS_BYTE_L << 8).contains(&self.states[channel])
+    }
+
+    /// Returns the number of bytes renormalizing given channel would read, or
+    /// `None` if that would read past the end of the data.
+    ///
+    /// Every byte is bounds-checked, as the length can't be told from the
+    /// state alone: a zero state is renormalized by skipping all the zero
+    /// bytes that follow.
+    #[inline]
+    pub(crate) fn renorm_len(&self, channel: usize) -> Option<usize> {
+        let input = &self.data[self.position()..];
+        let mut state = self.states[channel];
+        let mut len = 0;
+        while state < RANS_BYTE_L {
+            state = (state << 8) | u32::from(*input.get(len)?);
+            len += 1;
+        }
+        Some(len)
+    }
+
+    /// Returns whether renormalizing given channel doesn't read past the end
+    /// of the data.
+    #[inline]
+    fn can_renorm(&self, channel: usize) -> bool {
+        self.renorm_len(channel).is_some()
+    }
+
+    /// Returns the states of all the channels after renormalizing them one
+    /// after another, along with the number of bytes read, or `None` if that
+    /// would read past the end of the data. The decoder itself is left
+    /// unchanged.
+    #[inline]
+    fn renorm_all_states(&self) -> Option<([u32; N], usize)> {
+        // A single pass over the data, with every byte bounds-checked, as the
+        // number of bytes a zero state reads depends on the data
+        let input = &self.data[self.position()..];
+        let mut states = self.states;
+        let mut len = 0;
+        for state in &mut states {
+            while *state < RANS_BYTE_L {
+                *state = (*state << 8) | u32::from(*input.get(len)?);
+                len += 1;
+            }
+        }
+        Some((states, len))
+    }
+
+    /// Makes sure that renormalizing given channel doesn't read past the end
+    /// of the data, if the `checked` feature is enabled.
+    #[inline]
+    fn check_renorm(&self, channel: usize) {
+        if cfg!(feature = "checked") {
+            assert!(
+                self.can_renorm(channel),
+                "decoder read past the end of the data"
+            );
         }
     }
 
@@ -61,17 +441,22 @@ impl<const N: usize> RansDecoderMulti<N> for ByteRansDecoderMulti<'_, N> {
 
     #[inline]
     fn get_at(&mut self, channel: usize, scale_bits: u32) -> u32 {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
 
-        unsafe { ryg_rans_sys::rans_byte::rans_dec_get(&mut self.states[channel], scale_bits) }
+        unsafe { crate::backend::rans_byte::rans_dec_get(&mut self.states[channel], scale_bits) }
     }
 
     #[inline]
     fn advance_at(&mut self, channel: usize, symbol: &Self::Symbol, scale_bits: u32) {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
+        if cfg!(feature = "checked") {
+            self.advance_step_at(channel, symbol, scale_bits);
+            self.renorm_at(channel);
+            return;
+        }
 
         unsafe {
-            ryg_rans_sys::rans_byte::rans_dec_advance_symbol(
+            crate::backend::rans_byte::rans_dec_advance_symbol(
                 &mut self.states[channel],
                 &mut self.ptr,
                 &symbol.symbol,
@@ -84,10 +469,10 @@ impl<const N: usize> RansDecoderMulti<N> for ByteRansDecoderMulti<'_, N> {
 
     #[inline]
     fn advance_step_at(&mut self, channel: usize, symbol: &Self::Symbol, scale_bits: u32) {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
 
         unsafe {
-            ryg_rans_sys::rans_byte::rans_dec_advance_symbol_step(
+            crate::backend::rans_byte::rans_dec_advance_symbol_step(
                 &mut self.states[channel],
                 &symbol.symbol,
                 scale_bits,
@@ -97,22 +482,458 @@ impl<const N: usize> RansDecoderMulti<N> for ByteRansDecoderMulti<'_, N> {
 
     #[inline]
     fn renorm_at(&mut self, channel: usize) {
-        debug_assert!(channel <= N);
+        checked_assert!(channel < N);
+        self.check_renorm(channel);
 
         unsafe {
-            ryg_rans_sys::rans_byte::rans_dec_renorm(&mut self.states[channel], &mut self.ptr);
+            crate::backend::rans_byte::rans_dec_renorm(&mut self.states[channel], &mut self.ptr);
         }
 
         check_dec_pointer!(self);
     }
+
+    #[inline]
+    fn try_renorm_at(&mut self, channel: usize) -> Result<(), RansError> {
+        checked_assert!(channel < N);
+        if !self.can_renorm(channel) {
+            return Err(RansError::InputExhausted);
+        }
+
+        self.renorm_at(channel);
+        Ok(())
+    }
+
+    #[inline]
+    fn try_renorm_all(&mut self) -> Result<(), RansError> {
+        let (states, len) = self.renorm_all_states().ok_or(RansError::InputExhausted)?;
+        self.states = states;
+        self.ptr = unsafe { self.ptr.add(len) };
+
+        Ok(())
+    }
 }
 
 impl RansDecoder for ByteRansDecoderMulti<'_, 1> {}
 
+impl ByteRansDecoderMulti<'_, 1> {
+    /// Decodes a single symbol using the model registered with
+    /// [`Self::set_model()`] and returns its index. See
+    /// [`Self::decode_at()`].
+    ///
+    /// # Panics
+    /// Panics if no model has been registered.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::model::Model;
+    ///
+    /// let mut decoder = ByteRansDecoder::new([2, 0, 0, 2]);
+    /// decoder.set_model(&Model::from_freqs(vec![2, 2], 2));
+    /// assert_eq!(decoder.decode(), 1);
+    /// assert_eq!(decoder.decode(), 0);
+    /// ```
+    pub fn decode(&mut self) -> usize {
+        self.decode_at(0)
+    }
+}
+
+/// Multi-stream interleaved rANS decoder - byte-aligned version with the
+/// number of channels chosen at runtime.
+///
+/// This is the decoding counterpart of
+/// [`DynRansEncoderMulti`](crate::byte_encoder::DynRansEncoderMulti). The
+/// methods mirror the ones of [`RansDecoderMulti`], and the data produced by
+/// [`ByteRansEncoderMulti`](crate::byte_encoder::ByteRansEncoderMulti) can be
+/// decoded as well, as long as the number of channels matches.
+#[derive(Debug)]
+pub struct DynRansDecoderMulti<'a> {
+    states: Vec<crate::backend::rans_byte::RansState>,
+    data: DecoderInput<'a>,
+    /// Index of the next byte to read in `data`
+    pos: usize,
+}
+
+impl<'a> DynRansDecoderMulti<'a> {
+    /// Creates a new `DynRansDecoderMulti` instance with given number of
+    /// channels and `data`.
+    ///
+    /// # Panics
+    /// Panics if `channels` is zero, or `data` is too short to contain the
+    /// initial states of all channels.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    ///
+    /// let mut decoder = DynRansDecoderMulti::new(2, [2, 0, 0, 1, 0, 0, 0, 1]);
+    /// assert_eq!(decoder.channels(), 2);
+    /// assert_eq!(decoder.get_at(0, 2), 2);
+    /// ```
+    #[must_use]
+    pub fn new<T: Into<DecoderInput<'a>>>(channels: usize, data: T) -> Self {
+        let data = data.into();
+        assert!(channels > 0, "number of channels must be non-zero");
+        assert!(data.len() >= channels * size_of::<crate::backend::rans_byte::RansState>());
+
+        let mut decoder = Self {
+            states: vec![0; channels],
+            data,
+            pos: 0,
+        };
+        for channel in 0..channels {
+            decoder.with_ptr(
+                |state, ptr| unsafe {
+                    crate::backend::rans_byte::rans_dec_init(state, ptr);
+                },
+                channel,
+            );
+        }
+        decoder
+    }
+
+    /// Creates a new `DynRansDecoderMulti` instance with given number of
+    /// channels and `data`, checking that the data is long enough to contain
+    /// the initial states of all channels.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidChannel`] if `channels` is zero,
+    /// [`RansError::EmptyInput`] if `data` is empty, or
+    /// [`RansError::UnexpectedEof`] if it is too short to contain the initial
+    /// states.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    /// use rans::RansError;
+    ///
+    /// let mut decoder = DynRansDecoderMulti::try_new(1, [0, 0, 128, 0]).unwrap();
+    /// assert_eq!(decoder.get_at(0, 4), 0);
+    /// assert_eq!(
+    ///     DynRansDecoderMulti::try_new(2, [0, 0, 128, 0]).unwrap_err(),
+    ///     RansError::UnexpectedEof
+    /// );
+    /// ```
+    pub fn try_new<T: Into<DecoderInput<'a>>>(channels: usize, data: T) -> Result<Self, RansError> {
+        let data = data.into();
+        if channels == 0 {
+            return Err(RansError::InvalidChannel);
+        }
+        if data.is_empty() {
+            return Err(RansError::EmptyInput);
+        }
+        if data.len() < channels * size_of::<crate::backend::rans_byte::RansState>() {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        Ok(Self::new(channels, data))
+    }
+
+    /// Returns the number of channels of this decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    ///
+    /// let decoder = DynRansDecoderMulti::new(1, [0, 0, 128, 0]);
+    /// assert_eq!(decoder.channels(), 1);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn channels(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns the number of bytes of the data that haven't been read by
+    /// the decoder yet. See [`ByteRansDecoderMulti::bytes_remaining()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    ///
+    /// let decoder = DynRansDecoderMulti::new(1, [0, 0, 128, 0, 42]);
+    /// assert_eq!(decoder.bytes_remaining(), 1);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn bytes_remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// Returns the position of the decoder in the data. See
+    /// [`ByteRansDecoderMulti::position()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    ///
+    /// let decoder = DynRansDecoderMulti::new(2, [0, 0, 128, 0, 0, 0, 128, 0, 42]);
+    /// assert_eq!(decoder.position(), 8);
+    /// ```
+    #[doc(alias = "bytes_consumed")]
+    #[must_use]
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Re-initializes the states of all the channels from the data at the
+    /// current position. See [`ByteRansDecoderMulti::reinit_tail()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the rest of the data is too
+    /// short to contain the initial states of all channels. The decoder is
+    /// left unchanged in such case.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    /// use rans::RansError;
+    ///
+    /// let mut decoder = DynRansDecoderMulti::new(1, [0, 0, 128, 0, 2, 0, 0, 1]);
+    /// assert_eq!(decoder.get_at(0, 2), 0);
+    /// decoder.reinit_tail().unwrap();
+    /// assert_eq!(decoder.get_at(0, 2), 2);
+    /// assert_eq!(decoder.reinit_tail(), Err(RansError::UnexpectedEof));
+    /// ```
+    pub fn reinit_tail(&mut self) -> Result<(), RansError> {
+        if self.bytes_remaining()
+            < self.states.len() * size_of::<crate::backend::rans_byte::RansState>()
+        {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        for channel in 0..self.states.len() {
+            self.with_ptr(
+                |state, ptr| unsafe {
+                    crate::backend::rans_byte::rans_dec_init(state, ptr);
+                },
+                channel,
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns whether all the symbols have been decoded. See
+    /// [`ByteRansDecoderMulti::is_exhausted()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    ///
+    /// let decoder = DynRansDecoderMulti::new(2, [0, 0, 128, 0, 0, 0, 128, 0]);
+    /// assert!(decoder.is_exhausted());
+    /// ```
+    #[must_use]
+    pub fn is_exhausted(&self) -> bool {
+        self.bytes_remaining() == 0 && self.states.iter().all(|&state| state == RANS_BYTE_L)
+    }
+
+    /// Gets the cumulative frequency for the current symbol at specified
+    /// channel. See [`RansDecoderMulti::get_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    ///
+    /// let mut decoder = DynRansDecoderMulti::new(2, [2, 0, 0, 1, 0, 0, 0, 1]);
+    /// assert_eq!(decoder.get_at(0, 2), 2);
+    /// assert_eq!(decoder.get_at(1, 2), 0);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn get_at(&mut self, channel: usize, scale_bits: u32) -> u32 {
+        unsafe { crate::backend::rans_byte::rans_dec_get(&mut self.states[channel], scale_bits) }
+    }
+
+    /// Advances the data position after reading a symbol at given channel.
+    /// See [`RansDecoderMulti::advance_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, DynRansDecoderMulti};
+    /// use rans::RansDecSymbol;
+    ///
+    /// let mut decoder = DynRansDecoderMulti::new(2, [2, 0, 0, 1, 0, 0, 0, 1]);
+    /// decoder.advance_at(0, &ByteRansDecSymbol::new(2, 2), 2);
+    /// assert_eq!(decoder.get_at(0, 2), 0);
+    /// ```
+    #[inline]
+    pub fn advance_at(&mut self, channel: usize, symbol: &ByteRansDecSymbol, scale_bits: u32) {
+        self.with_ptr(
+            |state, ptr| unsafe {
+                crate::backend::rans_byte::rans_dec_advance_symbol(
+                    state,
+                    ptr,
+                    &symbol.symbol,
+                    scale_bits,
+                );
+            },
+            channel,
+        );
+    }
+
+    /// Pops a single symbol from the internal state of given channel, without
+    /// doing renormalization. See [`RansDecoderMulti::advance_step_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, DynRansDecoderMulti};
+    /// use rans::RansDecSymbol;
+    ///
+    /// let mut decoder = DynRansDecoderMulti::new(2, [2, 0, 0, 1, 0, 0, 0, 1]);
+    /// decoder.advance_step_at(0, &ByteRansDecSymbol::new(2, 2), 2);
+    /// decoder.renorm_at(0);
+    /// assert_eq!(decoder.get_at(0, 2), 0);
+    /// ```
+    #[inline]
+    pub fn advance_step_at(&mut self, channel: usize, symbol: &ByteRansDecSymbol, scale_bits: u32) {
+        unsafe {
+            crate::backend::rans_byte::rans_dec_advance_symbol_step(
+                &mut self.states[channel],
+                &symbol.symbol,
+                scale_bits,
+            );
+        }
+    }
+
+    /// Renormalizes the internal state of given channel. See
+    /// [`RansDecoderMulti::renorm_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    ///
+    /// let mut decoder = DynRansDecoderMulti::new(1, [0, 0, 128, 0]);
+    /// decoder.renorm_at(0);
+    /// assert_eq!(decoder.get_at(0, 4), 0);
+    /// ```
+    #[inline]
+    pub fn renorm_at(&mut self, channel: usize) {
+        self.with_ptr(
+            |state, ptr| unsafe {
+                crate::backend::rans_byte::rans_dec_renorm(state, ptr);
+            },
+            channel,
+        );
+    }
+
+    /// Renormalizes the internal states of all channels. See
+    /// [`RansDecoderMulti::renorm_all()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::DynRansDecoderMulti;
+    ///
+    /// let mut decoder = DynRansDecoderMulti::new(2, [0, 0, 128, 0, 0, 0, 128, 0]);
+    /// decoder.renorm_all();
+    /// assert_eq!(decoder.get_at(1, 4), 0);
+    /// ```
+    pub fn renorm_all(&mut self) {
+        for i in 0..self.channels() {
+            self.renorm_at(i);
+        }
+    }
+
+    /// Decodes a single symbol at given channel using given model and returns
+    /// its index. See [`RansDecoderMulti::decode_symbol_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, DynRansDecoderMulti};
+    /// use rans::model::{DecModel, Model};
+    ///
+    /// let model = Model::from_freqs(vec![2, 2], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder = DynRansDecoderMulti::new(2, [2, 0, 0, 1, 0, 0, 0, 1]);
+    /// assert_eq!(decoder.decode_symbol_at(0, &dec_model), 1);
+    /// assert_eq!(decoder.decode_symbol_at(1, &dec_model), 0);
+    /// ```
+    pub fn decode_symbol_at(
+        &mut self,
+        channel: usize,
+        model: &DecModel<ByteRansDecSymbol>,
+    ) -> usize {
+        let cum_freq = self.get_at(channel, model.scale_bits());
+        let (index, symbol) = model.lookup(cum_freq);
+        self.advance_at(channel, symbol, model.scale_bits());
+        index
+    }
+
+    #[inline]
+    fn with_ptr(&mut self, f: impl FnOnce(&mut u32, &mut *mut u8), channel: usize) {
+        let state = &mut self.states[channel];
+        // The input is never written to, the pointer only needs to be mutable
+        // to match the signatures of the underlying rANS implementation
+        let begin_ptr = self.data.as_ptr() as *mut u8;
+        unsafe {
+            let mut ptr = begin_ptr.add(self.pos);
+            f(state, &mut ptr);
+            self.pos = ptr.offset_from(begin_ptr) as usize;
+        }
+
+        debug_assert!(
+            self.pos <= self.data.len(),
+            "Data pointer is in an invalid state. Make sure you are not reading more symbols than originally encoded."
+        );
+    }
+}
+
 /// rANS decoder symbol - byte-aligned version.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct ByteRansDecSymbol {
-    symbol: ryg_rans_sys::rans_byte::RansDecSymbol,
+    symbol: crate::backend::rans_byte::RansDecSymbol,
+}
+
+impl ByteRansDecSymbol {
+    /// Creates a new symbol like [`RansDecSymbol::new()`], but can be used in
+    /// constant expressions, e.g. to build static tables of symbols for
+    /// fixed models at compile time.
+    ///
+    /// Only available with the `pure-rust` feature, as the C implementation
+    /// can't be called at compile time.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecSymbol;
+    /// use rans::RansDecSymbol;
+    ///
+    /// static SYMBOLS: [ByteRansDecSymbol; 2] = [
+    ///     ByteRansDecSymbol::new_const(0, 3),
+    ///     ByteRansDecSymbol::new_const(3, 1),
+    /// ];
+    /// assert_eq!(SYMBOLS[1].cum_freq(), 3);
+    /// assert_eq!(SYMBOLS[1], ByteRansDecSymbol::new(3, 1));
+    /// ```
+    #[cfg(feature = "pure-rust")]
+    #[must_use]
+    pub const fn new_const(cum_freq: u32, freq: u32) -> Self {
+        Self {
+            symbol: crate::backend::rans_byte::dec_symbol(cum_freq, freq),
+        }
+    }
+
+    /// Returns the fields of the symbol, used to implement the comparison
+    /// traits (the backend symbol types don't implement them).
+    #[inline]
+    fn key(&self) -> (u16, u16) {
+        (self.symbol.start, self.symbol.freq)
+    }
+}
+
+impl PartialEq for ByteRansDecSymbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for ByteRansDecSymbol {}
+
+impl Hash for ByteRansDecSymbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
 }
 
 impl RansDecSymbol for ByteRansDecSymbol {
@@ -120,7 +941,7 @@ impl RansDecSymbol for ByteRansDecSymbol {
     fn new(cum_freq: u32, freq: u32) -> Self {
         unsafe {
             let mut symbol = MaybeUninit::uninit();
-            ryg_rans_sys::rans_byte::rans_dec_symbol_init(symbol.as_mut_ptr(), cum_freq, freq);
+            crate::backend::rans_byte::rans_dec_symbol_init(symbol.as_mut_ptr(), cum_freq, freq);
 
             Self {
                 symbol: symbol.assume_init(),
@@ -141,8 +962,17 @@ impl RansDecSymbol for ByteRansDecSymbol {
 
 #[cfg(test)]
 mod tests {
-    use crate::byte_decoder::{ByteRansDecoder, ByteRansDecoderMulti};
+    use crate::backend::RANS_BYTE_L;
+    use crate::byte_decoder::{
+        ByteRansDecSymbol, ByteRansDecoder, ByteRansDecoderMulti, DynRansDecoderMulti,
+    };
+    use crate::byte_encoder::{
+        ByteRansEncSymbol, ByteRansEncoder, ByteRansEncoderMulti, DynRansEncoderMulti,
+    };
     use crate::decoder::tests as dec_tests;
+    use crate::encoder::tests as enc_tests;
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::{RansEncoderMulti, RansError};
 
     #[test]
     fn test_decode_empty() {
@@ -158,6 +988,53 @@ mod tests {
         dec_tests::test_decode_two_symbols(decoder);
     }
 
+    #[test]
+    fn test_decode_bits() {
+        let data = enc_tests::encode_bits(ByteRansEncoder::new(1024));
+        let decoder = ByteRansDecoder::new(data);
+
+        dec_tests::test_decode_bits(decoder);
+    }
+
+    #[test]
+    fn test_state_matches_encoder() {
+        let mut encoder = ByteRansEncoder::new(1024);
+        encoder.put_bits_at(0, 0xdead_beef, 32);
+        let state = encoder.state_at(0);
+        encoder.flush_all();
+
+        let decoder = ByteRansDecoder::new(encoder.data());
+        assert_eq!(decoder.state_at(0), state);
+    }
+
+    #[test]
+    fn test_save_restore_state() {
+        let data = enc_tests::encode_bits(ByteRansEncoder::new(1024));
+        let decoder = ByteRansDecoder::new(data);
+
+        dec_tests::test_save_restore_state(
+            decoder,
+            ByteRansDecoder::save_state,
+            ByteRansDecoder::restore_state,
+        );
+    }
+
+    #[test]
+    fn test_decode_symbols_with_model() {
+        let decoder = ByteRansDecoder::new([2, 0, 0, 2]);
+
+        dec_tests::test_decode_symbols_with_model(decoder);
+    }
+
+    #[test]
+    fn test_decode_with_registered_model() {
+        let mut decoder = ByteRansDecoder::new([2, 0, 0, 2]);
+        decoder.set_model(&Model::from_freqs(vec![2, 2], 2));
+
+        assert_eq!(decoder.decode_at(0), 1);
+        assert_eq!(decoder.decode_at(0), 0);
+    }
+
     #[test]
     fn test_decode_symbols_clone() {
         let decoder = ByteRansDecoder::new([2, 0, 0, 2]);
@@ -183,6 +1060,181 @@ mod tests {
         dec_tests::test_decode_interleaved(decoder);
     }
 
+    #[test]
+    fn test_try_new() {
+        assert!(ByteRansDecoder::try_new([0, 0, 128, 0]).is_ok());
+        assert_eq!(
+            ByteRansDecoder::try_new(vec![]).unwrap_err(),
+            RansError::EmptyInput
+        );
+        assert_eq!(
+            ByteRansDecoderMulti::<2>::try_new([0, 0, 128, 0]).unwrap_err(),
+            RansError::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_dyn_round_trip() {
+        const SCALE_BITS: u32 = 8;
+        let model = Model::from_counts(&[1, 5, 20, 3, 100, 40, 2], SCALE_BITS);
+        let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+        let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+        let symbols: Vec<_> = (0..1000).map(|i| (i * 13 + i / 3) % 7).collect();
+
+        for channels in 1..=5 {
+            let mut encoder = DynRansEncoderMulti::new(channels, 4096);
+            for (i, &symbol) in symbols.iter().enumerate().rev() {
+                encoder.put_at(i % channels, enc_model.symbol(symbol));
+            }
+            encoder.flush_all();
+
+            let mut decoder = DynRansDecoderMulti::new(channels, encoder.data());
+            for (i, &symbol) in symbols.iter().enumerate() {
+                let channel = channels - 1 - i % channels;
+                assert_eq!(decoder.decode_symbol_at(channel, &dec_model), symbol);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dyn_try_new() {
+        assert_eq!(
+            DynRansDecoderMulti::try_new(0, [0, 0, 128, 0]).unwrap_err(),
+            RansError::InvalidChannel
+        );
+        assert_eq!(
+            DynRansDecoderMulti::try_new(1, vec![]).unwrap_err(),
+            RansError::EmptyInput
+        );
+        assert_eq!(
+            DynRansDecoderMulti::try_new(2, [0, 0, 128, 0, 0, 0]).unwrap_err(),
+            RansError::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_put_all_decode_n() {
+        dec_tests::test_put_all_decode_n(
+            ByteRansEncoderMulti::<3>::new(1024),
+            ByteRansDecoderMulti::<3>::new,
+        );
+    }
+
+    #[test]
+    fn test_decode_symbols_all() {
+        dec_tests::test_decode_symbols_all(
+            ByteRansEncoderMulti::<3>::new(1024),
+            ByteRansDecoderMulti::<3>::new,
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        dec_tests::test_sections(
+            ByteRansEncoderMulti::<2>::new(1 << 16),
+            ByteRansDecoderMulti::<2>::new,
+        );
+    }
+
+    #[test]
+    fn test_uniform() {
+        dec_tests::test_uniform(ByteRansEncoder::new(1024), ByteRansDecoder::new);
+    }
+
+    #[test]
+    fn test_is_exhausted() {
+        dec_tests::test_is_exhausted(
+            ByteRansEncoderMulti::<2>::new(1024),
+            ByteRansDecoderMulti::<2>::new,
+            ByteRansDecoderMulti::<2>::is_exhausted,
+        );
+    }
+
+    #[test]
+    fn test_reinit_tail() {
+        dec_tests::test_reinit_tail(
+            || ByteRansEncoderMulti::<2>::new(1 << 16),
+            ByteRansDecoderMulti::<2>::new,
+            ByteRansDecoderMulti::<2>::reinit_tail,
+        );
+    }
+
+    #[test]
+    fn test_try_advance_truncated() {
+        dec_tests::test_try_advance_truncated(
+            ByteRansEncoderMulti::<2>::new(1 << 16),
+            ByteRansDecoderMulti::<2>::new,
+            4,
+        );
+    }
+
+    #[test]
+    fn test_try_renorm_zero_state() {
+        use crate::RansDecoderMulti;
+
+        // A zero state is renormalized by skipping zero bytes until a non-zero
+        // one is found, so it can't be bounds-checked by its length alone
+        let mut decoder = ByteRansDecoder::new([0; 7]);
+        assert_eq!(decoder.state_at(0), 0);
+        assert_eq!(decoder.try_renorm_at(0), Err(RansError::InputExhausted));
+        assert_eq!(decoder.state_at(0), 0);
+        assert_eq!(decoder.position(), 4);
+
+        let mut decoder = ByteRansDecoder::new([0, 0, 0, 0, 0, 0, 128, 0, 0]);
+        assert_eq!(decoder.try_renorm_at(0), Ok(()));
+        assert_eq!(decoder.state_at(0), RANS_BYTE_L);
+        assert_eq!(decoder.position(), 9);
+    }
+
+    #[test]
+    fn test_try_renorm_all_zero_state() {
+        use crate::RansDecoderMulti;
+
+        let mut decoder = ByteRansDecoderMulti::<2>::new([0, 0, 128, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(decoder.state_at(1), 0);
+        assert_eq!(decoder.try_renorm_all(), Err(RansError::InputExhausted));
+        assert_eq!(decoder.state_at(1), 0);
+        assert_eq!(decoder.position(), 8);
+
+        let mut decoder =
+            ByteRansDecoderMulti::<2>::new([0, 0, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 0, 0]);
+        decoder.set_state_at(0, 1);
+        assert_eq!(decoder.try_renorm_all(), Ok(()));
+        assert_eq!(decoder.state_at(0), 0x0100_0000);
+        assert_eq!(decoder.state_at(1), RANS_BYTE_L);
+        assert_eq!(decoder.position(), 16);
+    }
+
+    #[test]
+    #[cfg(feature = "checked")]
+    #[should_panic(expected = "decoder read past the end of the data")]
+    fn test_renorm_all_zero_state() {
+        use crate::RansDecoderMulti;
+
+        let mut decoder = ByteRansDecoderMulti::<2>::new([0; 11]);
+        decoder.renorm_all();
+    }
+
+    #[test]
+    #[cfg(feature = "checked")]
+    #[should_panic(expected = "decoder read past the end of the data")]
+    fn test_checked_read_past_end() {
+        use crate::{RansDecSymbol, RansDecoder};
+
+        let mut decoder = ByteRansDecoder::new([0, 0, 128, 0]);
+        decoder.advance(&ByteRansDecSymbol::new(0, 1), 8);
+    }
+
+    #[test]
+    fn test_is_send_sync() {
+        dec_tests::test_is_send_sync::<ByteRansDecoderMulti<'static, 2>>();
+    }
+
+    #[test]
+    fn test_symbol_eq_hash() {
+        dec_tests::test_symbol_eq_hash::<ByteRansDecSymbol>();
+    }
+
     #[test]
     fn test_has_debug_output() {
         let decoder = ByteRansDecoder::new([0, 0, 128, 0]);
@@ -1,126 +1,1228 @@
+use std::hash::{Hash, Hasher};
 use std::mem::MaybeUninit;
+use std::ops::Range;
 
-use crate::encoder::{check_enc_pointer, RansEncSymbol, RansEncoder, RansEncoderMulti};
+use crate::backend::RANS_BYTE_L;
+use crate::encoder::{
+    assert_scale_bits, check_enc_pointer, EncCheckpoint, RansEncSymbol, RansEncoder,
+    RansEncoderMulti,
+};
+use crate::lookup::AliasTable;
+use crate::model::Model;
+use crate::RansError;
+
+/// Maximum number of bytes a single `put` can write into the buffer.
+pub(crate) const MAX_PUT_LEN: usize = 2;
+/// Number of bytes a single `flush` writes into the buffer.
+pub(crate) const FLUSH_LEN: usize = 4;
+/// Initial buffer size, in bytes, of encoders with a growable buffer.
+const INITIAL_GROWABLE_LEN: usize = 256;
 
 /// Multi-stream interleaved rANS encoder - byte-aligned version.
+///
+/// The encoder is both [`Send`] and [`Sync`], so it can be moved to (or
+/// shared with) other threads.
 #[derive(Debug)]
 pub struct ByteRansEncoderMulti<const N: usize> {
-    states: [ryg_rans_sys::rans_byte::RansState; N],
+    states: [crate::backend::rans_byte::RansState; N],
     dst: Vec<u8>,
     ptr: *mut u8,
+    growable: bool,
+    /// Length of the data when the current section started
+    section_start: usize,
+}
+
+/// Single-stream rANS encoder - byte-aligned version.
+pub type ByteRansEncoder = ByteRansEncoderMulti<1>;
+
+// SAFETY: `ptr` always points into `dst`, which is owned by the encoder and
+// does not move along with it. The pointer is only
+// ever advanced by methods taking `&mut self`.
+unsafe impl<const N: usize> Send for ByteRansEncoderMulti<N> {}
+unsafe impl<const N: usize> Sync for ByteRansEncoderMulti<N> {}
+
+impl<const N: usize> ByteRansEncoderMulti<N> {
+    /// Creates a new `ByteMultiRansEncoder` instance that can contain `max_len`
+    /// bytes in the internal buffer.
+    ///
+    /// Note that most of the API is inside the [`RansEncoderMulti`] trait, so
+    /// you probably want to `use rans::RansEncoderMulti`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(max_len: usize) -> Self {
+        debug_assert!(N > 0);
+
+        // The states get initialized by `reset()` below
+        let mut encoder = Self {
+            states: [0; N],
+            dst: vec![0; max_len],
+            ptr: std::ptr::null_mut(),
+            growable: false,
+            section_start: 0,
+        };
+        encoder.reset();
+        encoder
+    }
+
+    /// Creates a new `ByteRansEncoderMulti` instance that uses given vector as
+    /// its internal buffer, so that the allocation can be reused across
+    /// encoders. The whole capacity of the vector is used and its contents
+    /// are overwritten.
+    ///
+    /// Use [`Self::into_buffer()`] to get the vector back.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::with_buffer(Vec::with_capacity(1024));
+    /// encoder.flush_all();
+    /// let buffer = encoder.into_buffer();
+    /// assert_eq!(buffer.len(), 8);
+    /// assert!(buffer.capacity() >= 1024);
+    ///
+    /// let encoder = ByteRansEncoderMulti::<2>::with_buffer(buffer);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn with_buffer(mut buffer: Vec<u8>) -> Self {
+        debug_assert!(N > 0);
+
+        buffer.resize(buffer.capacity(), 0);
+        // The states get initialized by `reset()` below
+        let mut encoder = Self {
+            states: [0; N],
+            dst: buffer,
+            ptr: std::ptr::null_mut(),
+            growable: false,
+            section_start: 0,
+        };
+        encoder.reset();
+        encoder
+    }
+
+    /// Consumes this encoder, returning its internal buffer with the encoded
+    /// data moved to the beginning. The buffer's capacity is retained,
+    /// so it can be passed to [`Self::with_buffer()`] again.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.flush_all();
+    /// let len = encoder.len();
+    /// assert_eq!(encoder.into_buffer().len(), len);
+    /// ```
+    #[must_use]
+    pub fn into_buffer(mut self) -> Vec<u8> {
+        let start = self.dst.len() - self.len();
+        self.dst.copy_within(start.., 0);
+        self.dst.truncate(self.dst.len() - start);
+        self.dst
+    }
+
+    /// Creates a new `ByteMultiRansEncoder` instance with a growable internal
+    /// buffer.
+    ///
+    /// Instead of being limited to a fixed size, the buffer is transparently
+    /// reallocated whenever the remaining space gets too small to hold the
+    /// next symbol. Already encoded data is preserved, so this is useful when
+    /// the size of the output cannot be estimated up front.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::{RansEncSymbol, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::with_growable_buffer();
+    /// let symbol = ByteRansEncSymbol::new(0, 1, 8);
+    /// for _ in 0..10000 {
+    ///     encoder.put(&symbol);
+    /// }
+    /// encoder.flush();
+    /// assert!(encoder.len() > 5000);
+    /// ```
+    #[must_use]
+    pub fn with_growable_buffer() -> Self {
+        let mut encoder = Self::new(INITIAL_GROWABLE_LEN);
+        encoder.growable = true;
+        encoder
+    }
+
+    /// Puts a symbol into given channel using given alias table instead of
+    /// a regular [`ByteRansEncSymbol`]. The data has to be decoded with
+    /// [`RansDecoderMulti::decode_alias_at()`](crate::RansDecoderMulti::decode_alias_at)
+    /// using the same table.
+    ///
+    /// This is slower than [`RansEncoderMulti::put_at()`], as the encoder
+    /// symbol is computed on the fly.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.put_alias_at(0, &table, 1);
+    /// encoder.put_alias_at(1, &table, 2);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new(encoder.data());
+    /// assert_eq!(decoder.decode_alias_at(0, &table), 2);
+    /// assert_eq!(decoder.decode_alias_at(1, &table), 1);
+    /// ```
+    pub fn put_alias_at(&mut self, channel: usize, table: &AliasTable, symbol: usize) {
+        checked_assert!(channel < N);
+
+        // The slot the symbol ends up in depends on the state after
+        // renormalization, which only depends on the symbol's frequency
+        let freq = table.freq(symbol);
+        let x_max = ((RANS_BYTE_L >> table.scale_bits()) << 8) * freq;
+        let mut x = self.states[channel];
+        while x >= x_max {
+            x >>= 8;
+        }
+        let offset = x % freq;
+        let start = table.remap(symbol, offset) - offset;
+        self.put_at(
+            channel,
+            &ByteRansEncSymbol::new(start, freq, table.scale_bits()),
+        );
+    }
+
+    /// Creates a checkpoint of this encoder's channel states and write
+    /// position, which can be restored later with [`Self::restore()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// let checkpoint = encoder.checkpoint();
+    /// encoder.flush_all();
+    /// encoder.restore(&checkpoint);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn checkpoint(&self) -> EncCheckpoint<u32, N> {
+        EncCheckpoint {
+            states: self.states,
+            len: self.len(),
+        }
+    }
+
+    /// Restores this encoder's channel states and write position from given
+    /// checkpoint, discarding everything put into the encoder since.
+    ///
+    /// The checkpoint must have been created by this encoder, after its last
+    /// reset; otherwise, the encoder produces garbage.
+    ///
+    /// # Panics
+    /// Panics if the checkpoint's data is longer than this encoder's buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.flush_at(0);
+    /// let checkpoint = encoder.checkpoint();
+    /// encoder.flush_at(1);
+    /// encoder.restore(&checkpoint);
+    /// assert_eq!(encoder.len(), checkpoint.data_len());
+    /// ```
+    pub fn restore(&mut self, checkpoint: &EncCheckpoint<u32, N>) {
+        assert!(
+            checkpoint.len <= self.dst.len(),
+            "checkpoint does not fit in the buffer"
+        );
+
+        self.states = checkpoint.states;
+        self.section_start = self.section_start.min(checkpoint.len);
+        unsafe {
+            self.ptr = self.dst.as_mut_ptr().add(self.dst.len() - checkpoint.len);
+        }
+    }
+
+    /// Returns the current rANS state of given channel.
+    ///
+    /// Together with [`Self::set_state_at()`], this makes it possible to hand
+    /// streams over between this crate and other rANS implementations.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    ///
+    /// let encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// assert_eq!(encoder.state_at(1), 0x0080_0000);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn state_at(&self, channel: usize) -> u32 {
+        self.states[channel]
+    }
+
+    /// Overwrites the rANS state of given channel.
+    ///
+    /// The state has to be normalized, i.e. within the range the encoder keeps
+    /// its states in between the symbols; otherwise, the encoder produces
+    /// garbage.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<1>::new(1024);
+    /// encoder.set_state_at(0, 0x0102_0304);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data(), [4, 3, 2, 1]);
+    /// ```
+    #[inline]
+    pub fn set_state_at(&mut self, channel: usize, state: u32) {
+        self.states[channel] = state;
+    }
+
+    /// Makes sure there are at least `len` bytes left in the buffer, growing
+    /// it if this encoder has a growable buffer. Otherwise, panics if the
+    /// checks are enabled.
+    #[inline]
+    fn ensure_space(&mut self, len: usize) {
+        if self.growable && self.remaining_len() < len {
+            self.grow(len);
+        }
+        checked_assert!(self.remaining_len() >= len, "encoder buffer overflow");
+    }
+
+    #[cold]
+    fn grow(&mut self, additional: usize) {
+        let used = self.dst.len() - self.remaining_len();
+        let new_len = (self.dst.len() * 2).max(self.dst.len() + additional);
+
+        let mut dst = vec![0; new_len];
+        dst[new_len - used..].copy_from_slice(&self.dst[self.dst.len() - used..]);
+        self.dst = dst;
+        unsafe {
+            self.ptr = self.dst.as_mut_ptr().add(new_len - used);
+        }
+    }
+
+    #[inline]
+    fn remaining_len(&self) -> usize {
+        unsafe { self.ptr.offset_from(self.dst.as_ptr()) as usize }
+    }
+
+    #[inline]
+    fn is_ptr_valid(&self) -> bool {
+        let range = self.dst.as_ptr_range();
+        let range_inclusive = range.start..=range.end;
+        range_inclusive.contains(&(self.ptr as *const u8))
+    }
+}
+
+impl<const N: usize> RansEncoderMulti<N> for ByteRansEncoderMulti<N> {
+    type Symbol = ByteRansEncSymbol;
+
+    fn reset(&mut self) {
+        self.clear();
+        self.section_start = 0;
+
+        unsafe {
+            let mut ptr: *mut u8 = self.dst.as_mut_ptr();
+            ptr = ptr.add(self.dst.len());
+            self.ptr = ptr;
+        }
+    }
+
+    fn clear(&mut self) {
+        for state in &mut self.states {
+            unsafe {
+                crate::backend::rans_byte::rans_enc_init(state);
+            }
+        }
+    }
+
+    #[inline]
+    fn put_at(&mut self, channel: usize, symbol: &Self::Symbol) {
+        checked_assert!(channel < N);
+        self.ensure_space(MAX_PUT_LEN);
+
+        unsafe {
+            crate::backend::rans_byte::rans_enc_put_symbol(
+                &mut self.states[channel],
+                &mut self.ptr,
+                &symbol.symbol,
+            );
+        }
+
+        check_enc_pointer!(self);
+    }
+
+    fn try_put_at(&mut self, channel: usize, symbol: &Self::Symbol) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+        if !self.growable && self.remaining_len() < MAX_PUT_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.put_at(channel, symbol);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush_at(&mut self, channel: usize) {
+        checked_assert!(channel < N);
+        self.ensure_space(FLUSH_LEN);
+
+        unsafe {
+            crate::backend::rans_byte::rans_enc_flush(&mut self.states[channel], &mut self.ptr);
+        }
+
+        check_enc_pointer!(self);
+    }
+
+    fn try_flush_at(&mut self, channel: usize) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+        if !self.growable && self.remaining_len() < FLUSH_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.flush_at(channel);
+        Ok(())
+    }
+
+    fn flush_section(&mut self) -> Range<usize> {
+        self.flush_all();
+        self.clear();
+        let section = self.section_start..self.len();
+        self.section_start = self.len();
+        section
+    }
+
+    #[inline]
+    fn data(&self) -> &[u8] {
+        unsafe {
+            let begin_ptr = self.dst.as_ptr();
+            let start_index = self.ptr.offset_from(begin_ptr) as usize;
+            &self.dst[start_index..]
+        }
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.dst.len()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        if self.remaining_len() < additional {
+            self.grow(additional);
+        }
+    }
+}
+
+impl RansEncoder for ByteRansEncoderMulti<1> {}
+
+impl ByteRansEncoderMulti<1> {
+    /// Puts a symbol using given alias table instead of a regular
+    /// [`ByteRansEncSymbol`]. See [`Self::put_alias_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    /// use rans::{RansDecoder, RansEncoderMulti};
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put_alias(&table, 0);
+    /// encoder.put_alias(&table, 2);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoder::new(encoder.data());
+    /// assert_eq!(decoder.decode_alias(&table), 2);
+    /// assert_eq!(decoder.decode_alias(&table), 0);
+    /// ```
+    pub fn put_alias(&mut self, table: &AliasTable, symbol: usize) {
+        self.put_alias_at(0, table, symbol);
+    }
+
+    /// Encodes a block of bytes using the encoder symbols precomputed in
+    /// `table`, so that decoding yields them in the same order (i.e. the
+    /// bytes are put in reverse).
+    ///
+    /// The output is the same as putting the bytes one by one with
+    /// [`RansEncoder::put_index()`], but the symbol parameters are read from
+    /// the table's flat arrays and the encoder state is kept in registers
+    /// for the whole block, which is considerably faster for large blocks.
+    ///
+    /// # Panics
+    /// In debug builds, or with the `checked` feature enabled, panics if any
+    /// of the bytes has zero frequency, or if the buffer (unless growable)
+    /// doesn't have 2 bytes left for every byte of the block.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::byte_encoder::{ByteRansEncTable, ByteRansEncoder};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::{RansDecoder, RansEncoder, RansEncoderMulti};
+    ///
+    /// let data = b"abracadabra";
+    /// let model = Model::train(&[data], 12);
+    /// let table = ByteRansEncTable::new(&model);
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.encode_block(&table, data);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = ByteRansDecoder::new(encoder.data());
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    /// let decoded: Vec<u8> = (0..data.len())
+    ///     .map(|_| decoder.decode_symbol(&dec_model) as u8)
+    ///     .collect();
+    /// assert_eq!(decoded, data);
+    /// ```
+    pub fn encode_block(&mut self, table: &ByteRansEncTable, data: &[u8]) {
+        self.ensure_space(data.len() * MAX_PUT_LEN);
+
+        let mut x = self.states[0];
+        let mut ptr = self.ptr;
+        for &byte in data.iter().rev() {
+            let index = usize::from(byte);
+            let x_max = table.x_max[index];
+            checked_assert!(x_max != 0, "symbol has zero frequency");
+
+            // Same as `rans_enc_put_symbol()`
+            while x >= x_max {
+                unsafe {
+                    ptr = ptr.sub(1);
+                    *ptr = x as u8;
+                }
+                x >>= 8;
+            }
+            let q = ((u64::from(x) * u64::from(table.rcp_freq[index])) >> 32) as u32
+                >> table.rcp_shift[index];
+            x = x
+                .wrapping_add(table.bias[index])
+                .wrapping_add(q.wrapping_mul(u32::from(table.cmpl_freq[index])));
+        }
+        self.states[0] = x;
+        self.ptr = ptr;
+
+        check_enc_pointer!(self);
+    }
 }
 
-/// Single-stream rANS encoder - byte-aligned version.
-pub type ByteRansEncoder = ByteRansEncoderMulti<1>;
+/// Multi-stream interleaved rANS encoder - byte-aligned version writing into
+/// a buffer owned by the caller.
+///
+/// Unlike [`ByteRansEncoderMulti`], this encoder never allocates: running out
+/// of space is reported by [`RansEncoderMulti::try_put_at()`] and
+/// [`RansEncoderMulti::try_flush_at()`] as [`RansError::BufferOverflow`],
+/// while the non-fallible methods panic. This makes it suitable for embedded
+/// systems and arena-allocated buffers. The output is the same as the one of
+/// [`ByteRansEncoderMulti`] and can be decoded by
+/// [`ByteRansDecoderMulti`](crate::byte_decoder::ByteRansDecoderMulti).
+#[derive(Debug)]
+pub struct ByteRansSliceEncoderMulti<'a, const N: usize> {
+    states: [crate::backend::rans_byte::RansState; N],
+    dst: &'a mut [u8],
+    /// Index of the first byte of the encoded data in `dst`
+    pos: usize,
+    /// Length of the data when the current section started
+    section_start: usize,
+}
+
+/// Single-stream rANS encoder - byte-aligned version writing into a buffer
+/// owned by the caller.
+pub type ByteRansSliceEncoder<'a> = ByteRansSliceEncoderMulti<'a, 1>;
+
+impl<'a, const N: usize> ByteRansSliceEncoderMulti<'a, N> {
+    /// Creates a new `ByteRansSliceEncoderMulti` instance that writes the
+    /// encoded data into the end of given buffer. The previous contents of
+    /// the buffer are overwritten.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansSliceEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut buffer = [0; 16];
+    /// let mut encoder = ByteRansSliceEncoderMulti::<2>::new(&mut buffer);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0, 0, 0, 128, 0]);
+    /// ```
+    #[must_use]
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        debug_assert!(N > 0);
+
+        // The states get initialized by `reset()` below
+        let mut encoder = Self {
+            states: [0; N],
+            pos: buffer.len(),
+            dst: buffer,
+            section_start: 0,
+        };
+        encoder.reset();
+        encoder
+    }
+
+    /// Returns the number of bytes left in the buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansSliceEncoder;
+    /// use rans::RansEncoder;
+    ///
+    /// let mut buffer = [0; 16];
+    /// let mut encoder = ByteRansSliceEncoder::new(&mut buffer);
+    /// encoder.flush();
+    /// assert_eq!(encoder.remaining_len(), 12);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn remaining_len(&self) -> usize {
+        self.pos
+    }
+
+    #[inline]
+    fn with_ptr(&mut self, f: impl FnOnce(&mut u32, &mut *mut u8), channel: usize) {
+        unsafe {
+            let begin_ptr = self.dst.as_mut_ptr();
+            let mut ptr = begin_ptr.add(self.pos);
+            f(&mut self.states[channel], &mut ptr);
+            self.pos = ptr.offset_from(begin_ptr) as usize;
+        }
+    }
+}
+
+impl<const N: usize> RansEncoderMulti<N> for ByteRansSliceEncoderMulti<'_, N> {
+    type Symbol = ByteRansEncSymbol;
+
+    fn reset(&mut self) {
+        self.clear();
+        self.pos = self.dst.len();
+        self.section_start = 0;
+    }
+
+    fn clear(&mut self) {
+        for state in &mut self.states {
+            unsafe {
+                crate::backend::rans_byte::rans_enc_init(state);
+            }
+        }
+    }
+
+    #[inline]
+    fn put_at(&mut self, channel: usize, symbol: &Self::Symbol) {
+        assert!(self.pos >= MAX_PUT_LEN, "encoder buffer overflow");
+
+        self.with_ptr(
+            |state, ptr| unsafe {
+                crate::backend::rans_byte::rans_enc_put_symbol(state, ptr, &symbol.symbol);
+            },
+            channel,
+        );
+    }
+
+    fn try_put_at(&mut self, channel: usize, symbol: &Self::Symbol) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+        if self.pos < MAX_PUT_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.put_at(channel, symbol);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush_at(&mut self, channel: usize) {
+        assert!(self.pos >= FLUSH_LEN, "encoder buffer overflow");
+
+        self.with_ptr(
+            |state, ptr| unsafe {
+                crate::backend::rans_byte::rans_enc_flush(state, ptr);
+            },
+            channel,
+        );
+    }
+
+    fn try_flush_at(&mut self, channel: usize) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+        if self.pos < FLUSH_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.flush_at(channel);
+        Ok(())
+    }
+
+    fn flush_section(&mut self) -> Range<usize> {
+        self.flush_all();
+        self.clear();
+        let section = self.section_start..self.len();
+        self.section_start = self.len();
+        section
+    }
+
+    #[inline]
+    fn data(&self) -> &[u8] {
+        &self.dst[self.pos..]
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.dst.len()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        assert!(
+            self.pos >= additional,
+            "can't grow a buffer owned by the caller"
+        );
+    }
+}
+
+impl RansEncoder for ByteRansSliceEncoderMulti<'_, 1> {}
+
+/// Multi-stream interleaved rANS encoder - byte-aligned version with the
+/// number of channels chosen at runtime.
+///
+/// This is useful when the number of channels is only known when the
+/// program runs, e.g. because it's stored in the header of a file format.
+/// The methods mirror the ones of [`RansEncoderMulti`] and the output is the
+/// same as the one of [`ByteRansEncoderMulti`] with the same number of
+/// channels, so it can be decoded by either
+/// [`ByteRansDecoderMulti`](crate::byte_decoder::ByteRansDecoderMulti) or
+/// [`DynRansDecoderMulti`](crate::byte_decoder::DynRansDecoderMulti).
+#[derive(Debug)]
+pub struct DynRansEncoderMulti {
+    states: Vec<crate::backend::rans_byte::RansState>,
+    dst: Vec<u8>,
+    /// Index of the first byte of the encoded data in `dst`
+    pos: usize,
+}
+
+impl DynRansEncoderMulti {
+    /// Creates a new `DynRansEncoderMulti` instance with given number of
+    /// channels that can contain `max_len` bytes in the internal buffer.
+    ///
+    /// # Panics
+    /// Panics if `channels` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    ///
+    /// let encoder = DynRansEncoderMulti::new(3, 1024);
+    /// assert_eq!(encoder.channels(), 3);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(channels: usize, max_len: usize) -> Self {
+        assert!(channels > 0, "number of channels must be non-zero");
+
+        // The states get initialized by `reset()` below
+        let mut encoder = Self {
+            states: vec![0; channels],
+            dst: vec![0; max_len],
+            pos: max_len,
+        };
+        encoder.reset();
+        encoder
+    }
+
+    /// Returns the number of channels of this encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    ///
+    /// let encoder = DynRansEncoderMulti::new(4, 1024);
+    /// assert_eq!(encoder.channels(), 4);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn channels(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Clears the encoder's internal buffer and resets the states of all
+    /// channels. See [`RansEncoderMulti::reset()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    ///
+    /// let mut encoder = DynRansEncoderMulti::new(2, 1024);
+    /// encoder.flush_all();
+    /// encoder.reset();
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    pub fn reset(&mut self) {
+        self.clear();
+        self.pos = self.dst.len();
+    }
+
+    /// Resets the states of all channels, keeping the data encoded so far.
+    /// See [`RansEncoderMulti::clear()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    ///
+    /// let mut encoder = DynRansEncoderMulti::new(2, 1024);
+    /// encoder.flush_all();
+    /// encoder.clear();
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.len(), 16);
+    /// ```
+    pub fn clear(&mut self) {
+        for state in &mut self.states {
+            unsafe {
+                crate::backend::rans_byte::rans_enc_init(state);
+            }
+        }
+    }
+
+    /// Puts a symbol into given channel. See [`RansEncoderMulti::put_at()`].
+    ///
+    /// # Panics
+    /// Panics if `channel` is out of range or the symbol does not fit in the
+    /// buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, DynRansEncoderMulti};
+    /// use rans::RansEncSymbol;
+    ///
+    /// let mut encoder = DynRansEncoderMulti::new(2, 1024);
+    /// let symbol = ByteRansEncSymbol::new(2, 2, 2);
+    /// encoder.put_at(0, &symbol);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0, 2, 0, 0, 1]);
+    /// ```
+    #[inline]
+    pub fn put_at(&mut self, channel: usize, symbol: &ByteRansEncSymbol) {
+        assert!(self.pos >= MAX_PUT_LEN, "encoder buffer overflow");
+
+        self.with_ptr(
+            |state, ptr| unsafe {
+                crate::backend::rans_byte::rans_enc_put_symbol(state, ptr, &symbol.symbol);
+            },
+            channel,
+        );
+    }
+
+    /// Puts a symbol into given channel, checking that the channel index is
+    /// valid and that there is enough space left in the buffer. See
+    /// [`RansEncoderMulti::try_put_at()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidChannel`] if `channel` is out of range, or
+    /// [`RansError::BufferOverflow`] if the symbol does not fit in the buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, DynRansEncoderMulti};
+    /// use rans::{RansEncSymbol, RansError};
+    ///
+    /// let mut encoder = DynRansEncoderMulti::new(2, 1024);
+    /// let symbol = ByteRansEncSymbol::new(2, 2, 2);
+    /// assert_eq!(encoder.try_put_at(1, &symbol), Ok(()));
+    /// assert_eq!(encoder.try_put_at(2, &symbol), Err(RansError::InvalidChannel));
+    /// ```
+    pub fn try_put_at(
+        &mut self,
+        channel: usize,
+        symbol: &ByteRansEncSymbol,
+    ) -> Result<(), RansError> {
+        if channel >= self.channels() {
+            return Err(RansError::InvalidChannel);
+        }
+        if self.pos < MAX_PUT_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.put_at(channel, symbol);
+        Ok(())
+    }
+
+    /// Flushes the encoder's intermediate data at given channel into the
+    /// buffer. See [`RansEncoderMulti::flush_at()`].
+    ///
+    /// # Panics
+    /// Panics if `channel` is out of range or the data does not fit in the
+    /// buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    ///
+    /// let mut encoder = DynRansEncoderMulti::new(2, 1024);
+    /// encoder.flush_at(0);
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0]);
+    /// ```
+    #[inline]
+    pub fn flush_at(&mut self, channel: usize) {
+        assert!(self.pos >= FLUSH_LEN, "encoder buffer overflow");
+
+        self.with_ptr(
+            |state, ptr| unsafe {
+                crate::backend::rans_byte::rans_enc_flush(state, ptr);
+            },
+            channel,
+        );
+    }
 
-impl<const N: usize> ByteRansEncoderMulti<N> {
-    /// Creates a new `ByteMultiRansEncoder` instance that can contain `max_len`
-    /// bytes in the internal buffer.
+    /// Flushes the encoder's intermediate data at given channel into the
+    /// buffer, checking that the channel index is valid and that there is
+    /// enough space left in the buffer. See
+    /// [`RansEncoderMulti::try_flush_at()`].
     ///
-    /// Note that most of the API is inside the [`RansEncoderMulti`] trait, so
-    /// you probably want to `use rans::RansEncoderMulti`.
+    /// # Errors
+    /// Returns [`RansError::InvalidChannel`] if `channel` is out of range, or
+    /// [`RansError::BufferOverflow`] if the data does not fit in the buffer.
     ///
     /// # Examples
     /// ```
-    /// use rans::byte_encoder::ByteRansEncoderMulti;
-    /// use rans::RansEncoderMulti;
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    /// use rans::RansError;
     ///
-    /// let encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// let mut encoder = DynRansEncoderMulti::new(2, 6);
+    /// assert_eq!(encoder.try_flush_at(0), Ok(()));
+    /// assert_eq!(encoder.try_flush_at(1), Err(RansError::BufferOverflow));
+    /// ```
+    pub fn try_flush_at(&mut self, channel: usize) -> Result<(), RansError> {
+        if channel >= self.channels() {
+            return Err(RansError::InvalidChannel);
+        }
+        if self.pos < FLUSH_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.flush_at(channel);
+        Ok(())
+    }
+
+    /// Flushes the encoder's intermediate data at all channels into the
+    /// buffer. See [`RansEncoderMulti::flush_all()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    ///
+    /// let mut encoder = DynRansEncoderMulti::new(3, 1024);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.len(), 12);
+    /// ```
+    pub fn flush_all(&mut self) {
+        for i in 0..self.channels() {
+            self.flush_at(i);
+        }
+    }
+
+    /// Flushes the encoder's intermediate data at all channels into the
+    /// buffer, checking that there is enough space left in the buffer. See
+    /// [`RansEncoderMulti::try_flush_all()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::BufferOverflow`] if the data does not fit in the
+    /// buffer. The channels flushed before the error occurred stay flushed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    /// use rans::RansError;
+    ///
+    /// let mut encoder = DynRansEncoderMulti::new(3, 10);
+    /// assert_eq!(encoder.try_flush_all(), Err(RansError::BufferOverflow));
+    /// assert_eq!(encoder.len(), 8);
+    /// ```
+    pub fn try_flush_all(&mut self) -> Result<(), RansError> {
+        for i in 0..self.channels() {
+            self.try_flush_at(i)?;
+        }
+        Ok(())
+    }
+
+    /// Returns this encoder's internal buffer content.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    ///
+    /// let encoder = DynRansEncoderMulti::new(2, 1024);
     /// assert_eq!(encoder.data(), []);
     /// ```
     #[must_use]
-    pub fn new(max_len: usize) -> Self {
-        debug_assert!(N > 0);
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.dst[self.pos..]
+    }
 
-        unsafe {
-            #[allow(clippy::uninit_assumed_init)]
-            let states: [ryg_rans_sys::rans_byte::RansState; N] =
-                MaybeUninit::uninit().assume_init();
-            let dst = vec![0; max_len];
+    /// Returns this encoder's internal buffer current length in bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    ///
+    /// let encoder = DynRansEncoderMulti::new(2, 1024);
+    /// assert_eq!(encoder.len(), 0);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.dst.len() - self.pos
+    }
 
-            let mut encoder = Self {
-                states,
-                dst,
-                ptr: std::ptr::null_mut(),
-            };
-            encoder.reset();
-            encoder
-        }
+    /// Returns whether this encoder's internal buffer is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::DynRansEncoderMulti;
+    ///
+    /// let encoder = DynRansEncoderMulti::new(2, 1024);
+    /// assert!(encoder.is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     #[inline]
-    fn is_ptr_valid(&self) -> bool {
-        let range = self.dst.as_ptr_range();
-        let range_inclusive = range.start..=range.end;
-        range_inclusive.contains(&(self.ptr as *const u8))
+    fn with_ptr(&mut self, f: impl FnOnce(&mut u32, &mut *mut u8), channel: usize) {
+        let state = &mut self.states[channel];
+        unsafe {
+            let begin_ptr = self.dst.as_mut_ptr();
+            let mut ptr = begin_ptr.add(self.pos);
+            f(state, &mut ptr);
+            self.pos = ptr.offset_from(begin_ptr) as usize;
+        }
     }
 }
 
-impl<const N: usize> RansEncoderMulti<N> for ByteRansEncoderMulti<N> {
-    type Symbol = ByteRansEncSymbol;
+/// rANS encoder symbol - byte-aligned version.
+#[derive(Debug, Clone, Copy)]
+pub struct ByteRansEncSymbol {
+    symbol: crate::backend::rans_byte::RansEncSymbol,
+    scale_bits: u32,
+}
 
-    fn reset(&mut self) {
-        unsafe {
-            for state in &mut self.states {
-                ryg_rans_sys::rans_byte::rans_enc_init(state);
-            }
+impl ByteRansEncSymbol {
+    /// Creates a new symbol like [`RansEncSymbol::new()`], but can be used in
+    /// constant expressions, e.g. to build static tables of symbols for
+    /// fixed models at compile time.
+    ///
+    /// Only available with the `pure-rust` feature, as the C implementation
+    /// can't be called at compile time.
+    ///
+    /// # Panics
+    /// Panics if `scale_bits` is larger than 16. In a constant expression,
+    /// this is a compile-time error.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::RansEncSymbol;
+    ///
+    /// static SYMBOLS: [ByteRansEncSymbol; 2] = [
+    ///     ByteRansEncSymbol::new_const(0, 3, 2),
+    ///     ByteRansEncSymbol::new_const(3, 1, 2),
+    /// ];
+    /// assert_eq!(SYMBOLS[1], ByteRansEncSymbol::new(3, 1, 2));
+    /// ```
+    #[cfg(feature = "pure-rust")]
+    #[must_use]
+    pub const fn new_const(cum_freq: u32, freq: u32, scale_bits: u32) -> Self {
+        assert!(scale_bits <= 16, "scale_bits must not be larger than 16");
 
-            let mut ptr: *mut u8 = self.dst.as_mut_ptr();
-            ptr = ptr.add(self.dst.len());
-            self.ptr = ptr;
+        Self {
+            symbol: crate::backend::rans_byte::enc_symbol(cum_freq, freq, scale_bits),
+            scale_bits,
         }
     }
 
+    /// Returns the fields of the symbol, used to implement the comparison
+    /// traits (the backend symbol types don't implement them).
     #[inline]
-    fn put_at(&mut self, channel: usize, symbol: &Self::Symbol) {
-        debug_assert!(channel <= N);
+    fn key(&self) -> (u32, u32, u32, u16, u16, u32) {
+        let symbol = &self.symbol;
+        (
+            symbol.x_max,
+            symbol.rcp_freq,
+            symbol.bias,
+            symbol.cmpl_freq,
+            symbol.rcp_shift,
+            self.scale_bits,
+        )
+    }
+}
 
-        unsafe {
-            ryg_rans_sys::rans_byte::rans_enc_put_symbol(
-                &mut self.states[channel],
-                &mut self.ptr,
-                &symbol.symbol,
-            );
-        }
+impl PartialEq for ByteRansEncSymbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
 
-        check_enc_pointer!(self);
+impl Eq for ByteRansEncSymbol {}
+
+impl Hash for ByteRansEncSymbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
     }
+}
 
-    #[inline]
-    fn flush_at(&mut self, channel: usize) {
-        debug_assert!(channel <= N);
+/// Encoder symbols of all the bytes of a model, precomputed and stored as a
+/// structure of arrays, for [`ByteRansEncoder::encode_block()`].
+///
+/// Storing every parameter of the division-free encoding in a separate
+/// array indexed directly by the byte keeps the data touched by the encoder
+/// dense, instead of scattered over [`ByteRansEncSymbol`] structures, which
+/// matters for large alphabets.
+///
+/// # Examples
+/// ```
+/// use rans::byte_encoder::ByteRansEncTable;
+/// use rans::model::Model;
+///
+/// let table = ByteRansEncTable::new(&Model::from_counts(&[1, 0, 3], 8));
+/// assert_eq!(table.scale_bits(), 8);
+/// assert!(table.contains(2));
+/// assert!(!table.contains(1));
+/// assert!(!table.contains(255));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ByteRansEncTable {
+    x_max: [u32; 256],
+    rcp_freq: [u32; 256],
+    bias: [u32; 256],
+    cmpl_freq: [u16; 256],
+    rcp_shift: [u16; 256],
+    scale_bits: u32,
+}
 
-        unsafe {
-            ryg_rans_sys::rans_byte::rans_enc_flush(&mut self.states[channel], &mut self.ptr);
-        }
+impl ByteRansEncTable {
+    /// Creates a new table containing the encoder symbols of all the symbols
+    /// of `model`.
+    ///
+    /// # Panics
+    /// Panics if the model has more than 256 symbols, or if its `scale_bits`
+    /// is larger than 16.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = ByteRansEncTable::new(&Model::from_counts(&[1; 256], 12));
+    /// assert!((0..=255).all(|byte| table.contains(byte)));
+    /// ```
+    #[must_use]
+    pub fn new(model: &Model) -> Self {
+        assert!(
+            model.len() <= 256,
+            "model must not have more than 256 symbols"
+        );
+        assert_scale_bits(model.scale_bits(), ByteRansEncSymbol::MAX_SCALE_BITS);
 
-        check_enc_pointer!(self);
-    }
+        let mut table = Self {
+            x_max: [0; 256],
+            rcp_freq: [0; 256],
+            bias: [0; 256],
+            cmpl_freq: [0; 256],
+            rcp_shift: [0; 256],
+            scale_bits: model.scale_bits(),
+        };
+        for index in 0..model.len() {
+            let freq = model.freq(index);
+            if freq == 0 {
+                continue;
+            }
 
-    #[inline]
-    fn data(&self) -> &[u8] {
-        unsafe {
-            let begin_ptr = self.dst.as_ptr();
-            let start_index = self.ptr.offset_from(begin_ptr) as usize;
-            &self.dst[start_index..]
+            let symbol =
+                ByteRansEncSymbol::new(model.cum_freq(index), freq, model.scale_bits()).symbol;
+            table.x_max[index] = symbol.x_max;
+            table.rcp_freq[index] = symbol.rcp_freq;
+            table.bias[index] = symbol.bias;
+            table.cmpl_freq[index] = symbol.cmpl_freq;
+            table.rcp_shift[index] = symbol.rcp_shift;
         }
+        table
     }
-}
 
-impl RansEncoder for ByteRansEncoderMulti<1> {}
+    /// Returns the scale bits of the model the table was created from.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = ByteRansEncTable::new(&Model::from_counts(&[1, 1], 4));
+    /// assert_eq!(table.scale_bits(), 4);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
 
-/// rANS encoder symbol - byte-aligned version.
-#[derive(Debug, Clone)]
-pub struct ByteRansEncSymbol {
-    symbol: ryg_rans_sys::rans_byte::RansEncSymbol,
+    /// Returns whether `byte` can be encoded with this table, i.e. it has
+    /// non-zero frequency in the model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = ByteRansEncTable::new(&Model::from_counts(&[0, 1], 4));
+    /// assert!(!table.contains(0));
+    /// assert!(table.contains(1));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn contains(&self, byte: u8) -> bool {
+        self.x_max[usize::from(byte)] != 0
+    }
 }
 
 impl RansEncSymbol for ByteRansEncSymbol {
     #[inline]
     fn new(cum_freq: u32, freq: u32, scale_bits: u32) -> Self {
+        assert_scale_bits(scale_bits, Self::MAX_SCALE_BITS);
+
         unsafe {
             let mut symbol = MaybeUninit::uninit();
-            ryg_rans_sys::rans_byte::rans_enc_symbol_init(
+            crate::backend::rans_byte::rans_enc_symbol_init(
                 symbol.as_mut_ptr(),
                 cum_freq,
                 freq,
@@ -129,15 +1231,45 @@ impl RansEncSymbol for ByteRansEncSymbol {
 
             Self {
                 symbol: symbol.assume_init(),
+                scale_bits,
             }
         }
     }
+
+    #[inline]
+    fn cum_freq(&self) -> u32 {
+        // Symbols of frequency 1 have the bias adjusted, see
+        // `rans_enc_symbol_init()`
+        if self.freq() == 1 {
+            self.symbol.bias - ((1 << self.scale_bits) - 1)
+        } else {
+            self.symbol.bias
+        }
+    }
+
+    #[inline]
+    fn freq(&self) -> u32 {
+        (1 << self.scale_bits) - u32::from(self.symbol.cmpl_freq)
+    }
+
+    #[inline]
+    fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::byte_encoder::{ByteRansEncoder, ByteRansEncoderMulti};
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::byte_encoder::{
+        ByteRansEncSymbol, ByteRansEncTable, ByteRansEncoder, ByteRansEncoderMulti,
+        ByteRansSliceEncoder, ByteRansSliceEncoderMulti, DynRansEncoderMulti,
+    };
     use crate::encoder::tests as enc_tests;
+    use crate::model::{EncModel, Model};
+    use crate::{RansEncSymbol, RansEncoder, RansEncoderMulti, RansError};
 
     #[test]
     fn test_encode_nothing() {
@@ -163,6 +1295,15 @@ mod tests {
         enc_tests::test_encode_and_reset(encoder, &data1, &data2);
     }
 
+    #[test]
+    fn test_encode_and_clear() {
+        enc_tests::test_encode_and_clear(
+            ByteRansEncoder::new(1024),
+            ByteRansEncoder::new(1024),
+            ByteRansEncoder::new(1024),
+        );
+    }
+
     #[test]
     fn test_encode_two_symbols() {
         let encoder = ByteRansEncoder::new(1024);
@@ -197,9 +1338,237 @@ mod tests {
         enc_tests::encode_interleaved(encoder, &data);
     }
 
+    #[test]
+    fn test_encode_with_buffer() {
+        // Leftover garbage in the buffer must not matter
+        let mut buffer = vec![0xff; 1024];
+        buffer.truncate(10);
+        let encoder = ByteRansEncoderMulti::<2>::with_buffer(buffer);
+        let data = [12, 0, 128, 0, 0, 0, 128, 0, 24, 0];
+
+        let encoder = enc_tests::encode_interleaved(encoder, &data);
+        let buffer = encoder.into_buffer();
+        assert_eq!(buffer.len(), 10);
+        assert!(buffer.capacity() >= 1024);
+    }
+
+    #[test]
+    fn test_put_index() {
+        enc_tests::test_put_index(ByteRansEncoder::new(1024), ByteRansEncoder::new(1024));
+    }
+
+    #[test]
+    fn test_try_put_overflow() {
+        let encoder = ByteRansEncoder::new(16);
+
+        enc_tests::test_try_put_overflow(encoder);
+    }
+
+    #[test]
+    fn test_try_flush_overflow() {
+        let encoder = ByteRansEncoderMulti::<2>::new(6);
+
+        enc_tests::test_try_flush_overflow(encoder);
+    }
+
+    #[test]
+    fn test_encode_growable() {
+        let encoder = ByteRansEncoderMulti::<2>::with_growable_buffer();
+        let reference = ByteRansEncoderMulti::<2>::new(1 << 16);
+
+        enc_tests::test_encode_growable(encoder, reference);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let encoder = ByteRansEncoderMulti::<2>::new(16);
+        let reference = ByteRansEncoderMulti::<2>::new(1 << 16);
+
+        enc_tests::test_reserve(encoder, reference);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't grow a buffer owned by the caller")]
+    fn test_slice_reserve_too_much() {
+        let mut buffer = [0; 16];
+        let mut encoder = ByteRansSliceEncoderMulti::<2>::new(&mut buffer);
+        encoder.reserve(16);
+        encoder.reserve(17);
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let encoder = ByteRansEncoderMulti::<2>::with_growable_buffer();
+        let reference = ByteRansEncoderMulti::<2>::new(1 << 16);
+
+        enc_tests::test_checkpoint_restore(
+            encoder,
+            reference,
+            ByteRansEncoderMulti::checkpoint,
+            ByteRansEncoderMulti::restore,
+        );
+    }
+
+    #[test]
+    fn test_slice_encode_more_data() {
+        let mut buffer = [0; 1024];
+        let encoder = ByteRansSliceEncoder::new(&mut buffer);
+        let data = [
+            106, 184, 212, 0, 84, 205, 93, 162, 171, 34, 28, 50, 161, 66, 2,
+        ];
+
+        enc_tests::test_encode_more_data(encoder, &data);
+    }
+
+    #[test]
+    fn test_slice_encode_interleaved() {
+        let mut buffer = [0; 10];
+        let encoder = ByteRansSliceEncoderMulti::<2>::new(&mut buffer);
+        let data = [12, 0, 128, 0, 0, 0, 128, 0, 24, 0];
+
+        enc_tests::encode_interleaved(encoder, &data);
+    }
+
+    #[test]
+    fn test_slice_try_put_overflow() {
+        let mut buffer = [0; 16];
+        let encoder = ByteRansSliceEncoder::new(&mut buffer);
+
+        enc_tests::test_try_put_overflow(encoder);
+    }
+
+    #[test]
+    fn test_slice_try_flush_overflow() {
+        let mut buffer = [0; 6];
+        let encoder = ByteRansSliceEncoderMulti::<2>::new(&mut buffer);
+
+        enc_tests::test_try_flush_overflow(encoder);
+    }
+
+    #[test]
+    #[should_panic(expected = "encoder buffer overflow")]
+    fn test_slice_flush_overflow_panics() {
+        let mut buffer = [0; 3];
+        let mut encoder = ByteRansSliceEncoder::new(&mut buffer);
+
+        encoder.flush();
+    }
+
+    #[test]
+    fn test_dyn_encode_matches_static() {
+        const SCALE_BITS: u32 = 8;
+        let symbols: Vec<_> = (0..8)
+            .map(|i| ByteRansEncSymbol::new(i * 32, 32, SCALE_BITS))
+            .collect();
+
+        let mut encoder = DynRansEncoderMulti::new(4, 1024);
+        let mut reference = ByteRansEncoderMulti::<4>::new(1024);
+        for i in 0..100 {
+            encoder.put_at(i % 4, &symbols[i % 7]);
+            reference.put_at(i % 4, &symbols[i % 7]);
+        }
+        encoder.flush_all();
+        reference.flush_all();
+
+        assert_eq!(encoder.data(), reference.data());
+    }
+
+    #[test]
+    fn test_dyn_try_put_overflow() {
+        let mut encoder = DynRansEncoderMulti::new(1, 16);
+        let symbol = ByteRansEncSymbol::new(0, 1, 8);
+
+        let mut result = Ok(());
+        for _ in 0..1000 {
+            result = encoder.try_put_at(0, &symbol);
+            if result.is_err() {
+                break;
+            }
+        }
+        assert_eq!(result, Err(RansError::BufferOverflow));
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "checked"))]
+    #[should_panic(expected = "encoder buffer overflow")]
+    fn test_checked_flush_overflow() {
+        let mut encoder = ByteRansEncoder::new(2);
+        encoder.flush();
+    }
+
+    #[test]
+    fn test_symbol_try_new() {
+        assert!(ByteRansEncSymbol::try_new(0, 1 << 16, 16).is_ok());
+        assert!(ByteRansEncSymbol::try_new(65535, 1, 16).is_ok());
+        for (cum_freq, freq, scale_bits) in [(0, 0, 8), (255, 2, 8), (u32::MAX, 1, 8), (0, 1, 17)] {
+            assert_eq!(
+                ByteRansEncSymbol::try_new(cum_freq, freq, scale_bits).unwrap_err(),
+                RansError::InvalidSymbol
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_send_sync() {
+        enc_tests::test_is_send_sync::<ByteRansEncoderMulti<2>>();
+    }
+
+    #[test]
+    #[should_panic(expected = "scale_bits must not be larger than 16")]
+    fn test_symbol_scale_bits_too_large() {
+        let _ = ByteRansEncSymbol::new(0, 1, 17);
+    }
+
+    #[test]
+    fn test_symbol_accessors() {
+        enc_tests::test_symbol_accessors::<ByteRansEncSymbol>();
+    }
+
+    #[test]
+    fn test_symbol_eq_hash() {
+        enc_tests::test_symbol_eq_hash::<ByteRansEncSymbol>();
+    }
+
     #[test]
     fn test_has_debug_output() {
         let encoder = ByteRansEncoder::new(1024);
         enc_tests::test_has_debug_output(encoder);
     }
+
+    #[test]
+    fn test_encode_block() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let counts: Vec<u32> = (0..256).map(|_| rng.gen_range(0..100)).collect();
+        let model = Model::from_counts(&counts, 12);
+        let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+        let table = ByteRansEncTable::new(&model);
+        let data: Vec<u8> = (0..10000)
+            .map(|_| rng.gen())
+            .filter(|&byte| table.contains(byte))
+            .collect();
+
+        let mut by_symbol = ByteRansEncoder::with_growable_buffer();
+        by_symbol.put_index(usize::from(data[0]), &enc_model);
+        for &byte in data.iter().rev() {
+            by_symbol.put_index(usize::from(byte), &enc_model);
+        }
+        by_symbol.flush();
+
+        let mut by_block = ByteRansEncoder::with_growable_buffer();
+        by_block.put_index(usize::from(data[0]), &enc_model);
+        by_block.encode_block(&table, &data);
+        by_block.encode_block(&table, &[]);
+        by_block.flush();
+
+        assert_eq!(by_block.data(), by_symbol.data());
+    }
+
+    #[test]
+    fn test_enc_table_zero_freqs() {
+        let model = Model::from_counts(&[0, 5, 0, 1], 8);
+        let table = ByteRansEncTable::new(&model);
+
+        let present: Vec<u8> = (0..=255).filter(|&byte| table.contains(byte)).collect();
+        assert_eq!(present, [1, 3]);
+    }
 }
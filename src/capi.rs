@@ -0,0 +1,468 @@
+use std::ffi::c_char;
+use std::{ptr, slice};
+
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::{
+    simple, RansDecSymbol, RansDecoderMulti, RansEncSymbol, RansEncoder, RansEncoderMulti,
+    RansError,
+};
+
+/// Maximum number of bits used for frequency quantization supported by the
+/// encoder and the decoder of the C API.
+pub const RANS_MAX_SCALE_BITS: u32 = 16;
+
+/// Result of the fallible C API functions: [`RansStatus::Ok`] on success, or
+/// the [`RansError`] that occurred.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RansStatus {
+    /// The operation succeeded.
+    Ok = 0,
+    /// See [`RansError::EmptyInput`].
+    EmptyInput,
+    /// See [`RansError::BufferOverflow`].
+    BufferOverflow,
+    /// See [`RansError::InvalidChannel`].
+    InvalidChannel,
+    /// See [`RansError::UnexpectedEof`].
+    UnexpectedEof,
+    /// See [`RansError::InvalidHeader`].
+    InvalidHeader,
+    /// See [`RansError::InvalidModel`].
+    InvalidModel,
+    /// See [`RansError::ChecksumMismatch`].
+    ChecksumMismatch,
+    /// See [`RansError::InvalidSymbol`].
+    InvalidSymbol,
+    /// See [`RansError::VerificationFailed`].
+    VerificationFailed,
+    /// See [`RansError::InputExhausted`].
+    InputExhausted,
+    /// See [`RansError::InvalidMagic`].
+    InvalidMagic,
+    /// See [`RansError::UnsupportedVersion`].
+    UnsupportedVersion,
+    /// See [`RansError::ContentChecksumMismatch`].
+    ContentChecksumMismatch,
+}
+
+impl From<RansError> for RansStatus {
+    fn from(error: RansError) -> Self {
+        match error {
+            RansError::EmptyInput => Self::EmptyInput,
+            RansError::BufferOverflow => Self::BufferOverflow,
+            RansError::InvalidChannel => Self::InvalidChannel,
+            RansError::UnexpectedEof => Self::UnexpectedEof,
+            RansError::InvalidHeader => Self::InvalidHeader,
+            RansError::InvalidModel => Self::InvalidModel,
+            RansError::ChecksumMismatch => Self::ChecksumMismatch,
+            RansError::InvalidSymbol => Self::InvalidSymbol,
+            RansError::VerificationFailed => Self::VerificationFailed,
+            RansError::InputExhausted => Self::InputExhausted,
+            RansError::InvalidMagic => Self::InvalidMagic,
+            RansError::UnsupportedVersion => Self::UnsupportedVersion,
+            RansError::ContentChecksumMismatch => Self::ContentChecksumMismatch,
+        }
+    }
+}
+
+impl From<Result<(), RansError>> for RansStatus {
+    fn from(result: Result<(), RansError>) -> Self {
+        result.map_or_else(Self::from, |()| Self::Ok)
+    }
+}
+
+/// Opaque single-stream byte-aligned rANS encoder, created with
+/// [`rans_encoder_new()`] and destroyed with [`rans_encoder_free()`].
+#[derive(Debug)]
+pub struct RansEncoderHandle {
+    encoder: ByteRansEncoder,
+}
+
+/// Opaque single-stream byte-aligned rANS decoder, created with
+/// [`rans_decoder_new()`] and destroyed with [`rans_decoder_free()`].
+#[derive(Debug)]
+pub struct RansDecoderHandle {
+    decoder: ByteRansDecoder<'static>,
+}
+
+/// Returns a static, NUL-terminated description of given status.
+#[no_mangle]
+pub extern "C" fn rans_status_message(status: RansStatus) -> *const c_char {
+    let message: &[u8] = match status {
+        RansStatus::Ok => b"success\0",
+        RansStatus::EmptyInput => b"input data is empty\0",
+        RansStatus::BufferOverflow => b"not enough space in the output buffer\0",
+        RansStatus::InvalidChannel => b"channel index out of range\0",
+        RansStatus::UnexpectedEof => b"unexpected end of input\0",
+        RansStatus::InvalidHeader => b"invalid header\0",
+        RansStatus::InvalidModel => b"invalid frequency table\0",
+        RansStatus::ChecksumMismatch => b"checksum mismatch\0",
+        RansStatus::InvalidSymbol => b"invalid symbol definition\0",
+        RansStatus::VerificationFailed => b"verification of the encoded data failed\0",
+        RansStatus::InputExhausted => b"decoder input exhausted\0",
+        RansStatus::InvalidMagic => b"invalid magic bytes\0",
+        RansStatus::UnsupportedVersion => b"unsupported format version\0",
+        RansStatus::ContentChecksumMismatch => b"content checksum mismatch\0",
+    };
+    message.as_ptr().cast()
+}
+
+/// Creates a new encoder that can contain `max_len` bytes of encoded data.
+/// The returned encoder has to be destroyed with [`rans_encoder_free()`].
+#[no_mangle]
+pub extern "C" fn rans_encoder_new(max_len: usize) -> *mut RansEncoderHandle {
+    Box::into_raw(Box::new(RansEncoderHandle {
+        encoder: ByteRansEncoder::new(max_len),
+    }))
+}
+
+/// Destroys an encoder. Does nothing if `encoder` is null.
+///
+/// # Safety
+/// `encoder` must be null or a pointer returned by [`rans_encoder_new()`]
+/// that hasn't been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rans_encoder_free(encoder: *mut RansEncoderHandle) {
+    if !encoder.is_null() {
+        drop(Box::from_raw(encoder));
+    }
+}
+
+/// Discards all the data put into an encoder, so that it can be reused.
+///
+/// # Safety
+/// `encoder` must be a valid pointer returned by [`rans_encoder_new()`].
+#[no_mangle]
+pub unsafe extern "C" fn rans_encoder_reset(encoder: *mut RansEncoderHandle) {
+    (*encoder).encoder.reset();
+}
+
+/// Encodes a symbol with given cumulative frequency and frequency, quantized
+/// to `scale_bits` bits. As with any rANS encoder, the symbols have to be put
+/// in the reverse order of decoding.
+///
+/// Returns [`RansStatus::InvalidSymbol`] if the symbol definition is invalid
+/// (see [`RansError::InvalidSymbol`]), or [`RansStatus::BufferOverflow`] if
+/// the encoder is full.
+///
+/// # Safety
+/// `encoder` must be a valid pointer returned by [`rans_encoder_new()`].
+#[no_mangle]
+pub unsafe extern "C" fn rans_encoder_put(
+    encoder: *mut RansEncoderHandle,
+    cum_freq: u32,
+    freq: u32,
+    scale_bits: u32,
+) -> RansStatus {
+    ByteRansEncSymbol::try_new(cum_freq, freq, scale_bits)
+        .and_then(|symbol| (*encoder).encoder.try_put(&symbol))
+        .into()
+}
+
+/// Flushes an encoder, writing its final state into the data.
+///
+/// Returns [`RansStatus::BufferOverflow`] if the encoder is full.
+///
+/// # Safety
+/// `encoder` must be a valid pointer returned by [`rans_encoder_new()`].
+#[no_mangle]
+pub unsafe extern "C" fn rans_encoder_flush(encoder: *mut RansEncoderHandle) -> RansStatus {
+    (*encoder).encoder.try_flush().into()
+}
+
+/// Returns a pointer to the data encoded so far, storing its length in
+/// `*len`. The pointer stays valid until the encoder is modified or
+/// destroyed.
+///
+/// # Safety
+/// `encoder` must be a valid pointer returned by [`rans_encoder_new()`], and
+/// `len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rans_encoder_data(
+    encoder: *const RansEncoderHandle,
+    len: *mut usize,
+) -> *const u8 {
+    let data = (*encoder).encoder.data();
+    *len = data.len();
+    data.as_ptr()
+}
+
+/// Creates a new decoder of a copy of given data, storing it in `*decoder`.
+/// The decoder has to be destroyed with [`rans_decoder_free()`].
+///
+/// Returns [`RansStatus::EmptyInput`] if `len` is zero, or
+/// [`RansStatus::UnexpectedEof`] if the data is too short to contain the
+/// initial state.
+///
+/// # Safety
+/// `data` must be valid for reads of `len` bytes (or can be null if `len` is
+/// zero), and `decoder` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rans_decoder_new(
+    data: *const u8,
+    len: usize,
+    decoder: *mut *mut RansDecoderHandle,
+) -> RansStatus {
+    match ByteRansDecoder::try_new(input_slice(data, len).to_vec()) {
+        Ok(inner) => {
+            *decoder = Box::into_raw(Box::new(RansDecoderHandle { decoder: inner }));
+            RansStatus::Ok
+        }
+        Err(error) => error.into(),
+    }
+}
+
+/// Destroys a decoder. Does nothing if `decoder` is null.
+///
+/// # Safety
+/// `decoder` must be null or a pointer created by [`rans_decoder_new()`] that
+/// hasn't been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rans_decoder_free(decoder: *mut RansDecoderHandle) {
+    if !decoder.is_null() {
+        drop(Box::from_raw(decoder));
+    }
+}
+
+/// Stores the cumulative frequency of the next symbol, quantized to
+/// `scale_bits` bits, in `*cum_freq`. The symbol whose range contains it has
+/// to be passed to [`rans_decoder_advance()`] next.
+///
+/// Returns [`RansStatus::InvalidSymbol`] if `scale_bits` is larger than
+/// [`RANS_MAX_SCALE_BITS`].
+///
+/// # Safety
+/// `decoder` must be a valid pointer created by [`rans_decoder_new()`], and
+/// `cum_freq` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rans_decoder_get(
+    decoder: *mut RansDecoderHandle,
+    scale_bits: u32,
+    cum_freq: *mut u32,
+) -> RansStatus {
+    if scale_bits > RANS_MAX_SCALE_BITS {
+        return RansStatus::InvalidSymbol;
+    }
+
+    *cum_freq = (*decoder).decoder.get_at(0, scale_bits);
+    RansStatus::Ok
+}
+
+/// Advances a decoder past a symbol with given cumulative frequency and
+/// frequency, quantized to `scale_bits` bits.
+///
+/// Returns [`RansStatus::InvalidSymbol`] if the symbol definition is invalid
+/// (see [`RansError::InvalidSymbol`]), or [`RansStatus::InputExhausted`] if
+/// the data ended (see [`RansError::InputExhausted`]), in which case the
+/// decoder shouldn't be used anymore.
+///
+/// # Safety
+/// `decoder` must be a valid pointer created by [`rans_decoder_new()`].
+#[no_mangle]
+pub unsafe extern "C" fn rans_decoder_advance(
+    decoder: *mut RansDecoderHandle,
+    cum_freq: u32,
+    freq: u32,
+    scale_bits: u32,
+) -> RansStatus {
+    let symbol = match ByteRansDecSymbol::try_new(cum_freq, freq, scale_bits) {
+        Ok(symbol) => symbol,
+        Err(error) => return error.into(),
+    };
+
+    // Unlike `advance()`, this never reads past the end of the data, as the
+    // input can't be trusted to be well-formed
+    (*decoder)
+        .decoder
+        .try_advance_at(0, &symbol, scale_bits)
+        .into()
+}
+
+/// Compresses a byte buffer with [`simple::compress()`], storing the
+/// compressed data in `*output` and its length in `*output_len`. The output
+/// has to be freed with [`rans_buffer_free()`].
+///
+/// # Safety
+/// `data` must be valid for reads of `len` bytes (or can be null if `len` is
+/// zero), and `output` and `output_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rans_compress(
+    data: *const u8,
+    len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> RansStatus {
+    let compressed = simple::compress(input_slice(data, len));
+    (*output, *output_len) = into_raw_buffer(compressed);
+    RansStatus::Ok
+}
+
+/// Decompresses data produced by [`rans_compress()`], storing the original
+/// data in `*output` and its length in `*output_len`. The output has to be
+/// freed with [`rans_buffer_free()`]. Nothing is stored on error.
+///
+/// Returns any of the errors of [`simple::decompress()`].
+///
+/// # Safety
+/// `data` must be valid for reads of `len` bytes (or can be null if `len` is
+/// zero), and `output` and `output_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rans_decompress(
+    data: *const u8,
+    len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> RansStatus {
+    match simple::decompress(input_slice(data, len)) {
+        Ok(decompressed) => {
+            (*output, *output_len) = into_raw_buffer(decompressed);
+            RansStatus::Ok
+        }
+        Err(error) => error.into(),
+    }
+}
+
+/// Frees a buffer returned by [`rans_compress()`] or [`rans_decompress()`].
+/// Does nothing if `data` is null.
+///
+/// # Safety
+/// `data` and `len` must be a buffer and its length returned by
+/// [`rans_compress()`] or [`rans_decompress()`] that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rans_buffer_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+unsafe fn input_slice<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+fn into_raw_buffer(data: Vec<u8>) -> (*mut u8, usize) {
+    let len = data.len();
+    (Box::into_raw(data.into_boxed_slice()).cast(), len)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::{ptr, slice};
+
+    use crate::capi::{
+        rans_buffer_free, rans_compress, rans_decoder_advance, rans_decoder_free, rans_decoder_get,
+        rans_decoder_new, rans_decompress, rans_encoder_data, rans_encoder_flush,
+        rans_encoder_free, rans_encoder_new, rans_encoder_put, rans_status_message, RansStatus,
+    };
+
+    #[test]
+    fn test_encoder_decoder() {
+        unsafe {
+            let encoder = rans_encoder_new(1024);
+            assert_eq!(rans_encoder_put(encoder, 0, 3, 2), RansStatus::Ok);
+            assert_eq!(rans_encoder_put(encoder, 3, 1, 2), RansStatus::Ok);
+            assert_eq!(
+                rans_encoder_put(encoder, 3, 2, 2),
+                RansStatus::InvalidSymbol
+            );
+            assert_eq!(rans_encoder_flush(encoder), RansStatus::Ok);
+
+            let mut len = 0;
+            let data = rans_encoder_data(encoder, &mut len);
+            let mut decoder = ptr::null_mut();
+            assert_eq!(rans_decoder_new(data, len, &mut decoder), RansStatus::Ok);
+            rans_encoder_free(encoder);
+
+            let mut cum_freq = 0;
+            assert_eq!(rans_decoder_get(decoder, 2, &mut cum_freq), RansStatus::Ok);
+            assert_eq!(cum_freq, 3);
+            assert_eq!(rans_decoder_advance(decoder, 3, 1, 2), RansStatus::Ok);
+            assert_eq!(rans_decoder_get(decoder, 2, &mut cum_freq), RansStatus::Ok);
+            assert!(cum_freq < 3);
+            assert_eq!(rans_decoder_advance(decoder, 0, 3, 2), RansStatus::Ok);
+            assert_eq!(
+                rans_decoder_get(decoder, 17, &mut cum_freq),
+                RansStatus::InvalidSymbol
+            );
+            rans_decoder_free(decoder);
+        }
+    }
+
+    #[test]
+    fn test_decoder_past_end() {
+        unsafe {
+            let mut decoder = ptr::null_mut();
+            let data = [0, 0, 128, 0];
+            assert_eq!(
+                rans_decoder_new(data.as_ptr(), 3, &mut decoder),
+                RansStatus::UnexpectedEof
+            );
+            assert_eq!(
+                rans_decoder_new(ptr::null(), 0, &mut decoder),
+                RansStatus::EmptyInput
+            );
+
+            assert_eq!(
+                rans_decoder_new(data.as_ptr(), 4, &mut decoder),
+                RansStatus::Ok
+            );
+            assert_eq!(
+                rans_decoder_advance(decoder, 0, 1, 8),
+                RansStatus::InputExhausted
+            );
+            rans_decoder_free(decoder);
+        }
+    }
+
+    #[test]
+    fn test_compress_decompress() {
+        let data = b"abracadabra".repeat(100);
+
+        unsafe {
+            let mut compressed = ptr::null_mut();
+            let mut compressed_len = 0;
+            assert_eq!(
+                rans_compress(
+                    data.as_ptr(),
+                    data.len(),
+                    &mut compressed,
+                    &mut compressed_len
+                ),
+                RansStatus::Ok
+            );
+
+            let mut decompressed = ptr::null_mut();
+            let mut decompressed_len = 0;
+            assert_eq!(
+                rans_decompress(
+                    compressed,
+                    compressed_len,
+                    &mut decompressed,
+                    &mut decompressed_len
+                ),
+                RansStatus::Ok
+            );
+            assert_eq!(slice::from_raw_parts(decompressed, decompressed_len), data);
+
+            assert_eq!(
+                rans_decompress(compressed, 1, &mut decompressed, &mut decompressed_len),
+                RansStatus::UnexpectedEof
+            );
+
+            rans_buffer_free(compressed, compressed_len);
+            rans_buffer_free(decompressed, decompressed_len);
+        }
+    }
+
+    #[test]
+    fn test_status_message() {
+        let message = unsafe { CStr::from_ptr(rans_status_message(RansStatus::InvalidHeader)) };
+        assert_eq!(message.to_str().unwrap(), "invalid header");
+    }
+}
@@ -0,0 +1,199 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::model::{DecModel, EncModel, Model};
+use crate::{RansDecoder, RansEncoder, RansError};
+
+/// Encoder and decoder contexts built once from a single [`Model`].
+///
+/// A codec owns both the encoder symbols and the decoder lookup table of a
+/// model, so they can't get out of sync, and encodes whole sequences of
+/// symbols at once using the byte-aligned coder. The symbols are encoded in
+/// reverse internally, so they are decoded in their original order.
+///
+/// # Examples
+/// ```
+/// use rans::codec::RansCodec;
+/// use rans::model::Model;
+///
+/// let codec = RansCodec::new(&Model::from_counts(&[10, 3, 1, 50], 12));
+/// let symbols = [3, 3, 0, 1, 3, 2, 3, 3];
+///
+/// let encoded = codec.encode_symbols(&symbols);
+/// assert_eq!(codec.decode_symbols(&encoded, symbols.len()).unwrap(), symbols);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RansCodec {
+    model: Model,
+    enc_model: EncModel<ByteRansEncSymbol>,
+    dec_model: DecModel<ByteRansDecSymbol>,
+}
+
+impl RansCodec {
+    /// Creates a new codec for given model.
+    ///
+    /// # Panics
+    /// Panics if the model's `scale_bits` is larger than 16 or if it has more
+    /// than 65536 symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::codec::RansCodec;
+    /// use rans::model::Model;
+    ///
+    /// let codec = RansCodec::new(&Model::from_counts(&[1, 3], 8));
+    /// assert_eq!(codec.model().freqs(), [64, 192]);
+    /// ```
+    #[must_use]
+    pub fn new(model: &Model) -> Self {
+        assert!(
+            model.scale_bits() <= 16,
+            "scale_bits must not be larger than 16"
+        );
+        assert!(
+            model.len() <= 1 << 16,
+            "codec models must not have more than 65536 symbols"
+        );
+
+        Self {
+            model: model.clone(),
+            enc_model: EncModel::new(model),
+            dec_model: DecModel::new(model),
+        }
+    }
+
+    /// Returns the model this codec was built from.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::codec::RansCodec;
+    /// use rans::model::Model;
+    ///
+    /// let codec = RansCodec::new(&Model::from_counts(&[1, 3], 8));
+    /// assert_eq!(codec.model().scale_bits(), 8);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Encodes a sequence of symbol indices, returning the flushed encoder
+    /// output. The output doesn't contain the number of symbols; it has to be
+    /// passed to [`Self::decode_symbols()`] separately.
+    ///
+    /// # Panics
+    /// Panics if any of the symbols is out of the model's range or has zero
+    /// frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::codec::RansCodec;
+    /// use rans::model::Model;
+    ///
+    /// let codec = RansCodec::new(&Model::from_counts(&[1, 3], 2));
+    /// assert_eq!(codec.encode_symbols(&[]), [0, 0, 128, 0]);
+    /// assert_eq!(codec.encode_symbols(&[1]), [171, 170, 170, 0]);
+    /// ```
+    #[must_use]
+    pub fn encode_symbols(&self, symbols: &[u16]) -> Vec<u8> {
+        // With at most 16-bit frequencies, a single symbol emits at most
+        // 2 bytes.
+        let mut encoder = ByteRansEncoder::new(symbols.len() * 2 + 4);
+        for &symbol in symbols.iter().rev() {
+            let symbol = usize::from(symbol);
+            assert!(
+                symbol < self.model.len() && self.model.freq(symbol) > 0,
+                "symbol {symbol} can't be encoded with this model"
+            );
+            encoder.put_index(symbol, &self.enc_model);
+        }
+        encoder.flush();
+
+        encoder.into_buffer()
+    }
+
+    /// Decodes `n` symbol indices from data produced by
+    /// [`Self::encode_symbols()`].
+    ///
+    /// The rANS bitstream itself is trusted, so this should not be used for
+    /// data coming from untrusted sources.
+    ///
+    /// # Errors
+    /// Returns [`RansError::EmptyInput`] or [`RansError::UnexpectedEof`] if
+    /// `data` is too short to contain the encoder's flushed state.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::codec::RansCodec;
+    /// use rans::model::Model;
+    /// use rans::RansError;
+    ///
+    /// let codec = RansCodec::new(&Model::from_counts(&[1, 3], 2));
+    /// assert_eq!(codec.decode_symbols(&[171, 170, 170, 0], 1).unwrap(), [1]);
+    /// assert_eq!(codec.decode_symbols(&[171, 170], 1), Err(RansError::UnexpectedEof));
+    /// ```
+    pub fn decode_symbols(&self, data: &[u8], n: usize) -> Result<Vec<u16>, RansError> {
+        let mut decoder = ByteRansDecoder::try_new(data)?;
+        let mut symbols = Vec::with_capacity(n);
+        for _ in 0..n {
+            symbols.push(decoder.decode_symbol(&self.dec_model) as u16);
+        }
+
+        Ok(symbols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::RansCodec;
+    use crate::model::Model;
+
+    #[test]
+    fn test_round_trip_large_alphabet() {
+        let counts: Vec<u32> = (0..1000).map(|i| 1 + 1000 / (1 + i)).collect();
+        let codec = RansCodec::new(&Model::from_counts(&counts, 16));
+        let symbols: Vec<u16> = (0..10000_u32).map(|i| (i * i % 997) as u16).collect();
+
+        let encoded = codec.encode_symbols(&symbols);
+        assert_eq!(
+            codec.decode_symbols(&encoded, symbols.len()).unwrap(),
+            symbols
+        );
+    }
+
+    #[test]
+    fn test_round_trip_full_alphabet() {
+        let symbols: Vec<u16> = (0..=u16::MAX).rev().collect();
+        let codec = RansCodec::new(&Model::from_symbols(&symbols, 16));
+
+        let encoded = codec.encode_symbols(&symbols);
+        assert_eq!(
+            codec.decode_symbols(&encoded, symbols.len()).unwrap(),
+            symbols
+        );
+    }
+
+    #[test]
+    fn test_matches_model_estimate() {
+        let model = Model::from_counts(&[5, 1, 20], 12);
+        let codec = RansCodec::new(&model);
+        let symbols: Vec<u16> = (0..1000).map(|i| [0, 2, 2, 2, 1, 2][i % 6]).collect();
+
+        let mut counts = [0; 3];
+        for &symbol in &symbols {
+            counts[usize::from(symbol)] += 1;
+        }
+
+        let estimate = model.estimate_size(&counts) / 8.0;
+        let actual = codec.encode_symbols(&symbols).len() as f64;
+        assert!(estimate <= actual && actual <= estimate + 5.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "symbol 1 can't be encoded")]
+    fn test_zero_frequency_symbol() {
+        let codec = RansCodec::new(&Model::from_counts(&[1, 0], 4));
+
+        let _ = codec.encode_symbols(&[0, 1]);
+    }
+}
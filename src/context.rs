@@ -0,0 +1,870 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+
+use crate::decoder_input::DecoderInput;
+use crate::model::{DecModel, EncModel, Model};
+use crate::simple::scale_counts;
+use crate::{RansBackend, RansDecoderMulti, RansEncoderMulti, RansError};
+
+/// Chooses the model each symbol is coded with, based on arbitrary context
+/// such as the previously coded symbols or the position in the data.
+///
+/// [`ContextEncoder`] and [`ContextDecoder`] consult the selector in exactly
+/// the same way: for every symbol, in the order the symbols are decoded,
+/// [`Self::select()`] is called with the symbol's position, and then
+/// [`Self::update()`] is called with the symbol itself. As long as the
+/// selector is deterministic and both sides start with a selector in the
+/// same state, the encoder and the decoder always pick the same models.
+///
+/// Any `FnMut(usize) -> usize` closure is a selector choosing the model
+/// based on the position only.
+///
+/// # Examples
+/// ```
+/// use rans::context::ContextSelector;
+///
+/// /// Uses model 1 after a zero and model 0 otherwise.
+/// #[derive(Debug, Default)]
+/// struct AfterZero {
+///     last: usize,
+/// }
+///
+/// impl ContextSelector for AfterZero {
+///     fn select(&mut self, _position: usize) -> usize {
+///         usize::from(self.last == 0)
+///     }
+///
+///     fn update(&mut self, symbol: usize) {
+///         self.last = symbol;
+///     }
+/// }
+///
+/// let mut selector = AfterZero::default();
+/// assert_eq!(selector.select(0), 1);
+/// selector.update(3);
+/// assert_eq!(selector.select(1), 0);
+/// ```
+pub trait ContextSelector {
+    /// Returns the index of the model to code the symbol at given position
+    /// (counted from the first symbol of the data) with.
+    fn select(&mut self, position: usize) -> usize;
+
+    /// Informs the selector about the symbol coded with the model it has
+    /// just selected. Does nothing by default.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::ContextSelector;
+    ///
+    /// let mut selector = |position: usize| position % 2;
+    /// selector.update(42);
+    /// assert_eq!(selector.select(3), 1);
+    /// ```
+    #[inline]
+    fn update(&mut self, _symbol: usize) {}
+}
+
+impl<F: FnMut(usize) -> usize> ContextSelector for F {
+    #[inline]
+    fn select(&mut self, position: usize) -> usize {
+        self(position)
+    }
+}
+
+/// Selector using the previous symbol as the index of the model, i.e. an
+/// order-1 context model with a separate model for every symbol of the
+/// alphabet.
+///
+/// # Examples
+/// ```
+/// use rans::context::{ContextSelector, PreviousSymbol};
+///
+/// let mut selector = PreviousSymbol::new(0);
+/// assert_eq!(selector.select(0), 0);
+/// selector.update(5);
+/// assert_eq!(selector.select(1), 5);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PreviousSymbol {
+    previous: usize,
+}
+
+impl PreviousSymbol {
+    /// Creates a new selector, selecting `initial` for the first symbol.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::{ContextSelector, PreviousSymbol};
+    ///
+    /// let mut selector = PreviousSymbol::new(2);
+    /// assert_eq!(selector.select(0), 2);
+    /// ```
+    #[must_use]
+    pub fn new(initial: usize) -> Self {
+        Self { previous: initial }
+    }
+}
+
+impl ContextSelector for PreviousSymbol {
+    #[inline]
+    fn select(&mut self, _position: usize) -> usize {
+        self.previous
+    }
+
+    #[inline]
+    fn update(&mut self, symbol: usize) {
+        self.previous = symbol;
+    }
+}
+
+/// Marks a hash bucket without a materialized model, or the end of the list
+/// of models in [`HashedOrder2`].
+const NONE: u32 = u32::MAX;
+
+/// Selector of an order-2 context model, choosing the model by the previous
+/// two symbols.
+///
+/// A separate model for every pair of symbols would be far too many to
+/// transmit (or even to build), so the pairs are hashed into `1 << hash_bits`
+/// buckets, and only up to `max_models` of the buckets get a model of their
+/// own at any time. When a symbol's bucket has no model, the symbol is coded
+/// with the order-1 model of the previous symbol instead, and the bucket gets
+/// the model of the least recently used bucket, which falls back to order-1
+/// from then on.
+///
+/// The model indices are laid out as follows: the first `alphabet_len`
+/// models are the order-1 models, indexed by the previous symbol, and the
+/// next `max_models` ones are the order-2 models. The models can be built
+/// from the data with [`Self::build_models()`]. The previous two symbols of
+/// the first symbol are zeros.
+///
+/// # Examples
+/// ```
+/// use rans::context::{ContextDecoder, ContextEncoder, HashedOrder2};
+/// use rans::ByteBackend;
+///
+/// let data = b"the quick brown fox jumps over the lazy dog; the end";
+/// let symbols: Vec<usize> = data.iter().map(|&byte| usize::from(byte)).collect();
+/// let selector = HashedOrder2::new(256, 64, 12);
+/// let models = selector.build_models(&symbols, 12);
+/// assert_eq!(models.len(), selector.num_models());
+///
+/// let mut encoder = ContextEncoder::<ByteBackend, _, 1>::new(&models, selector.clone());
+/// for &symbol in &symbols {
+///     encoder.put(symbol);
+/// }
+/// encoder.flush();
+///
+/// let mut decoder = ContextDecoder::<ByteBackend, _, 1>::new(encoder.data(), &models, selector);
+/// for &symbol in &symbols {
+///     assert_eq!(decoder.decode(), symbol);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashedOrder2 {
+    alphabet_len: usize,
+    max_models: usize,
+    hash_bits: u32,
+    /// Order-2 model of every bucket, or `NONE`
+    bucket_models: Vec<u32>,
+    /// Bucket owning every materialized order-2 model
+    model_buckets: Vec<u32>,
+    /// Links of the list of the order-2 models, from the most to the least
+    /// recently used one
+    newer: Vec<u32>,
+    older: Vec<u32>,
+    newest: u32,
+    oldest: u32,
+    history: [usize; 2],
+}
+
+impl HashedOrder2 {
+    /// Creates a new selector for symbols in `0..alphabet_len`, hashing the
+    /// pairs of symbols into `1 << hash_bits` buckets and keeping up to
+    /// `max_models` order-2 models.
+    ///
+    /// # Panics
+    /// Panics if `alphabet_len` is zero, or if `hash_bits` is not in
+    /// `1..=24`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::HashedOrder2;
+    ///
+    /// let selector = HashedOrder2::new(256, 1024, 16);
+    /// assert_eq!(selector.num_models(), 256 + 1024);
+    /// ```
+    #[must_use]
+    pub fn new(alphabet_len: usize, max_models: usize, hash_bits: u32) -> Self {
+        assert!(alphabet_len > 0, "alphabet must not be empty");
+        assert!(
+            (1..=24).contains(&hash_bits),
+            "hash_bits must be between 1 and 24"
+        );
+
+        Self {
+            alphabet_len,
+            max_models,
+            hash_bits,
+            bucket_models: vec![NONE; 1 << hash_bits],
+            model_buckets: Vec::with_capacity(max_models),
+            newer: Vec::with_capacity(max_models),
+            older: Vec::with_capacity(max_models),
+            newest: NONE,
+            oldest: NONE,
+            history: [0; 2],
+        }
+    }
+
+    /// Returns the total number of models the selector chooses from, i.e.
+    /// `alphabet_len + max_models`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::HashedOrder2;
+    ///
+    /// assert_eq!(HashedOrder2::new(4, 8, 10).num_models(), 12);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn num_models(&self) -> usize {
+        self.alphabet_len + self.max_models
+    }
+
+    /// Builds the models for coding given symbols with this selector, by
+    /// running a copy of the selector over the symbols and counting the
+    /// symbols coded with every model. Models which are never selected are
+    /// uniform.
+    ///
+    /// # Panics
+    /// Panics if any of the symbols is not smaller than `alphabet_len`, or if
+    /// `alphabet_len` is larger than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::HashedOrder2;
+    ///
+    /// let models = HashedOrder2::new(3, 4, 8).build_models(&[1, 2, 1, 2, 1, 2], 8);
+    /// assert_eq!(models.len(), 7);
+    /// // The first symbol is coded with the order-1 model of symbol 0
+    /// assert_eq!(models[0].freqs(), [0, 256, 0]);
+    /// ```
+    #[must_use]
+    pub fn build_models(&self, symbols: &[usize], scale_bits: u32) -> Vec<Model> {
+        let mut selector = self.clone();
+        let mut counts = vec![vec![0_u64; self.alphabet_len]; self.num_models()];
+        for (position, &symbol) in symbols.iter().enumerate() {
+            let index = selector.select(position);
+            selector.update(symbol);
+            counts[index][symbol] += 1;
+        }
+
+        let uniform = vec![1; self.alphabet_len];
+        counts
+            .iter()
+            .map(|counts| {
+                if counts.iter().all(|&count| count == 0) {
+                    Model::from_counts(&uniform, scale_bits)
+                } else {
+                    Model::from_counts(&scale_counts(counts), scale_bits)
+                }
+            })
+            .collect()
+    }
+
+    fn bucket(&self) -> usize {
+        let [previous, before_previous] = self.history;
+        let pair = (before_previous as u64) << 32 ^ previous as u64;
+        (pair.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (64 - self.hash_bits)) as usize
+    }
+
+    /// Removes given model from the list of the order-2 models.
+    fn unlink(&mut self, model: u32) {
+        let (newer, older) = (self.newer[model as usize], self.older[model as usize]);
+        match newer {
+            NONE => self.newest = older,
+            newer => self.older[newer as usize] = older,
+        }
+        match older {
+            NONE => self.oldest = newer,
+            older => self.newer[older as usize] = newer,
+        }
+    }
+
+    /// Puts given model at the front of the list of the order-2 models.
+    fn push_newest(&mut self, model: u32) {
+        self.newer[model as usize] = NONE;
+        self.older[model as usize] = self.newest;
+        match self.newest {
+            NONE => self.oldest = model,
+            newest => self.newer[newest as usize] = model,
+        }
+        self.newest = model;
+    }
+}
+
+impl ContextSelector for HashedOrder2 {
+    fn select(&mut self, _position: usize) -> usize {
+        let bucket = self.bucket();
+        let model = self.bucket_models[bucket];
+        if model != NONE {
+            self.unlink(model);
+            self.push_newest(model);
+            return self.alphabet_len + model as usize;
+        }
+
+        if self.model_buckets.len() < self.max_models {
+            let model = self.model_buckets.len() as u32;
+            self.model_buckets.push(bucket as u32);
+            self.newer.push(NONE);
+            self.older.push(NONE);
+            self.bucket_models[bucket] = model;
+            self.push_newest(model);
+        } else if self.max_models > 0 {
+            let model = self.oldest;
+            self.unlink(model);
+            self.bucket_models[self.model_buckets[model as usize] as usize] = NONE;
+            self.model_buckets[model as usize] = bucket as u32;
+            self.bucket_models[bucket] = model;
+            self.push_newest(model);
+        }
+        self.history[0]
+    }
+
+    fn update(&mut self, symbol: usize) {
+        assert!(
+            symbol < self.alphabet_len,
+            "symbol {symbol} out of range of the alphabet"
+        );
+        self.history = [symbol, self.history[0]];
+    }
+}
+
+/// Encoder coding every symbol with one of several models, chosen by a
+/// [`ContextSelector`].
+///
+/// The symbol at position `i` is coded in channel `i % N`, so that the
+/// decoder can use its channels in a round-robin fashion. Since rANS decodes
+/// the symbols in the reverse order of encoding, while the selector has to
+/// see them in the decoding order, the symbols are buffered and only get
+/// encoded when [`Self::flush()`] is called.
+///
+/// # Examples
+/// ```
+/// use rans::context::{ContextDecoder, ContextEncoder, PreviousSymbol};
+/// use rans::model::Model;
+/// use rans::ByteBackend;
+///
+/// // A symbol is likely to be followed by the same one
+/// let models = [
+///     Model::from_counts(&[10, 1, 1], 12),
+///     Model::from_counts(&[1, 10, 1], 12),
+///     Model::from_counts(&[1, 1, 10], 12),
+/// ];
+/// let symbols = [0, 0, 0, 1, 1, 1, 1, 2, 2, 0];
+///
+/// let mut encoder = ContextEncoder::<ByteBackend, _, 2>::new(&models, PreviousSymbol::new(0));
+/// for &symbol in &symbols {
+///     encoder.put(symbol);
+/// }
+/// encoder.flush();
+///
+/// let mut decoder =
+///     ContextDecoder::<ByteBackend, _, 2>::new(encoder.data(), &models, PreviousSymbol::new(0));
+/// for &symbol in &symbols {
+///     assert_eq!(decoder.decode(), symbol);
+/// }
+/// ```
+pub struct ContextEncoder<B: RansBackend, S, const N: usize> {
+    encoder: B::Encoder<N>,
+    models: Vec<Model>,
+    enc_models: Vec<EncModel<B::EncSymbol>>,
+    selector: S,
+    symbols: Vec<usize>,
+}
+
+impl<B: RansBackend, S: ContextSelector, const N: usize> ContextEncoder<B, S, N> {
+    /// Creates a new `ContextEncoder` instance choosing from given models
+    /// with given selector. The decoder has to be created with the same
+    /// models and a selector in the same state.
+    ///
+    /// # Panics
+    /// Panics if `models` is empty, or if any of the models' `scale_bits` is
+    /// larger than the backend's [`RansBackend::MAX_SCALE_BITS`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::ContextEncoder;
+    /// use rans::model::Model;
+    /// use rans::B64Backend;
+    ///
+    /// let models = [Model::from_counts(&[1, 3], 20)];
+    /// let encoder = ContextEncoder::<B64Backend, _, 4>::new(&models, |_| 0);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(models: &[Model], selector: S) -> Self {
+        assert!(!models.is_empty(), "at least one model is required");
+
+        Self {
+            encoder: B::new_encoder(0),
+            models: models.to_vec(),
+            enc_models: models.iter().map(EncModel::new).collect(),
+            selector,
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Adds `symbol` to the data to be encoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::ContextEncoder;
+    /// use rans::model::Model;
+    /// use rans::ByteBackend;
+    ///
+    /// let models = [Model::from_counts(&[1, 3], 12)];
+    /// let mut encoder = ContextEncoder::<ByteBackend, _, 1>::new(&models, |_| 0);
+    /// encoder.put(1);
+    /// ```
+    #[inline]
+    pub fn put(&mut self, symbol: usize) {
+        self.symbols.push(symbol);
+    }
+
+    /// Encodes all the symbols added so far and flushes all the channels.
+    /// After this, [`Self::data()`] returns the complete encoded data.
+    ///
+    /// # Panics
+    /// Panics if the selector returns an index out of range of the models,
+    /// or if any of the symbols can't be encoded with the selected model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::ContextEncoder;
+    /// use rans::model::Model;
+    /// use rans::ByteBackend;
+    ///
+    /// let models = [Model::from_counts(&[1, 3], 12)];
+    /// let mut encoder = ContextEncoder::<ByteBackend, _, 2>::new(&models, |_| 0);
+    /// encoder.put(1);
+    /// encoder.flush();
+    /// assert_eq!(encoder.data().len(), 8);
+    /// ```
+    pub fn flush(&mut self) {
+        // Run the selector forward to find out the models the decoder will
+        // use, then encode the symbols backwards
+        let mut selected = Vec::with_capacity(self.symbols.len());
+        for (position, &symbol) in self.symbols.iter().enumerate() {
+            let index = self.selector.select(position);
+            let model = self
+                .models
+                .get(index)
+                .expect("context selector returned a model index out of range");
+            assert!(
+                symbol < model.len() && model.freq(symbol) > 0,
+                "symbol {symbol} can't be encoded with model {index}"
+            );
+            selected.push(index);
+            self.selector.update(symbol);
+        }
+
+        self.encoder
+            .reserve(B::max_encoded_len::<N>(self.symbols.len()));
+        for (position, (&symbol, &index)) in self.symbols.iter().zip(&selected).enumerate().rev() {
+            // Encoder channel `N - 1 - i` ends up as decoder channel `i`
+            let channel = N - 1 - position % N;
+            self.encoder
+                .put_index_at(channel, symbol, &self.enc_models[index]);
+        }
+        self.encoder.flush_all();
+        self.symbols.clear();
+    }
+
+    /// Returns the encoded data. This is empty until [`Self::flush()`] is
+    /// called.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::ContextEncoder;
+    /// use rans::model::Model;
+    /// use rans::ByteBackend;
+    ///
+    /// let models = [Model::from_counts(&[1, 3], 12)];
+    /// let mut encoder = ContextEncoder::<ByteBackend, _, 1>::new(&models, |_| 0);
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        self.encoder.data()
+    }
+
+    /// Returns a reference to the selector.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::{ContextEncoder, PreviousSymbol};
+    /// use rans::model::Model;
+    /// use rans::ByteBackend;
+    ///
+    /// let models = [Model::from_counts(&[1, 3], 12)];
+    /// let encoder = ContextEncoder::<ByteBackend, _, 1>::new(&models, PreviousSymbol::new(0));
+    /// assert_eq!(*encoder.selector(), PreviousSymbol::new(0));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn selector(&self) -> &S {
+        &self.selector
+    }
+}
+
+impl<B: RansBackend, S: Debug, const N: usize> Debug for ContextEncoder<B, S, N>
+where
+    B::Encoder<N>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextEncoder")
+            .field("encoder", &self.encoder)
+            .field("models", &self.models.len())
+            .field("selector", &self.selector)
+            .field("symbols", &self.symbols.len())
+            .finish()
+    }
+}
+
+/// Decoder decoding symbols encoded by a [`ContextEncoder`].
+///
+/// See [`ContextEncoder`] for an example.
+pub struct ContextDecoder<'a, B: RansBackend, S, const N: usize> {
+    decoder: B::Decoder<'a, N>,
+    dec_models: Vec<DecModel<B::DecSymbol>>,
+    selector: S,
+    position: usize,
+}
+
+impl<'a, B: RansBackend, S: ContextSelector, const N: usize> ContextDecoder<'a, B, S, N> {
+    /// Creates a new `ContextDecoder` instance with given `data`, choosing
+    /// from given models with given selector, which have to be the same as
+    /// the ones passed to the encoder.
+    ///
+    /// # Panics
+    /// Panics if `models` is empty, or if any of the models' `scale_bits` is
+    /// larger than the backend's [`RansBackend::MAX_SCALE_BITS`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::ContextDecoder;
+    /// use rans::model::Model;
+    /// use rans::ByteBackend;
+    ///
+    /// let models = [Model::from_counts(&[1, 3], 12)];
+    /// let decoder = ContextDecoder::<ByteBackend, _, 1>::new([0, 0, 128, 0], &models, |_| 0);
+    /// ```
+    #[must_use]
+    pub fn new<T: Into<DecoderInput<'a>>>(data: T, models: &[Model], selector: S) -> Self {
+        Self::with_decoder(B::new_decoder(data), models, selector)
+    }
+
+    /// Creates a new `ContextDecoder` instance like [`Self::new()`],
+    /// checking that the data is long enough to contain the initial states.
+    ///
+    /// # Errors
+    /// Returns [`RansError::EmptyInput`] if `data` is empty, or
+    /// [`RansError::UnexpectedEof`] if it is too short to contain the initial
+    /// states.
+    ///
+    /// # Panics
+    /// Panics if `models` is empty, or if any of the models' `scale_bits` is
+    /// larger than the backend's [`RansBackend::MAX_SCALE_BITS`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::ContextDecoder;
+    /// use rans::model::Model;
+    /// use rans::{ByteBackend, RansError};
+    ///
+    /// let models = [Model::from_counts(&[1, 3], 12)];
+    /// let result = ContextDecoder::<ByteBackend, _, 1>::try_new([0, 0], &models, |_| 0);
+    /// assert_eq!(result.err(), Some(RansError::UnexpectedEof));
+    /// ```
+    pub fn try_new<T: Into<DecoderInput<'a>>>(
+        data: T,
+        models: &[Model],
+        selector: S,
+    ) -> Result<Self, RansError> {
+        Ok(Self::with_decoder(
+            B::try_new_decoder(data)?,
+            models,
+            selector,
+        ))
+    }
+
+    fn with_decoder(decoder: B::Decoder<'a, N>, models: &[Model], selector: S) -> Self {
+        assert!(!models.is_empty(), "at least one model is required");
+
+        Self {
+            decoder,
+            dec_models: models.iter().map(DecModel::new).collect(),
+            selector,
+            position: 0,
+        }
+    }
+
+    /// Decodes a single symbol with the model chosen by the selector, and
+    /// updates the selector.
+    ///
+    /// # Panics
+    /// Panics if the selector returns an index out of range of the models.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::{ContextDecoder, ContextEncoder};
+    /// use rans::model::Model;
+    /// use rans::ByteBackend;
+    ///
+    /// let models = [Model::from_counts(&[1, 3], 12), Model::from_counts(&[3, 1], 12)];
+    /// let selector = |position: usize| position % 2;
+    ///
+    /// let mut encoder = ContextEncoder::<ByteBackend, _, 1>::new(&models, selector);
+    /// encoder.put(1);
+    /// encoder.put(0);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = ContextDecoder::<ByteBackend, _, 1>::new(encoder.data(), &models, selector);
+    /// assert_eq!(decoder.decode(), 1);
+    /// assert_eq!(decoder.decode(), 0);
+    /// ```
+    pub fn decode(&mut self) -> usize {
+        let index = self.selector.select(self.position);
+        let model = self
+            .dec_models
+            .get(index)
+            .expect("context selector returned a model index out of range");
+        let symbol = self.decoder.decode_symbol_at(self.position % N, model);
+
+        self.selector.update(symbol);
+        self.position += 1;
+        symbol
+    }
+
+    /// Returns a reference to the selector.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::context::{ContextDecoder, PreviousSymbol};
+    /// use rans::model::Model;
+    /// use rans::ByteBackend;
+    ///
+    /// let models = [Model::from_counts(&[1, 3], 12)];
+    /// let decoder =
+    ///     ContextDecoder::<ByteBackend, _, 1>::new([0, 0, 128, 0], &models, PreviousSymbol::new(0));
+    /// assert_eq!(*decoder.selector(), PreviousSymbol::new(0));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn selector(&self) -> &S {
+        &self.selector
+    }
+}
+
+impl<'a, B: RansBackend, S: Debug, const N: usize> Debug for ContextDecoder<'a, B, S, N>
+where
+    B::Decoder<'a, N>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextDecoder")
+            .field("decoder", &self.decoder)
+            .field("models", &self.dec_models.len())
+            .field("selector", &self.selector)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::context::{
+        ContextDecoder, ContextEncoder, ContextSelector, HashedOrder2, PreviousSymbol,
+    };
+    use crate::model::Model;
+    use crate::{B64Backend, ByteBackend, RansBackend};
+
+    /// Alternates between the models of two interleaved planes, choosing the
+    /// model of each plane by its previous symbol.
+    #[derive(Debug, Clone, Default)]
+    struct Planes {
+        previous: [usize; 2],
+        position: usize,
+    }
+
+    impl ContextSelector for Planes {
+        fn select(&mut self, position: usize) -> usize {
+            self.position = position;
+            (position % 2) * 2 + usize::from(self.previous[position % 2] > 0)
+        }
+
+        fn update(&mut self, symbol: usize) {
+            self.previous[self.position % 2] = symbol;
+        }
+    }
+
+    fn round_trip<B: RansBackend, S: ContextSelector + Clone, const N: usize>(
+        models: &[Model],
+        selector: S,
+        symbols: &[usize],
+    ) -> usize {
+        let mut encoder = ContextEncoder::<B, _, N>::new(models, selector.clone());
+        for &symbol in symbols {
+            encoder.put(symbol);
+        }
+        encoder.flush();
+
+        let mut decoder = ContextDecoder::<B, _, N>::new(encoder.data(), models, selector);
+        for &symbol in symbols {
+            assert_eq!(decoder.decode(), symbol);
+        }
+
+        encoder.data().len()
+    }
+
+    #[test]
+    fn test_previous_symbol() {
+        let models: Vec<Model> = (0..4)
+            .map(|i| {
+                let mut counts = [1; 4];
+                counts[(i + 1) % 4] = 100;
+                Model::from_counts(&counts, 12)
+            })
+            .collect();
+        // Every symbol is predictable from the previous one
+        let symbols: Vec<usize> = (0..10000).map(|i| i % 4).collect();
+
+        let len = round_trip::<ByteBackend, _, 1>(&models, PreviousSymbol::new(3), &symbols);
+        assert!(len < 10000 / 8);
+        round_trip::<B64Backend, _, 4>(&models, PreviousSymbol::new(3), &symbols);
+    }
+
+    #[test]
+    fn test_planes() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let models = [
+            Model::from_counts(&[5, 1, 1], 12),
+            Model::from_counts(&[1, 5, 5], 12),
+            Model::from_counts(&[1, 2], 8),
+            Model::from_counts(&[2, 1], 8),
+        ];
+        let symbols: Vec<usize> = (0..5000)
+            .map(|i| {
+                if i % 2 == 0 {
+                    rng.gen_range(0..3)
+                } else {
+                    rng.gen_range(0..2)
+                }
+            })
+            .collect();
+
+        round_trip::<ByteBackend, _, 2>(&models, Planes::default(), &symbols);
+        round_trip::<B64Backend, _, 3>(&models, Planes::default(), &symbols);
+    }
+
+    /// Text made of random words from a small vocabulary, in which the next
+    /// letter depends on the two previous ones much more than on the
+    /// previous one only.
+    fn words(len: usize) -> Vec<usize> {
+        const WORDS: [&str; 8] = [
+            "then ", "than ", "that ", "this ", "thin ", "hen ", "tan ", "hat ",
+        ];
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut symbols = Vec::with_capacity(len);
+        while symbols.len() < len {
+            let word = WORDS[rng.gen_range(0..WORDS.len())];
+            symbols.extend(word.bytes().map(usize::from));
+        }
+        symbols.truncate(len);
+        symbols
+    }
+
+    #[test]
+    fn test_hashed_order2_beats_order1() {
+        let symbols = words(20000);
+
+        let mut counts = vec![vec![0; 256]; 256];
+        let mut previous = 0;
+        for &symbol in &symbols {
+            counts[previous][symbol] += 1;
+            previous = symbol;
+        }
+        let order1_models: Vec<Model> = counts
+            .iter()
+            .map(|counts| {
+                if counts.iter().all(|&count| count == 0) {
+                    Model::from_counts(&[1; 256], 12)
+                } else {
+                    Model::from_counts(counts, 12)
+                }
+            })
+            .collect();
+        let order1_len =
+            round_trip::<ByteBackend, _, 1>(&order1_models, PreviousSymbol::new(0), &symbols);
+
+        let selector = HashedOrder2::new(256, 64, 12);
+        let models = selector.build_models(&symbols, 12);
+        let order2_len = round_trip::<ByteBackend, _, 2>(&models, selector, &symbols);
+        assert!(order2_len < order1_len * 3 / 4);
+    }
+
+    #[test]
+    fn test_hashed_order2_eviction() {
+        let mut selector = HashedOrder2::new(4, 2, 8);
+        // Every pair is new, so the order-1 models are used, while the
+        // buckets of [0, 0] and [0, 1] get the order-2 models 0 and 1
+        assert_eq!(selector.select(0), 0);
+        selector.update(1);
+        assert_eq!(selector.select(1), 1);
+        selector.update(0);
+        // [1, 0] takes the model of the least recently used bucket, [0, 0]
+        assert_eq!(selector.select(2), 0);
+        selector.update(1);
+        // [0, 1] still has its model...
+        assert_eq!(selector.select(3), 4 + 1);
+        selector.update(0);
+        // ...and so does [1, 0], but [0, 0] has lost it
+        assert_eq!(selector.select(4), 4);
+        selector.update(0);
+        assert_eq!(selector.select(5), 0);
+    }
+
+    #[test]
+    fn test_hashed_order2_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        let symbols: Vec<usize> = (0..5000).map(|_| rng.gen_range(0..20)).collect();
+
+        for max_models in [0, 1, 7, 1000] {
+            let selector = HashedOrder2::new(20, max_models, 6);
+            let models = selector.build_models(&symbols, 10);
+            round_trip::<ByteBackend, _, 1>(&models, selector.clone(), &symbols);
+            round_trip::<B64Backend, _, 4>(&models, selector, &symbols);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "symbol 3 out of range of the alphabet")]
+    fn test_hashed_order2_symbol_out_of_range() {
+        let mut selector = HashedOrder2::new(3, 4, 8);
+        selector.update(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "context selector returned a model index out of range")]
+    fn test_selector_out_of_range() {
+        let models = [Model::from_counts(&[1, 3], 12)];
+        let mut encoder = ContextEncoder::<ByteBackend, _, 1>::new(&models, |_| 1);
+        encoder.put(0);
+        encoder.flush();
+    }
+}
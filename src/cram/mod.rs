@@ -0,0 +1,3 @@
+/// rANS Nx16 codec from the CRAM 3.1 specification, with its RLE and
+/// bit-packing transforms.
+pub mod rans4x16;
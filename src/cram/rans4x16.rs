@@ -0,0 +1,1068 @@
+use crate::model::Model;
+use crate::simple::scale_counts;
+use crate::RansError;
+
+/// Use the order-1 model instead of the order-0 one.
+pub const ORDER: u8 = 0x01;
+/// Interleave 32 rANS states instead of 4.
+pub const X32: u8 = 0x04;
+/// Split the data into 4 interleaved stripes compressed separately.
+pub const STRIPE: u8 = 0x08;
+/// Don't store the uncompressed size; it has to be passed to
+/// [`decode_with_len()`] instead.
+pub const NOSZ: u8 = 0x10;
+/// Store the data uncompressed.
+pub const CAT: u8 = 0x20;
+/// Run-length encode the data before entropy coding it.
+pub const RLE: u8 = 0x40;
+/// Pack the data to 4, 2, 1 or 0 bits per symbol if it uses at most 16
+/// distinct symbols.
+pub const PACK: u8 = 0x80;
+
+/// Largest uncompressed length accepted by the decoder, so that a corrupted
+/// header can't make it allocate more memory than that. Data longer than this
+/// can be encoded, but not decoded.
+pub const MAX_LEN: usize = 1 << 28;
+
+/// Lower bound of the normalization interval.
+const RANS_L: u32 = 1 << 15;
+/// Scale bits of the frequency tables produced by the encoder.
+const TF_SHIFT: u32 = 12;
+/// Number of stripes used by the encoder when [`STRIPE`] is set.
+const NUM_STRIPES: usize = 4;
+
+/// Compresses `data` using the rANS Nx16 codec from the CRAM 3.1
+/// specification, with the transforms selected by `flags` (a combination of
+/// [`ORDER`], [`X32`], [`STRIPE`], [`NOSZ`], [`CAT`], [`RLE`] and [`PACK`]).
+///
+/// [`PACK`] and [`RLE`] are only applied if the data is suitable for them;
+/// the flags stored in the output always describe what was actually done.
+///
+/// # Examples
+/// ```
+/// use rans::cram::rans4x16::{decode, encode, ORDER, RLE};
+///
+/// let data = b"aaaaaaaabbbbbbbbaaaaaaaabbbbbbbb";
+/// let compressed = encode(data, ORDER | RLE);
+/// assert_eq!(decode(&compressed).unwrap(), data);
+/// ```
+#[must_use]
+pub fn encode(data: &[u8], flags: u8) -> Vec<u8> {
+    let mut output = Vec::new();
+    if flags & STRIPE != 0 {
+        encode_stripe(&mut output, data, flags);
+    } else {
+        encode_stream(&mut output, data, flags);
+    }
+    output
+}
+
+/// Decompresses data produced by [`encode()`] (or any other implementation
+/// of the rANS Nx16 codec).
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the input is truncated,
+/// [`RansError::BufferOverflow`] if any of the lengths stored in the headers
+/// exceeds [`MAX_LEN`], or
+/// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if the headers
+/// are malformed (including the case of the size not being stored because
+/// of the [`NOSZ`] flag - use [`decode_with_len()`] then).
+///
+/// # Examples
+/// ```
+/// use rans::cram::rans4x16::{decode, encode, PACK};
+///
+/// let compressed = encode(b"ACGTTGCA", PACK);
+/// assert_eq!(decode(&compressed).unwrap(), b"ACGTTGCA");
+/// ```
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, RansError> {
+    decode_stream(&mut Reader::new(data), None)
+}
+
+/// Decompresses data produced by [`encode()`] with the [`NOSZ`] flag set,
+/// given the uncompressed length. If the data does contain the size, `len`
+/// is ignored.
+///
+/// # Errors
+/// Same as in the case of [`decode()`].
+///
+/// # Examples
+/// ```
+/// use rans::cram::rans4x16::{decode_with_len, encode, NOSZ};
+///
+/// let compressed = encode(b"hello", NOSZ);
+/// assert_eq!(decode_with_len(&compressed, 5).unwrap(), b"hello");
+/// ```
+pub fn decode_with_len(data: &[u8], len: usize) -> Result<Vec<u8>, RansError> {
+    decode_stream(&mut Reader::new(data), Some(len))
+}
+
+fn encode_stripe(output: &mut Vec<u8>, data: &[u8], flags: u8) {
+    output.push(flags & !NOSZ);
+    write_u7(output, data.len() as u32);
+    output.push(NUM_STRIPES as u8);
+
+    let sub_flags = (flags & !STRIPE) | NOSZ;
+    let streams: Vec<Vec<u8>> = (0..NUM_STRIPES)
+        .map(|stripe| {
+            let stripe_data: Vec<u8> = data
+                .iter()
+                .skip(stripe)
+                .step_by(NUM_STRIPES)
+                .copied()
+                .collect();
+            let mut stream = Vec::new();
+            encode_stream(&mut stream, &stripe_data, sub_flags);
+            stream
+        })
+        .collect();
+
+    for stream in &streams {
+        write_u7(output, stream.len() as u32);
+    }
+    for stream in &streams {
+        output.extend_from_slice(stream);
+    }
+}
+
+fn encode_stream(output: &mut Vec<u8>, data: &[u8], mut flags: u8) {
+    flags &= !STRIPE;
+    let num_states = if flags & X32 != 0 { 32 } else { 4 };
+
+    let mut headers = Vec::new();
+    let mut body = data.to_vec();
+    if flags & PACK != 0 {
+        match pack(&body) {
+            Some((meta, packed)) => {
+                headers.extend_from_slice(&meta);
+                body = packed;
+            }
+            None => flags &= !PACK,
+        }
+    }
+    if flags & RLE != 0 {
+        match rle_encode(&body) {
+            Some((meta, literals)) => {
+                write_rle_meta(&mut headers, &meta, literals.len());
+                body = literals;
+            }
+            None => flags &= !RLE,
+        }
+    }
+
+    let mut encoded = Vec::new();
+    if body.is_empty() || flags & CAT != 0 {
+        flags |= CAT;
+        encoded.extend_from_slice(&body);
+    } else if flags & ORDER != 0 {
+        encode_o1(&mut encoded, &body, num_states);
+    } else {
+        encode_o0(&mut encoded, &body, num_states);
+    }
+
+    output.push(flags);
+    if flags & NOSZ == 0 {
+        write_u7(output, data.len() as u32);
+    }
+    output.extend_from_slice(&headers);
+    output.extend_from_slice(&encoded);
+}
+
+fn decode_stream(reader: &mut Reader<'_>, len: Option<usize>) -> Result<Vec<u8>, RansError> {
+    let flags = reader.read_u8()?;
+    let len = if flags & NOSZ == 0 {
+        reader.read_len()?
+    } else {
+        len.ok_or(RansError::InvalidHeader)?
+    };
+    if len > MAX_LEN {
+        return Err(RansError::BufferOverflow);
+    }
+    if flags & STRIPE != 0 {
+        return decode_stripe(reader, len);
+    }
+    let num_states = if flags & X32 != 0 { 32 } else { 4 };
+
+    let mut body_len = len;
+    let mut pack_meta = None;
+    if flags & PACK != 0 {
+        let num_symbols = usize::from(reader.read_u8()?);
+        if num_symbols == 0 || num_symbols > 16 {
+            return Err(RansError::InvalidHeader);
+        }
+        let symbols = reader.read_data(num_symbols)?.to_vec();
+        body_len = reader.read_len()?;
+        pack_meta = Some((symbols, len));
+    }
+    let mut rle_meta = None;
+    if flags & RLE != 0 {
+        let (meta, literals_len) = read_rle_meta(reader)?;
+        rle_meta = Some((meta, body_len));
+        body_len = literals_len;
+    }
+
+    let mut body = if flags & CAT != 0 {
+        reader.read_data(body_len)?.to_vec()
+    } else if body_len == 0 {
+        Vec::new()
+    } else if flags & ORDER != 0 {
+        decode_o1(reader, body_len, num_states)?
+    } else {
+        decode_o0(reader, body_len, num_states)?
+    };
+
+    if let Some((meta, len)) = rle_meta {
+        body = rle_decode(&body, &meta, len)?;
+    }
+    if let Some((symbols, len)) = pack_meta {
+        body = unpack(&body, &symbols, len)?;
+    }
+    Ok(body)
+}
+
+fn decode_stripe(reader: &mut Reader<'_>, len: usize) -> Result<Vec<u8>, RansError> {
+    let num_stripes = usize::from(reader.read_u8()?);
+    if num_stripes == 0 {
+        return Err(RansError::InvalidHeader);
+    }
+    let mut compressed_lens = Vec::with_capacity(num_stripes);
+    for _ in 0..num_stripes {
+        compressed_lens.push(reader.read_u7()? as usize);
+    }
+
+    let mut output = vec![0; len];
+    for (stripe, &compressed_len) in compressed_lens.iter().enumerate() {
+        let stripe_len = len / num_stripes + usize::from(stripe < len % num_stripes);
+        let mut stripe_reader = Reader::new(reader.read_data(compressed_len)?);
+        let stripe_data = decode_stream(&mut stripe_reader, Some(stripe_len))?;
+        if stripe_data.len() != stripe_len {
+            return Err(RansError::InvalidHeader);
+        }
+
+        for (i, &byte) in stripe_data.iter().enumerate() {
+            output[i * num_stripes + stripe] = byte;
+        }
+    }
+    Ok(output)
+}
+
+/// Packs the data to fewer bits per symbol. Returns the metadata header and
+/// the packed data, or `None` if there are too many distinct symbols.
+fn pack(data: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut present = [false; 256];
+    for &byte in data {
+        present[byte as usize] = true;
+    }
+    let symbols: Vec<u8> = (0..=255).filter(|&byte| present[byte as usize]).collect();
+    if symbols.is_empty() || symbols.len() > 16 {
+        return None;
+    }
+
+    let mut map = [0; 256];
+    for (index, &symbol) in symbols.iter().enumerate() {
+        map[symbol as usize] = index as u8;
+    }
+    let bits = pack_bits(symbols.len());
+    let packed = match 8_usize.checked_div(bits) {
+        Some(per_byte) => data
+            .chunks(per_byte)
+            .map(|chunk| {
+                chunk.iter().enumerate().fold(0, |acc, (i, &byte)| {
+                    acc | (map[byte as usize] << (i * bits))
+                })
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let mut meta = vec![symbols.len() as u8];
+    meta.extend_from_slice(&symbols);
+    write_u7(&mut meta, packed.len() as u32);
+    Some((meta, packed))
+}
+
+fn unpack(data: &[u8], symbols: &[u8], len: usize) -> Result<Vec<u8>, RansError> {
+    let bits = pack_bits(symbols.len());
+    if bits == 0 {
+        // Nothing is stored, so the length is only bounded by `MAX_LEN`
+        return Ok(vec![symbols[0]; len]);
+    }
+
+    let per_byte = 8 / bits;
+    if data.len() < (len + per_byte - 1) / per_byte {
+        return Err(RansError::UnexpectedEof);
+    }
+    let mask = (1 << bits) - 1;
+    let mut output = Vec::with_capacity(len);
+    for i in 0..len {
+        let index = (data[i / per_byte] >> ((i % per_byte) * bits)) & mask;
+        output.push(
+            *symbols
+                .get(usize::from(index))
+                .ok_or(RansError::InvalidModel)?,
+        );
+    }
+    Ok(output)
+}
+
+/// Returns the number of bits per symbol used to pack an alphabet of given
+/// size.
+fn pack_bits(num_symbols: usize) -> usize {
+    match num_symbols {
+        0..=1 => 0,
+        2 => 1,
+        3..=4 => 2,
+        _ => 4,
+    }
+}
+
+/// Run-length encodes the symbols for which it's beneficial. Returns the
+/// metadata (the list of run-length encoded symbols followed by the run
+/// lengths) and the literals, or `None` if no symbol is worth encoding this
+/// way.
+fn rle_encode(data: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    // The number of bytes saved by run-length encoding each symbol
+    let mut saved = [0_i64; 256];
+    let mut i = 0;
+    while i < data.len() {
+        let run_len = data[i..]
+            .iter()
+            .take_while(|&&byte| byte == data[i])
+            .count();
+        saved[data[i] as usize] += run_len as i64 - 2;
+        i += run_len;
+    }
+
+    let symbols: Vec<u8> = (0..=255).filter(|&byte| saved[byte as usize] > 0).collect();
+    if symbols.is_empty() {
+        return None;
+    }
+
+    let mut meta = vec![symbols.len() as u8];
+    meta.extend_from_slice(&symbols);
+    let mut literals = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let byte = data[i];
+        literals.push(byte);
+        if saved[byte as usize] > 0 {
+            let run_len = data[i..].iter().take_while(|&&x| x == byte).count();
+            write_u7(&mut meta, (run_len - 1) as u32);
+            i += run_len;
+        } else {
+            i += 1;
+        }
+    }
+    Some((meta, literals))
+}
+
+fn write_rle_meta(output: &mut Vec<u8>, meta: &[u8], literals_len: usize) {
+    let mut compressed = Vec::new();
+    encode_o0(&mut compressed, meta, 4);
+
+    if compressed.len() < meta.len() {
+        write_u7(output, meta.len() as u32 * 2);
+        write_u7(output, literals_len as u32);
+        write_u7(output, compressed.len() as u32);
+        output.extend_from_slice(&compressed);
+    } else {
+        write_u7(output, meta.len() as u32 * 2 + 1);
+        write_u7(output, literals_len as u32);
+        output.extend_from_slice(meta);
+    }
+}
+
+/// Reads the run-length encoding metadata and returns it along with the
+/// number of literals.
+fn read_rle_meta(reader: &mut Reader<'_>) -> Result<(Vec<u8>, usize), RansError> {
+    let meta_len = reader.read_len()?;
+    let literals_len = reader.read_len()?;
+    let meta = if meta_len & 1 != 0 {
+        reader.read_data(meta_len / 2)?.to_vec()
+    } else {
+        let compressed_len = reader.read_u7()? as usize;
+        let mut meta_reader = Reader::new(reader.read_data(compressed_len)?);
+        decode_o0(&mut meta_reader, meta_len / 2, 4)?
+    };
+    Ok((meta, literals_len))
+}
+
+fn rle_decode(literals: &[u8], meta: &[u8], len: usize) -> Result<Vec<u8>, RansError> {
+    let mut reader = Reader::new(meta);
+    let num_symbols = match reader.read_u8()? {
+        0 => 256,
+        num_symbols => usize::from(num_symbols),
+    };
+    let mut is_run = [false; 256];
+    for &symbol in reader.read_data(num_symbols)? {
+        is_run[symbol as usize] = true;
+    }
+
+    let mut output = Vec::with_capacity(len);
+    for &byte in literals {
+        let count = if is_run[byte as usize] {
+            reader.read_u7()? as usize + 1
+        } else {
+            1
+        };
+        if output.len() + count > len {
+            return Err(RansError::InvalidHeader);
+        }
+        output.resize(output.len() + count, byte);
+    }
+
+    if output.len() != len {
+        return Err(RansError::InvalidHeader);
+    }
+    Ok(output)
+}
+
+fn encode_o0(output: &mut Vec<u8>, data: &[u8], num_states: usize) {
+    let mut counts = [0; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let model = Model::from_counts(&scale_counts(&counts), TF_SHIFT);
+
+    let present: Vec<bool> = model.freqs().iter().map(|&freq| freq > 0).collect();
+    write_alphabet(output, &present);
+    for &freq in model.freqs().iter().filter(|&&freq| freq > 0) {
+        write_u7(output, freq);
+    }
+
+    let mut writer = ReverseWriter::default();
+    let mut states = vec![RANS_L; num_states];
+    for (i, &byte) in data.iter().enumerate().rev() {
+        let symbol = byte as usize;
+        writer.put(
+            &mut states[i % num_states],
+            model.cum_freq(symbol),
+            model.freq(symbol),
+            TF_SHIFT,
+        );
+    }
+    for &state in states.iter().rev() {
+        writer.flush(state);
+    }
+    writer.append_to(output);
+}
+
+fn decode_o0(reader: &mut Reader<'_>, len: usize, num_states: usize) -> Result<Vec<u8>, RansError> {
+    let alphabet = read_alphabet(reader)?;
+    let mut freqs = [0; 256];
+    for (symbol, &present) in alphabet.iter().enumerate() {
+        if present {
+            freqs[symbol] = reader.read_u7()?;
+        }
+    }
+    let table = DecodingTable::new(&mut freqs, TF_SHIFT)?.ok_or(RansError::InvalidModel)?;
+
+    let mut states = Vec::with_capacity(num_states);
+    for _ in 0..num_states {
+        states.push(reader.read_u32()?);
+    }
+
+    let mut output = Vec::with_capacity(len);
+    for i in 0..len {
+        let state = &mut states[i % num_states];
+        output.push(table.decode(state, reader)?);
+    }
+    Ok(output)
+}
+
+fn encode_o1(output: &mut Vec<u8>, data: &[u8], num_states: usize) {
+    let segment_len = data.len() / num_states;
+    let context_at = |i: usize| {
+        if i == 0 || (segment_len > 0 && i % segment_len == 0 && i < segment_len * num_states) {
+            0
+        } else {
+            data[i - 1] as usize
+        }
+    };
+
+    let mut counts = vec![[0; 256]; 256];
+    for (i, &byte) in data.iter().enumerate() {
+        counts[context_at(i)][byte as usize] += 1;
+    }
+    let mut alphabet = [false; 256];
+    alphabet[0] = true;
+    for &byte in data {
+        alphabet[byte as usize] = true;
+    }
+    let models: Vec<Option<Model>> = counts
+        .iter()
+        .map(|counts| {
+            if counts.iter().all(|&count| count == 0) {
+                None
+            } else {
+                Some(Model::from_counts(&scale_counts(counts), TF_SHIFT))
+            }
+        })
+        .collect();
+
+    // Frequency tables; the zero frequencies of consecutive symbols are
+    // run-length encoded
+    let symbols: Vec<usize> = (0..256).filter(|&symbol| alphabet[symbol]).collect();
+    let mut tables = Vec::new();
+    write_alphabet(&mut tables, &alphabet);
+    for &context in &symbols {
+        let freqs = models[context].as_ref().map(Model::freqs);
+        let freq_of = |symbol: usize| freqs.map_or(0, |freqs| freqs[symbol]);
+
+        let mut i = 0;
+        while i < symbols.len() {
+            let freq = freq_of(symbols[i]);
+            write_u7(&mut tables, freq);
+            i += 1;
+            if freq == 0 {
+                let run = symbols[i..]
+                    .iter()
+                    .take(255)
+                    .take_while(|&&symbol| freq_of(symbol) == 0)
+                    .count();
+                tables.push(run as u8);
+                i += run;
+            }
+        }
+    }
+
+    let mut compressed_tables = Vec::new();
+    encode_o0(&mut compressed_tables, &tables, 4);
+    if compressed_tables.len() < tables.len() {
+        output.push(((TF_SHIFT as u8) << 4) | 1);
+        write_u7(output, tables.len() as u32);
+        write_u7(output, compressed_tables.len() as u32);
+        output.extend_from_slice(&compressed_tables);
+    } else {
+        output.push((TF_SHIFT as u8) << 4);
+        output.extend_from_slice(&tables);
+    }
+
+    let mut writer = ReverseWriter::default();
+    let mut states = vec![RANS_L; num_states];
+    let mut put = |states: &mut [u32], state: usize, i: usize| {
+        let model = models[context_at(i)]
+            .as_ref()
+            .expect("model of a present context");
+        let symbol = data[i] as usize;
+        writer.put(
+            &mut states[state],
+            model.cum_freq(symbol),
+            model.freq(symbol),
+            TF_SHIFT,
+        );
+    };
+    // The remainder is handled by the last state
+    for i in (segment_len * num_states..data.len()).rev() {
+        put(&mut states, num_states - 1, i);
+    }
+    for i in (0..segment_len).rev() {
+        for state in (0..num_states).rev() {
+            put(&mut states, state, state * segment_len + i);
+        }
+    }
+    for &state in states.iter().rev() {
+        writer.flush(state);
+    }
+    writer.append_to(output);
+}
+
+fn decode_o1(reader: &mut Reader<'_>, len: usize, num_states: usize) -> Result<Vec<u8>, RansError> {
+    let comp = reader.read_u8()?;
+    let shift = u32::from(comp >> 4);
+    if shift == 0 || shift > TF_SHIFT {
+        return Err(RansError::InvalidHeader);
+    }
+
+    let tables = if comp & 1 != 0 {
+        let tables_len = reader.read_len()?;
+        let compressed_len = reader.read_u7()? as usize;
+        let mut tables_reader = Reader::new(reader.read_data(compressed_len)?);
+        let tables = decode_o0(&mut tables_reader, tables_len, 4)?;
+        read_tables_o1(&mut Reader::new(&tables), shift)?
+    } else {
+        read_tables_o1(reader, shift)?
+    };
+
+    let mut states = Vec::with_capacity(num_states);
+    for _ in 0..num_states {
+        states.push(reader.read_u32()?);
+    }
+
+    let segment_len = len / num_states;
+    let mut output = vec![0; len];
+    let mut contexts = vec![0; num_states];
+    let mut decode = |state: usize, i: usize, output: &mut [u8]| {
+        let table = tables[contexts[state]]
+            .as_ref()
+            .ok_or(RansError::InvalidModel)?;
+        let byte = table.decode(&mut states[state], reader)?;
+        output[i] = byte;
+        contexts[state] = byte as usize;
+        Ok::<(), RansError>(())
+    };
+    for i in 0..segment_len {
+        for state in 0..num_states {
+            decode(state, state * segment_len + i, &mut output)?;
+        }
+    }
+    for i in segment_len * num_states..len {
+        decode(num_states - 1, i, &mut output)?;
+    }
+    Ok(output)
+}
+
+fn read_tables_o1(
+    reader: &mut Reader<'_>,
+    shift: u32,
+) -> Result<Vec<Option<DecodingTable>>, RansError> {
+    let alphabet = read_alphabet(reader)?;
+    let symbols: Vec<usize> = (0..256).filter(|&symbol| alphabet[symbol]).collect();
+
+    let mut tables: Vec<Option<DecodingTable>> = (0..256).map(|_| None).collect();
+    for &context in &symbols {
+        let mut freqs = [0; 256];
+        let mut i = 0;
+        while i < symbols.len() {
+            let freq = reader.read_u7()?;
+            freqs[symbols[i]] = freq;
+            i += 1;
+            if freq == 0 {
+                i += usize::from(reader.read_u8()?);
+            }
+        }
+        tables[context] = DecodingTable::new(&mut freqs, shift)?;
+    }
+    Ok(tables)
+}
+
+/// Writes the set of symbols present in the data, run-length encoding the
+/// consecutive ones.
+fn write_alphabet(output: &mut Vec<u8>, present: &[bool]) {
+    let mut run = 0;
+    for symbol in 0..present.len() {
+        if !present[symbol] {
+            continue;
+        }
+        if run > 0 {
+            run -= 1;
+            continue;
+        }
+
+        output.push(symbol as u8);
+        if symbol > 0 && present[symbol - 1] {
+            run = present[symbol + 1..]
+                .iter()
+                .take_while(|&&present| present)
+                .count();
+            output.push(run as u8);
+        }
+    }
+    output.push(0);
+}
+
+fn read_alphabet(reader: &mut Reader<'_>) -> Result<[bool; 256], RansError> {
+    let mut present = [false; 256];
+    let mut run = 0;
+    let mut symbol = usize::from(reader.read_u8()?);
+    loop {
+        present[symbol] = true;
+        if run > 0 {
+            run -= 1;
+            symbol += 1;
+            if symbol > 255 {
+                return Err(RansError::InvalidModel);
+            }
+        } else {
+            let next = usize::from(reader.read_u8()?);
+            if next == symbol + 1 {
+                symbol = next;
+                run = reader.read_u8()?;
+            } else {
+                symbol = next;
+            }
+        }
+
+        if symbol == 0 {
+            return Ok(present);
+        }
+    }
+}
+
+/// Lookup tables used to decode symbols with a single frequency table.
+#[derive(Debug)]
+struct DecodingTable {
+    shift: u32,
+    freqs: [u32; 256],
+    cum_freqs: [u32; 256],
+    symbols: Vec<u8>,
+}
+
+impl DecodingTable {
+    /// Normalizes the frequencies to `1 << shift` and builds the table.
+    /// Returns `None` if all the frequencies are zero.
+    fn new(freqs: &mut [u32; 256], shift: u32) -> Result<Option<Self>, RansError> {
+        let total: u64 = freqs.iter().map(|&freq| u64::from(freq)).sum();
+        if total == 0 {
+            return Ok(None);
+        }
+        if !total.is_power_of_two() || total > 1 << shift {
+            return Err(RansError::InvalidModel);
+        }
+        let scale = (1 << shift) / total as u32;
+        for freq in freqs.iter_mut() {
+            *freq *= scale;
+        }
+
+        let mut cum_freqs = [0; 256];
+        let mut symbols = Vec::with_capacity(1 << shift);
+        for (symbol, &freq) in freqs.iter().enumerate() {
+            cum_freqs[symbol] = symbols.len() as u32;
+            symbols.resize(symbols.len() + freq as usize, symbol as u8);
+        }
+
+        Ok(Some(Self {
+            shift,
+            freqs: *freqs,
+            cum_freqs,
+            symbols,
+        }))
+    }
+
+    #[inline]
+    fn decode(&self, state: &mut u32, reader: &mut Reader<'_>) -> Result<u8, RansError> {
+        let mask = (1 << self.shift) - 1;
+        let cum_freq = *state & mask;
+        let symbol = self.symbols[cum_freq as usize];
+
+        // Wrapping arithmetic, so that corrupted states can't cause a panic
+        let x = self.freqs[symbol as usize]
+            .wrapping_mul(*state >> self.shift)
+            .wrapping_add(cum_freq - self.cum_freqs[symbol as usize]);
+        *state = if x < RANS_L {
+            (x << 16) | u32::from(reader.read_u16()?)
+        } else {
+            x
+        };
+        Ok(symbol)
+    }
+}
+
+/// Output buffer of the encoder, filled from the end.
+#[derive(Debug, Default)]
+struct ReverseWriter {
+    reversed: Vec<u8>,
+}
+
+impl ReverseWriter {
+    #[inline]
+    fn put(&mut self, state: &mut u32, cum_freq: u32, freq: u32, shift: u32) {
+        let mut x = *state;
+        let x_max = ((RANS_L >> shift) << 16) * freq;
+        if x >= x_max {
+            self.reversed.extend_from_slice(&[(x >> 8) as u8, x as u8]);
+            x >>= 16;
+        }
+        *state = ((x / freq) << shift) + (x % freq) + cum_freq;
+    }
+
+    fn flush(&mut self, state: u32) {
+        self.reversed
+            .extend(state.to_le_bytes().iter().rev().copied());
+    }
+
+    fn append_to(self, output: &mut Vec<u8>) {
+        output.extend(self.reversed.iter().rev());
+    }
+}
+
+#[derive(Debug)]
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn read_data(&mut self, len: usize) -> Result<&'a [u8], RansError> {
+        let end = self.pos.checked_add(len).ok_or(RansError::UnexpectedEof)?;
+        let data = self
+            .data
+            .get(self.pos..end)
+            .ok_or(RansError::UnexpectedEof)?;
+        self.pos = end;
+        Ok(data)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, RansError> {
+        Ok(self.read_data(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, RansError> {
+        let bytes = self.read_data(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, RansError> {
+        let bytes = self.read_data(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a variable-length integer stored as 7-bit groups, most
+    /// significant first.
+    fn read_u7(&mut self) -> Result<u32, RansError> {
+        let mut value: u32 = 0;
+        for _ in 0..5 {
+            let byte = self.read_u8()?;
+            value = (value << 7) | u32::from(byte & 0x7f);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(RansError::InvalidHeader)
+    }
+
+    /// Reads a length stored with [`Self::read_u7()`], rejecting lengths
+    /// larger than [`MAX_LEN`].
+    fn read_len(&mut self) -> Result<usize, RansError> {
+        let len = self.read_u7()? as usize;
+        if len > MAX_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+        Ok(len)
+    }
+}
+
+/// Writes a variable-length integer as 7-bit groups, most significant first.
+fn write_u7(output: &mut Vec<u8>, value: u32) {
+    let groups = ((32 - value.leading_zeros() + 6) / 7).max(1);
+    for group in (1..groups).rev() {
+        output.push(((value >> (group * 7)) & 0x7f) as u8 | 0x80);
+    }
+    output.push((value & 0x7f) as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::cram::rans4x16::{
+        decode, decode_with_len, encode, read_alphabet, write_alphabet, write_u7, Reader, CAT,
+        MAX_LEN, NOSZ, ORDER, PACK, RLE, STRIPE, X32,
+    };
+    use crate::RansError;
+
+    const ALL_FLAGS: [u8; 7] = [ORDER, X32, STRIPE, NOSZ, CAT, RLE, PACK];
+
+    fn test_data() -> Vec<Vec<u8>> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        vec![
+            Vec::new(),
+            vec![7],
+            b"abc".to_vec(),
+            vec![42; 1000],
+            (0..5000).map(|_| rng.gen_range(0..4) * 60).collect(),
+            (0..5000).map(|_| rng.gen::<u8>()).collect(),
+            (0..5000_u32).map(|i| (i / 37 % 5) as u8 + b'A').collect(),
+            (0..3000_u32).map(|i| (i * i % 251) as u8).collect(),
+        ]
+    }
+
+    #[test]
+    fn test_round_trip_all_flag_combinations() {
+        for data in test_data() {
+            for mask in 0..1 << ALL_FLAGS.len() {
+                let flags = ALL_FLAGS
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| mask & (1 << i) != 0)
+                    .fold(0, |acc, (_, &flag)| acc | flag);
+
+                let compressed = encode(&data, flags);
+                let decompressed = if flags & NOSZ != 0 && flags & STRIPE == 0 {
+                    decode_with_len(&compressed, data.len())
+                } else {
+                    decode(&compressed)
+                };
+                assert_eq!(decompressed.as_ref(), Ok(&data), "flags: {flags:#x}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_transforms_reduce_size() {
+        let data: Vec<u8> = (0..5000_u32).map(|i| (i / 37 % 5) as u8 + b'A').collect();
+
+        let plain = encode(&data, 0).len();
+        assert!(encode(&data, PACK).len() < plain);
+        assert!(encode(&data, RLE).len() < plain);
+        assert!(encode(&data, ORDER).len() < plain);
+    }
+
+    #[test]
+    fn test_cat() {
+        assert_eq!(encode(b"abc", CAT), [CAT, 3, b'a', b'b', b'c']);
+    }
+
+    #[test]
+    fn test_hand_assembled_streams() {
+        // Streams assembled by hand following the layout of the CRAM 3.1
+        // codecs specification, rather than produced by `encode()`. They are
+        // not output of htscodecs or samtools, so they only check this decoder
+        // against our reading of the specification. The frequencies are
+        // stored unnormalized, and the states are all 0x8000 except where
+        // noted.
+        let state = |state: u32| state.to_le_bytes();
+        let initial_states = |count: usize| state(0x8000).repeat(count);
+        let o0_ab = |num_states: usize| {
+            // Alphabet: 'a', 'b' (a run of 0 more), end; frequencies 1, 1
+            let mut stream = vec![0, 2, b'a', b'b', 0, 0, 1, 1];
+            // "a" and "b" popped from the first two states
+            stream.extend(state(0x1_0000));
+            stream.extend(state(0x1_0800));
+            stream.extend(initial_states(num_states - 2));
+            stream
+        };
+
+        let mut o1_ab = vec![ORDER, 2];
+        // Uncompressed tables with 12-bit frequencies; alphabet: 0, 'a', 'b'
+        // (a run of 0 more), end
+        o1_ab.extend([0xc0, 0, b'a', b'b', 0, 0]);
+        // Context 0: 0 (and 0 more zeros), 1, 0 (and 0 more zeros)
+        o1_ab.extend([0, 0, 1, 0, 0]);
+        // Context 'a': 0 (and 1 more zero), 1
+        o1_ab.extend([0, 1, 1]);
+        // Context 'b': 0 (and 2 more zeros)
+        o1_ab.extend([0, 2]);
+        // Only the last state is used for fewer symbols than states, and
+        // symbols with the full frequency don't change it
+        o1_ab.extend(initial_states(4));
+
+        let vectors: [(&[u8], u8, Vec<u8>); 8] = [
+            (b"ab", 0, o0_ab(4)),
+            (b"ab", X32, {
+                let mut stream = o0_ab(32);
+                stream[0] = X32;
+                stream
+            }),
+            (b"ab", ORDER, o1_ab),
+            (b"abc", CAT, vec![CAT, 3, b'a', b'b', b'c']),
+            (b"abc", NOSZ | CAT, vec![NOSZ | CAT, b'a', b'b', b'c']),
+            // Stripes "ae", "b", "c" and "d"
+            (
+                b"abcde",
+                STRIPE | CAT,
+                vec![
+                    STRIPE | CAT,
+                    5,
+                    4,
+                    3,
+                    2,
+                    2,
+                    2,
+                    NOSZ | CAT,
+                    b'a',
+                    b'e',
+                    NOSZ | CAT,
+                    b'b',
+                    NOSZ | CAT,
+                    b'c',
+                    NOSZ | CAT,
+                    b'd',
+                ],
+            ),
+            // Uncompressed metadata of length 3 (stored as 3 * 2 + 1), 2
+            // literals; 1 run symbol, 'a', run of 3 more; literals "ab"
+            (
+                b"aaaab",
+                RLE | CAT,
+                vec![RLE | CAT, 5, 7, 2, 1, b'a', 3, b'a', b'b'],
+            ),
+            // 2 symbols, 'a', 'b', 1 packed byte: 0b0110
+            (
+                b"abba",
+                PACK | CAT,
+                vec![PACK | CAT, 4, 2, b'a', b'b', 1, 0b0110],
+            ),
+        ];
+
+        for (data, flags, stream) in vectors {
+            let decoded = if flags & NOSZ != 0 {
+                decode_with_len(&stream, data.len())
+            } else {
+                decode(&stream)
+            };
+            assert_eq!(decoded.as_deref(), Ok(data), "flags: {flags:#x}");
+            // The transforms are deterministic, so the encoder produces the
+            // same bytes as long as no frequency table is involved
+            if flags & CAT != 0 {
+                assert_eq!(encode(data, flags), stream, "flags: {flags:#x}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_len_limit() {
+        // Unpacking a single symbol doesn't read anything, so the length
+        // has to be limited up front
+        for len in [MAX_LEN as u32 + 1, u32::MAX] {
+            let mut stream = vec![PACK | CAT];
+            write_u7(&mut stream, len);
+            stream.extend([1, b'a', 0]);
+            assert_eq!(decode(&stream), Err(RansError::BufferOverflow));
+        }
+        assert_eq!(
+            decode_with_len(&[NOSZ | CAT], MAX_LEN + 1),
+            Err(RansError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn test_missing_size() {
+        let compressed = encode(b"abc", NOSZ);
+
+        assert_eq!(decode(&compressed), Err(RansError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data: Vec<u8> = (0..1000_u32).map(|i| (i * i % 7) as u8).collect();
+        for flags in [0, ORDER, RLE | PACK, ORDER | STRIPE] {
+            let compressed = encode(&data, flags);
+            for len in 0..compressed.len() {
+                assert!(decode(&compressed[..len]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_u7() {
+        let mut data = Vec::new();
+        for value in [0, 127, 128, 16383, 16384, u32::MAX] {
+            write_u7(&mut data, value);
+        }
+        assert_eq!(&data[..5], [0, 127, 0x81, 0, 0xff]);
+
+        let mut reader = Reader::new(&data);
+        for value in [0, 127, 128, 16383, 16384, u32::MAX] {
+            assert_eq!(reader.read_u7(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let mut present = [false; 256];
+        for symbol in [0, 1, 2, 3, 10, 12, 13, 255] {
+            present[symbol] = true;
+        }
+
+        let mut data = Vec::new();
+        write_alphabet(&mut data, &present);
+        assert_eq!(data, [0, 1, 2, 10, 12, 13, 0, 255, 0]);
+        assert_eq!(read_alphabet(&mut Reader::new(&data)), Ok(present));
+    }
+}
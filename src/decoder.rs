@@ -1,3 +1,8 @@
+use crate::encoder::{check_symbol, uniform_split, uniform_symbol, uniform_value, MAX_BITS_CHUNK};
+use crate::lookup::AliasTable;
+use crate::model::DecModel;
+use crate::RansError;
+
 /// Interleaved multi-stream rANS decoder interface.
 pub trait RansDecoderMulti<const N: usize> {
     /// Type of a Symbol value that can be encoded using this decoder.
@@ -82,9 +87,73 @@ pub trait RansDecoderMulti<const N: usize> {
     /// ```
     fn renorm_at(&mut self, channel: usize);
 
+    /// Renormalizes the state of given channel like [`Self::renorm_at()`],
+    /// but checks that the data doesn't end first, so that malformed or
+    /// truncated data never makes the decoder read past the end of it.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to renormalize the channel. The state of the channel is left
+    /// unchanged in such case, and the decoder shouldn't be used anymore.
+    ///
+    /// The default implementation has no way to tell how much data is left,
+    /// so it just calls [`Self::renorm_at()`] and never fails; all the
+    /// decoders of this crate override it with a checked version.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::{RansDecSymbol, RansDecoderMulti, RansError};
+    ///
+    /// let mut decoder = ByteRansDecoder::new([0, 0, 128, 0]);
+    /// decoder.advance_step_at(0, &ByteRansDecSymbol::new(0, 1), 8);
+    /// assert_eq!(decoder.try_renorm_at(0), Err(RansError::InputExhausted));
+    /// ```
+    fn try_renorm_at(&mut self, channel: usize) -> Result<(), RansError> {
+        self.renorm_at(channel);
+        Ok(())
+    }
+
+    /// Advances the data position after reading a symbol at given channel
+    /// like [`Self::advance_at()`], but checks that the data doesn't end
+    /// first. Equivalent to calling [`Self::advance_step_at()`] and
+    /// [`Self::try_renorm_at()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to renormalize the channel, in which case the decoder shouldn't be
+    /// used anymore.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::{RansDecSymbol, RansDecoderMulti, RansError};
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 128, 0]);
+    /// decoder.try_advance_at(0, &ByteRansDecSymbol::new(2, 2), 2).unwrap();
+    /// assert_eq!(
+    ///     decoder.try_advance_at(1, &ByteRansDecSymbol::new(0, 1), 2),
+    ///     Err(RansError::InputExhausted)
+    /// );
+    /// ```
+    fn try_advance_at(
+        &mut self,
+        channel: usize,
+        symbol: &Self::Symbol,
+        scale_bits: u32,
+    ) -> Result<(), RansError> {
+        self.advance_step_at(channel, symbol, scale_bits);
+        self.try_renorm_at(channel)
+    }
+
     /// Renormalizes the data in all channels' internal buffers after advancing
     /// a symbol.
     ///
+    /// Together with [`Self::advance_step_at()`], this makes it possible to
+    /// pop a symbol from every channel first and refill all the states
+    /// afterwards. Equivalent to calling [`Self::renorm_at()`] for channels
+    /// `0..N` in order.
+    ///
     /// # Examples
     /// ```
     /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
@@ -106,6 +175,307 @@ pub trait RansDecoderMulti<const N: usize> {
             self.renorm_at(i);
         }
     }
+
+    /// Renormalizes all the channels like [`Self::renorm_all()`], but checks
+    /// that the data doesn't end first.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to renormalize all the channels, in which case the decoder shouldn't
+    /// be used anymore.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::{RansDecSymbol, RansDecoderMulti, RansError};
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 128, 0]);
+    /// decoder.advance_step_at(0, &ByteRansDecSymbol::new(2, 2), 2);
+    /// decoder.advance_step_at(1, &ByteRansDecSymbol::new(0, 1), 2);
+    /// assert_eq!(decoder.try_renorm_all(), Err(RansError::InputExhausted));
+    /// ```
+    fn try_renorm_all(&mut self) -> Result<(), RansError> {
+        for i in 0..N {
+            self.try_renorm_at(i)?;
+        }
+        Ok(())
+    }
+
+    /// Pops a single symbol from the internal state of every channel, without
+    /// doing renormalization or modifying the internal buffer. Equivalent to
+    /// calling [`Self::advance_step_at()`] for each channel.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::{RansDecSymbol, RansDecoderMulti};
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]);
+    /// let symbol_1 = ByteRansDecSymbol::new(0, 2);
+    /// let symbol_2 = ByteRansDecSymbol::new(2, 2);
+    /// decoder.advance_step_all(&[&symbol_2, &symbol_1], 2);
+    /// decoder.renorm_all();
+    /// assert_eq!(decoder.get_at(0, 2), 0);
+    /// assert_eq!(decoder.get_at(1, 2), 0);
+    /// ```
+    fn advance_step_all(&mut self, symbols: &[&Self::Symbol; N], scale_bits: u32) {
+        for (channel, symbol) in symbols.iter().enumerate() {
+            self.advance_step_at(channel, symbol, scale_bits);
+        }
+    }
+
+    /// Advances the data position at all channels after reading a symbol at
+    /// each one of them. Equivalent to calling [`Self::advance_step_all()`]
+    /// and [`Self::renorm_all()`], and produces the same result as calling
+    /// [`Self::advance_at()`] for channels `0..N` in order.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::{RansDecSymbol, RansDecoderMulti};
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]);
+    /// let symbol_1 = ByteRansDecSymbol::new(0, 2);
+    /// let symbol_2 = ByteRansDecSymbol::new(2, 2);
+    /// assert_eq!(decoder.get_at(0, 2), 2);
+    /// assert_eq!(decoder.get_at(1, 2), 0);
+    /// decoder.advance_all(&[&symbol_2, &symbol_1], 2);
+    /// assert_eq!(decoder.get_at(0, 2), 0);
+    /// assert_eq!(decoder.get_at(1, 2), 0);
+    /// ```
+    fn advance_all(&mut self, symbols: &[&Self::Symbol; N], scale_bits: u32) {
+        self.advance_step_all(symbols, scale_bits);
+        self.renorm_all();
+    }
+
+    /// Decodes a single symbol at every channel using given model and returns
+    /// their indices. Equivalent to calling [`Self::decode_symbol_at()`] for
+    /// channels `0..N` in order, but renormalizes all the channels at once
+    /// with [`Self::advance_all()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let model = Model::from_freqs(vec![2, 2], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]);
+    /// assert_eq!(decoder.decode_symbols_all(&dec_model), [1, 0]);
+    /// ```
+    fn decode_symbols_all(&mut self, model: &DecModel<Self::Symbol>) -> [usize; N] {
+        let lookups: [(usize, &Self::Symbol); N] =
+            std::array::from_fn(|channel| model.lookup(self.get_at(channel, model.scale_bits())));
+        self.advance_all(&lookups.map(|(_, symbol)| symbol), model.scale_bits());
+
+        lookups.map(|(index, _)| index)
+    }
+
+    /// Reads `num_bits` raw bits put with
+    /// [`RansEncoderMulti::put_bits_at()`](crate::RansEncoderMulti::put_bits_at)
+    /// from the specified channel.
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.put_bits_at(0, 0b101, 3);
+    /// encoder.put_bits_at(1, 0xdead_beef, 32);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new(encoder.data());
+    /// assert_eq!(decoder.get_bits_at(1, 3), 0b101);
+    /// assert_eq!(decoder.get_bits_at(0, 32), 0xdead_beef);
+    /// ```
+    fn get_bits_at(&mut self, channel: usize, num_bits: u32) -> u32 {
+        assert!(num_bits <= 32, "num_bits must not be larger than 32");
+
+        let mut value = 0;
+        let mut remaining = num_bits;
+        while remaining > 0 {
+            // The most significant chunk was put last, and it's the only one
+            // that can be shorter than the maximum
+            let chunk_bits = match remaining % MAX_BITS_CHUNK {
+                0 => MAX_BITS_CHUNK,
+                bits => bits,
+            };
+            remaining -= chunk_bits;
+            let chunk = self.get_at(channel, chunk_bits);
+            self.advance_at(channel, &Self::Symbol::new(chunk, 1), chunk_bits);
+            value |= chunk << remaining;
+        }
+        value
+    }
+
+    /// Reads `num_bits` raw bits from the specified channel like
+    /// [`Self::get_bits_at()`], but checks that the data doesn't end first.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to read the bits, in which case the decoder shouldn't be used anymore.
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::{RansDecoderMulti, RansEncoderMulti, RansError};
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.put_bits_at(1, 0xdead_beef, 32);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new(encoder.data());
+    /// assert_eq!(decoder.try_get_bits_at(0, 32), Ok(0xdead_beef));
+    /// assert_eq!(
+    ///     decoder.try_get_bits_at(1, 32),
+    ///     Err(RansError::InputExhausted)
+    /// );
+    /// ```
+    fn try_get_bits_at(&mut self, channel: usize, num_bits: u32) -> Result<u32, RansError> {
+        assert!(num_bits <= 32, "num_bits must not be larger than 32");
+
+        // Same as `Self::get_bits_at()`, with every chunk checked
+        let mut value = 0;
+        let mut remaining = num_bits;
+        while remaining > 0 {
+            let chunk_bits = match remaining % MAX_BITS_CHUNK {
+                0 => MAX_BITS_CHUNK,
+                bits => bits,
+            };
+            remaining -= chunk_bits;
+            let chunk = self.get_at(channel, chunk_bits);
+            self.try_advance_at(channel, &Self::Symbol::new(chunk, 1), chunk_bits)?;
+            value |= chunk << remaining;
+        }
+        Ok(value)
+    }
+
+    /// Reads an integer from the range `[0, n)` put with
+    /// [`RansEncoderMulti::put_uniform_at()`](crate::RansEncoderMulti::put_uniform_at)
+    /// from the specified channel. `n` has to be the same as the one the
+    /// value was put with.
+    ///
+    /// # Panics
+    /// Panics if `n` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.put_uniform_at(0, 6, 7);
+    /// encoder.put_uniform_at(1, 0, 100);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new(encoder.data());
+    /// assert_eq!(decoder.get_uniform_at(1, 7), 6);
+    /// assert_eq!(decoder.get_uniform_at(0, 100), 0);
+    /// ```
+    fn get_uniform_at(&mut self, channel: usize, n: u32) -> u32 {
+        assert!(n > 0, "n must not be zero");
+
+        let (num_symbols, low_bits) = uniform_split(n);
+        let mut high = 0;
+        if num_symbols > 1 {
+            high = uniform_value(self.get_at(channel, MAX_BITS_CHUNK), num_symbols);
+            let (cum_freq, freq) = uniform_symbol(high, num_symbols);
+            self.advance_at(channel, &Self::Symbol::new(cum_freq, freq), MAX_BITS_CHUNK);
+        }
+        (high << low_bits) | self.get_bits_at(channel, low_bits)
+    }
+
+    /// Decodes a single symbol at given channel using given model and returns
+    /// its index. Equivalent to calling [`Self::get_at()`], looking up the
+    /// symbol in the model and calling [`Self::advance_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let model = Model::from_freqs(vec![2, 2], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]);
+    /// assert_eq!(decoder.decode_symbol_at(0, &dec_model), 1);
+    /// assert_eq!(decoder.decode_symbol_at(1, &dec_model), 0);
+    /// ```
+    fn decode_symbol_at(&mut self, channel: usize, model: &DecModel<Self::Symbol>) -> usize {
+        let cum_freq = self.get_at(channel, model.scale_bits());
+        let (index, symbol) = model.lookup(cum_freq);
+        self.advance_at(channel, symbol, model.scale_bits());
+        index
+    }
+
+    /// Decodes a batch of `n` symbols put with
+    /// [`RansEncoderMulti::put_all()`](crate::RansEncoderMulti::put_all) using
+    /// given model and returns their indices, in the order they were put in.
+    /// The `i`-th symbol is read from channel `i % N`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let model = Model::from_freqs(vec![2, 2], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]);
+    /// assert_eq!(decoder.decode_n(&dec_model, 2), [1, 0]);
+    /// ```
+    fn decode_n(&mut self, model: &DecModel<Self::Symbol>, n: usize) -> Vec<usize> {
+        (0..n)
+            .map(|i| self.decode_symbol_at(i % N, model))
+            .collect()
+    }
+
+    /// Decodes a single symbol at given channel, put by the encoders'
+    /// `put_alias_at()` methods using given alias table, and returns its
+    /// index.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.put_alias_at(0, &table, 1);
+    /// encoder.put_alias_at(1, &table, 2);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new(encoder.data());
+    /// assert_eq!(decoder.decode_alias_at(0, &table), 2);
+    /// assert_eq!(decoder.decode_alias_at(1, &table), 1);
+    /// ```
+    fn decode_alias_at(&mut self, channel: usize, table: &AliasTable) -> usize {
+        let slot = self.get_at(channel, table.scale_bits());
+        let (index, offset) = table.lookup(slot);
+        // Pretend the symbol starts `offset` slots before the current one,
+        // which is all the decoder needs to know about it
+        let symbol = Self::Symbol::new(slot - offset, table.freq(index));
+        self.advance_at(channel, &symbol, table.scale_bits());
+        index
+    }
 }
 
 /// Single-stream rANS decoder interface.
@@ -143,10 +513,149 @@ pub trait RansDecoder: RansDecoderMulti<1> {
     fn advance(&mut self, symbol: &Self::Symbol, scale_bits: u32) {
         self.advance_at(0, symbol, scale_bits);
     }
+
+    /// Advances the data position after reading a symbol, checking that the
+    /// data doesn't end first. See [`RansDecoderMulti::try_advance_at()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to renormalize the state, in which case the decoder shouldn't be used
+    /// anymore.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::{RansDecSymbol, RansDecoder, RansError};
+    ///
+    /// let mut decoder = ByteRansDecoder::new([2, 0, 0, 2]);
+    /// assert_eq!(
+    ///     decoder.try_advance(&ByteRansDecSymbol::new(2, 2), 4),
+    ///     Err(RansError::InputExhausted)
+    /// );
+    /// ```
+    fn try_advance(&mut self, symbol: &Self::Symbol, scale_bits: u32) -> Result<(), RansError> {
+        self.try_advance_at(0, symbol, scale_bits)
+    }
+
+    /// Reads `num_bits` raw bits put with
+    /// [`RansEncoder::put_bits()`](crate::RansEncoder::put_bits). See
+    /// [`RansDecoderMulti::get_bits_at()`].
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::RansDecoder;
+    ///
+    /// let mut decoder = ByteRansDecoder::new([11, 0, 0, 8]);
+    /// assert_eq!(decoder.get_bits(4), 0b1011);
+    /// ```
+    fn get_bits(&mut self, num_bits: u32) -> u32 {
+        self.get_bits_at(0, num_bits)
+    }
+
+    /// Reads `num_bits` raw bits, checking that the data doesn't end first.
+    /// See [`RansDecoderMulti::try_get_bits_at()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to read the bits, in which case the decoder shouldn't be used anymore.
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::{RansDecoder, RansError};
+    ///
+    /// let mut decoder = ByteRansDecoder::new([11, 0, 0, 8]);
+    /// assert_eq!(decoder.try_get_bits(4), Ok(0b1011));
+    /// assert_eq!(decoder.try_get_bits(8), Err(RansError::InputExhausted));
+    /// ```
+    fn try_get_bits(&mut self, num_bits: u32) -> Result<u32, RansError> {
+        self.try_get_bits_at(0, num_bits)
+    }
+
+    /// Reads an integer from the range `[0, n)` put with
+    /// [`RansEncoder::put_uniform()`](crate::RansEncoder::put_uniform). See
+    /// [`RansDecoderMulti::get_uniform_at()`].
+    ///
+    /// # Panics
+    /// Panics if `n` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::{RansDecoder, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put_uniform(4, 10);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = ByteRansDecoder::new(encoder.data());
+    /// assert_eq!(decoder.get_uniform(10), 4);
+    /// ```
+    fn get_uniform(&mut self, n: u32) -> u32 {
+        self.get_uniform_at(0, n)
+    }
+
+    /// Decodes a single symbol using given model and returns its index.
+    /// Equivalent to calling [`Self::get()`], looking up the symbol in the
+    /// model and calling [`Self::advance()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::RansDecoder;
+    ///
+    /// let model = Model::from_freqs(vec![2, 2], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder = ByteRansDecoder::new([2, 0, 0, 2]);
+    /// assert_eq!(decoder.decode_symbol(&dec_model), 1);
+    /// assert_eq!(decoder.decode_symbol(&dec_model), 0);
+    /// ```
+    fn decode_symbol(&mut self, model: &DecModel<Self::Symbol>) -> usize {
+        self.decode_symbol_at(0, model)
+    }
+
+    /// Decodes a single symbol put by the encoders' `put_alias()` methods
+    /// using given alias table, and returns its index. See
+    /// [`RansDecoderMulti::decode_alias_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    /// use rans::{RansDecoder, RansEncoder, RansEncoderMulti};
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put_alias(&table, 1);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = ByteRansDecoder::new(encoder.data());
+    /// assert_eq!(decoder.decode_alias(&table), 1);
+    /// ```
+    fn decode_alias(&mut self, table: &AliasTable) -> usize {
+        self.decode_alias_at(0, table)
+    }
 }
 
 /// A symbol that can be decoded using a rANS decoder.
 pub trait RansDecSymbol {
+    /// Maximum number of bits used for frequency quantization supported by
+    /// the decoder.
+    const MAX_SCALE_BITS: u32 = 16;
+
     /// Creates a new rANS decoder symbol instance.
     ///
     /// # Examples
@@ -159,6 +668,33 @@ pub trait RansDecSymbol {
     #[must_use]
     fn new(cum_freq: u32, freq: u32) -> Self;
 
+    /// Creates a new rANS decoder symbol instance, checking that the symbol
+    /// definition is valid for given `scale_bits`.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidSymbol`] if `freq` is zero,
+    /// `cum_freq + freq` is larger than `1 << scale_bits`, or `scale_bits` is
+    /// larger than [`Self::MAX_SCALE_BITS`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecSymbol;
+    /// use rans::{RansDecSymbol, RansError};
+    ///
+    /// assert!(ByteRansDecSymbol::try_new(0, 16, 4).is_ok());
+    /// assert_eq!(
+    ///     ByteRansDecSymbol::try_new(0, 0, 4).unwrap_err(),
+    ///     RansError::InvalidSymbol
+    /// );
+    /// ```
+    fn try_new(cum_freq: u32, freq: u32, scale_bits: u32) -> Result<Self, RansError>
+    where
+        Self: Sized,
+    {
+        check_symbol(cum_freq, freq, scale_bits, Self::MAX_SCALE_BITS)?;
+        Ok(Self::new(cum_freq, freq))
+    }
+
     /// Returns this symbol's cumulative frequency.
     ///
     /// # Examples
@@ -186,19 +722,68 @@ pub trait RansDecSymbol {
     fn freq(&self) -> u32;
 }
 
+/// Snapshot of a decoder's channel states and read position, created with
+/// the decoders' `save_state()` methods.
+///
+/// Restoring a snapshot with the decoders' `restore_state()` methods rewinds
+/// the decoder to the moment the snapshot was created, which makes it
+/// possible to decode speculatively, e.g. to try decoding the data in
+/// several ways when sniffing its format.
+///
+/// # Examples
+/// ```
+/// use rans::byte_decoder::ByteRansDecoder;
+/// use rans::RansDecoder;
+///
+/// let mut decoder = ByteRansDecoder::new([11, 0, 0, 8]);
+/// let snapshot = decoder.save_state();
+/// assert_eq!(decoder.get_bits(4), 0b1011);
+///
+/// decoder.restore_state(&snapshot);
+/// assert_eq!(decoder.get_bits(4), 0b1011);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecSnapshot<S, const N: usize> {
+    pub(crate) states: [S; N],
+    pub(crate) pos: usize,
+}
+
+impl<S, const N: usize> DecSnapshot<S, N> {
+    /// Returns the number of bytes of the input the decoder had consumed at
+    /// the time the snapshot was created.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    ///
+    /// let decoder = ByteRansDecoder::new([11, 0, 0, 8]);
+    /// assert_eq!(decoder.save_state().position(), 4);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
 macro_rules! check_dec_pointer {
     ($self:ident) => {
-        debug_assert!($self.is_ptr_valid(), "Data pointer is in an invalid state. Make sure you are not reading more symbols than originally encoded.");
+        checked_assert!($self.is_ptr_valid(), "Data pointer is in an invalid state. Make sure you are not reading more symbols than originally encoded.");
     }
 }
 pub(crate) use check_dec_pointer;
 
 #[cfg(test)]
 pub(crate) mod tests {
+    use std::collections::HashSet;
     use std::fmt::Debug;
+    use std::hash::Hash;
 
+    use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
     use crate::decoder::RansDecSymbol;
-    use crate::{RansDecoder, RansDecoderMulti};
+    use crate::encoder::tests::BITS_DATA;
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::{RansDecoder, RansDecoderMulti, RansEncoder, RansEncoderMulti, RansError};
 
     pub(crate) fn test_decode_empty<T: RansDecoder>(mut decoder: T) {
         assert_eq!(decoder.get(2), 0);
@@ -216,6 +801,20 @@ pub(crate) mod tests {
         decoder.advance(&symbol1, 2);
     }
 
+    pub(crate) fn test_decode_symbols_with_model<T: RansDecoder>(mut decoder: T) {
+        let model = Model::from_freqs(vec![2, 2], 2);
+        let dec_model = DecModel::<T::Symbol>::new(&model);
+
+        assert_eq!(decoder.decode_symbol(&dec_model), 1);
+        assert_eq!(decoder.decode_symbol(&dec_model), 0);
+    }
+
+    pub(crate) fn test_decode_bits<T: RansDecoder>(mut decoder: T) {
+        for &(value, num_bits) in &BITS_DATA {
+            assert_eq!(decoder.get_bits(num_bits), value);
+        }
+    }
+
     pub(crate) fn test_decode_symbols_clone<T>(mut decoder: T)
     where
         T: RansDecoder,
@@ -276,6 +875,27 @@ pub(crate) mod tests {
         unreachable!("Invalid symbol frequency");
     }
 
+    pub(crate) fn test_save_restore_state<T: RansDecoder, S>(
+        mut decoder: T,
+        save_state: impl Fn(&T) -> S,
+        restore_state: impl Fn(&mut T, &S),
+    ) {
+        let snapshot = save_state(&decoder);
+        for &(value, num_bits) in &BITS_DATA {
+            assert_eq!(decoder.get_bits(num_bits), value);
+        }
+
+        restore_state(&mut decoder, &snapshot);
+        for &(value, num_bits) in &BITS_DATA {
+            let snapshot = save_state(&decoder);
+            // Decode the next symbol wrongly first
+            let _ = decoder.get_bits(7);
+            restore_state(&mut decoder, &snapshot);
+
+            assert_eq!(decoder.get_bits(num_bits), value);
+        }
+    }
+
     pub(crate) fn test_decode_interleaved<T: RansDecoderMulti<2>>(mut decoder: T) {
         const SCALE_BITS: u32 = 4;
         let symbol1 = T::Symbol::new(0, 4);
@@ -305,7 +925,251 @@ pub(crate) mod tests {
         decoder.renorm_all();
     }
 
+    pub(crate) fn test_put_all_decode_n<E, D>(mut encoder: E, decoder_fn: impl Fn(Vec<u8>) -> D)
+    where
+        E: RansEncoderMulti<3>,
+        D: RansDecoderMulti<3>,
+    {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100], 12);
+        let enc_model = EncModel::<E::Symbol>::new(&model);
+        let dec_model = DecModel::<D::Symbol>::new(&model);
+        let batch1: Vec<usize> = (0..100).map(|i| (i * 7 + i / 5) % 6).collect();
+        let batch2: Vec<usize> = (0..31).map(|i| i * 5 % 6).collect();
+
+        for batch in [&batch1, &batch2] {
+            let symbols: Vec<_> = batch.iter().map(|&i| enc_model.symbol(i)).collect();
+            encoder.put_all(&symbols);
+        }
+        encoder.flush_all();
+
+        let mut decoder = decoder_fn(encoder.data().to_vec());
+        assert_eq!(decoder.decode_n(&dec_model, batch2.len()), batch2);
+        assert_eq!(decoder.decode_n(&dec_model, batch1.len()), batch1);
+    }
+
+    pub(crate) fn test_decode_symbols_all<E, D>(mut encoder: E, decoder_fn: impl Fn(Vec<u8>) -> D)
+    where
+        E: RansEncoderMulti<3>,
+        D: RansDecoderMulti<3>,
+    {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100, 1, 1], 12);
+        let enc_model = EncModel::<E::Symbol>::new(&model);
+        let dec_model = DecModel::<D::Symbol>::new(&model);
+        let batch: Vec<usize> = (0..300).map(|i| (i * 7 + i / 5) % 8).collect();
+
+        let symbols: Vec<_> = batch.iter().map(|&i| enc_model.symbol(i)).collect();
+        encoder.put_all(&symbols);
+        encoder.flush_all();
+        let data = encoder.data().to_vec();
+
+        let mut decoder = decoder_fn(data.clone());
+        let decoded: Vec<usize> = (0..batch.len() / 3)
+            .flat_map(|_| decoder.decode_symbols_all(&dec_model))
+            .collect();
+        assert_eq!(decoded, batch);
+        assert_eq!(decoder.try_renorm_all(), Ok(()));
+
+        let mut decoder = decoder_fn(data[..data.len() - 2].to_vec());
+        let result = (0..batch.len() / 3).try_for_each(|_| {
+            for channel in 0..3 {
+                let cum_freq = decoder.get_at(channel, model.scale_bits());
+                let symbol = dec_model.symbol(dec_model.symbol_index(cum_freq));
+                decoder.advance_step_at(channel, symbol, model.scale_bits());
+            }
+            decoder.try_renorm_all()
+        });
+        assert_eq!(result, Err(RansError::InputExhausted));
+    }
+
+    pub(crate) fn test_uniform<E, D>(mut encoder: E, decoder_fn: impl Fn(Vec<u8>) -> D)
+    where
+        E: RansEncoder,
+        D: RansDecoder,
+    {
+        let values: Vec<(u32, u32)> = [1, 2, 3, 10, 255, 1 << 16, 65_537, 1_000_003, u32::MAX]
+            .iter()
+            .flat_map(|&n| [(0, n), (n / 3, n), (n - 1, n)])
+            .collect();
+
+        for &(value, n) in values.iter().rev() {
+            encoder.put_uniform(value, n);
+        }
+        encoder.flush();
+
+        let mut decoder = decoder_fn(encoder.data().to_vec());
+        for &(value, n) in &values {
+            assert_eq!(decoder.get_uniform(n), value);
+        }
+    }
+
+    pub(crate) fn test_sections<E, D>(mut encoder: E, decoder_fn: impl Fn(Vec<u8>) -> D)
+    where
+        E: RansEncoderMulti<2>,
+        D: RansDecoderMulti<2>,
+    {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100], 12);
+        let enc_model = EncModel::<E::Symbol>::new(&model);
+        let dec_model = DecModel::<D::Symbol>::new(&model);
+        let sections: Vec<Vec<usize>> = (0..10)
+            .map(|i| (0..i * 37).map(|j| (i + j * j) % 6).collect())
+            .collect();
+
+        let ranges: Vec<_> = sections
+            .iter()
+            .map(|symbols| {
+                let refs: Vec<_> = symbols.iter().map(|&s| enc_model.symbol(s)).collect();
+                encoder.put_all(&refs);
+                encoder.flush_section()
+            })
+            .collect();
+        assert_eq!(ranges.last().unwrap().end, encoder.len());
+
+        for (symbols, range) in sections.iter().zip(ranges) {
+            let mut decoder = decoder_fn(encoder.section_data(range).to_vec());
+            assert_eq!(decoder.decode_n(&dec_model, symbols.len()), *symbols);
+        }
+    }
+
+    pub(crate) fn test_is_exhausted<E, D>(
+        mut encoder: E,
+        decoder_fn: impl Fn(Vec<u8>) -> D,
+        is_exhausted: impl Fn(&D) -> bool,
+    ) where
+        E: RansEncoderMulti<2>,
+        D: RansDecoderMulti<2>,
+    {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100], 12);
+        let enc_model = EncModel::<E::Symbol>::new(&model);
+        let dec_model = DecModel::<D::Symbol>::new(&model);
+        let symbols: Vec<_> = (0..101).map(|i| enc_model.symbol(i * 7 % 6)).collect();
+        encoder.put_all(&symbols);
+        encoder.flush_all();
+
+        let mut decoder = decoder_fn(encoder.data().to_vec());
+        for i in 0..symbols.len() {
+            assert!(!is_exhausted(&decoder));
+            assert_eq!(decoder.decode_symbol_at(i % 2, &dec_model), i * 7 % 6);
+        }
+        assert!(is_exhausted(&decoder));
+    }
+
+    pub(crate) fn test_reinit_tail<E, D>(
+        encoder_fn: impl Fn() -> E,
+        decoder_fn: impl Fn(Vec<u8>) -> D,
+        reinit_tail: impl Fn(&mut D) -> Result<(), RansError>,
+    ) where
+        E: RansEncoderMulti<2>,
+        D: RansDecoderMulti<2>,
+    {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100], 12);
+        let enc_model = EncModel::<E::Symbol>::new(&model);
+        let dec_model = DecModel::<D::Symbol>::new(&model);
+        let blocks: Vec<Vec<usize>> = (0..5)
+            .map(|i| (0..i * 41).map(|j| (i + j * j) % 6).collect())
+            .collect();
+
+        let mut data = Vec::new();
+        for symbols in &blocks {
+            let mut encoder = encoder_fn();
+            let refs: Vec<_> = symbols.iter().map(|&s| enc_model.symbol(s)).collect();
+            encoder.put_all(&refs);
+            encoder.flush_all();
+            data.extend_from_slice(encoder.data());
+        }
+
+        let mut decoder = decoder_fn(data);
+        for (i, symbols) in blocks.iter().enumerate() {
+            if i > 0 {
+                reinit_tail(&mut decoder).unwrap();
+            }
+            assert_eq!(decoder.decode_n(&dec_model, symbols.len()), *symbols);
+        }
+        assert_eq!(reinit_tail(&mut decoder), Err(RansError::UnexpectedEof));
+    }
+
+    pub(crate) fn test_try_advance_truncated<E, D>(
+        mut encoder: E,
+        decoder_fn: impl Fn(Vec<u8>) -> D,
+        state_len: usize,
+    ) where
+        E: RansEncoderMulti<2>,
+        D: RansDecoderMulti<2>,
+    {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100], 12);
+        let enc_model = EncModel::<E::Symbol>::new(&model);
+        let dec_model = DecModel::<D::Symbol>::new(&model);
+        let symbols: Vec<_> = (0..500).map(|i| enc_model.symbol(i * 7 % 6)).collect();
+        encoder.put_all(&symbols);
+        encoder.flush_all();
+        let data = encoder.data();
+
+        for len in (2 * state_len..=data.len()).step_by(3) {
+            let mut decoder = decoder_fn(data[..len].to_vec());
+            let result = (0..symbols.len()).try_for_each(|i| {
+                let channel = i % 2;
+                let cum_freq = decoder.get_at(channel, model.scale_bits());
+                let symbol = dec_model.symbol(dec_model.symbol_index(cum_freq));
+                decoder.try_advance_at(channel, symbol, model.scale_bits())
+            });
+
+            if len == data.len() {
+                assert_eq!(result, Ok(()));
+            } else {
+                assert_eq!(result, Err(RansError::InputExhausted));
+            }
+        }
+    }
+
+    pub(crate) fn test_symbol_eq_hash<S: RansDecSymbol + Copy + Eq + Hash>() {
+        let symbol = S::new(3, 2);
+        let copy = symbol;
+        assert!(symbol == copy);
+        assert!(S::new(3, 2) == symbol);
+        assert!(S::new(3, 1) != symbol);
+        assert!(S::new(2, 2) != symbol);
+
+        let set: HashSet<_> = [symbol, copy, S::new(0, 1), S::new(3, 2)].into();
+        assert_eq!(set.len(), 2);
+    }
+
+    pub(crate) fn test_is_send_sync<T: Send + Sync>() {}
+
     pub(crate) fn test_has_debug_output<T: RansDecoder + Debug>(decoder: T) {
         assert!(!format!("{decoder:?}").is_empty());
     }
+
+    /// Decoder implementing only the required methods of
+    /// [`RansDecoderMulti`], to exercise the provided ones.
+    struct MinimalDecoder<'a>(ByteRansDecoderMulti<'a, 2>);
+
+    impl RansDecoderMulti<2> for MinimalDecoder<'_> {
+        type Symbol = ByteRansDecSymbol;
+
+        fn get_at(&mut self, channel: usize, scale_bits: u32) -> u32 {
+            self.0.get_at(channel, scale_bits)
+        }
+
+        fn advance_at(&mut self, channel: usize, symbol: &Self::Symbol, scale_bits: u32) {
+            self.0.advance_at(channel, symbol, scale_bits);
+        }
+
+        fn advance_step_at(&mut self, channel: usize, symbol: &Self::Symbol, scale_bits: u32) {
+            self.0.advance_step_at(channel, symbol, scale_bits);
+        }
+
+        fn renorm_at(&mut self, channel: usize) {
+            self.0.renorm_at(channel);
+        }
+    }
+
+    #[test]
+    fn test_default_try_renorm_at() {
+        let mut decoder = MinimalDecoder(ByteRansDecoderMulti::new([2, 0, 0, 1, 0, 0, 0, 1, 0, 0]));
+        let symbol = ByteRansDecSymbol::new(2, 2);
+
+        assert_eq!(decoder.get_at(0, 2), 2);
+        assert_eq!(decoder.try_advance_at(0, &symbol, 2), Ok(()));
+        assert_eq!(decoder.try_renorm_all(), Ok(()));
+        assert_eq!(decoder.get_at(0, 2), 0);
+    }
 }
@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::fmt::Debug;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::mut_cow::MutCow;
+
+/// Read-only input data of a decoder.
+///
+/// Decoding never modifies the input, so the decoders can operate on borrowed
+/// slices, owned buffers and buffers shared between threads alike. You
+/// usually don't need to create this directly, as the decoder constructors
+/// accept anything that can be converted into `DecoderInput`.
+pub enum DecoderInput<'a> {
+    /// Borrowed data.
+    Borrowed(&'a [u8]),
+    /// Owned data.
+    Owned(Vec<u8>),
+    /// Data shared through reference counting.
+    Shared(Arc<[u8]>),
+    /// Read-only memory-mapped file.
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Debug for DecoderInput<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a> From<&'a [u8]> for DecoderInput<'a> {
+    fn from(reference: &'a [u8]) -> Self {
+        Self::Borrowed(reference)
+    }
+}
+
+impl<'a, const N: usize> From<&'a [u8; N]> for DecoderInput<'a> {
+    fn from(reference: &'a [u8; N]) -> Self {
+        Self::Borrowed(reference)
+    }
+}
+
+impl<'a> From<&'a mut [u8]> for DecoderInput<'a> {
+    fn from(reference: &'a mut [u8]) -> Self {
+        Self::Borrowed(reference)
+    }
+}
+
+impl<'a, const N: usize> From<&'a mut [u8; N]> for DecoderInput<'a> {
+    fn from(reference: &'a mut [u8; N]) -> Self {
+        Self::Borrowed(reference)
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for DecoderInput<'_> {
+    fn from(owned: [u8; N]) -> Self {
+        Self::Owned(Vec::from(owned))
+    }
+}
+
+impl From<Vec<u8>> for DecoderInput<'_> {
+    fn from(owned: Vec<u8>) -> Self {
+        Self::Owned(owned)
+    }
+}
+
+impl From<Arc<[u8]>> for DecoderInput<'_> {
+    fn from(shared: Arc<[u8]>) -> Self {
+        Self::Shared(shared)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl From<memmap2::Mmap> for DecoderInput<'_> {
+    fn from(mapped: memmap2::Mmap) -> Self {
+        Self::Mapped(mapped)
+    }
+}
+
+impl<'a> From<Cow<'a, [u8]>> for DecoderInput<'a> {
+    fn from(cow: Cow<'a, [u8]>) -> Self {
+        match cow {
+            Cow::Borrowed(reference) => Self::Borrowed(reference),
+            Cow::Owned(owned) => Self::Owned(owned),
+        }
+    }
+}
+
+/// Reborrows mutably borrowed data immutably; decoders never modify their
+/// input, so the borrowed data is left intact.
+impl<'a> From<MutCow<'a, [u8]>> for DecoderInput<'a> {
+    fn from(mut_cow: MutCow<'a, [u8]>) -> Self {
+        match mut_cow {
+            MutCow::Borrowed(reference) => Self::Borrowed(reference),
+            MutCow::Owned(owned) => Self::Owned(owned),
+        }
+    }
+}
+
+impl Deref for DecoderInput<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            DecoderInput::Borrowed(reference) => reference,
+            DecoderInput::Owned(owned) => owned,
+            DecoderInput::Shared(shared) => shared,
+            #[cfg(feature = "mmap")]
+            DecoderInput::Mapped(mapped) => mapped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::sync::Arc;
+
+    use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    use crate::decoder_input::DecoderInput;
+    use crate::frame::{FrameDecoder, FrameEncoder};
+    use crate::model::{DecModel, Model};
+    use crate::mut_cow::MutCow;
+    use crate::RansDecoderMulti;
+
+    #[test]
+    fn test_create_from_slice() {
+        let data = vec![1, 2, 3, 4];
+        let input = DecoderInput::from(data.as_slice());
+
+        assert!(matches!(input, DecoderInput::Borrowed(_)));
+        assert_eq!(*input, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_create_from_owned() {
+        let input = DecoderInput::from(vec![1, 2, 3, 4]);
+
+        assert!(matches!(input, DecoderInput::Owned(_)));
+        assert_eq!(*input, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_create_from_arc() {
+        let data: Arc<[u8]> = Arc::from([1, 2, 3, 4]);
+        let input = DecoderInput::from(Arc::clone(&data));
+
+        assert!(matches!(input, DecoderInput::Shared(_)));
+        assert_eq!(input.as_ptr(), data.as_ptr());
+    }
+
+    #[test]
+    fn test_create_from_cow() {
+        let data = [1, 2, 3, 4];
+        let borrowed = DecoderInput::from(Cow::Borrowed(&data[..]));
+        let owned = DecoderInput::from(Cow::<[u8]>::Owned(data.to_vec()));
+
+        assert!(matches!(borrowed, DecoderInput::Borrowed(_)));
+        assert!(matches!(owned, DecoderInput::Owned(_)));
+        assert_eq!(*borrowed, *owned);
+    }
+
+    #[test]
+    fn test_create_from_mut_cow() {
+        let mut data = [1, 2, 3, 4];
+        let input = DecoderInput::from(MutCow::from(&mut data));
+
+        assert!(matches!(input, DecoderInput::Borrowed(_)));
+        assert_eq!(*input, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_decoding_leaves_mut_cow_intact() {
+        let model = Model::from_counts(&[1, 2, 3], 8);
+        let symbols = [0, 1, 2, 2, 1, 2];
+        let data = FrameEncoder::new().encode(&model, &symbols);
+        let frame = FrameDecoder::new(&data).unwrap();
+        let mut payload = frame.payload().to_vec();
+        let expected = payload.clone();
+
+        let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+        let mut decoder = ByteRansDecoder::new(MutCow::from(payload.as_mut_slice()));
+        for symbol in symbols {
+            assert_eq!(decoder.decode_symbol_at(0, &dec_model), symbol);
+        }
+        drop(decoder);
+
+        assert_eq!(payload, expected);
+    }
+
+    #[test]
+    fn test_has_debug_output() {
+        let input = DecoderInput::from(vec![1, 2, 3, 4]);
+        assert_eq!(format!("{input:?}"), "[1, 2, 3, 4]");
+    }
+}
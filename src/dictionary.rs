@@ -0,0 +1,293 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::model::{DecModel, EncModel, Model};
+use crate::simple::decode_symbols_checked;
+use crate::{varint, RansEncoder, RansEncoderMulti, RansError};
+
+/// Preset byte model shared by the compressing and decompressing side, so
+/// that it doesn't have to be stored with every message.
+///
+/// For small messages, the frequency table embedded by compressors such as
+/// [`crate::simple::compress()`] often takes more space than the data
+/// itself. A dictionary is trained once on sample data, distributed (e.g.
+/// serialized with [`Self::to_bytes()`]), and then used to compress many
+/// messages, each of which only contains its length and the rANS bitstream.
+///
+/// The messages do not identify the dictionary they were compressed with;
+/// decompressing them with a different one produces garbage.
+///
+/// # Examples
+/// ```
+/// use rans::dictionary::Dictionary;
+///
+/// let samples = ["GET /index.html", "GET /about.html", "POST /login"];
+/// let dictionary = Dictionary::train(&samples, 12);
+///
+/// let compressed = dictionary.compress(b"GET /login.html");
+/// assert!(compressed.len() < 15);
+/// assert_eq!(dictionary.decompress(&compressed).unwrap(), b"GET /login.html");
+///
+/// let received = Dictionary::from_bytes(&dictionary.to_bytes()).unwrap();
+/// assert_eq!(received.decompress(&compressed).unwrap(), b"GET /login.html");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    model: Model,
+    enc_model: EncModel<ByteRansEncSymbol>,
+    dec_model: DecModel<ByteRansDecSymbol>,
+}
+
+impl Dictionary {
+    /// Creates a new dictionary using given model.
+    ///
+    /// # Panics
+    /// Panics if the model has more than 256 symbols or if its `scale_bits`
+    /// is larger than 16.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::dictionary::Dictionary;
+    /// use rans::model::Model;
+    ///
+    /// let dictionary = Dictionary::new(Model::from_counts(&[1; 256], 8));
+    /// assert_eq!(dictionary.model().scale_bits(), 8);
+    /// ```
+    #[must_use]
+    pub fn new(model: Model) -> Self {
+        assert!(
+            model.len() <= 256,
+            "dictionary models must not have more than 256 symbols"
+        );
+        assert!(
+            model.scale_bits() <= 16,
+            "scale_bits must not be larger than 16"
+        );
+
+        Self {
+            enc_model: EncModel::new(&model),
+            dec_model: DecModel::new(&model),
+            model,
+        }
+    }
+
+    /// Creates a new dictionary trained on given sample data. See
+    /// [`Model::train()`].
+    ///
+    /// # Panics
+    /// Panics if `scale_bits` is smaller than 8 or larger than 16.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::dictionary::Dictionary;
+    ///
+    /// let dictionary = Dictionary::train(&[b"abc", b"abd"], 10);
+    /// assert_eq!(dictionary.model().len(), 256);
+    /// ```
+    #[must_use]
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], scale_bits: u32) -> Self {
+        Self::new(Model::train(samples, scale_bits))
+    }
+
+    /// Reads a dictionary serialized with [`Self::to_bytes()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the data is truncated,
+    /// [`RansError::InvalidHeader`] if there is trailing data or the
+    /// `scale_bits` is not supported, or [`RansError::InvalidModel`] if the
+    /// frequency table is malformed or has more than 256 symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::dictionary::Dictionary;
+    /// use rans::RansError;
+    ///
+    /// let bytes = Dictionary::train(&["sample"], 12).to_bytes();
+    /// assert!(Dictionary::from_bytes(&bytes).is_ok());
+    /// assert_eq!(
+    ///     Dictionary::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+    ///     RansError::UnexpectedEof
+    /// );
+    /// ```
+    pub fn from_bytes(data: &[u8]) -> Result<Self, RansError> {
+        let model = Model::from_bytes(data)?;
+        if model.scale_bits() > 16 {
+            return Err(RansError::InvalidHeader);
+        }
+        if model.len() > 256 {
+            return Err(RansError::InvalidModel);
+        }
+
+        Ok(Self::new(model))
+    }
+
+    /// Serializes this dictionary's model. See [`Model::to_bytes()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::dictionary::Dictionary;
+    /// use rans::model::Model;
+    ///
+    /// let dictionary = Dictionary::new(Model::from_counts(&[3, 1], 2));
+    /// assert_eq!(dictionary.to_bytes(), [2, 2, 7, 4]);
+    /// ```
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.model.to_bytes()
+    }
+
+    /// Returns the model used by this dictionary.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::dictionary::Dictionary;
+    ///
+    /// let dictionary = Dictionary::train(&["aaab"], 12);
+    /// assert_eq!(dictionary.model().freq(usize::from(b'a')), 2461);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Compresses a message using this dictionary. Use [`Self::decompress()`]
+    /// with the same dictionary to get the original data back.
+    ///
+    /// # Panics
+    /// Panics if the message contains a byte with zero frequency in the
+    /// dictionary's model. This can't happen for trained dictionaries.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::dictionary::Dictionary;
+    ///
+    /// let dictionary = Dictionary::train(&["aaaaaaab"], 12);
+    /// assert_eq!(dictionary.compress(b""), [0]);
+    /// assert_eq!(dictionary.compress(b"aaaaaaaaaaaa").len(), 5);
+    /// ```
+    #[must_use]
+    pub fn compress(&self, data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        varint::write_u64(&mut output, data.len() as u64);
+        if data.is_empty() {
+            return output;
+        }
+
+        // With at most 16-bit frequencies, a single symbol emits at most
+        // 2 bytes.
+        let mut encoder = ByteRansEncoder::new(data.len() * 2 + 4);
+        for &byte in data.iter().rev() {
+            let symbol = byte as usize;
+            assert!(
+                symbol < self.model.len() && self.model.freq(symbol) > 0,
+                "byte not present in the dictionary"
+            );
+            encoder.put_index(symbol, &self.enc_model);
+        }
+        encoder.flush();
+
+        output.extend_from_slice(encoder.data());
+        output
+    }
+
+    /// Decompresses a message produced by [`Self::compress()`] with the same
+    /// dictionary.
+    ///
+    /// Malformed data results in an error, but, as with
+    /// [`simple::decompress()`](crate::simple::decompress), the output length
+    /// is only limited by the header.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the input is truncated,
+    /// [`RansError::InvalidHeader`] if the length is malformed, or
+    /// [`RansError::InputExhausted`] if the bitstream ends before all the
+    /// bytes are decoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::dictionary::Dictionary;
+    /// use rans::RansError;
+    ///
+    /// let dictionary = Dictionary::train(&["hello"], 12);
+    /// let compressed = dictionary.compress(b"hello");
+    /// assert_eq!(dictionary.decompress(&compressed).unwrap(), b"hello");
+    /// assert_eq!(dictionary.decompress(&compressed[..2]), Err(RansError::UnexpectedEof));
+    /// ```
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, RansError> {
+        let mut pos = 0;
+        let len = usize::try_from(varint::read_u64(data, &mut pos)?)
+            .map_err(|_| RansError::InvalidHeader)?;
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+
+        let payload = &data[pos..];
+        if payload.len() < 4 {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        decode_symbols_checked(&mut ByteRansDecoder::new(payload), &self.dec_model, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::Dictionary;
+    use crate::model::Model;
+    use crate::{varint, RansError};
+
+    #[test]
+    fn test_round_trip_unseen_bytes() {
+        let dictionary = Dictionary::train(&["abc"; 100], 12);
+        let data: Vec<u8> = (0..=255).collect();
+
+        let compressed = dictionary.compress(&data);
+        assert_eq!(dictionary.decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_smaller_than_embedded_table() {
+        let samples: Vec<String> = (0..100)
+            .map(|i| format!("{{\"id\": {i}, \"name\": \"user{i}\"}}"))
+            .collect();
+        let dictionary = Dictionary::train(&samples, 12);
+        let message = b"{\"id\": 123, \"name\": \"user123\"}";
+
+        let compressed = dictionary.compress(message);
+        assert!(compressed.len() < crate::simple::compress(message).len() / 2);
+        assert_eq!(dictionary.decompress(&compressed).unwrap(), message);
+    }
+
+    #[test]
+    fn test_declared_len_too_large() {
+        let dictionary = Dictionary::train(&["abc"], 12);
+        let compressed = dictionary.compress(b"abc");
+        let mut corrupted = Vec::new();
+        varint::write_u64(&mut corrupted, (1 << 46) - 1);
+        corrupted.extend_from_slice(&compressed[1..]);
+
+        assert_eq!(
+            dictionary.decompress(&corrupted),
+            Err(RansError::InputExhausted)
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_trailing_data() {
+        let mut bytes = Dictionary::train(&["abc"], 12).to_bytes();
+        bytes.push(0);
+
+        assert_eq!(
+            Dictionary::from_bytes(&bytes).unwrap_err(),
+            RansError::InvalidHeader
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "not present in the dictionary")]
+    fn test_compress_missing_byte() {
+        let dictionary = Dictionary::new(Model::from_counts(&[1, 1], 4));
+
+        let _ = dictionary.compress(&[0, 1, 2]);
+    }
+}
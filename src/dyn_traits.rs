@@ -0,0 +1,491 @@
+use crate::b64_decoder::{B64RansDecSymbol, B64RansDecoderMulti};
+use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoderMulti};
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti, DynRansDecoderMulti};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti, DynRansEncoderMulti};
+use crate::encoder::MAX_BITS_CHUNK;
+use crate::model::Model;
+use crate::simd_decoder::{SimdRansDecSymbol, SimdRansDecoder};
+use crate::simd_encoder::{SimdRansEncSymbol, SimdRansEncoder};
+use crate::{RansDecSymbol, RansDecoderMulti, RansEncSymbol, RansEncoderMulti};
+
+/// Object-safe rANS encoder interface.
+///
+/// Unlike [`RansEncoderMulti`], this trait has no type parameters or
+/// associated types, so encoders of different kinds (and with different
+/// numbers of channels) can be used through a `Box<dyn RansEncoderDyn>`
+/// chosen at runtime. Symbols are passed as plain frequencies instead, which
+/// is slower than reusing prepared encoder symbols.
+///
+/// # Examples
+/// ```
+/// use rans::b64_encoder::B64RansEncoder;
+/// use rans::byte_encoder::ByteRansEncoderMulti;
+/// use rans::RansEncoderDyn;
+///
+/// fn create_encoder(kind: u8) -> Box<dyn RansEncoderDyn> {
+///     match kind {
+///         0 => Box::new(ByteRansEncoderMulti::<2>::new(1024)),
+///         _ => Box::new(B64RansEncoder::new(1024)),
+///     }
+/// }
+///
+/// let mut encoder = create_encoder(0);
+/// encoder.encode_symbol(1, 2, 2, 2);
+/// encoder.finish();
+/// assert_eq!(encoder.encoded_data(), [2, 0, 0, 1, 0, 0, 128, 0]);
+/// ```
+pub trait RansEncoderDyn {
+    /// Returns the number of interleaved channels of this encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderDyn;
+    ///
+    /// let encoder: Box<dyn RansEncoderDyn> = Box::new(ByteRansEncoderMulti::<3>::new(1024));
+    /// assert_eq!(encoder.channel_count(), 3);
+    /// ```
+    #[must_use]
+    fn channel_count(&self) -> usize;
+
+    /// Encodes a symbol with given cumulative frequency and frequency into
+    /// the specified channel.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::RansEncoderDyn;
+    ///
+    /// let mut encoder: Box<dyn RansEncoderDyn> = Box::new(ByteRansEncoder::new(1024));
+    /// encoder.encode_symbol(0, 2, 2, 2);
+    /// encoder.finish();
+    /// assert_eq!(encoder.encoded_data(), [2, 0, 0, 1]);
+    /// ```
+    fn encode_symbol(&mut self, channel: usize, cum_freq: u32, freq: u32, scale_bits: u32);
+
+    /// Encodes the lowest `num_bits` bits of `value` into the specified
+    /// channel as raw bits. The result is the same as with
+    /// [`RansEncoderMulti::put_bits_at()`].
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::{RansDecoder, RansEncoderDyn};
+    ///
+    /// let mut encoder: Box<dyn RansEncoderDyn> = Box::new(ByteRansEncoder::new(1024));
+    /// encoder.encode_bits(0, 0xdead_beef, 32);
+    /// encoder.finish();
+    ///
+    /// let mut decoder = ByteRansDecoder::new(encoder.encoded_data());
+    /// assert_eq!(decoder.get_bits(32), 0xdead_beef);
+    /// ```
+    fn encode_bits(&mut self, channel: usize, value: u32, num_bits: u32) {
+        assert!(num_bits <= 32, "num_bits must not be larger than 32");
+
+        let mut shift = 0;
+        while shift < num_bits {
+            let chunk_bits = (num_bits - shift).min(MAX_BITS_CHUNK);
+            let chunk = (value >> shift) & ((1 << chunk_bits) - 1);
+            self.encode_symbol(channel, chunk, 1, chunk_bits);
+            shift += chunk_bits;
+        }
+    }
+
+    /// Encodes given symbol of a model into the specified channel.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::model::Model;
+    /// use rans::RansEncoderDyn;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// let mut encoder: Box<dyn RansEncoderDyn> = Box::new(ByteRansEncoder::new(1024));
+    /// encoder.encode_model_symbol(0, &model, 1);
+    /// encoder.finish();
+    /// assert_eq!(encoder.encoded_data(), [171, 170, 170, 0]);
+    /// ```
+    fn encode_model_symbol(&mut self, channel: usize, model: &Model, symbol: usize) {
+        self.encode_symbol(
+            channel,
+            model.cum_freq(symbol),
+            model.freq(symbol),
+            model.scale_bits(),
+        );
+    }
+
+    /// Flushes all the channels of the encoder. See
+    /// [`RansEncoderMulti::flush_all()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::RansEncoderDyn;
+    ///
+    /// let mut encoder: Box<dyn RansEncoderDyn> = Box::new(ByteRansEncoder::new(1024));
+    /// encoder.finish();
+    /// assert_eq!(encoder.encoded_data(), [0, 0, 128, 0]);
+    /// ```
+    fn finish(&mut self);
+
+    /// Returns the encoded data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::RansEncoderDyn;
+    ///
+    /// let encoder: Box<dyn RansEncoderDyn> = Box::new(ByteRansEncoder::new(1024));
+    /// assert_eq!(encoder.encoded_data(), []);
+    /// ```
+    #[must_use]
+    fn encoded_data(&self) -> &[u8];
+}
+
+/// Object-safe rANS decoder interface.
+///
+/// Unlike [`RansDecoderMulti`], this trait has no type parameters or
+/// associated types, so decoders of different kinds (and with different
+/// numbers of channels) can be used through a `Box<dyn RansDecoderDyn>`
+/// chosen at runtime, e.g. based on a format header. Symbols are passed as
+/// plain frequencies instead, which is slower than reusing prepared decoder
+/// symbols.
+///
+/// # Examples
+/// ```
+/// use rans::b64_decoder::B64RansDecoder;
+/// use rans::byte_decoder::ByteRansDecoder;
+/// use rans::model::Model;
+/// use rans::RansDecoderDyn;
+///
+/// fn create_decoder(header: u8, data: &[u8]) -> Box<dyn RansDecoderDyn + '_> {
+///     match header {
+///         0 => Box::new(ByteRansDecoder::new(data)),
+///         _ => Box::new(B64RansDecoder::new(data)),
+///     }
+/// }
+///
+/// let model = Model::from_counts(&[1, 3], 2);
+/// let mut decoder = create_decoder(0, &[171, 170, 170, 0]);
+/// assert_eq!(decoder.decode_model_symbol(0, &model), 1);
+/// ```
+pub trait RansDecoderDyn {
+    /// Returns the number of interleaved channels of this decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::RansDecoderDyn;
+    ///
+    /// let decoder: Box<dyn RansDecoderDyn> =
+    ///     Box::new(ByteRansDecoderMulti::<2>::new([0, 0, 128, 0, 0, 0, 128, 0]));
+    /// assert_eq!(decoder.channel_count(), 2);
+    /// ```
+    #[must_use]
+    fn channel_count(&self) -> usize;
+
+    /// Returns the cumulative frequency of the current symbol at the
+    /// specified channel. See [`RansDecoderMulti::get_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::RansDecoderDyn;
+    ///
+    /// let mut decoder: Box<dyn RansDecoderDyn> = Box::new(ByteRansDecoder::new([2, 0, 0, 1]));
+    /// assert_eq!(decoder.decode_cum_freq(0, 2), 2);
+    /// ```
+    #[must_use]
+    fn decode_cum_freq(&mut self, channel: usize, scale_bits: u32) -> u32;
+
+    /// Advances the specified channel past a symbol with given cumulative
+    /// frequency and frequency. See [`RansDecoderMulti::advance_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::RansDecoderDyn;
+    ///
+    /// let mut decoder: Box<dyn RansDecoderDyn> = Box::new(ByteRansDecoder::new([2, 0, 0, 2]));
+    /// decoder.advance_symbol(0, 2, 2, 2);
+    /// assert_eq!(decoder.decode_cum_freq(0, 2), 0);
+    /// ```
+    fn advance_symbol(&mut self, channel: usize, cum_freq: u32, freq: u32, scale_bits: u32);
+
+    /// Decodes `num_bits` raw bits from the specified channel. See
+    /// [`RansDecoderMulti::get_bits_at()`].
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::{RansDecoderDyn, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put_bits(0x1234_5678, 32);
+    /// encoder.flush();
+    ///
+    /// let mut decoder: Box<dyn RansDecoderDyn> = Box::new(ByteRansDecoder::new(encoder.data()));
+    /// assert_eq!(decoder.decode_bits(0, 32), 0x1234_5678);
+    /// ```
+    fn decode_bits(&mut self, channel: usize, num_bits: u32) -> u32 {
+        assert!(num_bits <= 32, "num_bits must not be larger than 32");
+
+        let mut value = 0;
+        let mut remaining = num_bits;
+        while remaining > 0 {
+            let chunk_bits = match remaining % MAX_BITS_CHUNK {
+                0 => MAX_BITS_CHUNK,
+                bits => bits,
+            };
+            remaining -= chunk_bits;
+            let chunk = self.decode_cum_freq(channel, chunk_bits);
+            self.advance_symbol(channel, chunk, 1, chunk_bits);
+            value |= chunk << remaining;
+        }
+        value
+    }
+
+    /// Decodes a single symbol of given model at the specified channel and
+    /// returns its index. The symbol is looked up with
+    /// [`Model::symbol_for()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::model::Model;
+    /// use rans::RansDecoderDyn;
+    ///
+    /// let model = Model::from_freqs(vec![2, 2], 2);
+    /// let mut decoder: Box<dyn RansDecoderDyn> = Box::new(ByteRansDecoder::new([2, 0, 0, 2]));
+    /// assert_eq!(decoder.decode_model_symbol(0, &model), 1);
+    /// assert_eq!(decoder.decode_model_symbol(0, &model), 0);
+    /// ```
+    fn decode_model_symbol(&mut self, channel: usize, model: &Model) -> usize {
+        let scale_bits = model.scale_bits();
+        let symbol = model.symbol_for(self.decode_cum_freq(channel, scale_bits));
+        self.advance_symbol(
+            channel,
+            model.cum_freq(symbol),
+            model.freq(symbol),
+            scale_bits,
+        );
+        symbol
+    }
+
+    /// Returns the number of bytes of the input data that haven't been read
+    /// yet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::RansDecoderDyn;
+    ///
+    /// let decoder: Box<dyn RansDecoderDyn> = Box::new(ByteRansDecoder::new([0, 0, 128, 0, 42]));
+    /// assert_eq!(decoder.bytes_remaining(), 1);
+    /// ```
+    #[must_use]
+    fn bytes_remaining(&self) -> usize;
+
+    /// Returns the position of the decoder in the input data, i.e. the number
+    /// of bytes read so far.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::RansDecoderDyn;
+    ///
+    /// let decoder: Box<dyn RansDecoderDyn> = Box::new(ByteRansDecoder::new([0, 0, 128, 0, 42]));
+    /// assert_eq!(decoder.position(), 4);
+    /// ```
+    #[doc(alias = "bytes_consumed")]
+    #[must_use]
+    fn position(&self) -> usize;
+}
+
+macro_rules! impl_encoder_dyn {
+    ($($encoder:ident: $symbol:ty),*) => {$(
+        impl<const N: usize> RansEncoderDyn for $encoder<N> {
+            fn channel_count(&self) -> usize {
+                N
+            }
+
+            fn encode_symbol(&mut self, channel: usize, cum_freq: u32, freq: u32, scale_bits: u32) {
+                self.put_at(channel, &<$symbol>::new(cum_freq, freq, scale_bits));
+            }
+
+            fn finish(&mut self) {
+                self.flush_all();
+            }
+
+            fn encoded_data(&self) -> &[u8] {
+                self.data()
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_decoder_dyn {
+    ($($decoder:ident: $symbol:ty),*) => {$(
+        impl<const N: usize> RansDecoderDyn for $decoder<'_, N> {
+            fn channel_count(&self) -> usize {
+                N
+            }
+
+            fn decode_cum_freq(&mut self, channel: usize, scale_bits: u32) -> u32 {
+                self.get_at(channel, scale_bits)
+            }
+
+            fn advance_symbol(&mut self, channel: usize, cum_freq: u32, freq: u32, scale_bits: u32) {
+                self.advance_at(channel, &<$symbol>::new(cum_freq, freq), scale_bits);
+            }
+
+            fn bytes_remaining(&self) -> usize {
+                $decoder::bytes_remaining(self)
+            }
+
+            fn position(&self) -> usize {
+                $decoder::position(self)
+            }
+        }
+    )*};
+}
+
+impl_encoder_dyn!(
+    ByteRansEncoderMulti: ByteRansEncSymbol,
+    B64RansEncoderMulti: B64RansEncSymbol,
+    SimdRansEncoder: SimdRansEncSymbol
+);
+impl_decoder_dyn!(
+    ByteRansDecoderMulti: ByteRansDecSymbol,
+    B64RansDecoderMulti: B64RansDecSymbol,
+    SimdRansDecoder: SimdRansDecSymbol
+);
+
+impl RansEncoderDyn for DynRansEncoderMulti {
+    fn channel_count(&self) -> usize {
+        self.channels()
+    }
+
+    fn encode_symbol(&mut self, channel: usize, cum_freq: u32, freq: u32, scale_bits: u32) {
+        self.put_at(channel, &ByteRansEncSymbol::new(cum_freq, freq, scale_bits));
+    }
+
+    fn finish(&mut self) {
+        self.flush_all();
+    }
+
+    fn encoded_data(&self) -> &[u8] {
+        self.data()
+    }
+}
+
+impl RansDecoderDyn for DynRansDecoderMulti<'_> {
+    fn channel_count(&self) -> usize {
+        self.channels()
+    }
+
+    fn decode_cum_freq(&mut self, channel: usize, scale_bits: u32) -> u32 {
+        self.get_at(channel, scale_bits)
+    }
+
+    fn advance_symbol(&mut self, channel: usize, cum_freq: u32, freq: u32, scale_bits: u32) {
+        self.advance_at(channel, &ByteRansDecSymbol::new(cum_freq, freq), scale_bits);
+    }
+
+    fn bytes_remaining(&self) -> usize {
+        DynRansDecoderMulti::bytes_remaining(self)
+    }
+
+    fn position(&self) -> usize {
+        DynRansDecoderMulti::position(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::b64_decoder::B64RansDecoderMulti;
+    use crate::b64_encoder::B64RansEncoderMulti;
+    use crate::byte_decoder::{ByteRansDecoderMulti, DynRansDecoderMulti};
+    use crate::byte_encoder::{ByteRansEncoderMulti, DynRansEncoderMulti};
+    use crate::model::Model;
+    use crate::simd_decoder::SimdRansDecoder;
+    use crate::simd_encoder::SimdRansEncoder;
+    use crate::{RansDecoderDyn, RansEncoderDyn};
+
+    fn encoder_for(kind: u8) -> Box<dyn RansEncoderDyn> {
+        match kind {
+            0 => Box::new(ByteRansEncoderMulti::<2>::new(1 << 16)),
+            1 => Box::new(B64RansEncoderMulti::<3>::new(1 << 16)),
+            2 => Box::new(SimdRansEncoder::<4>::new(1 << 16)),
+            _ => Box::new(DynRansEncoderMulti::new(5, 1 << 16)),
+        }
+    }
+
+    fn decoder_for(kind: u8, data: &[u8]) -> Box<dyn RansDecoderDyn + '_> {
+        match kind {
+            0 => Box::new(ByteRansDecoderMulti::<2>::new(data)),
+            1 => Box::new(B64RansDecoderMulti::<3>::new(data)),
+            2 => Box::new(SimdRansDecoder::<4>::new(data)),
+            _ => Box::new(DynRansDecoderMulti::new(5, data)),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let model = Model::from_counts(&[3, 10, 58, 1, 34], 12);
+        let symbols: Vec<usize> = (0..1000).map(|i| (i * 13 + i / 3) % 5).collect();
+
+        for kind in 0..4 {
+            let mut encoder = encoder_for(kind);
+            let channels = encoder.channel_count();
+            for (i, &symbol) in symbols.iter().enumerate().rev() {
+                let channel = channels - 1 - i % channels;
+                encoder.encode_bits(channel, i as u32, 10);
+                encoder.encode_model_symbol(channel, &model, symbol);
+            }
+            encoder.finish();
+
+            let mut decoder = decoder_for(kind, encoder.encoded_data());
+            assert_eq!(decoder.channel_count(), channels);
+            for (i, &symbol) in symbols.iter().enumerate() {
+                let channel = i % channels;
+                assert_eq!(decoder.decode_model_symbol(channel, &model), symbol);
+                assert_eq!(decoder.decode_bits(channel, 10), i as u32);
+            }
+            assert_eq!(decoder.bytes_remaining(), 0);
+        }
+    }
+
+    #[test]
+    fn test_position_with_trailing_data() {
+        let model = Model::from_counts(&[3, 10, 58, 1, 34], 12);
+        let symbols: Vec<usize> = (0..1000).map(|i| (i * 7 + i / 5) % 5).collect();
+
+        for kind in 0..4 {
+            let mut encoder = encoder_for(kind);
+            let channels = encoder.channel_count();
+            for (i, &symbol) in symbols.iter().enumerate().rev() {
+                encoder.encode_model_symbol(channels - 1 - i % channels, &model, symbol);
+            }
+            encoder.finish();
+            let mut data = encoder.encoded_data().to_vec();
+            let payload_len = data.len();
+            data.extend_from_slice(b"trailing data");
+
+            let mut decoder = decoder_for(kind, &data);
+            for (i, &symbol) in symbols.iter().enumerate() {
+                assert_eq!(decoder.decode_model_symbol(i % channels, &model), symbol);
+            }
+            assert_eq!(decoder.position(), payload_len);
+            assert_eq!(decoder.bytes_remaining(), b"trailing data".len());
+        }
+    }
+}
@@ -1,10 +1,18 @@
+use std::ops::Range;
+
+use crate::model::EncModel;
+use crate::RansError;
+
+/// Maximum number of raw bits put into an encoder as a single symbol.
+pub(crate) const MAX_BITS_CHUNK: u32 = 16;
+
 /// Interleaved multi-stream rANS encoder interface.
 pub trait RansEncoderMulti<const N: usize> {
     /// Type of a Symbol value that can be encoded using this encoder.
     type Symbol: RansEncSymbol;
 
     /// Resets this encoder's internal state, so that the internal buffer
-    /// becomes empty.
+    /// becomes empty. Use [`Self::clear()`] to keep the encoded data instead.
     ///
     /// # Examples
     /// ```
@@ -20,6 +28,56 @@ pub trait RansEncoderMulti<const N: usize> {
     /// ```
     fn reset(&mut self);
 
+    /// Resets the states of all the channels, but keeps the data encoded so
+    /// far, unlike [`Self::reset()`].
+    ///
+    /// After flushing, this makes it possible to encode another, independent
+    /// segment into the same buffer, in front of the previous ones. Since the
+    /// decoder reads the data forwards, the last encoded segment is decoded
+    /// first; once it's done, the next segment starts
+    /// [`bytes_remaining()`](crate::byte_decoder::ByteRansDecoderMulti::bytes_remaining)
+    /// bytes before the end of the data, and the decoder can continue with it
+    /// after a call to
+    /// [`reinit_tail()`](crate::byte_decoder::ByteRansDecoderMulti::reinit_tail).
+    ///
+    /// # Panics
+    /// The default implementation always panics, since the states can't be
+    /// reset without access to the encoder's internals; all the encoders of
+    /// this crate override it.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::{RansDecSymbol, RansDecoder, RansEncSymbol, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put(&ByteRansEncSymbol::new(0, 1, 4));
+    /// encoder.flush();
+    /// let first_len = encoder.len();
+    ///
+    /// encoder.clear();
+    /// assert_eq!(encoder.len(), first_len);
+    /// encoder.put(&ByteRansEncSymbol::new(3, 1, 4));
+    /// encoder.flush();
+    ///
+    /// let data = encoder.data();
+    /// let mut decoder = ByteRansDecoder::new(data);
+    /// assert_eq!(decoder.get(4), 3);
+    /// assert_eq!(decoder.bytes_remaining(), first_len);
+    ///
+    /// let mut decoder = ByteRansDecoder::new(&data[data.len() - first_len..]);
+    /// assert_eq!(decoder.get(4), 0);
+    ///
+    /// let mut decoder = ByteRansDecoder::new(data);
+    /// decoder.advance(&ByteRansDecSymbol::new(3, 1), 4);
+    /// decoder.reinit_tail().unwrap();
+    /// assert_eq!(decoder.get(4), 0);
+    /// ```
+    fn clear(&mut self) {
+        panic!("this encoder doesn't support clearing its states");
+    }
+
     /// Puts a symbol into the specified channel.
     ///
     /// # Examples
@@ -36,6 +94,223 @@ pub trait RansEncoderMulti<const N: usize> {
     /// ```
     fn put_at(&mut self, channel: usize, symbol: &Self::Symbol);
 
+    /// Puts a symbol into the specified channel, checking that the channel
+    /// index is valid and that there is enough space left in the buffer.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidChannel`] if `channel` is out of range, or
+    /// [`RansError::BufferOverflow`] if the symbol might not fit in the
+    /// buffer. In both cases, the encoder's state is left untouched.
+    ///
+    /// The default implementation only checks the channel index and then
+    /// calls [`Self::put_at()`]; encoders that can run out of space override
+    /// it to check the buffer as well.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::{RansEncSymbol, RansEncoderMulti, RansError};
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// let symbol = ByteRansEncSymbol::new(0, 1, 4);
+    /// assert_eq!(encoder.try_put_at(0, &symbol), Ok(()));
+    /// assert_eq!(encoder.try_put_at(2, &symbol), Err(RansError::InvalidChannel));
+    /// ```
+    fn try_put_at(&mut self, channel: usize, symbol: &Self::Symbol) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+
+        self.put_at(channel, symbol);
+        Ok(())
+    }
+
+    /// Puts the symbol with given index in `model` into the specified
+    /// channel. This lets the caller work with symbol indices only, leaving
+    /// the encoder symbols to the model.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of range of the model. In debug builds, or
+    /// with the `checked` feature enabled, also panics if the symbol has zero
+    /// frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{DecModel, EncModel, Model};
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let model = Model::from_counts(&[1, 2, 5], 4);
+    /// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.put_index_at(0, 2, &enc_model);
+    /// encoder.put_index_at(1, 0, &enc_model);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new(encoder.data());
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    /// assert_eq!(decoder.decode_symbol_at(1, &dec_model), 2);
+    /// assert_eq!(decoder.decode_symbol_at(0, &dec_model), 0);
+    /// ```
+    fn put_index_at(&mut self, channel: usize, index: usize, model: &EncModel<Self::Symbol>) {
+        checked_assert!(
+            model.contains(index),
+            "symbol {} has zero frequency in the model",
+            index
+        );
+
+        self.put_at(channel, model.symbol(index));
+    }
+
+    /// Puts the symbol with given index in `model` into the specified channel
+    /// like [`Self::put_index_at()`], checking that the symbol can be encoded
+    /// and that it fits in the buffer.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidSymbol`] if `index` is out of range of the
+    /// model or the symbol has zero frequency, or any of the errors of
+    /// [`Self::try_put_at()`]. In all cases, the encoder's state is left
+    /// untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::{RansEncoderMulti, RansError};
+    ///
+    /// let model = Model::from_counts(&[1, 0, 5], 4);
+    /// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// assert_eq!(encoder.try_put_index_at(0, 2, &enc_model), Ok(()));
+    /// assert_eq!(
+    ///     encoder.try_put_index_at(0, 1, &enc_model),
+    ///     Err(RansError::InvalidSymbol)
+    /// );
+    /// assert_eq!(
+    ///     encoder.try_put_index_at(2, 0, &enc_model),
+    ///     Err(RansError::InvalidChannel)
+    /// );
+    /// ```
+    fn try_put_index_at(
+        &mut self,
+        channel: usize,
+        index: usize,
+        model: &EncModel<Self::Symbol>,
+    ) -> Result<(), RansError> {
+        if !model.contains(index) {
+            return Err(RansError::InvalidSymbol);
+        }
+
+        self.try_put_at(channel, model.symbol(index))
+    }
+
+    /// Puts the lowest `num_bits` bits of `value` into the specified channel
+    /// as raw, uniformly distributed bits, bypassing any model. This is
+    /// equivalent to putting a symbol of frequency 1 with `value` as its
+    /// cumulative frequency and `num_bits` scale bits, split into chunks of
+    /// at most 16 bits.
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.put_bits_at(0, 0b101, 3);
+    /// encoder.put_bits_at(1, 0xdead_beef, 32);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.len(), 12);
+    /// ```
+    fn put_bits_at(&mut self, channel: usize, value: u32, num_bits: u32) {
+        assert!(num_bits <= 32, "num_bits must not be larger than 32");
+
+        // The decoder reads the most significant chunk first, so it has to be
+        // put last
+        let mut shift = 0;
+        while shift < num_bits {
+            let chunk_bits = (num_bits - shift).min(MAX_BITS_CHUNK);
+            let chunk = (value >> shift) & ((1 << chunk_bits) - 1);
+            self.put_at(channel, &Self::Symbol::new(chunk, 1, chunk_bits));
+            shift += chunk_bits;
+        }
+    }
+
+    /// Puts an integer from the range `[0, n)` into the specified channel,
+    /// with all the values of the range being (almost) equally probable.
+    /// Unlike [`Self::put_bits_at()`], `n` doesn't have to be a power of two.
+    ///
+    /// Ranges of up to `1 << 16` values are coded as a single symbol; for
+    /// larger ones, the lowest bits of `value` are put as raw bits.
+    ///
+    /// # Panics
+    /// Panics if `value` is not smaller than `n`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.put_uniform_at(0, 2, 3);
+    /// encoder.put_uniform_at(1, 1_000_000, 1_000_001);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new(encoder.data());
+    /// assert_eq!(decoder.get_uniform_at(1, 3), 2);
+    /// assert_eq!(decoder.get_uniform_at(0, 1_000_001), 1_000_000);
+    /// ```
+    fn put_uniform_at(&mut self, channel: usize, value: u32, n: u32) {
+        assert!(value < n, "value must be smaller than n");
+
+        // The decoder reads the symbol first, so it has to be put last
+        let (num_symbols, low_bits) = uniform_split(n);
+        self.put_bits_at(channel, value & ((1 << low_bits) - 1), low_bits);
+        if num_symbols > 1 {
+            let (cum_freq, freq) = uniform_symbol(value >> low_bits, num_symbols);
+            self.put_at(channel, &Self::Symbol::new(cum_freq, freq, MAX_BITS_CHUNK));
+        }
+    }
+
+    /// Puts a batch of symbols, spreading them over all the channels, so that
+    /// they can be decoded in their original order with a single call to
+    /// [`RansDecoderMulti::decode_n()`](crate::RansDecoderMulti::decode_n).
+    ///
+    /// The symbols are put in reverse order, with the `i`-th symbol going into
+    /// channel `N - 1 - i % N`. As with all the other methods, the last batch
+    /// put is the first one decoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{DecModel, EncModel, Model};
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let model = Model::from_counts(&[1, 2, 5], 4);
+    /// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    /// let symbols: Vec<_> = [2, 0, 1, 2, 2].iter().map(|&i| enc_model.symbol(i)).collect();
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.put_all(&symbols);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = ByteRansDecoderMulti::<2>::new(encoder.data());
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    /// assert_eq!(decoder.decode_n(&dec_model, 5), [2, 0, 1, 2, 2]);
+    /// ```
+    fn put_all(&mut self, symbols: &[&Self::Symbol]) {
+        for (i, symbol) in symbols.iter().enumerate().rev() {
+            self.put_at(N - 1 - i % N, symbol);
+        }
+    }
+
     /// Flushes the encoder's intermediate data at given channel into the
     /// buffer.
     ///
@@ -51,6 +326,36 @@ pub trait RansEncoderMulti<const N: usize> {
     /// ```
     fn flush_at(&mut self, channel: usize);
 
+    /// Flushes the encoder's intermediate data at given channel into the
+    /// buffer, checking that the channel index is valid and that there is
+    /// enough space left in the buffer.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidChannel`] if `channel` is out of range, or
+    /// [`RansError::BufferOverflow`] if the data does not fit in the buffer.
+    ///
+    /// The default implementation only checks the channel index and then
+    /// calls [`Self::flush_at()`]; encoders that can run out of space
+    /// override it to check the buffer as well.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::{RansEncoderMulti, RansError};
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(6);
+    /// assert_eq!(encoder.try_flush_at(0), Ok(()));
+    /// assert_eq!(encoder.try_flush_at(1), Err(RansError::BufferOverflow));
+    /// ```
+    fn try_flush_at(&mut self, channel: usize) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+
+        self.flush_at(channel);
+        Ok(())
+    }
+
     /// Flushes the encoder's intermediate data at all channels into the buffer.
     ///
     /// # Examples
@@ -69,6 +374,84 @@ pub trait RansEncoderMulti<const N: usize> {
         }
     }
 
+    /// Flushes the encoder's intermediate data at all channels into the
+    /// buffer, checking that there is enough space left in the buffer.
+    ///
+    /// # Errors
+    /// Returns [`RansError::BufferOverflow`] if the data does not fit in the
+    /// buffer. The channels flushed before the error occurred stay flushed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// assert_eq!(encoder.try_flush_all(), Ok(()));
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0, 0, 0, 128, 0]);
+    /// ```
+    fn try_flush_all(&mut self) -> Result<(), RansError> {
+        for i in 0..N {
+            self.try_flush_at(i)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes all the channels and starts a new section, returning the
+    /// range of the flushed section.
+    ///
+    /// Every section can be decoded independently, so this makes it possible
+    /// to encode many blocks into a single buffer without creating a new
+    /// encoder for each of them. Since the encoder writes backwards, the
+    /// sections are stored in reverse order and the range is measured from
+    /// the end of the data, so that it doesn't change when more sections are
+    /// encoded. Use [`Self::section_data()`] to get the section's bytes.
+    ///
+    /// # Panics
+    /// The default implementation always panics, as it can't tell where the
+    /// current section starts; all the encoders of this crate override it.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoder;
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::{RansDecoder, RansEncSymbol, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put(&ByteRansEncSymbol::new(1, 1, 4));
+    /// let first = encoder.flush_section();
+    /// encoder.put(&ByteRansEncSymbol::new(2, 1, 4));
+    /// let second = encoder.flush_section();
+    /// assert_eq!((first, second.clone()), (0..4, 4..8));
+    ///
+    /// let mut decoder = ByteRansDecoder::new(encoder.section_data(second));
+    /// assert_eq!(decoder.get(4), 2);
+    /// ```
+    fn flush_section(&mut self) -> Range<usize> {
+        panic!("this encoder doesn't support sections");
+    }
+
+    /// Returns the data of a section, given its range returned by
+    /// [`Self::flush_section()`].
+    ///
+    /// # Panics
+    /// Panics if the range is out of bounds of the encoded data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// let section = encoder.flush_section();
+    /// assert_eq!(encoder.section_data(section), [0, 0, 128, 0]);
+    /// ```
+    #[must_use]
+    fn section_data(&self, range: Range<usize>) -> &[u8] {
+        let data = self.data();
+        &data[data.len() - range.end..data.len() - range.start]
+    }
+
     /// Returns this encoder's internal buffer content.
     ///
     /// # Examples
@@ -113,6 +496,78 @@ pub trait RansEncoderMulti<const N: usize> {
     fn is_empty(&self) -> bool {
         self.data().is_empty()
     }
+
+    /// Returns the total size of this encoder's internal buffer in bytes,
+    /// including the part already containing the encoded data.
+    ///
+    /// The default implementation returns the length of the encoded data, as
+    /// if there was no room left in the buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// assert_eq!(encoder.capacity(), 1024);
+    /// ```
+    #[must_use]
+    fn capacity(&self) -> usize {
+        self.len()
+    }
+
+    /// Returns the number of bytes that can still be written into this
+    /// encoder's internal buffer without growing it.
+    ///
+    /// Putting a symbol writes at most a few bytes, so checking this between
+    /// the symbols makes it possible to detect an approaching overflow before
+    /// it happens.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderMulti::<2>::new(1024);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.remaining_capacity(), 1016);
+    /// ```
+    #[must_use]
+    #[inline]
+    fn remaining_capacity(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    /// Makes sure at least `additional` more bytes can be written into this
+    /// encoder's internal buffer, reallocating it if needed. The data
+    /// encoded so far is preserved.
+    ///
+    /// The default implementation does nothing, which suits encoders that
+    /// grow their buffers on their own.
+    ///
+    /// # Panics
+    /// Panics if the buffer needs to grow, but it's owned by the caller and
+    /// thus can't be reallocated.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::{RansEncSymbol, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(4);
+    /// let symbol = ByteRansEncSymbol::new(0, 1, 8);
+    /// for _ in 0..100 {
+    ///     if encoder.remaining_capacity() < 2 {
+    ///         encoder.reserve(64);
+    ///     }
+    ///     encoder.put(&symbol);
+    /// }
+    /// encoder.flush();
+    /// assert!(encoder.capacity() >= encoder.len());
+    /// ```
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
 }
 
 /// Single-stream rANS encoder interface.
@@ -134,6 +589,127 @@ pub trait RansEncoder: RansEncoderMulti<1> {
         self.put_at(0, symbol);
     }
 
+    /// Puts the specified symbol into this encoder, checking that there is
+    /// enough space left in the buffer.
+    ///
+    /// # Errors
+    /// Returns [`RansError::BufferOverflow`] if the symbol might not fit in
+    /// the buffer. In such case, the encoder's state is left untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::{RansEncSymbol, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// let symbol = ByteRansEncSymbol::new(0, 1, 4);
+    /// assert_eq!(encoder.try_put(&symbol), Ok(()));
+    /// ```
+    fn try_put(&mut self, symbol: &Self::Symbol) -> Result<(), RansError> {
+        self.try_put_at(0, symbol)
+    }
+
+    /// Puts the symbol with given index in `model` into this encoder. See
+    /// [`RansEncoderMulti::put_index_at()`].
+    ///
+    /// # Panics
+    /// Panics if `index` is out of range of the model. In debug builds, or
+    /// with the `checked` feature enabled, also panics if the symbol has zero
+    /// frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::{RansEncoder, RansEncoderMulti};
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put_index(1, &enc_model);
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [171, 170, 170, 0]);
+    /// ```
+    fn put_index(&mut self, index: usize, model: &EncModel<Self::Symbol>) {
+        self.put_index_at(0, index, model);
+    }
+
+    /// Puts the symbol with given index in `model` into this encoder,
+    /// checking that the symbol can be encoded and that it fits in the
+    /// buffer. See [`RansEncoderMulti::try_put_index_at()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidSymbol`] if `index` is out of range of the
+    /// model or the symbol has zero frequency, or
+    /// [`RansError::BufferOverflow`] if the symbol might not fit in the
+    /// buffer. In both cases, the encoder's state is left untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::{RansEncoder, RansError};
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// assert_eq!(encoder.try_put_index(1, &enc_model), Ok(()));
+    /// assert_eq!(
+    ///     encoder.try_put_index(2, &enc_model),
+    ///     Err(RansError::InvalidSymbol)
+    /// );
+    /// ```
+    fn try_put_index(
+        &mut self,
+        index: usize,
+        model: &EncModel<Self::Symbol>,
+    ) -> Result<(), RansError> {
+        self.try_put_index_at(0, index, model)
+    }
+
+    /// Puts the lowest `num_bits` bits of `value` into this encoder as raw,
+    /// uniformly distributed bits. See [`RansEncoderMulti::put_bits_at()`].
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::{RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put_bits(0b1011, 4);
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [11, 0, 0, 8]);
+    /// ```
+    fn put_bits(&mut self, value: u32, num_bits: u32) {
+        self.put_bits_at(0, value, num_bits);
+    }
+
+    /// Puts an integer from the range `[0, n)` into this encoder, with all
+    /// the values of the range being (almost) equally probable. See
+    /// [`RansEncoderMulti::put_uniform_at()`].
+    ///
+    /// # Panics
+    /// Panics if `value` is not smaller than `n`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::{RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put_uniform(4, 10);
+    /// encoder.flush();
+    /// assert_eq!(encoder.len(), 4);
+    /// ```
+    fn put_uniform(&mut self, value: u32, n: u32) {
+        self.put_uniform_at(0, value, n);
+    }
+
     /// Flushes the encoder's intermediate data into the buffer.
     ///
     /// # Examples
@@ -149,12 +725,40 @@ pub trait RansEncoder: RansEncoderMulti<1> {
     fn flush(&mut self) {
         self.flush_at(0);
     }
+
+    /// Flushes the encoder's intermediate data into the buffer, checking that
+    /// there is enough space left in the buffer.
+    ///
+    /// # Errors
+    /// Returns [`RansError::BufferOverflow`] if the data does not fit in the
+    /// buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::{RansEncoder, RansEncoderMulti, RansError};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(2);
+    /// assert_eq!(encoder.try_flush(), Err(RansError::BufferOverflow));
+    /// ```
+    fn try_flush(&mut self) -> Result<(), RansError> {
+        self.try_flush_at(0)
+    }
 }
 
 /// A symbol that can be encoded using a rANS encoder.
 pub trait RansEncSymbol {
+    /// Maximum number of bits used for frequency quantization supported by
+    /// the encoder.
+    const MAX_SCALE_BITS: u32 = 16;
+
     /// Creates a new rANS encoder symbol instance.
     ///
+    /// # Panics
+    /// Panics if `scale_bits` is larger than [`Self::MAX_SCALE_BITS`]. Other
+    /// invalid symbol definitions are only checked in debug builds; use
+    /// [`Self::try_new()`] to validate the symbol fully.
+    ///
     /// # Examples
     /// ```
     /// use rans::byte_encoder::ByteRansEncSymbol;
@@ -164,20 +768,208 @@ pub trait RansEncSymbol {
     /// ```
     #[must_use]
     fn new(cum_freq: u32, freq: u32, scale_bits: u32) -> Self;
+
+    /// Returns this symbol's cumulative frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::RansEncSymbol;
+    ///
+    /// let symbol = ByteRansEncSymbol::new(3, 2, 4);
+    /// assert_eq!(symbol.cum_freq(), 3);
+    /// ```
+    #[must_use]
+    fn cum_freq(&self) -> u32;
+
+    /// Returns this symbol's frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::RansEncSymbol;
+    ///
+    /// let symbol = ByteRansEncSymbol::new(3, 2, 4);
+    /// assert_eq!(symbol.freq(), 2);
+    /// ```
+    #[must_use]
+    fn freq(&self) -> u32;
+
+    /// Returns the number of bits used for frequency quantization this
+    /// symbol has been created with.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::RansEncSymbol;
+    ///
+    /// let symbol = ByteRansEncSymbol::new(3, 2, 4);
+    /// assert_eq!(symbol.scale_bits(), 4);
+    /// ```
+    #[must_use]
+    fn scale_bits(&self) -> u32;
+
+    /// Creates a new rANS encoder symbol instance, checking that the symbol
+    /// definition is valid.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidSymbol`] if `freq` is zero,
+    /// `cum_freq + freq` is larger than `1 << scale_bits`, or `scale_bits` is
+    /// larger than [`Self::MAX_SCALE_BITS`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::{RansEncSymbol, RansError};
+    ///
+    /// assert!(ByteRansEncSymbol::try_new(0, 16, 4).is_ok());
+    /// assert_eq!(
+    ///     ByteRansEncSymbol::try_new(8, 9, 4).unwrap_err(),
+    ///     RansError::InvalidSymbol
+    /// );
+    /// ```
+    fn try_new(cum_freq: u32, freq: u32, scale_bits: u32) -> Result<Self, RansError>
+    where
+        Self: Sized,
+    {
+        check_symbol(cum_freq, freq, scale_bits, Self::MAX_SCALE_BITS)?;
+        Ok(Self::new(cum_freq, freq, scale_bits))
+    }
+}
+
+/// Splits the range `[0, n)` of a uniformly coded integer into the number of
+/// values of the part coded as a symbol and the number of low bits coded as
+/// raw bits.
+pub(crate) fn uniform_split(n: u32) -> (u32, u32) {
+    let bits = u32::BITS - (n - 1).leading_zeros();
+    let low_bits = bits.saturating_sub(MAX_BITS_CHUNK);
+    let num_symbols = (u64::from(n) + (1 << low_bits) - 1) >> low_bits;
+    (num_symbols as u32, low_bits)
+}
+
+/// Returns the cumulative frequency and frequency of `value` out of
+/// `num_symbols` equally probable values, using `MAX_BITS_CHUNK` scale bits.
+/// The remainder of the frequencies is given to the first values.
+pub(crate) fn uniform_symbol(value: u32, num_symbols: u32) -> (u32, u32) {
+    let freq = (1 << MAX_BITS_CHUNK) / num_symbols;
+    let extra = (1 << MAX_BITS_CHUNK) % num_symbols;
+    (
+        value * freq + value.min(extra),
+        freq + u32::from(value < extra),
+    )
+}
+
+/// Returns the value whose symbol created with [`uniform_symbol()`] contains
+/// given cumulative frequency.
+pub(crate) fn uniform_value(cum_freq: u32, num_symbols: u32) -> u32 {
+    let freq = (1 << MAX_BITS_CHUNK) / num_symbols;
+    let extra = (1 << MAX_BITS_CHUNK) % num_symbols;
+    let split = extra * (freq + 1);
+    if cum_freq < split {
+        cum_freq / (freq + 1)
+    } else {
+        extra + (cum_freq - split) / freq
+    }
+}
+
+/// Panics if `scale_bits` is larger than `max_scale_bits`, which the
+/// underlying coder can't handle.
+#[inline]
+pub(crate) fn assert_scale_bits(scale_bits: u32, max_scale_bits: u32) {
+    assert!(
+        scale_bits <= max_scale_bits,
+        "scale_bits must not be larger than {max_scale_bits}"
+    );
+}
+
+/// Checks that a symbol with given parameters can be coded by a coder
+/// supporting up to `max_scale_bits` bits of frequency quantization.
+pub(crate) fn check_symbol(
+    cum_freq: u32,
+    freq: u32,
+    scale_bits: u32,
+    max_scale_bits: u32,
+) -> Result<(), RansError> {
+    if scale_bits > max_scale_bits
+        || freq == 0
+        || u64::from(cum_freq) + u64::from(freq) > 1 << scale_bits
+    {
+        return Err(RansError::InvalidSymbol);
+    }
+    Ok(())
+}
+
+/// Snapshot of an encoder's channel states and write position, created with
+/// the encoders' `checkpoint()` methods.
+///
+/// Restoring a checkpoint with the encoders' `restore()` methods discards
+/// everything put into the encoder after the checkpoint was created, which
+/// makes it possible to encode speculatively, e.g. to try several ways of
+/// encoding the same data and only keep the cheapest one.
+///
+/// # Examples
+/// ```
+/// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+/// use rans::{RansEncSymbol, RansEncoder, RansEncoderMulti};
+///
+/// let mut encoder = ByteRansEncoder::new(1024);
+/// encoder.put(&ByteRansEncSymbol::new(0, 1, 2));
+/// let checkpoint = encoder.checkpoint();
+///
+/// encoder.put(&ByteRansEncSymbol::new(0, 1, 8));
+/// encoder.restore(&checkpoint);
+/// encoder.put(&ByteRansEncSymbol::new(1, 3, 2));
+/// encoder.flush();
+///
+/// let mut expected = ByteRansEncoder::new(1024);
+/// expected.put(&ByteRansEncSymbol::new(0, 1, 2));
+/// expected.put(&ByteRansEncSymbol::new(1, 3, 2));
+/// expected.flush();
+/// assert_eq!(encoder.data(), expected.data());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncCheckpoint<S, const N: usize> {
+    pub(crate) states: [S; N],
+    pub(crate) len: usize,
+}
+
+impl<S, const N: usize> EncCheckpoint<S, N> {
+    /// Returns the length of the encoder's data at the time the checkpoint
+    /// was created, in bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::{RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.flush();
+    /// assert_eq!(encoder.checkpoint().data_len(), 4);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn data_len(&self) -> usize {
+        self.len
+    }
 }
 
 macro_rules! check_enc_pointer {
     ($self:ident) => {
-        debug_assert!($self.is_ptr_valid(), "Data pointer is in an invalid state. Consider calling reset() earlier or increasing `max_len` when constructing the encoder.");
+        checked_assert!($self.is_ptr_valid(), "Data pointer is in an invalid state. Consider calling reset() earlier or increasing `max_len` when constructing the encoder.");
     }
 }
 pub(crate) use check_enc_pointer;
 
 #[cfg(test)]
 pub(crate) mod tests {
+    use std::collections::HashSet;
     use std::fmt::Debug;
+    use std::hash::Hash;
 
+    use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
     use crate::encoder::{RansEncSymbol, RansEncoder, RansEncoderMulti};
+    use crate::model::{EncModel, Model};
+    use crate::RansError;
 
     pub(crate) fn test_encode_nothing<T: RansEncoder>(encoder: T) {
         assert_eq!(encoder.len(), 0);
@@ -205,6 +997,27 @@ pub(crate) mod tests {
         assert_eq!(encoder.data(), data);
     }
 
+    /// Raw bits (value, number of bits) used by the bypass coding tests.
+    pub(crate) const BITS_DATA: [(u32, u32); 8] = [
+        (0, 0),
+        (1, 1),
+        (0b101_1010, 7),
+        (0xffff, 16),
+        (0x1_2345, 17),
+        (0x7fff_ffff, 31),
+        (0xdead_beef, 32),
+        (0, 32),
+    ];
+
+    pub(crate) fn encode_bits<T: RansEncoder>(mut encoder: T) -> Vec<u8> {
+        for &(value, num_bits) in BITS_DATA.iter().rev() {
+            encoder.put_bits(value, num_bits);
+        }
+        encoder.flush();
+
+        encoder.data().to_vec()
+    }
+
     pub(crate) fn test_encode_symbols_clone<T>(mut encoder: T, data: &[u8])
     where
         T: RansEncoder,
@@ -249,6 +1062,37 @@ pub(crate) mod tests {
         assert_eq!(encoder.data(), data2);
     }
 
+    pub(crate) fn test_encode_and_clear<T: RansEncoder>(
+        mut encoder: T,
+        mut first: T,
+        mut second: T,
+    ) {
+        const SCALE_BITS: u32 = 8;
+        let symbol1 = T::Symbol::new(0, 1, SCALE_BITS);
+        let symbol2 = T::Symbol::new(1, 200, SCALE_BITS);
+
+        for i in 0..100 {
+            let symbol = if i % 3 == 0 { &symbol1 } else { &symbol2 };
+            encoder.put(symbol);
+            first.put(symbol);
+        }
+        encoder.flush();
+        first.flush();
+
+        encoder.clear();
+        assert_eq!(encoder.data(), first.data());
+
+        for i in 0..50 {
+            let symbol = if i % 2 == 0 { &symbol1 } else { &symbol2 };
+            encoder.put(symbol);
+            second.put(symbol);
+        }
+        encoder.flush();
+        second.flush();
+
+        assert_eq!(encoder.data(), [second.data(), first.data()].concat());
+    }
+
     pub(crate) fn test_encode_more_data<T: RansEncoder>(mut encoder: T, data: &[u8]) {
         const SCALE_BITS: u32 = 8;
         let s1 = T::Symbol::new(0, 3, SCALE_BITS);
@@ -272,7 +1116,7 @@ pub(crate) mod tests {
         assert_eq!(encoder.data(), data);
     }
 
-    pub(crate) fn encode_interleaved<T: RansEncoderMulti<2>>(mut encoder: T, data: &[u8]) {
+    pub(crate) fn encode_interleaved<T: RansEncoderMulti<2>>(mut encoder: T, data: &[u8]) -> T {
         const SCALE_BITS: u32 = 4;
         let symbol1 = T::Symbol::new(0, 4, SCALE_BITS);
         let symbol2 = T::Symbol::new(4, 4, SCALE_BITS);
@@ -290,9 +1134,227 @@ pub(crate) mod tests {
         encoder.flush_all();
 
         assert_eq!(encoder.data(), data);
+        encoder
+    }
+
+    pub(crate) fn test_try_put_overflow<T: RansEncoder>(mut encoder: T) {
+        const SCALE_BITS: u32 = 8;
+        let symbol = T::Symbol::new(0, 1, SCALE_BITS);
+
+        let mut result = Ok(());
+        for _ in 0..1000 {
+            result = encoder.try_put(&symbol);
+            if result.is_err() {
+                break;
+            }
+        }
+        assert_eq!(result, Err(RansError::BufferOverflow));
+
+        let len = encoder.len();
+        assert_eq!(encoder.try_put(&symbol), Err(RansError::BufferOverflow));
+        assert_eq!(encoder.len(), len);
+    }
+
+    pub(crate) fn test_put_index<T: RansEncoder>(mut by_symbol: T, mut by_index: T) {
+        let model = Model::from_counts(&[10, 0, 50, 7, 3, 100], 12);
+        let enc_model = EncModel::<T::Symbol>::new(&model);
+        let indices: Vec<usize> = (0..200).map(|i| [0, 2, 3, 4, 5][i * 7 % 5]).collect();
+
+        for &index in &indices {
+            by_symbol.put(enc_model.symbol(index));
+            by_index.put_index(index, &enc_model);
+        }
+        let len = by_index.len();
+        assert_eq!(
+            by_index.try_put_index(1, &enc_model),
+            Err(RansError::InvalidSymbol)
+        );
+        assert_eq!(
+            by_index.try_put_index(6, &enc_model),
+            Err(RansError::InvalidSymbol)
+        );
+        assert_eq!(by_index.len(), len);
+        assert_eq!(by_index.try_put_index(2, &enc_model), Ok(()));
+        by_symbol.put(enc_model.symbol(2));
+
+        by_symbol.flush();
+        by_index.flush();
+        assert_eq!(by_index.data(), by_symbol.data());
+    }
+
+    pub(crate) fn test_try_flush_overflow<T: RansEncoderMulti<2>>(mut encoder: T) {
+        assert_eq!(encoder.try_flush_all(), Err(RansError::BufferOverflow));
+        assert_eq!(encoder.try_flush_at(2), Err(RansError::InvalidChannel));
+    }
+
+    pub(crate) fn test_encode_growable<T: RansEncoderMulti<2>>(mut encoder: T, mut reference: T) {
+        const SCALE_BITS: u32 = 8;
+        let symbol1 = T::Symbol::new(0, 1, SCALE_BITS);
+        let symbol2 = T::Symbol::new(1, 200, SCALE_BITS);
+
+        for i in 0..5000 {
+            let symbol = if i % 3 == 0 { &symbol1 } else { &symbol2 };
+            assert_eq!(encoder.try_put_at(i % 2, symbol), Ok(()));
+            reference.put_at(i % 2, symbol);
+        }
+        assert_eq!(encoder.try_flush_all(), Ok(()));
+        reference.flush_all();
+
+        assert_eq!(encoder.data(), reference.data());
+    }
+
+    pub(crate) fn test_reserve<T: RansEncoderMulti<2>>(mut encoder: T, mut reference: T) {
+        const SCALE_BITS: u32 = 8;
+        let symbol1 = T::Symbol::new(0, 1, SCALE_BITS);
+        let symbol2 = T::Symbol::new(1, 200, SCALE_BITS);
+
+        for i in 0..5000 {
+            if encoder.remaining_capacity() < 8 {
+                let len = encoder.len();
+                encoder.reserve(100);
+                assert!(encoder.remaining_capacity() >= 100);
+                assert_eq!(encoder.len(), len);
+            }
+            let symbol = if i % 3 == 0 { &symbol1 } else { &symbol2 };
+            assert_eq!(encoder.try_put_at(i % 2, symbol), Ok(()));
+            reference.put_at(i % 2, symbol);
+        }
+        encoder.reserve(8);
+        encoder.flush_all();
+        reference.flush_all();
+
+        assert_eq!(encoder.data(), reference.data());
+        assert_eq!(
+            encoder.remaining_capacity(),
+            encoder.capacity() - encoder.len()
+        );
+    }
+
+    pub(crate) fn test_checkpoint_restore<T: RansEncoderMulti<2>, C>(
+        mut encoder: T,
+        mut reference: T,
+        checkpoint: impl Fn(&T) -> C,
+        restore: impl Fn(&mut T, &C),
+    ) {
+        const SCALE_BITS: u32 = 8;
+        let symbol1 = T::Symbol::new(0, 1, SCALE_BITS);
+        let symbol2 = T::Symbol::new(1, 200, SCALE_BITS);
+
+        for i in 0..1000 {
+            let symbol = if i % 3 == 0 { &symbol1 } else { &symbol2 };
+            let saved = checkpoint(&encoder);
+            // Speculatively put enough symbols to make the buffer grow
+            for _ in 0..100 {
+                encoder.put_at(i % 2, &symbol1);
+            }
+            restore(&mut encoder, &saved);
+
+            encoder.put_at(i % 2, symbol);
+            reference.put_at(i % 2, symbol);
+        }
+        encoder.flush_all();
+        reference.flush_all();
+
+        assert_eq!(encoder.data(), reference.data());
+    }
+
+    pub(crate) fn test_is_send_sync<T: Send + Sync>() {}
+
+    pub(crate) fn test_symbol_accessors<S: RansEncSymbol>() {
+        for scale_bits in [1, 4, 12, S::MAX_SCALE_BITS] {
+            let total = 1_u64 << scale_bits;
+            let cases = [(0, 1), (total - 1, 1), (0, total), (1, total - 1), (3, 2)];
+            for (cum_freq, freq) in cases.into_iter().filter(|&(c, f)| c + f <= total) {
+                let (cum_freq, freq) = (cum_freq as u32, freq as u32);
+                let symbol = S::new(cum_freq, freq, scale_bits);
+                assert_eq!(symbol.cum_freq(), cum_freq);
+                assert_eq!(symbol.freq(), freq);
+                assert_eq!(symbol.scale_bits(), scale_bits);
+            }
+        }
+    }
+
+    pub(crate) fn test_symbol_eq_hash<S: RansEncSymbol + Copy + Eq + Hash>() {
+        let symbol = S::new(3, 2, 4);
+        let copy = symbol;
+        assert!(symbol == copy);
+        assert!(S::new(3, 2, 4) == symbol);
+        assert!(S::new(3, 1, 4) != symbol);
+        assert!(S::new(3, 2, 5) != symbol);
+
+        let set: HashSet<_> = [symbol, copy, S::new(0, 1, 4), S::new(3, 2, 4)].into();
+        assert_eq!(set.len(), 2);
     }
 
     pub(crate) fn test_has_debug_output<T: RansEncoder + Debug>(encoder: T) {
         assert!(!format!("{encoder:?}").is_empty());
     }
+
+    /// Encoder implementing only the required methods of
+    /// [`RansEncoderMulti`], to exercise the provided ones.
+    struct MinimalEncoder(ByteRansEncoderMulti<2>);
+
+    impl RansEncoderMulti<2> for MinimalEncoder {
+        type Symbol = ByteRansEncSymbol;
+
+        fn reset(&mut self) {
+            self.0.reset();
+        }
+
+        fn put_at(&mut self, channel: usize, symbol: &Self::Symbol) {
+            self.0.put_at(channel, symbol);
+        }
+
+        fn flush_at(&mut self, channel: usize) {
+            self.0.flush_at(channel);
+        }
+
+        fn data(&self) -> &[u8] {
+            self.0.data()
+        }
+    }
+
+    #[test]
+    fn test_default_try_methods() {
+        let mut encoder = MinimalEncoder(ByteRansEncoderMulti::new(1024));
+        let symbol = ByteRansEncSymbol::new(0, 1, 4);
+
+        assert_eq!(encoder.try_put_at(0, &symbol), Ok(()));
+        assert_eq!(
+            encoder.try_put_at(2, &symbol),
+            Err(RansError::InvalidChannel)
+        );
+        assert_eq!(encoder.try_flush_at(0), Ok(()));
+        assert_eq!(encoder.try_flush_at(1), Ok(()));
+        assert_eq!(encoder.try_flush_at(2), Err(RansError::InvalidChannel));
+
+        let mut expected = ByteRansEncoderMulti::<2>::new(1024);
+        expected.put_at(0, &symbol);
+        expected.flush_all();
+        assert_eq!(encoder.data(), expected.data());
+    }
+
+    #[test]
+    fn test_default_capacity() {
+        let mut encoder = MinimalEncoder(ByteRansEncoderMulti::new(1024));
+        encoder.reserve(100);
+        encoder.flush_all();
+
+        assert_eq!(encoder.capacity(), 8);
+        assert_eq!(encoder.remaining_capacity(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't support clearing its states")]
+    fn test_default_clear() {
+        let mut encoder = MinimalEncoder(ByteRansEncoderMulti::new(1024));
+        encoder.clear();
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't support sections")]
+    fn test_default_flush_section() {
+        let mut encoder = MinimalEncoder(ByteRansEncoderMulti::new(1024));
+        let _ = encoder.flush_section();
+    }
 }
@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Error type returned by the fallible operations of this crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RansError {
+    /// The input data is empty.
+    EmptyInput,
+    /// There is not enough space left in the encoder's buffer.
+    BufferOverflow,
+    /// The channel index is out of range.
+    InvalidChannel,
+    /// The input ended before all the expected data could be read.
+    UnexpectedEof,
+    /// The input header is malformed.
+    InvalidHeader,
+    /// The input contains a frequency table that is not properly normalized.
+    InvalidModel,
+    /// The checksum of the data doesn't match the stored one.
+    ChecksumMismatch,
+    /// The symbol's frequency is zero, or its range exceeds the total of
+    /// `1 << scale_bits`, or `scale_bits` is too large for the coder.
+    InvalidSymbol,
+    /// The encoded data doesn't decode back to the symbols put into the
+    /// encoder.
+    VerificationFailed,
+    /// The decoder reached the end of its input while renormalizing, i.e. the
+    /// data is truncated or malformed.
+    InputExhausted,
+    /// The input doesn't start with the expected magic bytes, i.e. it's not
+    /// in the expected format.
+    InvalidMagic,
+    /// The input is in a version of the format that is not supported.
+    UnsupportedVersion,
+    /// The checksum of the decoded data doesn't match the stored one, e.g.
+    /// because the data was decoded with a different model than it was
+    /// encoded with.
+    ContentChecksumMismatch,
+}
+
+impl Display for RansError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RansError::EmptyInput => write!(f, "input data is empty"),
+            RansError::BufferOverflow => write!(f, "not enough space in the output buffer"),
+            RansError::InvalidChannel => write!(f, "channel index out of range"),
+            RansError::UnexpectedEof => write!(f, "unexpected end of input"),
+            RansError::InvalidHeader => write!(f, "invalid header"),
+            RansError::InvalidModel => write!(f, "invalid frequency table"),
+            RansError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            RansError::InvalidSymbol => write!(f, "invalid symbol definition"),
+            RansError::VerificationFailed => write!(f, "verification of the encoded data failed"),
+            RansError::InputExhausted => write!(f, "decoder input exhausted"),
+            RansError::InvalidMagic => write!(f, "invalid magic bytes"),
+            RansError::UnsupportedVersion => write!(f, "unsupported format version"),
+            RansError::ContentChecksumMismatch => write!(f, "content checksum mismatch"),
+        }
+    }
+}
+
+impl Error for RansError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::RansError;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(RansError::EmptyInput.to_string(), "input data is empty");
+        assert_eq!(
+            RansError::BufferOverflow.to_string(),
+            "not enough space in the output buffer"
+        );
+        assert_eq!(
+            RansError::InvalidChannel.to_string(),
+            "channel index out of range"
+        );
+        assert_eq!(
+            RansError::UnexpectedEof.to_string(),
+            "unexpected end of input"
+        );
+        assert_eq!(RansError::InvalidHeader.to_string(), "invalid header");
+        assert_eq!(
+            RansError::InvalidModel.to_string(),
+            "invalid frequency table"
+        );
+        assert_eq!(RansError::ChecksumMismatch.to_string(), "checksum mismatch");
+        assert_eq!(
+            RansError::InvalidSymbol.to_string(),
+            "invalid symbol definition"
+        );
+        assert_eq!(
+            RansError::VerificationFailed.to_string(),
+            "verification of the encoded data failed"
+        );
+        assert_eq!(
+            RansError::InputExhausted.to_string(),
+            "decoder input exhausted"
+        );
+        assert_eq!(RansError::InvalidMagic.to_string(), "invalid magic bytes");
+        assert_eq!(
+            RansError::UnsupportedVersion.to_string(),
+            "unsupported format version"
+        );
+        assert_eq!(
+            RansError::ContentChecksumMismatch.to_string(),
+            "content checksum mismatch"
+        );
+    }
+}
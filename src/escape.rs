@@ -0,0 +1,409 @@
+use crate::model::{DecModel, EncModel, Model, NormalizationStrategy};
+use crate::{RansDecSymbol, RansDecoderMulti, RansEncSymbol, RansEncoderMulti};
+
+/// Frequency table with an additional escape symbol, which allows coding
+/// values that have zero frequency in the table (or are outside of it
+/// entirely).
+///
+/// Values with non-zero frequency are coded as usual. Any other value is
+/// coded as the escape symbol followed by the value itself, stored as
+/// `literal_bits` raw bits. This way a static model doesn't have to assign
+/// a non-zero frequency to every possible value just in case it occurs.
+///
+/// # Examples
+/// ```
+/// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+/// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+/// use rans::escape::{EscapeDecModel, EscapeEncModel, EscapeModel};
+/// use rans::RansEncoderMulti;
+///
+/// let model = EscapeModel::from_counts(&[10, 0, 5], 8, 8);
+/// let enc_model = EscapeEncModel::<ByteRansEncSymbol>::new(&model);
+/// let dec_model = EscapeDecModel::<ByteRansDecSymbol>::new(&model);
+///
+/// let values = [0, 2, 1, 200, 0];
+/// let mut encoder = ByteRansEncoder::new(1024);
+/// for &value in values.iter().rev() {
+///     enc_model.put_at(&mut encoder, 0, value);
+/// }
+/// encoder.flush_all();
+///
+/// let mut decoder = ByteRansDecoder::new(encoder.data());
+/// for &value in &values {
+///     assert_eq!(dec_model.decode_at(&mut decoder, 0), value);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeModel {
+    model: Model,
+    literal_bits: u32,
+}
+
+impl EscapeModel {
+    /// Creates a new model by normalizing raw symbol counts, with an escape
+    /// symbol appended after the counted symbols. The escape symbol is
+    /// treated as if it had a count of 1, so it always gets a non-zero
+    /// frequency; this also means that all the counts can be zero.
+    ///
+    /// # Panics
+    /// Panics if `literal_bits` is larger than 32, or in any of the cases
+    /// [`Model::from_counts()`] panics.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::escape::EscapeModel;
+    ///
+    /// let model = EscapeModel::from_counts(&[10, 0, 5], 4, 8);
+    /// assert_eq!(model.escape_symbol(), 3);
+    /// assert_eq!(model.model().freqs(), [10, 0, 5, 1]);
+    /// ```
+    #[must_use]
+    pub fn from_counts(counts: &[u32], scale_bits: u32, literal_bits: u32) -> Self {
+        Self::from_counts_with_strategy(
+            counts,
+            scale_bits,
+            literal_bits,
+            NormalizationStrategy::default(),
+        )
+    }
+
+    /// Creates a new model like [`Self::from_counts()`], normalizing the
+    /// counts with given strategy.
+    ///
+    /// # Panics
+    /// Panics if `literal_bits` is larger than 32, or in any of the cases
+    /// [`Model::from_counts_with_strategy()`] panics.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::escape::EscapeModel;
+    /// use rans::model::NormalizationStrategy;
+    ///
+    /// let model =
+    ///     EscapeModel::from_counts_with_strategy(&[0, 0], 4, 8, NormalizationStrategy::Optimal);
+    /// assert_eq!(model.model().freqs(), [0, 0, 16]);
+    /// ```
+    #[must_use]
+    pub fn from_counts_with_strategy(
+        counts: &[u32],
+        scale_bits: u32,
+        literal_bits: u32,
+        strategy: NormalizationStrategy,
+    ) -> Self {
+        assert!(
+            literal_bits <= 32,
+            "literal_bits must not be larger than 32"
+        );
+
+        let mut counts = counts.to_vec();
+        counts.push(1);
+        Self {
+            model: Model::from_counts_with_strategy(&counts, scale_bits, strategy),
+            literal_bits,
+        }
+    }
+
+    /// Creates an escape model from a model that already has the escape
+    /// symbol as its last symbol.
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_model(model: Model, literal_bits: u32) -> Result<Self, crate::RansError> {
+        if literal_bits > 32 || model.freqs().last().map_or(true, |&freq| freq == 0) {
+            return Err(crate::RansError::InvalidModel);
+        }
+
+        Ok(Self {
+            model,
+            literal_bits,
+        })
+    }
+
+    /// Returns the underlying model, including the escape symbol as its last
+    /// symbol.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::escape::EscapeModel;
+    ///
+    /// let model = EscapeModel::from_counts(&[1, 1, 2], 2, 8);
+    /// assert_eq!(model.model().len(), 4);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Returns the index of the escape symbol.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::escape::EscapeModel;
+    ///
+    /// let model = EscapeModel::from_counts(&[1, 1, 2], 2, 8);
+    /// assert_eq!(model.escape_symbol(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn escape_symbol(&self) -> usize {
+        self.model.len() - 1
+    }
+
+    /// Returns the number of raw bits used to store escaped values.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::escape::EscapeModel;
+    ///
+    /// let model = EscapeModel::from_counts(&[1, 1, 2], 2, 8);
+    /// assert_eq!(model.literal_bits(), 8);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn literal_bits(&self) -> u32 {
+        self.literal_bits
+    }
+
+    /// Returns whether given value has to be escaped, i.e. if it has zero
+    /// frequency or is outside of the counted symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::escape::EscapeModel;
+    ///
+    /// let model = EscapeModel::from_counts(&[1, 0, 2], 4, 8);
+    /// assert!(!model.is_escaped(0));
+    /// assert!(model.is_escaped(1));
+    /// assert!(model.is_escaped(3));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_escaped(&self, value: u32) -> bool {
+        is_escaped(self.model.freqs(), self.escape_symbol(), value)
+    }
+
+    /// Returns the cost of encoding given value, in bits. For escaped values,
+    /// this includes both the escape symbol and the literal bits.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::escape::EscapeModel;
+    ///
+    /// let model = EscapeModel::from_counts(&[7, 0], 3, 8);
+    /// assert_eq!(model.cost_bits(0), 3.0 - 7.0_f64.log2());
+    /// assert_eq!(model.cost_bits(1), 3.0 + 8.0);
+    /// ```
+    #[must_use]
+    pub fn cost_bits(&self, value: u32) -> f64 {
+        if self.is_escaped(value) {
+            self.model.cost_bits(self.escape_symbol()) + f64::from(self.literal_bits)
+        } else {
+            self.model.cost_bits(value as usize)
+        }
+    }
+}
+
+fn is_escaped(freqs: &[u32], escape_symbol: usize, value: u32) -> bool {
+    let index = value as usize;
+    index >= escape_symbol || freqs[index] == 0
+}
+
+/// Set of encoder symbols built from an [`EscapeModel`].
+#[derive(Debug, Clone)]
+pub struct EscapeEncModel<S> {
+    enc_model: EncModel<S>,
+    freqs: Vec<u32>,
+    literal_bits: u32,
+}
+
+impl<S: RansEncSymbol> EscapeEncModel<S> {
+    /// Creates encoder symbols for every symbol of given model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::escape::{EscapeEncModel, EscapeModel};
+    ///
+    /// let model = EscapeModel::from_counts(&[1, 3], 4, 8);
+    /// let enc_model = EscapeEncModel::<ByteRansEncSymbol>::new(&model);
+    /// assert_eq!(enc_model.enc_model().len(), 3);
+    /// ```
+    #[must_use]
+    pub fn new(model: &EscapeModel) -> Self {
+        Self {
+            enc_model: EncModel::new(model.model()),
+            freqs: model.model().freqs().to_vec(),
+            literal_bits: model.literal_bits(),
+        }
+    }
+
+    /// Puts given value into the specified channel, escaping it if it has
+    /// zero frequency in the model.
+    ///
+    /// Since the escape symbol has to be decoded before the literal, the
+    /// literal is put first.
+    ///
+    /// # Panics
+    /// Panics if the value has to be escaped and doesn't fit in the model's
+    /// `literal_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::escape::{EscapeEncModel, EscapeModel};
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let model = EscapeModel::from_counts(&[1, 3], 4, 8);
+    /// let enc_model = EscapeEncModel::<ByteRansEncSymbol>::new(&model);
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// enc_model.put_at(&mut encoder, 0, 1);
+    /// enc_model.put_at(&mut encoder, 0, 255);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.len(), 5);
+    /// ```
+    pub fn put_at<E, const N: usize>(&self, encoder: &mut E, channel: usize, value: u32)
+    where
+        E: RansEncoderMulti<N, Symbol = S>,
+    {
+        let escape_symbol = self.enc_model.len() - 1;
+        if is_escaped(&self.freqs, escape_symbol, value) {
+            assert!(
+                self.literal_bits == 32 || value >> self.literal_bits == 0,
+                "escaped value doesn't fit in literal_bits"
+            );
+            encoder.put_bits_at(channel, value, self.literal_bits);
+            encoder.put_index_at(channel, escape_symbol, &self.enc_model);
+        } else {
+            encoder.put_index_at(channel, value as usize, &self.enc_model);
+        }
+    }
+
+    /// Returns the encoder symbols, including the escape symbol as the last
+    /// one.
+    #[must_use]
+    #[inline]
+    pub fn enc_model(&self) -> &EncModel<S> {
+        &self.enc_model
+    }
+}
+
+/// Set of decoder symbols built from an [`EscapeModel`].
+#[derive(Debug, Clone)]
+pub struct EscapeDecModel<S> {
+    dec_model: DecModel<S>,
+    literal_bits: u32,
+}
+
+impl<S: RansDecSymbol> EscapeDecModel<S> {
+    /// Creates decoder symbols and a lookup table for given model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecSymbol;
+    /// use rans::escape::{EscapeDecModel, EscapeModel};
+    ///
+    /// let model = EscapeModel::from_counts(&[1, 3], 4, 8);
+    /// let dec_model = EscapeDecModel::<ByteRansDecSymbol>::new(&model);
+    /// assert_eq!(dec_model.dec_model().len(), 3);
+    /// ```
+    #[must_use]
+    pub fn new(model: &EscapeModel) -> Self {
+        Self {
+            dec_model: DecModel::new(model.model()),
+            literal_bits: model.literal_bits(),
+        }
+    }
+
+    /// Decodes a single value at given channel, reading the literal if the
+    /// escape symbol was decoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::escape::{EscapeDecModel, EscapeEncModel, EscapeModel};
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let model = EscapeModel::from_counts(&[1, 3], 4, 8);
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// EscapeEncModel::<ByteRansEncSymbol>::new(&model).put_at(&mut encoder, 0, 42);
+    /// encoder.flush_all();
+    ///
+    /// let dec_model = EscapeDecModel::<ByteRansDecSymbol>::new(&model);
+    /// let mut decoder = ByteRansDecoder::new(encoder.data());
+    /// assert_eq!(dec_model.decode_at(&mut decoder, 0), 42);
+    /// ```
+    pub fn decode_at<D, const N: usize>(&self, decoder: &mut D, channel: usize) -> u32
+    where
+        D: RansDecoderMulti<N, Symbol = S>,
+    {
+        let symbol = decoder.decode_symbol_at(channel, &self.dec_model);
+        if symbol == self.dec_model.len() - 1 {
+            decoder.get_bits_at(channel, self.literal_bits)
+        } else {
+            symbol as u32
+        }
+    }
+
+    /// Returns the decoder symbols, including the escape symbol as the last
+    /// one.
+    #[must_use]
+    #[inline]
+    pub fn dec_model(&self) -> &DecModel<S> {
+        &self.dec_model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::b64_decoder::{B64RansDecSymbol, B64RansDecoderMulti};
+    use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoderMulti};
+    use crate::escape::{EscapeDecModel, EscapeEncModel, EscapeModel};
+    use crate::RansEncoderMulti;
+
+    #[test]
+    fn test_round_trip() {
+        let mut counts = [0; 256];
+        counts[b'a' as usize] = 50;
+        counts[b'b' as usize] = 20;
+        counts[b'c' as usize] = 5;
+        let model = EscapeModel::from_counts(&counts[..128], 20, 32);
+        let enc_model = EscapeEncModel::<B64RansEncSymbol>::new(&model);
+        let dec_model = EscapeDecModel::<B64RansDecSymbol>::new(&model);
+        let values: Vec<u32> = (0..1000_u32)
+            .map(|i| match i % 10 {
+                0 => i.wrapping_mul(0x9e37_79b9),
+                1 => 127 + i % 3,
+                _ => u32::from(b'a') + i % 3,
+            })
+            .collect();
+
+        let mut encoder = B64RansEncoderMulti::<2>::new(1 << 16);
+        for (i, &value) in values.iter().enumerate().rev() {
+            enc_model.put_at(&mut encoder, 1 - i % 2, value);
+        }
+        encoder.flush_all();
+
+        let mut decoder = B64RansDecoderMulti::<2>::new(encoder.data());
+        for (i, &value) in values.iter().enumerate() {
+            assert_eq!(dec_model.decode_at(&mut decoder, i % 2), value);
+        }
+    }
+
+    #[test]
+    fn test_only_escape() {
+        let model = EscapeModel::from_counts(&[], 12, 16);
+
+        assert_eq!(model.escape_symbol(), 0);
+        assert_eq!(model.cost_bits(12345), 16.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't fit in literal_bits")]
+    fn test_literal_too_large() {
+        let model = EscapeModel::from_counts(&[1], 8, 4);
+        let enc_model = EscapeEncModel::<B64RansEncSymbol>::new(&model);
+
+        enc_model.put_at(&mut B64RansEncoderMulti::<1>::new(1024), 0, 16);
+    }
+}
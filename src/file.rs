@@ -0,0 +1,126 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::decoder_input::DecoderInput;
+use crate::frame::FrameDecoder;
+use crate::model::Model;
+use crate::stream::invalid_data;
+
+/// Maps the file at `path` into memory read-only, so it can be passed to
+/// any of the decoders without reading it into RAM first.
+///
+/// # Safety
+/// The file must not be modified, truncated or deleted by this or any other
+/// process while the mapping is alive. Otherwise, the decoder may observe
+/// the data changing underneath it, or the process may be killed with
+/// `SIGBUS` when accessing a truncated part of the mapping.
+///
+/// # Errors
+/// Returns any error that occurs while opening or mapping the file.
+///
+/// # Examples
+/// ```
+/// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+/// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+/// use rans::file::map_file;
+/// use rans::{RansDecSymbol, RansDecoder, RansEncSymbol, RansEncoder, RansEncoderMulti};
+///
+/// let mut encoder = ByteRansEncoder::new(1024);
+/// encoder.put(&ByteRansEncSymbol::new(2, 2, 2));
+/// encoder.flush();
+/// let path = std::env::temp_dir().join("rans-map-file-doctest.bin");
+/// std::fs::write(&path, encoder.data()).unwrap();
+///
+/// let mut decoder = ByteRansDecoder::new(unsafe { map_file(&path) }.unwrap());
+/// assert_eq!(decoder.get(2), 2);
+/// decoder.advance(&ByteRansDecSymbol::new(2, 2), 2);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub unsafe fn map_file<P: AsRef<Path>>(path: P) -> io::Result<DecoderInput<'static>> {
+    let file = File::open(path)?;
+    Ok(DecoderInput::from(Mmap::map(&file)?))
+}
+
+/// Decodes the [frame](crate::frame) stored in the file at `path` using
+/// given `model`, which has to be the same as the one used by the encoder.
+///
+/// The file is memory-mapped, so only the decoded symbols are kept in RAM,
+/// and the pages of the file are loaded on demand by the operating system.
+/// Any data after the frame is ignored.
+///
+/// # Safety
+/// The file must not be modified, truncated or deleted while it is being
+/// decoded; see [`map_file()`].
+///
+/// # Errors
+/// Returns any error that occurs while opening or mapping the file, or an
+/// error of kind [`io::ErrorKind::InvalidData`] wrapping the
+/// [`RansError`](crate::RansError) returned by [`FrameDecoder::new()`] or
+/// [`FrameDecoder::decode()`].
+///
+/// # Examples
+/// ```
+/// use rans::file::decode_file;
+/// use rans::frame::FrameEncoder;
+/// use rans::model::Model;
+///
+/// let model = Model::from_counts(&[1, 2, 3], 8);
+/// let path = std::env::temp_dir().join("rans-decode-file-doctest.bin");
+/// std::fs::write(&path, FrameEncoder::new().encode(&model, &[2, 0, 1])).unwrap();
+///
+/// assert_eq!(unsafe { decode_file(&path, &model) }.unwrap(), [2, 0, 1]);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub unsafe fn decode_file<P: AsRef<Path>>(path: P, model: &Model) -> io::Result<Vec<usize>> {
+    let data = map_file(path)?;
+    FrameDecoder::new(&data)
+        .and_then(|decoder| decoder.decode(model))
+        .map_err(invalid_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+
+    use crate::file::decode_file;
+    use crate::frame::FrameEncoder;
+    use crate::model::Model;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rans-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_decode_file() {
+        let model = Model::from_counts(&[10, 5, 1, 1], 12);
+        let symbols: Vec<usize> = (0..100_000).map(|i| i * 7 % 13 % 4).collect();
+        let path = temp_path("decode.bin");
+        std::fs::write(&path, FrameEncoder::new().encode(&model, &symbols)).unwrap();
+
+        let decoded = unsafe { decode_file(&path, &model) };
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(decoded.unwrap(), symbols);
+    }
+
+    #[test]
+    fn test_decode_file_invalid() {
+        let model = Model::from_counts(&[1, 1], 8);
+        let path = temp_path("invalid.bin");
+        std::fs::write(&path, b"not a frame").unwrap();
+
+        let error = unsafe { decode_file(&path, &model) }.unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_decode_file_missing() {
+        let model = Model::from_counts(&[1, 1], 8);
+        let error = unsafe { decode_file(temp_path("missing.bin"), &model) }.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+}
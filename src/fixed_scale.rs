@@ -0,0 +1,532 @@
+use crate::b64_decoder::B64RansDecoderMulti;
+use crate::b64_encoder::B64RansEncoderMulti;
+use crate::byte_decoder::ByteRansDecoderMulti;
+use crate::byte_encoder::ByteRansEncoderMulti;
+use crate::model::DecModel;
+use crate::{RansDecSymbol, RansDecoderMulti, RansEncSymbol, RansEncoderMulti};
+
+/// Adapter over a multi-stream encoder that only accepts symbols created
+/// with `SCALE_BITS` bits of frequency quantization.
+///
+/// Since the number of bits is a part of the type, the symbols can be
+/// created with [`Self::symbol()`] without passing it around, and putting a
+/// symbol created with a different number of bits panics instead of silently
+/// producing a corrupt stream.
+///
+/// # Examples
+/// ```
+/// use rans::fixed_scale::{ByteRansDecoderFixed, ByteRansEncoderFixed};
+///
+/// let mut encoder = ByteRansEncoderFixed::<4>::new(1024);
+/// encoder.put(&ByteRansEncoderFixed::<4>::symbol(3, 5));
+/// encoder.flush();
+///
+/// let mut decoder = ByteRansDecoderFixed::<4>::new(encoder.data());
+/// let cum_freq = decoder.get();
+/// assert!((3..8).contains(&cum_freq));
+/// decoder.advance(&ByteRansDecoderFixed::<4>::symbol(3, 5));
+/// ```
+#[derive(Debug)]
+pub struct FixedScaleEncoder<E, const N: usize, const SCALE_BITS: u32> {
+    encoder: E,
+}
+
+/// Byte-aligned rANS encoder with a fixed number of scale bits.
+pub type ByteRansEncoderFixed<const SCALE_BITS: u32> =
+    FixedScaleEncoder<ByteRansEncoderMulti<1>, 1, SCALE_BITS>;
+/// 64-bit rANS encoder with a fixed number of scale bits.
+pub type B64RansEncoderFixed<const SCALE_BITS: u32> =
+    FixedScaleEncoder<B64RansEncoderMulti<1>, 1, SCALE_BITS>;
+
+impl<E: RansEncoderMulti<N>, const N: usize, const SCALE_BITS: u32>
+    FixedScaleEncoder<E, N, SCALE_BITS>
+{
+    /// Creates a new `FixedScaleEncoder` instance wrapping given encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::fixed_scale::FixedScaleEncoder;
+    ///
+    /// let encoder = FixedScaleEncoder::<_, 2, 12>::from_encoder(ByteRansEncoderMulti::new(1024));
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn from_encoder(encoder: E) -> Self {
+        Self { encoder }
+    }
+
+    /// Creates a new encoder symbol with `SCALE_BITS` bits of frequency
+    /// quantization.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansEncoderFixed;
+    /// use rans::RansEncSymbol;
+    ///
+    /// let symbol = ByteRansEncoderFixed::<12>::symbol(0, 1024);
+    /// assert_eq!(symbol.scale_bits(), 12);
+    /// ```
+    #[must_use]
+    pub fn symbol(cum_freq: u32, freq: u32) -> E::Symbol {
+        E::Symbol::new(cum_freq, freq, SCALE_BITS)
+    }
+
+    /// Puts a symbol into the specified channel.
+    ///
+    /// # Panics
+    /// Panics if the symbol has not been created with `SCALE_BITS` bits of
+    /// frequency quantization.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::fixed_scale::FixedScaleEncoder;
+    ///
+    /// let mut encoder = FixedScaleEncoder::<_, 2, 4>::from_encoder(ByteRansEncoderMulti::new(1024));
+    /// let symbol = FixedScaleEncoder::<ByteRansEncoderMulti<2>, 2, 4>::symbol(0, 1);
+    /// encoder.put_at(0, &symbol);
+    /// encoder.put_at(1, &symbol);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data(), [0, 0, 0, 8, 0, 0, 0, 8]);
+    /// ```
+    #[inline]
+    pub fn put_at(&mut self, channel: usize, symbol: &E::Symbol) {
+        assert_eq!(
+            symbol.scale_bits(),
+            SCALE_BITS,
+            "symbol created with a different number of scale bits"
+        );
+        self.encoder.put_at(channel, symbol);
+    }
+
+    /// Flushes the encoder's intermediate data at all channels into the
+    /// buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::fixed_scale::FixedScaleEncoder;
+    ///
+    /// let mut encoder = FixedScaleEncoder::<_, 2, 4>::from_encoder(ByteRansEncoderMulti::new(1024));
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0, 0, 0, 128, 0]);
+    /// ```
+    pub fn flush_all(&mut self) {
+        self.encoder.flush_all();
+    }
+
+    /// Returns the wrapped encoder's internal buffer content.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansEncoderFixed;
+    ///
+    /// let encoder = ByteRansEncoderFixed::<4>::new(1024);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        self.encoder.data()
+    }
+
+    /// Returns a mutable reference to the wrapped encoder.
+    ///
+    /// Note that the symbols put using the wrapped encoder directly are not
+    /// checked.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansEncoderFixed;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderFixed::<4>::new(1024);
+    /// encoder.encoder_mut().reset();
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn encoder_mut(&mut self) -> &mut E {
+        &mut self.encoder
+    }
+
+    /// Consumes this adapter, returning the wrapped encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansEncoderFixed;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = ByteRansEncoderFixed::<4>::new(1024);
+    /// encoder.flush();
+    /// assert_eq!(encoder.into_inner().len(), 4);
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> E {
+        self.encoder
+    }
+}
+
+impl<E: RansEncoderMulti<1>, const SCALE_BITS: u32> FixedScaleEncoder<E, 1, SCALE_BITS> {
+    /// Puts a symbol into the encoder.
+    ///
+    /// # Panics
+    /// Panics if the symbol has not been created with `SCALE_BITS` bits of
+    /// frequency quantization.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::fixed_scale::ByteRansEncoderFixed;
+    /// use rans::RansEncSymbol;
+    ///
+    /// let mut encoder = ByteRansEncoderFixed::<4>::new(1024);
+    /// encoder.put(&ByteRansEncoderFixed::<4>::symbol(0, 1));
+    /// let result = std::panic::catch_unwind(move || {
+    ///     encoder.put(&ByteRansEncSymbol::new(0, 1, 8));
+    /// });
+    /// assert!(result.is_err());
+    /// ```
+    #[inline]
+    pub fn put(&mut self, symbol: &E::Symbol) {
+        self.put_at(0, symbol);
+    }
+
+    /// Flushes the encoder's intermediate data into the buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansEncoderFixed;
+    ///
+    /// let mut encoder = ByteRansEncoderFixed::<4>::new(1024);
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0]);
+    /// ```
+    pub fn flush(&mut self) {
+        self.encoder.flush_at(0);
+    }
+}
+
+impl<const SCALE_BITS: u32> ByteRansEncoderFixed<SCALE_BITS> {
+    /// Creates a new byte-aligned encoder with a fixed number of scale bits
+    /// that can contain `max_len` bytes in the internal buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansEncoderFixed;
+    ///
+    /// let encoder = ByteRansEncoderFixed::<12>::new(1024);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(max_len: usize) -> Self {
+        Self::from_encoder(ByteRansEncoderMulti::new(max_len))
+    }
+}
+
+impl<const SCALE_BITS: u32> B64RansEncoderFixed<SCALE_BITS> {
+    /// Creates a new 64-bit encoder with a fixed number of scale bits that
+    /// can contain `max_len` bytes in the internal buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::B64RansEncoderFixed;
+    ///
+    /// let encoder = B64RansEncoderFixed::<24>::new(1024);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(max_len: usize) -> Self {
+        Self::from_encoder(B64RansEncoderMulti::new(max_len))
+    }
+}
+
+/// Adapter over a multi-stream decoder that always uses `SCALE_BITS` bits of
+/// frequency quantization, so that it doesn't need to be passed to every
+/// call.
+///
+/// See [`FixedScaleEncoder`] for an example.
+#[derive(Debug)]
+pub struct FixedScaleDecoder<D, const N: usize, const SCALE_BITS: u32> {
+    decoder: D,
+}
+
+/// Byte-aligned rANS decoder with a fixed number of scale bits.
+pub type ByteRansDecoderFixed<'a, const SCALE_BITS: u32> =
+    FixedScaleDecoder<ByteRansDecoderMulti<'a, 1>, 1, SCALE_BITS>;
+/// 64-bit rANS decoder with a fixed number of scale bits.
+pub type B64RansDecoderFixed<'a, const SCALE_BITS: u32> =
+    FixedScaleDecoder<B64RansDecoderMulti<'a, 1>, 1, SCALE_BITS>;
+
+impl<D: RansDecoderMulti<N>, const N: usize, const SCALE_BITS: u32>
+    FixedScaleDecoder<D, N, SCALE_BITS>
+{
+    /// Creates a new `FixedScaleDecoder` instance wrapping given decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::fixed_scale::FixedScaleDecoder;
+    ///
+    /// let mut decoder = FixedScaleDecoder::<_, 2, 2>::from_decoder(ByteRansDecoderMulti::new([
+    ///     2, 0, 0, 1, 0, 0, 0, 1,
+    /// ]));
+    /// assert_eq!(decoder.get_at(0), 2);
+    /// ```
+    #[must_use]
+    pub fn from_decoder(decoder: D) -> Self {
+        Self { decoder }
+    }
+
+    /// Creates a new decoder symbol. This is only provided for symmetry with
+    /// [`FixedScaleEncoder::symbol()`], as decoder symbols don't depend on
+    /// the number of scale bits.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansDecoderFixed;
+    /// use rans::RansDecSymbol;
+    ///
+    /// let symbol = ByteRansDecoderFixed::<12>::symbol(0, 1024);
+    /// assert_eq!(symbol.freq(), 1024);
+    /// ```
+    #[must_use]
+    pub fn symbol(cum_freq: u32, freq: u32) -> D::Symbol {
+        D::Symbol::new(cum_freq, freq)
+    }
+
+    /// Gets the cumulative frequency for the current symbol at specified
+    /// channel. See [`RansDecoderMulti::get_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::fixed_scale::FixedScaleDecoder;
+    ///
+    /// let mut decoder = FixedScaleDecoder::<_, 2, 2>::from_decoder(ByteRansDecoderMulti::new([
+    ///     2, 0, 0, 1, 0, 0, 0, 1,
+    /// ]));
+    /// assert_eq!(decoder.get_at(0), 2);
+    /// assert_eq!(decoder.get_at(1), 0);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn get_at(&mut self, channel: usize) -> u32 {
+        self.decoder.get_at(channel, SCALE_BITS)
+    }
+
+    /// Advances the data position after reading a symbol at given channel.
+    /// See [`RansDecoderMulti::advance_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::fixed_scale::FixedScaleDecoder;
+    ///
+    /// type Decoder<'a> = FixedScaleDecoder<ByteRansDecoderMulti<'a, 2>, 2, 2>;
+    ///
+    /// let mut decoder = Decoder::from_decoder(ByteRansDecoderMulti::new([2, 0, 0, 1, 0, 0, 0, 1]));
+    /// decoder.advance_at(0, &Decoder::symbol(2, 2));
+    /// assert_eq!(decoder.get_at(0), 0);
+    /// ```
+    #[inline]
+    pub fn advance_at(&mut self, channel: usize, symbol: &D::Symbol) {
+        self.decoder.advance_at(channel, symbol, SCALE_BITS);
+    }
+
+    /// Decodes a single symbol at given channel using given model and returns
+    /// its index. See [`RansDecoderMulti::decode_symbol_at()`].
+    ///
+    /// # Panics
+    /// Panics if the model does not use `SCALE_BITS` bits of frequency
+    /// quantization.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::fixed_scale::FixedScaleDecoder;
+    /// use rans::model::{DecModel, Model};
+    ///
+    /// let model = Model::from_freqs(vec![2, 2], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder = FixedScaleDecoder::<_, 2, 2>::from_decoder(ByteRansDecoderMulti::new([
+    ///     2, 0, 0, 1, 0, 0, 0, 1,
+    /// ]));
+    /// assert_eq!(decoder.decode_symbol_at(0, &dec_model), 1);
+    /// assert_eq!(decoder.decode_symbol_at(1, &dec_model), 0);
+    /// ```
+    pub fn decode_symbol_at(&mut self, channel: usize, model: &DecModel<D::Symbol>) -> usize {
+        assert_eq!(
+            model.scale_bits(),
+            SCALE_BITS,
+            "model uses a different number of scale bits"
+        );
+        self.decoder.decode_symbol_at(channel, model)
+    }
+
+    /// Returns a mutable reference to the wrapped decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansDecoderFixed;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = ByteRansDecoderFixed::<4>::new([0, 0, 128, 0]);
+    /// assert_eq!(decoder.decoder_mut().get_at(0, 4), 0);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Consumes this adapter, returning the wrapped decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansDecoderFixed;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let decoder = ByteRansDecoderFixed::<4>::new([0, 0, 128, 0]);
+    /// assert_eq!(decoder.into_inner().get_at(0, 4), 0);
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+
+impl<D: RansDecoderMulti<1>, const SCALE_BITS: u32> FixedScaleDecoder<D, 1, SCALE_BITS> {
+    /// Gets the cumulative frequency for the current symbol.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansDecoderFixed;
+    ///
+    /// let mut decoder = ByteRansDecoderFixed::<2>::new([2, 0, 0, 2]);
+    /// assert_eq!(decoder.get(), 2);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn get(&mut self) -> u32 {
+        self.get_at(0)
+    }
+
+    /// Advances the data position after reading a symbol.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansDecoderFixed;
+    ///
+    /// let mut decoder = ByteRansDecoderFixed::<2>::new([2, 0, 0, 2]);
+    /// assert_eq!(decoder.get(), 2);
+    /// decoder.advance(&ByteRansDecoderFixed::<2>::symbol(2, 2));
+    /// assert_eq!(decoder.get(), 0);
+    /// ```
+    #[inline]
+    pub fn advance(&mut self, symbol: &D::Symbol) {
+        self.advance_at(0, symbol);
+    }
+
+    /// Decodes a single symbol using given model and returns its index.
+    ///
+    /// # Panics
+    /// Panics if the model does not use `SCALE_BITS` bits of frequency
+    /// quantization.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecSymbol;
+    /// use rans::fixed_scale::ByteRansDecoderFixed;
+    /// use rans::model::{DecModel, Model};
+    ///
+    /// let model = Model::from_freqs(vec![2, 2], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder = ByteRansDecoderFixed::<2>::new([2, 0, 0, 2]);
+    /// assert_eq!(decoder.decode_symbol(&dec_model), 1);
+    /// assert_eq!(decoder.decode_symbol(&dec_model), 0);
+    /// ```
+    pub fn decode_symbol(&mut self, model: &DecModel<D::Symbol>) -> usize {
+        self.decode_symbol_at(0, model)
+    }
+}
+
+impl<'a, const SCALE_BITS: u32> ByteRansDecoderFixed<'a, SCALE_BITS> {
+    /// Creates a new byte-aligned decoder with a fixed number of scale bits
+    /// and given `data`. See [`ByteRansDecoderMulti::new()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::ByteRansDecoderFixed;
+    ///
+    /// let mut decoder = ByteRansDecoderFixed::<4>::new([0, 0, 128, 0]);
+    /// assert_eq!(decoder.get(), 0);
+    /// ```
+    #[must_use]
+    pub fn new<T: Into<crate::decoder_input::DecoderInput<'a>>>(data: T) -> Self {
+        Self::from_decoder(ByteRansDecoderMulti::new(data))
+    }
+}
+
+impl<'a, const SCALE_BITS: u32> B64RansDecoderFixed<'a, SCALE_BITS> {
+    /// Creates a new 64-bit decoder with a fixed number of scale bits and
+    /// given `data`. See [`B64RansDecoderMulti::new()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::fixed_scale::B64RansDecoderFixed;
+    ///
+    /// let mut decoder = B64RansDecoderFixed::<24>::new([0, 0, 0, 128, 0, 0, 0, 0]);
+    /// assert_eq!(decoder.get(), 0);
+    /// ```
+    #[must_use]
+    pub fn new<T: Into<crate::decoder_input::DecoderInput<'a>>>(data: T) -> Self {
+        Self::from_decoder(B64RansDecoderMulti::new(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_encoder::ByteRansEncSymbol;
+    use crate::fixed_scale::{
+        B64RansDecoderFixed, B64RansEncoderFixed, ByteRansDecoderFixed, ByteRansEncoderFixed,
+    };
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::RansEncSymbol;
+
+    #[test]
+    fn test_round_trip() {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100], 24);
+        let enc_model = EncModel::new(&model);
+        let dec_model = DecModel::new(&model);
+        let symbols: Vec<usize> = (0..1000).map(|i| (i * 7 + i / 3) % 6).collect();
+
+        let mut encoder = B64RansEncoderFixed::<24>::new(1 << 16);
+        for &symbol in symbols.iter().rev() {
+            encoder.put(enc_model.symbol(symbol));
+        }
+        encoder.flush();
+
+        let mut decoder = B64RansDecoderFixed::<24>::new(encoder.data());
+        for &symbol in &symbols {
+            assert_eq!(decoder.decode_symbol(&dec_model), symbol);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "symbol created with a different number of scale bits")]
+    fn test_put_scale_bits_mismatch() {
+        let mut encoder = ByteRansEncoderFixed::<12>::new(1024);
+        encoder.put(&ByteRansEncSymbol::new(0, 1, 8));
+    }
+
+    #[test]
+    #[should_panic(expected = "model uses a different number of scale bits")]
+    fn test_decode_scale_bits_mismatch() {
+        let model = Model::from_counts(&[1, 2], 8);
+        let mut decoder = ByteRansDecoderFixed::<12>::new([0, 0, 128, 0]);
+        decoder.decode_symbol(&DecModel::new(&model));
+    }
+}
@@ -0,0 +1,1159 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::model::{DecModel, EncModel, Model};
+use crate::transform::{apply_all, reverse_all, Transform};
+use crate::uint_coder::{TokenScheme, UintDecModel, UintEncModel};
+use crate::{varint, RansDecoder, RansEncoder, RansEncoderMulti, RansError};
+
+/// Magic bytes at the beginning of every frame.
+pub const MAGIC: [u8; 4] = *b"rANS";
+/// Version of the frame format written by [`FrameEncoder`].
+pub const VERSION: u8 = 1;
+
+/// Flag set if the frame contains a CRC-32 checksum of the header and the
+/// payload.
+const FLAG_CRC32: u8 = 0x01;
+/// Flag set if the header contains the list of transforms applied to the data.
+const FLAG_TRANSFORMS: u8 = 0x02;
+/// Flag set if the payload contains (symbol, run length) pairs, and the
+/// header contains the model of the run length tokens.
+const FLAG_RUN_LENGTH: u8 = 0x04;
+/// Flag set if the frame contains a CRC-32 checksum of the encoded symbols.
+const FLAG_CONTENT_CRC32: u8 = 0x08;
+/// All the flags known to this version of the format.
+const KNOWN_FLAGS: u8 = FLAG_CRC32 | FLAG_TRANSFORMS | FLAG_RUN_LENGTH | FLAG_CONTENT_CRC32;
+/// Token scheme used to code the run lengths.
+const RUN_LENGTH_SCHEME: TokenScheme = TokenScheme::new(4, 2, 0);
+/// Scale bits of the model of the run length tokens.
+const RUN_LENGTH_SCALE_BITS: u32 = 12;
+/// Length of the magic, the version, the flags and the scale bits.
+const FIXED_HEADER_LEN: usize = MAGIC.len() + 3;
+/// Length of the payload of an empty frame, i.e. of the final rANS state.
+const MIN_PAYLOAD_LEN: usize = 4;
+
+/// Checksum algorithm used to protect a frame against corruption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Checksum {
+    /// No checksum.
+    None,
+    /// CRC-32 (the IEEE 802.3 polynomial, as used by zlib and PNG).
+    Crc32,
+}
+
+/// Whether [`FrameEncoder`] codes runs of repeated symbols as (symbol, run
+/// length) pairs.
+///
+/// Run-length coding drastically helps data dominated by long runs, such as
+/// sparse masks or quantized zeros, which plain order-0 coding can't
+/// compress below a fraction of a bit per symbol. The symbols are coded with
+/// the frame's model, and the run lengths with the [hybrid integer
+/// coder](crate::uint_coder), whose model is stored in the frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RunLengthMode {
+    /// Always code the symbols one by one.
+    Never,
+    /// Always code the runs, unless the frame is empty.
+    Always,
+    /// Estimate the size of both representations and choose the smaller one
+    /// for each frame.
+    Auto,
+}
+
+/// Header of a frame, describing the rANS payload that follows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameHeader {
+    scale_bits: u32,
+    num_symbols: u64,
+    payload_len: usize,
+    checksum: Option<u32>,
+    content_checksum: Option<u32>,
+    transforms: Vec<Transform>,
+    run_length_model: Option<Model>,
+}
+
+impl FrameHeader {
+    /// Returns the scale bits of the model the payload was encoded with.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let frame = FrameEncoder::new().encode(&model, &[0, 1]);
+    /// assert_eq!(FrameDecoder::new(&frame).unwrap().header().scale_bits(), 8);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
+
+    /// Returns the number of symbols encoded in the payload.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let frame = FrameEncoder::new().encode(&model, &[0, 1, 1]);
+    /// assert_eq!(FrameDecoder::new(&frame).unwrap().header().num_symbols(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn num_symbols(&self) -> u64 {
+        self.num_symbols
+    }
+
+    /// Returns the length of the payload in bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let frame = FrameEncoder::new().encode(&model, &[]);
+    /// assert_eq!(FrameDecoder::new(&frame).unwrap().header().payload_len(), 4);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn payload_len(&self) -> usize {
+        self.payload_len
+    }
+
+    /// Returns the CRC-32 checksum of the frame, if it has one. The checksum
+    /// covers all the bytes of the frame except itself, i.e. both the header
+    /// and the payload.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{Checksum, FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let frame = FrameEncoder::with_checksum(Checksum::None).encode(&model, &[0]);
+    /// assert_eq!(FrameDecoder::new(&frame).unwrap().header().crc32(), None);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn crc32(&self) -> Option<u32> {
+        self.checksum
+    }
+
+    /// Returns the CRC-32 checksum of the encoded symbols, if the frame has
+    /// one; see [`FrameEncoder::with_content_checksum()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let frame = FrameEncoder::new().encode(&model, &[0]);
+    /// assert_eq!(FrameDecoder::new(&frame).unwrap().header().content_crc32(), None);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn content_crc32(&self) -> Option<u32> {
+        self.content_checksum
+    }
+
+    /// Returns the transforms applied to the data before encoding it, in the
+    /// order they were applied.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    /// use rans::transform::Transform;
+    ///
+    /// let model = Model::from_counts(&[1; 256], 12);
+    /// let frame = FrameEncoder::new()
+    ///     .with_transforms(&[Transform::Delta])
+    ///     .encode_bytes(&model, b"abc");
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert_eq!(decoder.header().transforms(), [Transform::Delta]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn transforms(&self) -> &[Transform] {
+        &self.transforms
+    }
+
+    /// Returns whether the payload is run-length coded; see
+    /// [`RunLengthMode`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder, RunLengthMode};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let symbols: Vec<usize> = (0..1000).map(|i| i / 100 % 2).collect();
+    /// let frame = FrameEncoder::new()
+    ///     .with_run_length(RunLengthMode::Auto)
+    ///     .encode(&model, &symbols);
+    /// assert!(FrameDecoder::new(&frame).unwrap().header().run_length());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn run_length(&self) -> bool {
+        self.run_length_model.is_some()
+    }
+}
+
+/// Encoder of self-describing frames.
+///
+/// A frame consists of a header followed by a byte-aligned rANS payload. The
+/// header contains the [`MAGIC`] bytes, the format [`VERSION`], the scale
+/// bits of the model, the number of encoded symbols, the length of the
+/// payload and, optionally, the list of [transforms](crate::transform)
+/// applied to the data, the model of the run lengths (see
+/// [`RunLengthMode`]), the checksum of the whole frame and the checksum of
+/// the encoded symbols. The frequency table of the symbols itself is not
+/// stored; the decoder has to use the same model.
+#[derive(Debug, Clone)]
+pub struct FrameEncoder {
+    checksum: Checksum,
+    content_checksum: bool,
+    transforms: Vec<Transform>,
+    run_length: RunLengthMode,
+}
+
+impl FrameEncoder {
+    /// Creates a new `FrameEncoder` instance writing frames with a CRC-32
+    /// checksum.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::FrameEncoder;
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 4);
+    /// let frame = FrameEncoder::new().encode(&model, &[1, 1, 0]);
+    /// assert_eq!(&frame[..4], b"rANS");
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_checksum(Checksum::Crc32)
+    }
+
+    /// Creates a new `FrameEncoder` instance writing frames with given
+    /// `checksum`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{Checksum, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 4);
+    /// let with_crc = FrameEncoder::new().encode(&model, &[1]);
+    /// let without_crc = FrameEncoder::with_checksum(Checksum::None).encode(&model, &[1]);
+    /// assert_eq!(with_crc.len(), without_crc.len() + 4);
+    /// ```
+    #[must_use]
+    pub fn with_checksum(checksum: Checksum) -> Self {
+        Self {
+            checksum,
+            content_checksum: false,
+            transforms: Vec::new(),
+            run_length: RunLengthMode::Never,
+        }
+    }
+
+    /// Sets whether the frames contain a CRC-32 checksum of the encoded
+    /// symbols, like the content checksum of Zstandard frames. Disabled by
+    /// default.
+    ///
+    /// Unlike the checksum of the frame, which is verified by
+    /// [`FrameDecoder::new()`] before decoding anything, this one is verified
+    /// by [`FrameDecoder::decode()`] after decoding the symbols, so it also
+    /// catches decoding with a different model than the frame was encoded
+    /// with. The symbols are checksummed as little-endian `u32` values, in
+    /// the order they were given to the encoder (i.e. after any transforms
+    /// are applied).
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    /// use rans::RansError;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 4);
+    /// let frame = FrameEncoder::new()
+    ///     .with_content_checksum(true)
+    ///     .encode(&model, &[1, 0, 1]);
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert!(decoder.header().content_crc32().is_some());
+    /// assert_eq!(decoder.decode(&model).unwrap(), [1, 0, 1]);
+    ///
+    /// // Same frequencies, but the symbols are shifted by one
+    /// let other_model = Model::from_freqs(vec![0, 4, 12], 4);
+    /// assert_eq!(
+    ///     decoder.decode(&other_model),
+    ///     Err(RansError::ContentChecksumMismatch)
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_content_checksum(mut self, content_checksum: bool) -> Self {
+        self.content_checksum = content_checksum;
+        self
+    }
+
+    /// Sets the transforms recorded in the frame header and applied to the
+    /// data by [`Self::encode_bytes()`].
+    ///
+    /// # Panics
+    /// Panics if there are more than 255 transforms.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    /// use rans::transform::{apply_all, Transform};
+    ///
+    /// let transforms = [Transform::Delta, Transform::Zigzag];
+    /// let data: Vec<u8> = (0..100).map(|i| 100 + i / 3).collect();
+    /// let model = Model::train(&[apply_all(&transforms, &data)], 12);
+    ///
+    /// let frame = FrameEncoder::new()
+    ///     .with_transforms(&transforms)
+    ///     .encode_bytes(&model, &data);
+    /// assert!(frame.len() < 50);
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert_eq!(decoder.decode_bytes(&model).unwrap(), data);
+    /// ```
+    #[must_use]
+    pub fn with_transforms(mut self, transforms: &[Transform]) -> Self {
+        assert!(
+            transforms.len() <= usize::from(u8::MAX),
+            "frames must not have more than 255 transforms"
+        );
+        self.transforms = transforms.to_vec();
+        self
+    }
+
+    /// Sets whether runs of repeated symbols are coded as (symbol, run
+    /// length) pairs. The default is [`RunLengthMode::Never`]. The choice is
+    /// recorded in the header of every frame, so the decoder doesn't need to
+    /// know it.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder, RunLengthMode};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 12);
+    /// let mask: Vec<usize> = (0..10000).map(|i| usize::from(i % 2000 < 10)).collect();
+    ///
+    /// let plain = FrameEncoder::new().encode(&model, &mask);
+    /// let frame = FrameEncoder::new()
+    ///     .with_run_length(RunLengthMode::Auto)
+    ///     .encode(&model, &mask);
+    /// assert!(frame.len() * 10 < plain.len());
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert_eq!(decoder.decode(&model).unwrap(), mask);
+    /// ```
+    #[must_use]
+    pub fn with_run_length(mut self, run_length: RunLengthMode) -> Self {
+        self.run_length = run_length;
+        self
+    }
+
+    /// Encodes `symbols` (indices of the symbols in `model`) into a new
+    /// frame.
+    ///
+    /// # Panics
+    /// Panics if any of the symbols is out of range of the model. Encoding a
+    /// symbol with zero frequency produces data that can't be decoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[5, 1, 2], 8);
+    /// let frame = FrameEncoder::new().encode(&model, &[0, 2, 0, 1]);
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert_eq!(decoder.decode(&model).unwrap(), [0, 2, 0, 1]);
+    /// ```
+    #[must_use]
+    pub fn encode(&self, model: &Model, symbols: &[usize]) -> Vec<u8> {
+        self.encode_iter(model, symbols.iter().copied(), symbols.len())
+    }
+
+    /// Applies the transforms set with [`Self::with_transforms()`] to `data`
+    /// and encodes the resulting bytes into a new frame. `model` has to be
+    /// built from the transformed data.
+    ///
+    /// # Panics
+    /// Panics if any of the transformed bytes is out of range of the model.
+    /// Encoding a byte with zero frequency produces data that can't be
+    /// decoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::train(&[b"hello"], 10);
+    /// let frame = FrameEncoder::new().encode_bytes(&model, b"hello");
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert_eq!(decoder.decode_bytes(&model).unwrap(), b"hello");
+    /// ```
+    #[must_use]
+    pub fn encode_bytes(&self, model: &Model, data: &[u8]) -> Vec<u8> {
+        let data = apply_all(&self.transforms, data);
+        self.encode_iter(
+            model,
+            data.iter().map(|&byte| usize::from(byte)),
+            data.len(),
+        )
+    }
+
+    fn encode_iter<I>(&self, model: &Model, symbols: I, num_symbols: usize) -> Vec<u8>
+    where
+        I: DoubleEndedIterator<Item = usize> + Clone,
+    {
+        let run_length = match self.run_length {
+            _ if num_symbols == 0 => None,
+            RunLengthMode::Never => None,
+            RunLengthMode::Always => Some(RunLengthCoding::new(symbols.clone())),
+            RunLengthMode::Auto => Some(RunLengthCoding::new(symbols.clone()))
+                .filter(|coding| coding.estimate_bits(model) < plain_bits(model, symbols.clone())),
+        };
+
+        let enc_model = EncModel::<ByteRansEncSymbol>::new(model);
+        let mut encoder = ByteRansEncoder::with_growable_buffer();
+        match &run_length {
+            Some(coding) => coding.encode(&enc_model, &mut encoder),
+            None => {
+                for symbol in symbols.clone().rev() {
+                    encoder.put_index(symbol, &enc_model);
+                }
+            }
+        }
+        encoder.flush();
+        let payload = encoder.data();
+
+        let mut output = Vec::with_capacity(payload.len() + 32);
+        output.extend_from_slice(&MAGIC);
+        output.push(VERSION);
+        let mut flags = match self.checksum {
+            Checksum::None => 0,
+            Checksum::Crc32 => FLAG_CRC32,
+        };
+        if !self.transforms.is_empty() {
+            flags |= FLAG_TRANSFORMS;
+        }
+        if run_length.is_some() {
+            flags |= FLAG_RUN_LENGTH;
+        }
+        if self.content_checksum {
+            flags |= FLAG_CONTENT_CRC32;
+        }
+        output.push(flags);
+        output.push(model.scale_bits() as u8);
+        varint::write_u64(&mut output, num_symbols as u64);
+        varint::write_u64(&mut output, payload.len() as u64);
+        if !self.transforms.is_empty() {
+            output.push(self.transforms.len() as u8);
+            output.extend(self.transforms.iter().map(|transform| transform.id()));
+        }
+        if let Some(coding) = &run_length {
+            output.extend_from_slice(&coding.model.to_bytes());
+        }
+        let content_checksum = self
+            .content_checksum
+            .then(|| content_crc32(symbols).to_le_bytes());
+        let content_checksum: &[u8] = content_checksum.as_ref().map_or(&[], |bytes| bytes);
+        if self.checksum == Checksum::Crc32 {
+            let checksum = crc32_concat(&[&output, content_checksum, payload]);
+            output.extend_from_slice(&checksum.to_le_bytes());
+        }
+        output.extend_from_slice(content_checksum);
+        output.extend_from_slice(payload);
+        output
+    }
+}
+
+impl Default for FrameEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decoder of frames produced by [`FrameEncoder`].
+#[derive(Debug, Clone)]
+pub struct FrameDecoder<'a> {
+    header: FrameHeader,
+    payload: &'a [u8],
+    frame_len: usize,
+}
+
+impl<'a> FrameDecoder<'a> {
+    /// Parses the frame at the beginning of `data` and verifies its header
+    /// and its checksum, so that malformed frames are rejected before the
+    /// payload is decoded. Any data after the frame is
+    /// ignored; use [`Self::frame_len()`] to find out where the next frame
+    /// starts.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the frame is truncated,
+    /// [`RansError::InvalidMagic`] if it doesn't start with [`MAGIC`],
+    /// [`RansError::UnsupportedVersion`] if its version is not [`VERSION`],
+    /// [`RansError::InvalidHeader`] if the flags, the scale bits, the
+    /// declared lengths or the transforms are invalid, or
+    /// [`RansError::ChecksumMismatch`] if the frame is corrupted.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    /// use rans::RansError;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let mut frame = FrameEncoder::new().encode(&model, &[0, 1]);
+    /// assert!(FrameDecoder::new(&frame).is_ok());
+    ///
+    /// *frame.last_mut().unwrap() ^= 1;
+    /// assert_eq!(
+    ///     FrameDecoder::new(&frame).unwrap_err(),
+    ///     RansError::ChecksumMismatch
+    /// );
+    /// ```
+    pub fn new(data: &'a [u8]) -> Result<Self, RansError> {
+        let fixed = data
+            .get(..FIXED_HEADER_LEN)
+            .ok_or(RansError::UnexpectedEof)?;
+        if fixed[..MAGIC.len()] != MAGIC {
+            return Err(RansError::InvalidMagic);
+        }
+        if fixed[4] != VERSION {
+            return Err(RansError::UnsupportedVersion);
+        }
+        let flags = fixed[5];
+        let scale_bits = u32::from(fixed[6]);
+        if flags & !KNOWN_FLAGS != 0 || scale_bits > 16 {
+            return Err(RansError::InvalidHeader);
+        }
+
+        let mut pos = FIXED_HEADER_LEN;
+        let num_symbols = varint::read_u64(data, &mut pos)?;
+        let payload_len = usize::try_from(varint::read_u64(data, &mut pos)?)
+            .map_err(|_| RansError::InvalidHeader)?;
+        // The payload always contains the final rANS state, and nothing else
+        // if there are no symbols
+        if usize::try_from(num_symbols).is_err()
+            || payload_len < MIN_PAYLOAD_LEN
+            || (num_symbols == 0
+                && (payload_len != MIN_PAYLOAD_LEN || flags & FLAG_RUN_LENGTH != 0))
+        {
+            return Err(RansError::InvalidHeader);
+        }
+        let transforms = if flags & FLAG_TRANSFORMS != 0 {
+            let count = usize::from(*data.get(pos).ok_or(RansError::UnexpectedEof)?);
+            let ids = data
+                .get(pos + 1..pos + 1 + count)
+                .ok_or(RansError::UnexpectedEof)?;
+            pos += 1 + count;
+            ids.iter()
+                .map(|&id| Transform::from_id(id).ok_or(RansError::InvalidHeader))
+                .collect::<Result<_, _>>()?
+        } else {
+            Vec::new()
+        };
+        let run_length_model = if flags & FLAG_RUN_LENGTH != 0 {
+            let model = Model::read_bytes(data, &mut pos)?;
+            if model.scale_bits() > 16 || model.len() > RUN_LENGTH_SCHEME.num_tokens() {
+                return Err(RansError::InvalidHeader);
+            }
+            Some(model)
+        } else {
+            None
+        };
+        let checksum_pos = pos;
+        let checksum = if flags & FLAG_CRC32 != 0 {
+            Some(read_u32(data, &mut pos)?)
+        } else {
+            None
+        };
+        let content_checksum = if flags & FLAG_CONTENT_CRC32 != 0 {
+            Some(read_u32(data, &mut pos)?)
+        } else {
+            None
+        };
+
+        let end = pos
+            .checked_add(payload_len)
+            .ok_or(RansError::InvalidHeader)?;
+        let payload = data.get(pos..end).ok_or(RansError::UnexpectedEof)?;
+        if checksum.is_some_and(|checksum| {
+            checksum != crc32_concat(&[&data[..checksum_pos], &data[checksum_pos + 4..end]])
+        }) {
+            return Err(RansError::ChecksumMismatch);
+        }
+
+        Ok(Self {
+            header: FrameHeader {
+                scale_bits,
+                num_symbols,
+                payload_len,
+                checksum,
+                content_checksum,
+                transforms,
+                run_length_model,
+            },
+            payload,
+            frame_len: end,
+        })
+    }
+
+    /// Returns the header of the frame.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let frame = FrameEncoder::new().encode(&model, &[0, 1]);
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert!(decoder.header().crc32().is_some());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn header(&self) -> &FrameHeader {
+        &self.header
+    }
+
+    /// Returns the rANS payload of the frame.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let frame = FrameEncoder::new().encode(&model, &[]);
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert_eq!(decoder.payload(), [0, 0, 128, 0]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    /// Returns the total length of the frame in bytes, including the header.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let mut data = FrameEncoder::new().encode(&model, &[0, 1]);
+    /// let frame_len = data.len();
+    /// data.extend_from_slice(b"trailing data");
+    /// assert_eq!(FrameDecoder::new(&data).unwrap().frame_len(), frame_len);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    /// Decodes the symbols stored in the frame using given `model`, which
+    /// has to be the same as the one used by the encoder.
+    ///
+    /// The payload is decoded with every read checked against its end, so a
+    /// frame that is malformed despite passing [`Self::new()`] (e.g. one
+    /// without a checksum) is rejected instead of producing garbage symbols.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidModel`] if the scale bits of `model` don't
+    /// match the ones stored in the header, [`RansError::InputExhausted`] if
+    /// the payload ends before all the symbols are decoded,
+    /// [`RansError::InvalidHeader`] if the decoded run lengths exceed the
+    /// number of symbols stored in the header or the payload isn't fully
+    /// consumed by them, or [`RansError::ContentChecksumMismatch`] if the
+    /// frame has a
+    /// checksum of the symbols (see
+    /// [`FrameEncoder::with_content_checksum()`]) and the decoded symbols
+    /// don't match it.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    /// use rans::RansError;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let frame = FrameEncoder::new().encode(&model, &[1, 0]);
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert_eq!(decoder.decode(&model).unwrap(), [1, 0]);
+    ///
+    /// let other_model = Model::from_counts(&[1, 1], 12);
+    /// assert_eq!(decoder.decode(&other_model), Err(RansError::InvalidModel));
+    /// ```
+    pub fn decode(&self, model: &Model) -> Result<Vec<usize>, RansError> {
+        if model.scale_bits() != self.header.scale_bits {
+            return Err(RansError::InvalidModel);
+        }
+        let num_symbols =
+            usize::try_from(self.header.num_symbols).map_err(|_| RansError::InvalidHeader)?;
+
+        let scale_bits = self.header.scale_bits;
+        let dec_model = DecModel::<ByteRansDecSymbol>::new(model);
+        let mut decoder = ByteRansDecoder::try_new(self.payload)?;
+        let mut symbols = Vec::with_capacity(num_symbols.min(self.payload.len() * 8));
+        match &self.header.run_length_model {
+            Some(run_length_model) => {
+                let run_dec_model =
+                    UintDecModel::<ByteRansDecSymbol>::new(run_length_model, RUN_LENGTH_SCHEME);
+                while symbols.len() < num_symbols {
+                    let (index, symbol) = dec_model.lookup(decoder.get(scale_bits));
+                    decoder.try_advance(symbol, scale_bits)?;
+                    let run = run_dec_model.try_decode_at(&mut decoder, 0)? as usize + 1;
+                    if run > num_symbols - symbols.len() {
+                        return Err(RansError::InvalidHeader);
+                    }
+                    symbols.resize(symbols.len() + run, index);
+                }
+            }
+            None => {
+                for _ in 0..num_symbols {
+                    let (index, symbol) = dec_model.lookup(decoder.get(scale_bits));
+                    decoder.try_advance(symbol, scale_bits)?;
+                    symbols.push(index);
+                }
+            }
+        }
+        if !decoder.is_exhausted() {
+            return Err(RansError::InvalidHeader);
+        }
+
+        if self
+            .header
+            .content_checksum
+            .is_some_and(|checksum| checksum != content_crc32(symbols.iter().copied()))
+        {
+            return Err(RansError::ContentChecksumMismatch);
+        }
+        Ok(symbols)
+    }
+
+    /// Decodes the bytes stored in the frame using given `model` and
+    /// reverses the transforms recorded in the header, undoing
+    /// [`FrameEncoder::encode_bytes()`].
+    ///
+    /// # Errors
+    /// Returns the same errors as [`Self::decode()`], as well as
+    /// [`RansError::InvalidModel`] if the model has more than 256 symbols,
+    /// or any error returned by [`reverse_all()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::frame::{FrameDecoder, FrameEncoder};
+    /// use rans::model::Model;
+    /// use rans::transform::{apply_all, Transform};
+    ///
+    /// let transforms = [Transform::RunLength];
+    /// let data = [0; 1000];
+    /// let model = Model::train(&[apply_all(&transforms, &data)], 8);
+    /// let frame = FrameEncoder::new()
+    ///     .with_transforms(&transforms)
+    ///     .encode_bytes(&model, &data);
+    /// let decoder = FrameDecoder::new(&frame).unwrap();
+    /// assert_eq!(decoder.decode_bytes(&model).unwrap(), data);
+    /// ```
+    pub fn decode_bytes(&self, model: &Model) -> Result<Vec<u8>, RansError> {
+        if model.len() > 256 {
+            return Err(RansError::InvalidModel);
+        }
+
+        let data: Vec<u8> = self
+            .decode(model)?
+            .into_iter()
+            .map(|symbol| symbol as u8)
+            .collect();
+        reverse_all(&self.header.transforms, &data)
+    }
+}
+
+/// Lookup table of the CRC-32 (reflected 0xEDB88320 polynomial) algorithm.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Runs of repeated symbols of a frame, along with the model of their lengths.
+struct RunLengthCoding {
+    /// Symbols and their run lengths minus one
+    runs: Vec<(usize, u32)>,
+    model: Model,
+}
+
+impl RunLengthCoding {
+    fn new<I: Iterator<Item = usize>>(symbols: I) -> Self {
+        let mut runs: Vec<(usize, u32)> = Vec::new();
+        for symbol in symbols {
+            match runs.last_mut() {
+                Some((last, len)) if *last == symbol && *len < u32::MAX => *len += 1,
+                _ => runs.push((symbol, 0)),
+            }
+        }
+
+        let lens: Vec<u32> = runs.iter().map(|&(_, len)| len).collect();
+        let model = Model::from_counts(&RUN_LENGTH_SCHEME.histogram(&lens), RUN_LENGTH_SCALE_BITS);
+        Self { runs, model }
+    }
+
+    /// Estimates the size of the run-length coded payload and the model
+    /// stored in the header, in bits.
+    fn estimate_bits(&self, model: &Model) -> f64 {
+        let tokens = &self.model;
+        let (symbol_bits, run_bits) =
+            self.runs
+                .iter()
+                .fold((0.0, 0.0), |(symbol_bits, run_bits), &(symbol, len)| {
+                    let (token, num_bits, _) = RUN_LENGTH_SCHEME.tokenize(len);
+                    (
+                        symbol_bits + model.cost_bits(symbol),
+                        run_bits + tokens.cost_bits(token as usize) + f64::from(num_bits),
+                    )
+                });
+        symbol_bits + run_bits + (tokens.to_bytes().len() * 8) as f64
+    }
+
+    fn encode(&self, enc_model: &EncModel<ByteRansEncSymbol>, encoder: &mut ByteRansEncoder) {
+        let run_enc_model = UintEncModel::<ByteRansEncSymbol>::new(&self.model, RUN_LENGTH_SCHEME);
+        for &(symbol, len) in self.runs.iter().rev() {
+            run_enc_model.put_at(encoder, 0, len);
+            encoder.put_index(symbol, enc_model);
+        }
+    }
+}
+
+/// Computes the size of `symbols` coded one by one with `model`, in bits.
+fn plain_bits<I: Iterator<Item = usize>>(model: &Model, symbols: I) -> f64 {
+    symbols.map(|symbol| model.cost_bits(symbol)).sum()
+}
+
+/// Computes the CRC-32 checksum of `data`.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(!0, data)
+}
+
+/// Computes the CRC-32 checksum of the concatenation of `parts`.
+fn crc32_concat(parts: &[&[u8]]) -> u32 {
+    !parts.iter().fold(!0, |crc, part| crc32_update(crc, part))
+}
+
+/// Computes the CRC-32 checksum of `symbols`, each as a little-endian `u32`.
+fn content_crc32<I: Iterator<Item = usize>>(symbols: I) -> u32 {
+    !symbols.fold(!0, |crc, symbol| {
+        crc32_update(crc, &(symbol as u32).to_le_bytes())
+    })
+}
+
+/// Updates the (inverted) CRC-32 register `crc` with `data`.
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        CRC32_TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Reads a little-endian `u32` at `pos`, advancing it.
+fn read_u32(data: &[u8], pos: &mut usize) -> Result<u32, RansError> {
+    let bytes = data.get(*pos..*pos + 4).ok_or(RansError::UnexpectedEof)?;
+    *pos += 4;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::frame::{crc32, Checksum, FrameDecoder, FrameEncoder, RunLengthMode};
+    use crate::model::Model;
+    use crate::transform::{apply_all, Transform};
+    use crate::RansError;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let model = Model::from_counts(&[10, 1, 30, 5, 0, 7], 12);
+        let symbols: Vec<usize> = (0..10000)
+            .map(|_| [0, 1, 2, 3, 5][rng.gen_range(0..5)])
+            .collect();
+
+        for checksum in [Checksum::None, Checksum::Crc32] {
+            let frame = FrameEncoder::with_checksum(checksum).encode(&model, &symbols);
+            let decoder = FrameDecoder::new(&frame).unwrap();
+
+            assert_eq!(decoder.header().num_symbols(), 10000);
+            assert_eq!(decoder.frame_len(), frame.len());
+            assert_eq!(decoder.decode(&model).unwrap(), symbols);
+        }
+    }
+
+    #[test]
+    fn test_consecutive_frames() {
+        let model = Model::from_counts(&[1, 1, 1, 1], 8);
+        let mut data = FrameEncoder::new().encode(&model, &[0, 1, 2]);
+        data.extend(FrameEncoder::new().encode(&model, &[3, 3]));
+
+        let first = FrameDecoder::new(&data).unwrap();
+        let second = FrameDecoder::new(&data[first.frame_len()..]).unwrap();
+        assert_eq!(first.decode(&model).unwrap(), [0, 1, 2]);
+        assert_eq!(second.decode(&model).unwrap(), [3, 3]);
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let model = Model::from_counts(&[1, 1], 8);
+        let frame = FrameEncoder::new().encode(&model, &[0, 1]);
+
+        for (index, value, error) in [
+            (0, b'x', RansError::InvalidMagic),
+            (3, b's', RansError::InvalidMagic),
+            (4, 2, RansError::UnsupportedVersion),
+            (5, 0x80, RansError::InvalidHeader),
+            (6, 17, RansError::InvalidHeader),
+        ] {
+            let mut corrupted = frame.clone();
+            corrupted[index] = value;
+            assert_eq!(FrameDecoder::new(&corrupted).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let model = Model::from_counts(&[1, 1], 8);
+        let frame = FrameEncoder::with_checksum(Checksum::None).encode(&model, &[]);
+        // Number of symbols and payload length
+        assert_eq!(frame[7..9], [0, 4]);
+
+        for (num_symbols, payload_len) in [(0, 5), (1, 3), (0, 0)] {
+            let mut corrupted = frame.clone();
+            corrupted[7] = num_symbols;
+            corrupted[8] = payload_len;
+            corrupted.push(0);
+            assert_eq!(
+                FrameDecoder::new(&corrupted).unwrap_err(),
+                RansError::InvalidHeader
+            );
+        }
+    }
+
+    #[test]
+    fn test_content_checksum() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(3);
+        let model = Model::from_counts(&[10, 1, 30, 5, 0, 7], 12);
+        // Reads the same data, but decodes different symbols
+        let mut freqs = model.freqs().to_vec();
+        freqs.insert(0, 0);
+        let other_model = Model::from_freqs(freqs, 12);
+        let symbols: Vec<usize> = (0..10000)
+            .map(|_| [0, 1, 2, 3, 5][rng.gen_range(0..5)])
+            .collect();
+
+        for mode in [RunLengthMode::Never, RunLengthMode::Always] {
+            let frame = FrameEncoder::new()
+                .with_run_length(mode)
+                .with_content_checksum(true)
+                .encode(&model, &symbols);
+            let decoder = FrameDecoder::new(&frame).unwrap();
+
+            let bytes: Vec<u8> = symbols
+                .iter()
+                .flat_map(|&symbol| (symbol as u32).to_le_bytes())
+                .collect();
+            assert_eq!(decoder.header().content_crc32(), Some(crc32(&bytes)));
+            assert_eq!(decoder.decode(&model).unwrap(), symbols);
+            assert_eq!(
+                decoder.decode(&other_model),
+                Err(RansError::ContentChecksumMismatch)
+            );
+        }
+
+        // Without the checksum, decoding with a wrong model yields garbage
+        let plain = FrameEncoder::new().encode(&model, &symbols);
+        let decoder = FrameDecoder::new(&plain).unwrap();
+        assert_eq!(decoder.header().content_crc32(), None);
+        assert_ne!(decoder.decode(&other_model).unwrap(), symbols);
+    }
+
+    #[test]
+    fn test_transforms() {
+        let transforms = [Transform::Delta, Transform::Zigzag, Transform::RunLength];
+        let data: Vec<u8> = (0..10000_u32).map(|i| (i / 50 % 7 * 3) as u8).collect();
+        let model = Model::train(&[apply_all(&transforms, &data)], 12);
+
+        let plain = FrameEncoder::new().encode_bytes(&model, &data);
+        let frame = FrameEncoder::new()
+            .with_transforms(&transforms)
+            .encode_bytes(&model, &data);
+        assert!(frame.len() * 4 < plain.len());
+
+        let decoder = FrameDecoder::new(&frame).unwrap();
+        assert_eq!(decoder.header().transforms(), transforms);
+        assert_eq!(decoder.decode_bytes(&model).unwrap(), data);
+
+        let mut corrupted = frame.clone();
+        let pos = corrupted
+            .windows(4)
+            .position(|w| w == [3, 0, 1, 3])
+            .unwrap();
+        corrupted[pos + 1] = 100;
+        assert_eq!(
+            FrameDecoder::new(&corrupted).unwrap_err(),
+            RansError::InvalidHeader
+        );
+    }
+
+    #[test]
+    fn test_run_length_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        let model = Model::from_counts(&[10, 1, 30, 5, 0, 7], 12);
+        let mut symbols = Vec::new();
+        while symbols.len() < 100_000 {
+            let symbol = [0, 1, 2, 3, 5][rng.gen_range(0..5)];
+            let run = if rng.gen_bool(0.1) {
+                rng.gen_range(1..5000)
+            } else {
+                rng.gen_range(1..4)
+            };
+            symbols.extend(std::iter::repeat(symbol).take(run));
+        }
+
+        for mode in [RunLengthMode::Always, RunLengthMode::Auto] {
+            let frame = FrameEncoder::new()
+                .with_run_length(mode)
+                .encode(&model, &symbols);
+            let decoder = FrameDecoder::new(&frame).unwrap();
+
+            assert!(decoder.header().run_length());
+            assert_eq!(decoder.frame_len(), frame.len());
+            assert_eq!(decoder.decode(&model).unwrap(), symbols);
+        }
+    }
+
+    #[test]
+    fn test_run_length_auto_selection() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(2);
+        let model = Model::from_counts(&[1, 1, 1, 1], 8);
+        let symbols: Vec<usize> = (0..10000).map(|_| rng.gen_range(0..4)).collect();
+
+        let plain = FrameEncoder::new().encode(&model, &symbols);
+        let auto = FrameEncoder::new()
+            .with_run_length(RunLengthMode::Auto)
+            .encode(&model, &symbols);
+        assert_eq!(auto, plain);
+
+        let always = FrameEncoder::new()
+            .with_run_length(RunLengthMode::Always)
+            .encode(&model, &symbols);
+        assert!(always.len() > plain.len());
+        let decoder = FrameDecoder::new(&always).unwrap();
+        assert_eq!(decoder.decode(&model).unwrap(), symbols);
+
+        let empty = FrameEncoder::new()
+            .with_run_length(RunLengthMode::Always)
+            .encode(&model, &[]);
+        assert!(!FrameDecoder::new(&empty).unwrap().header().run_length());
+    }
+
+    #[test]
+    fn test_run_length_exceeds_num_symbols() {
+        let model = Model::from_counts(&[1, 1], 8);
+        let mut frame = FrameEncoder::with_checksum(Checksum::None)
+            .with_run_length(RunLengthMode::Always)
+            .encode(&model, &[1; 100]);
+        // Number of symbols
+        assert_eq!(frame[7], 100);
+        frame[7] = 50;
+
+        let decoder = FrameDecoder::new(&frame).unwrap();
+        assert_eq!(decoder.decode(&model), Err(RansError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_corrupted_num_symbols() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(4);
+        let model = Model::from_counts(&[10, 1, 30, 5], 8);
+        let symbols: Vec<usize> = (0..100).map(|_| rng.gen_range(0..4)).collect();
+
+        for mode in [RunLengthMode::Never, RunLengthMode::Always] {
+            // The checksum covers the header
+            let mut frame = FrameEncoder::new()
+                .with_run_length(mode)
+                .encode(&model, &symbols);
+            // Number of symbols
+            assert_eq!(frame[7], 100);
+            frame[7] = 99;
+            assert_eq!(
+                FrameDecoder::new(&frame).unwrap_err(),
+                RansError::ChecksumMismatch
+            );
+
+            // Without it, the payload has to match the number of symbols
+            let frame = FrameEncoder::with_checksum(Checksum::None)
+                .with_run_length(mode)
+                .encode(&model, &symbols);
+            for (num_symbols, error) in [
+                (&[99][..], RansError::InvalidHeader),
+                (&[101], RansError::InputExhausted),
+                (&[0xff, 0x7f], RansError::InputExhausted),
+            ] {
+                let mut corrupted = frame[..7].to_vec();
+                corrupted.extend_from_slice(num_symbols);
+                corrupted.extend_from_slice(&frame[8..]);
+
+                let decoder = FrameDecoder::new(&corrupted).unwrap();
+                assert_eq!(decoder.decode(&model), Err(error));
+            }
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let model = Model::from_counts(&[1, 1], 8);
+        let frame = FrameEncoder::new().encode(&model, &[0, 1]);
+
+        for len in 0..frame.len() {
+            assert_eq!(
+                FrameDecoder::new(&frame[..len]).unwrap_err(),
+                RansError::UnexpectedEof
+            );
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::ops::Range;
+
+use crate::model::Model;
+use crate::RansError;
+
+/// Table of symbol frequencies, normalized or not.
+///
+/// This is the plain, unvalidated counterpart of [`Model`]: it can hold raw
+/// counts as well as normalized frequencies, and converts between
+/// frequencies and cumulative frequencies, which is what most frequency
+/// table formats store. Once the table is ready, turn it into a [`Model`]
+/// (with [`Self::to_model()`] or [`Self::normalize()`]) to create the
+/// encoder and decoder symbols from it.
+///
+/// # Examples
+/// ```
+/// use rans::freq::FreqTable;
+///
+/// let table = FreqTable::from_cum_freqs(&[0, 1, 3, 4]).unwrap();
+/// assert_eq!(table.freqs(), [1, 2, 1]);
+/// assert_eq!(table.cum_freqs(), [0, 1, 3, 4]);
+///
+/// let model = table.to_model(2).unwrap();
+/// assert_eq!(model.freqs(), [1, 2, 1]);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct FreqTable {
+    freqs: Vec<u32>,
+}
+
+impl FreqTable {
+    /// Creates a new table with given frequencies.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    ///
+    /// let table = FreqTable::new(vec![3, 0, 5]);
+    /// assert_eq!(table.total(), 8);
+    /// ```
+    #[must_use]
+    pub fn new(freqs: Vec<u32>) -> Self {
+        Self { freqs }
+    }
+
+    /// Creates a new table from cumulative frequencies, i.e. the start of
+    /// every symbol followed by the total. The first value is the start of
+    /// the first symbol and doesn't need to be 0.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidModel`] if `cum_freqs` is empty or not
+    /// sorted.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    /// use rans::RansError;
+    ///
+    /// let table = FreqTable::from_cum_freqs(&[0, 2, 2, 8]).unwrap();
+    /// assert_eq!(table.freqs(), [2, 0, 6]);
+    /// assert_eq!(
+    ///     FreqTable::from_cum_freqs(&[0, 3, 2]),
+    ///     Err(RansError::InvalidModel)
+    /// );
+    /// ```
+    pub fn from_cum_freqs(cum_freqs: &[u32]) -> Result<Self, RansError> {
+        if cum_freqs.is_empty() {
+            return Err(RansError::InvalidModel);
+        }
+
+        let freqs = cum_freqs
+            .windows(2)
+            .map(|pair| pair[1].checked_sub(pair[0]).ok_or(RansError::InvalidModel))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { freqs })
+    }
+
+    /// Returns the frequencies of all symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    ///
+    /// let table = FreqTable::new(vec![3, 0, 5]);
+    /// assert_eq!(table.freqs(), [3, 0, 5]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn freqs(&self) -> &[u32] {
+        &self.freqs
+    }
+
+    /// Returns the cumulative frequencies of all symbols, followed by the
+    /// total, i.e. `len() + 1` values starting with 0. This is the inverse of
+    /// [`Self::from_cum_freqs()`].
+    ///
+    /// # Panics
+    /// Panics if the total doesn't fit in `u32`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    ///
+    /// let table = FreqTable::new(vec![3, 0, 5]);
+    /// assert_eq!(table.cum_freqs(), [0, 3, 3, 8]);
+    /// ```
+    #[must_use]
+    pub fn cum_freqs(&self) -> Vec<u32> {
+        let mut cum_freqs = Vec::with_capacity(self.freqs.len() + 1);
+        let mut cum_freq = 0_u32;
+        cum_freqs.push(cum_freq);
+        for &freq in &self.freqs {
+            cum_freq = cum_freq
+                .checked_add(freq)
+                .expect("total frequency must fit in u32");
+            cum_freqs.push(cum_freq);
+        }
+        cum_freqs
+    }
+
+    /// Returns the number of symbols in this table, including the ones with
+    /// zero frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    ///
+    /// assert_eq!(FreqTable::new(vec![3, 0, 5]).len(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.freqs.len()
+    }
+
+    /// Returns whether this table contains no symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    ///
+    /// assert!(FreqTable::default().is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.freqs.is_empty()
+    }
+
+    /// Returns the sum of all frequencies.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    ///
+    /// assert_eq!(FreqTable::new(vec![u32::MAX, 1]).total(), 1 << 32);
+    /// ```
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.freqs.iter().map(|&freq| u64::from(freq)).sum()
+    }
+
+    /// Returns a table of the symbols in given range, e.g. to split a joint
+    /// table into the tables of its parts.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    ///
+    /// let table = FreqTable::new(vec![1, 2, 3, 4]);
+    /// assert_eq!(table.slice(1..3).freqs(), [2, 3]);
+    /// ```
+    #[must_use]
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        Self::new(self.freqs[range].to_vec())
+    }
+
+    /// Adds the frequencies of `other` to the frequencies of the same symbols
+    /// in this table, e.g. to combine the histograms of multiple blocks. The
+    /// table is extended if `other` has more symbols; the sums saturate at
+    /// `u32::MAX`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    ///
+    /// let mut table = FreqTable::new(vec![1, 2]);
+    /// table.merge(&FreqTable::new(vec![10, 0, 7]));
+    /// assert_eq!(table.freqs(), [11, 2, 7]);
+    /// ```
+    pub fn merge(&mut self, other: &Self) {
+        if self.freqs.len() < other.freqs.len() {
+            self.freqs.resize(other.freqs.len(), 0);
+        }
+        for (freq, &other) in self.freqs.iter_mut().zip(&other.freqs) {
+            *freq = freq.saturating_add(other);
+        }
+    }
+
+    /// Checks that this table is normalized, i.e. that its frequencies sum
+    /// up to exactly `1 << scale_bits`.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidModel`] if the table is not normalized,
+    /// or if `scale_bits` is larger than 31.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    /// use rans::RansError;
+    ///
+    /// let table = FreqTable::new(vec![1, 2, 1]);
+    /// assert_eq!(table.validate(2), Ok(()));
+    /// assert_eq!(table.validate(3), Err(RansError::InvalidModel));
+    /// ```
+    pub fn validate(&self, scale_bits: u32) -> Result<(), RansError> {
+        if scale_bits > 31 || self.total() != 1 << scale_bits {
+            return Err(RansError::InvalidModel);
+        }
+
+        Ok(())
+    }
+
+    /// Converts this normalized table into a [`Model`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidModel`] if the table is not normalized to
+    /// `1 << scale_bits`. See [`Self::validate()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    ///
+    /// let model = FreqTable::new(vec![1, 2, 1]).to_model(2).unwrap();
+    /// assert_eq!(model.cum_freqs(), [0, 1, 3]);
+    /// ```
+    pub fn to_model(&self, scale_bits: u32) -> Result<Model, RansError> {
+        self.validate(scale_bits)?;
+
+        Ok(Model::from_freqs(self.freqs.clone(), scale_bits))
+    }
+
+    /// Normalizes this table, treated as raw counts, into a [`Model`] with
+    /// the same algorithm as [`Model::from_counts()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidModel`] if all frequencies are zero, if
+    /// `scale_bits` is larger than 31, or if there are more symbols with
+    /// non-zero frequency than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::freq::FreqTable;
+    /// use rans::RansError;
+    ///
+    /// let model = FreqTable::new(vec![10, 0, 30]).normalize(4).unwrap();
+    /// assert_eq!(model.freqs(), [4, 0, 12]);
+    /// assert_eq!(
+    ///     FreqTable::new(vec![1, 1, 1]).normalize(1),
+    ///     Err(RansError::InvalidModel)
+    /// );
+    /// ```
+    pub fn normalize(&self, scale_bits: u32) -> Result<Model, RansError> {
+        let present = self.freqs.iter().filter(|&&freq| freq > 0).count() as u64;
+        if scale_bits > 31 || present == 0 || present > 1 << scale_bits {
+            return Err(RansError::InvalidModel);
+        }
+
+        Ok(Model::from_counts(&self.freqs, scale_bits))
+    }
+}
+
+impl From<Vec<u32>> for FreqTable {
+    fn from(freqs: Vec<u32>) -> Self {
+        Self::new(freqs)
+    }
+}
+
+impl From<&Model> for FreqTable {
+    fn from(model: &Model) -> Self {
+        Self::new(model.freqs().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::freq::FreqTable;
+    use crate::model::Model;
+    use crate::RansError;
+
+    #[test]
+    fn test_cum_freqs_round_trip() {
+        let table = FreqTable::new(vec![0, 5, 0, 0, 17, 1, 0]);
+        let cum_freqs = table.cum_freqs();
+
+        assert_eq!(cum_freqs, [0, 0, 5, 5, 5, 22, 23, 23]);
+        assert_eq!(FreqTable::from_cum_freqs(&cum_freqs), Ok(table));
+    }
+
+    #[test]
+    fn test_from_cum_freqs_offset() {
+        let table = FreqTable::from_cum_freqs(&[100, 101, 103]).unwrap();
+
+        assert_eq!(table.freqs(), [1, 2]);
+        assert_eq!(table.total(), 3);
+    }
+
+    #[test]
+    fn test_from_cum_freqs_invalid() {
+        assert_eq!(FreqTable::from_cum_freqs(&[]), Err(RansError::InvalidModel));
+        assert_eq!(
+            FreqTable::from_cum_freqs(&[0, 1, 0]),
+            Err(RansError::InvalidModel)
+        );
+        assert_eq!(FreqTable::from_cum_freqs(&[5]), Ok(FreqTable::default()));
+    }
+
+    #[test]
+    fn test_slice_and_merge() {
+        let table = FreqTable::new(vec![1, 2, 3, 4, 5, 6]);
+        let mut merged = table.slice(0..3);
+        merged.merge(&table.slice(3..6));
+
+        assert_eq!(merged.freqs(), [5, 7, 9]);
+        merged.merge(&FreqTable::new(vec![u32::MAX]));
+        assert_eq!(merged.freqs(), [u32::MAX, 7, 9]);
+    }
+
+    #[test]
+    fn test_model_conversions() {
+        let model = Model::from_counts(&[3, 0, 9, 1], 10);
+        let table = FreqTable::from(&model);
+
+        assert_eq!(table.validate(10), Ok(()));
+        assert_eq!(table.to_model(10), Ok(model));
+        assert_eq!(table.to_model(11), Err(RansError::InvalidModel));
+        assert_eq!(table.to_model(32), Err(RansError::InvalidModel));
+    }
+
+    #[test]
+    fn test_normalize_invalid() {
+        assert_eq!(
+            FreqTable::new(vec![0, 0]).normalize(8),
+            Err(RansError::InvalidModel)
+        );
+        assert_eq!(
+            FreqTable::default().normalize(8),
+            Err(RansError::InvalidModel)
+        );
+        assert_eq!(
+            FreqTable::new(vec![1]).normalize(32),
+            Err(RansError::InvalidModel)
+        );
+    }
+}
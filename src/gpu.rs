@@ -0,0 +1,566 @@
+use crate::model::{DecModel, EncModel};
+use crate::simd_decoder::SimdRansDecSymbol;
+use crate::simd_encoder::{SimdRansEncSymbol, RANS_WORD_L};
+use crate::{RansDecSymbol, RansEncSymbol, RansError};
+
+/// Number of 32-bit words in the stream header.
+const HEADER_LEN: usize = 4;
+
+/// Shape of the stream produced by [`encode()`]: the number of lanes (rANS
+/// states decoded in lockstep, i.e. threads of a subgroup) and the number of
+/// symbols each lane decodes in a single block.
+///
+/// # Examples
+/// ```
+/// use rans::gpu::GpuLayout;
+///
+/// let layout = GpuLayout::new(32, 1024);
+/// assert_eq!(layout.symbols_per_block(), 32768);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GpuLayout {
+    lanes: u32,
+    block_size: u32,
+}
+
+impl GpuLayout {
+    /// Creates a new layout with given number of lanes and symbols per lane
+    /// in a block. `lanes` should usually be the subgroup (warp) size of the
+    /// target GPU.
+    ///
+    /// # Panics
+    /// Panics if `lanes` or `block_size` is zero, or if a block would contain
+    /// more than `u32::MAX` symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::GpuLayout;
+    ///
+    /// let layout = GpuLayout::new(64, 256);
+    /// assert_eq!(layout.lanes(), 64);
+    /// assert_eq!(layout.block_size(), 256);
+    /// ```
+    #[must_use]
+    pub fn new(lanes: u32, block_size: u32) -> Self {
+        assert!(
+            lanes > 0 && block_size > 0,
+            "lanes and block_size must not be zero"
+        );
+        assert!(
+            lanes.checked_mul(block_size).is_some(),
+            "a block must not contain more than u32::MAX symbols"
+        );
+
+        Self { lanes, block_size }
+    }
+
+    /// Returns the number of lanes.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::GpuLayout;
+    ///
+    /// assert_eq!(GpuLayout::new(32, 1024).lanes(), 32);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn lanes(&self) -> u32 {
+        self.lanes
+    }
+
+    /// Returns the number of symbols decoded by every lane in a single
+    /// block. Only the last block can be shorter than that.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::GpuLayout;
+    ///
+    /// assert_eq!(GpuLayout::new(32, 1024).block_size(), 1024);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Returns the number of symbols in a single (not last) block.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::GpuLayout;
+    ///
+    /// assert_eq!(GpuLayout::new(32, 1024).symbols_per_block(), 32768);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn symbols_per_block(&self) -> usize {
+        self.lanes as usize * self.block_size as usize
+    }
+
+    fn num_blocks(&self, len: usize) -> usize {
+        len / self.symbols_per_block() + usize::from(len % self.symbols_per_block() != 0)
+    }
+}
+
+impl Default for GpuLayout {
+    /// 32 lanes (the subgroup size of most GPUs) decoding 1024 symbols each
+    /// per block.
+    fn default() -> Self {
+        Self::new(32, 1024)
+    }
+}
+
+/// Encodes symbols into a blocked, strided stream meant to be decoded by GPU
+/// subgroups (warps), one block per subgroup, each lane decoding every
+/// `lanes`-th symbol of the block with its own 32-bit rANS state.
+///
+/// The stream is made of little-endian 32-bit words, so that it can be
+/// uploaded as is to a storage buffer of `uint`s:
+///
+/// * the header: the number of lanes, the number of symbols per lane in a
+///   block, the total number of symbols and the number of blocks,
+/// * `num_blocks + 1` offsets of the blocks in the stream, in words, the last
+///   one pointing at the end of the last block,
+/// * the blocks, each one starting with the initial states of all the lanes,
+///   followed by the 16-bit renormalization words, two per 32-bit word (lower
+///   half first), padded with zeros to a whole 32-bit word.
+///
+/// Symbol `j` of block `b` (symbol `b * lanes * block_size + j` of the input)
+/// is decoded by lane `j % lanes` in step `j / lanes`. After decoding a
+/// symbol, a lane whose state dropped below `1 << 16` shifts it left by 16
+/// bits and reads the next renormalization word into its lower bits; within
+/// a step, the lanes read the consecutive words in the lane order, so a
+/// lane's word is the one following the words read by the lower lanes
+/// (counted with a subgroup ballot). The decoding arithmetic is the same as
+/// in [`crate::simd_decoder::SimdRansDecoder`], so the model can have at most
+/// 16 `scale_bits`.
+///
+/// [`GpuDecoder`] is a CPU reference decoder of this format, mirroring the
+/// steps a GPU decoder has to take.
+///
+/// # Panics
+/// Panics if any of the symbols is out of the model's range or has zero
+/// frequency, or if the stream would contain more than `u32::MAX` symbols or
+/// words.
+///
+/// # Examples
+/// ```
+/// use rans::gpu::{encode, GpuDecoder, GpuLayout};
+/// use rans::model::{DecModel, EncModel, Model};
+///
+/// let model = Model::from_counts(&[5, 2, 1], 12);
+/// let symbols: Vec<usize> = (0..1000).map(|i| i % 7 / 3).collect();
+///
+/// let data = encode(&symbols, &EncModel::new(&model), GpuLayout::new(32, 8));
+///
+/// let decoder = GpuDecoder::new(&data).unwrap();
+/// assert_eq!(decoder.num_blocks(), 4);
+/// assert_eq!(decoder.decode(&DecModel::new(&model)).unwrap(), symbols);
+/// ```
+#[must_use]
+pub fn encode(
+    symbols: &[usize],
+    model: &EncModel<SimdRansEncSymbol>,
+    layout: GpuLayout,
+) -> Vec<u8> {
+    let num_blocks = layout.num_blocks(symbols.len());
+    let mut words = vec![
+        layout.lanes,
+        layout.block_size,
+        u32::try_from(symbols.len()).expect("too many symbols for the GPU layout"),
+        num_blocks as u32,
+    ];
+    // Placeholders for the block offsets
+    words.resize(HEADER_LEN + num_blocks + 1, 0);
+    words[HEADER_LEN] = to_offset(words.len());
+
+    let lanes = layout.lanes as usize;
+    let mut states = vec![0; lanes];
+    let mut renorm_words = Vec::new();
+    for (block, block_symbols) in symbols.chunks(layout.symbols_per_block()).enumerate() {
+        states.fill(RANS_WORD_L);
+        renorm_words.clear();
+
+        // Going backwards through the steps and the lanes, so that the
+        // decoder reads the words forwards, in the lane order
+        for (j, &symbol) in block_symbols.iter().enumerate().rev() {
+            let symbol = model.symbol(symbol);
+            assert!(
+                symbol.freq() != 0,
+                "cannot encode a symbol with zero frequency"
+            );
+
+            let x = &mut states[j % lanes];
+            if symbol.needs_renorm(*x) {
+                renorm_words.push(*x as u16);
+                *x >>= 16;
+            }
+            *x = symbol.put(*x);
+        }
+        renorm_words.reverse();
+        if renorm_words.len() % 2 != 0 {
+            renorm_words.push(0);
+        }
+
+        words.extend_from_slice(&states);
+        words.extend(
+            renorm_words
+                .chunks_exact(2)
+                .map(|pair| u32::from(pair[0]) | u32::from(pair[1]) << 16),
+        );
+        words[HEADER_LEN + block + 1] = to_offset(words.len());
+    }
+
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+fn to_offset(len: usize) -> u32 {
+    u32::try_from(len).expect("too many words for the GPU layout")
+}
+
+/// CPU reference decoder of the stream produced by [`encode()`].
+///
+/// The decoding follows the steps of a GPU subgroup decoding a block, so it
+/// can be used to check a GPU implementation, or to decode the data where no
+/// GPU is available. The header and block offsets are validated when the
+/// decoder is created, and reading past the end of a block is reported as
+/// an error, but the rANS bitstreams are otherwise trusted.
+#[derive(Debug, Clone)]
+pub struct GpuDecoder<'a> {
+    data: &'a [u8],
+    layout: GpuLayout,
+    len: usize,
+    /// Offsets of the blocks in the stream, in words, plus the end of the
+    /// last one
+    offsets: Vec<usize>,
+}
+
+impl<'a> GpuDecoder<'a> {
+    /// Reads the header and the block offsets of given stream.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the stream is truncated, or
+    /// [`RansError::InvalidHeader`] if the header or the block offsets are
+    /// malformed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::{encode, GpuDecoder, GpuLayout};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::RansError;
+    ///
+    /// let model = EncModel::new(&Model::from_counts(&[1, 1], 8));
+    /// let data = encode(&[0, 1, 1], &model, GpuLayout::default());
+    /// assert!(GpuDecoder::new(&data).is_ok());
+    /// assert_eq!(
+    ///     GpuDecoder::new(&data[..20]).unwrap_err(),
+    ///     RansError::UnexpectedEof
+    /// );
+    /// ```
+    pub fn new(data: &'a [u8]) -> Result<Self, RansError> {
+        let num_words = data.len() / 4;
+        if num_words < HEADER_LEN {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        let lanes = read_word(data, 0);
+        let block_size = read_word(data, 1);
+        let len = read_word(data, 2) as usize;
+        let num_blocks = read_word(data, 3) as usize;
+        if lanes == 0 || block_size == 0 || lanes.checked_mul(block_size).is_none() {
+            return Err(RansError::InvalidHeader);
+        }
+        let layout = GpuLayout::new(lanes, block_size);
+        if num_blocks != layout.num_blocks(len) {
+            return Err(RansError::InvalidHeader);
+        }
+        if num_blocks + 1 > num_words - HEADER_LEN {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        let offsets: Vec<usize> = (0..=num_blocks)
+            .map(|block| read_word(data, HEADER_LEN + block) as usize)
+            .collect();
+        if offsets[0] != HEADER_LEN + num_blocks + 1
+            || offsets
+                .windows(2)
+                .any(|pair| pair[0] + lanes as usize > pair[1])
+        {
+            return Err(RansError::InvalidHeader);
+        }
+        if offsets[num_blocks] > num_words {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        Ok(Self {
+            data,
+            layout,
+            len,
+            offsets,
+        })
+    }
+
+    /// Returns the layout of the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::{encode, GpuDecoder, GpuLayout};
+    /// use rans::model::{EncModel, Model};
+    ///
+    /// let model = EncModel::new(&Model::from_counts(&[1, 1], 8));
+    /// let data = encode(&[0, 1, 1], &model, GpuLayout::new(4, 16));
+    /// assert_eq!(GpuDecoder::new(&data).unwrap().layout(), GpuLayout::new(4, 16));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn layout(&self) -> GpuLayout {
+        self.layout
+    }
+
+    /// Returns the number of symbols in the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::{encode, GpuDecoder, GpuLayout};
+    /// use rans::model::{EncModel, Model};
+    ///
+    /// let model = EncModel::new(&Model::from_counts(&[1, 1], 8));
+    /// let data = encode(&[0, 1, 1], &model, GpuLayout::default());
+    /// assert_eq!(GpuDecoder::new(&data).unwrap().len(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the stream contains no symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::{encode, GpuDecoder, GpuLayout};
+    /// use rans::model::{EncModel, Model};
+    ///
+    /// let model = EncModel::new(&Model::from_counts(&[1, 1], 8));
+    /// let data = encode(&[], &model, GpuLayout::default());
+    /// assert!(GpuDecoder::new(&data).unwrap().is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of blocks in the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::{encode, GpuDecoder, GpuLayout};
+    /// use rans::model::{EncModel, Model};
+    ///
+    /// let model = EncModel::new(&Model::from_counts(&[1, 1], 8));
+    /// let data = encode(&[0; 100], &model, GpuLayout::new(4, 8));
+    /// assert_eq!(GpuDecoder::new(&data).unwrap().num_blocks(), 4);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn num_blocks(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Decodes a single block, appending its symbols to `output`.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the block ends before all its
+    /// symbols are decoded.
+    ///
+    /// # Panics
+    /// Panics if `block` is out of range.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::{encode, GpuDecoder, GpuLayout};
+    /// use rans::model::{DecModel, EncModel, Model};
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// let symbols: Vec<usize> = (0..100).map(|i| i % 3 / 2).collect();
+    /// let data = encode(&symbols, &EncModel::new(&model), GpuLayout::new(4, 8));
+    ///
+    /// let decoder = GpuDecoder::new(&data).unwrap();
+    /// let mut output = Vec::new();
+    /// decoder.decode_block(3, &DecModel::new(&model), &mut output).unwrap();
+    /// assert_eq!(output, symbols[96..]);
+    /// ```
+    pub fn decode_block(
+        &self,
+        block: usize,
+        model: &DecModel<SimdRansDecSymbol>,
+        output: &mut Vec<usize>,
+    ) -> Result<(), RansError> {
+        assert!(block < self.num_blocks(), "block index out of range");
+
+        let lanes = self.layout.lanes as usize;
+        let block_start = block * self.layout.symbols_per_block();
+        let block_len = (self.len - block_start).min(self.layout.symbols_per_block());
+        let mut states: Vec<u32> = (0..lanes)
+            .map(|lane| read_word(self.data, self.offsets[block] + lane))
+            .collect();
+        let words = &self.data[(self.offsets[block] + lanes) * 4..self.offsets[block + 1] * 4];
+
+        let scale_bits = model.scale_bits();
+        let mask = (1 << scale_bits) - 1;
+        let mut pos = 0;
+        output.reserve(block_len);
+        for step_start in (0..block_len).step_by(lanes) {
+            let active_lanes = (block_len - step_start).min(lanes);
+
+            // Every lane decodes its symbol...
+            for x in &mut states[..active_lanes] {
+                let cum_freq = *x & mask;
+                let (index, symbol) = model.lookup(cum_freq);
+                *x = symbol.freq() * (*x >> scale_bits) + cum_freq - symbol.cum_freq();
+                output.push(index);
+            }
+
+            // ...and the lanes needing renormalization read the consecutive
+            // words in the lane order
+            for x in &mut states[..active_lanes] {
+                if *x < RANS_WORD_L {
+                    let word = words.get(pos..pos + 2).ok_or(RansError::UnexpectedEof)?;
+                    *x = *x << 16 | u32::from(u16::from_le_bytes([word[0], word[1]]));
+                    pos += 2;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes all the symbols in the stream.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if any of the blocks ends before
+    /// all its symbols are decoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::gpu::{encode, GpuDecoder, GpuLayout};
+    /// use rans::model::{DecModel, EncModel, Model};
+    ///
+    /// let model = Model::from_counts(&[3, 1], 8);
+    /// let data = encode(&[0, 1, 0, 0], &EncModel::new(&model), GpuLayout::default());
+    ///
+    /// let decoder = GpuDecoder::new(&data).unwrap();
+    /// assert_eq!(decoder.decode(&DecModel::new(&model)).unwrap(), [0, 1, 0, 0]);
+    /// ```
+    pub fn decode(&self, model: &DecModel<SimdRansDecSymbol>) -> Result<Vec<usize>, RansError> {
+        let mut output = Vec::new();
+        for block in 0..self.num_blocks() {
+            self.decode_block(block, model, &mut output)?;
+        }
+        Ok(output)
+    }
+}
+
+fn read_word(data: &[u8], index: usize) -> u32 {
+    let bytes = &data[index * 4..index * 4 + 4];
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::gpu::{encode, GpuDecoder, GpuLayout};
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::RansError;
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let model = Model::from_counts(&[1000, 300, 100, 30, 10, 3, 1], 14);
+        let enc_model = EncModel::new(&model);
+        let dec_model = DecModel::new(&model);
+
+        for layout in [
+            GpuLayout::new(1, 1),
+            GpuLayout::new(3, 5),
+            GpuLayout::new(32, 64),
+            GpuLayout::new(64, 1000),
+        ] {
+            for len in [0, 1, 31, 32, 33, 1000, 10000] {
+                let symbols: Vec<usize> = (0..len)
+                    .map(|_| model.symbol_for(rng.gen_range(0..1 << 14)))
+                    .collect();
+
+                let data = encode(&symbols, &enc_model, layout);
+                assert_eq!(data.len() % 4, 0);
+
+                let decoder = GpuDecoder::new(&data).unwrap();
+                assert_eq!(decoder.layout(), layout);
+                assert_eq!(decoder.len(), len);
+                assert_eq!(decoder.decode(&dec_model).unwrap(), symbols);
+            }
+        }
+    }
+
+    #[test]
+    fn test_blocks_are_independent() {
+        let model = Model::from_counts(&[5, 3, 1], 10);
+        let symbols: Vec<usize> = (0..1000).map(|i| i * 7 % 9 / 4).collect();
+        let layout = GpuLayout::new(8, 16);
+        let data = encode(&symbols, &EncModel::new(&model), layout);
+
+        let decoder = GpuDecoder::new(&data).unwrap();
+        let dec_model = DecModel::new(&model);
+        for block in (0..decoder.num_blocks()).rev() {
+            let mut output = Vec::new();
+            decoder
+                .decode_block(block, &dec_model, &mut output)
+                .unwrap();
+
+            let start = block * layout.symbols_per_block();
+            let end = symbols.len().min(start + layout.symbols_per_block());
+            assert_eq!(output, symbols[start..end]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_stream() {
+        let model = Model::from_counts(&[1, 2, 3], 12);
+        let symbols = vec![2; 1000];
+        let data = encode(&symbols, &EncModel::new(&model), GpuLayout::new(4, 64));
+
+        for len in [0, 15, 20, data.len() - 4] {
+            assert_eq!(
+                GpuDecoder::new(&data[..len]).unwrap_err(),
+                RansError::UnexpectedEof
+            );
+        }
+
+        let mut invalid = data.clone();
+        invalid[0..4].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(
+            GpuDecoder::new(&invalid).unwrap_err(),
+            RansError::InvalidHeader
+        );
+
+        let mut invalid = data.clone();
+        invalid[12..16].copy_from_slice(&3u32.to_le_bytes());
+        assert_eq!(
+            GpuDecoder::new(&invalid).unwrap_err(),
+            RansError::InvalidHeader
+        );
+
+        // The first block ends right after the lane states
+        let mut invalid = data.clone();
+        let first = u32::from_le_bytes([data[16], data[17], data[18], data[19]]);
+        invalid[20..24].copy_from_slice(&(first + 4).to_le_bytes());
+        let decoder = GpuDecoder::new(&invalid).unwrap();
+        assert_eq!(
+            decoder.decode(&DecModel::new(&model)).unwrap_err(),
+            RansError::UnexpectedEof
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::model::DecModel;
+use crate::simple::{decode_symbol_checked, read_model};
+use crate::{varint, RansError};
+
+/// Default limit on the length of the output of [`decompress()`].
+pub const DEFAULT_MAX_OUTPUT_LEN: usize = 1 << 28;
+/// Limit on the initial capacity of the output buffer, so that a large
+/// declared length doesn't cause a large allocation up front.
+const MAX_INITIAL_CAPACITY: usize = 1 << 20;
+
+/// Decompresses data produced by
+/// [`simple::compress()`](crate::simple::compress), treating it as untrusted.
+///
+/// Unlike [`simple::decompress()`](crate::simple::decompress), this validates
+/// the rANS bitstream as well as the header, so that no input can make it
+/// read outside of `data`, panic, or allocate more than
+/// [`DEFAULT_MAX_OUTPUT_LEN`] bytes for the output. Use
+/// [`decompress_with_limit()`] to set a different limit.
+///
+/// # Errors
+/// Returns [`RansError::BufferOverflow`] if the declared length exceeds the
+/// limit, [`RansError::UnexpectedEof`] if the input is truncated,
+/// [`RansError::InvalidModel`] if the frequency table doesn't sum up to the
+/// expected total, [`RansError::InputExhausted`] if the bitstream ends before
+/// all the symbols are decoded, or [`RansError::InvalidHeader`] if the rest
+/// of the input is malformed or doesn't match the declared length.
+///
+/// # Examples
+/// ```
+/// use rans::hardened::decompress;
+/// use rans::simple::compress;
+/// use rans::RansError;
+///
+/// let compressed = compress(b"hello, world");
+/// assert_eq!(decompress(&compressed).unwrap(), b"hello, world");
+///
+/// let mut corrupted = compressed.clone();
+/// corrupted.truncate(corrupted.len() - 1);
+/// assert!(decompress(&corrupted).is_err());
+/// ```
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, RansError> {
+    decompress_with_limit(data, DEFAULT_MAX_OUTPUT_LEN)
+}
+
+/// Decompresses data produced by
+/// [`simple::compress()`](crate::simple::compress), treating it as untrusted
+/// and rejecting any input that declares more than `max_len` bytes of output.
+/// See [`decompress()`].
+///
+/// # Errors
+/// Same as in the case of [`decompress()`].
+///
+/// # Examples
+/// ```
+/// use rans::hardened::decompress_with_limit;
+/// use rans::simple::compress;
+/// use rans::RansError;
+///
+/// let compressed = compress(&[0; 1000]);
+/// assert_eq!(decompress_with_limit(&compressed, 1000).unwrap(), [0; 1000]);
+/// assert_eq!(
+///     decompress_with_limit(&compressed, 999),
+///     Err(RansError::BufferOverflow)
+/// );
+/// ```
+pub fn decompress_with_limit(data: &[u8], max_len: usize) -> Result<Vec<u8>, RansError> {
+    let mut pos = 0;
+    let len = varint::read_u64(data, &mut pos)?;
+    if len > max_len as u64 {
+        return Err(RansError::BufferOverflow);
+    }
+    let len = len as usize;
+    if len == 0 {
+        return if pos == data.len() {
+            Ok(Vec::new())
+        } else {
+            Err(RansError::InvalidHeader)
+        };
+    }
+
+    let model = read_model(data, &mut pos)?;
+    let payload = &data[pos..];
+    let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    let mut decoder = ByteRansDecoder::try_new(payload).map_err(|_| RansError::UnexpectedEof)?;
+    if !decoder.is_state_valid(0) {
+        return Err(RansError::InvalidHeader);
+    }
+
+    // The state stays valid after every step, so renormalizing can't read
+    // past the end of the payload once it's checked to be long enough
+    let mut output = Vec::with_capacity(len.min(MAX_INITIAL_CAPACITY));
+    for _ in 0..len {
+        output.push(decode_symbol_checked(&mut decoder, 0, &dec_model)? as u8);
+    }
+    if !decoder.is_exhausted() {
+        return Err(RansError::InvalidHeader);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::hardened::{decompress, decompress_with_limit};
+    use crate::simple::compress;
+    use crate::{varint, RansError};
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        for len in [0, 1, 2, 100, 10000] {
+            let data: Vec<u8> = (0..len).map(|_| rng.gen_range(0..16) * 3).collect();
+            assert_eq!(decompress(&compress(&data)).unwrap(), data);
+        }
+        assert_eq!(decompress(&compress(&[7; 5000])).unwrap(), [7; 5000]);
+    }
+
+    #[test]
+    fn test_declared_len_limit() {
+        let mut data = Vec::new();
+        varint::write_u64(&mut data, u64::MAX);
+        assert_eq!(decompress(&data), Err(RansError::BufferOverflow));
+
+        let compressed = compress(b"abc");
+        assert_eq!(decompress_with_limit(&compressed, 3).unwrap(), b"abc");
+        assert_eq!(
+            decompress_with_limit(&compressed, 2),
+            Err(RansError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn test_declared_len_mismatch() {
+        let data: Vec<u8> = (0..1000_u32).map(|x| (x * x % 13) as u8).collect();
+        let mut compressed = compress(&data);
+
+        // Too many symbols run out of data, too few leave some unread
+        compressed[..2].copy_from_slice(&[0xe9, 0x07]);
+        assert_eq!(decompress(&compressed), Err(RansError::InputExhausted));
+        compressed[..2].copy_from_slice(&[0xe7, 0x07]);
+        assert_eq!(decompress(&compressed), Err(RansError::InvalidHeader));
+        // Trailing data after an empty input
+        assert_eq!(decompress(&[0, 0]), Err(RansError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_invalid_initial_state() {
+        let mut compressed = compress(b"aab");
+        let state_pos = compressed.len() - 4;
+        compressed[state_pos..].copy_from_slice(&[0, 0, 0, 0]);
+
+        assert_eq!(decompress(&compressed), Err(RansError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_random_input() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        for _ in 0..10000 {
+            let len = rng.gen_range(0..64);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let _ = decompress(&data);
+        }
+    }
+
+    #[test]
+    fn test_mutated_input() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(2);
+        let data: Vec<u8> = (0..2000_u32).map(|x| (x * x % 37) as u8).collect();
+        let compressed = compress(&data);
+
+        for _ in 0..2000 {
+            let mut mutated = compressed.clone();
+            for _ in 0..rng.gen_range(1..4) {
+                let pos = rng.gen_range(0..mutated.len());
+                mutated[pos] = rng.gen();
+            }
+            mutated.truncate(rng.gen_range(mutated.len() / 2..=mutated.len()));
+            let _ = decompress(&mutated);
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use crate::model::DecModel;
+use crate::{RansDecoderMulti, RansEncoderMulti, RansError};
+
+/// Adapter over a multi-stream encoder that puts consecutive symbols into
+/// consecutive channels, so that the caller doesn't have to keep track of
+/// the channel indices.
+///
+/// As with any rANS encoder, the symbols have to be put in the reverse order
+/// of decoding. The data has to be decoded with an [`InterleavedDecoder`],
+/// which rotates through the channels in the matching order, regardless of
+/// the number of symbols encoded.
+///
+/// # Examples
+/// ```
+/// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+/// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+/// use rans::interleaved::{InterleavedDecoder, InterleavedEncoder};
+/// use rans::model::{DecModel, EncModel, Model};
+///
+/// let model = Model::from_counts(&[1, 2, 5], 4);
+/// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+/// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+/// let symbols = [0, 2, 2, 1, 2];
+///
+/// let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+/// for &symbol in symbols.iter().rev() {
+///     encoder.put(enc_model.symbol(symbol));
+/// }
+/// encoder.flush();
+///
+/// let mut decoder = InterleavedDecoder::new(ByteRansDecoderMulti::<2>::new(encoder.data()));
+/// for &symbol in &symbols {
+///     assert_eq!(decoder.decode_symbol(&dec_model), symbol);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InterleavedEncoder<E, const N: usize> {
+    encoder: E,
+    /// Channel the next symbol is put into
+    channel: usize,
+}
+
+impl<E: RansEncoderMulti<N>, const N: usize> InterleavedEncoder<E, N> {
+    /// Creates a new `InterleavedEncoder` instance wrapping given encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::interleaved::InterleavedEncoder;
+    ///
+    /// let encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<4>::new(1024));
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(encoder: E) -> Self {
+        Self {
+            encoder,
+            channel: 0,
+        }
+    }
+
+    /// Puts a symbol into the current channel and moves on to the next one.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::interleaved::InterleavedEncoder;
+    /// use rans::RansEncSymbol;
+    ///
+    /// let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// encoder.put(&ByteRansEncSymbol::new(0, 2, 2));
+    /// encoder.put(&ByteRansEncSymbol::new(2, 2, 2));
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [2, 0, 0, 1, 0, 0, 0, 1]);
+    /// ```
+    #[inline]
+    pub fn put(&mut self, symbol: &E::Symbol) {
+        self.encoder.put_at(self.channel, symbol);
+        self.next_channel();
+    }
+
+    /// Puts a symbol into the current channel and moves on to the next one,
+    /// checking that there is enough space left in the buffer. The channel
+    /// is not changed if an error occurs.
+    ///
+    /// # Errors
+    /// Returns [`RansError::BufferOverflow`] if the symbol does not fit in
+    /// the buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::interleaved::InterleavedEncoder;
+    /// use rans::RansEncSymbol;
+    ///
+    /// let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// assert_eq!(encoder.try_put(&ByteRansEncSymbol::new(0, 2, 2)), Ok(()));
+    /// ```
+    pub fn try_put(&mut self, symbol: &E::Symbol) -> Result<(), RansError> {
+        self.encoder.try_put_at(self.channel, symbol)?;
+        self.next_channel();
+        Ok(())
+    }
+
+    /// Puts `num_bits` raw bits of `value` into the current channel and
+    /// moves on to the next one. See [`RansEncoderMulti::put_bits_at()`].
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::interleaved::{InterleavedDecoder, InterleavedEncoder};
+    ///
+    /// let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// encoder.put_bits(0xbeef, 16);
+    /// encoder.put_bits(0b101, 3);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = InterleavedDecoder::new(ByteRansDecoderMulti::<2>::new(encoder.data()));
+    /// assert_eq!(decoder.get_bits(3), 0b101);
+    /// assert_eq!(decoder.get_bits(16), 0xbeef);
+    /// ```
+    pub fn put_bits(&mut self, value: u32, num_bits: u32) {
+        self.encoder.put_bits_at(self.channel, value, num_bits);
+        self.next_channel();
+    }
+
+    /// Flushes all the channels of the encoder, in the order expected by
+    /// [`InterleavedDecoder`]. After this, [`Self::data()`] returns the
+    /// complete encoded data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::interleaved::InterleavedEncoder;
+    ///
+    /// let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0, 0, 0, 128, 0]);
+    /// ```
+    pub fn flush(&mut self) {
+        // The channel that got the last symbol is flushed last, so that the
+        // decoder reads its state first and can always start at channel 0
+        for i in 0..N {
+            self.encoder.flush_at((self.channel + i) % N);
+        }
+    }
+
+    /// Flushes all the channels of the encoder, in the order expected by
+    /// [`InterleavedDecoder`], checking that there is enough space left in the
+    /// buffer.
+    ///
+    /// # Errors
+    /// Returns [`RansError::BufferOverflow`] if the data does not fit in the
+    /// buffer. The channels flushed before the error occurred stay flushed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::interleaved::InterleavedEncoder;
+    /// use rans::RansError;
+    ///
+    /// let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(6));
+    /// assert_eq!(encoder.try_flush(), Err(RansError::BufferOverflow));
+    /// ```
+    pub fn try_flush(&mut self) -> Result<(), RansError> {
+        for i in 0..N {
+            self.encoder.try_flush_at((self.channel + i) % N)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the wrapped encoder's internal buffer content.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::interleaved::InterleavedEncoder;
+    ///
+    /// let encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        self.encoder.data()
+    }
+
+    /// Returns the channel the next symbol is put into.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::interleaved::InterleavedEncoder;
+    /// use rans::RansEncSymbol;
+    ///
+    /// let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// encoder.put(&ByteRansEncSymbol::new(0, 2, 2));
+    /// assert_eq!(encoder.channel(), 1);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn channel(&self) -> usize {
+        self.channel
+    }
+
+    /// Returns a reference to the wrapped encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::interleaved::InterleavedEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// assert!(encoder.encoder().is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn encoder(&self) -> &E {
+        &self.encoder
+    }
+
+    /// Consumes this adapter, returning the wrapped encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::interleaved::InterleavedEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// encoder.flush();
+    /// assert_eq!(encoder.into_inner().len(), 8);
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> E {
+        self.encoder
+    }
+
+    #[inline]
+    fn next_channel(&mut self) {
+        self.channel = (self.channel + 1) % N;
+    }
+}
+
+/// Adapter over a multi-stream decoder that reads consecutive symbols from
+/// consecutive channels, mirroring the order of an [`InterleavedEncoder`].
+///
+/// See [`InterleavedEncoder`] for an example.
+#[derive(Debug)]
+pub struct InterleavedDecoder<D, const N: usize> {
+    decoder: D,
+    /// Channel the next symbol is read from
+    channel: usize,
+}
+
+impl<D: RansDecoderMulti<N>, const N: usize> InterleavedDecoder<D, N> {
+    /// Creates a new `InterleavedDecoder` instance wrapping given decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::interleaved::InterleavedDecoder;
+    ///
+    /// let mut decoder =
+    ///     InterleavedDecoder::new(ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]));
+    /// assert_eq!(decoder.get(2), 2);
+    /// ```
+    #[must_use]
+    pub fn new(decoder: D) -> Self {
+        Self {
+            decoder,
+            channel: 0,
+        }
+    }
+
+    /// Gets the cumulative frequency for the current symbol at the current
+    /// channel. This does not advance the data position nor change the
+    /// channel; for that, use [`Self::advance()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::interleaved::InterleavedDecoder;
+    ///
+    /// let mut decoder =
+    ///     InterleavedDecoder::new(ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]));
+    /// assert_eq!(decoder.get(2), 2);
+    /// assert_eq!(decoder.get(2), 2);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn get(&mut self, scale_bits: u32) -> u32 {
+        self.decoder.get_at(self.channel, scale_bits)
+    }
+
+    /// Advances the data position of the current channel after reading a
+    /// symbol and moves on to the next channel.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::interleaved::InterleavedDecoder;
+    /// use rans::RansDecSymbol;
+    ///
+    /// let mut decoder =
+    ///     InterleavedDecoder::new(ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]));
+    /// assert_eq!(decoder.get(2), 2);
+    /// decoder.advance(&ByteRansDecSymbol::new(2, 2), 2);
+    /// assert_eq!(decoder.get(2), 0);
+    /// decoder.advance(&ByteRansDecSymbol::new(0, 2), 2);
+    /// ```
+    #[inline]
+    pub fn advance(&mut self, symbol: &D::Symbol, scale_bits: u32) {
+        self.decoder.advance_at(self.channel, symbol, scale_bits);
+        self.next_channel();
+    }
+
+    /// Reads `num_bits` raw bits put with [`InterleavedEncoder::put_bits()`]
+    /// from the current channel and moves on to the next one.
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is larger than 32.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::interleaved::{InterleavedDecoder, InterleavedEncoder};
+    ///
+    /// let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<3>::new(1024));
+    /// encoder.put_bits(7, 4);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = InterleavedDecoder::new(ByteRansDecoderMulti::<3>::new(encoder.data()));
+    /// assert_eq!(decoder.get_bits(4), 7);
+    /// ```
+    pub fn get_bits(&mut self, num_bits: u32) -> u32 {
+        let value = self.decoder.get_bits_at(self.channel, num_bits);
+        self.next_channel();
+        value
+    }
+
+    /// Decodes a single symbol at the current channel using given model,
+    /// moves on to the next channel and returns the symbol's index.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::interleaved::InterleavedDecoder;
+    /// use rans::model::{DecModel, Model};
+    ///
+    /// let model = Model::from_freqs(vec![2, 2], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder =
+    ///     InterleavedDecoder::new(ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]));
+    /// assert_eq!(decoder.decode_symbol(&dec_model), 1);
+    /// assert_eq!(decoder.decode_symbol(&dec_model), 0);
+    /// ```
+    pub fn decode_symbol(&mut self, model: &DecModel<D::Symbol>) -> usize {
+        let index = self.decoder.decode_symbol_at(self.channel, model);
+        self.next_channel();
+        index
+    }
+
+    /// Returns the channel the next symbol is read from.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::interleaved::InterleavedDecoder;
+    /// use rans::RansDecSymbol;
+    ///
+    /// let mut decoder =
+    ///     InterleavedDecoder::new(ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]));
+    /// decoder.advance(&ByteRansDecSymbol::new(2, 2), 2);
+    /// assert_eq!(decoder.channel(), 1);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn channel(&self) -> usize {
+        self.channel
+    }
+
+    /// Returns a mutable reference to the wrapped decoder.
+    ///
+    /// Note that using the decoder directly does not change the channel the
+    /// adapter reads the next symbol from.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::interleaved::InterleavedDecoder;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder =
+    ///     InterleavedDecoder::new(ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]));
+    /// assert_eq!(decoder.decoder_mut().get_at(1, 2), 0);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Consumes this adapter, returning the wrapped decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecoderMulti;
+    /// use rans::interleaved::InterleavedDecoder;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let decoder =
+    ///     InterleavedDecoder::new(ByteRansDecoderMulti::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]));
+    /// assert_eq!(decoder.into_inner().get_at(0, 2), 2);
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+
+    #[inline]
+    fn next_channel(&mut self) {
+        self.channel = (self.channel + 1) % N;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::b64_decoder::{B64RansDecSymbol, B64RansDecoderMulti};
+    use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoderMulti};
+    use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    use crate::interleaved::{InterleavedDecoder, InterleavedEncoder};
+    use crate::model::{DecModel, EncModel, Model};
+
+    fn random_symbols(len: usize) -> Vec<usize> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(len as u64);
+        (0..len).map(|_| rng.gen_range(0..6)).collect()
+    }
+
+    fn test_round_trip_byte<const N: usize>() {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100], 10);
+        let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+        let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+
+        // Cover all the possible numbers of symbols in the last round
+        for len in 0..2 * N + 1 {
+            let symbols = random_symbols(len * 37);
+
+            let mut encoder = InterleavedEncoder::new(ByteRansEncoderMulti::<N>::new(1 << 16));
+            for &symbol in symbols.iter().rev() {
+                encoder.put(enc_model.symbol(symbol));
+            }
+            encoder.flush();
+
+            let mut decoder =
+                InterleavedDecoder::new(ByteRansDecoderMulti::<N>::new(encoder.data()));
+            for &symbol in &symbols {
+                assert_eq!(decoder.decode_symbol(&dec_model), symbol);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        test_round_trip_byte::<1>();
+        test_round_trip_byte::<2>();
+        test_round_trip_byte::<3>();
+        test_round_trip_byte::<4>();
+    }
+
+    #[test]
+    fn test_round_trip_b64() {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100], 20);
+        let enc_model = EncModel::<B64RansEncSymbol>::new(&model);
+        let dec_model = DecModel::<B64RansDecSymbol>::new(&model);
+        let symbols = random_symbols(1001);
+
+        let mut encoder = InterleavedEncoder::new(B64RansEncoderMulti::<3>::new(1 << 16));
+        for &symbol in symbols.iter().rev() {
+            encoder.put(enc_model.symbol(symbol));
+        }
+        encoder.flush();
+
+        let mut decoder = InterleavedDecoder::new(B64RansDecoderMulti::<3>::new(encoder.data()));
+        for &symbol in &symbols {
+            assert_eq!(decoder.decode_symbol(&dec_model), symbol);
+        }
+    }
+}
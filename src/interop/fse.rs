@@ -0,0 +1,321 @@
+use crate::model::Model;
+use crate::RansError;
+
+/// Smallest table log supported by the format.
+pub const MIN_TABLE_LOG: u32 = 5;
+/// Largest table log supported by the format.
+pub const MAX_TABLE_LOG: u32 = 15;
+/// Maximum number of symbols supported by the format.
+pub const MAX_SYMBOLS: usize = 256;
+
+/// Appends the frequency table of `model` to `output` in the FSE
+/// normalized-count format (the one used by Zstandard for its FSE table
+/// descriptions). The model's scale bits are the table log.
+///
+/// Trailing zero-frequency symbols are not stored, so the model read back
+/// with [`read_ncount()`] may have a shorter alphabet.
+///
+/// # Panics
+/// Panics if the model's scale bits are not between [`MIN_TABLE_LOG`] and
+/// [`MAX_TABLE_LOG`], or if the last symbol with non-zero frequency is not
+/// smaller than [`MAX_SYMBOLS`].
+///
+/// # Examples
+/// ```
+/// use rans::interop::fse::write_ncount;
+/// use rans::model::Model;
+///
+/// let mut output = Vec::new();
+/// write_ncount(&mut output, &Model::from_freqs(vec![16, 16], 5));
+/// assert_eq!(output, [0x10, 0x3f]);
+/// ```
+pub fn write_ncount(output: &mut Vec<u8>, model: &Model) {
+    let table_log = model.scale_bits();
+    assert!(
+        (MIN_TABLE_LOG..=MAX_TABLE_LOG).contains(&table_log),
+        "scale_bits must be between {MIN_TABLE_LOG} and {MAX_TABLE_LOG}"
+    );
+    let freqs = model.freqs();
+    let num_symbols = freqs
+        .iter()
+        .rposition(|&freq| freq > 0)
+        .map_or(0, |i| i + 1);
+    assert!(num_symbols <= MAX_SYMBOLS, "too many symbols");
+
+    let mut writer = BitWriter::new(output);
+    writer.write(table_log - MIN_TABLE_LOG, 4);
+
+    // The values are stored with one extra unit of accuracy, so that
+    // "less than 1" probabilities (not produced by `Model`) can be coded as 0
+    let mut remaining = (1 << table_log) + 1;
+    let mut threshold = 1 << table_log;
+    let mut num_bits = table_log + 1;
+    let mut symbol = 0;
+    let mut previous_zero = false;
+    while symbol < num_symbols && remaining > 1 {
+        if previous_zero {
+            // Run of zero-frequency symbols, in 2-bit units of up to 3
+            let mut start = symbol;
+            while freqs[symbol] == 0 {
+                symbol += 1;
+            }
+            while symbol >= start + 3 {
+                writer.write(3, 2);
+                start += 3;
+            }
+            writer.write((symbol - start) as u32, 2);
+        }
+
+        let freq = freqs[symbol];
+        symbol += 1;
+        let max = 2 * threshold - 1 - remaining;
+        remaining -= freq;
+        let mut value = freq + 1;
+        if value >= threshold {
+            value += max;
+        }
+        // Small values only take `num_bits - 1` bits
+        writer.write(value, if value < max { num_bits - 1 } else { num_bits });
+        previous_zero = value == 1;
+
+        while remaining < threshold {
+            num_bits -= 1;
+            threshold >>= 1;
+        }
+    }
+    writer.finish();
+}
+
+/// Reads a frequency table stored in the FSE normalized-count format from
+/// the beginning of `data`. Returns the model and the number of bytes read.
+///
+/// Symbols with a "less than 1" probability, which Zstandard uses for rare
+/// symbols, get a frequency of 1.
+///
+/// # Errors
+/// Returns [`RansError::InvalidHeader`] if the table log is too large,
+/// [`RansError::InvalidModel`] if the counts are malformed, or
+/// [`RansError::UnexpectedEof`] if `data` ends before the table does.
+///
+/// # Examples
+/// ```
+/// use rans::interop::fse::read_ncount;
+///
+/// let (model, len) = read_ncount(&[0x10, 0x3f, 0xff]).unwrap();
+/// assert_eq!(model.freqs(), [16, 16]);
+/// assert_eq!(model.scale_bits(), 5);
+/// assert_eq!(len, 2);
+/// ```
+pub fn read_ncount(data: &[u8]) -> Result<(Model, usize), RansError> {
+    if data.is_empty() {
+        return Err(RansError::UnexpectedEof);
+    }
+    let mut reader = BitReader::new(data);
+    let table_log = reader.read(4) + MIN_TABLE_LOG;
+    if table_log > MAX_TABLE_LOG {
+        return Err(RansError::InvalidHeader);
+    }
+
+    let mut freqs = Vec::new();
+    let mut remaining = (1 << table_log) + 1;
+    let mut threshold = 1 << table_log;
+    let mut num_bits = table_log + 1;
+    let mut previous_zero = false;
+    while remaining > 1 {
+        if previous_zero {
+            let mut run = 0;
+            loop {
+                let repeat = reader.read(2);
+                run += repeat as usize;
+                if freqs.len() + run >= MAX_SYMBOLS {
+                    return Err(RansError::InvalidModel);
+                }
+                if repeat != 3 {
+                    break;
+                }
+            }
+            freqs.resize(freqs.len() + run, 0);
+        }
+        if freqs.len() >= MAX_SYMBOLS {
+            return Err(RansError::InvalidModel);
+        }
+
+        let max = 2 * threshold - 1 - remaining;
+        let low = reader.peek(num_bits - 1);
+        let value = if low < max {
+            reader.consume(num_bits - 1);
+            low
+        } else {
+            let value = reader.read(num_bits);
+            if value >= threshold {
+                value - max
+            } else {
+                value
+            }
+        };
+
+        // 0 encodes a "less than 1" probability
+        let freq = value.checked_sub(1).unwrap_or(1);
+        if freq > remaining - 1 {
+            return Err(RansError::InvalidModel);
+        }
+        remaining -= freq;
+        freqs.push(freq);
+        previous_zero = value == 1;
+
+        while remaining < threshold {
+            num_bits -= 1;
+            threshold >>= 1;
+        }
+    }
+
+    let len = reader.bytes_read();
+    if len > data.len() {
+        return Err(RansError::UnexpectedEof);
+    }
+    Ok((Model::from_freqs(freqs, table_log), len))
+}
+
+/// Little-endian, LSB-first bit writer.
+#[derive(Debug)]
+struct BitWriter<'a> {
+    output: &'a mut Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(output: &'a mut Vec<u8>) -> Self {
+        Self {
+            output,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn write(&mut self, value: u32, num_bits: u32) {
+        self.buffer |= u64::from(value) << self.count;
+        self.count += num_bits;
+        while self.count >= 8 {
+            self.output.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(self) {
+        if self.count > 0 {
+            self.output.push(self.buffer as u8);
+        }
+    }
+}
+
+/// Little-endian, LSB-first bit reader. Bits past the end of the data are
+/// read as zeros.
+#[derive(Debug)]
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn peek(&self, num_bits: u32) -> u32 {
+        let mut word = [0; 4];
+        for (i, byte) in word.iter_mut().enumerate() {
+            *byte = self.data.get(self.pos / 8 + i).copied().unwrap_or(0);
+        }
+        let value = u32::from_le_bytes(word) >> (self.pos % 8);
+        value & ((1 << num_bits) - 1)
+    }
+
+    fn consume(&mut self, num_bits: u32) {
+        self.pos += num_bits as usize;
+    }
+
+    fn read(&mut self, num_bits: u32) -> u32 {
+        let value = self.peek(num_bits);
+        self.consume(num_bits);
+        value
+    }
+
+    fn bytes_read(&self) -> usize {
+        (self.pos + 7) / 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::interop::fse::{read_ncount, write_ncount};
+    use crate::model::Model;
+    use crate::RansError;
+
+    #[test]
+    fn test_round_trip_random() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        for scale_bits in 5..=15 {
+            for _ in 0..20 {
+                let len = rng.gen_range(1..=256);
+                let counts: Vec<u32> = (0..len)
+                    .map(|_| {
+                        if rng.gen_bool(0.5) {
+                            0
+                        } else {
+                            rng.gen_range(1..1000)
+                        }
+                    })
+                    .collect();
+                if counts.iter().all(|&count| count == 0)
+                    || counts.iter().filter(|&&count| count > 0).count() > 1 << scale_bits
+                {
+                    continue;
+                }
+                let model = Model::from_counts(&counts, scale_bits);
+
+                let mut output = vec![0xaa];
+                write_ncount(&mut output, &model);
+                let (read, len) = read_ncount(&output[1..]).unwrap();
+
+                assert_eq!(len, output.len() - 1);
+                let num_symbols = read.len();
+                assert_eq!(read.freqs(), &model.freqs()[..num_symbols]);
+                assert!(model.freqs()[num_symbols..].iter().all(|&freq| freq == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_zero_run() {
+        let mut freqs = vec![0; 256];
+        freqs[0] = 16;
+        freqs[255] = 16;
+        let model = Model::from_freqs(freqs, 5);
+
+        let mut output = Vec::new();
+        write_ncount(&mut output, &model);
+        assert_eq!(read_ncount(&output).unwrap().0, model);
+    }
+
+    #[test]
+    fn test_less_than_one_probability() {
+        // Table log 5, counts [-1, 31]
+        let data = [0x00, 0x7e];
+        let (model, len) = read_ncount(&data).unwrap();
+
+        assert_eq!(model.freqs(), [1, 31]);
+        assert_eq!(len, 2);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(read_ncount(&[]), Err(RansError::UnexpectedEof));
+        assert_eq!(read_ncount(&[0x0b]), Err(RansError::InvalidHeader));
+        assert_eq!(read_ncount(&[0x10]), Err(RansError::UnexpectedEof));
+    }
+}
@@ -0,0 +1,5 @@
+/// FSE normalized-count headers, as used by Zstandard.
+pub mod fse;
+/// Known-answer test vectors of the ryg_rans sample programs, to check that
+/// a backend reproduces the reference bitstreams bit-exactly.
+pub mod ryg;
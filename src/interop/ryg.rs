@@ -0,0 +1,468 @@
+use crate::b64_decoder::{B64RansDecSymbol, B64RansDecoderMulti};
+use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoderMulti};
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+use crate::frame::crc32;
+use crate::model::{DecModel, EncModel, Model};
+use crate::{RansDecoderMulti, RansEncoderMulti, RansError};
+
+/// Number of bits of the frequencies used by the ryg_rans sample programs.
+pub const PROB_BITS: u32 = 14;
+
+/// Length and CRC-32 checksum of a piece of data, used to describe
+/// reference bitstreams too large to be embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest {
+    /// Length of the data, in bytes
+    pub len: usize,
+    /// CRC-32 (ISO-HDLC, as used by zlib) checksum of the data
+    pub crc32: u32,
+}
+
+impl Digest {
+    /// Computes the digest of given data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::interop::ryg::Digest;
+    ///
+    /// let digest = Digest::of(b"123456789");
+    /// assert_eq!(digest.len, 9);
+    /// assert_eq!(digest.crc32, 0xcbf4_3926);
+    /// ```
+    #[must_use]
+    pub fn of(data: &[u8]) -> Self {
+        Self {
+            len: data.len(),
+            crc32: crc32(data),
+        }
+    }
+}
+
+/// Bitstreams produced by the four coding loops of the ryg_rans sample
+/// programs (`main.cpp` and `main64.cpp`): a single stream and two
+/// interleaved streams, with the byte-aligned and the 64-bit coder.
+///
+/// The 64-bit coder writes 32-bit words; they are stored in little-endian
+/// byte order, as written by the sample program on x86.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Streams<T> {
+    /// Single byte-aligned stream
+    pub byte: T,
+    /// Two interleaved byte-aligned streams
+    pub byte_interleaved: T,
+    /// Single 64-bit stream
+    pub b64: T,
+    /// Two interleaved 64-bit streams
+    pub b64_interleaved: T,
+}
+
+impl<T> Streams<T> {
+    fn map<U>(self, f: impl Fn(T) -> U) -> Streams<U> {
+        Streams {
+            byte: f(self.byte),
+            byte_interleaved: f(self.byte_interleaved),
+            b64: f(self.b64),
+            b64_interleaved: f(self.b64_interleaved),
+        }
+    }
+
+    fn as_ref(&self) -> Streams<&T> {
+        Streams {
+            byte: &self.byte,
+            byte_interleaved: &self.byte_interleaved,
+            b64: &self.b64,
+            b64_interleaved: &self.b64_interleaved,
+        }
+    }
+}
+
+/// Short excerpt of `book1` from the Calgary corpus (Thomas Hardy's "Far
+/// from the Madding Crowd"), the input of the ryg_rans sample programs.
+pub const SAMPLE: &[u8] = b"When Farmer Oak smiled, the corners of his mouth
+spread till they were within an unimportant distance of
+his ears, his eyes were reduced to chinks, and diverging
+wrinkles appeared round them, extending upon his
+countenance like the rays in a rudimentary sketch of
+the rising sun.
+";
+
+/// Reference bitstreams of [`SAMPLE`], produced by the coding loops of the
+/// ryg_rans sample programs.
+pub const SAMPLE_STREAMS: Streams<&[u8]> = Streams {
+    byte: &[
+        0x61, 0x0c, 0x7f, 0x4a, 0x31, 0x4d, 0xb2, 0x9d, 0x54, 0x2f, 0x6a, 0xe3, 0xde, 0x12, 0xf1,
+        0xba, 0x9a, 0x21, 0x44, 0x89, 0x84, 0x11, 0x7b, 0x49, 0x6f, 0x27, 0x99, 0x7a, 0x3c, 0x43,
+        0xc0, 0x96, 0xe2, 0x75, 0x31, 0x58, 0x89, 0xe6, 0x98, 0xc0, 0x17, 0x81, 0xe1, 0xcd, 0x3f,
+        0x2a, 0xfb, 0x6b, 0x47, 0xd5, 0xae, 0x87, 0x96, 0x86, 0xbf, 0x47, 0x2f, 0x32, 0x86, 0xaf,
+        0x7e, 0xd6, 0x1d, 0xa5, 0xe3, 0x6d, 0x1c, 0x43, 0x95, 0x6e, 0xeb, 0x50, 0x96, 0xfa, 0x31,
+        0x66, 0xa9, 0x27, 0x6c, 0xd5, 0x3c, 0x28, 0x9b, 0x63, 0xb9, 0x60, 0x52, 0x43, 0x34, 0xc6,
+        0x60, 0xf5, 0x7d, 0x48, 0x39, 0x73, 0xc3, 0xb9, 0x55, 0xf5, 0xdb, 0xc4, 0x1a, 0xf7, 0x55,
+        0xb9, 0x61, 0xe4, 0x77, 0x08, 0x6b, 0x68, 0x0d, 0x94, 0x51, 0x5d, 0x0a, 0x4f, 0x3c, 0xc8,
+        0x77, 0x7e, 0xfc, 0x20, 0x74, 0xe7, 0xca, 0x6e, 0xbe, 0x80, 0x73, 0x1b, 0x64, 0x3a, 0x5f,
+        0xc7, 0xf1, 0x7f, 0x89, 0xa2, 0x31, 0x16, 0x02, 0xb7, 0x69, 0xc8, 0xd4, 0x00, 0x98, 0x28,
+        0xba, 0x3b,
+    ],
+    byte_interleaved: &[
+        0x65, 0xcc, 0x42, 0x49, 0x2f, 0x5e, 0x82, 0x00, 0xac, 0x93, 0xb3, 0x06, 0xf2, 0xc2, 0x51,
+        0x26, 0x29, 0x46, 0xde, 0xf9, 0xc9, 0xbe, 0x0e, 0x00, 0xff, 0x77, 0x58, 0x87, 0x07, 0xaa,
+        0xab, 0x44, 0x93, 0x8a, 0xd6, 0xc1, 0x4e, 0x85, 0x85, 0x6e, 0x75, 0x80, 0xd9, 0x36, 0x8b,
+        0x9e, 0x17, 0x90, 0x19, 0xef, 0x64, 0x91, 0xa2, 0x89, 0xfa, 0x7d, 0x9a, 0xa0, 0x77, 0x80,
+        0x61, 0x1e, 0x2f, 0x44, 0xbf, 0xb0, 0x4e, 0x92, 0x1e, 0xf3, 0xc6, 0xc0, 0xe6, 0x7b, 0x88,
+        0x97, 0x3a, 0xd8, 0x02, 0xa4, 0x87, 0x33, 0xf4, 0x21, 0x56, 0x17, 0x83, 0x45, 0xb8, 0x53,
+        0x3d, 0x09, 0x32, 0x49, 0xf4, 0x53, 0x1e, 0x99, 0x10, 0x1a, 0xad, 0x71, 0x91, 0x45, 0x1f,
+        0xa9, 0x47, 0x02, 0x7f, 0x43, 0xec, 0x6d, 0xcb, 0xe0, 0x14, 0x4a, 0xf9, 0x3b, 0x10, 0xb0,
+        0x2f, 0xc4, 0x58, 0xa4, 0xe6, 0x3b, 0x5a, 0xfd, 0x73, 0x24, 0x72, 0x23, 0xc6, 0xa9, 0xf8,
+        0xd7, 0x28, 0xc8, 0x1a, 0x91, 0x62, 0x90, 0x99, 0xb9, 0xc2, 0xdf, 0x2b, 0x66, 0x1a, 0x20,
+        0xa5, 0xc5, 0xe6, 0x76, 0x3b, 0x00,
+    ],
+    b64: &[
+        0x83, 0x4c, 0x76, 0x9e, 0x4a, 0x00, 0x00, 0x00, 0x49, 0x1f, 0x59, 0x23, 0x08, 0xda, 0xbf,
+        0x1a, 0x46, 0xc9, 0x1b, 0xc8, 0x62, 0x0a, 0xf9, 0x51, 0xe0, 0xf0, 0x1f, 0xf8, 0x4f, 0xc3,
+        0x03, 0x8c, 0xc8, 0xba, 0xcf, 0x22, 0xb2, 0x0d, 0x6f, 0xab, 0x0c, 0x44, 0x5e, 0x85, 0xca,
+        0xb1, 0xfc, 0x03, 0x01, 0xad, 0x4a, 0xaf, 0x51, 0xef, 0x0b, 0xe6, 0x1c, 0x49, 0xb8, 0x7c,
+        0x13, 0x97, 0xdf, 0xdb, 0xa5, 0x37, 0x42, 0x40, 0x1b, 0x8a, 0xf8, 0x93, 0xb0, 0x08, 0x2d,
+        0xd8, 0x5d, 0x7d, 0x0f, 0x92, 0x4e, 0x84, 0x5b, 0x96, 0xe2, 0x8a, 0x9d, 0x85, 0xc0, 0xfd,
+        0xd4, 0x9f, 0xbd, 0x40, 0x6c, 0xc5, 0x09, 0xe6, 0xf7, 0xd3, 0xf0, 0x13, 0x10, 0xbb, 0xec,
+        0xec, 0x69, 0xc0, 0xee, 0x4a, 0x3e, 0x9c, 0x39, 0xd3, 0xb4, 0x06, 0x61, 0x6e, 0x01, 0xd8,
+        0x46, 0xee, 0x4b, 0x42, 0xa8, 0x10, 0x8e, 0x35, 0x5d, 0x82, 0xbf, 0xf4, 0x2a, 0x36, 0xb4,
+        0xfc, 0x7d, 0xd3, 0xa4, 0xae, 0xb1, 0x7f, 0xb1, 0xed, 0x8b, 0x9e, 0x81, 0x36, 0xc7, 0x59,
+        0x1b, 0x5d, 0xfa, 0x83, 0xd5, 0x34,
+    ],
+    b64_interleaved: &[
+        0x69, 0x0c, 0xe6, 0x30, 0x99, 0x20, 0x7a, 0x49, 0x7e, 0x1f, 0xbb, 0xfc, 0x81, 0x00, 0x00,
+        0x00, 0xe9, 0xb0, 0x63, 0x71, 0x70, 0x64, 0xfa, 0x83, 0xc6, 0x92, 0xb1, 0x6c, 0xe5, 0x96,
+        0x74, 0x1f, 0x18, 0xdb, 0xdc, 0xba, 0x1c, 0x2d, 0xad, 0x9b, 0x3c, 0x37, 0xdc, 0xe1, 0x34,
+        0xe6, 0x70, 0x88, 0xe6, 0x82, 0x6c, 0x44, 0xc2, 0x60, 0x07, 0x0b, 0xd0, 0x9f, 0x65, 0x56,
+        0xfb, 0xf9, 0x83, 0xcd, 0xe3, 0x8c, 0xf4, 0x22, 0xee, 0x00, 0x33, 0x7e, 0x81, 0xb0, 0xc0,
+        0xf7, 0x21, 0x76, 0x85, 0x81, 0x4c, 0x34, 0xbe, 0x0e, 0xbe, 0x85, 0x35, 0x2b, 0x37, 0xaa,
+        0xc7, 0x0e, 0xff, 0x52, 0xab, 0xa2, 0xc5, 0x00, 0x5b, 0xb0, 0xca, 0x7e, 0x51, 0x0b, 0x43,
+        0xcf, 0x1e, 0x76, 0x52, 0xf2, 0xd5, 0x09, 0xd8, 0x42, 0x97, 0x8a, 0x5f, 0x59, 0x4f, 0x12,
+        0x93, 0xab, 0xc1, 0x26, 0x2b, 0x4a, 0x7d, 0xff, 0xc5, 0x2a, 0x80, 0xee, 0xa9, 0xc8, 0x0b,
+        0x67, 0x86, 0xb6, 0x7a, 0xe7, 0x06, 0xac, 0x67, 0xc6, 0x27, 0x7f, 0xf5, 0x24, 0xdc, 0xc5,
+        0x4c, 0x57, 0x50, 0x16, 0xfc, 0xd3, 0x4c, 0x44, 0x9b, 0xf7,
+    ],
+};
+
+/// Digest of `book1` from the Calgary corpus.
+pub const BOOK1: Digest = Digest {
+    len: 768_771,
+    crc32: 0x24e1_9972,
+};
+
+/// Digests of the reference bitstreams of `book1`, produced by the ryg_rans
+/// sample programs.
+pub const BOOK1_STREAMS: Streams<Digest> = Streams {
+    byte: Digest {
+        len: 435_113,
+        crc32: 0x4185_f246,
+    },
+    byte_interleaved: Digest {
+        len: 435_117,
+        crc32: 0x017c_92c4,
+    },
+    b64: Digest {
+        len: 435_116,
+        crc32: 0xee05_d9e8,
+    },
+    b64_interleaved: Digest {
+        len: 435_120,
+        crc32: 0x83ff_a63f,
+    },
+};
+
+/// Normalizes byte counts to `1 << scale_bits` exactly like
+/// `SymbolStats::normalize_freqs()` of the ryg_rans sample programs.
+///
+/// The cumulative counts are scaled down, then every symbol whose frequency
+/// dropped to zero steals a slot from the least frequent symbol that has
+/// more than one. This differs from [`Model::from_counts()`], so it's needed
+/// to reproduce the reference bitstreams.
+///
+/// # Panics
+/// Panics if all counts are zero, or if `scale_bits` is smaller than 8 or
+/// larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::interop::ryg::normalize_freqs;
+///
+/// let mut counts = [0; 256];
+/// counts[0] = 1;
+/// counts[1] = 1000;
+/// let model = normalize_freqs(&counts, 8);
+/// assert_eq!(model.freq(0), 1);
+/// assert_eq!(model.freq(1), 255);
+/// ```
+#[must_use]
+pub fn normalize_freqs(counts: &[u32; 256], scale_bits: u32) -> Model {
+    assert!(
+        (8..=31).contains(&scale_bits),
+        "scale_bits must be between 8 and 31"
+    );
+
+    let mut cum_freqs = [0_u64; 257];
+    for (i, &count) in counts.iter().enumerate() {
+        cum_freqs[i + 1] = cum_freqs[i] + u64::from(count);
+    }
+    let cur_total = cum_freqs[256];
+    assert!(
+        cur_total > 0,
+        "at least one symbol must have a non-zero count"
+    );
+
+    // Resample the distribution based on the cumulative counts
+    for cum_freq in &mut cum_freqs[1..] {
+        *cum_freq = ((u128::from(*cum_freq) << scale_bits) / u128::from(cur_total)) as u64;
+    }
+
+    // Give the symbols that got zero frequency a slot of the least frequent
+    // symbol that can spare one
+    for i in 0..256 {
+        if counts[i] == 0 || cum_freqs[i + 1] != cum_freqs[i] {
+            continue;
+        }
+
+        let mut best_freq = u64::MAX;
+        let mut best_steal = None;
+        for j in 0..256 {
+            let freq = cum_freqs[j + 1] - cum_freqs[j];
+            if freq > 1 && freq < best_freq {
+                best_freq = freq;
+                best_steal = Some(j);
+            }
+        }
+
+        let best_steal = best_steal.expect("there must be a symbol to steal from");
+        if best_steal < i {
+            for cum_freq in &mut cum_freqs[best_steal + 1..=i] {
+                *cum_freq -= 1;
+            }
+        } else {
+            for cum_freq in &mut cum_freqs[i + 1..=best_steal] {
+                *cum_freq += 1;
+            }
+        }
+    }
+
+    let freqs = cum_freqs
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]) as u32)
+        .collect();
+    Model::from_freqs(freqs, scale_bits)
+}
+
+/// Encodes `data` with the coding loops of the ryg_rans sample programs,
+/// using the model they build: [`normalize_freqs()`] of the byte counts of
+/// `data`, with [`PROB_BITS`] bits.
+///
+/// # Panics
+/// Panics if `data` is empty.
+///
+/// # Examples
+/// ```
+/// use rans::interop::ryg::{encode, SAMPLE, SAMPLE_STREAMS};
+///
+/// let streams = encode(SAMPLE);
+/// assert_eq!(streams.byte, SAMPLE_STREAMS.byte);
+/// ```
+#[must_use]
+pub fn encode(data: &[u8]) -> Streams<Vec<u8>> {
+    let model = sample_model(data);
+
+    Streams {
+        byte: encode_stream(
+            ByteRansEncoderMulti::<1>::new(data.len() * 2 + 4),
+            &EncModel::<ByteRansEncSymbol>::new(&model),
+            data,
+        ),
+        byte_interleaved: encode_stream(
+            ByteRansEncoderMulti::<2>::new(data.len() * 2 + 8),
+            &EncModel::<ByteRansEncSymbol>::new(&model),
+            data,
+        ),
+        b64: encode_stream(
+            B64RansEncoderMulti::<1>::new_le(data.len() * 4 + 8),
+            &EncModel::<B64RansEncSymbol>::new(&model),
+            data,
+        ),
+        b64_interleaved: encode_stream(
+            B64RansEncoderMulti::<2>::new_le(data.len() * 4 + 16),
+            &EncModel::<B64RansEncSymbol>::new(&model),
+            data,
+        ),
+    }
+}
+
+/// Checks that the current backend reproduces [`SAMPLE_STREAMS`] bit-exactly
+/// and decodes them back to [`SAMPLE`].
+///
+/// # Errors
+/// Returns [`RansError::VerificationFailed`] if any of the bitstreams differs
+/// from the reference, or doesn't decode back to the input.
+///
+/// # Examples
+/// ```
+/// use rans::interop::ryg::verify_sample;
+///
+/// assert_eq!(verify_sample(), Ok(()));
+/// ```
+pub fn verify_sample() -> Result<(), RansError> {
+    let streams = encode(SAMPLE);
+    if streams.as_ref().map(Vec::as_slice) != SAMPLE_STREAMS {
+        return Err(RansError::VerificationFailed);
+    }
+
+    verify_decoding(SAMPLE, &streams)
+}
+
+/// Checks that the current backend reproduces [`BOOK1_STREAMS`] bit-exactly
+/// and decodes them back to the input.
+///
+/// `book1` is not embedded in this crate; it's a part of the Calgary corpus
+/// and is shipped with ryg_rans.
+///
+/// # Errors
+/// Returns [`RansError::VerificationFailed`] if any of the bitstreams differs
+/// from the reference, or doesn't decode back to the input.
+///
+/// # Panics
+/// Panics if `book1` doesn't match [`BOOK1`].
+///
+/// # Examples
+/// ```no_run
+/// use rans::interop::ryg::verify_book1;
+///
+/// let book1 = std::fs::read("book1").unwrap();
+/// assert_eq!(verify_book1(&book1), Ok(()));
+/// ```
+pub fn verify_book1(book1: &[u8]) -> Result<(), RansError> {
+    assert_eq!(
+        Digest::of(book1),
+        BOOK1,
+        "data is not book1 from the Calgary corpus"
+    );
+
+    let streams = encode(book1);
+    if streams.as_ref().map(|stream| Digest::of(stream)) != BOOK1_STREAMS {
+        return Err(RansError::VerificationFailed);
+    }
+
+    verify_decoding(book1, &streams)
+}
+
+fn sample_model(data: &[u8]) -> Model {
+    let mut counts = [0; 256];
+    for &byte in data {
+        counts[usize::from(byte)] += 1;
+    }
+
+    normalize_freqs(&counts, PROB_BITS)
+}
+
+/// Encodes `data` like the sample programs: in reverse, with the last symbol
+/// going to channel 0 if the number of symbols is not divisible by the number
+/// of channels, flushing the channels in reverse order.
+fn encode_stream<E: RansEncoderMulti<N>, const N: usize>(
+    mut encoder: E,
+    model: &EncModel<E::Symbol>,
+    data: &[u8],
+) -> Vec<u8> {
+    for (i, &byte) in data.iter().enumerate().rev() {
+        encoder.put_index_at(i % N, usize::from(byte), model);
+    }
+    for channel in (0..N).rev() {
+        encoder.flush_at(channel);
+    }
+
+    encoder.data().to_vec()
+}
+
+fn decode_stream<D: RansDecoderMulti<N>, const N: usize>(
+    mut decoder: D,
+    model: &DecModel<D::Symbol>,
+    len: usize,
+) -> Vec<u8> {
+    (0..len)
+        .map(|i| decoder.decode_symbol_at(i % N, model) as u8)
+        .collect()
+}
+
+fn verify_decoding(data: &[u8], streams: &Streams<Vec<u8>>) -> Result<(), RansError> {
+    let model = sample_model(data);
+    let byte_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    let b64_model = DecModel::<B64RansDecSymbol>::new(&model);
+
+    let decoded = [
+        decode_stream(
+            ByteRansDecoderMulti::<1>::new(streams.byte.as_slice()),
+            &byte_model,
+            data.len(),
+        ),
+        decode_stream(
+            ByteRansDecoderMulti::<2>::new(streams.byte_interleaved.as_slice()),
+            &byte_model,
+            data.len(),
+        ),
+        decode_stream(
+            B64RansDecoderMulti::<1>::new_le(streams.b64.as_slice()),
+            &b64_model,
+            data.len(),
+        ),
+        decode_stream(
+            B64RansDecoderMulti::<2>::new_le(streams.b64_interleaved.as_slice()),
+            &b64_model,
+            data.len(),
+        ),
+    ];
+    if decoded.iter().any(|decoded| decoded != data) {
+        return Err(RansError::VerificationFailed);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interop::ryg::{
+        encode, normalize_freqs, verify_book1, verify_decoding, verify_sample, SAMPLE,
+        SAMPLE_STREAMS,
+    };
+
+    #[test]
+    fn test_sample() {
+        assert_eq!(verify_sample(), Ok(()));
+    }
+
+    #[test]
+    fn test_odd_length() {
+        let data = &SAMPLE[..SAMPLE.len() - 1];
+        let streams = encode(data);
+
+        assert_ne!(streams.byte_interleaved, SAMPLE_STREAMS.byte_interleaved);
+        assert_eq!(verify_decoding(data, &streams), Ok(()));
+    }
+
+    #[test]
+    fn test_normalize_steals_from_least_frequent() {
+        let mut counts = [0; 256];
+        counts[10] = 1;
+        counts[20] = 300;
+        counts[30] = 1;
+        counts[40] = 1000;
+        let model = normalize_freqs(&counts, 8);
+
+        assert_eq!(model.freq(10), 1);
+        assert_eq!(model.freq(30), 1);
+        assert_eq!(model.freqs().iter().filter(|&&freq| freq > 0).count(), 4);
+        assert_eq!(model.freqs().iter().sum::<u32>(), 256);
+    }
+
+    #[test]
+    #[ignore = "needs book1 from the Calgary corpus; set RANS_BOOK1 to its path"]
+    fn test_book1() {
+        let book1 = std::fs::read(std::env::var("RANS_BOOK1").unwrap()).unwrap();
+
+        assert_eq!(verify_book1(&book1), Ok(()));
+    }
+}
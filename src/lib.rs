@@ -19,6 +19,31 @@
 //!
 //! See the [ryg_rans](https://github.com/rygorous/ryg_rans) repository for more details.
 //!
+//! # Crate features
+//! * `ffi` (enabled by default) uses the C implementation of rANS from [ryg-rans-sys](https://github.com/m4tx/ryg-rans-sys/).
+//! * `pure-rust` uses a port of the same implementation written in Rust
+//!   instead. It produces exactly the same output as the C version, but doesn't
+//!   need a C compiler, which makes it easy to cross-compile the crate or build
+//!   it for targets such as `wasm32-unknown-unknown`. It also provides
+//!   `new_const()` constructors for the byte and 64-bit symbols, so that
+//!   probability tables can be built into `static` arrays at compile time. If
+//!   both features are enabled, `pure-rust` takes precedence.
+//! * `checked` keeps the validity checks of the channel indices and the buffer
+//!   positions enabled in release builds, turning what would be out-of-bounds
+//!   memory accesses into panics. It also keeps the check that the symbols put
+//!   into [`per_channel::PerChannelEncoder`] belong to their channel's model.
+//!   Without it, these checks are only done in debug builds.
+//! * `parallel` enables the `parallel` module, which compresses independent
+//!   blocks of the input on multiple threads using [rayon](https://docs.rs/rayon).
+//! * `serde` implements [serde](https://serde.rs)'s `Serialize` and
+//!   `Deserialize` for the models and the encoder/decoder symbols, so that
+//!   frequency tables can be stored in any format supported by serde.
+//! * `test-util` enables the `testing` module, with generators of valid models
+//!   and symbol sequences and a round trip assertion, so that downstream crates
+//!   can property-test their integrations.
+//! * `verify` enables the `verify` module, with an encoder wrapper that decodes
+//!   every flushed block back to check it against the model.
+//!
 //! # See also
 //! * [rANS on Wikipedia](https://en.wikipedia.org/wiki/Asymmetric_numeral_systems#Range_variants_(rANS)_and_streaming)
 //!
@@ -63,19 +88,152 @@
     unused_qualifications
 )]
 
+/// Asserts that a condition holds in debug builds, as well as in release
+/// builds with the `checked` feature enabled.
+macro_rules! checked_assert {
+    ($($arg:tt)*) => {
+        if cfg!(any(debug_assertions, feature = "checked")) {
+            assert!($($arg)*);
+        }
+    };
+}
+
 pub use decoder::*;
+pub use dyn_traits::*;
 pub use encoder::*;
+pub use error::*;
+pub use rans_backend::*;
 
-/// 64-bit rANS decoder.
+/// Adaptive order-0 model and the encoder/decoder using it.
+pub mod adaptive;
+/// Asynchronous streaming compression using tokio.
+#[cfg(feature = "tokio")]
+pub mod async_stream;
+/// 64-bit (high-precision) rANS decoder.
 pub mod b64_decoder;
-/// 64-bit rANS encoder.
+/// 64-bit (high-precision) rANS encoder.
 pub mod b64_encoder;
+mod backend;
+/// Unary, Golomb-Rice and exp-Golomb binarization of integers for the binary
+/// rANS coder.
+pub mod binarization;
+/// Binary rANS coder with adaptive bit probabilities.
+pub mod binary;
+/// Bit-plane coding of signed transform coefficients (e.g. from a wavelet
+/// transform or a DCT) with the binary rANS coder.
+pub mod bitplane;
+mod blocks;
 /// Byte-aligned rANS decoder.
 pub mod byte_decoder;
 /// Byte-aligned rANS encoder.
 pub mod byte_encoder;
+/// C API over the byte-aligned coder and the `simple` compressor, for use
+/// from non-Rust code.
+#[cfg(feature = "capi")]
+pub mod capi;
+/// `RansCodec` type bundling the encoder and decoder symbols of a model.
+pub mod codec;
+/// Coding symbols with one of several models, chosen per symbol by its
+/// context.
+pub mod context;
+/// Codecs from the CRAM file format specification.
+pub mod cram;
 mod decoder;
+/// `DecoderInput` type holding the read-only data of a decoder.
+pub mod decoder_input;
+/// Preset models (dictionaries) for compressing many small messages.
+pub mod dictionary;
+mod dyn_traits;
 mod encoder;
+mod error;
+/// Models with an escape symbol for coding values outside of their
+/// alphabet.
+pub mod escape;
+/// Decoding of memory-mapped files.
+#[cfg(feature = "mmap")]
+pub mod file;
+/// Encoder and decoder adapters with the number of scale bits fixed at
+/// compile time.
+pub mod fixed_scale;
+/// Self-describing container format for rANS-encoded data.
+pub mod frame;
+/// `FreqTable` type converting between symbol frequencies and cumulative
+/// frequencies.
+pub mod freq;
+/// Blocked, strided stream layout for decoding on GPUs, along with a CPU
+/// reference decoder.
+pub mod gpu;
+/// Decompression of untrusted data, validating the bitstream as well as the
+/// header.
+pub mod hardened;
+/// Adapters rotating through the channels of multi-stream encoders and
+/// decoders.
+pub mod interleaved;
+/// Frequency table formats of other entropy coding libraries.
+pub mod interop;
+/// Alternative symbol lookup tables for large alphabets.
+pub mod lookup;
+/// LZ77 compressor with rANS-coded literals, match lengths and distances.
+pub mod lzr;
+/// Entropy, cross entropy and Kullback-Leibler divergence of histograms and
+/// models.
+pub mod math;
+/// Frequency tables (models) and the encoder/decoder symbol sets built from
+/// them.
+pub mod model;
 /// `MutCow` smart pointer to work with mutably-borrowed/owned data in a
 /// unified way.
 pub mod mut_cow;
+/// High-level order-1 (context-conditioned) byte compressor.
+pub mod order1;
+/// Parallel block-based compression using rayon.
+#[cfg(feature = "parallel")]
+pub mod parallel;
+/// Adapters associating a distinct model with each channel of multi-stream
+/// encoders and decoders.
+pub mod per_channel;
+/// Compression of multiple equally long planes (e.g. image or audio
+/// channels), each with its own model, interleaved in a multi-stream coder.
+pub mod planar;
+mod rans_backend;
+/// Rice/rANS hybrid coding of audio prediction residuals, in blocks with
+/// adaptive Rice parameters.
+pub mod rice;
+mod search;
+/// Block-indexed compressed format allowing random access to the data.
+pub mod seekable;
+#[cfg(feature = "serde")]
+mod serde_impls;
+/// Interleaved 16-bit word rANS decoder using SIMD instructions.
+pub mod simd_decoder;
+/// Interleaved 16-bit word rANS encoder for the SIMD decoder.
+pub mod simd_encoder;
+/// High-level order-0 byte compressor.
+pub mod simple;
+/// Encoder wrapper collecting compression statistics.
+pub mod stats;
+/// Block-based streaming compression over `std::io`.
+pub mod stream;
+/// Table-based ANS (tANS) encoder and decoder.
+pub mod tans;
+/// Utilities for property-testing code built on top of this crate.
+#[cfg(feature = "test-util")]
+pub mod testing;
+/// Reversible preprocessing transforms (delta, zigzag, move-to-front,
+/// run-length).
+pub mod transform;
+/// Two-pass order-0 byte compressor with a configurable model precision.
+pub mod twopass;
+/// Hybrid token + raw bits coding of large integers (JPEG XL style).
+pub mod uint_coder;
+mod varint;
+/// Encoder wrapper verifying the encoded data by decoding it back.
+#[cfg(feature = "verify")]
+pub mod verify;
+/// 16-bit word rANS decoder.
+pub mod w16_decoder;
+/// 16-bit word rANS encoder.
+pub mod w16_encoder;
+/// WebAssembly bindings for decompressing data in the browser.
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,385 @@
+use crate::model::Model;
+
+/// A single bucket of an [`AliasTable`], split into two parts belonging to
+/// (at most) two different symbols.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// First slot belonging to the second symbol
+    divider: u32,
+    /// Symbols owning the parts below and above the divider
+    symbols: [u32; 2],
+    /// Differences between the slots and the offsets within the symbols in
+    /// both parts
+    biases: [u32; 2],
+}
+
+/// Symbol lookup table built using the alias method.
+///
+/// The `1 << scale_bits` slots are split into a power-of-two number of
+/// equally sized buckets, at least as many as there are symbols in the
+/// model. Every bucket is shared by at most two symbols, so mapping a slot
+/// to its symbol takes a single comparison, and the table only has one
+/// entry per bucket instead of one per slot, which keeps it cache-friendly
+/// even for large `scale_bits`.
+///
+/// The price is that the symbols no longer occupy contiguous ranges of
+/// slots, so the data has to be encoded using the same table with the
+/// encoders' `put_alias_at()` methods and decoded with
+/// [`RansDecoderMulti::decode_alias_at()`](crate::RansDecoderMulti::decode_alias_at).
+/// The encoded data is not compatible with the regular, [`Model`]-based
+/// encoding.
+///
+/// # Examples
+/// ```
+/// use rans::byte_decoder::ByteRansDecoder;
+/// use rans::byte_encoder::ByteRansEncoder;
+/// use rans::lookup::AliasTable;
+/// use rans::model::Model;
+/// use rans::{RansDecoder, RansEncoder, RansEncoderMulti};
+///
+/// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+///
+/// let mut encoder = ByteRansEncoder::new(1024);
+/// encoder.put_alias(&table, 0);
+/// encoder.put_alias(&table, 2);
+/// encoder.flush();
+///
+/// let mut decoder = ByteRansDecoder::new(encoder.data());
+/// assert_eq!(decoder.decode_alias(&table), 2);
+/// assert_eq!(decoder.decode_alias(&table), 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AliasTable {
+    scale_bits: u32,
+    bucket_shift: u32,
+    freqs: Vec<u32>,
+    cum_freqs: Vec<u32>,
+    buckets: Vec<Bucket>,
+    /// Slot for every (symbol, offset) pair, indexed by the symbol's
+    /// cumulative frequency plus the offset
+    remap: Vec<u32>,
+}
+
+impl AliasTable {
+    /// Builds an alias table for given model.
+    ///
+    /// # Panics
+    /// Panics if the model is empty, or if it has more symbols than
+    /// `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// assert_eq!(table.len(), 3);
+    /// ```
+    #[must_use]
+    pub fn new(model: &Model) -> Self {
+        assert!(!model.is_empty(), "model must not be empty");
+        let scale_bits = model.scale_bits();
+        let num_buckets = model.len().next_power_of_two();
+        assert!(
+            num_buckets <= 1 << scale_bits,
+            "too many symbols for given scale_bits"
+        );
+
+        let bucket_bits = num_buckets.trailing_zeros();
+        let bucket_shift = scale_bits - bucket_bits;
+        let bucket_size = 1_u32 << bucket_shift;
+
+        // Vose's alias method: pair every bucket whose symbol doesn't fill it
+        // with a symbol that has more than a bucket's worth of slots left.
+        // The buckets past the end of the alphabet belong to no symbol and
+        // are filled entirely by the others.
+        let mut remaining: Vec<u32> = (0..num_buckets)
+            .map(|symbol| model.freqs().get(symbol).copied().unwrap_or(0))
+            .collect();
+        let mut buckets: Vec<Bucket> = (0..num_buckets as u32)
+            .map(|bucket| Bucket {
+                divider: (bucket + 1) << bucket_shift,
+                symbols: [bucket, bucket],
+                biases: [0, 0],
+            })
+            .collect();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..num_buckets)
+            .filter(|&symbol| remaining[symbol] != bucket_size)
+            .partition(|&symbol| remaining[symbol] < bucket_size);
+        while let (Some(&small_symbol), Some(&large_symbol)) = (small.last(), large.last()) {
+            small.pop();
+            let bucket = &mut buckets[small_symbol];
+            bucket.divider = ((small_symbol as u32) << bucket_shift) + remaining[small_symbol];
+            bucket.symbols[1] = large_symbol as u32;
+
+            remaining[large_symbol] -= bucket_size - remaining[small_symbol];
+            if remaining[large_symbol] <= bucket_size {
+                large.pop();
+                if remaining[large_symbol] < bucket_size {
+                    small.push(large_symbol);
+                }
+            }
+        }
+        debug_assert!(small.is_empty() && large.is_empty());
+
+        // Assign the offsets within every symbol in the order of the slots,
+        // so that an offset is never larger than the slot it's mapped to
+        let mut remap = vec![0; 1 << scale_bits];
+        let mut next_offsets = vec![0; model.len()];
+        for (index, bucket) in buckets.iter_mut().enumerate() {
+            let bucket_start = (index as u32) << bucket_shift;
+            let parts = [
+                bucket_start..bucket.divider,
+                bucket.divider..bucket_start + bucket_size,
+            ];
+            for (part, slots) in parts.into_iter().enumerate() {
+                if slots.is_empty() {
+                    continue;
+                }
+
+                let symbol = bucket.symbols[part] as usize;
+                let offset = next_offsets[symbol];
+                bucket.biases[part] = slots.start - offset;
+                let cum_freq = model.cum_freq(symbol) + offset;
+                for (i, slot) in slots.clone().enumerate() {
+                    remap[cum_freq as usize + i] = slot;
+                }
+                next_offsets[symbol] += slots.len() as u32;
+            }
+        }
+
+        Self {
+            scale_bits,
+            bucket_shift,
+            freqs: model.freqs().to_vec(),
+            cum_freqs: model.cum_freqs().to_vec(),
+            buckets,
+            remap,
+        }
+    }
+
+    /// Returns the number of bits used for frequency quantization.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// assert_eq!(table.scale_bits(), 4);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
+
+    /// Returns the number of symbols in this table.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// assert_eq!(table.len(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.freqs.len()
+    }
+
+    /// Returns whether this table contains no symbols. This is never the
+    /// case, as tables can't be built for empty models.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// assert!(!table.is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.freqs.is_empty()
+    }
+
+    /// Returns the frequency of given symbol.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// assert_eq!(table.freq(2), 10);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn freq(&self, symbol: usize) -> u32 {
+        self.freqs[symbol]
+    }
+
+    /// Maps a slot (the value returned by the decoders' `get` methods) to the
+    /// symbol owning it and the offset of the slot within that symbol.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// for slot in 0..16 {
+    ///     let (symbol, offset) = table.lookup(slot);
+    ///     assert!(offset < table.freq(symbol));
+    ///     assert_eq!(table.remap(symbol, offset), slot);
+    /// }
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn lookup(&self, slot: u32) -> (usize, u32) {
+        debug_assert!(slot < 1 << self.scale_bits);
+
+        let bucket = &self.buckets[(slot >> self.bucket_shift) as usize];
+        let part = usize::from(slot >= bucket.divider);
+        (bucket.symbols[part] as usize, slot - bucket.biases[part])
+    }
+
+    /// Maps an offset within given symbol to its slot. This is the inverse of
+    /// [`Self::lookup()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// let slot = table.remap(2, 3);
+    /// assert_eq!(table.lookup(slot), (2, 3));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn remap(&self, symbol: usize, offset: u32) -> u32 {
+        debug_assert!(offset < self.freqs[symbol]);
+
+        self.remap[(self.cum_freqs[symbol] + offset) as usize]
+    }
+
+    /// Returns the number of bits to shift a slot right by to get its bucket.
+    #[inline]
+    pub(crate) fn bucket_shift(&self) -> u32 {
+        self.bucket_shift
+    }
+
+    /// Returns the divider, the two symbols and the two biases of every
+    /// bucket. A part of a bucket may be empty, in which case its symbol is
+    /// meaningless and might not even be a valid index.
+    pub(crate) fn buckets(&self) -> impl Iterator<Item = (u32, [u32; 2], [u32; 2])> + '_ {
+        self.buckets
+            .iter()
+            .map(|bucket| (bucket.divider, bucket.symbols, bucket.biases))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::b64_decoder::B64RansDecoderMulti;
+    use crate::b64_encoder::B64RansEncoderMulti;
+    use crate::byte_decoder::ByteRansDecoderMulti;
+    use crate::byte_encoder::ByteRansEncoderMulti;
+    use crate::lookup::AliasTable;
+    use crate::model::Model;
+    use crate::simd_decoder::SimdRansDecoder;
+    use crate::simd_encoder::SimdRansEncoder;
+    use crate::{RansDecoderMulti, RansEncoderMulti};
+
+    fn random_model(rng: &mut Xoshiro256PlusPlus, scale_bits: u32) -> Model {
+        let counts: Vec<u32> = (0..300).map(|_| rng.gen_range(0..1000)).collect();
+        Model::from_counts(&counts, scale_bits)
+    }
+
+    fn random_symbols(rng: &mut Xoshiro256PlusPlus, model: &Model) -> Vec<usize> {
+        (0..10_000)
+            .map(|_| model.symbol_for(rng.gen_range(0..1 << model.scale_bits())))
+            .collect()
+    }
+
+    #[test]
+    fn test_lookup_covers_all_slots() {
+        let counts = [1, 0, 7, 3, 3, 100, 1, 1, 0, 20, 2];
+        for scale_bits in [4, 8, 12] {
+            let model = Model::from_counts(&counts, scale_bits);
+            let table = AliasTable::new(&model);
+
+            let mut seen = vec![0; counts.len()];
+            for slot in 0..1 << scale_bits {
+                let (symbol, offset) = table.lookup(slot);
+                assert!(offset < model.freq(symbol));
+                assert_eq!(table.remap(symbol, offset), slot);
+                seen[symbol] += 1;
+            }
+            assert_eq!(seen, model.freqs());
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        for scale_bits in [12, 14, 16] {
+            let model = random_model(&mut rng, scale_bits);
+            let table = AliasTable::new(&model);
+            let symbols = random_symbols(&mut rng, &model);
+
+            let mut encoder = ByteRansEncoderMulti::<2>::with_growable_buffer();
+            for (i, &symbol) in symbols.iter().enumerate().rev() {
+                encoder.put_alias_at(i % 2, &table, symbol);
+            }
+            encoder.flush_all();
+            let mut decoder = ByteRansDecoderMulti::<2>::new(encoder.data());
+            for (i, &symbol) in symbols.iter().enumerate() {
+                assert_eq!(decoder.decode_alias_at(1 - i % 2, &table), symbol);
+            }
+
+            let mut encoder = B64RansEncoderMulti::<2>::with_growable_buffer();
+            for (i, &symbol) in symbols.iter().enumerate().rev() {
+                encoder.put_alias_at(i % 2, &table, symbol);
+            }
+            encoder.flush_all();
+            let mut decoder = B64RansDecoderMulti::<2>::new(encoder.data());
+            for (i, &symbol) in symbols.iter().enumerate() {
+                assert_eq!(decoder.decode_alias_at(1 - i % 2, &table), symbol);
+            }
+
+            let mut encoder = SimdRansEncoder::<2>::with_growable_buffer();
+            for (i, &symbol) in symbols.iter().enumerate().rev() {
+                encoder.put_alias_at(i % 2, &table, symbol);
+            }
+            encoder.flush_all();
+            let mut decoder = SimdRansDecoder::<2>::new(encoder.data());
+            for (i, &symbol) in symbols.iter().enumerate() {
+                assert_eq!(decoder.decode_alias_at(1 - i % 2, &table), symbol);
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_symbol() {
+        let table = AliasTable::new(&Model::from_freqs(vec![4], 2));
+
+        for slot in 0..4 {
+            assert_eq!(table.lookup(slot), (0, slot));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "too many symbols")]
+    fn test_too_many_symbols() {
+        let _ = AliasTable::new(&Model::from_freqs(vec![1, 1, 1, 1, 0], 2));
+    }
+}
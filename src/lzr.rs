@@ -0,0 +1,522 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::model::{DecModel, EncModel, Model};
+use crate::simple::{decode_symbol_checked, output_buffer, scale_counts, SCALE_BITS};
+use crate::uint_coder::{TokenScheme, UintDecModel, UintEncModel};
+use crate::{varint, RansEncoder, RansEncoderMulti, RansError};
+
+/// Symbol of the literal model announcing a match instead of a literal.
+const MATCH: usize = 256;
+/// Number of symbols of the literal model: all the bytes and [`MATCH`].
+const NUM_LITERALS: usize = MATCH + 1;
+/// Length of the shortest match.
+const MIN_MATCH: usize = 3;
+/// Length of the longest match.
+const MAX_MATCH: usize = 4096;
+/// Largest distance of a match.
+const WINDOW_SIZE: usize = 1 << 20;
+/// Number of bits of the hashes of the first [`MIN_MATCH`] bytes of a match.
+const HASH_BITS: u32 = 16;
+/// Number of earlier positions with the same hash checked for a match.
+const MAX_CHAIN: usize = 64;
+/// Scheme of the tokens of the match lengths and distances.
+const TOKEN_SCHEME: TokenScheme = TokenScheme::new(4, 2, 0);
+
+/// Strategy of splitting the data into literals and matches.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Parser {
+    /// Takes the longest match at the current position, if any.
+    Greedy,
+    /// Like [`Self::Greedy`], but emits a literal instead of the match if
+    /// there is a longer match starting at the next position. This is a
+    /// little slower, but usually compresses better.
+    #[default]
+    Lazy,
+}
+
+/// Single step of the parsed data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Literal(u8),
+    Match { len: usize, distance: usize },
+}
+
+/// Compresses a byte buffer with LZ77 and [`Parser::Lazy`] parsing. See
+/// [`compress_with()`].
+///
+/// # Examples
+/// ```
+/// use rans::lzr::{compress, decompress};
+///
+/// let data = b"abracadabra ".repeat(100);
+/// let compressed = compress(&data);
+/// assert!(compressed.len() < data.len() / 10);
+/// assert_eq!(decompress(&compressed).unwrap(), data);
+/// ```
+#[must_use]
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    compress_with(data, Parser::default())
+}
+
+/// Compresses a byte buffer with LZ77, using given parser to find the
+/// matches, and codes the literals, the match lengths and the match
+/// distances with separate static models.
+///
+/// The lengths and distances are split into tokens coded with the models,
+/// followed by raw residual bits (see [`TokenScheme`]). Whether a literal or
+/// a match comes next is coded with the literal model, which has an extra
+/// symbol for the latter. Matches are between 3 and 4096 bytes long and reach
+/// up to 1 MiB back.
+///
+/// The output starts with the original length and the three models, followed
+/// by the rANS bitstream. Use [`decompress()`] to get the original data back;
+/// the format doesn't depend on the parser.
+///
+/// # Examples
+/// ```
+/// use rans::lzr::{compress_with, decompress, Parser};
+///
+/// let data: Vec<u8> = b"to be or not to be".repeat(10);
+/// let greedy = compress_with(&data, Parser::Greedy);
+/// let lazy = compress_with(&data, Parser::Lazy);
+/// assert_eq!(decompress(&greedy).unwrap(), data);
+/// assert_eq!(decompress(&lazy).unwrap(), data);
+/// ```
+#[must_use]
+pub fn compress_with(data: &[u8], parser: Parser) -> Vec<u8> {
+    let mut output = Vec::new();
+    varint::write_u64(&mut output, data.len() as u64);
+    if data.is_empty() {
+        return output;
+    }
+
+    let commands = parse(data, parser);
+    let mut literal_counts = vec![0; NUM_LITERALS];
+    let mut length_counts = vec![0; TOKEN_SCHEME.num_tokens()];
+    let mut distance_counts = vec![0; TOKEN_SCHEME.num_tokens()];
+    for &command in &commands {
+        match command {
+            Command::Literal(byte) => literal_counts[usize::from(byte)] += 1,
+            Command::Match { len, distance } => {
+                literal_counts[MATCH] += 1;
+                length_counts[TOKEN_SCHEME.tokenize((len - MIN_MATCH) as u32).0 as usize] += 1;
+                distance_counts[TOKEN_SCHEME.tokenize((distance - 1) as u32).0 as usize] += 1;
+            }
+        }
+    }
+
+    let literal_model = build_model(&literal_counts);
+    let length_model = build_model(&length_counts);
+    let distance_model = build_model(&distance_counts);
+    for model in [&literal_model, &length_model, &distance_model] {
+        output.extend_from_slice(&model.to_bytes());
+    }
+
+    let literal_enc_model = EncModel::<ByteRansEncSymbol>::new(&literal_model);
+    let length_enc_model = UintEncModel::new(&length_model, TOKEN_SCHEME);
+    let distance_enc_model = UintEncModel::new(&distance_model, TOKEN_SCHEME);
+    let mut encoder = ByteRansEncoder::with_growable_buffer();
+    for &command in commands.iter().rev() {
+        match command {
+            Command::Literal(byte) => encoder.put_index(usize::from(byte), &literal_enc_model),
+            Command::Match { len, distance } => {
+                distance_enc_model.put_at(&mut encoder, 0, (distance - 1) as u32);
+                length_enc_model.put_at(&mut encoder, 0, (len - MIN_MATCH) as u32);
+                encoder.put_index(MATCH, &literal_enc_model);
+            }
+        }
+    }
+    encoder.flush();
+
+    output.extend_from_slice(encoder.data());
+    output
+}
+
+/// Decompresses data produced by [`compress()`] or [`compress_with()`].
+///
+/// The header, the bitstream and the matches are all validated, but the
+/// output length is only limited by the header.
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the input is truncated,
+/// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if the header is
+/// malformed, [`RansError::InputExhausted`] if the bitstream ends before all
+/// the bytes are decoded, or [`RansError::InvalidSymbol`] if a match reaches
+/// before the start or past the end of the data.
+///
+/// # Examples
+/// ```
+/// use rans::lzr::{compress, decompress};
+/// use rans::RansError;
+///
+/// let compressed = compress(b"hello hello");
+/// assert_eq!(decompress(&compressed).unwrap(), b"hello hello");
+/// assert_eq!(decompress(&compressed[..3]), Err(RansError::UnexpectedEof));
+/// ```
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, RansError> {
+    let mut pos = 0;
+    let len =
+        usize::try_from(varint::read_u64(data, &mut pos)?).map_err(|_| RansError::InvalidHeader)?;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    let literal_model = read_model(data, &mut pos, NUM_LITERALS)?;
+    let length_model = read_model(data, &mut pos, TOKEN_SCHEME.num_tokens())?;
+    let distance_model = read_model(data, &mut pos, TOKEN_SCHEME.num_tokens())?;
+    let payload = &data[pos..];
+    if payload.len() < 4 {
+        return Err(RansError::UnexpectedEof);
+    }
+
+    let literal_dec_model = DecModel::<ByteRansDecSymbol>::new(&literal_model);
+    let length_dec_model = UintDecModel::new(&length_model, TOKEN_SCHEME);
+    let distance_dec_model = UintDecModel::new(&distance_model, TOKEN_SCHEME);
+    let mut decoder = ByteRansDecoder::new(payload);
+    let mut output = output_buffer(&decoder, len);
+    while output.len() < len {
+        let symbol = decode_symbol_checked(&mut decoder, 0, &literal_dec_model)?;
+        if symbol != MATCH {
+            output.push(symbol as u8);
+            continue;
+        }
+
+        let match_len = length_dec_model.try_decode_at(&mut decoder, 0)? as usize + MIN_MATCH;
+        let distance = distance_dec_model.try_decode_at(&mut decoder, 0)? as usize + 1;
+        if distance > output.len() || match_len > len - output.len() {
+            return Err(RansError::InvalidSymbol);
+        }
+        // The match may overlap the bytes it produces, so it has to be copied
+        // byte by byte
+        let start = output.len() - distance;
+        for i in start..start + match_len {
+            output.push(output[i]);
+        }
+    }
+
+    Ok(output)
+}
+
+fn build_model(counts: &[u64]) -> Model {
+    if counts.iter().all(|&count| count == 0) {
+        // The model is never used, but it still has to be valid
+        let mut counts = vec![0; counts.len()];
+        counts[0] = 1;
+        return Model::from_counts(&counts, SCALE_BITS);
+    }
+    Model::from_counts(&scale_counts(counts), SCALE_BITS)
+}
+
+fn read_model(data: &[u8], pos: &mut usize, len: usize) -> Result<Model, RansError> {
+    let model = Model::read_bytes(data, pos)?;
+    if model.scale_bits() != SCALE_BITS || model.len() != len {
+        return Err(RansError::InvalidHeader);
+    }
+    Ok(model)
+}
+
+/// Splits the data into literals and matches.
+fn parse(data: &[u8], parser: Parser) -> Vec<Command> {
+    let mut finder = MatchFinder::new(data);
+    let mut commands = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let (len, distance) = finder.find(pos);
+        if len < MIN_MATCH {
+            commands.push(Command::Literal(data[pos]));
+            finder.insert(pos);
+            pos += 1;
+            continue;
+        }
+
+        finder.insert(pos);
+        if parser == Parser::Lazy && finder.find(pos + 1).0 > len {
+            commands.push(Command::Literal(data[pos]));
+            pos += 1;
+            continue;
+        }
+
+        commands.push(Command::Match { len, distance });
+        for pos in pos + 1..pos + len {
+            finder.insert(pos);
+        }
+        pos += len;
+    }
+
+    commands
+}
+
+/// Finder of the longest earlier match using hash chains: the last position
+/// with every hash of [`MIN_MATCH`] bytes, and the previous position with the
+/// same hash for every position in the window.
+#[derive(Debug)]
+struct MatchFinder<'a> {
+    data: &'a [u8],
+    /// Last position plus one with every hash, or 0
+    head: Vec<u32>,
+    /// Previous position plus one with the same hash as the position, or 0,
+    /// indexed by the position modulo [`WINDOW_SIZE`]
+    prev: Vec<u32>,
+}
+
+impl<'a> MatchFinder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            head: vec![0; 1 << HASH_BITS],
+            prev: vec![0; WINDOW_SIZE.min(data.len())],
+        }
+    }
+
+    fn hash(&self, pos: usize) -> usize {
+        let bytes = &self.data[pos..pos + MIN_MATCH];
+        let value = u32::from(bytes[0]) | u32::from(bytes[1]) << 8 | u32::from(bytes[2]) << 16;
+        (value.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    }
+
+    /// Adds the position to the chain of its hash.
+    fn insert(&mut self, pos: usize) {
+        if pos + MIN_MATCH > self.data.len() {
+            return;
+        }
+
+        let hash = self.hash(pos);
+        self.prev[pos % WINDOW_SIZE] = self.head[hash];
+        self.head[hash] = pos as u32 + 1;
+    }
+
+    /// Returns the length and the distance of the longest match at given
+    /// position, with a length of 0 if there is none.
+    fn find(&self, pos: usize) -> (usize, usize) {
+        if pos + MIN_MATCH > self.data.len() {
+            return (0, 0);
+        }
+
+        let max_len = MAX_MATCH.min(self.data.len() - pos);
+        let mut best = (0, 0);
+        let mut candidate = self.head[self.hash(pos)];
+        for _ in 0..MAX_CHAIN {
+            if candidate == 0 {
+                break;
+            }
+            let start = candidate as usize - 1;
+            // Positions are inserted in order, so the chain only gets further
+            // away from here
+            if pos - start > WINDOW_SIZE {
+                break;
+            }
+
+            let len = self.data[start..]
+                .iter()
+                .zip(&self.data[pos..pos + max_len])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len > best.0 {
+                best = (len, pos - start);
+                if len == max_len {
+                    break;
+                }
+            }
+            candidate = self.prev[start % WINDOW_SIZE];
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::byte_encoder::ByteRansEncoder;
+    use crate::lzr::{
+        build_model, compress, compress_with, decompress, parse, Command, Parser, MATCH, MAX_MATCH,
+        NUM_LITERALS, TOKEN_SCHEME,
+    };
+    use crate::model::{EncModel, Model};
+    use crate::simple::SCALE_BITS;
+    use crate::uint_coder::UintEncModel;
+    use crate::{order1, simple, varint, RansEncoder, RansEncoderMulti, RansError};
+
+    /// Text-like data with many repeated words, both near and far apart.
+    fn words(len: usize) -> Vec<u8> {
+        const WORDS: [&str; 12] = [
+            "lorem",
+            "ipsum",
+            "dolor",
+            "sit",
+            "amet",
+            "consectetur",
+            "adipiscing",
+            "elit",
+            "sed",
+            "do",
+            "eiusmod",
+            "tempor",
+        ];
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut data = Vec::with_capacity(len + 16);
+        while data.len() < len {
+            data.extend_from_slice(WORDS[rng.gen_range(0..WORDS.len())].as_bytes());
+            data.push(if rng.gen_range(0..10) == 0 {
+                b'\n'
+            } else {
+                b' '
+            });
+        }
+        data.truncate(len);
+        data
+    }
+
+    #[test]
+    fn test_round_trip_empty() {
+        let compressed = compress(&[]);
+
+        assert_eq!(compressed, [0]);
+        assert_eq!(decompress(&compressed), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_round_trip_short() {
+        for data in [&b"a"[..], b"ab", b"abc", b"aaaa", b"abcabcabc"] {
+            for parser in [Parser::Greedy, Parser::Lazy] {
+                assert_eq!(decompress(&compress_with(data, parser)).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_random() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        let data: Vec<u8> = (0..50000).map(|_| rng.gen()).collect();
+
+        let compressed = compress(&data);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        // No matches to speak of, so only the header is added
+        assert!(compressed.len() < data.len() + data.len() / 50);
+    }
+
+    #[test]
+    fn test_round_trip_long_runs() {
+        let mut data = vec![7; 3 * MAX_MATCH + 5];
+        data.extend_from_slice(&[1, 2, 3]);
+        data.extend(std::iter::repeat(9).take(100000));
+
+        let commands = parse(&data, Parser::Lazy);
+        assert!(commands.len() < 40);
+        assert!(commands.iter().all(|&command| match command {
+            Command::Literal(_) => true,
+            Command::Match { len, .. } => len <= MAX_MATCH,
+        }));
+        assert_eq!(decompress(&compress(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_beats_entropy_coders_on_text() {
+        let data = words(100000);
+        let greedy = compress_with(&data, Parser::Greedy);
+        let lazy = compress_with(&data, Parser::Lazy);
+
+        assert_eq!(decompress(&greedy).unwrap(), data);
+        assert_eq!(decompress(&lazy).unwrap(), data);
+        for compressed in [greedy, lazy] {
+            assert!(compressed.len() < order1::compress(&data).len() * 2 / 3);
+            assert!(compressed.len() < simple::compress(&data).len() / 3);
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let compressed = compress(b"abcabcabcabc");
+
+        for len in 0..compressed.len() - 4 {
+            assert_eq!(
+                decompress(&compressed[..len]),
+                Err(RansError::UnexpectedEof)
+            );
+        }
+    }
+
+    #[test]
+    fn test_truncated_bitstream() {
+        let data = words(1000);
+        let compressed = compress(&data);
+
+        for cut in 1..64 {
+            assert_eq!(
+                decompress(&compressed[..compressed.len() - cut]),
+                Err(RansError::InputExhausted)
+            );
+        }
+    }
+
+    #[test]
+    fn test_corrupted_bitstream() {
+        let data = words(1000);
+        let compressed = compress(&data);
+        let header_len = compressed.len() - 64;
+
+        // Must not read out of bounds or panic, whatever the result is
+        for i in header_len..compressed.len() {
+            for bit in 0..8 {
+                let mut corrupted = compressed.clone();
+                corrupted[i] ^= 1 << bit;
+                if let Ok(output) = decompress(&corrupted) {
+                    assert_eq!(output.len(), data.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_match_past_end() {
+        // The last match runs past the end when the declared length is shorter
+        let data = b"xyzxyzxyzxyz";
+        let mut compressed = Vec::new();
+        varint::write_u64(&mut compressed, data.len() as u64 - 1);
+        compressed.extend_from_slice(&compress(data)[1..]);
+
+        assert_eq!(decompress(&compressed), Err(RansError::InvalidSymbol));
+    }
+
+    #[test]
+    fn test_match_before_start() {
+        // A match as the very first command, which no encoder would produce
+        let mut literal_counts = [0; NUM_LITERALS];
+        literal_counts[MATCH] = 1;
+        let literal_model = Model::from_counts(&literal_counts, SCALE_BITS);
+        let token_model = build_model(&vec![0; TOKEN_SCHEME.num_tokens()]);
+
+        let mut compressed = vec![3];
+        for model in [&literal_model, &token_model, &token_model] {
+            compressed.extend_from_slice(&model.to_bytes());
+        }
+        let mut encoder = ByteRansEncoder::new(64);
+        let token_enc_model = UintEncModel::new(&token_model, TOKEN_SCHEME);
+        token_enc_model.put_at(&mut encoder, 0, 0);
+        token_enc_model.put_at(&mut encoder, 0, 0);
+        encoder.put_index(MATCH, &EncModel::new(&literal_model));
+        encoder.flush();
+        compressed.extend_from_slice(encoder.data());
+
+        assert_eq!(decompress(&compressed), Err(RansError::InvalidSymbol));
+    }
+
+    #[test]
+    fn test_invalid_models() {
+        let compressed = compress(b"abcabcabc");
+
+        // A literal model with a byte alphabet only
+        let mut corrupted = vec![9];
+        corrupted.extend_from_slice(&Model::from_counts(&[1; 256], SCALE_BITS).to_bytes());
+        corrupted.extend_from_slice(&compressed[1..]);
+        assert_eq!(decompress(&corrupted), Err(RansError::InvalidHeader));
+
+        // A literal model with different scale bits
+        let mut corrupted = vec![9];
+        corrupted.extend_from_slice(&Model::from_counts(&[1; NUM_LITERALS], 10).to_bytes());
+        corrupted.extend_from_slice(&compressed[1..]);
+        assert_eq!(decompress(&corrupted), Err(RansError::InvalidHeader));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::model::Model;
+
+/// Returns the empirical entropy of a histogram, in bits per symbol, i.e. the
+/// average number of bits an ideal entropy coder needs for each symbol when
+/// the model exactly matches the counts.
+///
+/// Returns `0.0` if all the counts are zero.
+///
+/// # Examples
+/// ```
+/// use rans::math::entropy;
+///
+/// assert_eq!(entropy(&[2, 1, 0, 1]), 1.5);
+/// assert_eq!(entropy(&[0, 7, 0]), 0.0);
+/// assert_eq!(entropy(&[]), 0.0);
+/// ```
+#[must_use]
+pub fn entropy(counts: &[u32]) -> f64 {
+    let total = total_count(counts);
+    if total == 0 {
+        return 0.0;
+    }
+
+    let total = total as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let probability = f64::from(count) / total;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+/// Returns the cross entropy between a histogram and a model, in bits per
+/// symbol, i.e. the average number of bits needed for each symbol when the
+/// symbols occurring with given counts are encoded with `model`.
+///
+/// This is never less than [`entropy()`] of the counts, and can be used to
+/// compare candidate models, including the same model normalized to
+/// different `scale_bits`. The result is infinite if any of the symbols with
+/// non-zero count has zero frequency in the model, and `0.0` if all the
+/// counts are zero.
+///
+/// # Panics
+/// Panics if there are more counts than symbols in the model.
+///
+/// # Examples
+/// ```
+/// use rans::math::cross_entropy;
+/// use rans::model::Model;
+///
+/// let model = Model::from_freqs(vec![2, 1, 1], 2);
+/// assert_eq!(cross_entropy(&[2, 1, 1], &model), 1.5);
+/// assert_eq!(cross_entropy(&[0, 1, 1], &model), 2.0);
+///
+/// // A finer scale approximates the counts better
+/// let counts = [1000, 10, 1];
+/// let coarse = Model::from_counts(&counts, 6);
+/// let fine = Model::from_counts(&counts, 12);
+/// assert!(cross_entropy(&counts, &fine) < cross_entropy(&counts, &coarse));
+/// ```
+#[must_use]
+pub fn cross_entropy(counts: &[u32], model: &Model) -> f64 {
+    let size = model.estimate_size(counts);
+    let total = total_count(counts);
+    if total == 0 {
+        return 0.0;
+    }
+
+    size / total as f64
+}
+
+/// Returns the Kullback-Leibler divergence of a model from a histogram, in
+/// bits per symbol, i.e. the number of bits per symbol wasted by encoding
+/// the symbols occurring with given counts with `model` instead of a model
+/// matching the counts exactly.
+///
+/// This is the difference between [`cross_entropy()`] and [`entropy()`]. The
+/// result is infinite if any of the symbols with non-zero count has zero
+/// frequency in the model, and `0.0` if all the counts are zero.
+///
+/// # Panics
+/// Panics if there are more counts than symbols in the model.
+///
+/// # Examples
+/// ```
+/// use rans::math::kl_divergence;
+/// use rans::model::Model;
+///
+/// let model = Model::from_freqs(vec![2, 1, 1], 2);
+/// assert_eq!(kl_divergence(&[2, 1, 1], &model), 0.0);
+/// assert_eq!(kl_divergence(&[0, 1, 1], &model), 1.0);
+/// assert_eq!(kl_divergence(&[1, 1], &Model::from_freqs(vec![4, 0], 2)), f64::INFINITY);
+/// ```
+#[must_use]
+pub fn kl_divergence(counts: &[u32], model: &Model) -> f64 {
+    // Rounding errors could make the difference slightly negative
+    (cross_entropy(counts, model) - entropy(counts)).max(0.0)
+}
+
+fn total_count(counts: &[u32]) -> u64 {
+    counts.iter().map(|&count| u64::from(count)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{cross_entropy, entropy, kl_divergence};
+    use crate::model::Model;
+
+    #[test]
+    fn test_uniform() {
+        let counts = [5; 256];
+        let model = Model::from_counts(&counts, 8);
+
+        assert!((entropy(&counts) - 8.0).abs() < 1e-9);
+        assert!((cross_entropy(&counts, &model) - 8.0).abs() < 1e-9);
+        assert!(kl_divergence(&counts, &model) < 1e-9);
+    }
+
+    #[test]
+    fn test_model_entropy() {
+        let model = Model::from_counts(&[30, 1, 7, 100, 2], 12);
+
+        assert!((entropy(model.freqs()) - model.entropy()).abs() < 1e-9);
+        assert!((cross_entropy(model.freqs(), &model) - model.entropy()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mismatched_model() {
+        let counts = [90, 10];
+        let model = Model::from_counts(&[50, 50], 12);
+
+        let divergence = kl_divergence(&counts, &model);
+        assert!(divergence > 0.0);
+        assert!((cross_entropy(&counts, &model) - entropy(&counts) - divergence).abs() < 1e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "more counts than symbols in the model")]
+    fn test_too_many_counts() {
+        let _ = cross_entropy(&[1, 2, 3], &Model::from_freqs(vec![4], 2));
+    }
+}
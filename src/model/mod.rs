@@ -0,0 +1,1774 @@
+/// Models following common parametric distributions, for codecs that model
+/// their symbols (e.g. prediction residuals) analytically instead of
+/// transmitting a whole frequency table.
+///
+/// All the distributions are truncated to `n_symbols` symbols. Every symbol
+/// gets a non-zero frequency, so any value in `0..n_symbols` can be encoded
+/// with the resulting model, even if it's very unlikely according to the
+/// distribution.
+pub mod presets;
+/// Models of an explicit subset of a larger alphabet, e.g. the handful of
+/// byte values or 16-bit tokens that actually occur in a short input.
+pub mod sparse;
+
+use crate::encoder::{assert_scale_bits, check_symbol};
+use crate::lookup::AliasTable;
+use crate::math::kl_divergence;
+use crate::simple::scale_counts;
+use crate::{search, varint, RansDecSymbol, RansEncSymbol, RansError};
+
+/// Quantization loss, in bits per symbol, accepted by [`Model::fit()`].
+pub const DEFAULT_FIT_TOLERANCE: f64 = 0.01;
+
+/// Normalized frequency table of a symbol alphabet.
+///
+/// The frequencies of all symbols in a model sum up to exactly
+/// `1 << scale_bits`, which is the form required by the rANS encoders and
+/// decoders. Symbols are identified by their index in the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Model {
+    scale_bits: u32,
+    freqs: Vec<u32>,
+    cum_freqs: Vec<u32>,
+}
+
+impl Model {
+    /// Creates a new model by normalizing raw symbol counts to
+    /// `1 << scale_bits`, using [`NormalizationStrategy::LargestRemainder`].
+    ///
+    /// The counts are scaled proportionally and rounded down; the rounding
+    /// leftover goes to the symbols with the largest remainders (lowest index
+    /// wins on ties). Every symbol with a non-zero count is guaranteed to get
+    /// a frequency of at least 1; if that makes the total too large, the
+    /// excess is taken from the most frequent symbols. The result is fully
+    /// deterministic.
+    ///
+    /// # Panics
+    /// Panics if all counts are zero, if `scale_bits` is larger than 31, or if
+    /// there are more symbols with non-zero count than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[10, 0, 30], 4);
+    /// assert_eq!(model.freqs(), [4, 0, 12]);
+    /// assert_eq!(model.cum_freqs(), [0, 4, 4]);
+    /// ```
+    #[must_use]
+    pub fn from_counts(counts: &[u32], scale_bits: u32) -> Self {
+        Self::from_counts_with_strategy(counts, scale_bits, NormalizationStrategy::default())
+    }
+
+    /// Creates a new model by normalizing raw symbol counts to
+    /// `1 << scale_bits` with given normalization strategy.
+    ///
+    /// Regardless of the strategy, every symbol with a non-zero count gets a
+    /// non-zero frequency, symbols with zero count get zero frequency, and
+    /// the result is fully deterministic.
+    ///
+    /// # Panics
+    /// Panics if all counts are zero, if `scale_bits` is larger than 31, or if
+    /// there are more symbols with non-zero count than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::{Model, NormalizationStrategy};
+    ///
+    /// let counts = [1, 1, 1, 1, 1, 1, 1, 1, 1000];
+    /// let rounding = Model::from_counts_with_strategy(&counts, 4, NormalizationStrategy::Rounding);
+    /// let optimal = Model::from_counts_with_strategy(&counts, 4, NormalizationStrategy::Optimal);
+    /// assert_eq!(rounding.freqs(), [1, 1, 1, 1, 1, 1, 1, 1, 8]);
+    /// assert!(optimal.estimate_size(&counts) <= rounding.estimate_size(&counts));
+    /// ```
+    #[must_use]
+    pub fn from_counts_with_strategy(
+        counts: &[u32],
+        scale_bits: u32,
+        strategy: NormalizationStrategy,
+    ) -> Self {
+        assert!(scale_bits <= 31, "scale_bits must not be larger than 31");
+
+        let total = 1_u64 << scale_bits;
+        let sum: u64 = counts.iter().map(|&count| u64::from(count)).sum();
+        assert!(sum > 0, "at least one symbol must have a non-zero count");
+        let present = counts.iter().filter(|&&count| count > 0).count() as u64;
+        assert!(
+            present <= total,
+            "too many distinct symbols for given scale_bits"
+        );
+
+        let freqs = match strategy {
+            NormalizationStrategy::LargestRemainder => {
+                normalize_largest_remainder(counts, sum, total)
+            }
+            NormalizationStrategy::Rounding => normalize_rounding(counts, sum, total),
+            NormalizationStrategy::Fast => normalize_fast(counts, sum, total),
+            NormalizationStrategy::Optimal => normalize_optimal(counts, sum, total),
+        };
+
+        Self::from_freqs(freqs, scale_bits)
+    }
+
+    /// Creates a new model from raw symbol counts like [`Self::from_counts()`],
+    /// choosing the smallest `scale_bits` (up to `max_scale_bits`) at which
+    /// normalizing the counts loses at most [`DEFAULT_FIT_TOLERANCE`] bits
+    /// per symbol. See [`Self::fit_with_tolerance()`].
+    ///
+    /// # Panics
+    /// Panics if all counts are zero, if `max_scale_bits` is larger than 31,
+    /// or if there are more symbols with non-zero count than
+    /// `1 << max_scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::fit(&[10, 10, 10, 10], 16);
+    /// assert_eq!(model.scale_bits(), 2);
+    ///
+    /// let model = Model::fit(&[1000, 10, 1], 16);
+    /// assert!(model.scale_bits() > 2 && model.scale_bits() < 16);
+    /// ```
+    #[must_use]
+    pub fn fit(counts: &[u32], max_scale_bits: u32) -> Self {
+        Self::fit_with_tolerance(counts, max_scale_bits, DEFAULT_FIT_TOLERANCE)
+    }
+
+    /// Creates a new model from raw symbol counts like [`Self::from_counts()`],
+    /// choosing the smallest `scale_bits` (up to `max_scale_bits`) at which
+    /// normalizing the counts loses at most `tolerance` bits per symbol.
+    ///
+    /// The loss is the [`kl_divergence()`](crate::math::kl_divergence) of the
+    /// model from the counts. Smaller `scale_bits` mean smaller decoding
+    /// tables and model headers, so this trades them against the compression
+    /// ratio. If none of the `scale_bits` is good enough, the model is
+    /// normalized to `max_scale_bits`.
+    ///
+    /// # Panics
+    /// Panics if `tolerance` is negative or NaN, or in any of the cases
+    /// [`Self::fit()`] panics.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::math::kl_divergence;
+    /// use rans::model::Model;
+    ///
+    /// let counts = [5000, 300, 20, 1];
+    /// let model = Model::fit_with_tolerance(&counts, 16, 0.001);
+    /// assert!(kl_divergence(&counts, &model) <= 0.001);
+    /// assert!(model.scale_bits() >= Model::fit_with_tolerance(&counts, 16, 0.1).scale_bits());
+    /// ```
+    #[must_use]
+    pub fn fit_with_tolerance(counts: &[u32], max_scale_bits: u32, tolerance: f64) -> Self {
+        assert!(tolerance >= 0.0, "tolerance must be a non-negative number");
+        assert!(
+            max_scale_bits <= 31,
+            "max_scale_bits must not be larger than 31"
+        );
+
+        // Every symbol needs at least one slot
+        let present = counts.iter().filter(|&&count| count > 0).count();
+        let min_scale_bits = present.next_power_of_two().trailing_zeros().max(1);
+        for scale_bits in min_scale_bits..max_scale_bits {
+            let model = Self::from_counts(counts, scale_bits);
+            if kl_divergence(counts, &model) <= tolerance {
+                return model;
+            }
+        }
+
+        Self::from_counts(counts, max_scale_bits)
+    }
+
+    /// Creates a new byte model trained on given sample data, e.g. to be
+    /// used as a [`Dictionary`](crate::dictionary::Dictionary) for many small
+    /// messages.
+    ///
+    /// The model has 256 symbols. Every byte gets a non-zero frequency, even
+    /// if it doesn't occur in the samples, so any data can be encoded with
+    /// the model; such bytes are treated as if they were 256 times less
+    /// frequent than a byte occurring once.
+    ///
+    /// # Panics
+    /// Panics if `scale_bits` is smaller than 8 or larger than 31.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::train(&["hello", "help", "hell"], 12);
+    /// assert_eq!(model.len(), 256);
+    /// assert!(model.freq(usize::from(b'l')) > model.freq(usize::from(b'p')));
+    /// assert!(model.freq(usize::from(b'z')) > 0);
+    /// ```
+    #[must_use]
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], scale_bits: u32) -> Self {
+        assert!(scale_bits >= 8, "scale_bits must be at least 8");
+
+        let mut counts = [1_u64; 256];
+        for sample in samples {
+            for &byte in sample.as_ref() {
+                counts[byte as usize] += 256;
+            }
+        }
+
+        Self::from_counts(&scale_counts(&counts), scale_bits)
+    }
+
+    /// Creates a new model from the histogram of given 16-bit symbols, e.g.
+    /// quantized coefficients or token IDs.
+    ///
+    /// The model has `max + 1` symbols, where `max` is the largest symbol in
+    /// `symbols`; only the symbols occurring in the data get non-zero
+    /// frequencies.
+    ///
+    /// # Panics
+    /// Panics if `symbols` is empty, if `scale_bits` is larger than 31, or if
+    /// there are more distinct symbols than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_symbols(&[1000, 3, 1000, 1000], 8);
+    /// assert_eq!(model.len(), 1001);
+    /// assert_eq!(model.freq(3), 64);
+    /// assert_eq!(model.freq(1000), 192);
+    /// ```
+    #[must_use]
+    pub fn from_symbols(symbols: &[u16], scale_bits: u32) -> Self {
+        let max = symbols.iter().copied().max();
+        let max = usize::from(max.expect("symbols must not be empty"));
+
+        let mut counts = vec![0_u64; max + 1];
+        for &symbol in symbols {
+            counts[usize::from(symbol)] += 1;
+        }
+
+        Self::from_counts(&scale_counts(&counts), scale_bits)
+    }
+
+    /// Creates a new model from symbol probabilities.
+    ///
+    /// The probabilities do not need to sum up to 1; they are treated as
+    /// relative weights. They are quantized to integer counts and then
+    /// normalized with the same algorithm as [`Self::from_counts()`], so
+    /// every symbol with a non-zero probability gets a non-zero frequency.
+    ///
+    /// # Panics
+    /// Panics if any of the probabilities is negative or not finite, or in
+    /// any of the cases [`Self::from_counts()`] panics.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_probabilities(&[0.25, 0.25, 0.5], 4);
+    /// assert_eq!(model.freqs(), [4, 4, 8]);
+    /// ```
+    #[must_use]
+    pub fn from_probabilities(probabilities: &[f32], scale_bits: u32) -> Self {
+        const RESOLUTION: f32 = (1 << 24) as f32;
+
+        assert!(
+            probabilities.iter().all(|&p| p.is_finite() && p >= 0.0),
+            "probabilities must be finite and non-negative"
+        );
+        let max = probabilities.iter().copied().fold(0.0_f32, f32::max);
+        assert!(
+            max > 0.0,
+            "at least one symbol must have a non-zero probability"
+        );
+
+        let counts: Vec<u32> = probabilities
+            .iter()
+            .map(|&p| {
+                if p == 0.0 {
+                    0
+                } else {
+                    ((p / max * RESOLUTION) as u32).max(1)
+                }
+            })
+            .collect();
+
+        Self::from_counts(&counts, scale_bits)
+    }
+
+    /// Creates a new model from floating-point symbol weights (e.g. `f32` or
+    /// `f64` probabilities), checking that the weights can be normalized.
+    ///
+    /// The weights do not need to sum up to 1. The normalization is exact and
+    /// deterministic: every symbol with a non-zero weight first gets a
+    /// frequency of 1, and the remaining `(1 << scale_bits) - n` slots (`n`
+    /// being the number of such symbols) are distributed proportionally to
+    /// the weights, rounding down; the rounding leftover goes to the symbols
+    /// with the largest remainders (lowest index wins on ties). Symbols with
+    /// zero weight get zero frequency, and the frequencies always sum up to
+    /// exactly `1 << scale_bits`.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidModel`] if any of the weights is negative
+    /// or not finite, if all weights are zero, if `scale_bits` is larger
+    /// than 31, or if there are more symbols with non-zero weight than
+    /// `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::RansError;
+    ///
+    /// let model = Model::try_from_weights(&[0.5_f64, 0.25, 0.25, 0.0], 4).unwrap();
+    /// assert_eq!(model.freqs(), [8, 4, 4, 0]);
+    ///
+    /// let model = Model::try_from_weights(&[1.0_f32, 1e-30], 8).unwrap();
+    /// assert_eq!(model.freqs(), [255, 1]);
+    ///
+    /// assert_eq!(
+    ///     Model::try_from_weights(&[1.0, f64::NAN], 8),
+    ///     Err(RansError::InvalidModel)
+    /// );
+    /// assert_eq!(
+    ///     Model::try_from_weights(&[1.0, 1.0, 1.0], 1),
+    ///     Err(RansError::InvalidModel)
+    /// );
+    /// ```
+    pub fn try_from_weights<T: Copy + Into<f64>>(
+        weights: &[T],
+        scale_bits: u32,
+    ) -> Result<Self, RansError> {
+        let weights: Vec<f64> = weights.iter().map(|&weight| weight.into()).collect();
+        if scale_bits > 31
+            || weights
+                .iter()
+                .any(|&weight| !weight.is_finite() || weight < 0.0)
+        {
+            return Err(RansError::InvalidModel);
+        }
+        let present = weights.iter().filter(|&&weight| weight > 0.0).count() as u64;
+        let total = 1_u64 << scale_bits;
+        if present == 0 || present > total {
+            return Err(RansError::InvalidModel);
+        }
+
+        // Dividing by the largest weight first keeps the sum finite
+        let max = weights.iter().copied().fold(0.0, f64::max);
+        let sum: f64 = weights.iter().map(|&weight| weight / max).sum();
+        let rest = total - present;
+
+        let mut freqs = Vec::with_capacity(weights.len());
+        let mut remainders = Vec::new();
+        let mut assigned = 0;
+        for (index, &weight) in weights.iter().enumerate() {
+            if weight == 0.0 {
+                freqs.push(0);
+                continue;
+            }
+
+            let scaled = (weight / max / sum * rest as f64).min(rest as f64);
+            let extra = scaled.floor();
+            remainders.push((scaled - extra, index));
+            freqs.push(1 + extra as u32);
+            assigned += extra as u64;
+        }
+
+        if assigned <= rest {
+            let leftover = (rest - assigned) as usize;
+            remainders.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+            for &(_, index) in remainders.iter().take(leftover) {
+                freqs[index] += 1;
+            }
+        } else {
+            // Only possible through floating-point rounding, and then by a
+            // tiny amount; take it from the most frequent symbols
+            for _ in 0..assigned - rest {
+                let largest = most_frequent(&freqs);
+                freqs[largest] -= 1;
+            }
+        }
+
+        Ok(Self::from_freqs(freqs, scale_bits))
+    }
+
+    /// Creates a new model from already normalized symbol frequencies.
+    ///
+    /// # Panics
+    /// Panics if `scale_bits` is larger than 31 or if the frequencies do not
+    /// sum up to exactly `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_freqs(vec![1, 2, 1], 2);
+    /// assert_eq!(model.cum_freqs(), [0, 1, 3]);
+    /// ```
+    #[must_use]
+    pub fn from_freqs(freqs: Vec<u32>, scale_bits: u32) -> Self {
+        assert!(scale_bits <= 31, "scale_bits must not be larger than 31");
+        let sum: u64 = freqs.iter().map(|&freq| u64::from(freq)).sum();
+        assert_eq!(
+            sum,
+            1 << scale_bits,
+            "frequencies must sum up to 1 << scale_bits"
+        );
+
+        let cum_freqs = freqs
+            .iter()
+            .scan(0, |acc, &freq| {
+                let cum_freq = *acc;
+                *acc += freq;
+                Some(cum_freq)
+            })
+            .collect();
+
+        Self {
+            scale_bits,
+            freqs,
+            cum_freqs,
+        }
+    }
+
+    /// Returns the number of bits used for frequency quantization.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 1], 8);
+    /// assert_eq!(model.scale_bits(), 8);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
+
+    /// Returns the number of symbols in this model, including the ones with
+    /// zero frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 0, 1], 8);
+    /// assert_eq!(model.len(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.freqs.len()
+    }
+
+    /// Returns whether this model contains no symbols. A model constructed
+    /// with any of the constructors is never empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1], 8);
+    /// assert!(!model.is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.freqs.is_empty()
+    }
+
+    /// Returns the normalized frequencies of all symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// assert_eq!(model.freqs(), [1, 3]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn freqs(&self) -> &[u32] {
+        &self.freqs
+    }
+
+    /// Returns the cumulative frequencies of all symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// assert_eq!(model.cum_freqs(), [0, 1]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn cum_freqs(&self) -> &[u32] {
+        &self.cum_freqs
+    }
+
+    /// Returns the normalized frequency of given symbol.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// assert_eq!(model.freq(1), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn freq(&self, symbol: usize) -> u32 {
+        self.freqs[symbol]
+    }
+
+    /// Returns the cumulative frequency of given symbol.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// assert_eq!(model.cum_freq(1), 1);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn cum_freq(&self, symbol: usize) -> u32 {
+        self.cum_freqs[symbol]
+    }
+
+    /// Returns the symbol whose range contains given cumulative frequency.
+    ///
+    /// This performs a binary search over the cumulative frequencies, finished
+    /// off with a vectorized linear scan (using AVX2 with the `avx2` feature
+    /// on x86, NEON on aarch64); for decoding, [`DecModel`] provides a
+    /// constant-time lookup instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[1, 0, 3], 2);
+    /// assert_eq!(model.symbol_for(0), 0);
+    /// assert_eq!(model.symbol_for(1), 2);
+    /// assert_eq!(model.symbol_for(3), 2);
+    /// ```
+    #[must_use]
+    pub fn symbol_for(&self, cum_freq: u32) -> usize {
+        debug_assert!(cum_freq < 1 << self.scale_bits);
+
+        // The last symbol starting at or before `cum_freq` is the one; this
+        // skips the zero-frequency symbols sharing the same start.
+        search::count_at_most(&self.cum_freqs, cum_freq) - 1
+    }
+
+    /// Returns the cost of encoding given symbol, in bits, i.e.
+    /// `scale_bits - log2(freq)`. Symbols with zero frequency can't be encoded
+    /// at all, so their cost is infinite.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_freqs(vec![1, 3, 0, 4], 3);
+    /// assert_eq!(model.cost_bits(0), 3.0);
+    /// assert_eq!(model.cost_bits(3), 1.0);
+    /// assert_eq!(model.cost_bits(2), f64::INFINITY);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn cost_bits(&self, symbol: usize) -> f64 {
+        f64::from(self.scale_bits) - f64::from(self.freqs[symbol]).log2()
+    }
+
+    /// Estimates the size, in bits, of encoding the symbols with given counts
+    /// using this model. This is the sum of the symbols' costs, which the
+    /// actual output of an encoder matches up to the final flush and the
+    /// renormalization granularity.
+    ///
+    /// The result is infinite if any of the symbols with non-zero count has
+    /// zero frequency in this model.
+    ///
+    /// # Panics
+    /// Panics if there are more counts than symbols in this model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_freqs(vec![1, 3, 0, 4], 3);
+    /// assert_eq!(model.estimate_size(&[2, 0, 0, 5]), 11.0);
+    /// assert_eq!(model.estimate_size(&[0, 0, 1]), f64::INFINITY);
+    /// ```
+    #[must_use]
+    pub fn estimate_size(&self, counts: &[u32]) -> f64 {
+        assert!(
+            counts.len() <= self.freqs.len(),
+            "more counts than symbols in the model"
+        );
+
+        counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(symbol, &count)| f64::from(count) * self.cost_bits(symbol))
+            .sum()
+    }
+
+    /// Returns the entropy of this model's distribution, in bits per symbol,
+    /// i.e. the average cost of the symbols encoded with this model if they
+    /// occur with the frequencies the model expects.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// assert_eq!(Model::from_freqs(vec![2, 0, 1, 1], 2).entropy(), 1.5);
+    /// assert_eq!(Model::from_freqs(vec![16], 4).entropy(), 0.0);
+    /// ```
+    #[must_use]
+    pub fn entropy(&self) -> f64 {
+        let total = f64::from(self.scale_bits).exp2();
+        (0..self.freqs.len())
+            .filter(|&symbol| self.freqs[symbol] > 0)
+            .map(|symbol| f64::from(self.freqs[symbol]) / total * self.cost_bits(symbol))
+            .sum()
+    }
+
+    /// Serializes this model into a compact byte representation, which can
+    /// be read back with [`Self::from_bytes()`].
+    ///
+    /// The frequencies are stored as variable-length differences between
+    /// consecutive non-zero frequencies, and runs of zero-frequency symbols
+    /// are run-length encoded, so sparse or smooth tables take only a few
+    /// bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[3, 1], 2);
+    /// assert_eq!(model.to_bytes(), [2, 2, 7, 4]);
+    ///
+    /// let mut counts = [0; 256];
+    /// counts[usize::from(b'a')..=usize::from(b'z')].fill(1);
+    /// let model = Model::from_counts(&counts, 12);
+    /// assert!(model.to_bytes().len() < 40);
+    /// ```
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = vec![self.scale_bits as u8];
+        varint::write_u64(&mut output, self.freqs.len() as u64);
+
+        let mut prev_freq = 0;
+        let mut zero_run = 0;
+        for &freq in &self.freqs {
+            if freq == 0 {
+                zero_run += 1;
+                continue;
+            }
+
+            if zero_run > 0 {
+                output.push(0);
+                varint::write_u64(&mut output, zero_run - 1);
+                zero_run = 0;
+            }
+            // 0 is reserved for zero runs; non-zero frequencies are stored as
+            // zigzag-encoded deltas plus one.
+            let delta = i64::from(freq) - i64::from(prev_freq);
+            varint::write_u64(&mut output, ((delta << 1) ^ (delta >> 63)) as u64 + 1);
+            prev_freq = freq;
+        }
+        if zero_run > 0 {
+            output.push(0);
+            varint::write_u64(&mut output, zero_run - 1);
+        }
+
+        output
+    }
+
+    /// Reads a model serialized with [`Self::to_bytes()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the data is truncated,
+    /// [`RansError::InvalidHeader`] if `scale_bits` is larger than 31, there
+    /// are more than 2<sup>24</sup> symbols or there is trailing data, or
+    /// [`RansError::InvalidModel`] if the frequencies are malformed or don't
+    /// sum up to `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::RansError;
+    ///
+    /// let model = Model::from_counts(&[0, 0, 5, 0, 3, 1, 0], 10);
+    /// let bytes = model.to_bytes();
+    /// assert_eq!(Model::from_bytes(&bytes), Ok(model));
+    /// assert_eq!(
+    ///     Model::from_bytes(&bytes[..bytes.len() - 1]),
+    ///     Err(RansError::UnexpectedEof)
+    /// );
+    /// ```
+    pub fn from_bytes(data: &[u8]) -> Result<Self, RansError> {
+        let mut pos = 0;
+        let model = Self::read_bytes(data, &mut pos)?;
+        if pos != data.len() {
+            return Err(RansError::InvalidHeader);
+        }
+
+        Ok(model)
+    }
+
+    /// Reads a model serialized with [`Self::to_bytes()`] from `data`
+    /// starting at `*pos` and advances `*pos` past it.
+    pub(crate) fn read_bytes(data: &[u8], pos: &mut usize) -> Result<Self, RansError> {
+        /// Limit on the number of symbols, so that malformed input can't
+        /// trigger huge allocations.
+        const MAX_SYMBOLS: u64 = 1 << 24;
+
+        let scale_bits = u32::from(*data.get(*pos).ok_or(RansError::UnexpectedEof)?);
+        *pos += 1;
+        let len = varint::read_u64(data, pos)?;
+        if scale_bits > 31 || len > MAX_SYMBOLS {
+            return Err(RansError::InvalidHeader);
+        }
+
+        let len = len as usize;
+        let mut freqs = Vec::with_capacity(len);
+        let mut prev_freq = 0_i64;
+        let mut sum = 0_u64;
+        while freqs.len() < len {
+            let token = varint::read_u64(data, pos)?;
+            if token == 0 {
+                let run = varint::read_u64(data, pos)?;
+                if run >= (len - freqs.len()) as u64 {
+                    return Err(RansError::InvalidModel);
+                }
+                freqs.resize(freqs.len() + run as usize + 1, 0);
+                continue;
+            }
+
+            let zigzag = token - 1;
+            let delta = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+            let freq = prev_freq
+                .checked_add(delta)
+                .filter(|&freq| freq > 0 && freq <= 1 << scale_bits)
+                .ok_or(RansError::InvalidModel)?;
+            freqs.push(freq as u32);
+            prev_freq = freq;
+            sum += freq as u64;
+        }
+
+        if sum != 1 << scale_bits {
+            return Err(RansError::InvalidModel);
+        }
+        Ok(Self::from_freqs(freqs, scale_bits))
+    }
+}
+
+/// Algorithm used to normalize raw symbol counts into frequencies summing up
+/// to `1 << scale_bits`. See [`Model::from_counts_with_strategy()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NormalizationStrategy {
+    /// Scales the counts down, then gives the rounding leftover to the
+    /// symbols with the largest remainders. Symbols bumped from 0 to 1 are
+    /// paid for by the most frequent symbols.
+    #[default]
+    LargestRemainder,
+    /// Rounds each scaled count to the nearest integer, bumps zeros to 1 and
+    /// corrects the total on the most frequent symbol. This is what most
+    /// simple rANS examples do; it is cheap, but wastes space on skewed data.
+    Rounding,
+    /// Normalization modeled after the one in Finite State Entropy: rare
+    /// symbols get a frequency of 1, small probabilities are rounded with
+    /// a bias towards rounding up, and the leftover goes to the most frequent
+    /// symbol. Falls back to [`Self::LargestRemainder`] if that would take
+    /// too much from it.
+    Fast,
+    /// Finds the frequencies minimizing the encoded size of the counted data
+    /// (equivalently, the Kullback-Leibler divergence from the counts). This
+    /// is the slowest strategy.
+    Optimal,
+}
+
+fn normalize_largest_remainder(counts: &[u32], sum: u64, total: u64) -> Vec<u32> {
+    let mut freqs = Vec::with_capacity(counts.len());
+    let mut remainders = Vec::new();
+    let mut assigned = 0;
+    for (index, &count) in counts.iter().enumerate() {
+        if count == 0 {
+            freqs.push(0);
+            continue;
+        }
+
+        let scaled = u64::from(count) * total;
+        let freq = (scaled / sum).max(1);
+        remainders.push((scaled % sum, index));
+        freqs.push(freq as u32);
+        assigned += freq;
+    }
+
+    if assigned <= total {
+        // Give the rounding leftover to the symbols that lost the most.
+        let leftover = (total - assigned) as usize;
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for &(_, index) in remainders.iter().take(leftover) {
+            freqs[index] += 1;
+        }
+    } else {
+        // Symbols bumped from 0 to 1 made the total too large; take the
+        // excess back from the most frequent symbols.
+        take_excess(&mut freqs, assigned - total);
+    }
+
+    freqs
+}
+
+fn normalize_rounding(counts: &[u32], sum: u64, total: u64) -> Vec<u32> {
+    let mut freqs: Vec<u32> = counts
+        .iter()
+        .map(|&count| {
+            if count == 0 {
+                0
+            } else {
+                ((u64::from(count) * total + sum / 2) / sum).max(1) as u32
+            }
+        })
+        .collect();
+
+    let assigned: u64 = freqs.iter().map(|&freq| u64::from(freq)).sum();
+    if assigned < total {
+        let largest = most_frequent(&freqs);
+        freqs[largest] += (total - assigned) as u32;
+    } else {
+        take_excess(&mut freqs, assigned - total);
+    }
+
+    freqs
+}
+
+fn normalize_fast(counts: &[u32], sum: u64, total: u64) -> Vec<u32> {
+    // Rounding thresholds for scaled probabilities below 8, as fractions of
+    // 1 << 20; taken from the reference FSE implementation.
+    const ROUND_UP_THRESHOLDS: [u128; 8] = [
+        0, 473_195, 504_333, 520_860, 550_000, 700_000, 750_000, 830_000,
+    ];
+
+    let low_threshold = sum / total;
+    let mut freqs = Vec::with_capacity(counts.len());
+    let mut assigned = 0;
+    for &count in counts {
+        let count = u64::from(count);
+        let freq = if count == 0 {
+            0
+        } else if count <= low_threshold {
+            1
+        } else {
+            let scaled = count * total;
+            let mut freq = scaled / sum;
+            if freq < 8 {
+                let rest = u128::from(scaled % sum) << 20;
+                if rest > ROUND_UP_THRESHOLDS[freq as usize] * u128::from(sum) {
+                    freq += 1;
+                }
+            }
+            freq.max(1)
+        };
+        freqs.push(freq as u32);
+        assigned += freq;
+    }
+
+    let largest = most_frequent(&freqs);
+    if assigned <= total {
+        freqs[largest] += (total - assigned) as u32;
+    } else if assigned - total < u64::from(freqs[largest] / 2) {
+        freqs[largest] -= (assigned - total) as u32;
+    } else {
+        return normalize_largest_remainder(counts, sum, total);
+    }
+
+    freqs
+}
+
+fn normalize_optimal(counts: &[u32], sum: u64, total: u64) -> Vec<u32> {
+    // Cost change, in bits, of incrementing or decrementing the frequency of
+    // a symbol. The total cost is convex in each frequency, so moving single
+    // units between symbols while it lowers the total cost finds the optimum.
+    let gain = |index: usize, freqs: &[u32]| {
+        f64::from(counts[index]) * (1.0 / f64::from(freqs[index])).ln_1p()
+    };
+    let loss = |index: usize, freqs: &[u32]| {
+        -f64::from(counts[index]) * (-1.0 / f64::from(freqs[index])).ln_1p()
+    };
+
+    let mut freqs = normalize_largest_remainder(counts, sum, total);
+    loop {
+        let best_inc = (0..freqs.len())
+            .filter(|&index| counts[index] > 0)
+            .map(|index| (gain(index, &freqs), index))
+            .fold(None, |best: Option<(f64, usize)>, candidate| match best {
+                Some(best) if best.0 >= candidate.0 => Some(best),
+                _ => Some(candidate),
+            });
+        let best_dec = (0..freqs.len())
+            .filter(|&index| freqs[index] > 1)
+            .map(|index| (loss(index, &freqs), index))
+            .fold(None, |best: Option<(f64, usize)>, candidate| match best {
+                Some(best) if best.0 <= candidate.0 => Some(best),
+                _ => Some(candidate),
+            });
+
+        match (best_inc, best_dec) {
+            (Some((gain, inc)), Some((loss, dec))) if inc != dec && gain > loss => {
+                freqs[inc] += 1;
+                freqs[dec] -= 1;
+            }
+            _ => break,
+        }
+    }
+
+    freqs
+}
+
+/// Returns the index of the symbol with the largest frequency; lowest index
+/// wins on ties.
+fn most_frequent(freqs: &[u32]) -> usize {
+    let mut best = 0;
+    for (index, &freq) in freqs.iter().enumerate() {
+        if freq > freqs[best] {
+            best = index;
+        }
+    }
+    best
+}
+
+/// Decreases the frequencies by `excess` in total, one unit at a time,
+/// starting from the most frequent symbols and never going below 1.
+fn take_excess(freqs: &mut [u32], mut excess: u64) {
+    let mut order: Vec<usize> = (0..freqs.len()).filter(|&i| freqs[i] > 1).collect();
+    order.sort_by(|&a, &b| freqs[b].cmp(&freqs[a]).then(a.cmp(&b)));
+    while excess > 0 {
+        for &index in &order {
+            if excess == 0 {
+                break;
+            }
+            if freqs[index] > 1 {
+                freqs[index] -= 1;
+                excess -= 1;
+            }
+        }
+    }
+}
+
+/// Set of encoder symbols built from a [`Model`].
+#[derive(Debug, Clone)]
+pub struct EncModel<S> {
+    scale_bits: u32,
+    symbols: Vec<S>,
+}
+
+impl<S: RansEncSymbol> EncModel<S> {
+    /// Creates encoder symbols for every symbol of given model. Symbols with
+    /// zero frequency get placeholder values and must never be encoded.
+    ///
+    /// # Panics
+    /// Panics if the model's `scale_bits` is larger than
+    /// [`RansEncSymbol::MAX_SCALE_BITS`] of `S`. Use [`Self::try_new()`] to
+    /// get an error instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::{RansEncoder, RansEncoderMulti};
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put(enc_model.symbol(1));
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [171, 170, 170, 0]);
+    /// ```
+    #[must_use]
+    pub fn new(model: &Model) -> Self {
+        let scale_bits = model.scale_bits();
+        assert_scale_bits(scale_bits, S::MAX_SCALE_BITS);
+        let symbols = model
+            .cum_freqs()
+            .iter()
+            .zip(model.freqs())
+            .map(|(&cum_freq, &freq)| S::new(cum_freq, freq, scale_bits))
+            .collect();
+
+        Self {
+            scale_bits,
+            symbols,
+        }
+    }
+
+    /// Creates encoder symbols for every symbol of given model, checking
+    /// that the model can be coded with the encoder symbol type `S`.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidSymbol`] if the model's `scale_bits` is
+    /// larger than [`RansEncSymbol::MAX_SCALE_BITS`] of `S`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_encoder::B64RansEncSymbol;
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::model::{EncModel, Model};
+    /// use rans::RansError;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 20);
+    /// assert!(EncModel::<B64RansEncSymbol>::try_new(&model).is_ok());
+    /// assert_eq!(
+    ///     EncModel::<ByteRansEncSymbol>::try_new(&model).unwrap_err(),
+    ///     RansError::InvalidSymbol
+    /// );
+    /// ```
+    pub fn try_new(model: &Model) -> Result<Self, RansError> {
+        for (&cum_freq, &freq) in model.cum_freqs().iter().zip(model.freqs()) {
+            if freq != 0 {
+                check_symbol(cum_freq, freq, model.scale_bits(), S::MAX_SCALE_BITS)?;
+            }
+        }
+
+        Ok(Self::new(model))
+    }
+
+    /// Returns whether the symbol at given index can be encoded with this
+    /// model, i.e. whether it's a part of the model and has non-zero
+    /// frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::model::{EncModel, Model};
+    ///
+    /// let model = Model::from_counts(&[1, 0, 3], 4);
+    /// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    /// assert!(enc_model.contains(0));
+    /// assert!(!enc_model.contains(1));
+    /// assert!(!enc_model.contains(3));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn contains(&self, index: usize) -> bool {
+        index < self.symbols.len() && self.symbols[index].freq() != 0
+    }
+}
+
+impl<S> EncModel<S> {
+    /// Returns the number of bits used for frequency quantization.
+    #[must_use]
+    #[inline]
+    pub fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
+
+    /// Returns the number of symbols in this model.
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns whether this model contains no symbols.
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Returns the encoder symbol at given index.
+    #[must_use]
+    #[inline]
+    pub fn symbol(&self, index: usize) -> &S {
+        &self.symbols[index]
+    }
+}
+
+/// Structure used by [`DecModel`] to map cumulative frequencies back to
+/// symbols, trading memory for lookup speed. See [`DecModel::with_lookup()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LookupKind {
+    /// Table with an entry for every one of the `1 << scale_bits` cumulative
+    /// frequencies. This is the fastest lookup, but the table takes
+    /// `4 << scale_bits` bytes, which is 256 KiB for 16-bit `scale_bits`, so
+    /// with many models in use the tables no longer fit in the cache.
+    #[default]
+    Dense,
+    /// Binary search over the cumulative frequencies of the symbols. This
+    /// needs no memory besides the symbols themselves, but takes
+    /// `O(log(num_symbols))` steps.
+    BinarySearch,
+    /// Alias table, with two entries per bucket and a power-of-two number of
+    /// buckets at least as large as the number of symbols. The lookup is a
+    /// single comparison, but the symbols are laid out differently than in
+    /// regular encoding, so this can only decode data put with the encoders'
+    /// `put_alias_at()` methods using an [`AliasTable`] built for the same
+    /// model.
+    Alias,
+}
+
+#[derive(Debug, Clone)]
+enum LookupTable<S> {
+    Dense(Vec<u32>),
+    BinarySearch(Vec<u32>),
+    Alias {
+        bucket_shift: u32,
+        dividers: Vec<u32>,
+        /// Symbol index and the decoder symbol, biased to the slots of the
+        /// part, for both parts of every bucket
+        parts: Vec<(u32, S)>,
+    },
+}
+
+/// Set of decoder symbols built from a [`Model`], along with a lookup table
+/// mapping cumulative frequencies back to symbol indices.
+#[derive(Debug, Clone)]
+pub struct DecModel<S> {
+    scale_bits: u32,
+    symbols: Vec<S>,
+    table: LookupTable<S>,
+}
+
+impl<S: RansDecSymbol> DecModel<S> {
+    /// Creates decoder symbols and the lookup table for given model.
+    ///
+    /// The lookup table has `1 << scale_bits` entries.
+    ///
+    /// # Panics
+    /// Panics if the model's `scale_bits` is larger than
+    /// [`RansDecSymbol::MAX_SCALE_BITS`] of `S`. Use [`Self::try_new()`] to
+    /// get an error instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::RansDecoder;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder = ByteRansDecoder::new([171, 170, 170, 0]);
+    /// let cum_freq = decoder.get(dec_model.scale_bits());
+    /// let index = dec_model.symbol_index(cum_freq);
+    /// assert_eq!(index, 1);
+    /// decoder.advance(dec_model.symbol(index), dec_model.scale_bits());
+    /// ```
+    #[must_use]
+    pub fn new(model: &Model) -> Self {
+        Self::with_lookup(model, LookupKind::Dense)
+    }
+
+    /// Creates decoder symbols and a lookup table of given kind for given
+    /// model.
+    ///
+    /// # Panics
+    /// Panics if the model's `scale_bits` is larger than
+    /// [`RansDecSymbol::MAX_SCALE_BITS`] of `S`. With [`LookupKind::Alias`],
+    /// also panics if the model is empty or has more symbols than
+    /// `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::model::{DecModel, LookupKind, Model};
+    /// use rans::RansDecoder;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 2);
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::with_lookup(&model, LookupKind::BinarySearch);
+    /// assert_eq!(dec_model.lookup_kind(), LookupKind::BinarySearch);
+    ///
+    /// let mut decoder = ByteRansDecoder::new([171, 170, 170, 0]);
+    /// assert_eq!(decoder.decode_symbol(&dec_model), 1);
+    /// ```
+    #[must_use]
+    pub fn with_lookup(model: &Model, kind: LookupKind) -> Self {
+        let scale_bits = model.scale_bits();
+        assert_scale_bits(scale_bits, S::MAX_SCALE_BITS);
+        let symbols = model
+            .cum_freqs()
+            .iter()
+            .zip(model.freqs())
+            .map(|(&cum_freq, &freq)| S::new(cum_freq, freq))
+            .collect();
+
+        let table = match kind {
+            LookupKind::Dense => {
+                let mut cum_freq_to_symbol = Vec::with_capacity(1 << scale_bits);
+                for (index, &freq) in model.freqs().iter().enumerate() {
+                    cum_freq_to_symbol.extend(std::iter::repeat(index as u32).take(freq as usize));
+                }
+                LookupTable::Dense(cum_freq_to_symbol)
+            }
+            LookupKind::BinarySearch => LookupTable::BinarySearch(model.cum_freqs().to_vec()),
+            LookupKind::Alias => alias_lookup_table(model),
+        };
+
+        Self {
+            scale_bits,
+            symbols,
+            table,
+        }
+    }
+
+    /// Creates decoder symbols and the lookup table for given model,
+    /// checking that the model can be coded with the decoder symbol type `S`.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidSymbol`] if the model's `scale_bits` is
+    /// larger than [`RansDecSymbol::MAX_SCALE_BITS`] of `S`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::b64_decoder::B64RansDecSymbol;
+    /// use rans::byte_decoder::ByteRansDecSymbol;
+    /// use rans::model::{DecModel, Model};
+    /// use rans::RansError;
+    ///
+    /// let model = Model::from_counts(&[1, 3], 20);
+    /// assert!(DecModel::<B64RansDecSymbol>::try_new(&model).is_ok());
+    /// assert_eq!(
+    ///     DecModel::<ByteRansDecSymbol>::try_new(&model).unwrap_err(),
+    ///     RansError::InvalidSymbol
+    /// );
+    /// ```
+    pub fn try_new(model: &Model) -> Result<Self, RansError> {
+        for (&cum_freq, &freq) in model.cum_freqs().iter().zip(model.freqs()) {
+            if freq != 0 {
+                check_symbol(cum_freq, freq, model.scale_bits(), S::MAX_SCALE_BITS)?;
+            }
+        }
+
+        Ok(Self::new(model))
+    }
+}
+
+fn alias_lookup_table<S: RansDecSymbol>(model: &Model) -> LookupTable<S> {
+    let table = AliasTable::new(model);
+    let bucket_shift = table.bucket_shift();
+    let bucket_size = 1 << bucket_shift;
+
+    let mut dividers = Vec::new();
+    let mut parts = Vec::new();
+    for (bucket, (divider, symbols, biases)) in table.buckets().enumerate() {
+        let bucket_start = (bucket as u32) << bucket_shift;
+        dividers.push(divider);
+
+        // An empty part is never looked up, so it just repeats the other one
+        let empty = [
+            divider == bucket_start,
+            divider == bucket_start + bucket_size,
+        ];
+        for (part, &is_empty) in empty.iter().enumerate() {
+            let part = if is_empty { 1 - part } else { part };
+            let index = symbols[part];
+            let symbol = S::new(biases[part], model.freqs()[index as usize]);
+            parts.push((index, symbol));
+        }
+    }
+
+    LookupTable::Alias {
+        bucket_shift,
+        dividers,
+        parts,
+    }
+}
+
+impl<S> DecModel<S> {
+    /// Returns the number of bits used for frequency quantization.
+    #[must_use]
+    #[inline]
+    pub fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
+
+    /// Returns the number of symbols in this model.
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns whether this model contains no symbols.
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Returns the decoder symbol at given index.
+    #[must_use]
+    #[inline]
+    pub fn symbol(&self, index: usize) -> &S {
+        &self.symbols[index]
+    }
+
+    /// Returns the kind of the lookup table of this model.
+    #[must_use]
+    #[inline]
+    pub fn lookup_kind(&self) -> LookupKind {
+        match self.table {
+            LookupTable::Dense(_) => LookupKind::Dense,
+            LookupTable::BinarySearch(_) => LookupKind::BinarySearch,
+            LookupTable::Alias { .. } => LookupKind::Alias,
+        }
+    }
+
+    /// Returns the index of the symbol whose range contains given cumulative
+    /// frequency, as returned by the decoder's `get` methods.
+    ///
+    /// With [`LookupKind::Alias`], the decoder has to be advanced with the
+    /// symbol returned by [`Self::lookup()`] instead of [`Self::symbol()`].
+    #[must_use]
+    #[inline]
+    pub fn symbol_index(&self, cum_freq: u32) -> usize {
+        self.lookup(cum_freq).0
+    }
+
+    /// Returns the index of the symbol whose range contains given cumulative
+    /// frequency, as returned by the decoder's `get` methods, along with the
+    /// decoder symbol to advance the decoder with.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::{DecModel, LookupKind, Model};
+    /// use rans::{RansDecoder, RansEncoder, RansEncoderMulti};
+    ///
+    /// let model = Model::from_counts(&[1, 2, 5], 4);
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// encoder.put_alias(&AliasTable::new(&model), 1);
+    /// encoder.flush();
+    ///
+    /// let dec_model = DecModel::<ByteRansDecSymbol>::with_lookup(&model, LookupKind::Alias);
+    /// let mut decoder = ByteRansDecoder::new(encoder.data());
+    /// let (index, symbol) = dec_model.lookup(decoder.get(dec_model.scale_bits()));
+    /// assert_eq!(index, 1);
+    /// decoder.advance(symbol, dec_model.scale_bits());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn lookup(&self, cum_freq: u32) -> (usize, &S) {
+        let index = match &self.table {
+            LookupTable::Dense(cum_freq_to_symbol) => {
+                cum_freq_to_symbol[cum_freq as usize] as usize
+            }
+            LookupTable::BinarySearch(cum_freqs) => search::count_at_most(cum_freqs, cum_freq) - 1,
+            LookupTable::Alias {
+                bucket_shift,
+                dividers,
+                parts,
+            } => {
+                let bucket = (cum_freq >> bucket_shift) as usize;
+                let (index, symbol) =
+                    &parts[2 * bucket + usize::from(cum_freq >= dividers[bucket])];
+                return (*index as usize, symbol);
+            }
+        };
+        (index, &self.symbols[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::b64_decoder::{B64RansDecSymbol, B64RansDecoder};
+    use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoder};
+    use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    use crate::lookup::AliasTable;
+    use crate::math::kl_divergence;
+    use crate::model::{
+        DecModel, EncModel, LookupKind, Model, NormalizationStrategy, DEFAULT_FIT_TOLERANCE,
+    };
+    use crate::{
+        RansDecSymbol, RansDecoder, RansEncSymbol, RansEncoder, RansEncoderMulti, RansError,
+    };
+
+    #[test]
+    fn test_from_counts_sums_to_total() {
+        let counts = [1, 1000, 0, 3, 7, 0, 123456, 1, 1];
+        let model = Model::from_counts(&counts, 12);
+
+        assert_eq!(model.freqs().iter().sum::<u32>(), 1 << 12);
+        for (&count, &freq) in counts.iter().zip(model.freqs()) {
+            assert_eq!(count == 0, freq == 0);
+        }
+    }
+
+    #[test]
+    fn test_strategies_valid() {
+        let strategies = [
+            NormalizationStrategy::LargestRemainder,
+            NormalizationStrategy::Rounding,
+            NormalizationStrategy::Fast,
+            NormalizationStrategy::Optimal,
+        ];
+        let inputs: [(&[u32], u32); 4] = [
+            (&[1, 1000, 0, 3, 7, 0, 123456, 1, 1], 12),
+            (&[1, 1000, 1, 1], 2),
+            (&[5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 1000000], 4),
+            (&[u32::MAX, 1, u32::MAX, 0, 2], 31),
+        ];
+
+        for strategy in strategies {
+            for &(counts, scale_bits) in &inputs {
+                let model = Model::from_counts_with_strategy(counts, scale_bits, strategy);
+
+                assert_eq!(
+                    model.freqs().iter().map(|&f| u64::from(f)).sum::<u64>(),
+                    1 << scale_bits
+                );
+                for (&count, &freq) in counts.iter().zip(model.freqs()) {
+                    assert_eq!(count == 0, freq == 0, "{strategy:?}, {counts:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_optimal_strategy_is_smallest() {
+        let counts: Vec<u32> = (0..64).map(|i| 1 + 100000 / (1 + i * i)).collect();
+
+        let size = |strategy| {
+            Model::from_counts_with_strategy(&counts, 10, strategy).estimate_size(&counts)
+        };
+        let optimal = size(NormalizationStrategy::Optimal);
+        assert!(optimal <= size(NormalizationStrategy::LargestRemainder));
+        assert!(optimal < size(NormalizationStrategy::Rounding));
+        assert!(optimal <= size(NormalizationStrategy::Fast));
+    }
+
+    #[test]
+    fn test_train_smoothing() {
+        let model = Model::train::<&[u8]>(&[], 8);
+
+        assert_eq!(model.freqs(), [1; 256]);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let models = [
+            Model::from_counts(&[1], 0),
+            Model::from_counts(&[0, 0, 0, 1, 0, 0], 31),
+            Model::from_counts(&[1, 1000, 0, 3, 7, 0, 123456, 1, 1], 12),
+            Model::train(&["The quick brown fox jumps over the lazy dog"], 12),
+        ];
+
+        for model in models {
+            assert_eq!(Model::from_bytes(&model.to_bytes()), Ok(model));
+        }
+    }
+
+    #[test]
+    fn test_bytes_compact() {
+        let counts: Vec<u32> = (0..256).map(|i| 1000 / (1 + (i % 64))).collect();
+        let model = Model::from_counts(&counts, 12);
+
+        assert!(model.to_bytes().len() < 300);
+    }
+
+    #[test]
+    fn test_from_bytes_invalid() {
+        // Frequencies don't sum up to 1 << scale_bits
+        assert_eq!(
+            Model::from_bytes(&[2, 2, 7, 2]),
+            Err(RansError::InvalidModel)
+        );
+        // Zero run longer than the number of symbols
+        assert_eq!(
+            Model::from_bytes(&[2, 2, 0, 2]),
+            Err(RansError::InvalidModel)
+        );
+        // Negative frequency
+        assert_eq!(Model::from_bytes(&[2, 1, 2]), Err(RansError::InvalidModel));
+        assert_eq!(Model::from_bytes(&[32, 0]), Err(RansError::InvalidHeader));
+        assert_eq!(
+            Model::from_bytes(&[2, 2, 7, 4, 0]),
+            Err(RansError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn test_from_counts_all_slots_used() {
+        let model = Model::from_counts(&[1, 1000, 1, 1], 2);
+
+        assert_eq!(model.freqs(), [1, 1, 1, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "too many distinct symbols")]
+    fn test_from_counts_too_many_symbols() {
+        let _ = Model::from_counts(&[1, 1, 1, 1, 1], 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "non-zero count")]
+    fn test_from_counts_all_zero() {
+        let _ = Model::from_counts(&[0, 0], 2);
+    }
+
+    #[test]
+    fn test_from_probabilities_tiny() {
+        let model = Model::from_probabilities(&[1.0, 1e-30, 0.0], 8);
+
+        assert_eq!(model.freqs(), [255, 1, 0]);
+    }
+
+    #[test]
+    fn test_fit() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(98);
+        for _ in 0..50 {
+            let len = rng.gen_range(1..200);
+            let counts: Vec<u32> = (0..len)
+                .map(|_| rng.gen_range(0..1000) * rng.gen_range(0..2))
+                .chain([1])
+                .collect();
+
+            let model = Model::fit(&counts, 20);
+            if model.scale_bits() < 20 {
+                assert!(kl_divergence(&counts, &model) <= DEFAULT_FIT_TOLERANCE);
+            }
+            if model.scale_bits() > 1 {
+                let coarser = Model::from_counts(&counts, model.scale_bits() - 1);
+                assert!(
+                    counts.iter().filter(|&&count| count > 0).count()
+                        > 1 << (model.scale_bits() - 1)
+                        || kl_divergence(&counts, &coarser) > DEFAULT_FIT_TOLERANCE
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fit_limits() {
+        assert_eq!(Model::fit(&[0, 7], 16).scale_bits(), 1);
+        assert_eq!(Model::fit(&[1; 8], 16).scale_bits(), 3);
+        assert_eq!(Model::fit(&[1_000_000, 1], 6).scale_bits(), 6);
+        assert_eq!(
+            Model::fit_with_tolerance(&[1_000_000, 1], 31, 0.0).scale_bits(),
+            31
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "too many distinct symbols")]
+    fn test_fit_too_many_symbols() {
+        let _ = Model::fit(&[1; 5], 2);
+    }
+
+    #[test]
+    fn test_try_from_weights_exact() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(96);
+        for scale_bits in [1, 4, 8, 12, 16, 24, 31] {
+            for _ in 0..20 {
+                let len = rng.gen_range(1..=300.min(1_usize << scale_bits));
+                let weights: Vec<f64> = (0..len)
+                    .map(|_| match rng.gen_range(0..4) {
+                        0 => 0.0,
+                        1 => rng.gen::<f64>() * 1e-300,
+                        2 => rng.gen::<f64>() * 1e300,
+                        _ => rng.gen::<f64>(),
+                    })
+                    .chain([1.0])
+                    .collect();
+                if weights.iter().filter(|&&weight| weight > 0.0).count() > 1_usize << scale_bits {
+                    continue;
+                }
+
+                let model = Model::try_from_weights(&weights, scale_bits).unwrap();
+                for (&weight, &freq) in weights.iter().zip(model.freqs()) {
+                    assert_eq!(weight > 0.0, freq > 0);
+                }
+                assert_eq!(
+                    model
+                        .freqs()
+                        .iter()
+                        .map(|&freq| u64::from(freq))
+                        .sum::<u64>(),
+                    1 << scale_bits
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_from_weights_invalid() {
+        assert_eq!(
+            Model::try_from_weights(&[0.0_f32, 0.0], 8),
+            Err(RansError::InvalidModel)
+        );
+        assert_eq!(
+            Model::try_from_weights(&[1.0, -0.5], 8),
+            Err(RansError::InvalidModel)
+        );
+        assert_eq!(
+            Model::try_from_weights(&[1.0, f64::INFINITY], 8),
+            Err(RansError::InvalidModel)
+        );
+        assert_eq!(
+            Model::try_from_weights(&[1.0_f32], 32),
+            Err(RansError::InvalidModel)
+        );
+        assert_eq!(
+            Model::try_from_weights::<f32>(&[], 8),
+            Err(RansError::InvalidModel)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "sum up to")]
+    fn test_from_freqs_invalid_sum() {
+        let _ = Model::from_freqs(vec![1, 2], 2);
+    }
+
+    #[test]
+    fn test_symbol_for() {
+        let model = Model::from_freqs(vec![0, 2, 0, 0, 1, 1, 0], 2);
+
+        let symbols: Vec<usize> = (0..4).map(|x| model.symbol_for(x)).collect();
+        assert_eq!(symbols, [1, 1, 4, 5]);
+    }
+
+    #[test]
+    fn test_estimate_size_matches_encoded_size() {
+        let counts = [300, 1, 40, 0, 2000, 17];
+        let model = Model::from_counts(&counts, 12);
+        let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+
+        let mut encoder = ByteRansEncoder::new(1 << 16);
+        for (symbol, &count) in counts.iter().enumerate() {
+            for _ in 0..count {
+                encoder.put(enc_model.symbol(symbol));
+            }
+        }
+        encoder.flush();
+
+        // The encoder can waste up to the state size (flush) plus a byte
+        let estimate = model.estimate_size(&counts) / 8.0;
+        let actual = encoder.data().len() as f64;
+        assert!(estimate <= actual && actual <= estimate + 5.0);
+    }
+
+    #[test]
+    fn test_dec_model_lookup_matches_model() {
+        let model = Model::from_counts(&[5, 0, 1, 17, 0, 2, 9], 6);
+        let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+
+        for cum_freq in 0..1 << 6 {
+            assert_eq!(dec_model.symbol_index(cum_freq), model.symbol_for(cum_freq));
+        }
+    }
+
+    #[test]
+    fn test_dec_model_binary_search_matches_dense() {
+        let model = Model::from_counts(&[0, 5, 0, 1, 17, 0, 2, 9, 0], 6);
+        let dense = DecModel::<ByteRansDecSymbol>::new(&model);
+        let binary_search =
+            DecModel::<ByteRansDecSymbol>::with_lookup(&model, LookupKind::BinarySearch);
+        assert_eq!(dense.lookup_kind(), LookupKind::Dense);
+        assert_eq!(binary_search.lookup_kind(), LookupKind::BinarySearch);
+
+        for cum_freq in 0..1 << 6 {
+            let index = dense.symbol_index(cum_freq);
+            assert_eq!(binary_search.symbol_index(cum_freq), index);
+            assert!(std::ptr::eq(
+                binary_search.lookup(cum_freq).1,
+                binary_search.symbol(index)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_dec_model_alias_matches_alias_table() {
+        let model = Model::from_counts(&[5, 0, 1, 17, 0, 2, 9], 6);
+        let table = AliasTable::new(&model);
+        let dec_model = DecModel::<ByteRansDecSymbol>::with_lookup(&model, LookupKind::Alias);
+        assert_eq!(dec_model.lookup_kind(), LookupKind::Alias);
+
+        for slot in 0..1 << 6 {
+            let (index, offset) = table.lookup(slot);
+            assert_eq!(dec_model.symbol_index(slot), index);
+            assert_eq!(
+                *dec_model.lookup(slot).1,
+                ByteRansDecSymbol::new(slot - offset, table.freq(index))
+            );
+        }
+    }
+
+    #[test]
+    fn test_dec_model_alias_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        let model = Model::from_counts(&[3, 10, 58, 0, 34, 41, 17, 55, 38], 16);
+        let table = AliasTable::new(&model);
+        let symbols: Vec<usize> = (0..1000)
+            .map(|_| loop {
+                let symbol = rng.gen_range(0..model.len());
+                if model.freq(symbol) != 0 {
+                    break symbol;
+                }
+            })
+            .collect();
+
+        let mut encoder = ByteRansEncoder::new(4096);
+        for &symbol in symbols.iter().rev() {
+            encoder.put_alias(&table, symbol);
+        }
+        encoder.flush();
+
+        let dec_model = DecModel::<ByteRansDecSymbol>::with_lookup(&model, LookupKind::Alias);
+        let mut decoder = ByteRansDecoder::new(encoder.data());
+        for &symbol in &symbols {
+            assert_eq!(decoder.decode_symbol(&dec_model), symbol);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_byte() {
+        round_trip::<ByteRansEncSymbol, ByteRansDecSymbol, _, _>(
+            ByteRansEncoder::new(1024),
+            ByteRansDecoder::new,
+        );
+    }
+
+    #[test]
+    fn test_round_trip_b64() {
+        round_trip::<B64RansEncSymbol, B64RansDecSymbol, _, _>(B64RansEncoder::new(1024), |data| {
+            B64RansDecoder::new(data)
+        });
+    }
+
+    fn round_trip<ES, DS, E, D>(mut encoder: E, decoder: impl Fn(Vec<u8>) -> D)
+    where
+        ES: RansEncSymbol,
+        DS: RansDecSymbol,
+        E: RansEncoder<Symbol = ES>,
+        D: RansDecoder<Symbol = DS>,
+    {
+        let model = Model::from_counts(&[3, 10, 58, 0, 34, 41, 17, 55, 38], 10);
+        let enc_model = EncModel::<ES>::new(&model);
+        let symbols = [0, 1, 2, 4, 5, 6, 7, 8, 2, 2, 2, 5, 4, 2, 7, 8, 8, 6, 5];
+
+        for &symbol in symbols.iter().rev() {
+            encoder.put(enc_model.symbol(symbol));
+        }
+        encoder.flush();
+
+        for kind in [LookupKind::Dense, LookupKind::BinarySearch] {
+            let dec_model = DecModel::<DS>::with_lookup(&model, kind);
+            let mut decoder = decoder(encoder.data().to_owned());
+            for &symbol in &symbols {
+                assert_eq!(decoder.decode_symbol(&dec_model), symbol);
+            }
+        }
+    }
+}
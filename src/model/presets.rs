@@ -0,0 +1,206 @@
+use crate::model::Model;
+use crate::transform::zigzag_decode;
+
+/// Creates a model of a (truncated) geometric distribution: symbol `k` has
+/// probability proportional to `(1 - p)^k`.
+///
+/// `p` is the probability of symbol 0 in the untruncated distribution; the
+/// larger it is, the faster the probabilities fall off.
+///
+/// # Panics
+/// Panics if `p` is not in the `(0, 1)` range, if `n_symbols` is 0 or larger
+/// than `1 << scale_bits`, or if `scale_bits` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::model::presets::geometric;
+///
+/// let model = geometric(0.5, 4, 4);
+/// assert_eq!(model.freqs(), [9, 4, 2, 1]);
+/// ```
+#[must_use]
+pub fn geometric(p: f64, n_symbols: usize, scale_bits: u32) -> Model {
+    assert!(p > 0.0 && p < 1.0, "p must be in the (0, 1) range");
+
+    let ratio = 1.0 - p;
+    from_weights((0..n_symbols).map(|k| ratio.powi(k as i32)), scale_bits)
+}
+
+/// Creates a model of a (truncated, discrete) Laplacian distribution centered
+/// at zero: signed value `x` has probability proportional to
+/// `exp(-|x| / scale)`.
+///
+/// The symbols represent signed values mapped with
+/// [`zigzag_encode()`](crate::transform::zigzag_encode), i.e. symbols 0, 1,
+/// 2, 3, 4, ... stand for values 0, -1, 1, -2, 2, ..., which makes the model
+/// suitable for coding prediction residuals.
+///
+/// # Panics
+/// Panics if `scale` is not positive and finite, if `n_symbols` is 0 or larger
+/// than `1 << scale_bits`, or if `scale_bits` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::model::presets::laplacian;
+/// use rans::transform::zigzag_encode;
+///
+/// let model = laplacian(2.0, 64, 12);
+/// let freq = |value| model.freq(zigzag_encode(value) as usize);
+/// assert_eq!(freq(-3), freq(3));
+/// assert!(freq(0) > freq(1));
+/// ```
+#[must_use]
+pub fn laplacian(scale: f64, n_symbols: usize, scale_bits: u32) -> Model {
+    assert!(
+        scale > 0.0 && scale.is_finite(),
+        "scale must be positive and finite"
+    );
+
+    from_weights(
+        (0..n_symbols).map(|symbol| {
+            let value = f64::from(zigzag_decode(symbol as u32));
+            (-value.abs() / scale).exp()
+        }),
+        scale_bits,
+    )
+}
+
+/// Creates a model of a (truncated) Zipf distribution: symbol `k` has
+/// probability proportional to `1 / (k + 1)^exponent`.
+///
+/// An exponent of 0 gives a uniform distribution.
+///
+/// # Panics
+/// Panics if `exponent` is negative or not finite, if `n_symbols` is 0 or
+/// larger than `1 << scale_bits`, or if `scale_bits` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::model::presets::zipf;
+///
+/// let model = zipf(1.0, 4, 8);
+/// assert!(model.freq(0) > model.freq(1));
+/// assert_eq!(zipf(0.0, 4, 8).freqs(), [64, 64, 64, 64]);
+/// ```
+#[must_use]
+pub fn zipf(exponent: f64, n_symbols: usize, scale_bits: u32) -> Model {
+    assert!(
+        exponent >= 0.0 && exponent.is_finite(),
+        "exponent must be non-negative and finite"
+    );
+
+    from_weights(
+        (0..n_symbols).map(|k| (k as f64 + 1.0).powf(-exponent)),
+        scale_bits,
+    )
+}
+
+/// Quantizes the weights to integer counts, making sure that each of them is
+/// non-zero, and normalizes them to a model.
+fn from_weights<I: Iterator<Item = f64>>(weights: I, scale_bits: u32) -> Model {
+    const RESOLUTION: f64 = (1 << 24) as f64;
+
+    let weights: Vec<f64> = weights.collect();
+    assert!(!weights.is_empty(), "n_symbols must not be 0");
+    let max = weights.iter().copied().fold(0.0_f64, f64::max);
+
+    let counts: Vec<u32> = weights
+        .iter()
+        .map(|&weight| ((weight / max * RESOLUTION).round() as u32).max(1))
+        .collect();
+
+    Model::from_counts(&counts, scale_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::presets::{geometric, laplacian, zipf};
+    use crate::model::Model;
+    use crate::transform::zigzag_encode;
+
+    fn assert_valid(model: &Model, n_symbols: usize) {
+        assert_eq!(model.len(), n_symbols);
+        assert_eq!(
+            model
+                .freqs()
+                .iter()
+                .map(|&freq| u64::from(freq))
+                .sum::<u64>(),
+            1 << model.scale_bits()
+        );
+        assert!(model.freqs().iter().all(|&freq| freq > 0));
+    }
+
+    #[test]
+    fn test_geometric() {
+        let model = geometric(0.1, 100, 14);
+
+        assert_valid(&model, 100);
+        assert!(model.freqs()[..40]
+            .windows(2)
+            .all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn test_geometric_steep_tail() {
+        let model = geometric(0.99, 256, 12);
+
+        assert_valid(&model, 256);
+        assert_eq!(model.freq(255), 1);
+    }
+
+    #[test]
+    fn test_laplacian_symmetric() {
+        let model = laplacian(4.0, 255, 14);
+
+        assert_valid(&model, 255);
+        for value in 1..=127 {
+            assert_eq!(
+                model.freq(zigzag_encode(value) as usize),
+                model.freq(zigzag_encode(-value) as usize)
+            );
+        }
+        assert!(model.freq(0) > model.freq(zigzag_encode(10) as usize));
+    }
+
+    #[test]
+    fn test_zipf() {
+        let model = zipf(1.2, 1000, 16);
+
+        assert_valid(&model, 1000);
+        assert!(model.freqs().windows(2).all(|pair| pair[0] >= pair[1]));
+        assert!(model.freq(0) > 2 * model.freq(1));
+    }
+
+    #[test]
+    fn test_all_symbols_fill_table() {
+        let model = zipf(3.0, 256, 8);
+
+        assert_valid(&model, 256);
+        assert!(model.freqs().iter().all(|&freq| freq == 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "p must be in the (0, 1) range")]
+    fn test_geometric_invalid_p() {
+        let _ = geometric(1.0, 4, 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "scale must be positive and finite")]
+    fn test_laplacian_invalid_scale() {
+        let _ = laplacian(0.0, 4, 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "n_symbols must not be 0")]
+    fn test_zipf_no_symbols() {
+        let _ = zipf(1.0, 0, 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "too many distinct symbols for given scale_bits")]
+    fn test_too_many_symbols() {
+        let _ = geometric(0.5, 257, 8);
+    }
+}
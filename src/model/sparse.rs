@@ -0,0 +1,438 @@
+use crate::model::Model;
+use crate::{varint, RansError};
+
+/// Model of an explicit subset of a larger alphabet.
+///
+/// Only the symbols that are actually present are stored: the frequencies
+/// are kept in a dense [`Model`] indexed by the position of the symbol in the
+/// alphabet, and a remap table translates these indices back to the original
+/// symbols. Encoders and decoders built from [`Self::model()`] are therefore
+/// as small as the subset, and the serialized form doesn't have to describe
+/// the symbols that never occur.
+///
+/// # Examples
+/// ```
+/// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+/// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+/// use rans::model::sparse::SparseModel;
+/// use rans::model::{DecModel, EncModel};
+/// use rans::{RansDecoder, RansEncoder, RansEncoderMulti};
+///
+/// let data = [1000, 7, 1000, 40000];
+/// let mut counts = vec![0; 65536];
+/// for &symbol in &data {
+///     counts[symbol] += 1;
+/// }
+/// let sparse = SparseModel::from_counts(&counts, 8);
+/// assert_eq!(sparse.alphabet(), [7, 1000, 40000]);
+///
+/// let enc_model = EncModel::<ByteRansEncSymbol>::new(sparse.model());
+/// let mut encoder = ByteRansEncoder::new(32);
+/// for &symbol in data.iter().rev() {
+///     encoder.put(enc_model.symbol(sparse.index_of(symbol).unwrap()));
+/// }
+/// encoder.flush();
+///
+/// let dec_model = DecModel::<ByteRansDecSymbol>::new(sparse.model());
+/// assert_eq!(dec_model.len(), 3);
+/// let mut decoder = ByteRansDecoder::new(encoder.data());
+/// for &symbol in &data {
+///     assert_eq!(sparse.symbol(decoder.decode_symbol(&dec_model)), symbol);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseModel {
+    alphabet: Vec<usize>,
+    model: Model,
+}
+
+impl SparseModel {
+    /// Creates a new model by normalizing raw symbol counts to
+    /// `1 << scale_bits`, like [`Model::from_counts()`], but keeping only
+    /// the symbols with non-zero count in the alphabet.
+    ///
+    /// # Panics
+    /// Panics if all counts are zero, if `scale_bits` is larger than 31, or if
+    /// there are more symbols with non-zero count than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    ///
+    /// let sparse = SparseModel::from_counts(&[0, 10, 0, 0, 30], 4);
+    /// assert_eq!(sparse.alphabet(), [1, 4]);
+    /// assert_eq!(sparse.model().freqs(), [4, 12]);
+    /// ```
+    #[must_use]
+    pub fn from_counts(counts: &[u32], scale_bits: u32) -> Self {
+        let (alphabet, counts): (Vec<usize>, Vec<u32>) = counts
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .unzip();
+
+        Self {
+            alphabet,
+            model: Model::from_counts(&counts, scale_bits),
+        }
+    }
+
+    /// Creates a new sparse model with the same frequencies as `model`,
+    /// dropping the symbols with zero frequency from the alphabet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    /// use rans::model::Model;
+    ///
+    /// let model = Model::from_counts(&[10, 0, 30], 4);
+    /// let sparse = SparseModel::from_model(&model);
+    /// assert_eq!(sparse.alphabet(), [0, 2]);
+    /// assert_eq!(sparse.to_model(), model);
+    /// ```
+    #[must_use]
+    pub fn from_model(model: &Model) -> Self {
+        let (alphabet, freqs): (Vec<usize>, Vec<u32>) = model
+            .freqs()
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, freq)| freq > 0)
+            .unzip();
+
+        Self {
+            alphabet,
+            model: Model::from_freqs(freqs, model.scale_bits()),
+        }
+    }
+
+    /// Returns the symbols in the alphabet, in ascending order. The position
+    /// of a symbol in this slice is its index in [`Self::model()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    ///
+    /// let sparse = SparseModel::from_counts(&[0, 0, 1, 1], 8);
+    /// assert_eq!(sparse.alphabet(), [2, 3]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn alphabet(&self) -> &[usize] {
+        &self.alphabet
+    }
+
+    /// Returns the dense model of the alphabet, indexed by the position of
+    /// the symbol in [`Self::alphabet()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    ///
+    /// let sparse = SparseModel::from_counts(&[0, 1, 0, 3], 4);
+    /// assert_eq!(sparse.model().freqs(), [4, 12]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Returns the number of symbols in the alphabet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    ///
+    /// let sparse = SparseModel::from_counts(&[5, 0, 0, 1], 8);
+    /// assert_eq!(sparse.len(), 2);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.alphabet.len()
+    }
+
+    /// Returns whether the alphabet is empty. A model constructed with any of
+    /// the constructors is never empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    ///
+    /// assert!(!SparseModel::from_counts(&[0, 1], 8).is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.alphabet.is_empty()
+    }
+
+    /// Returns the index of `symbol` in the alphabet, or `None` if the symbol
+    /// is not a part of it.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    ///
+    /// let sparse = SparseModel::from_counts(&[0, 1, 0, 3], 4);
+    /// assert_eq!(sparse.index_of(3), Some(1));
+    /// assert_eq!(sparse.index_of(2), None);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn index_of(&self, symbol: usize) -> Option<usize> {
+        self.alphabet.binary_search(&symbol).ok()
+    }
+
+    /// Returns the symbol at given index of the alphabet.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    ///
+    /// let sparse = SparseModel::from_counts(&[0, 1, 0, 3], 4);
+    /// assert_eq!(sparse.symbol(1), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn symbol(&self, index: usize) -> usize {
+        self.alphabet[index]
+    }
+
+    /// Expands this model to a regular [`Model`] covering all the symbols up
+    /// to the largest one in the alphabet, the missing ones getting zero
+    /// frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    ///
+    /// let sparse = SparseModel::from_counts(&[0, 1, 0, 3], 4);
+    /// assert_eq!(sparse.to_model().freqs(), [0, 4, 0, 12]);
+    /// ```
+    #[must_use]
+    pub fn to_model(&self) -> Model {
+        let mut freqs = vec![0; self.alphabet.last().map_or(0, |&last| last + 1)];
+        for (&symbol, &freq) in self.alphabet.iter().zip(self.model.freqs()) {
+            freqs[symbol] = freq;
+        }
+
+        Model::from_freqs(freqs, self.model.scale_bits())
+    }
+
+    /// Serializes this model into a compact byte representation, which can
+    /// be read back with [`Self::from_bytes()`].
+    ///
+    /// The alphabet is stored as a list of ranges of consecutive symbols, so
+    /// both a few scattered symbols and a contiguous block of them take only
+    /// a few bytes, and is followed by [`Model::to_bytes()`] of the dense
+    /// model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    /// use rans::model::Model;
+    ///
+    /// let mut counts = vec![0; 65536];
+    /// counts[100] = 1;
+    /// counts[60000] = 3;
+    /// let sparse = SparseModel::from_counts(&counts, 12);
+    /// assert!(sparse.to_bytes().len() < 16);
+    /// assert!(sparse.to_bytes().len() < sparse.to_model().to_bytes().len());
+    /// ```
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ranges = Vec::new();
+        for &symbol in &self.alphabet {
+            match ranges.last_mut() {
+                Some((start, len)) if *start + *len == symbol => *len += 1,
+                _ => ranges.push((symbol, 1)),
+            }
+        }
+
+        let mut output = Vec::new();
+        varint::write_u64(&mut output, ranges.len() as u64);
+        let mut next = 0;
+        for (start, len) in ranges {
+            varint::write_u64(&mut output, (start - next) as u64);
+            varint::write_u64(&mut output, len as u64 - 1);
+            next = start + len;
+        }
+
+        output.extend_from_slice(&self.model.to_bytes());
+        output
+    }
+
+    /// Reads a model serialized with [`Self::to_bytes()`].
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the data is truncated,
+    /// [`RansError::InvalidHeader`] if the alphabet is malformed or too
+    /// large, or there is trailing data, or [`RansError::InvalidModel`] if
+    /// the frequencies are malformed, there is a symbol with zero frequency
+    /// or the number of frequencies doesn't match the alphabet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::sparse::SparseModel;
+    /// use rans::RansError;
+    ///
+    /// let sparse = SparseModel::from_counts(&[0, 0, 5, 0, 3, 1, 0], 10);
+    /// let bytes = sparse.to_bytes();
+    /// assert_eq!(SparseModel::from_bytes(&bytes), Ok(sparse));
+    /// assert_eq!(
+    ///     SparseModel::from_bytes(&bytes[..bytes.len() - 1]),
+    ///     Err(RansError::UnexpectedEof)
+    /// );
+    /// ```
+    pub fn from_bytes(data: &[u8]) -> Result<Self, RansError> {
+        let mut pos = 0;
+        let model = Self::read_bytes(data, &mut pos)?;
+        if pos != data.len() {
+            return Err(RansError::InvalidHeader);
+        }
+
+        Ok(model)
+    }
+
+    /// Reads a model serialized with [`Self::to_bytes()`] from `data`
+    /// starting at `*pos` and advances `*pos` past it.
+    pub(crate) fn read_bytes(data: &[u8], pos: &mut usize) -> Result<Self, RansError> {
+        /// Limit on the number of symbols in the alphabet, so that malformed
+        /// input can't trigger huge allocations.
+        const MAX_SYMBOLS: u64 = 1 << 24;
+        /// Limit on the symbol values, so that they fit in `usize` on all
+        /// supported platforms.
+        const MAX_SYMBOL: u64 = u32::MAX as u64;
+
+        let num_ranges = varint::read_u64(data, pos)?;
+        if num_ranges > MAX_SYMBOLS {
+            return Err(RansError::InvalidHeader);
+        }
+
+        let mut alphabet = Vec::new();
+        let mut next = 0_u64;
+        for _ in 0..num_ranges {
+            let start = next
+                .checked_add(varint::read_u64(data, pos)?)
+                .ok_or(RansError::InvalidHeader)?;
+            // The length of the range minus one
+            let len = varint::read_u64(data, pos)?;
+            if len >= MAX_SYMBOLS - alphabet.len() as u64
+                || start > MAX_SYMBOL
+                || len > MAX_SYMBOL - start
+            {
+                return Err(RansError::InvalidHeader);
+            }
+
+            alphabet.extend(start as usize..=(start + len) as usize);
+            next = start + len + 1;
+        }
+
+        let model = Model::read_bytes(data, pos)?;
+        if model.len() != alphabet.len() || model.freqs().contains(&0) {
+            return Err(RansError::InvalidModel);
+        }
+        Ok(Self { alphabet, model })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::model::sparse::SparseModel;
+    use crate::model::Model;
+    use crate::RansError;
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(89);
+        for _ in 0..50 {
+            let len = rng.gen_range(1..5000);
+            let counts: Vec<u32> = (0..len)
+                .map(|_| {
+                    if rng.gen_bool(0.1) {
+                        rng.gen_range(1..100)
+                    } else {
+                        0
+                    }
+                })
+                .chain([1])
+                .collect();
+            let sparse = SparseModel::from_counts(&counts, 16);
+
+            assert_eq!(SparseModel::from_bytes(&sparse.to_bytes()), Ok(sparse));
+        }
+    }
+
+    #[test]
+    fn test_contiguous_alphabet_compact() {
+        let counts: Vec<u32> = (0..100).map(|symbol| u32::from(symbol >= 16)).collect();
+        let sparse = SparseModel::from_counts(&counts, 12);
+        let bytes = sparse.to_bytes();
+
+        // A single range, followed by the dense model
+        assert_eq!(bytes[..3], [1, 16, 83]);
+        assert_eq!(bytes[3..], sparse.model().to_bytes());
+    }
+
+    #[test]
+    fn test_to_model_round_trip() {
+        let model = Model::from_counts(&[0, 3, 0, 0, 7, 1, 0, 2], 10);
+        let sparse = SparseModel::from_model(&model);
+
+        assert_eq!(sparse.len(), 4);
+        assert_eq!(sparse.to_model(), model);
+        assert_eq!(SparseModel::from_model(&sparse.to_model()), sparse);
+    }
+
+    #[test]
+    fn test_from_bytes_invalid() {
+        let bytes = SparseModel::from_counts(&[0, 1, 1], 4).to_bytes();
+
+        // Trailing data
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            SparseModel::from_bytes(&trailing),
+            Err(RansError::InvalidHeader)
+        );
+
+        // Alphabet larger than the model
+        let mut mismatched = bytes.clone();
+        mismatched[2] = 2;
+        assert_eq!(
+            SparseModel::from_bytes(&mismatched),
+            Err(RansError::InvalidModel)
+        );
+
+        // Symbol out of range
+        let mut out_of_range = vec![1, 0xff, 0xff, 0xff, 0xff, 0x1f, 0];
+        out_of_range.extend_from_slice(&Model::from_counts(&[1], 4).to_bytes());
+        assert_eq!(
+            SparseModel::from_bytes(&out_of_range),
+            Err(RansError::InvalidHeader)
+        );
+
+        // Zero frequency in the dense model
+        let mut zero_freq = vec![1, 0, 1];
+        zero_freq.extend_from_slice(&Model::from_counts(&[1, 0], 4).to_bytes());
+        assert_eq!(
+            SparseModel::from_bytes(&zero_freq),
+            Err(RansError::InvalidModel)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one symbol must have a non-zero count")]
+    fn test_from_counts_all_zero() {
+        let _ = SparseModel::from_counts(&[0, 0, 0], 8);
+    }
+}
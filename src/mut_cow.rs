@@ -1,4 +1,4 @@
-use std::borrow::{Borrow, BorrowMut};
+use std::borrow::{Borrow, BorrowMut, Cow};
 use std::fmt;
 use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
@@ -8,6 +8,10 @@ use std::ops::{Deref, DerefMut};
 /// Despite the name still contains "Cow" (Copy-on-write) part, this actually
 /// never does any copying. This allows the structure making use of it to either
 /// operate on a `&mut` reference to the data, or to own the data.
+///
+/// Decoders never write to their input: a `MutCow` passed to a decoder is
+/// converted into a [`DecoderInput`](crate::decoder_input::DecoderInput), so
+/// a lent `&mut [u8]` is left exactly as it was after decoding.
 pub enum MutCow<'a, B>
 where
     B: 'a + ToOwned + ?Sized,
@@ -31,32 +35,148 @@ where
     }
 }
 
-impl<'a> From<&'a mut [u8]> for MutCow<'a, [u8]> {
-    fn from(reference: &'a mut [u8]) -> Self {
+impl<'a, B> MutCow<'a, B>
+where
+    B: ToOwned + ?Sized,
+{
+    /// Returns `true` if the data is borrowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::mut_cow::MutCow;
+    ///
+    /// let mut data = [1, 2, 3];
+    /// assert!(MutCow::from(&mut data).is_borrowed());
+    /// assert!(!MutCow::from(vec![1, 2, 3]).is_borrowed());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self, MutCow::Borrowed(_))
+    }
+
+    /// Returns `true` if the data is owned.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::mut_cow::MutCow;
+    ///
+    /// let mut data = [1, 2, 3];
+    /// assert!(!MutCow::from(&mut data).is_owned());
+    /// assert!(MutCow::from(vec![1, 2, 3]).is_owned());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_owned(&self) -> bool {
+        !self.is_borrowed()
+    }
+
+    /// Unwraps the `MutCow`, returning the borrowed reference as `Ok` or the
+    /// owned data as `Err`, without any cloning. Any modifications made
+    /// through the `MutCow` are visible through the returned value.
+    ///
+    /// # Errors
+    /// Returns the owned data if the data is not borrowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::mut_cow::MutCow;
+    ///
+    /// let mut data = [1, 2, 3];
+    /// let mut mut_cow = MutCow::from(&mut data);
+    /// mut_cow[0] = 5;
+    /// assert_eq!(mut_cow.into_inner(), Ok(&mut [5, 2, 3][..]));
+    ///
+    /// let mut_cow = MutCow::from(vec![1, 2, 3]);
+    /// assert_eq!(mut_cow.into_inner(), Err(vec![1, 2, 3]));
+    /// ```
+    pub fn into_inner(self) -> Result<&'a mut B, <B as ToOwned>::Owned> {
+        match self {
+            MutCow::Borrowed(reference) => Ok(reference),
+            MutCow::Owned(owned) => Err(owned),
+        }
+    }
+
+    /// Extracts the owned data, cloning the data if it is borrowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::mut_cow::MutCow;
+    ///
+    /// let mut data = [1, 2, 3];
+    /// assert_eq!(MutCow::from(&mut data).into_owned(), vec![1, 2, 3]);
+    ///
+    /// let owned = vec![1, 2, 3];
+    /// let ptr = owned.as_ptr();
+    /// assert_eq!(MutCow::from(owned).into_owned().as_ptr(), ptr);
+    /// ```
+    #[must_use]
+    pub fn into_owned(self) -> <B as ToOwned>::Owned {
+        match self {
+            MutCow::Borrowed(reference) => reference.to_owned(),
+            MutCow::Owned(owned) => owned,
+        }
+    }
+}
+
+impl<'a, T: Clone> From<&'a mut [T]> for MutCow<'a, [T]> {
+    fn from(reference: &'a mut [T]) -> Self {
         Self::Borrowed(reference)
     }
 }
 
-impl<'a, const N: usize> From<&'a mut [u8; N]> for MutCow<'a, [u8]> {
-    fn from(reference: &'a mut [u8; N]) -> Self {
+impl<'a, T: Clone, const N: usize> From<&'a mut [T; N]> for MutCow<'a, [T]> {
+    fn from(reference: &'a mut [T; N]) -> Self {
         Self::Borrowed(reference)
     }
 }
 
-impl<const N: usize> From<[u8; N]> for MutCow<'_, [u8]> {
-    fn from(owned: [u8; N]) -> Self {
+impl<T: Clone, const N: usize> From<[T; N]> for MutCow<'_, [T]> {
+    fn from(owned: [T; N]) -> Self {
         Self::Owned(Vec::from(owned))
     }
 }
 
-impl From<Vec<u8>> for MutCow<'_, [u8]> {
-    fn from(owned: Vec<u8>) -> Self {
+impl<T: Clone> From<Vec<T>> for MutCow<'_, [T]> {
+    fn from(owned: Vec<T>) -> Self {
         Self::Owned(owned)
     }
 }
 
-impl Deref for MutCow<'_, [u8]> {
-    type Target = [u8];
+impl<T: Clone> From<Box<[T]>> for MutCow<'_, [T]> {
+    fn from(owned: Box<[T]>) -> Self {
+        Self::Owned(owned.into_vec())
+    }
+}
+
+impl<'a> From<&'a mut str> for MutCow<'a, str> {
+    fn from(reference: &'a mut str) -> Self {
+        Self::Borrowed(reference)
+    }
+}
+
+impl From<String> for MutCow<'_, str> {
+    fn from(owned: String) -> Self {
+        Self::Owned(owned)
+    }
+}
+
+/// Converts a [`Cow`] into a `MutCow`. As a `Cow` only borrows the data
+/// immutably, borrowed data is cloned.
+impl<'a, B> From<Cow<'a, B>> for MutCow<'a, B>
+where
+    B: ToOwned + ?Sized,
+{
+    fn from(cow: Cow<'a, B>) -> Self {
+        Self::Owned(cow.into_owned())
+    }
+}
+
+impl<B> Deref for MutCow<'_, B>
+where
+    B: ToOwned + ?Sized,
+{
+    type Target = B;
 
     fn deref(&self) -> &Self::Target {
         match self {
@@ -66,7 +186,11 @@ impl Deref for MutCow<'_, [u8]> {
     }
 }
 
-impl DerefMut for MutCow<'_, [u8]> {
+impl<B> DerefMut for MutCow<'_, B>
+where
+    B: ToOwned + ?Sized,
+    B::Owned: BorrowMut<B>,
+{
     fn deref_mut(&mut self) -> &mut Self::Target {
         match self {
             MutCow::Borrowed(reference) => reference,
@@ -75,8 +199,29 @@ impl DerefMut for MutCow<'_, [u8]> {
     }
 }
 
+impl<B> AsRef<B> for MutCow<'_, B>
+where
+    B: ToOwned + ?Sized,
+{
+    fn as_ref(&self) -> &B {
+        self
+    }
+}
+
+impl<B> AsMut<B> for MutCow<'_, B>
+where
+    B: ToOwned + ?Sized,
+    B::Owned: BorrowMut<B>,
+{
+    fn as_mut(&mut self) -> &mut B {
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
+
     use crate::mut_cow::MutCow;
 
     #[test]
@@ -108,6 +253,66 @@ mod tests {
         assert_eq!(*mut_cow, [5, 2, 3, 4]);
     }
 
+    #[test]
+    fn test_create_from_boxed_slice() {
+        let data: Box<[u32]> = Box::new([1, 2, 3, 4]);
+        let mut mut_cow = MutCow::from(data);
+        mut_cow[0] = 5;
+
+        assert!(mut_cow.is_owned());
+        assert_eq!(*mut_cow, [5, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_create_from_cow() {
+        let data: [u8; 4] = [1, 2, 3, 4];
+        let mut borrowed = MutCow::from(Cow::Borrowed(&data[..]));
+        borrowed[0] = 5;
+        let owned = MutCow::from(Cow::<[u8]>::Owned(data.to_vec()));
+
+        assert!(borrowed.is_owned());
+        assert_eq!(*borrowed, [5, 2, 3, 4]);
+        assert_eq!(*owned, [1, 2, 3, 4]);
+        assert_eq!(data, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_str() {
+        let mut data = String::from("hello");
+        let mut mut_cow = MutCow::from(data.as_mut_str());
+        mut_cow.make_ascii_uppercase();
+
+        assert!(mut_cow.is_borrowed());
+        assert_eq!(mut_cow.as_ref(), "HELLO");
+        assert_eq!(mut_cow.into_owned(), "HELLO");
+        assert_eq!(data, "HELLO");
+    }
+
+    #[test]
+    fn test_as_mut() {
+        fn fill(data: &mut [u32]) {
+            data.fill(7);
+        }
+
+        let mut data = [1, 2, 3];
+        let mut mut_cow = MutCow::from(&mut data);
+        fill(mut_cow.as_mut());
+
+        assert_eq!(data, [7, 7, 7]);
+    }
+
+    #[test]
+    fn test_into_inner() {
+        let mut data = [1, 2, 3, 4];
+        let mut mut_cow = MutCow::from(&mut data);
+        mut_cow[3] = 5;
+        let reference = mut_cow.into_inner().unwrap();
+        reference[0] = 6;
+
+        assert_eq!(data, [6, 2, 3, 5]);
+        assert_eq!(MutCow::from(vec![1]).into_inner(), Err(vec![1]));
+    }
+
     #[test]
     fn test_has_debug_output() {
         let mut data: [u8; 4] = [1, 2, 3, 4];
@@ -0,0 +1,226 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::model::{DecModel, EncModel, Model};
+use crate::simple::{
+    decode_symbol_checked, output_buffer, read_model, scale_counts, write_model, SCALE_BITS,
+};
+use crate::{varint, RansEncoder, RansEncoderMulti, RansError};
+
+/// Number of contexts (i.e. possible previous bytes).
+const NUM_CONTEXTS: usize = 256;
+/// Size of the bitmap of contexts present in the data, in bytes.
+const CONTEXT_BITMAP_LEN: usize = NUM_CONTEXTS / 8;
+
+/// Compresses a byte buffer using an order-1 static model, i.e. with a
+/// separate frequency table for every value of the previous byte. The first
+/// byte uses the context of a zero byte.
+///
+/// This usually compresses much better than [`crate::simple::compress()`]
+/// when consecutive bytes are correlated, such as in text or genomic data, at
+/// the cost of a larger header. The output starts with the original length
+/// and a bitmap of the contexts present in the data, followed by the
+/// frequency tables of these contexts and the rANS bitstream. Use
+/// [`decompress()`] to get the original data back.
+///
+/// # Examples
+/// ```
+/// use rans::order1::{compress, decompress};
+///
+/// let data = b"abracadabra";
+/// let compressed = compress(data);
+/// assert_eq!(decompress(&compressed).unwrap(), data);
+/// ```
+#[must_use]
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    varint::write_u64(&mut output, data.len() as u64);
+    if data.is_empty() {
+        return output;
+    }
+
+    let models = build_models(data);
+    let mut bitmap = [0; CONTEXT_BITMAP_LEN];
+    for (context, model) in models.iter().enumerate() {
+        if model.is_some() {
+            bitmap[context / 8] |= 1 << (context % 8);
+        }
+    }
+    output.extend_from_slice(&bitmap);
+    for model in models.iter().flatten() {
+        write_model(&mut output, model);
+    }
+
+    let enc_models: Vec<Option<EncModel<ByteRansEncSymbol>>> = models
+        .iter()
+        .map(|model| model.as_ref().map(EncModel::new))
+        .collect();
+
+    // With 12-bit frequencies, a single symbol emits at most 2 bytes.
+    let mut encoder = ByteRansEncoder::new(data.len() * 2 + 4);
+    for (i, &byte) in data.iter().enumerate().rev() {
+        let context = if i == 0 { 0 } else { data[i - 1] };
+        let enc_model = enc_models[context as usize]
+            .as_ref()
+            .expect("model of a present context");
+        encoder.put_index(byte as usize, enc_model);
+    }
+    encoder.flush();
+
+    output.extend_from_slice(encoder.data());
+    output
+}
+
+/// Decompresses data produced by [`compress()`].
+///
+/// A byte decoded in a context without a model results in
+/// [`RansError::InvalidModel`]; the output length is only limited by the
+/// header.
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the input is truncated,
+/// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if the header is
+/// malformed, or [`RansError::InputExhausted`] if the bitstream ends before
+/// all the bytes are decoded.
+///
+/// # Examples
+/// ```
+/// use rans::order1::{compress, decompress};
+/// use rans::RansError;
+///
+/// let compressed = compress(b"hello");
+/// assert_eq!(decompress(&compressed).unwrap(), b"hello");
+/// assert_eq!(decompress(&compressed[..3]), Err(RansError::UnexpectedEof));
+/// ```
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, RansError> {
+    let mut pos = 0;
+    let len =
+        usize::try_from(varint::read_u64(data, &mut pos)?).map_err(|_| RansError::InvalidHeader)?;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    let bitmap = data
+        .get(pos..pos + CONTEXT_BITMAP_LEN)
+        .ok_or(RansError::UnexpectedEof)?;
+    pos += CONTEXT_BITMAP_LEN;
+    let mut dec_models = Vec::with_capacity(NUM_CONTEXTS);
+    for context in 0..NUM_CONTEXTS {
+        if bitmap[context / 8] & (1 << (context % 8)) == 0 {
+            dec_models.push(None);
+        } else {
+            let model = read_model(data, &mut pos)?;
+            dec_models.push(Some(DecModel::<ByteRansDecSymbol>::new(&model)));
+        }
+    }
+
+    let payload = &data[pos..];
+    if payload.len() < 4 {
+        return Err(RansError::UnexpectedEof);
+    }
+
+    let mut decoder = ByteRansDecoder::new(payload);
+    let mut output = output_buffer(&decoder, len);
+    let mut context = 0;
+    for _ in 0..len {
+        let dec_model = dec_models[context]
+            .as_ref()
+            .ok_or(RansError::InvalidModel)?;
+        let byte = decode_symbol_checked(&mut decoder, 0, dec_model)?;
+        output.push(byte as u8);
+        context = byte;
+    }
+
+    Ok(output)
+}
+
+/// Builds the frequency tables of all the contexts present in the data.
+fn build_models(data: &[u8]) -> Vec<Option<Model>> {
+    let mut counts = vec![[0_u64; 256]; NUM_CONTEXTS];
+    let mut context = 0;
+    for &byte in data {
+        counts[context][byte as usize] += 1;
+        context = byte as usize;
+    }
+
+    counts
+        .iter()
+        .map(|counts| {
+            if counts.iter().all(|&count| count == 0) {
+                None
+            } else {
+                Some(Model::from_counts(&scale_counts(counts), SCALE_BITS))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::order1::{compress, decompress};
+    use crate::{simple, varint, RansError};
+
+    #[test]
+    fn test_round_trip_empty() {
+        let compressed = compress(&[]);
+
+        assert_eq!(compressed, [0]);
+        assert_eq!(decompress(&compressed), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_round_trip_single_byte() {
+        let compressed = compress(&[42]);
+
+        assert_eq!(decompress(&compressed).unwrap(), [42]);
+    }
+
+    #[test]
+    fn test_round_trip_all_bytes() {
+        let data: Vec<u8> = (0..10000_u32).map(|x| (x * x % 251) as u8).collect();
+        let compressed = compress(&data);
+
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_beats_order0_on_correlated_data() {
+        // Every byte is fully determined by the previous one, while all of
+        // them are equally frequent.
+        let data: Vec<u8> = (0..20000_u32).map(|x| (x % 16) as u8 * 3).collect();
+        let compressed = compress(&data);
+
+        assert!(compressed.len() < simple::compress(&data).len() / 4);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_missing_context() {
+        let mut compressed = compress(b"aaaa");
+        // Clear the bitmap bit of the initial (zero) context
+        compressed[1] &= !1;
+
+        assert_eq!(decompress(&compressed), Err(RansError::InvalidModel));
+    }
+
+    #[test]
+    fn test_declared_len_too_large() {
+        let compressed = compress(b"abba");
+        let mut corrupted = Vec::new();
+        varint::write_u64(&mut corrupted, (1 << 46) - 1);
+        corrupted.extend_from_slice(&compressed[1..]);
+
+        assert_eq!(decompress(&corrupted), Err(RansError::InputExhausted));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let compressed = compress(b"aab");
+
+        for len in 0..compressed.len() - 4 {
+            assert_eq!(
+                decompress(&compressed[..len]),
+                Err(RansError::UnexpectedEof)
+            );
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use rayon::prelude::*;
+
+use crate::blocks::BlockIndex;
+use crate::{simple, RansError};
+
+/// Default number of bytes in a single block compressed by
+/// [`par_compress()`].
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 20;
+
+/// Compresses a byte buffer by splitting it into independent blocks of
+/// [`DEFAULT_BLOCK_SIZE`] bytes and compressing them in parallel on the rayon
+/// thread pool.
+///
+/// Each block is compressed like with [`simple::compress()`], i.e. with its
+/// own order-0 model. The output starts with an index of the blocks, so that
+/// [`par_decompress()`] can decompress them in parallel as well. The output
+/// can also be read with [`crate::seekable::SeekableDecoder`].
+///
+/// # Examples
+/// ```
+/// use rans::parallel::{par_compress, par_decompress};
+///
+/// let data = b"abracadabra".repeat(1000);
+/// let compressed = par_compress(&data);
+/// assert_eq!(par_decompress(&compressed).unwrap(), data);
+/// ```
+#[must_use]
+pub fn par_compress(data: &[u8]) -> Vec<u8> {
+    par_compress_with_block_size(data, DEFAULT_BLOCK_SIZE)
+}
+
+/// Compresses a byte buffer like [`par_compress()`], but with blocks of
+/// `block_size` bytes. Smaller blocks allow more parallelism, at the cost of
+/// the compression ratio.
+///
+/// # Panics
+/// Panics if `block_size` is zero.
+///
+/// # Examples
+/// ```
+/// use rans::parallel::{par_compress_with_block_size, par_decompress};
+///
+/// let data = b"abracadabra".repeat(1000);
+/// let compressed = par_compress_with_block_size(&data, 1024);
+/// assert_eq!(par_decompress(&compressed).unwrap(), data);
+/// ```
+#[must_use]
+pub fn par_compress_with_block_size(data: &[u8], block_size: usize) -> Vec<u8> {
+    assert!(block_size > 0, "block_size must not be zero");
+
+    let blocks: Vec<Vec<u8>> = data.par_chunks(block_size).map(simple::compress).collect();
+    let mut output = Vec::with_capacity(blocks.iter().map(Vec::len).sum::<usize>() + 16);
+    BlockIndex::write(&mut output, data.len(), block_size, &blocks);
+    output
+}
+
+/// Decompresses data produced by [`par_compress()`] or
+/// [`par_compress_with_block_size()`], decompressing the blocks in parallel
+/// on the rayon thread pool.
+///
+/// The headers are validated, but the rANS bitstreams themselves are
+/// trusted, so this should not be used for data coming from untrusted
+/// sources.
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the input is truncated, or
+/// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if any of the
+/// headers is malformed.
+///
+/// # Examples
+/// ```
+/// use rans::parallel::{par_compress, par_decompress};
+/// use rans::RansError;
+///
+/// let compressed = par_compress(b"hello");
+/// assert_eq!(par_decompress(&compressed).unwrap(), b"hello");
+/// assert_eq!(par_decompress(&compressed[..4]), Err(RansError::UnexpectedEof));
+/// ```
+pub fn par_decompress(data: &[u8]) -> Result<Vec<u8>, RansError> {
+    let index = BlockIndex::read(data)?;
+    let blocks: Vec<Vec<u8>> = (0..index.num_blocks())
+        .into_par_iter()
+        .map(|block| index.decompress_block(data, block))
+        .collect::<Result<_, _>>()?;
+
+    let mut output = Vec::with_capacity(index.len());
+    for block in blocks {
+        output.extend_from_slice(&block);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parallel::{par_compress, par_compress_with_block_size, par_decompress};
+    use crate::RansError;
+
+    #[test]
+    fn test_round_trip_empty() {
+        let compressed = par_compress(&[]);
+
+        assert_eq!(par_decompress(&compressed), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_round_trip_block_sizes() {
+        let data: Vec<u8> = (0..100_000_u32).map(|x| (x % 251 * 7 % 13) as u8).collect();
+        for block_size in [1, 1000, 4096, 100_000, 1 << 20] {
+            let compressed = par_compress_with_block_size(&data, block_size);
+
+            assert_eq!(par_decompress(&compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_block_length_mismatch() {
+        let mut compressed = par_compress_with_block_size(b"aaaabbbb", 4);
+        // Claim the data is shorter than it really is
+        compressed[0] = 7;
+
+        assert_eq!(par_decompress(&compressed), Err(RansError::InvalidHeader));
+    }
+}
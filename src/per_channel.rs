@@ -0,0 +1,580 @@
+use crate::model::{DecModel, EncModel};
+use crate::{RansDecoderMulti, RansEncoderMulti, RansError};
+
+/// Adapter over a multi-stream encoder that associates a distinct model with
+/// each channel, e.g. to interleave planes with different distributions.
+///
+/// Symbols are put by their index in the model of the channel. In debug
+/// builds, or with the `checked` feature enabled, the index is validated
+/// against that model, so putting a symbol into the wrong channel is caught
+/// instead of silently corrupting the data.
+///
+/// The channels are flushed so that channel `i` of the matching
+/// [`PerChannelDecoder`] reads the data of channel `i` of this encoder. As
+/// with any rANS encoder, the symbols have to be put in the reverse order of
+/// decoding.
+///
+/// # Examples
+/// ```
+/// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+/// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+/// use rans::model::{DecModel, EncModel, Model};
+/// use rans::per_channel::{PerChannelDecoder, PerChannelEncoder};
+///
+/// let luma = Model::from_counts(&[1, 4, 10, 4, 1], 10);
+/// let chroma = Model::from_counts(&[20, 1, 1], 10);
+/// let pixels = [[0, 0], [2, 1], [4, 0], [1, 0]];
+///
+/// let enc_models = [
+///     &EncModel::<ByteRansEncSymbol>::new(&luma),
+///     &EncModel::new(&chroma),
+/// ];
+/// let mut encoder = PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), enc_models);
+/// for pixel in pixels.iter().rev() {
+///     encoder.put_each(pixel);
+/// }
+/// encoder.flush();
+///
+/// let dec_models = [
+///     &DecModel::<ByteRansDecSymbol>::new(&luma),
+///     &DecModel::new(&chroma),
+/// ];
+/// let mut decoder =
+///     PerChannelDecoder::new(ByteRansDecoderMulti::<2>::new(encoder.data()), dec_models);
+/// for pixel in &pixels {
+///     assert_eq!(decoder.decode_each(), *pixel);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PerChannelEncoder<'a, E: RansEncoderMulti<N>, const N: usize> {
+    encoder: E,
+    models: [&'a EncModel<E::Symbol>; N],
+}
+
+impl<'a, E: RansEncoderMulti<N>, const N: usize> PerChannelEncoder<'a, E, N> {
+    /// Creates a new `PerChannelEncoder` instance wrapping given encoder,
+    /// with `models[i]` being the model of channel `i`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    ///
+    /// let model = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let encoder = PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), [&model; 2]);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(encoder: E, models: [&'a EncModel<E::Symbol>; N]) -> Self {
+        Self { encoder, models }
+    }
+
+    /// Puts the symbol with given index in the model of the channel into
+    /// that channel.
+    ///
+    /// # Panics
+    /// In debug builds, or with the `checked` feature enabled, panics if
+    /// `channel` is out of range, or if `symbol` is not a valid symbol of the
+    /// channel's model, i.e. it's out of range or has zero frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    ///
+    /// let model_0 = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let model_1 = EncModel::new(&Model::from_counts(&[1, 1, 1, 1], 8));
+    /// let mut encoder =
+    ///     PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), [&model_0, &model_1]);
+    /// encoder.put_at(1, 3);
+    /// encoder.put_at(0, 1);
+    /// ```
+    #[inline]
+    pub fn put_at(&mut self, channel: usize, symbol: usize) {
+        checked_assert!(channel < N);
+        checked_assert!(
+            self.is_valid_symbol(channel, symbol),
+            "symbol {} is not a part of the model of channel {}",
+            symbol,
+            channel
+        );
+
+        self.encoder
+            .put_at(channel, self.models[channel].symbol(symbol));
+    }
+
+    /// Puts the symbol with given index in the model of the channel into
+    /// that channel, checking that the symbol belongs to the model and that
+    /// there is enough space left in the buffer.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InvalidChannel`] if `channel` is out of range,
+    /// [`RansError::InvalidSymbol`] if `symbol` is not a valid symbol of the
+    /// channel's model, or [`RansError::BufferOverflow`] if the symbol might
+    /// not fit in the buffer. In all cases, the encoder's state is left
+    /// untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    /// use rans::RansError;
+    ///
+    /// let model_0 = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 0, 3], 8));
+    /// let model_1 = EncModel::new(&Model::from_counts(&[1, 1, 1, 1], 8));
+    /// let mut encoder =
+    ///     PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), [&model_0, &model_1]);
+    /// assert_eq!(encoder.try_put_at(1, 3), Ok(()));
+    /// assert_eq!(encoder.try_put_at(0, 3), Err(RansError::InvalidSymbol));
+    /// assert_eq!(encoder.try_put_at(0, 1), Err(RansError::InvalidSymbol));
+    /// assert_eq!(encoder.try_put_at(2, 0), Err(RansError::InvalidChannel));
+    /// ```
+    pub fn try_put_at(&mut self, channel: usize, symbol: usize) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+        if !self.is_valid_symbol(channel, symbol) {
+            return Err(RansError::InvalidSymbol);
+        }
+
+        self.encoder
+            .try_put_at(channel, self.models[channel].symbol(symbol))
+    }
+
+    /// Puts `symbols[i]` into channel `i` for every channel, in the order
+    /// that makes [`PerChannelDecoder::decode_each()`] return them back.
+    ///
+    /// # Panics
+    /// In debug builds, or with the `checked` feature enabled, panics if any
+    /// of the symbols is not a valid symbol of its channel's model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    ///
+    /// let model = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let mut encoder = PerChannelEncoder::new(ByteRansEncoderMulti::<3>::new(1024), [&model; 3]);
+    /// encoder.put_each(&[0, 1, 1]);
+    /// ```
+    pub fn put_each(&mut self, symbols: &[usize; N]) {
+        // The decoder reads the channels in ascending order
+        for channel in (0..N).rev() {
+            self.put_at(channel, symbols[channel]);
+        }
+    }
+
+    /// Flushes all the channels of the encoder, in the order expected by
+    /// [`PerChannelDecoder`]. After this, [`Self::data()`] returns the
+    /// complete encoded data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    ///
+    /// let model = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let mut encoder = PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), [&model; 2]);
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [0, 0, 128, 0, 0, 0, 128, 0]);
+    /// ```
+    pub fn flush(&mut self) {
+        // Channel 0 is flushed last, so that the decoder reads its state first
+        for channel in (0..N).rev() {
+            self.encoder.flush_at(channel);
+        }
+    }
+
+    /// Flushes all the channels of the encoder, in the order expected by
+    /// [`PerChannelDecoder`], checking that there is enough space left in the
+    /// buffer.
+    ///
+    /// # Errors
+    /// Returns [`RansError::BufferOverflow`] if the data does not fit in the
+    /// buffer. The channels flushed before the error occurred stay flushed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    /// use rans::RansError;
+    ///
+    /// let model = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let mut encoder = PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(6), [&model; 2]);
+    /// assert_eq!(encoder.try_flush(), Err(RansError::BufferOverflow));
+    /// ```
+    pub fn try_flush(&mut self) -> Result<(), RansError> {
+        for channel in (0..N).rev() {
+            self.encoder.try_flush_at(channel)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the wrapped encoder's internal buffer content.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    ///
+    /// let model = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let encoder = PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), [&model; 2]);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        self.encoder.data()
+    }
+
+    /// Returns the model of given channel.
+    ///
+    /// # Panics
+    /// Panics if `channel` is out of range.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    ///
+    /// let model_0 = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let model_1 = EncModel::new(&Model::from_counts(&[1, 1, 1, 1], 8));
+    /// let encoder =
+    ///     PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), [&model_0, &model_1]);
+    /// assert_eq!(encoder.model(1).len(), 4);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn model(&self, channel: usize) -> &'a EncModel<E::Symbol> {
+        self.models[channel]
+    }
+
+    /// Returns a reference to the wrapped encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let model = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let encoder = PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), [&model; 2]);
+    /// assert!(encoder.encoder().is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn encoder(&self) -> &E {
+        &self.encoder
+    }
+
+    /// Consumes this adapter, returning the wrapped encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{EncModel, Model};
+    /// use rans::per_channel::PerChannelEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let model = EncModel::<ByteRansEncSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let mut encoder = PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), [&model; 2]);
+    /// encoder.flush();
+    /// assert_eq!(encoder.into_inner().len(), 8);
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> E {
+        self.encoder
+    }
+
+    #[inline]
+    fn is_valid_symbol(&self, channel: usize, symbol: usize) -> bool {
+        self.models[channel].contains(symbol)
+    }
+}
+
+/// Adapter over a multi-stream decoder that decodes each channel with its
+/// own model, mirroring a [`PerChannelEncoder`].
+///
+/// See [`PerChannelEncoder`] for an example.
+#[derive(Debug)]
+pub struct PerChannelDecoder<'a, D: RansDecoderMulti<N>, const N: usize> {
+    decoder: D,
+    models: [&'a DecModel<D::Symbol>; N],
+}
+
+impl<'a, D: RansDecoderMulti<N>, const N: usize> PerChannelDecoder<'a, D, N> {
+    /// Creates a new `PerChannelDecoder` instance wrapping given decoder,
+    /// with `models[i]` being the model of channel `i`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::per_channel::PerChannelDecoder;
+    ///
+    /// let model = DecModel::<ByteRansDecSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let decoder =
+    ///     PerChannelDecoder::new(ByteRansDecoderMulti::<2>::new([0, 0, 128, 0, 0, 0, 128, 0]), [&model; 2]);
+    /// assert_eq!(decoder.model(1).scale_bits(), 8);
+    /// ```
+    #[must_use]
+    pub fn new(decoder: D, models: [&'a DecModel<D::Symbol>; N]) -> Self {
+        Self { decoder, models }
+    }
+
+    /// Decodes a single symbol at given channel using the channel's model
+    /// and returns its index.
+    ///
+    /// # Panics
+    /// In debug builds, or with the `checked` feature enabled, panics if
+    /// `channel` is out of range.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::model::{DecModel, EncModel, Model};
+    /// use rans::per_channel::{PerChannelDecoder, PerChannelEncoder};
+    ///
+    /// let model_0 = Model::from_counts(&[1, 3], 8);
+    /// let model_1 = Model::from_counts(&[1, 1, 1, 1], 8);
+    ///
+    /// let enc_models = [&EncModel::<ByteRansEncSymbol>::new(&model_0), &EncModel::new(&model_1)];
+    /// let mut encoder = PerChannelEncoder::new(ByteRansEncoderMulti::<2>::new(1024), enc_models);
+    /// encoder.put_at(0, 1);
+    /// encoder.put_at(1, 3);
+    /// encoder.flush();
+    ///
+    /// let dec_models = [&DecModel::<ByteRansDecSymbol>::new(&model_0), &DecModel::new(&model_1)];
+    /// let mut decoder =
+    ///     PerChannelDecoder::new(ByteRansDecoderMulti::<2>::new(encoder.data()), dec_models);
+    /// assert_eq!(decoder.decode_at(1), 3);
+    /// assert_eq!(decoder.decode_at(0), 1);
+    /// ```
+    #[inline]
+    pub fn decode_at(&mut self, channel: usize) -> usize {
+        checked_assert!(channel < N);
+
+        self.decoder.decode_symbol_at(channel, self.models[channel])
+    }
+
+    /// Decodes a single symbol from every channel, returning the symbols put
+    /// with [`PerChannelEncoder::put_each()`].
+    ///
+    /// See [`PerChannelEncoder`] for an example.
+    pub fn decode_each(&mut self) -> [usize; N] {
+        let mut symbols = [0; N];
+        for (channel, symbol) in symbols.iter_mut().enumerate() {
+            *symbol = self.decode_at(channel);
+        }
+
+        symbols
+    }
+
+    /// Returns the model of given channel.
+    ///
+    /// # Panics
+    /// Panics if `channel` is out of range.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::per_channel::PerChannelDecoder;
+    ///
+    /// let model = DecModel::<ByteRansDecSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let decoder =
+    ///     PerChannelDecoder::new(ByteRansDecoderMulti::<2>::new([0, 0, 128, 0, 0, 0, 128, 0]), [&model; 2]);
+    /// assert_eq!(decoder.model(0).len(), 2);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn model(&self, channel: usize) -> &'a DecModel<D::Symbol> {
+        self.models[channel]
+    }
+
+    /// Returns a mutable reference to the wrapped decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::per_channel::PerChannelDecoder;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let model = DecModel::<ByteRansDecSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let mut decoder =
+    ///     PerChannelDecoder::new(ByteRansDecoderMulti::<2>::new([0, 0, 128, 0, 0, 0, 128, 0]), [&model; 2]);
+    /// assert_eq!(decoder.decoder_mut().get_at(1, 8), 0);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Consumes this adapter, returning the wrapped decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    /// use rans::model::{DecModel, Model};
+    /// use rans::per_channel::PerChannelDecoder;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let model = DecModel::<ByteRansDecSymbol>::new(&Model::from_counts(&[1, 3], 8));
+    /// let decoder =
+    ///     PerChannelDecoder::new(ByteRansDecoderMulti::<2>::new([0, 0, 128, 0, 0, 0, 128, 0]), [&model; 2]);
+    /// let mut decoder = decoder.into_inner();
+    /// assert_eq!(decoder.get_at(0, 8), 0);
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::b64_decoder::{B64RansDecSymbol, B64RansDecoderMulti};
+    use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoderMulti};
+    use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+    use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::per_channel::{PerChannelDecoder, PerChannelEncoder};
+
+    fn models() -> [Model; 3] {
+        [
+            Model::from_counts(&[100, 10, 1], 12),
+            Model::from_counts(&[1; 200], 12),
+            Model::from_counts(&[5, 0, 5, 0, 5, 0, 5], 12),
+        ]
+    }
+
+    fn random_pixels(len: usize) -> Vec<[usize; 3]> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(len as u64);
+        (0..len)
+            .map(|_| {
+                [
+                    rng.gen_range(0..3),
+                    rng.gen_range(0..200),
+                    rng.gen_range(0..4) * 2,
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_byte() {
+        let models = models();
+        let enc_models: Vec<_> = models
+            .iter()
+            .map(EncModel::<ByteRansEncSymbol>::new)
+            .collect();
+        let dec_models: Vec<_> = models
+            .iter()
+            .map(DecModel::<ByteRansDecSymbol>::new)
+            .collect();
+        let pixels = random_pixels(1000);
+
+        let mut encoder = PerChannelEncoder::new(
+            ByteRansEncoderMulti::<3>::new(1 << 16),
+            [&enc_models[0], &enc_models[1], &enc_models[2]],
+        );
+        for pixel in pixels.iter().rev() {
+            encoder.put_each(pixel);
+        }
+        encoder.flush();
+
+        let mut decoder = PerChannelDecoder::new(
+            ByteRansDecoderMulti::<3>::new(encoder.data()),
+            [&dec_models[0], &dec_models[1], &dec_models[2]],
+        );
+        for pixel in &pixels {
+            assert_eq!(decoder.decode_each(), *pixel);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_b64_mixed_order() {
+        let models = models();
+        let enc_models: Vec<_> = models
+            .iter()
+            .map(EncModel::<B64RansEncSymbol>::new)
+            .collect();
+        let dec_models: Vec<_> = models
+            .iter()
+            .map(DecModel::<B64RansDecSymbol>::new)
+            .collect();
+        let pixels = random_pixels(500);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(90);
+        let puts: Vec<(usize, usize)> = pixels
+            .iter()
+            .map(|pixel| {
+                let channel = rng.gen_range(0..3);
+                (channel, pixel[channel])
+            })
+            .collect();
+
+        let mut encoder = PerChannelEncoder::new(
+            B64RansEncoderMulti::<3>::new(1 << 16),
+            [&enc_models[0], &enc_models[1], &enc_models[2]],
+        );
+        for &(channel, symbol) in puts.iter().rev() {
+            encoder.put_at(channel, symbol);
+        }
+        encoder.flush();
+
+        let mut decoder = PerChannelDecoder::new(
+            B64RansDecoderMulti::<3>::new(encoder.data()),
+            [&dec_models[0], &dec_models[1], &dec_models[2]],
+        );
+        for &(channel, symbol) in &puts {
+            assert_eq!(decoder.decode_at(channel), symbol);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "symbol 1 is not a part of the model of channel 2")]
+    fn test_put_zero_frequency_symbol() {
+        let models = models();
+        let enc_models: Vec<_> = models
+            .iter()
+            .map(EncModel::<ByteRansEncSymbol>::new)
+            .collect();
+
+        let mut encoder = PerChannelEncoder::new(
+            ByteRansEncoderMulti::<3>::new(1024),
+            [&enc_models[0], &enc_models[1], &enc_models[2]],
+        );
+        encoder.put_at(2, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "symbol 100 is not a part of the model of channel 0")]
+    fn test_put_symbol_from_other_channel() {
+        let models = models();
+        let enc_models: Vec<_> = models
+            .iter()
+            .map(EncModel::<ByteRansEncSymbol>::new)
+            .collect();
+
+        let mut encoder = PerChannelEncoder::new(
+            ByteRansEncoderMulti::<3>::new(1024),
+            [&enc_models[0], &enc_models[1], &enc_models[2]],
+        );
+        encoder.put_each(&[100, 0, 0]);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+use crate::model::{DecModel, EncModel, Model};
+use crate::per_channel::PerChannelEncoder;
+use crate::simple::{
+    decode_symbol_checked, histogram, output_buffer, read_model, write_model, SCALE_BITS,
+};
+use crate::{varint, RansError};
+
+/// Compresses `N` planes of equal length (e.g. the R, G, B and A planes of an
+/// image, or the channels of an audio stream), each with its own order-0
+/// model.
+///
+/// Every plane gets a channel of a multi-stream encoder, and the planes are
+/// interleaved sample by sample, so that the decoder can decode the channels
+/// in parallel. The output starts with a header containing the length of the
+/// planes and the model of each of them. Use [`decompress_planes()`] to get
+/// the original planes back.
+///
+/// # Panics
+/// Panics if `N` is 0, or if the planes are not of equal length.
+///
+/// # Examples
+/// ```
+/// use rans::planar::{compress_planes, decompress_planes};
+///
+/// let red = [255, 250, 255, 255];
+/// let green = [0, 0, 10, 0];
+/// let blue = [128, 128, 128, 129];
+/// let compressed = compress_planes([&red, &green, &blue]);
+/// assert_eq!(decompress_planes(&compressed).unwrap(), [red, green, blue]);
+/// ```
+#[must_use]
+pub fn compress_planes<const N: usize>(planes: [&[u8]; N]) -> Vec<u8> {
+    assert!(N > 0, "there must be at least one plane");
+    let len = planes[0].len();
+    assert!(
+        planes.iter().all(|plane| plane.len() == len),
+        "all planes must be of equal length"
+    );
+
+    let mut output = Vec::new();
+    varint::write_u64(&mut output, len as u64);
+    if len == 0 {
+        return output;
+    }
+
+    let models = planes.map(|plane| Model::from_counts(&histogram(plane), SCALE_BITS));
+    for model in &models {
+        write_model(&mut output, model);
+    }
+
+    // With 12-bit frequencies, a single symbol emits at most 2 bytes.
+    let enc_models: Vec<_> = models
+        .iter()
+        .map(EncModel::<ByteRansEncSymbol>::new)
+        .collect();
+    let mut encoder = PerChannelEncoder::new(
+        ByteRansEncoderMulti::<N>::new(N * (len * 2 + 4)),
+        std::array::from_fn(|channel| &enc_models[channel]),
+    );
+    for i in (0..len).rev() {
+        encoder.put_each(&planes.map(|plane| usize::from(plane[i])));
+    }
+    encoder.flush();
+
+    output.extend_from_slice(encoder.data());
+    output
+}
+
+/// Decompresses data produced by [`compress_planes()`], returning the planes
+/// as separate buffers.
+///
+/// Malformed data results in an error, but the length of the planes is only
+/// limited by the header.
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the input is truncated,
+/// [`RansError::InvalidModel`] if the header is malformed,
+/// [`RansError::InputExhausted`] if the bitstream ends before all the planes
+/// are decoded, or [`RansError::InvalidHeader`] if the length is malformed or
+/// doesn't match the bitstream.
+///
+/// # Panics
+/// Panics if `N` is 0.
+///
+/// # Examples
+/// ```
+/// use rans::planar::{compress_planes, decompress_planes};
+/// use rans::RansError;
+///
+/// let compressed = compress_planes([b"left", b"rght"]);
+/// let [left, right] = decompress_planes(&compressed).unwrap();
+/// assert_eq!(left, b"left");
+/// assert_eq!(right, b"rght");
+/// assert_eq!(
+///     decompress_planes::<2>(&compressed[..3]),
+///     Err(RansError::UnexpectedEof)
+/// );
+/// ```
+pub fn decompress_planes<const N: usize>(data: &[u8]) -> Result<[Vec<u8>; N], RansError> {
+    assert!(N > 0, "there must be at least one plane");
+
+    let mut pos = 0;
+    let len =
+        usize::try_from(varint::read_u64(data, &mut pos)?).map_err(|_| RansError::InvalidHeader)?;
+    if len == 0 {
+        return Ok(std::array::from_fn(|_| Vec::new()));
+    }
+
+    let mut models = Vec::with_capacity(N);
+    for _ in 0..N {
+        models.push(read_model(data, &mut pos)?);
+    }
+    let payload = &data[pos..];
+    if payload.len() < N * 4 {
+        return Err(RansError::UnexpectedEof);
+    }
+
+    let dec_models: Vec<_> = models
+        .iter()
+        .map(DecModel::<ByteRansDecSymbol>::new)
+        .collect();
+    let mut decoder = ByteRansDecoderMulti::<N>::new(payload);
+    let mut planes: [Vec<u8>; N] = std::array::from_fn(|_| output_buffer(&decoder, len));
+    for _ in 0..len {
+        for (channel, (plane, dec_model)) in planes.iter_mut().zip(&dec_models).enumerate() {
+            plane.push(decode_symbol_checked(&mut decoder, channel, dec_model)? as u8);
+        }
+    }
+    if !decoder.is_exhausted() {
+        return Err(RansError::InvalidHeader);
+    }
+
+    Ok(planes)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::planar::{compress_planes, decompress_planes};
+    use crate::simple::compress;
+    use crate::{varint, RansError};
+
+    fn random_plane(rng: &mut Xoshiro256PlusPlus, len: usize, values: &[u8]) -> Vec<u8> {
+        (0..len)
+            .map(|_| values[rng.gen_range(0..values.len())])
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_rgba() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(91);
+        let red = random_plane(&mut rng, 5000, &[200, 201, 202, 255]);
+        let green = random_plane(&mut rng, 5000, &[0, 1]);
+        let blue: Vec<u8> = (0..5000).map(|_| rng.gen()).collect();
+        let alpha = vec![255; 5000];
+
+        let compressed = compress_planes([&red, &green, &blue, &alpha]);
+        assert_eq!(
+            decompress_planes(&compressed).unwrap(),
+            [red, green, blue, alpha]
+        );
+    }
+
+    #[test]
+    fn test_round_trip_empty() {
+        let compressed = compress_planes([&[], &[], &[]]);
+
+        assert_eq!(compressed, [0]);
+        assert_eq!(
+            decompress_planes::<3>(&compressed),
+            Ok([Vec::new(), Vec::new(), Vec::new()])
+        );
+    }
+
+    #[test]
+    fn test_round_trip_single_plane() {
+        let compressed = compress_planes([b"abracadabra"]);
+
+        assert_eq!(
+            decompress_planes(&compressed),
+            Ok([b"abracadabra".to_vec()])
+        );
+    }
+
+    #[test]
+    fn test_smaller_than_interleaved_order_0() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(91);
+        let left = random_plane(&mut rng, 10000, &[0, 1, 2, 3]);
+        let right = random_plane(&mut rng, 10000, &[4, 5, 6, 7]);
+        let interleaved: Vec<u8> = left
+            .iter()
+            .zip(&right)
+            .flat_map(|(&left, &right)| [left, right])
+            .collect();
+
+        // 2 bits per sample instead of 3
+        let planar = compress_planes([&left, &right]);
+        assert!(planar.len() * 5 < compress(&interleaved).len() * 4);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let compressed = compress_planes([b"aab", b"xyz"]);
+
+        for len in 0..compressed.len() - 8 {
+            assert_eq!(
+                decompress_planes::<2>(&compressed[..len]),
+                Err(RansError::UnexpectedEof)
+            );
+        }
+    }
+
+    #[test]
+    fn test_declared_len_mismatch() {
+        let compressed = compress_planes([b"aab", b"xyz"]);
+
+        for (len, error) in [
+            ((1 << 46) - 1, RansError::InputExhausted),
+            (2, RansError::InvalidHeader),
+        ] {
+            let mut corrupted = Vec::new();
+            varint::write_u64(&mut corrupted, len);
+            corrupted.extend_from_slice(&compressed[1..]);
+
+            assert_eq!(decompress_planes::<2>(&corrupted), Err(error));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "all planes must be of equal length")]
+    fn test_unequal_lengths() {
+        let _ = compress_planes([b"ab", b"abc"]);
+    }
+}
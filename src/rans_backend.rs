@@ -0,0 +1,264 @@
+use crate::b64_decoder::{B64RansDecSymbol, B64RansDecoderMulti};
+use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoderMulti};
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoderMulti};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+use crate::decoder_input::DecoderInput;
+use crate::simd_decoder::{SimdRansDecSymbol, SimdRansDecoder};
+use crate::simd_encoder::{SimdRansEncSymbol, SimdRansEncoder};
+use crate::{
+    b64_encoder, byte_encoder, simd_encoder, RansDecSymbol, RansDecoderMulti, RansEncSymbol,
+    RansEncoderMulti, RansError,
+};
+
+/// Family of rANS encoders and decoders sharing the same bitstream format,
+/// such as the byte-aligned or the 64-bit one.
+///
+/// This allows writing code generic over the coder variant: the encoder,
+/// decoder and symbol types, as well as the ways to construct them, are all
+/// available through the backend type.
+///
+/// # Examples
+/// ```
+/// use rans::model::{DecModel, EncModel, Model};
+/// use rans::{B64Backend, ByteBackend, RansBackend, RansDecoderMulti, RansEncoderMulti};
+///
+/// fn round_trip<B: RansBackend>(model: &Model, symbols: &[usize]) -> Vec<usize> {
+///     let enc_model = EncModel::<B::EncSymbol>::new(model);
+///     let mut encoder = B::new_encoder::<1>(B::max_encoded_len::<1>(symbols.len()));
+///     for &symbol in symbols.iter().rev() {
+///         encoder.put_at(0, enc_model.symbol(symbol));
+///     }
+///     encoder.flush_all();
+///
+///     let dec_model = DecModel::<B::DecSymbol>::new(model);
+///     let mut decoder = B::new_decoder::<1, _>(encoder.data());
+///     symbols
+///         .iter()
+///         .map(|_| decoder.decode_symbol_at(0, &dec_model))
+///         .collect()
+/// }
+///
+/// let model = Model::from_counts(&[1, 5, 2], 12);
+/// assert_eq!(round_trip::<ByteBackend>(&model, &[1, 2, 0]), [1, 2, 0]);
+/// assert_eq!(round_trip::<B64Backend>(&model, &[1, 2, 0]), [1, 2, 0]);
+/// ```
+pub trait RansBackend {
+    /// Maximum number of bits used for frequency quantization supported by
+    /// the backend.
+    const MAX_SCALE_BITS: u32;
+    /// Maximum number of bytes a single symbol can emit.
+    const MAX_PUT_LEN: usize;
+    /// Number of bytes flushing a single channel emits.
+    const FLUSH_LEN: usize;
+
+    /// Encoder symbol type.
+    type EncSymbol: RansEncSymbol + Clone;
+    /// Decoder symbol type.
+    type DecSymbol: RansDecSymbol + Clone;
+    /// Encoder type with `N` interleaved channels.
+    type Encoder<const N: usize>: RansEncoderMulti<N, Symbol = Self::EncSymbol>;
+    /// Decoder type with `N` interleaved channels.
+    type Decoder<'a, const N: usize>: RansDecoderMulti<N, Symbol = Self::DecSymbol>;
+
+    /// Creates a new encoder that can contain `max_len` bytes in the internal
+    /// buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::{ByteBackend, RansBackend, RansEncoderMulti};
+    ///
+    /// let encoder = ByteBackend::new_encoder::<2>(1024);
+    /// assert!(encoder.is_empty());
+    /// ```
+    #[must_use]
+    fn new_encoder<const N: usize>(max_len: usize) -> Self::Encoder<N>;
+
+    /// Creates a new decoder reading given data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::{ByteBackend, RansBackend, RansDecoderMulti};
+    ///
+    /// let mut decoder = ByteBackend::new_decoder::<1, _>([0, 0, 128, 0]);
+    /// assert_eq!(decoder.get_at(0, 4), 0);
+    /// ```
+    #[must_use]
+    fn new_decoder<'a, const N: usize, T: Into<DecoderInput<'a>>>(data: T) -> Self::Decoder<'a, N>;
+
+    /// Creates a new decoder reading given data, checking that it is long
+    /// enough to contain the initial states.
+    ///
+    /// # Errors
+    /// Returns [`RansError::EmptyInput`] if `data` is empty, or
+    /// [`RansError::UnexpectedEof`] if it is too short to contain the initial
+    /// states.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::{B64Backend, RansBackend, RansError};
+    ///
+    /// assert_eq!(
+    ///     B64Backend::try_new_decoder::<1, _>([0, 0, 0, 128]).unwrap_err(),
+    ///     RansError::UnexpectedEof
+    /// );
+    /// ```
+    fn try_new_decoder<'a, const N: usize, T: Into<DecoderInput<'a>>>(
+        data: T,
+    ) -> Result<Self::Decoder<'a, N>, RansError>;
+
+    /// Returns the maximum size of the output of an encoder with `N` channels
+    /// after putting `num_symbols` symbols and flushing all the channels.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::{ByteBackend, RansBackend};
+    ///
+    /// assert_eq!(ByteBackend::max_encoded_len::<2>(10), 28);
+    /// ```
+    #[must_use]
+    fn max_encoded_len<const N: usize>(num_symbols: usize) -> usize {
+        num_symbols * Self::MAX_PUT_LEN + N * Self::FLUSH_LEN
+    }
+}
+
+/// [`RansBackend`] of the byte-aligned coder from the
+/// [`byte_encoder`](crate::byte_encoder) and
+/// [`byte_decoder`](crate::byte_decoder) modules.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByteBackend;
+
+impl RansBackend for ByteBackend {
+    type DecSymbol = ByteRansDecSymbol;
+    type Decoder<'a, const N: usize> = ByteRansDecoderMulti<'a, N>;
+    type EncSymbol = ByteRansEncSymbol;
+    type Encoder<const N: usize> = ByteRansEncoderMulti<N>;
+
+    const FLUSH_LEN: usize = byte_encoder::FLUSH_LEN;
+    const MAX_PUT_LEN: usize = byte_encoder::MAX_PUT_LEN;
+    const MAX_SCALE_BITS: u32 = ByteRansEncSymbol::MAX_SCALE_BITS;
+
+    fn new_encoder<const N: usize>(max_len: usize) -> Self::Encoder<N> {
+        ByteRansEncoderMulti::new(max_len)
+    }
+
+    fn new_decoder<'a, const N: usize, T: Into<DecoderInput<'a>>>(data: T) -> Self::Decoder<'a, N> {
+        ByteRansDecoderMulti::new(data)
+    }
+
+    fn try_new_decoder<'a, const N: usize, T: Into<DecoderInput<'a>>>(
+        data: T,
+    ) -> Result<Self::Decoder<'a, N>, RansError> {
+        ByteRansDecoderMulti::try_new(data)
+    }
+}
+
+/// [`RansBackend`] of the 64-bit coder from the
+/// [`b64_encoder`](crate::b64_encoder) and
+/// [`b64_decoder`](crate::b64_decoder) modules.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct B64Backend;
+
+impl RansBackend for B64Backend {
+    type DecSymbol = B64RansDecSymbol;
+    type Decoder<'a, const N: usize> = B64RansDecoderMulti<'a, N>;
+    type EncSymbol = B64RansEncSymbol;
+    type Encoder<const N: usize> = B64RansEncoderMulti<N>;
+
+    const FLUSH_LEN: usize = b64_encoder::FLUSH_LEN;
+    const MAX_PUT_LEN: usize = b64_encoder::MAX_PUT_LEN;
+    const MAX_SCALE_BITS: u32 = B64RansEncSymbol::MAX_SCALE_BITS;
+
+    fn new_encoder<const N: usize>(max_len: usize) -> Self::Encoder<N> {
+        B64RansEncoderMulti::new(max_len)
+    }
+
+    fn new_decoder<'a, const N: usize, T: Into<DecoderInput<'a>>>(data: T) -> Self::Decoder<'a, N> {
+        B64RansDecoderMulti::new(data)
+    }
+
+    fn try_new_decoder<'a, const N: usize, T: Into<DecoderInput<'a>>>(
+        data: T,
+    ) -> Result<Self::Decoder<'a, N>, RansError> {
+        B64RansDecoderMulti::try_new(data)
+    }
+}
+
+/// [`RansBackend`] of the 16-bit word coder from the
+/// [`simd_encoder`](crate::simd_encoder) and
+/// [`simd_decoder`](crate::simd_decoder) modules.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimdBackend;
+
+impl RansBackend for SimdBackend {
+    type DecSymbol = SimdRansDecSymbol;
+    type Decoder<'a, const N: usize> = SimdRansDecoder<'a, N>;
+    type EncSymbol = SimdRansEncSymbol;
+    type Encoder<const N: usize> = SimdRansEncoder<N>;
+
+    const FLUSH_LEN: usize = simd_encoder::FLUSH_LEN;
+    const MAX_PUT_LEN: usize = simd_encoder::MAX_PUT_LEN;
+    const MAX_SCALE_BITS: u32 = SimdRansEncSymbol::MAX_SCALE_BITS;
+
+    fn new_encoder<const N: usize>(max_len: usize) -> Self::Encoder<N> {
+        SimdRansEncoder::new(max_len)
+    }
+
+    fn new_decoder<'a, const N: usize, T: Into<DecoderInput<'a>>>(data: T) -> Self::Decoder<'a, N> {
+        SimdRansDecoder::new(data)
+    }
+
+    fn try_new_decoder<'a, const N: usize, T: Into<DecoderInput<'a>>>(
+        data: T,
+    ) -> Result<Self::Decoder<'a, N>, RansError> {
+        SimdRansDecoder::try_new(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::{
+        B64Backend, ByteBackend, RansBackend, RansDecoderMulti, RansEncSymbol, RansEncoderMulti,
+        SimdBackend,
+    };
+
+    fn round_trip<B: RansBackend>() {
+        let model = Model::from_counts(&[7, 1, 30, 2, 5, 11], B::MAX_SCALE_BITS.min(14));
+        let enc_model = EncModel::<B::EncSymbol>::new(&model);
+        let dec_model = DecModel::<B::DecSymbol>::new(&model);
+        let symbols: Vec<usize> = (0..1000).map(|i| [2, 0, 5, 2, 3, 1, 2][i % 7]).collect();
+
+        let mut encoder = B::new_encoder::<4>(B::max_encoded_len::<4>(symbols.len()));
+        let refs: Vec<&B::EncSymbol> = symbols.iter().map(|&s| enc_model.symbol(s)).collect();
+        encoder.put_all(&refs);
+        encoder.flush_all();
+        assert!(encoder.len() <= B::max_encoded_len::<4>(symbols.len()));
+
+        let mut decoder = B::try_new_decoder::<4, _>(encoder.data()).unwrap();
+        assert_eq!(decoder.decode_n(&dec_model, symbols.len()), symbols);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip::<ByteBackend>();
+        round_trip::<B64Backend>();
+        round_trip::<SimdBackend>();
+    }
+
+    #[test]
+    fn test_max_put_len() {
+        fn check<B: RansBackend>() {
+            let symbol = B::EncSymbol::new(0, 1, B::MAX_SCALE_BITS);
+            let mut encoder = B::new_encoder::<1>(B::max_encoded_len::<1>(100));
+            for _ in 0..100 {
+                encoder.put_at(0, &symbol);
+            }
+            encoder.flush_all();
+            assert!(encoder.len() <= B::max_encoded_len::<1>(100));
+        }
+
+        check::<ByteBackend>();
+        check::<B64Backend>();
+        check::<SimdBackend>();
+    }
+}
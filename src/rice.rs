@@ -0,0 +1,473 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::model::{DecModel, EncModel, Model};
+use crate::simple::{decode_symbol_checked, output_buffer, scale_counts, SCALE_BITS};
+use crate::{varint, RansDecoder, RansEncoder, RansEncoderMulti, RansError};
+
+/// Number of residuals in a block coded by [`encode()`].
+pub const DEFAULT_BLOCK_SIZE: usize = 4096;
+/// Largest Rice parameter.
+pub const MAX_PARAMETER: u32 = 31;
+/// Token of the values whose quotient is too large to be coded as a token,
+/// and which are coded as 32 raw bits instead.
+const ESCAPE: u32 = 32;
+/// Number of the quotient tokens, including [`ESCAPE`].
+const NUM_TOKENS: usize = ESCAPE as usize + 1;
+
+/// Returns the Rice parameter minimizing the size of given values coded with
+/// the plain Rice code, i.e. `(value >> k) + 1 + k` bits per value, the way
+/// FLAC chooses it. The rANS-coded quotients of [`encode_block()`] usually
+/// take much less than that, but the parameter minimizing both is about the
+/// same.
+///
+/// # Examples
+/// ```
+/// use rans::rice::rice_parameter;
+///
+/// assert_eq!(rice_parameter(&[]), 0);
+/// assert_eq!(rice_parameter(&[0, 1, 0, 2]), 0);
+/// assert_eq!(rice_parameter(&[1000, 900, 1200, 800]), 9);
+/// ```
+#[must_use]
+pub fn rice_parameter(values: &[u32]) -> u32 {
+    let cost = |k: u32| {
+        values
+            .iter()
+            .map(|&value| u64::from(value >> k) + 1 + u64::from(k))
+            .sum::<u64>()
+    };
+    (0..=MAX_PARAMETER)
+        .min_by_key(|&k| cost(k))
+        .expect("the range is not empty")
+}
+
+/// Maps a signed residual to an unsigned value, interleaving the positive and
+/// negative residuals (0, -1, 1, -2, ...), so that small magnitudes get small
+/// values.
+#[inline]
+fn fold(residual: i32) -> u32 {
+    (residual << 1 ^ residual >> 31) as u32
+}
+
+/// Inverse of [`fold()`].
+#[inline]
+fn unfold(value: u32) -> i32 {
+    (value >> 1) as i32 ^ -((value & 1) as i32)
+}
+
+/// Encodes a single block of residuals, e.g. a FLAC-like subframe, with
+/// a Rice parameter chosen by [`rice_parameter()`].
+///
+/// Every residual is folded to an unsigned value (0, -1, 1, -2, ... become
+/// 0, 1, 2, 3, ...) and split into the `k` least significant bits and the
+/// quotient. The quotients are coded as tokens with a static model of the
+/// block; quotients of 32 and more are escaped, and the whole value is coded
+/// as 32 raw bits. The least significant bits are coded as raw (bypass) bits
+/// in the same rANS stream.
+///
+/// The output is, in this order:
+/// * the Rice parameter `k`, as a single byte,
+/// * the model of the tokens, serialized with [`Model::to_bytes()`], with 33
+///   tokens and 12 `scale_bits`,
+/// * the bitstream of a byte-aligned rANS encoder, with the token of every
+///   residual followed by its raw bits, in the order of the residuals.
+///
+/// An empty block is encoded as no bytes at all. The length of the block is
+/// not stored, so it has to be known when decoding with [`decode_block()`].
+/// This format is stable and will not change in the future versions of this
+/// crate.
+///
+/// # Examples
+/// ```
+/// use rans::rice::{decode_block, encode_block};
+///
+/// let residuals = [3, -1, 0, 12, -7, 2, -2, 1];
+/// let encoded = encode_block(&residuals);
+/// assert_eq!(encoded[0], 2);
+/// assert_eq!(decode_block(&encoded, residuals.len()).unwrap(), residuals);
+/// ```
+#[must_use]
+pub fn encode_block(residuals: &[i32]) -> Vec<u8> {
+    if residuals.is_empty() {
+        return Vec::new();
+    }
+
+    let values: Vec<u32> = residuals.iter().map(|&residual| fold(residual)).collect();
+    let k = rice_parameter(&values);
+    let token = |value: u32| (value >> k).min(ESCAPE) as usize;
+
+    let mut counts = [0_u64; NUM_TOKENS];
+    for &value in &values {
+        counts[token(value)] += 1;
+    }
+    let model = Model::from_counts(&scale_counts(&counts), SCALE_BITS);
+    let mut output = vec![k as u8];
+    output.extend_from_slice(&model.to_bytes());
+
+    let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    let mut encoder = ByteRansEncoder::with_growable_buffer();
+    for &value in values.iter().rev() {
+        let token = token(value);
+        if token == ESCAPE as usize {
+            encoder.put_bits(value, 32);
+        } else {
+            encoder.put_bits(value & low_mask(k), k);
+        }
+        encoder.put_index(token, &enc_model);
+    }
+    encoder.flush();
+
+    output.extend_from_slice(encoder.data());
+    output
+}
+
+/// Decodes a block of `len` residuals encoded with [`encode_block()`].
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the block is truncated,
+/// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if the header is
+/// malformed, [`RansError::InputExhausted`] if the bitstream ends before all
+/// the residuals are decoded, or [`RansError::InvalidSymbol`] if a decoded
+/// value doesn't fit in 32 bits, which means the data is corrupted.
+///
+/// # Examples
+/// ```
+/// use rans::rice::{decode_block, encode_block};
+/// use rans::RansError;
+///
+/// let encoded = encode_block(&[100, -100]);
+/// assert_eq!(decode_block(&encoded, 2).unwrap(), [100, -100]);
+/// assert_eq!(decode_block(&encoded[..1], 2), Err(RansError::UnexpectedEof));
+/// assert!(decode_block(&[], 0).unwrap().is_empty());
+/// ```
+pub fn decode_block(data: &[u8], len: usize) -> Result<Vec<i32>, RansError> {
+    if len == 0 {
+        return if data.is_empty() {
+            Ok(Vec::new())
+        } else {
+            Err(RansError::InvalidHeader)
+        };
+    }
+
+    let k = u32::from(*data.first().ok_or(RansError::UnexpectedEof)?);
+    if k > MAX_PARAMETER {
+        return Err(RansError::InvalidHeader);
+    }
+    let mut pos = 1;
+    let model = Model::read_bytes(data, &mut pos)?;
+    if model.scale_bits() != SCALE_BITS || model.len() != NUM_TOKENS {
+        return Err(RansError::InvalidHeader);
+    }
+    let payload = &data[pos..];
+    if payload.len() < 4 {
+        return Err(RansError::UnexpectedEof);
+    }
+
+    let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    let mut decoder = ByteRansDecoder::new(payload);
+    let mut output = output_buffer(&decoder, len);
+    for _ in 0..len {
+        let token = decode_symbol_checked(&mut decoder, 0, &dec_model)? as u32;
+        let value = if token == ESCAPE {
+            decoder.try_get_bits(32)?
+        } else {
+            let high =
+                u32::try_from(u64::from(token) << k).map_err(|_| RansError::InvalidSymbol)?;
+            high | decoder.try_get_bits(k)?
+        };
+        output.push(unfold(value));
+    }
+
+    Ok(output)
+}
+
+/// Encodes residuals in blocks of [`DEFAULT_BLOCK_SIZE`]. See
+/// [`encode_with_block_size()`].
+///
+/// # Examples
+/// ```
+/// use rans::rice::{decode, encode};
+///
+/// let residuals: Vec<i32> = (0..10000).map(|i| (i % 37) - 18).collect();
+/// let encoded = encode(&residuals);
+/// assert!(encoded.len() < residuals.len());
+/// assert_eq!(decode(&encoded).unwrap(), residuals);
+/// ```
+#[must_use]
+pub fn encode(residuals: &[i32]) -> Vec<u8> {
+    encode_with_block_size(residuals, DEFAULT_BLOCK_SIZE)
+}
+
+/// Encodes residuals in blocks of given size, each with its own Rice
+/// parameter and model, so that the coding follows the changes in the
+/// statistics of the signal.
+///
+/// The output is the number of residuals and the block size, both as
+/// LEB128 varints, followed by every block as its length in bytes (also a
+/// varint) and the block itself, encoded with [`encode_block()`]. The last
+/// block may be shorter than the others. Like the format of the blocks, this
+/// format is stable.
+///
+/// # Panics
+/// Panics if `block_size` is zero.
+///
+/// # Examples
+/// ```
+/// use rans::rice::{decode, encode_with_block_size};
+///
+/// // A quiet signal getting loud
+/// let residuals: Vec<i32> = (0..2000).map(|i| (i % 7 - 3) * if i < 1000 { 1 } else { 1000 }).collect();
+/// let encoded = encode_with_block_size(&residuals, 500);
+/// assert_eq!(decode(&encoded).unwrap(), residuals);
+/// ```
+#[must_use]
+pub fn encode_with_block_size(residuals: &[i32], block_size: usize) -> Vec<u8> {
+    assert!(block_size > 0, "block_size must not be zero");
+
+    let mut output = Vec::new();
+    varint::write_u64(&mut output, residuals.len() as u64);
+    varint::write_u64(&mut output, block_size as u64);
+    for block in residuals.chunks(block_size) {
+        let encoded = encode_block(block);
+        varint::write_u64(&mut output, encoded.len() as u64);
+        output.extend_from_slice(&encoded);
+    }
+
+    output
+}
+
+/// Decodes residuals encoded with [`encode()`] or
+/// [`encode_with_block_size()`].
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the input is truncated,
+/// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if any of the
+/// headers is malformed, or any of the errors of [`decode_block()`] if one
+/// of the blocks is corrupted.
+///
+/// # Examples
+/// ```
+/// use rans::rice::{decode, encode};
+/// use rans::RansError;
+///
+/// let encoded = encode(&[1, -2, 3]);
+/// assert_eq!(decode(&encoded).unwrap(), [1, -2, 3]);
+/// assert_eq!(decode(&encoded[..4]), Err(RansError::UnexpectedEof));
+/// ```
+pub fn decode(data: &[u8]) -> Result<Vec<i32>, RansError> {
+    let mut pos = 0;
+    let len =
+        usize::try_from(varint::read_u64(data, &mut pos)?).map_err(|_| RansError::InvalidHeader)?;
+    let block_size =
+        usize::try_from(varint::read_u64(data, &mut pos)?).map_err(|_| RansError::InvalidHeader)?;
+    if block_size == 0 {
+        return Err(RansError::InvalidHeader);
+    }
+
+    let mut output = Vec::new();
+    while output.len() < len {
+        let block_len = usize::try_from(varint::read_u64(data, &mut pos)?)
+            .map_err(|_| RansError::InvalidHeader)?;
+        let block = data
+            .get(pos..)
+            .and_then(|rest| rest.get(..block_len))
+            .ok_or(RansError::UnexpectedEof)?;
+        pos += block_len;
+        output.extend(decode_block(block, block_size.min(len - output.len()))?);
+    }
+    if pos != data.len() {
+        return Err(RansError::InvalidHeader);
+    }
+
+    Ok(output)
+}
+
+#[inline]
+fn low_mask(bits: u32) -> u32 {
+    if bits >= 32 {
+        u32::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::rice::{
+        decode, decode_block, encode, encode_block, encode_with_block_size, fold, rice_parameter,
+        unfold,
+    };
+    use crate::RansError;
+
+    /// Residuals of a predictor of a noisy signal, with a roughly Laplacian
+    /// distribution whose scale changes every now and then.
+    fn residuals(len: usize, seed: u64) -> Vec<i32> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+        let mut scale = 16.0;
+        (0..len)
+            .map(|i| {
+                if i % 3000 == 0 {
+                    scale = f64::from(rng.gen_range(1..2000));
+                }
+                let magnitude = (-scale * rng.gen::<f64>().ln()) as i32;
+                if rng.gen() {
+                    magnitude
+                } else {
+                    -magnitude
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fold() {
+        for (residual, value) in [(0, 0), (-1, 1), (1, 2), (-2, 3), (2, 4)] {
+            assert_eq!(fold(residual), value);
+            assert_eq!(unfold(value), residual);
+        }
+        assert_eq!(fold(i32::MAX), u32::MAX - 1);
+        assert_eq!(fold(i32::MIN), u32::MAX);
+        assert_eq!(unfold(u32::MAX), i32::MIN);
+    }
+
+    #[test]
+    fn test_rice_parameter() {
+        assert_eq!(rice_parameter(&[0; 100]), 0);
+        assert_eq!(rice_parameter(&[u32::MAX; 100]), 31);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(2);
+        for mean_bits in 2..24 {
+            // For geometrically distributed values, the best parameter is
+            // about log2(mean * ln(2))
+            let mean = f64::from(1 << mean_bits);
+            let values: Vec<u32> = (0..1000)
+                .map(|_| (-mean * rng.gen::<f64>().ln()) as u32)
+                .collect();
+            let k = rice_parameter(&values);
+            assert!(
+                mean_bits - 2 <= k && k <= mean_bits,
+                "{k} for mean 2^{mean_bits}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_trip_blocks() {
+        for len in [1, 2, 100, 5000] {
+            let residuals = residuals(len, len as u64);
+            let encoded = encode_block(&residuals);
+            assert_eq!(decode_block(&encoded, len).unwrap(), residuals);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_extremes() {
+        let residuals = [i32::MIN, i32::MAX, 0, -1, 1, i32::MIN, 0, 0, 0, 0, 5];
+        let encoded = encode_block(&residuals);
+        assert_eq!(decode_block(&encoded, residuals.len()).unwrap(), residuals);
+
+        // Silence, where everything but the header is nearly free
+        let encoded = encode(&[0; 100000]);
+        assert!(encoded.len() < 100000 / 4096 * 16);
+        assert_eq!(decode(&encoded).unwrap(), [0; 100000]);
+    }
+
+    #[test]
+    fn test_round_trip_stream() {
+        let residuals = residuals(20000, 0);
+        for block_size in [1, 7, 4096, 100000] {
+            let encoded = encode_with_block_size(&residuals, block_size);
+            assert_eq!(decode(&encoded).unwrap(), residuals);
+        }
+        assert!(decode(&encode(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_beats_plain_rice() {
+        let residuals = residuals(30000, 1);
+        let encoded = encode(&residuals);
+
+        // Plain Rice coding with the same parameters, as in FLAC
+        let rice_bits: u64 = residuals
+            .chunks(4096)
+            .map(|block| {
+                let values: Vec<u32> = block.iter().map(|&residual| fold(residual)).collect();
+                let k = rice_parameter(&values);
+                values
+                    .iter()
+                    .map(|&value| u64::from(value >> k) + 1 + u64::from(k))
+                    .sum::<u64>()
+            })
+            .sum();
+        assert!((encoded.len() as u64) < rice_bits / 8);
+    }
+
+    #[test]
+    fn test_stable_bitstream() {
+        let encoded = encode_block(&[3, -1, 0, 12, -7, 2, -2, 1, 0, -40, 1, 0]);
+        assert_eq!(
+            encoded,
+            [
+                3, 12, 33, 129, 48, 212, 42, 0, 0, 2, 0, 4, 1, 0, 22, 62, 90, 207, 2, 193, 232,
+                165, 19, 64, 2
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let encoded = encode_block(&[1, 2, 3]);
+
+        let mut corrupted = encoded.clone();
+        corrupted[0] = 32;
+        assert_eq!(decode_block(&corrupted, 3), Err(RansError::InvalidHeader));
+        assert_eq!(decode_block(&encoded, 0), Err(RansError::InvalidHeader));
+        for len in 0..encoded.len() - 4 {
+            assert_eq!(
+                decode_block(&encoded[..len], 3),
+                Err(RansError::UnexpectedEof)
+            );
+        }
+
+        // Trailing data after the last block, and a zero block size
+        let mut encoded = encode(&[1, 2, 3]);
+        encoded.push(0);
+        assert_eq!(decode(&encoded), Err(RansError::InvalidHeader));
+        assert_eq!(decode(&[3, 0]), Err(RansError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_truncated_bitstream() {
+        // With escaped values, so that the raw bits run out as well
+        let mut residuals = residuals(1000, 2);
+        residuals[500] = i32::MIN;
+        let encoded = encode_block(&residuals);
+
+        for cut in 1..64 {
+            assert_eq!(
+                decode_block(&encoded[..encoded.len() - cut], residuals.len()),
+                Err(RansError::InputExhausted)
+            );
+        }
+    }
+
+    #[test]
+    fn test_corrupted_bitstream() {
+        let mut residuals = residuals(1000, 3);
+        residuals[500] = i32::MAX;
+        let encoded = encode(&residuals);
+
+        // Must not read out of bounds or panic, whatever the result is
+        for i in encoded.len() - 64..encoded.len() {
+            for bit in 0..8 {
+                let mut corrupted = encoded.clone();
+                corrupted[i] ^= 1 << bit;
+                if let Ok(output) = decode(&corrupted) {
+                    assert_eq!(output.len(), residuals.len());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Search over sorted cumulative frequency arrays, vectorized where
+//! possible.
+
+/// Number of values left by the binary search for the linear scan. A small
+/// window is scanned faster with vector compares than it would be narrowed
+/// down any further with unpredictable branches.
+const SCAN_WINDOW: usize = 32;
+
+/// Returns the number of values in sorted (non-decreasing) `values` that are
+/// less than or equal to `value`, i.e. the index of the first value larger
+/// than `value`.
+pub(crate) fn count_at_most(values: &[u32], value: u32) -> usize {
+    // Everything before `lo` is at most `value`, everything from `hi` on is
+    // larger
+    let mut lo = 0;
+    let mut hi = values.len();
+    while hi - lo > SCAN_WINDOW {
+        let mid = lo + (hi - lo) / 2;
+        if values[mid] <= value {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+
+    lo + scan(&values[lo..hi], value)
+}
+
+/// Returns the number of leading values that are at most `value`.
+#[inline]
+fn scan(values: &[u32], value: u32) -> usize {
+    #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "avx2"))]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { x86::scan_avx2(values, value) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    return unsafe { aarch64::scan_neon(values, value) };
+
+    #[allow(unreachable_code)]
+    scan_scalar(values, value)
+}
+
+#[inline]
+fn scan_scalar(values: &[u32], value: u32) -> usize {
+    values.iter().take_while(|&&x| x <= value).count()
+}
+
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "avx2"))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn scan_avx2(values: &[u32], value: u32) -> usize {
+        // There are no unsigned compares, so both sides get their sign bit
+        // flipped and are compared as signed integers
+        let sign = _mm256_set1_epi32(i32::MIN);
+        let value_vec = _mm256_xor_si256(_mm256_set1_epi32(value as i32), sign);
+
+        let chunks = values.len() / 8;
+        for i in 0..chunks {
+            let x = _mm256_loadu_si256(values.as_ptr().add(i * 8) as *const __m256i);
+            let larger = _mm256_cmpgt_epi32(_mm256_xor_si256(x, sign), value_vec);
+            let mask = _mm256_movemask_ps(_mm256_castsi256_ps(larger));
+            if mask != 0 {
+                return i * 8 + mask.trailing_zeros() as usize;
+            }
+        }
+
+        chunks * 8 + super::scan_scalar(&values[chunks * 8..], value)
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64 {
+    use std::arch::aarch64::*;
+
+    pub(super) unsafe fn scan_neon(values: &[u32], value: u32) -> usize {
+        let value_vec = vdupq_n_u32(value);
+
+        let chunks = values.len() / 4;
+        for i in 0..chunks {
+            let x = vld1q_u32(values.as_ptr().add(i * 4));
+            // All-ones lanes for the values at most `value`, which, the
+            // values being sorted, are the leading ones
+            let at_most = vcleq_u32(x, value_vec);
+            let count = vaddvq_u32(vshrq_n_u32::<31>(at_most)) as usize;
+            if count < 4 {
+                return i * 4 + count;
+            }
+        }
+
+        chunks * 4 + super::scan_scalar(&values[chunks * 4..], value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::search::{count_at_most, scan, scan_scalar};
+
+    #[test]
+    fn test_count_at_most() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+
+        for len in [0, 1, 3, 8, 31, 32, 33, 100, 256, 1000] {
+            let mut values: Vec<u32> = (0..len).map(|_| rng.gen_range(0..50)).collect();
+            values.sort_unstable();
+
+            for value in 0..55 {
+                let expected = values.partition_point(|&x| x <= value);
+                assert_eq!(count_at_most(&values, value), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_large_values() {
+        let values = [0, 1 << 30, (1 << 31) - 1, 1 << 31, 1 << 31, u32::MAX];
+
+        for value in [0, 1, 1 << 30, 1 << 31, u32::MAX - 1, u32::MAX] {
+            for len in 0..=values.len() {
+                let values = &values[..len];
+                assert_eq!(scan(values, value), scan_scalar(values, value));
+            }
+        }
+    }
+}
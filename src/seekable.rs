@@ -0,0 +1,312 @@
+use std::ops::Range;
+
+use crate::blocks::BlockIndex;
+pub use crate::stream::DEFAULT_BLOCK_SIZE;
+use crate::{simple, RansError};
+
+/// Compresses a byte buffer into a seekable container, made of independently
+/// compressed blocks of [`DEFAULT_BLOCK_SIZE`] bytes.
+///
+/// The container starts with an index of the blocks, so that
+/// [`SeekableDecoder`] can decode an arbitrary range of the data by only
+/// decompressing the blocks overlapping it.
+///
+/// # Examples
+/// ```
+/// use rans::seekable::{compress, SeekableDecoder};
+///
+/// let data = b"abracadabra".repeat(10000);
+/// let compressed = compress(&data);
+///
+/// let decoder = SeekableDecoder::new(&compressed).unwrap();
+/// assert_eq!(decoder.decode_range(55000..55011).unwrap(), b"abracadabra");
+/// ```
+#[must_use]
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    compress_with_block_size(data, DEFAULT_BLOCK_SIZE)
+}
+
+/// Compresses a byte buffer like [`compress()`], but with blocks of
+/// `block_size` bytes. Smaller blocks make random access cheaper, at the cost
+/// of the compression ratio.
+///
+/// # Panics
+/// Panics if `block_size` is zero.
+///
+/// # Examples
+/// ```
+/// use rans::seekable::{compress_with_block_size, SeekableDecoder};
+///
+/// let data = b"abracadabra".repeat(1000);
+/// let compressed = compress_with_block_size(&data, 1024);
+///
+/// let decoder = SeekableDecoder::new(&compressed).unwrap();
+/// assert_eq!(decoder.num_blocks(), 11);
+/// ```
+#[must_use]
+pub fn compress_with_block_size(data: &[u8], block_size: usize) -> Vec<u8> {
+    assert!(block_size > 0, "block_size must not be zero");
+
+    let blocks: Vec<Vec<u8>> = data.chunks(block_size).map(simple::compress).collect();
+    let mut output = Vec::with_capacity(blocks.iter().map(Vec::len).sum::<usize>() + 16);
+    BlockIndex::write(&mut output, data.len(), block_size, &blocks);
+    output
+}
+
+/// Decoder of the seekable container produced by [`compress()`] or
+/// [`compress_with_block_size()`].
+///
+/// Only the index of the container is read when the decoder is created;
+/// the blocks are decompressed on demand. The container produced by
+/// `rans::parallel::par_compress()` has the same format too, so it can be read
+/// with this decoder as well.
+///
+/// The headers are validated, but the rANS bitstreams themselves are
+/// trusted, so this should not be used for data coming from untrusted
+/// sources.
+#[derive(Debug, Clone)]
+pub struct SeekableDecoder<'a> {
+    data: &'a [u8],
+    index: BlockIndex,
+}
+
+impl<'a> SeekableDecoder<'a> {
+    /// Reads the index of given seekable container.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the input is truncated, or
+    /// [`RansError::InvalidHeader`] if the index is malformed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::seekable::{compress, SeekableDecoder};
+    /// use rans::RansError;
+    ///
+    /// let compressed = compress(b"hello");
+    /// assert!(SeekableDecoder::new(&compressed).is_ok());
+    /// assert_eq!(
+    ///     SeekableDecoder::new(&compressed[..4]).unwrap_err(),
+    ///     RansError::UnexpectedEof
+    /// );
+    /// ```
+    pub fn new(data: &'a [u8]) -> Result<Self, RansError> {
+        let index = BlockIndex::read(data)?;
+        Ok(Self { data, index })
+    }
+
+    /// Returns the length of the uncompressed data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::seekable::{compress, SeekableDecoder};
+    ///
+    /// let compressed = compress(b"hello");
+    /// let decoder = SeekableDecoder::new(&compressed).unwrap();
+    /// assert_eq!(decoder.len(), 5);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns `true` if the uncompressed data is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::seekable::{compress, SeekableDecoder};
+    ///
+    /// let compressed = compress(b"");
+    /// let decoder = SeekableDecoder::new(&compressed).unwrap();
+    /// assert!(decoder.is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.index.len() == 0
+    }
+
+    /// Returns the number of bytes in a single block. Only the last block can
+    /// be shorter than that.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::seekable::{compress_with_block_size, SeekableDecoder};
+    ///
+    /// let compressed = compress_with_block_size(b"hello", 2);
+    /// let decoder = SeekableDecoder::new(&compressed).unwrap();
+    /// assert_eq!(decoder.block_size(), 2);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.index.block_size()
+    }
+
+    /// Returns the number of blocks in the container.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::seekable::{compress_with_block_size, SeekableDecoder};
+    ///
+    /// let compressed = compress_with_block_size(b"hello", 2);
+    /// let decoder = SeekableDecoder::new(&compressed).unwrap();
+    /// assert_eq!(decoder.num_blocks(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn num_blocks(&self) -> usize {
+        self.index.num_blocks()
+    }
+
+    /// Decompresses a single block of the container.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the block is truncated, or
+    /// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if its header
+    /// is malformed.
+    ///
+    /// # Panics
+    /// Panics if `block` is not less than [`Self::num_blocks()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::seekable::{compress_with_block_size, SeekableDecoder};
+    ///
+    /// let compressed = compress_with_block_size(b"hello", 2);
+    /// let decoder = SeekableDecoder::new(&compressed).unwrap();
+    /// assert_eq!(decoder.decode_block(1).unwrap(), b"ll");
+    /// assert_eq!(decoder.decode_block(2).unwrap(), b"o");
+    /// ```
+    pub fn decode_block(&self, block: usize) -> Result<Vec<u8>, RansError> {
+        assert!(block < self.num_blocks(), "block index out of range");
+
+        self.index.decompress_block(self.data, block)
+    }
+
+    /// Decodes given byte range of the uncompressed data, decompressing only
+    /// the blocks overlapping it.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if any of the blocks is truncated,
+    /// or [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if its
+    /// header is malformed.
+    ///
+    /// # Panics
+    /// Panics if the range starts after it ends, or ends after
+    /// [`Self::len()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::seekable::{compress_with_block_size, SeekableDecoder};
+    ///
+    /// let compressed = compress_with_block_size(b"hello, world", 4);
+    /// let decoder = SeekableDecoder::new(&compressed).unwrap();
+    /// assert_eq!(decoder.decode_range(3..9).unwrap(), b"lo, wo");
+    /// assert_eq!(decoder.decode_range(5..5).unwrap(), b"");
+    /// ```
+    pub fn decode_range(&self, range: Range<usize>) -> Result<Vec<u8>, RansError> {
+        assert!(range.start <= range.end, "range start is after its end");
+        assert!(range.end <= self.len(), "range end out of bounds");
+
+        let mut output = Vec::with_capacity(range.len());
+        if range.is_empty() {
+            return Ok(output);
+        }
+
+        let block_size = self.block_size();
+        let first_block = range.start / block_size;
+        let last_block = (range.end - 1) / block_size;
+        for block in first_block..=last_block {
+            let decompressed = self.index.decompress_block(self.data, block)?;
+            let block_start = block * block_size;
+            let start = range.start.max(block_start) - block_start;
+            let end = range.end.min(block_start + decompressed.len()) - block_start;
+            output.extend_from_slice(&decompressed[start..end]);
+        }
+        Ok(output)
+    }
+
+    /// Decodes the entire uncompressed data.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if any of the blocks is truncated,
+    /// or [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if its
+    /// header is malformed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::seekable::{compress, SeekableDecoder};
+    ///
+    /// let compressed = compress(b"hello");
+    /// let decoder = SeekableDecoder::new(&compressed).unwrap();
+    /// assert_eq!(decoder.decode_all().unwrap(), b"hello");
+    /// ```
+    pub fn decode_all(&self) -> Result<Vec<u8>, RansError> {
+        self.decode_range(0..self.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::seekable::{compress, compress_with_block_size, SeekableDecoder};
+    use crate::RansError;
+
+    fn test_data() -> Vec<u8> {
+        (0..10_000_u32).map(|x| (x % 251 * 7 % 13) as u8).collect()
+    }
+
+    #[test]
+    fn test_decode_range() {
+        let data = test_data();
+        let compressed = compress_with_block_size(&data, 1000);
+        let decoder = SeekableDecoder::new(&compressed).unwrap();
+        assert_eq!(decoder.num_blocks(), 10);
+
+        for (start, end) in [(0, 1), (0, 1000), (999, 1001), (1500, 4200), (9999, 10000)] {
+            assert_eq!(decoder.decode_range(start..end).unwrap(), &data[start..end]);
+        }
+        assert_eq!(decoder.decode_all().unwrap(), data);
+    }
+
+    #[test]
+    fn test_round_trip_default_block_size() {
+        let data = test_data();
+        let compressed = compress(&data);
+        let decoder = SeekableDecoder::new(&compressed).unwrap();
+
+        assert_eq!(decoder.len(), data.len());
+        assert_eq!(decoder.num_blocks(), 1);
+        assert_eq!(decoder.decode_all().unwrap(), data);
+    }
+
+    #[test]
+    fn test_empty() {
+        let compressed = compress(&[]);
+        let decoder = SeekableDecoder::new(&compressed).unwrap();
+
+        assert!(decoder.is_empty());
+        assert_eq!(decoder.num_blocks(), 0);
+        assert_eq!(decoder.decode_all(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_block_length_mismatch() {
+        let mut compressed = compress_with_block_size(b"aaaabbbb", 4);
+        // Claim the data is shorter than it really is
+        compressed[0] = 7;
+        let decoder = SeekableDecoder::new(&compressed).unwrap();
+
+        assert_eq!(decoder.decode_block(0), Ok(b"aaaa".to_vec()));
+        assert_eq!(decoder.decode_range(4..7), Err(RansError::InvalidHeader));
+    }
+
+    #[test]
+    #[should_panic(expected = "range end out of bounds")]
+    fn test_range_out_of_bounds() {
+        let compressed = compress(b"hello");
+        let decoder = SeekableDecoder::new(&compressed).unwrap();
+
+        let _ = decoder.decode_range(3..6);
+    }
+}
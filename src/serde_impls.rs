@@ -0,0 +1,227 @@
+use std::borrow::Cow;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::b64_decoder::B64RansDecSymbol;
+use crate::b64_encoder::B64RansEncSymbol;
+use crate::byte_decoder::ByteRansDecSymbol;
+use crate::byte_encoder::ByteRansEncSymbol;
+use crate::escape::EscapeModel;
+use crate::model::Model;
+use crate::simd_decoder::SimdRansDecSymbol;
+use crate::simd_encoder::SimdRansEncSymbol;
+use crate::{RansDecSymbol, RansEncSymbol, RansError};
+
+/// Serialized form of a [`Model`]; the cumulative frequencies are derived
+/// from the frequencies when deserializing.
+#[derive(Serialize, Deserialize)]
+struct ModelRepr<'a> {
+    scale_bits: u32,
+    freqs: Cow<'a, [u32]>,
+}
+
+impl Serialize for Model {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ModelRepr {
+            scale_bits: self.scale_bits(),
+            freqs: Cow::Borrowed(self.freqs()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Model {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ModelRepr::deserialize(deserializer)?;
+        let sum: u64 = repr.freqs.iter().map(|&freq| u64::from(freq)).sum();
+        if repr.scale_bits > 31 || sum != 1 << repr.scale_bits {
+            return Err(D::Error::custom(RansError::InvalidModel));
+        }
+
+        Ok(Model::from_freqs(repr.freqs.into_owned(), repr.scale_bits))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EscapeModelRepr<'a> {
+    model: Cow<'a, Model>,
+    literal_bits: u32,
+}
+
+impl Serialize for EscapeModel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EscapeModelRepr {
+            model: Cow::Borrowed(self.model()),
+            literal_bits: self.literal_bits(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EscapeModel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = EscapeModelRepr::deserialize(deserializer)?;
+        EscapeModel::from_model(repr.model.into_owned(), repr.literal_bits)
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Serialized form of the encoder symbols, independent of the coder's
+/// internal representation.
+#[derive(Serialize, Deserialize)]
+struct EncSymbolRepr {
+    cum_freq: u32,
+    freq: u32,
+    scale_bits: u32,
+}
+
+/// Serialized form of the decoder symbols.
+#[derive(Serialize, Deserialize)]
+struct DecSymbolRepr {
+    cum_freq: u32,
+    freq: u32,
+}
+
+macro_rules! impl_enc_symbol_serde {
+    ($($symbol:ty),*) => {$(
+        impl Serialize for $symbol {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                EncSymbolRepr {
+                    cum_freq: self.cum_freq(),
+                    freq: self.freq(),
+                    scale_bits: self.scale_bits(),
+                }
+                .serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $symbol {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let repr = EncSymbolRepr::deserialize(deserializer)?;
+                Self::try_new(repr.cum_freq, repr.freq, repr.scale_bits).map_err(D::Error::custom)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_dec_symbol_serde {
+    ($($symbol:ty),*) => {$(
+        impl Serialize for $symbol {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                DecSymbolRepr {
+                    cum_freq: self.cum_freq(),
+                    freq: self.freq(),
+                }
+                .serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $symbol {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                // Decoder symbols don't depend on the number of scale bits,
+                // so only check them against the maximum one
+                let repr = DecSymbolRepr::deserialize(deserializer)?;
+                Self::try_new(repr.cum_freq, repr.freq, Self::MAX_SCALE_BITS)
+                    .map_err(D::Error::custom)
+            }
+        }
+    )*};
+}
+
+impl_enc_symbol_serde!(ByteRansEncSymbol, B64RansEncSymbol, SimdRansEncSymbol);
+impl_dec_symbol_serde!(ByteRansDecSymbol, B64RansDecSymbol, SimdRansDecSymbol);
+
+#[cfg(test)]
+mod tests {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use crate::b64_decoder::B64RansDecSymbol;
+    use crate::b64_encoder::B64RansEncSymbol;
+    use crate::byte_decoder::ByteRansDecSymbol;
+    use crate::byte_encoder::ByteRansEncSymbol;
+    use crate::escape::EscapeModel;
+    use crate::model::{Model, NormalizationStrategy};
+    use crate::simd_decoder::SimdRansDecSymbol;
+    use crate::simd_encoder::SimdRansEncSymbol;
+    use crate::{RansDecSymbol, RansEncSymbol};
+
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_model() {
+        let model = Model::from_counts(&[3, 0, 1, 12], 4);
+
+        assert_eq!(
+            serde_json::to_string(&model).unwrap(),
+            r#"{"scale_bits":4,"freqs":[3,0,1,12]}"#
+        );
+        assert_eq!(round_trip(&model), model);
+        assert_eq!(
+            round_trip(&NormalizationStrategy::Optimal),
+            NormalizationStrategy::Optimal
+        );
+    }
+
+    #[test]
+    fn test_model_invalid() {
+        let result = serde_json::from_str::<Model>(r#"{"scale_bits":4,"freqs":[3,0,1,11]}"#);
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("invalid frequency table"));
+    }
+
+    #[test]
+    fn test_escape_model() {
+        let model = EscapeModel::from_counts(&[3, 0, 1, 12], 8, 16);
+
+        assert_eq!(round_trip(&model), model);
+        assert!(serde_json::from_str::<EscapeModel>(
+            r#"{"model":{"scale_bits":1,"freqs":[2,0]},"literal_bits":8}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_enc_symbols() {
+        fn check<S: RansEncSymbol + Serialize + DeserializeOwned>(scale_bits: u32) {
+            let symbol = round_trip(&S::new(5, 1, scale_bits));
+            assert_eq!(
+                (symbol.cum_freq(), symbol.freq(), symbol.scale_bits()),
+                (5, 1, scale_bits)
+            );
+
+            let symbol = round_trip(&S::new(3, 7, scale_bits));
+            assert_eq!(
+                (symbol.cum_freq(), symbol.freq(), symbol.scale_bits()),
+                (3, 7, scale_bits)
+            );
+        }
+
+        check::<ByteRansEncSymbol>(12);
+        check::<B64RansEncSymbol>(24);
+        check::<SimdRansEncSymbol>(12);
+        assert!(serde_json::from_str::<ByteRansEncSymbol>(
+            r#"{"cum_freq":3,"freq":2,"scale_bits":2}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_dec_symbols() {
+        fn check<S: RansDecSymbol + Serialize + DeserializeOwned>() {
+            let symbol = round_trip(&S::new(3, 7));
+            assert_eq!((symbol.cum_freq(), symbol.freq()), (3, 7));
+        }
+
+        check::<ByteRansDecSymbol>();
+        check::<B64RansDecSymbol>();
+        check::<SimdRansDecSymbol>();
+        assert!(serde_json::from_str::<ByteRansDecSymbol>(r#"{"cum_freq":3,"freq":0}"#).is_err());
+    }
+}
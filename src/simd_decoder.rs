@@ -0,0 +1,1004 @@
+use crate::decoder::DecSnapshot;
+use crate::decoder_input::DecoderInput;
+use crate::model::{DecModel, Model};
+use crate::simd_encoder::RANS_WORD_L;
+use crate::{RansDecSymbol, RansDecoder, RansDecoderMulti, RansError};
+
+/// Size of the initial state of a single channel, in bytes.
+const STATE_LEN: usize = 4;
+
+/// Multi-stream interleaved rANS decoder - 16-bit word version that decodes
+/// the channels using SIMD instructions where available.
+///
+/// This decodes the data produced by
+/// [`SimdRansEncoder`](crate::simd_encoder::SimdRansEncoder). Besides the
+/// regular [`RansDecoderMulti`] interface, it provides methods operating on
+/// all the channels at once ([`Self::get_all()`], [`Self::advance_all()`],
+/// [`Self::decode_symbols_all()`] and more), which is where the speedup comes
+/// from.
+///
+/// The SIMD code paths are enabled with the `sse41` and `avx2` crate features
+/// and are only used if the CPU turns out to support the respective
+/// instruction set at runtime. Otherwise (and on other architectures), a
+/// portable scalar implementation is used, which produces exactly the same
+/// results.
+#[derive(Debug)]
+pub struct SimdRansDecoder<'a, const N: usize> {
+    states: [u32; N],
+    data: DecoderInput<'a>,
+    pos: usize,
+    level: SimdLevel,
+    model: Option<DecModel<SimdRansDecSymbol>>,
+}
+
+impl<'a, const N: usize> SimdRansDecoder<'a, N> {
+    /// Creates a new `SimdRansDecoder` instance with given `data`.
+    ///
+    /// The data is only ever read, so it can be borrowed (`&[u8]`), owned
+    /// (`Vec<u8>`) or shared between threads (`Arc<[u8]>`) - see
+    /// [`DecoderInput`] for all the supported types.
+    ///
+    /// Note that most of the API is inside the [`RansDecoderMulti`] trait, so
+    /// you probably want to `use rans::RansDecoderMulti`.
+    ///
+    /// # Panics
+    /// Panics if the data is too short to contain the initial states of all
+    /// channels.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([108, 0, 0, 1, 0, 0, 0, 1]);
+    /// assert_eq!(decoder.get_at(0, 4), 12);
+    /// assert_eq!(decoder.get_at(1, 4), 0);
+    /// ```
+    #[must_use]
+    pub fn new<T: Into<DecoderInput<'a>>>(data: T) -> Self {
+        let data = data.into();
+        assert!(data.len() >= N * STATE_LEN);
+
+        let mut states = [0; N];
+        for (state, bytes) in states.iter_mut().zip(data.chunks_exact(STATE_LEN)) {
+            *state = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        Self {
+            states,
+            data,
+            pos: N * STATE_LEN,
+            level: SimdLevel::detect(),
+            model: None,
+        }
+    }
+
+    /// Creates a new `SimdRansDecoder` instance with given `data`, checking
+    /// that the data is long enough to contain the initial states of all
+    /// channels.
+    ///
+    /// # Errors
+    /// Returns [`RansError::EmptyInput`] if `data` is empty, or
+    /// [`RansError::UnexpectedEof`] if it is too short to contain the initial
+    /// states.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    /// use rans::{RansDecoderMulti, RansError};
+    ///
+    /// let mut decoder = SimdRansDecoder::<1>::try_new([0, 0, 1, 0]).unwrap();
+    /// assert_eq!(decoder.get_at(0, 4), 0);
+    /// assert_eq!(
+    ///     SimdRansDecoder::<2>::try_new([0, 0, 1, 0]).unwrap_err(),
+    ///     RansError::UnexpectedEof
+    /// );
+    /// ```
+    pub fn try_new<T: Into<DecoderInput<'a>>>(data: T) -> Result<Self, RansError> {
+        let data = data.into();
+        if data.is_empty() {
+            return Err(RansError::EmptyInput);
+        }
+        if data.len() < N * STATE_LEN {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        Ok(Self::new(data))
+    }
+
+    /// Gets the cumulative frequencies for the current symbols at all
+    /// channels.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([108, 0, 0, 1, 0, 0, 0, 1]);
+    /// assert_eq!(decoder.get_all(4), [12, 0]);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn get_all(&self, scale_bits: u32) -> [u32; N] {
+        let mask = (1 << scale_bits) - 1;
+        self.states.map(|state| state & mask)
+    }
+
+    /// Advances the data position at all channels after reading a symbol at
+    /// each one of them. Equivalent to calling [`Self::advance_step_all()`]
+    /// and [`Self::renorm_all()`](RansDecoderMulti::renorm_all).
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::{SimdRansDecSymbol, SimdRansDecoder};
+    /// use rans::RansDecSymbol;
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([108, 0, 0, 1, 0, 0, 0, 1]);
+    /// let symbol_1 = SimdRansDecSymbol::new(0, 4);
+    /// let symbol_4 = SimdRansDecSymbol::new(12, 4);
+    /// assert_eq!(decoder.get_all(4), [12, 0]);
+    /// decoder.advance_all(&[&symbol_4, &symbol_1], 4);
+    /// ```
+    #[inline]
+    pub fn advance_all(&mut self, symbols: &[&SimdRansDecSymbol; N], scale_bits: u32) {
+        self.advance_step_all(symbols, scale_bits);
+        self.renorm_all();
+    }
+
+    /// Pops a single symbol from the internal state of every channel, without
+    /// doing renormalization or modifying the internal buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::{SimdRansDecSymbol, SimdRansDecoder};
+    /// use rans::{RansDecSymbol, RansDecoderMulti};
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([108, 0, 0, 1, 0, 0, 0, 1]);
+    /// let symbol_1 = SimdRansDecSymbol::new(0, 4);
+    /// let symbol_4 = SimdRansDecSymbol::new(12, 4);
+    /// decoder.advance_step_all(&[&symbol_4, &symbol_1], 4);
+    /// decoder.renorm_all();
+    /// ```
+    #[inline]
+    pub fn advance_step_all(&mut self, symbols: &[&SimdRansDecSymbol; N], scale_bits: u32) {
+        debug_assert!(scale_bits <= 16);
+
+        let mut starts = [0; N];
+        let mut freqs = [0; N];
+        for (i, symbol) in symbols.iter().enumerate() {
+            starts[i] = symbol.start;
+            freqs[i] = symbol.freq;
+        }
+
+        let done = self
+            .level
+            .advance_step(&mut self.states, &starts, &freqs, scale_bits);
+        for i in done..N {
+            self.states[i] = advance_step(self.states[i], starts[i], freqs[i], scale_bits);
+        }
+    }
+
+    /// Decodes a single symbol at every channel using given model and returns
+    /// their indices. Equivalent to calling [`Self::get_all()`], looking up
+    /// the symbols in the model and calling [`Self::advance_all()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::{DecModel, Model};
+    /// use rans::simd_decoder::{SimdRansDecSymbol, SimdRansDecoder};
+    ///
+    /// let model = Model::from_freqs(vec![4, 4, 4, 4], 4);
+    /// let dec_model = DecModel::<SimdRansDecSymbol>::new(&model);
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([108, 0, 0, 1, 0, 0, 0, 1]);
+    /// assert_eq!(decoder.decode_symbols_all(&dec_model), [3, 0]);
+    /// ```
+    #[inline]
+    pub fn decode_symbols_all(&mut self, model: &DecModel<SimdRansDecSymbol>) -> [usize; N] {
+        let cum_freqs = self.get_all(model.scale_bits());
+        let lookups = cum_freqs.map(|cum_freq| model.lookup(cum_freq));
+        self.advance_all(&lookups.map(|(_, symbol)| symbol), model.scale_bits());
+
+        lookups.map(|(index, _)| index)
+    }
+
+    /// Registers the model used by [`Self::decode_at()`] and
+    /// [`Self::decode_all()`], so that the symbols don't need to be looked up
+    /// and passed back to the decoder by the caller. Replaces the previously
+    /// registered model, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::simd_decoder::SimdRansDecoder;
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([108, 0, 0, 1, 0, 0, 0, 1]);
+    /// decoder.set_model(&Model::from_freqs(vec![4, 4, 4, 4], 4));
+    /// assert_eq!(decoder.decode_at(0), 3);
+    /// assert_eq!(decoder.decode_at(1), 0);
+    /// ```
+    pub fn set_model(&mut self, model: &Model) {
+        self.model = Some(DecModel::new(model));
+    }
+
+    /// Decodes a single symbol at given channel using the model registered
+    /// with [`Self::set_model()`] and returns its index.
+    ///
+    /// # Panics
+    /// Panics if no model has been registered.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::simd_decoder::SimdRansDecoder;
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([108, 0, 0, 1, 0, 0, 0, 1]);
+    /// decoder.set_model(&Model::from_freqs(vec![4, 4, 4, 4], 4));
+    /// assert_eq!(decoder.decode_at(0), 3);
+    /// assert_eq!(decoder.decode_at(1), 0);
+    /// ```
+    pub fn decode_at(&mut self, channel: usize) -> usize {
+        let model = self
+            .model
+            .take()
+            .expect("no model registered; use set_model() first");
+        let index = self.decode_symbol_at(channel, &model);
+        self.model = Some(model);
+        index
+    }
+
+    /// Decodes a single symbol at every channel using the model registered
+    /// with [`Self::set_model()`] and returns their indices. See
+    /// [`Self::decode_symbols_all()`].
+    ///
+    /// # Panics
+    /// Panics if no model has been registered.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::simd_decoder::SimdRansDecoder;
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([108, 0, 0, 1, 0, 0, 0, 1]);
+    /// decoder.set_model(&Model::from_freqs(vec![4, 4, 4, 4], 4));
+    /// assert_eq!(decoder.decode_all(), [3, 0]);
+    /// ```
+    pub fn decode_all(&mut self) -> [usize; N] {
+        let model = self
+            .model
+            .take()
+            .expect("no model registered; use set_model() first");
+        let indices = self.decode_symbols_all(&model);
+        self.model = Some(model);
+        indices
+    }
+
+    /// Returns the number of bytes of the data that haven't been read by
+    /// the decoder yet.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    ///
+    /// let decoder = SimdRansDecoder::<1>::new([0, 0, 1, 0, 42]);
+    /// assert_eq!(decoder.bytes_remaining(), 1);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn bytes_remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// Returns the position of the decoder in the data, i.e. the number of
+    /// bytes read by the decoder so far.
+    ///
+    /// Once all the symbols are decoded, this is the length of the rANS
+    /// payload, so it can be used to find where the data following it
+    /// begins.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    ///
+    /// let decoder = SimdRansDecoder::<2>::new([0, 0, 1, 0, 0, 0, 1, 0, 42]);
+    /// assert_eq!(decoder.position(), 8);
+    /// ```
+    #[doc(alias = "bytes_consumed")]
+    #[must_use]
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns whether all the symbols have been decoded, i.e. the whole data
+    /// has been read and all the channels are back in their initial states.
+    ///
+    /// This is exact for well-formed data, except that symbols with
+    /// probability of 1 don't change the state of the coder, so they can't
+    /// be detected.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::{SimdRansDecSymbol, SimdRansDecoder};
+    /// use rans::simd_encoder::{SimdRansEncSymbol, SimdRansEncoder};
+    /// use rans::{RansDecSymbol, RansDecoderMulti, RansEncSymbol, RansEncoderMulti};
+    ///
+    /// let mut encoder = SimdRansEncoder::<1>::new(1024);
+    /// encoder.put_at(0, &SimdRansEncSymbol::new(1, 1, 4));
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = SimdRansDecoder::<1>::new(encoder.data());
+    /// assert!(!decoder.is_exhausted());
+    /// assert_eq!(decoder.get_at(0, 4), 1);
+    /// decoder.advance_at(0, &SimdRansDecSymbol::new(1, 1), 4);
+    /// assert!(decoder.is_exhausted());
+    /// ```
+    #[must_use]
+    pub fn is_exhausted(&self) -> bool {
+        self.bytes_remaining() == 0 && self.states.iter().all(|&state| state == RANS_WORD_L)
+    }
+
+    /// Saves this decoder's channel states and read position, so that they
+    /// can be restored later with [`Self::restore_state()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]);
+    /// let snapshot = decoder.save_state();
+    /// assert_eq!(decoder.get_bits_at(0, 2), 2);
+    /// decoder.restore_state(&snapshot);
+    /// assert_eq!(decoder.get_bits_at(0, 2), 2);
+    /// ```
+    #[must_use]
+    pub fn save_state(&self) -> DecSnapshot<u32, N> {
+        DecSnapshot {
+            states: self.states,
+            pos: self.pos,
+        }
+    }
+
+    /// Restores this decoder's channel states and read position from given
+    /// snapshot.
+    ///
+    /// The snapshot must have been created by a decoder reading the same
+    /// data; otherwise, the decoder returns garbage.
+    ///
+    /// # Panics
+    /// Panics if the snapshot's position is past the end of the data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new([2, 0, 0, 1, 0, 0, 0, 1]);
+    /// let snapshot = decoder.save_state();
+    /// assert_eq!(decoder.get_bits_at(0, 2), 2);
+    /// decoder.restore_state(&snapshot);
+    /// assert_eq!(decoder.get_bits_at(0, 2), 2);
+    /// ```
+    pub fn restore_state(&mut self, snapshot: &DecSnapshot<u32, N>) {
+        assert!(
+            snapshot.pos <= self.data.len(),
+            "snapshot position out of range"
+        );
+
+        self.states = snapshot.states;
+        self.pos = snapshot.pos;
+    }
+
+    /// Re-initializes the states of all the channels from the data at the
+    /// current position.
+    ///
+    /// Once all the symbols of a block are decoded, this makes it possible to
+    /// decode another, independently flushed block that follows it in the
+    /// data with the same decoder, without copying the data.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the rest of the data is too
+    /// short to contain the initial states of all channels. The decoder is
+    /// left unchanged in such case.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    /// use rans::{RansDecoderMulti, RansError};
+    ///
+    /// let mut decoder = SimdRansDecoder::<1>::new([0, 0, 1, 0, 108, 0, 0, 1]);
+    /// assert_eq!(decoder.get_at(0, 4), 0);
+    /// decoder.reinit_tail().unwrap();
+    /// assert_eq!(decoder.get_at(0, 4), 12);
+    /// assert_eq!(decoder.reinit_tail(), Err(RansError::UnexpectedEof));
+    /// ```
+    pub fn reinit_tail(&mut self) -> Result<(), RansError> {
+        let states = self
+            .data
+            .get(self.pos..self.pos + N * STATE_LEN)
+            .ok_or(RansError::UnexpectedEof)?;
+        for (state, bytes) in self.states.iter_mut().zip(states.chunks_exact(STATE_LEN)) {
+            *state = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        self.pos += N * STATE_LEN;
+        Ok(())
+    }
+
+    /// Returns the current rANS state of given channel.
+    ///
+    /// Together with [`Self::set_state_at()`], this makes it possible to hand
+    /// streams over between this crate and other rANS implementations.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    ///
+    /// let decoder = SimdRansDecoder::<1>::new([4, 3, 2, 1]);
+    /// assert_eq!(decoder.state_at(0), 0x0102_0304);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn state_at(&self, channel: usize) -> u32 {
+        self.states[channel]
+    }
+
+    /// Overwrites the rANS state of given channel.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    /// use rans::RansDecoderMulti;
+    ///
+    /// let mut decoder = SimdRansDecoder::<1>::new([4, 3, 2, 1]);
+    /// decoder.set_state_at(0, 0x0000_0123);
+    /// assert_eq!(decoder.get_at(0, 8), 0x23);
+    /// ```
+    #[inline]
+    pub fn set_state_at(&mut self, channel: usize, state: u32) {
+        self.states[channel] = state;
+    }
+
+    #[inline]
+    fn read_word(&mut self) -> u32 {
+        let word = u16::from_le_bytes([self.data[self.pos], self.data[self.pos + 1]]);
+        self.pos += 2;
+        u32::from(word)
+    }
+}
+
+impl<const N: usize> RansDecoderMulti<N> for SimdRansDecoder<'_, N> {
+    type Symbol = SimdRansDecSymbol;
+
+    #[inline]
+    fn get_at(&mut self, channel: usize, scale_bits: u32) -> u32 {
+        checked_assert!(channel < N);
+
+        self.states[channel] & ((1 << scale_bits) - 1)
+    }
+
+    #[inline]
+    fn advance_at(&mut self, channel: usize, symbol: &Self::Symbol, scale_bits: u32) {
+        self.advance_step_at(channel, symbol, scale_bits);
+        self.renorm_at(channel);
+    }
+
+    #[inline]
+    fn advance_step_at(&mut self, channel: usize, symbol: &Self::Symbol, scale_bits: u32) {
+        checked_assert!(channel < N);
+        debug_assert!(scale_bits <= 16);
+
+        self.states[channel] =
+            advance_step(self.states[channel], symbol.start, symbol.freq, scale_bits);
+    }
+
+    #[inline]
+    fn renorm_at(&mut self, channel: usize) {
+        checked_assert!(channel < N);
+
+        if self.states[channel] < RANS_WORD_L {
+            self.states[channel] = (self.states[channel] << 16) | self.read_word();
+        }
+    }
+
+    #[inline]
+    fn try_renorm_at(&mut self, channel: usize) -> Result<(), RansError> {
+        checked_assert!(channel < N);
+        if self.states[channel] < RANS_WORD_L && self.bytes_remaining() < 2 {
+            return Err(RansError::InputExhausted);
+        }
+
+        self.renorm_at(channel);
+        Ok(())
+    }
+
+    #[inline]
+    fn renorm_all(&mut self) {
+        let done = self
+            .level
+            .renorm(&mut self.states, &self.data, &mut self.pos);
+        for i in done..N {
+            self.renorm_at(i);
+        }
+    }
+
+    #[inline]
+    fn try_renorm_all(&mut self) -> Result<(), RansError> {
+        let words = self
+            .states
+            .iter()
+            .filter(|&&state| state < RANS_WORD_L)
+            .count();
+        if words * 2 > self.bytes_remaining() {
+            return Err(RansError::InputExhausted);
+        }
+
+        self.renorm_all();
+        Ok(())
+    }
+
+    #[inline]
+    fn advance_step_all(&mut self, symbols: &[&Self::Symbol; N], scale_bits: u32) {
+        SimdRansDecoder::advance_step_all(self, symbols, scale_bits);
+    }
+
+    #[inline]
+    fn advance_all(&mut self, symbols: &[&Self::Symbol; N], scale_bits: u32) {
+        SimdRansDecoder::advance_all(self, symbols, scale_bits);
+    }
+
+    #[inline]
+    fn decode_symbols_all(&mut self, model: &DecModel<Self::Symbol>) -> [usize; N] {
+        SimdRansDecoder::decode_symbols_all(self, model)
+    }
+}
+
+impl RansDecoder for SimdRansDecoder<'_, 1> {}
+
+/// rANS decoder symbol - 16-bit word version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimdRansDecSymbol {
+    start: u32,
+    freq: u32,
+}
+
+impl RansDecSymbol for SimdRansDecSymbol {
+    #[inline]
+    fn new(cum_freq: u32, freq: u32) -> Self {
+        debug_assert!(cum_freq + freq <= 1 << 16);
+
+        Self {
+            start: cum_freq,
+            freq,
+        }
+    }
+
+    #[inline]
+    fn cum_freq(&self) -> u32 {
+        self.start
+    }
+
+    #[inline]
+    fn freq(&self) -> u32 {
+        self.freq
+    }
+}
+
+#[inline]
+fn advance_step(x: u32, start: u32, freq: u32, scale_bits: u32) -> u32 {
+    let mask = (1 << scale_bits) - 1;
+    freq.wrapping_mul(x >> scale_bits)
+        .wrapping_add(x & mask)
+        .wrapping_sub(start)
+}
+
+/// Instruction set used to process the channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SimdLevel {
+    Scalar,
+    #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "sse41"))]
+    Sse41,
+    #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "avx2"))]
+    Avx2,
+}
+
+impl SimdLevel {
+    pub(crate) fn detect() -> Self {
+        #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "avx2"))]
+        if is_x86_feature_detected!("avx2") {
+            return Self::Avx2;
+        }
+        #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "sse41"))]
+        if is_x86_feature_detected!("sse4.1") {
+            return Self::Sse41;
+        }
+
+        Self::Scalar
+    }
+
+    /// Advances as many leading states as possible using SIMD instructions
+    /// and returns the number of states processed.
+    #[inline]
+    #[allow(unused_variables)]
+    fn advance_step(
+        self,
+        states: &mut [u32],
+        starts: &[u32],
+        freqs: &[u32],
+        scale_bits: u32,
+    ) -> usize {
+        match self {
+            Self::Scalar => 0,
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "sse41"))]
+            Self::Sse41 => unsafe { x86::advance_step_sse41(states, starts, freqs, scale_bits) },
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "avx2"))]
+            Self::Avx2 => unsafe { x86::advance_step_avx2(states, starts, freqs, scale_bits) },
+        }
+    }
+
+    /// Renormalizes as many leading states as possible using SIMD
+    /// instructions and returns the number of states processed.
+    #[inline]
+    #[allow(unused_variables)]
+    fn renorm(self, states: &mut [u32], data: &[u8], pos: &mut usize) -> usize {
+        match self {
+            Self::Scalar => 0,
+            // AVX2 doesn't make the shuffles across 128-bit lanes any faster,
+            // so SSE4.1 is used for renormalization in both cases
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "sse41"))]
+            Self::Sse41 => unsafe { x86::renorm_sse41(states, data, pos) },
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "avx2"))]
+            Self::Avx2 => unsafe { x86::renorm_sse41(states, data, pos) },
+        }
+    }
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    any(feature = "sse41", feature = "avx2")
+))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use crate::simd_encoder::RANS_WORD_L;
+
+    /// `pshufb` masks moving consecutive 16-bit words from memory into the
+    /// lower halves of the lanes that need to be renormalized, indexed by the
+    /// bitmask of those lanes.
+    static SHUFFLES: [[i8; 16]; 16] = build_shuffles();
+    /// Number of lanes set in each 4-bit mask.
+    static NUM_LANES: [u8; 16] = [0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4];
+
+    const fn build_shuffles() -> [[i8; 16]; 16] {
+        let mut shuffles = [[-1; 16]; 16];
+        let mut mask = 0;
+        while mask < 16 {
+            let mut word = 0;
+            let mut lane = 0;
+            while lane < 4 {
+                if mask & (1 << lane) != 0 {
+                    shuffles[mask][lane * 4] = word * 2;
+                    shuffles[mask][lane * 4 + 1] = word * 2 + 1;
+                    word += 1;
+                }
+                lane += 1;
+            }
+            mask += 1;
+        }
+        shuffles
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub(super) unsafe fn advance_step_sse41(
+        states: &mut [u32],
+        starts: &[u32],
+        freqs: &[u32],
+        scale_bits: u32,
+    ) -> usize {
+        let chunks = states.len() / 4;
+        let shift = _mm_cvtsi32_si128(scale_bits as i32);
+        let mask = _mm_set1_epi32((1 << scale_bits) - 1);
+
+        for i in 0..chunks {
+            let x = _mm_loadu_si128(states.as_ptr().add(i * 4) as *const __m128i);
+            let start = _mm_loadu_si128(starts.as_ptr().add(i * 4) as *const __m128i);
+            let freq = _mm_loadu_si128(freqs.as_ptr().add(i * 4) as *const __m128i);
+
+            let x_scaled = _mm_srl_epi32(x, shift);
+            let x = _mm_sub_epi32(
+                _mm_add_epi32(_mm_mullo_epi32(freq, x_scaled), _mm_and_si128(x, mask)),
+                start,
+            );
+            _mm_storeu_si128(states.as_mut_ptr().add(i * 4) as *mut __m128i, x);
+        }
+
+        chunks * 4
+    }
+
+    #[cfg(feature = "avx2")]
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn advance_step_avx2(
+        states: &mut [u32],
+        starts: &[u32],
+        freqs: &[u32],
+        scale_bits: u32,
+    ) -> usize {
+        let chunks = states.len() / 8;
+        let shift = _mm_cvtsi32_si128(scale_bits as i32);
+        let mask = _mm256_set1_epi32((1 << scale_bits) - 1);
+
+        for i in 0..chunks {
+            let x = _mm256_loadu_si256(states.as_ptr().add(i * 8) as *const __m256i);
+            let start = _mm256_loadu_si256(starts.as_ptr().add(i * 8) as *const __m256i);
+            let freq = _mm256_loadu_si256(freqs.as_ptr().add(i * 8) as *const __m256i);
+
+            let x_scaled = _mm256_srl_epi32(x, shift);
+            let x = _mm256_sub_epi32(
+                _mm256_add_epi32(
+                    _mm256_mullo_epi32(freq, x_scaled),
+                    _mm256_and_si256(x, mask),
+                ),
+                start,
+            );
+            _mm256_storeu_si256(states.as_mut_ptr().add(i * 8) as *mut __m256i, x);
+        }
+
+        let done = chunks * 8;
+        done + advance_step_sse41(
+            &mut states[done..],
+            &starts[done..],
+            &freqs[done..],
+            scale_bits,
+        )
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub(super) unsafe fn renorm_sse41(states: &mut [u32], data: &[u8], pos: &mut usize) -> usize {
+        let chunks = states.len() / 4;
+        let zero = _mm_setzero_si128();
+
+        for i in 0..chunks {
+            let x = _mm_loadu_si128(states.as_ptr().add(i * 4) as *const __m128i);
+
+            // x < RANS_WORD_L <=> the upper 16 bits of x are all zero
+            let needs_renorm = _mm_cmpeq_epi32(_mm_srli_epi32(x, 16), zero);
+            let lanes = _mm_movemask_ps(_mm_castsi128_ps(needs_renorm)) as usize;
+            if lanes == 0 {
+                continue;
+            }
+
+            let len = usize::from(NUM_LANES[lanes]) * 2;
+            assert!(*pos + len <= data.len(), "Reading past the end of the data");
+            let mut words = [0u8; 8];
+            words[..len].copy_from_slice(&data[*pos..*pos + len]);
+
+            let mem_vals = _mm_loadl_epi64(words.as_ptr() as *const __m128i);
+            let shuffle = _mm_loadu_si128(SHUFFLES[lanes].as_ptr() as *const __m128i);
+            let new_x = _mm_or_si128(_mm_slli_epi32(x, 16), _mm_shuffle_epi8(mem_vals, shuffle));
+            let x = _mm_blendv_epi8(x, new_x, needs_renorm);
+            _mm_storeu_si128(states.as_mut_ptr().add(i * 4) as *mut __m128i, x);
+
+            *pos += len;
+        }
+
+        debug_assert!(states[..chunks * 4].iter().all(|&x| x >= RANS_WORD_L));
+        chunks * 4
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::decoder::tests as dec_tests;
+    use crate::encoder::tests as enc_tests;
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::simd_decoder::{SimdLevel, SimdRansDecSymbol, SimdRansDecoder};
+    use crate::simd_encoder::{SimdRansEncSymbol, SimdRansEncoder};
+    use crate::{RansDecoderMulti, RansEncoderMulti, RansError};
+
+    #[test]
+    fn test_decode_empty() {
+        let decoder = SimdRansDecoder::<1>::new([0, 0, 1, 0]);
+
+        dec_tests::test_decode_empty(decoder);
+    }
+
+    #[test]
+    fn test_decode_two_symbols() {
+        let decoder = SimdRansDecoder::<1>::new([2, 0, 4, 0]);
+
+        dec_tests::test_decode_two_symbols(decoder);
+    }
+
+    #[test]
+    fn test_decode_bits() {
+        let data = enc_tests::encode_bits(SimdRansEncoder::<1>::new(1024));
+        let decoder = SimdRansDecoder::<1>::new(data);
+
+        dec_tests::test_decode_bits(decoder);
+    }
+
+    #[test]
+    fn test_state_matches_encoder() {
+        let mut encoder = SimdRansEncoder::<1>::new(1024);
+        encoder.put_bits_at(0, 0xdead_beef, 32);
+        let state = encoder.state_at(0);
+        encoder.flush_all();
+
+        let decoder = SimdRansDecoder::<1>::new(encoder.data());
+        assert_eq!(decoder.state_at(0), state);
+    }
+
+    #[test]
+    fn test_save_restore_state() {
+        let data = enc_tests::encode_bits(SimdRansEncoder::<1>::new(1024));
+        let decoder = SimdRansDecoder::<1>::new(data);
+
+        dec_tests::test_save_restore_state(
+            decoder,
+            SimdRansDecoder::<1>::save_state,
+            SimdRansDecoder::<1>::restore_state,
+        );
+    }
+
+    #[test]
+    fn test_decode_symbols_with_model() {
+        let decoder = SimdRansDecoder::<1>::new([2, 0, 4, 0]);
+
+        dec_tests::test_decode_symbols_with_model(decoder);
+    }
+
+    #[test]
+    fn test_decode_with_registered_model() {
+        let mut decoder = SimdRansDecoder::<1>::new([2, 0, 4, 0]);
+        decoder.set_model(&Model::from_freqs(vec![2, 2], 2));
+
+        assert_eq!(decoder.decode_at(0), 1);
+        assert_eq!(decoder.decode_at(0), 0);
+    }
+
+    #[test]
+    fn test_decode_interleaved() {
+        let data = [108, 0, 0, 1, 0, 0, 0, 1];
+        let decoder = SimdRansDecoder::<2>::new(data);
+
+        dec_tests::test_decode_interleaved(decoder);
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert!(SimdRansDecoder::<1>::try_new([0, 0, 1, 0]).is_ok());
+        assert_eq!(
+            SimdRansDecoder::<1>::try_new(vec![]).unwrap_err(),
+            RansError::EmptyInput
+        );
+        assert_eq!(
+            SimdRansDecoder::<2>::try_new([0, 0, 1, 0]).unwrap_err(),
+            RansError::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_round_trip_4() {
+        round_trip::<4>();
+    }
+
+    #[test]
+    fn test_round_trip_8() {
+        round_trip::<8>();
+    }
+
+    #[test]
+    fn test_round_trip_13() {
+        round_trip::<13>();
+    }
+
+    /// Encodes random data and decodes it with all the instruction sets
+    /// available, both lane by lane and all lanes at once.
+    fn round_trip<const N: usize>() {
+        const SCALE_BITS: u32 = 12;
+        const STEPS: usize = 1000;
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(N as u64);
+        let counts: Vec<u32> = (0..32).map(|i| 1 + (i * i) % 97).collect();
+        let model = Model::from_counts(&counts, SCALE_BITS);
+        let enc_model = EncModel::<SimdRansEncSymbol>::new(&model);
+        let dec_model = DecModel::new(&model);
+
+        let symbols: Vec<[usize; N]> = (0..STEPS)
+            .map(|_| [(); N].map(|_| rng.gen_range(0..counts.len())))
+            .collect();
+
+        let mut encoder = SimdRansEncoder::<N>::with_growable_buffer();
+        for step in symbols.iter().rev() {
+            // Encoder channel `i` is decoded by decoder channel `N - 1 - i`
+            for (channel, &symbol) in step.iter().rev().enumerate() {
+                encoder.put_at(channel, enc_model.symbol(symbol));
+            }
+        }
+        encoder.flush_all();
+        let data = encoder.data();
+
+        let mut levels = vec![SimdLevel::Scalar, SimdLevel::detect()];
+        levels.dedup();
+        for level in levels {
+            let mut decoder = SimdRansDecoder::<N>::new(data);
+            decoder.level = level;
+            for step in &symbols {
+                assert_eq!(&decoder.decode_symbols_all(&dec_model), step);
+            }
+            assert_eq!(decoder.pos, data.len());
+
+            let mut decoder = SimdRansDecoder::<N>::new(data);
+            decoder.level = level;
+            for step in &symbols {
+                for (channel, &symbol) in step.iter().enumerate() {
+                    assert_eq!(decoder.decode_symbol_at(channel, &dec_model), symbol);
+                }
+            }
+            assert_eq!(decoder.pos, data.len());
+        }
+    }
+
+    #[test]
+    fn test_sections() {
+        dec_tests::test_sections(
+            SimdRansEncoder::<2>::new(1 << 16),
+            SimdRansDecoder::<2>::new,
+        );
+    }
+
+    #[test]
+    fn test_uniform() {
+        dec_tests::test_uniform(SimdRansEncoder::<1>::new(1024), SimdRansDecoder::<1>::new);
+    }
+
+    #[test]
+    fn test_is_exhausted() {
+        dec_tests::test_is_exhausted(
+            SimdRansEncoder::<2>::new(1024),
+            SimdRansDecoder::<2>::new,
+            SimdRansDecoder::<2>::is_exhausted,
+        );
+    }
+
+    #[test]
+    fn test_reinit_tail() {
+        dec_tests::test_reinit_tail(
+            || SimdRansEncoder::<2>::new(1 << 16),
+            SimdRansDecoder::<2>::new,
+            SimdRansDecoder::<2>::reinit_tail,
+        );
+    }
+
+    #[test]
+    fn test_decode_symbols_all() {
+        dec_tests::test_decode_symbols_all(
+            SimdRansEncoder::<3>::new(1024),
+            SimdRansDecoder::<3>::new,
+        );
+    }
+
+    #[test]
+    fn test_try_advance_truncated() {
+        dec_tests::test_try_advance_truncated(
+            SimdRansEncoder::<2>::new(1 << 16),
+            SimdRansDecoder::<2>::new,
+            4,
+        );
+    }
+
+    #[test]
+    fn test_symbol_eq_hash() {
+        dec_tests::test_symbol_eq_hash::<SimdRansDecSymbol>();
+    }
+
+    #[test]
+    fn test_has_debug_output() {
+        let decoder = SimdRansDecoder::<1>::new([0, 0, 1, 0]);
+        dec_tests::test_has_debug_output(decoder);
+    }
+}
@@ -0,0 +1,979 @@
+use std::ops::Range;
+
+use crate::encoder::{
+    assert_scale_bits, EncCheckpoint, RansEncSymbol, RansEncoder, RansEncoderMulti,
+};
+use crate::lookup::AliasTable;
+use crate::simd_decoder::SimdLevel;
+use crate::RansError;
+
+/// Lower bound of the normalization interval.
+pub(crate) const RANS_WORD_L: u32 = 1 << 16;
+/// Maximum number of bytes a single `put` can write into the buffer.
+pub(crate) const MAX_PUT_LEN: usize = 2;
+/// Number of bytes a single `flush` writes into the buffer.
+pub(crate) const FLUSH_LEN: usize = 4;
+/// Initial buffer size, in bytes, of encoders with a growable buffer.
+const INITIAL_GROWABLE_LEN: usize = 256;
+
+/// Multi-stream interleaved rANS encoder - 16-bit word version, producing
+/// data for [`SimdRansDecoder`](crate::simd_decoder::SimdRansDecoder).
+///
+/// The states are 32 bits wide and are renormalized by emitting 16-bit
+/// little-endian words, which is the format that can be decoded using SIMD
+/// instructions. The scale bits can't be larger than 16.
+#[derive(Debug)]
+pub struct SimdRansEncoder<const N: usize> {
+    states: [u32; N],
+    dst: Vec<u8>,
+    pos: usize,
+    growable: bool,
+    /// Length of the data when the current section started
+    section_start: usize,
+    level: SimdLevel,
+}
+
+impl<const N: usize> SimdRansEncoder<N> {
+    /// Creates a new `SimdRansEncoder` instance that can contain `max_len`
+    /// bytes in the internal buffer.
+    ///
+    /// Note that most of the API is inside the [`RansEncoderMulti`] trait, so
+    /// you probably want to `use rans::RansEncoderMulti`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_encoder::SimdRansEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let encoder = SimdRansEncoder::<4>::new(1024);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(max_len: usize) -> Self {
+        debug_assert!(N > 0);
+
+        let mut encoder = Self {
+            states: [RANS_WORD_L; N],
+            dst: vec![0; max_len & !1],
+            pos: 0,
+            growable: false,
+            section_start: 0,
+            level: SimdLevel::detect(),
+        };
+        encoder.reset();
+        encoder
+    }
+
+    /// Creates a new `SimdRansEncoder` instance that uses given vector as its
+    /// internal buffer, so that the allocation can be reused across encoders.
+    /// The whole capacity of the vector is used and its contents are
+    /// overwritten.
+    ///
+    /// Use [`Self::into_buffer()`] to get the vector back.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_encoder::SimdRansEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = SimdRansEncoder::<2>::with_buffer(Vec::with_capacity(1024));
+    /// encoder.flush_all();
+    /// let buffer = encoder.into_buffer();
+    /// assert_eq!(buffer.len(), 8);
+    /// assert!(buffer.capacity() >= 1024);
+    ///
+    /// let encoder = SimdRansEncoder::<2>::with_buffer(buffer);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn with_buffer(mut buffer: Vec<u8>) -> Self {
+        debug_assert!(N > 0);
+
+        buffer.resize(buffer.capacity() & !1, 0);
+        // The states get initialized by `reset()` below
+        let mut encoder = Self {
+            states: [RANS_WORD_L; N],
+            dst: buffer,
+            pos: 0,
+            growable: false,
+            section_start: 0,
+            level: SimdLevel::detect(),
+        };
+        encoder.reset();
+        encoder
+    }
+
+    /// Consumes this encoder, returning its internal buffer with the encoded
+    /// data moved to the beginning. The buffer's capacity is retained,
+    /// so it can be passed to [`Self::with_buffer()`] again.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_encoder::SimdRansEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = SimdRansEncoder::<2>::new(1024);
+    /// encoder.flush_all();
+    /// let len = encoder.len();
+    /// assert_eq!(encoder.into_buffer().len(), len);
+    /// ```
+    #[must_use]
+    pub fn into_buffer(mut self) -> Vec<u8> {
+        let len = self.dst.len() - self.pos;
+        self.dst.copy_within(self.pos.., 0);
+        self.dst.truncate(len);
+        self.dst
+    }
+
+    /// Creates a new `SimdRansEncoder` instance with a growable internal
+    /// buffer.
+    ///
+    /// Instead of being limited to a fixed size, the buffer is transparently
+    /// reallocated whenever the remaining space gets too small to hold the
+    /// next symbol. Already encoded data is preserved, so this is useful when
+    /// the size of the output cannot be estimated up front.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_encoder::{SimdRansEncSymbol, SimdRansEncoder};
+    /// use rans::{RansEncSymbol, RansEncoderMulti};
+    ///
+    /// let mut encoder = SimdRansEncoder::<4>::with_growable_buffer();
+    /// let symbol = SimdRansEncSymbol::new(0, 1, 8);
+    /// for i in 0..10000 {
+    ///     encoder.put_at(i % 4, &symbol);
+    /// }
+    /// encoder.flush_all();
+    /// assert!(encoder.len() > 5000);
+    /// ```
+    #[must_use]
+    pub fn with_growable_buffer() -> Self {
+        let mut encoder = Self::new(INITIAL_GROWABLE_LEN);
+        encoder.growable = true;
+        encoder
+    }
+
+    /// Puts a symbol into given channel using given alias table instead of
+    /// a regular [`SimdRansEncSymbol`]. The data has to be decoded with
+    /// [`RansDecoderMulti::decode_alias_at()`](crate::RansDecoderMulti::decode_alias_at)
+    /// using the same table.
+    ///
+    /// This is slower than [`RansEncoderMulti::put_at()`], as the encoder
+    /// symbol is computed on the fly.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    /// use rans::simd_encoder::SimdRansEncoder;
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    /// use rans::{RansDecoderMulti, RansEncoderMulti};
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    ///
+    /// let mut encoder = SimdRansEncoder::<2>::new(1024);
+    /// encoder.put_alias_at(0, &table, 1);
+    /// encoder.put_alias_at(1, &table, 2);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = SimdRansDecoder::<2>::new(encoder.data());
+    /// assert_eq!(decoder.decode_alias_at(0, &table), 2);
+    /// assert_eq!(decoder.decode_alias_at(1, &table), 1);
+    /// ```
+    pub fn put_alias_at(&mut self, channel: usize, table: &AliasTable, symbol: usize) {
+        checked_assert!(channel < N);
+
+        // The slot the symbol ends up in depends on the state after
+        // renormalization, which only depends on the symbol's frequency
+        let freq = table.freq(symbol);
+        let mut x = self.states[channel];
+        if (x >> 16) >= (RANS_WORD_L >> table.scale_bits()) * freq {
+            x >>= 16;
+        }
+        let offset = x % freq;
+        let start = table.remap(symbol, offset) - offset;
+        self.put_at(
+            channel,
+            &SimdRansEncSymbol::new(start, freq, table.scale_bits()),
+        );
+    }
+
+    /// Puts a symbol into every channel at once, `symbols[i]` being the
+    /// symbol lane `i` of
+    /// [`SimdRansDecoder`](crate::simd_decoder::SimdRansDecoder)
+    /// decodes, e.g. with
+    /// [`SimdRansDecoder::decode_symbols_all()`](crate::simd_decoder::SimdRansDecoder::decode_symbols_all),
+    /// after the encoder is flushed with [`RansEncoderMulti::flush_all()`].
+    ///
+    /// The states are processed all at once, using SSE4.1 or AVX2
+    /// instructions when the corresponding crate features are enabled and
+    /// the CPU supports them, which is much faster than putting the symbols
+    /// one by one. The output is the same as the one of
+    /// [`RansEncoderMulti::put_all()`] with exactly `N` symbols, i.e.
+    /// `symbols[i]` goes into channel `N - 1 - i`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::{DecModel, EncModel, Model};
+    /// use rans::simd_decoder::{SimdRansDecSymbol, SimdRansDecoder};
+    /// use rans::simd_encoder::{SimdRansEncSymbol, SimdRansEncoder};
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let model = Model::from_counts(&[1, 2, 5], 12);
+    /// let enc_model = EncModel::<SimdRansEncSymbol>::new(&model);
+    /// let batches = [[0, 1, 2, 2, 2, 1, 2, 0], [2, 2, 2, 2, 1, 1, 0, 0]];
+    ///
+    /// let mut encoder = SimdRansEncoder::<8>::new(1024);
+    /// for batch in batches.iter().rev() {
+    ///     encoder.put_symbols_all(&batch.map(|symbol| enc_model.symbol(symbol)));
+    /// }
+    /// encoder.flush_all();
+    ///
+    /// let dec_model = DecModel::<SimdRansDecSymbol>::new(&model);
+    /// let mut decoder = SimdRansDecoder::<8>::new(encoder.data());
+    /// for batch in &batches {
+    ///     assert_eq!(decoder.decode_symbols_all(&dec_model), *batch);
+    /// }
+    /// ```
+    pub fn put_symbols_all(&mut self, symbols: &[&SimdRansEncSymbol; N]) {
+        self.ensure_space(N * MAX_PUT_LEN);
+
+        // Channel `c` holds the symbol of decoder lane `N - 1 - c`, and the
+        // channels are processed in order, so that the decoder reads the
+        // renormalization words in the lane order
+        let done = self
+            .level
+            .put_step(&mut self.states, symbols, &mut self.dst, &mut self.pos);
+        for (channel, symbol) in symbols.iter().rev().enumerate().skip(done) {
+            let mut x = self.states[channel];
+            if symbol.needs_renorm(x) {
+                self.write_word(x as u16);
+                x >>= 16;
+            }
+            self.states[channel] = symbol.put(x);
+        }
+    }
+
+    /// Creates a checkpoint of this encoder's channel states and write
+    /// position, which can be restored later with [`Self::restore()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_encoder::SimdRansEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = SimdRansEncoder::<2>::new(1024);
+    /// let checkpoint = encoder.checkpoint();
+    /// encoder.flush_all();
+    /// encoder.restore(&checkpoint);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn checkpoint(&self) -> EncCheckpoint<u32, N> {
+        EncCheckpoint {
+            states: self.states,
+            len: self.len(),
+        }
+    }
+
+    /// Restores this encoder's channel states and write position from given
+    /// checkpoint, discarding everything put into the encoder since.
+    ///
+    /// The checkpoint must have been created by this encoder, after its last
+    /// reset; otherwise, the encoder produces garbage.
+    ///
+    /// # Panics
+    /// Panics if the checkpoint's data is longer than this encoder's buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_encoder::SimdRansEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = SimdRansEncoder::<2>::new(1024);
+    /// encoder.flush_at(0);
+    /// let checkpoint = encoder.checkpoint();
+    /// encoder.flush_at(1);
+    /// encoder.restore(&checkpoint);
+    /// assert_eq!(encoder.len(), checkpoint.data_len());
+    /// ```
+    pub fn restore(&mut self, checkpoint: &EncCheckpoint<u32, N>) {
+        assert!(
+            checkpoint.len <= self.dst.len(),
+            "checkpoint does not fit in the buffer"
+        );
+
+        self.states = checkpoint.states;
+        self.section_start = self.section_start.min(checkpoint.len);
+        self.pos = self.dst.len() - checkpoint.len;
+    }
+
+    /// Returns the current rANS state of given channel.
+    ///
+    /// Together with [`Self::set_state_at()`], this makes it possible to hand
+    /// streams over between this crate and other rANS implementations.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_encoder::SimdRansEncoder;
+    ///
+    /// let encoder = SimdRansEncoder::<2>::new(1024);
+    /// assert_eq!(encoder.state_at(1), 0x0001_0000);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn state_at(&self, channel: usize) -> u32 {
+        self.states[channel]
+    }
+
+    /// Overwrites the rANS state of given channel.
+    ///
+    /// The state has to be normalized, i.e. within the range the encoder keeps
+    /// its states in between the symbols; otherwise, the encoder produces
+    /// garbage.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_encoder::SimdRansEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = SimdRansEncoder::<1>::new(1024);
+    /// encoder.set_state_at(0, 0x0102_0304);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.data(), [4, 3, 2, 1]);
+    /// ```
+    #[inline]
+    pub fn set_state_at(&mut self, channel: usize, state: u32) {
+        self.states[channel] = state;
+    }
+
+    /// Makes sure there are at least `len` bytes left in the buffer, growing
+    /// it if this encoder has a growable buffer.
+    #[inline]
+    fn ensure_space(&mut self, len: usize) {
+        if self.growable && self.pos < len {
+            self.grow(len);
+        }
+    }
+
+    #[cold]
+    fn grow(&mut self, additional: usize) {
+        let used = self.dst.len() - self.pos;
+        let new_len = (self.dst.len() * 2).max(self.dst.len() + additional);
+
+        let mut dst = vec![0; new_len];
+        dst[new_len - used..].copy_from_slice(&self.dst[self.pos..]);
+        self.dst = dst;
+        self.pos = new_len - used;
+    }
+
+    #[inline]
+    fn write_word(&mut self, word: u16) {
+        self.pos -= 2;
+        self.dst[self.pos..self.pos + 2].copy_from_slice(&word.to_le_bytes());
+    }
+}
+
+impl<const N: usize> RansEncoderMulti<N> for SimdRansEncoder<N> {
+    type Symbol = SimdRansEncSymbol;
+
+    fn reset(&mut self) {
+        self.clear();
+        self.pos = self.dst.len();
+        self.section_start = 0;
+    }
+
+    fn clear(&mut self) {
+        self.states = [RANS_WORD_L; N];
+    }
+
+    #[inline]
+    fn put_at(&mut self, channel: usize, symbol: &Self::Symbol) {
+        checked_assert!(channel < N);
+        self.ensure_space(MAX_PUT_LEN);
+
+        let mut x = self.states[channel];
+        if symbol.needs_renorm(x) {
+            self.write_word(x as u16);
+            x >>= 16;
+        }
+
+        self.states[channel] = symbol.put(x);
+    }
+
+    fn try_put_at(&mut self, channel: usize, symbol: &Self::Symbol) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+        if !self.growable && self.pos < MAX_PUT_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.put_at(channel, symbol);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush_at(&mut self, channel: usize) {
+        checked_assert!(channel < N);
+        self.ensure_space(FLUSH_LEN);
+
+        let x = self.states[channel];
+        self.write_word((x >> 16) as u16);
+        self.write_word(x as u16);
+    }
+
+    fn try_flush_at(&mut self, channel: usize) -> Result<(), RansError> {
+        if channel >= N {
+            return Err(RansError::InvalidChannel);
+        }
+        if !self.growable && self.pos < FLUSH_LEN {
+            return Err(RansError::BufferOverflow);
+        }
+
+        self.flush_at(channel);
+        Ok(())
+    }
+
+    fn flush_section(&mut self) -> Range<usize> {
+        self.flush_all();
+        self.clear();
+        let section = self.section_start..self.len();
+        self.section_start = self.len();
+        section
+    }
+
+    #[inline]
+    fn data(&self) -> &[u8] {
+        &self.dst[self.pos..]
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.dst.len()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        if self.pos < additional {
+            self.grow(additional);
+        }
+    }
+}
+
+impl RansEncoder for SimdRansEncoder<1> {}
+
+impl SimdRansEncoder<1> {
+    /// Puts a symbol using given alias table instead of a regular
+    /// [`SimdRansEncSymbol`]. See [`Self::put_alias_at()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::simd_decoder::SimdRansDecoder;
+    /// use rans::simd_encoder::SimdRansEncoder;
+    /// use rans::lookup::AliasTable;
+    /// use rans::model::Model;
+    /// use rans::{RansDecoder, RansEncoderMulti};
+    ///
+    /// let table = AliasTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    ///
+    /// let mut encoder = SimdRansEncoder::<1>::new(1024);
+    /// encoder.put_alias(&table, 0);
+    /// encoder.put_alias(&table, 2);
+    /// encoder.flush_all();
+    ///
+    /// let mut decoder = SimdRansDecoder::<1>::new(encoder.data());
+    /// assert_eq!(decoder.decode_alias(&table), 2);
+    /// assert_eq!(decoder.decode_alias(&table), 0);
+    /// ```
+    pub fn put_alias(&mut self, table: &AliasTable, symbol: usize) {
+        self.put_alias_at(0, table, symbol);
+    }
+}
+
+/// rANS encoder symbol - 16-bit word version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimdRansEncSymbol {
+    start: u32,
+    freq: u32,
+    scale_bits: u32,
+    /// Upper 16 bits of the (exclusive) upper bound of the pre-normalization
+    /// interval
+    x_max: u32,
+    /// Complement of frequency: (1 << scale_bits) - freq
+    cmpl_freq: u32,
+}
+
+impl RansEncSymbol for SimdRansEncSymbol {
+    #[inline]
+    fn new(cum_freq: u32, freq: u32, scale_bits: u32) -> Self {
+        assert_scale_bits(scale_bits, Self::MAX_SCALE_BITS);
+        debug_assert!(freq != 0);
+        debug_assert!(cum_freq + freq <= 1 << scale_bits);
+
+        Self {
+            start: cum_freq,
+            freq,
+            scale_bits,
+            x_max: (RANS_WORD_L >> scale_bits) * freq,
+            cmpl_freq: (1 << scale_bits) - freq,
+        }
+    }
+
+    #[inline]
+    fn cum_freq(&self) -> u32 {
+        self.start
+    }
+
+    #[inline]
+    fn freq(&self) -> u32 {
+        self.freq
+    }
+
+    #[inline]
+    fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
+}
+
+impl SimdRansEncSymbol {
+    /// Returns whether state `x` has to be renormalized (i.e. have its lower
+    /// 16 bits emitted) before this symbol can be put into it.
+    #[inline]
+    pub(crate) fn needs_renorm(&self, x: u32) -> bool {
+        (x >> 16) >= self.x_max
+    }
+
+    /// Puts this symbol into an already renormalized state `x`.
+    #[inline]
+    pub(crate) fn put(&self, x: u32) -> u32 {
+        put_step(x, self.start, self.freq, self.cmpl_freq)
+    }
+}
+
+/// Encodes a symbol into a renormalized state, i.e. computes
+/// `(x / freq) * M + x % freq + start`, rewritten as
+/// `x + (x / freq) * (M - freq) + start`.
+#[inline]
+fn put_step(x: u32, start: u32, freq: u32, cmpl_freq: u32) -> u32 {
+    x + (x / freq) * cmpl_freq + start
+}
+
+impl SimdLevel {
+    /// Puts a symbol into as many leading states as possible using SIMD
+    /// instructions, writing the renormalization words backwards from
+    /// `pos`, and returns the number of states processed.
+    #[inline]
+    #[allow(unused_variables)]
+    fn put_step<const N: usize>(
+        self,
+        states: &mut [u32; N],
+        symbols: &[&SimdRansEncSymbol; N],
+        dst: &mut [u8],
+        pos: &mut usize,
+    ) -> usize {
+        match self {
+            Self::Scalar => 0,
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "sse41"))]
+            Self::Sse41 => unsafe { x86::put_step_sse41(states, symbols, dst, pos, 0) },
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "avx2"))]
+            Self::Avx2 => unsafe { x86::put_step_avx2(states, symbols, dst, pos) },
+        }
+    }
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    any(feature = "sse41", feature = "avx2")
+))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use crate::simd_encoder::SimdRansEncSymbol;
+
+    /// `pshufb` masks packing the lower 16 bits of the lanes that need to be
+    /// renormalized into the upper end of the lower 8 bytes, indexed by the
+    /// bitmask of those lanes. The lanes are written in the same order as
+    /// if they were written one by one backwards, i.e. the first lane ends
+    /// up at the highest address.
+    static SHUFFLES: [[i8; 16]; 16] = build_shuffles();
+
+    const fn build_shuffles() -> [[i8; 16]; 16] {
+        let mut shuffles = [[-1; 16]; 16];
+        let mut mask = 0;
+        while mask < 16 {
+            let mut byte = 8;
+            let mut lane = 0;
+            while lane < 4 {
+                if mask & (1 << lane) != 0 {
+                    byte -= 2;
+                    shuffles[mask][byte] = lane as i8 * 4;
+                    shuffles[mask][byte + 1] = lane as i8 * 4 + 1;
+                }
+                lane += 1;
+            }
+            mask += 1;
+        }
+        shuffles
+    }
+
+    // There is no SIMD integer division, so the quotients are computed in
+    // double precision instead, as `x * (1 / freq)`. The reciprocal doesn't
+    // depend on the state, so it stays off the critical path between the
+    // symbols, and it's rounded up by one ulp, so that the product is never
+    // below the exact quotient. The states are below 2^32 and the
+    // frequencies at most 2^16, so the product exceeds the quotient by less
+    // than 2^-18, which is less than the distance between a non-integer
+    // quotient and the next integer (at least 2^-16), and truncating it
+    // yields the exact integer quotient.
+
+    /// Converts the lower two unsigned 32-bit lanes to doubles.
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn cvt_epu32_pd(x: __m128i) -> __m128d {
+        // Flip the sign bit to convert the values as signed integers, then
+        // compensate in double precision
+        let x = _mm_xor_si128(x, _mm_set1_epi32(i32::MIN));
+        _mm_add_pd(_mm_cvtepi32_pd(x), _mm_set1_pd(2_147_483_648.0))
+    }
+
+    /// Computes `x / freq` for the lower two lanes.
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn div_sse41(x: __m128i, freq: __m128i) -> __m128i {
+        let rcp = _mm_div_pd(_mm_set1_pd(1.0), _mm_cvtepi32_pd(freq));
+        let rcp = _mm_castsi128_pd(_mm_add_epi64(_mm_castpd_si128(rcp), _mm_set1_epi64x(1)));
+        // The quotients are below 2^31 unless `freq` is 1 and `M` is 1 as
+        // well, in which case they get multiplied by a zero `cmpl_freq`
+        _mm_cvttpd_epi32(_mm_mul_pd(cvt_epu32_pd(x), rcp))
+    }
+
+    /// Loads a field of the symbols of channels `channel..channel + 4`.
+    #[inline(always)]
+    unsafe fn load_sse41<const N: usize>(
+        symbols: &[&SimdRansEncSymbol; N],
+        channel: usize,
+        field: impl Fn(&SimdRansEncSymbol) -> u32,
+    ) -> __m128i {
+        let lane = |i: usize| field(symbols[N - 1 - channel - i]) as i32;
+        _mm_setr_epi32(lane(0), lane(1), lane(2), lane(3))
+    }
+
+    /// Renormalizes the states of 4 consecutive channels, writing the
+    /// lower 16 bits of the ones that need it backwards from `pos`. The
+    /// words are always stored as 8 bytes, so `pos` must be at least 8.
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn renorm_sse41(x: __m128i, x_max: __m128i, dst: &mut [u8], pos: &mut usize) -> __m128i {
+        debug_assert!(*pos >= 8);
+
+        // Both values fit in 17 bits, so they can be compared as signed
+        let x_hi = _mm_srli_epi32(x, 16);
+        let keep = _mm_cmpgt_epi32(x_max, x_hi);
+        let lanes = !_mm_movemask_ps(_mm_castsi128_ps(keep)) as usize & 0xf;
+
+        let shuffle = _mm_loadu_si128(SHUFFLES[lanes].as_ptr() as *const __m128i);
+        let words = _mm_shuffle_epi8(x, shuffle);
+        _mm_storel_epi64(dst.as_mut_ptr().add(*pos - 8) as *mut __m128i, words);
+        *pos -= lanes.count_ones() as usize * 2;
+
+        _mm_blendv_epi8(x_hi, x, keep)
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub(super) unsafe fn put_step_sse41<const N: usize>(
+        states: &mut [u32; N],
+        symbols: &[&SimdRansEncSymbol; N],
+        dst: &mut [u8],
+        pos: &mut usize,
+        from: usize,
+    ) -> usize {
+        let mut i = from;
+
+        while i + 4 <= N && *pos >= 8 {
+            let x = _mm_loadu_si128(states.as_ptr().add(i) as *const __m128i);
+            let x_max = load_sse41(symbols, i, |symbol| symbol.x_max);
+            let start = load_sse41(symbols, i, |symbol| symbol.start);
+            let freq = load_sse41(symbols, i, |symbol| symbol.freq);
+            let cmpl_freq = load_sse41(symbols, i, |symbol| symbol.cmpl_freq);
+
+            let x = renorm_sse41(x, x_max, dst, pos);
+
+            let q_lo = div_sse41(x, freq);
+            let q_hi = div_sse41(_mm_unpackhi_epi64(x, x), _mm_unpackhi_epi64(freq, freq));
+            let q = _mm_unpacklo_epi64(q_lo, q_hi);
+
+            let x = _mm_add_epi32(_mm_add_epi32(x, _mm_mullo_epi32(q, cmpl_freq)), start);
+            _mm_storeu_si128(states.as_mut_ptr().add(i) as *mut __m128i, x);
+            i += 4;
+        }
+
+        i
+    }
+
+    #[cfg(feature = "avx2")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn div_avx2(x: __m128i, freq: __m128i) -> __m128i {
+        let rcp = _mm256_div_pd(_mm256_set1_pd(1.0), _mm256_cvtepi32_pd(freq));
+        let rcp = _mm256_castsi256_pd(_mm256_add_epi64(
+            _mm256_castpd_si256(rcp),
+            _mm256_set1_epi64x(1),
+        ));
+        let x = _mm_xor_si128(x, _mm_set1_epi32(i32::MIN));
+        let x = _mm256_add_pd(_mm256_cvtepi32_pd(x), _mm256_set1_pd(2_147_483_648.0));
+        _mm256_cvttpd_epi32(_mm256_mul_pd(x, rcp))
+    }
+
+    /// Loads a field of the symbols of channels `channel..channel + 8`.
+    #[cfg(feature = "avx2")]
+    #[inline(always)]
+    unsafe fn load_avx2<const N: usize>(
+        symbols: &[&SimdRansEncSymbol; N],
+        channel: usize,
+        field: impl Fn(&SimdRansEncSymbol) -> u32,
+    ) -> __m256i {
+        let lane = |i: usize| field(symbols[N - 1 - channel - i]) as i32;
+        _mm256_setr_epi32(
+            lane(0),
+            lane(1),
+            lane(2),
+            lane(3),
+            lane(4),
+            lane(5),
+            lane(6),
+            lane(7),
+        )
+    }
+
+    #[cfg(feature = "avx2")]
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn put_step_avx2<const N: usize>(
+        states: &mut [u32; N],
+        symbols: &[&SimdRansEncSymbol; N],
+        dst: &mut [u8],
+        pos: &mut usize,
+    ) -> usize {
+        let mut i = 0;
+
+        while i + 8 <= N && *pos >= 16 {
+            let x = _mm256_loadu_si256(states.as_ptr().add(i) as *const __m256i);
+            let x_max = load_avx2(symbols, i, |symbol| symbol.x_max);
+            let start = load_avx2(symbols, i, |symbol| symbol.start);
+            let freq = load_avx2(symbols, i, |symbol| symbol.freq);
+            let cmpl_freq = load_avx2(symbols, i, |symbol| symbol.cmpl_freq);
+
+            // Packing the words doesn't get any faster with 256-bit
+            // registers, so each half is renormalized separately
+            let x_lo = renorm_sse41(
+                _mm256_castsi256_si128(x),
+                _mm256_castsi256_si128(x_max),
+                dst,
+                pos,
+            );
+            let x_hi = renorm_sse41(
+                _mm256_extracti128_si256(x, 1),
+                _mm256_extracti128_si256(x_max, 1),
+                dst,
+                pos,
+            );
+
+            let q_lo = div_avx2(x_lo, _mm256_castsi256_si128(freq));
+            let q_hi = div_avx2(x_hi, _mm256_extracti128_si256(freq, 1));
+            let x = _mm256_inserti128_si256(_mm256_castsi128_si256(x_lo), x_hi, 1);
+            let q = _mm256_inserti128_si256(_mm256_castsi128_si256(q_lo), q_hi, 1);
+
+            let x = _mm256_add_epi32(_mm256_add_epi32(x, _mm256_mullo_epi32(q, cmpl_freq)), start);
+            _mm256_storeu_si256(states.as_mut_ptr().add(i) as *mut __m256i, x);
+            i += 8;
+        }
+
+        put_step_sse41(states, symbols, dst, pos, i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::encoder::tests as enc_tests;
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::simd_decoder::{SimdLevel, SimdRansDecSymbol, SimdRansDecoder};
+    use crate::simd_encoder::{SimdRansEncSymbol, SimdRansEncoder};
+    use crate::RansEncoderMulti;
+
+    #[test]
+    fn test_encode_nothing() {
+        let encoder = SimdRansEncoder::<1>::new(1024);
+
+        enc_tests::test_encode_nothing(encoder);
+    }
+
+    #[test]
+    fn test_encode_empty_data() {
+        let encoder = SimdRansEncoder::<1>::new(1024);
+        let data = [0, 0, 1, 0];
+
+        enc_tests::test_encode_empty_data(encoder, &data);
+    }
+
+    #[test]
+    fn test_encode_and_clear() {
+        enc_tests::test_encode_and_clear(
+            SimdRansEncoder::<1>::new(1024),
+            SimdRansEncoder::<1>::new(1024),
+            SimdRansEncoder::<1>::new(1024),
+        );
+    }
+
+    #[test]
+    fn test_encode_two_symbols() {
+        let encoder = SimdRansEncoder::<1>::new(1024);
+        let data = [2, 0, 4, 0];
+
+        enc_tests::test_encode_two_symbols(encoder, &data)
+    }
+
+    #[test]
+    fn test_encode_interleaved() {
+        let encoder = SimdRansEncoder::<2>::new(1024);
+        let data = [108, 0, 0, 1, 0, 0, 0, 1];
+
+        enc_tests::encode_interleaved(encoder, &data);
+    }
+
+    #[test]
+    fn test_encode_with_buffer() {
+        // Leftover garbage in the buffer must not matter
+        let mut buffer = vec![0xff; 1024];
+        buffer.truncate(10);
+        let encoder = SimdRansEncoder::<2>::with_buffer(buffer);
+        let data = [108, 0, 0, 1, 0, 0, 0, 1];
+
+        let encoder = enc_tests::encode_interleaved(encoder, &data);
+        let buffer = encoder.into_buffer();
+        assert_eq!(buffer.len(), 8);
+        assert!(buffer.capacity() >= 1024);
+    }
+
+    #[test]
+    fn test_try_put_overflow() {
+        let encoder = SimdRansEncoder::<1>::new(16);
+
+        enc_tests::test_try_put_overflow(encoder);
+    }
+
+    #[test]
+    fn test_try_flush_overflow() {
+        let encoder = SimdRansEncoder::<2>::new(6);
+
+        enc_tests::test_try_flush_overflow(encoder);
+    }
+
+    #[test]
+    fn test_encode_growable() {
+        let encoder = SimdRansEncoder::<2>::with_growable_buffer();
+        let reference = SimdRansEncoder::<2>::new(1 << 16);
+
+        enc_tests::test_encode_growable(encoder, reference);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let encoder = SimdRansEncoder::<2>::new(16);
+        let reference = SimdRansEncoder::<2>::new(1 << 16);
+
+        enc_tests::test_reserve(encoder, reference);
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let encoder = SimdRansEncoder::<2>::with_growable_buffer();
+        let reference = SimdRansEncoder::<2>::new(1 << 16);
+
+        enc_tests::test_checkpoint_restore(
+            encoder,
+            reference,
+            SimdRansEncoder::checkpoint,
+            SimdRansEncoder::restore,
+        );
+    }
+
+    #[test]
+    fn test_symbol_accessors() {
+        enc_tests::test_symbol_accessors::<SimdRansEncSymbol>();
+    }
+
+    #[test]
+    fn test_symbol_eq_hash() {
+        enc_tests::test_symbol_eq_hash::<SimdRansEncSymbol>();
+    }
+
+    fn test_put_symbols_all<const N: usize>() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(N as u64);
+
+        // Includes symbols with frequency 1 and very large frequencies
+        let models = [
+            Model::from_freqs(vec![1, 1], 1),
+            Model::from_counts(&[5, 1, 1, 9], 4),
+            Model::from_counts(&[10000, 1, 1, 50, 7], 12),
+            Model::from_counts(&[100_000, 1, 1, 50, 7], 16),
+        ];
+        for model in models {
+            let enc_model = EncModel::<SimdRansEncSymbol>::new(&model);
+            let batches: Vec<[usize; N]> = (0..2000)
+                .map(|_| [0; N].map(|_| rng.gen_range(0..model.len())))
+                .collect();
+
+            let mut reference = SimdRansEncoder::<N>::new(1 << 16);
+            for batch in batches.iter().rev() {
+                let symbols: Vec<_> = batch.iter().map(|&i| enc_model.symbol(i)).collect();
+                reference.put_all(&symbols);
+            }
+            reference.flush_all();
+
+            let mut levels = vec![SimdLevel::Scalar, SimdLevel::detect()];
+            levels.dedup();
+            for level in levels {
+                let mut encoder = SimdRansEncoder::<N>::with_growable_buffer();
+                encoder.level = level;
+                for batch in batches.iter().rev() {
+                    encoder.put_symbols_all(&batch.map(|i| enc_model.symbol(i)));
+                }
+                encoder.flush_all();
+                assert_eq!(encoder.data(), reference.data());
+            }
+
+            let dec_model = DecModel::<SimdRansDecSymbol>::new(&model);
+            let mut decoder = SimdRansDecoder::<N>::new(reference.data());
+            for batch in &batches {
+                assert_eq!(decoder.decode_symbols_all(&dec_model), *batch);
+            }
+        }
+    }
+
+    #[test]
+    fn test_put_symbols_all_4() {
+        test_put_symbols_all::<4>();
+    }
+
+    #[test]
+    fn test_put_symbols_all_8() {
+        test_put_symbols_all::<8>();
+    }
+
+    #[test]
+    fn test_put_symbols_all_16() {
+        test_put_symbols_all::<16>();
+    }
+
+    #[test]
+    fn test_put_symbols_all_odd() {
+        test_put_symbols_all::<13>();
+    }
+
+    #[test]
+    fn test_has_debug_output() {
+        let encoder = SimdRansEncoder::<1>::new(1024);
+        enc_tests::test_has_debug_output(encoder);
+    }
+}
@@ -0,0 +1,470 @@
+use std::ops::RangeInclusive;
+
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder, ByteRansDecoderMulti};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::lookup::AliasTable;
+use crate::model::sparse::SparseModel;
+use crate::model::{DecModel, EncModel, Model};
+use crate::{
+    varint, RansDecSymbol, RansDecoder, RansDecoderMulti, RansEncoder, RansEncoderMulti, RansError,
+};
+
+pub(crate) const SCALE_BITS: u32 = 12;
+
+/// Compresses a byte buffer using an order-0 static model.
+///
+/// The output starts with a small header containing the original length and
+/// the frequencies of the bytes present in the input, followed by the rANS
+/// bitstream. Use [`decompress()`] to get the original data back.
+///
+/// # Examples
+/// ```
+/// use rans::simple::{compress, decompress};
+///
+/// let data = b"abracadabra";
+/// let compressed = compress(data);
+/// assert_eq!(decompress(&compressed).unwrap(), data);
+/// ```
+#[must_use]
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    varint::write_u64(&mut output, data.len() as u64);
+    if data.is_empty() {
+        return output;
+    }
+
+    let model = Model::from_counts(&histogram(data), SCALE_BITS);
+    write_model(&mut output, &model);
+
+    // With 12-bit frequencies, a single symbol emits at most 2 bytes.
+    let mut encoder = ByteRansEncoder::new(data.len() * 2 + 4);
+    let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+    for &byte in data.iter().rev() {
+        encoder.put_index(byte as usize, &enc_model);
+    }
+    encoder.flush();
+
+    output.extend_from_slice(encoder.data());
+    output
+}
+
+/// Decompresses data produced by [`compress()`].
+///
+/// Malformed data results in an error, but the output length is only
+/// limited by the header; use
+/// [`hardened::decompress()`](crate::hardened::decompress) for data coming
+/// from untrusted sources.
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the input is truncated,
+/// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if the header is
+/// malformed, or [`RansError::InputExhausted`] if the bitstream ends before
+/// all the bytes are decoded.
+///
+/// # Examples
+/// ```
+/// use rans::simple::{compress, decompress};
+/// use rans::RansError;
+///
+/// let compressed = compress(b"hello");
+/// assert_eq!(decompress(&compressed).unwrap(), b"hello");
+/// assert_eq!(decompress(&compressed[..3]), Err(RansError::UnexpectedEof));
+/// ```
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, RansError> {
+    let mut pos = 0;
+    let len =
+        usize::try_from(varint::read_u64(data, &mut pos)?).map_err(|_| RansError::InvalidHeader)?;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    let model = read_model(data, &mut pos)?;
+    let payload = &data[pos..];
+    if payload.len() < 4 {
+        return Err(RansError::UnexpectedEof);
+    }
+
+    let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    decode_symbols_checked(&mut ByteRansDecoder::new(payload), &dec_model, len)
+}
+
+/// Compresses a buffer of 16-bit symbols (e.g. quantized coefficients or
+/// token IDs) using an order-0 static model, without splitting them into
+/// bytes.
+///
+/// The model is a [`SparseModel`] covering only the symbols present in the
+/// input, and its `scale_bits` grows with their number, up to 16. Since large
+/// alphabets would make a regular lookup table slow, the symbols are coded
+/// using an [`AliasTable`]. Use [`decompress_u16()`] to get the original data
+/// back.
+///
+/// # Examples
+/// ```
+/// use rans::simple::{compress_u16, decompress_u16};
+///
+/// let data: Vec<u16> = (0..1000).map(|i| (i * i % 40000) as u16).collect();
+/// let compressed = compress_u16(&data);
+/// assert_eq!(decompress_u16(&compressed).unwrap(), data);
+/// ```
+#[must_use]
+pub fn compress_u16(data: &[u16]) -> Vec<u8> {
+    let mut output = Vec::new();
+    varint::write_u64(&mut output, data.len() as u64);
+    if data.is_empty() {
+        return output;
+    }
+
+    let mut counts = vec![0_u64; 1 << 16];
+    for &symbol in data {
+        counts[usize::from(symbol)] += 1;
+    }
+    let alphabet_bits = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .count()
+        .next_power_of_two()
+        .trailing_zeros();
+    let model = SparseModel::from_counts(
+        &scale_counts(&counts),
+        (alphabet_bits + 2).clamp(SCALE_BITS, 16),
+    );
+    output.extend_from_slice(&model.to_bytes());
+
+    // With at most 16-bit frequencies, a single symbol emits at most 2 bytes.
+    let mut encoder = ByteRansEncoder::new(data.len() * 2 + 4);
+    let table = AliasTable::new(model.model());
+    for &symbol in data.iter().rev() {
+        let index = model.index_of(usize::from(symbol)).unwrap();
+        encoder.put_alias(&table, index);
+    }
+    encoder.flush();
+
+    output.extend_from_slice(encoder.data());
+    output
+}
+
+/// Decompresses data produced by [`compress_u16()`]. Like [`decompress()`],
+/// this doesn't limit the output length.
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the input is truncated,
+/// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if the header is
+/// malformed, or [`RansError::InputExhausted`] if the bitstream ends before
+/// all the symbols are decoded.
+///
+/// # Examples
+/// ```
+/// use rans::simple::{compress_u16, decompress_u16};
+/// use rans::RansError;
+///
+/// let compressed = compress_u16(&[65535, 0, 65535]);
+/// assert_eq!(decompress_u16(&compressed).unwrap(), [65535, 0, 65535]);
+/// assert_eq!(decompress_u16(&compressed[..3]), Err(RansError::UnexpectedEof));
+/// ```
+pub fn decompress_u16(data: &[u8]) -> Result<Vec<u16>, RansError> {
+    let mut pos = 0;
+    let len =
+        usize::try_from(varint::read_u64(data, &mut pos)?).map_err(|_| RansError::InvalidHeader)?;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    let model = SparseModel::read_bytes(data, &mut pos)?;
+    if model.model().scale_bits() > 16 || model.symbol(model.len() - 1) > 0xffff {
+        return Err(RansError::InvalidHeader);
+    }
+    let payload = &data[pos..];
+    if payload.len() < 4 {
+        return Err(RansError::UnexpectedEof);
+    }
+
+    let table = AliasTable::new(model.model());
+    let scale_bits = table.scale_bits();
+    let mut decoder = ByteRansDecoder::new(payload);
+    let mut output = output_buffer(&decoder, len);
+    for _ in 0..len {
+        // Same as `RansDecoder::decode_alias()`, with the read checked
+        let slot = decoder.get(scale_bits);
+        let (index, offset) = table.lookup(slot);
+        let symbol = ByteRansDecSymbol::new(slot - offset, table.freq(index));
+        decoder.try_advance(&symbol, scale_bits)?;
+        output.push(model.symbol(index) as u16);
+    }
+
+    Ok(output)
+}
+
+/// Creates the output buffer for `len` symbols declared in a header and
+/// coded in the data of `decoder`.
+///
+/// The declared length isn't trusted with the allocation: the buffer starts
+/// with room for at most 8 symbols per byte of the data and grows from there,
+/// so that a corrupted header can't make the decoders allocate huge amounts
+/// of memory upfront. Together with decoding every symbol with
+/// [`decode_symbol_checked()`], which never reads past the end of the data,
+/// this makes the codecs built on top of it fail with [`RansError`] on
+/// truncated or corrupted input. The output can still be as long as the
+/// header declares if the model lets symbols be decoded without consuming
+/// any data, though.
+pub(crate) fn output_buffer<T, const N: usize>(
+    decoder: &ByteRansDecoderMulti<'_, N>,
+    len: usize,
+) -> Vec<T> {
+    Vec::with_capacity(len.min(decoder.bytes_remaining().saturating_mul(8)))
+}
+
+/// Decodes a single symbol coded with `dec_model` at given channel and
+/// returns its index, like
+/// [`RansDecoderMulti::decode_symbol_at()`](crate::RansDecoderMulti::decode_symbol_at),
+/// but with the read checked.
+pub(crate) fn decode_symbol_checked<const N: usize>(
+    decoder: &mut ByteRansDecoderMulti<'_, N>,
+    channel: usize,
+    dec_model: &DecModel<ByteRansDecSymbol>,
+) -> Result<usize, RansError> {
+    let scale_bits = dec_model.scale_bits();
+    let (index, symbol) = dec_model.lookup(decoder.get_at(channel, scale_bits));
+    decoder.try_advance_at(channel, symbol, scale_bits)?;
+    Ok(index)
+}
+
+/// Decodes `len` bytes coded with `dec_model`, using [`output_buffer()`] and
+/// [`decode_symbol_checked()`].
+pub(crate) fn decode_symbols_checked(
+    decoder: &mut ByteRansDecoder<'_>,
+    dec_model: &DecModel<ByteRansDecSymbol>,
+    len: usize,
+) -> Result<Vec<u8>, RansError> {
+    let mut output = output_buffer(decoder, len);
+    for _ in 0..len {
+        output.push(decode_symbol_checked(decoder, 0, dec_model)? as u8);
+    }
+
+    Ok(output)
+}
+
+pub(crate) fn histogram(data: &[u8]) -> Vec<u32> {
+    let mut counts = [0_u64; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+
+    scale_counts(&counts)
+}
+
+pub(crate) fn scale_counts(counts: &[u64]) -> Vec<u32> {
+    // Keep the counts within u32 range for huge inputs while making sure no
+    // present symbol drops to zero.
+    let max = counts.iter().copied().max().unwrap_or(0);
+    let shift = (64 - max.leading_zeros()).saturating_sub(32);
+    counts
+        .iter()
+        .map(|&raw| ((raw >> shift) as u32).max(raw.min(1) as u32))
+        .collect()
+}
+
+pub(crate) fn write_model(output: &mut Vec<u8>, model: &Model) {
+    let present: Vec<(usize, u32)> = model
+        .freqs()
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|&(_, freq)| freq > 0)
+        .collect();
+
+    output.push(model.scale_bits() as u8);
+    output.push((present.len() - 1) as u8);
+    for (symbol, freq) in present {
+        output.push(symbol as u8);
+        varint::write_u64(output, u64::from(freq));
+    }
+}
+
+pub(crate) fn read_model(data: &[u8], pos: &mut usize) -> Result<Model, RansError> {
+    read_model_with_scale(data, pos, SCALE_BITS..=SCALE_BITS)
+}
+
+/// Reads a model written by [`write_model()`], accepting any `scale_bits`
+/// within given range.
+pub(crate) fn read_model_with_scale(
+    data: &[u8],
+    pos: &mut usize,
+    valid_scale_bits: RangeInclusive<u32>,
+) -> Result<Model, RansError> {
+    let header = data.get(*pos..*pos + 2).ok_or(RansError::UnexpectedEof)?;
+    let (scale_bits, count) = (u32::from(header[0]), usize::from(header[1]) + 1);
+    *pos += 2;
+    if !valid_scale_bits.contains(&scale_bits) {
+        return Err(RansError::InvalidHeader);
+    }
+
+    let mut freqs = vec![0; 256];
+    let mut sum = 0;
+    for _ in 0..count {
+        let symbol = *data.get(*pos).ok_or(RansError::UnexpectedEof)? as usize;
+        *pos += 1;
+        let freq = varint::read_u64(data, pos)?;
+        if freq == 0 || freqs[symbol] != 0 || freq > 1 << scale_bits {
+            return Err(RansError::InvalidModel);
+        }
+
+        freqs[symbol] = freq as u32;
+        sum += freq;
+    }
+
+    if sum != 1 << scale_bits {
+        return Err(RansError::InvalidModel);
+    }
+    Ok(Model::from_freqs(freqs, scale_bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::sparse::SparseModel;
+    use crate::simple::{compress, compress_u16, decompress, decompress_u16, histogram};
+    use crate::{varint, RansError};
+
+    #[test]
+    fn test_round_trip_empty() {
+        let compressed = compress(&[]);
+
+        assert_eq!(compressed, [0]);
+        assert_eq!(decompress(&compressed), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_round_trip_single_symbol() {
+        let data = [42; 1000];
+        let compressed = compress(&data);
+
+        assert!(compressed.len() < 16);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_round_trip_all_bytes() {
+        let data: Vec<u8> = (0..10000_u32).map(|x| (x * x % 251) as u8).collect();
+        let compressed = compress(&data);
+
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_compresses_skewed_data() {
+        let data: Vec<u8> = (0..10000_u32)
+            .map(|x| if x % 10 == 0 { b'b' } else { b'a' })
+            .collect();
+        let compressed = compress(&data);
+
+        assert!(compressed.len() < data.len() / 4);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_invalid_model() {
+        let mut compressed = compress(b"aab");
+        // Frequency of the first symbol
+        compressed[4] += 1;
+
+        assert_eq!(decompress(&compressed), Err(RansError::InvalidModel));
+    }
+
+    #[test]
+    fn test_invalid_scale_bits() {
+        let mut compressed = compress(b"aab");
+        compressed[1] = 20;
+
+        assert_eq!(decompress(&compressed), Err(RansError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let compressed = compress(b"aab");
+
+        for len in 0..compressed.len() - 4 {
+            assert_eq!(
+                decompress(&compressed[..len]),
+                Err(RansError::UnexpectedEof)
+            );
+        }
+    }
+
+    #[test]
+    fn test_declared_len_too_large() {
+        let compressed = compress(b"aab");
+        let mut corrupted = Vec::new();
+        varint::write_u64(&mut corrupted, (1 << 46) - 1);
+        corrupted.extend_from_slice(&compressed[1..]);
+
+        assert_eq!(decompress(&corrupted), Err(RansError::InputExhausted));
+    }
+
+    #[test]
+    fn test_histogram_keeps_rare_symbols() {
+        let mut data = vec![0; 100];
+        data.push(1);
+
+        let counts = histogram(&data);
+        assert_eq!(counts[0], 100);
+        assert_eq!(counts[1], 1);
+    }
+
+    #[test]
+    fn test_round_trip_u16_full_alphabet() {
+        let data: Vec<u16> = (0..200_000_u32)
+            .map(|x| (x.wrapping_mul(2_654_435_761) >> 16) as u16)
+            .collect();
+        let compressed = compress_u16(&data);
+
+        assert_eq!(decompress_u16(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_round_trip_u16_empty() {
+        let compressed = compress_u16(&[]);
+
+        assert_eq!(compressed, [0]);
+        assert_eq!(decompress_u16(&compressed), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_compresses_skewed_u16_data() {
+        let data: Vec<u16> = (0..10000_u32)
+            .map(|x| if x % 10 == 0 { 50000 } else { 1234 })
+            .collect();
+        let compressed = compress_u16(&data);
+
+        assert!(compressed.len() < data.len() / 4);
+        assert_eq!(decompress_u16(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_round_trip_u16_sparse_alphabet() {
+        let data: Vec<u16> = (0..20_u16)
+            .map(|x| [7, 30000, 65535][usize::from(x % 3)])
+            .collect();
+        let compressed = compress_u16(&data);
+
+        assert!(compressed.len() < 32);
+        assert_eq!(decompress_u16(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_u16_declared_len_too_large() {
+        let compressed = compress_u16(&[7, 30000, 65535]);
+        let mut corrupted = Vec::new();
+        varint::write_u64(&mut corrupted, (1 << 46) - 1);
+        corrupted.extend_from_slice(&compressed[1..]);
+
+        assert_eq!(decompress_u16(&corrupted), Err(RansError::InputExhausted));
+    }
+
+    #[test]
+    fn test_u16_invalid_scale_bits() {
+        let mut compressed = vec![1];
+        compressed.extend_from_slice(&SparseModel::from_counts(&[1], 17).to_bytes());
+        compressed.extend_from_slice(&[0, 0, 128, 0]);
+
+        assert_eq!(decompress_u16(&compressed), Err(RansError::InvalidHeader));
+    }
+}
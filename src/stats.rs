@@ -0,0 +1,394 @@
+use std::ops::Range;
+
+use crate::{RansEncSymbol, RansEncoder, RansEncoderMulti, RansError};
+
+/// Statistics of the data put into a [`StatsEncoder`].
+///
+/// Comparing [`Self::bits_per_symbol()`] with [`Self::ideal_bits_per_symbol()`]
+/// shows the overhead of the coder itself (mostly the flushed states), while
+/// comparing [`Self::ideal_bits_per_symbol()`] with the
+/// [entropy](crate::model::Model::entropy) of the model shows how well the
+/// model fits the actual data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncoderStats {
+    channel_symbols: Vec<u64>,
+    ideal_bits: f64,
+    raw_bits: f64,
+    output_len: usize,
+}
+
+impl EncoderStats {
+    /// Returns the number of symbols put into given channel. Raw bits and
+    /// uniform values are not included.
+    ///
+    /// # Panics
+    /// Panics if `channel` is out of range.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::stats::StatsEncoder;
+    /// use rans::{RansEncSymbol, RansEncoderMulti};
+    ///
+    /// let mut encoder = StatsEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// encoder.put_at(1, &ByteRansEncSymbol::new(0, 1, 4));
+    /// assert_eq!(encoder.stats().num_symbols_at(0), 0);
+    /// assert_eq!(encoder.stats().num_symbols_at(1), 1);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn num_symbols_at(&self, channel: usize) -> u64 {
+        self.channel_symbols[channel]
+    }
+
+    /// Returns the total number of symbols put into all the channels. Raw
+    /// bits and uniform values are not included.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+    /// use rans::stats::StatsEncoder;
+    /// use rans::{RansEncSymbol, RansEncoderMulti};
+    ///
+    /// let mut encoder = StatsEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// let symbol = ByteRansEncSymbol::new(0, 1, 4);
+    /// encoder.put_all(&[&symbol, &symbol, &symbol]);
+    /// encoder.put_bits_at(0, 7, 3);
+    /// assert_eq!(encoder.stats().num_symbols(), 3);
+    /// ```
+    #[must_use]
+    pub fn num_symbols(&self) -> u64 {
+        self.channel_symbols.iter().sum()
+    }
+
+    /// Returns the length of the encoder's output in bytes, including the
+    /// flushed states.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::stats::StatsEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = StatsEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.stats().output_len(), 8);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn output_len(&self) -> usize {
+        self.output_len
+    }
+
+    /// Returns the information content of the symbols, in bits, according to
+    /// their frequencies, i.e. the sum of `scale_bits - log2(freq)` over all
+    /// the symbols. This is the size an ideal entropy coder using the same
+    /// model would produce for them.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::stats::StatsEncoder;
+    /// use rans::{RansEncSymbol, RansEncoder};
+    ///
+    /// let mut encoder = StatsEncoder::new(ByteRansEncoder::new(1024));
+    /// encoder.put(&ByteRansEncSymbol::new(0, 4, 4));
+    /// encoder.put(&ByteRansEncSymbol::new(4, 8, 4));
+    /// assert_eq!(encoder.stats().ideal_bits(), 3.0);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn ideal_bits(&self) -> f64 {
+        self.ideal_bits
+    }
+
+    /// Returns the information content of the raw bits and uniform values
+    /// put into the encoder, in bits.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::stats::StatsEncoder;
+    /// use rans::RansEncoder;
+    ///
+    /// let mut encoder = StatsEncoder::new(ByteRansEncoder::new(1024));
+    /// encoder.put_bits(0xabc, 12);
+    /// encoder.put_uniform(3, 4);
+    /// assert_eq!(encoder.stats().raw_bits(), 14.0);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn raw_bits(&self) -> f64 {
+        self.raw_bits
+    }
+
+    /// Returns the measured number of output bits per symbol, not counting
+    /// the raw bits and uniform values. This includes the overhead of the
+    /// flushed states, so it's only meaningful for a reasonable number of
+    /// symbols.
+    ///
+    /// Returns `NaN` if no symbols have been put into the encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::stats::StatsEncoder;
+    /// use rans::{RansEncSymbol, RansEncoder};
+    ///
+    /// let mut encoder = StatsEncoder::new(ByteRansEncoder::new(1024));
+    /// let symbol = ByteRansEncSymbol::new(0, 4, 4);
+    /// for _ in 0..400 {
+    ///     encoder.put(&symbol);
+    /// }
+    /// encoder.flush();
+    /// let stats = encoder.stats();
+    /// assert!((stats.bits_per_symbol() - stats.ideal_bits_per_symbol()).abs() < 0.1);
+    /// ```
+    #[must_use]
+    pub fn bits_per_symbol(&self) -> f64 {
+        ((self.output_len * 8) as f64 - self.raw_bits) / self.num_symbols() as f64
+    }
+
+    /// Returns [`Self::ideal_bits()`] per symbol.
+    ///
+    /// Returns `NaN` if no symbols have been put into the encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::stats::StatsEncoder;
+    /// use rans::{RansEncSymbol, RansEncoder};
+    ///
+    /// let mut encoder = StatsEncoder::new(ByteRansEncoder::new(1024));
+    /// encoder.put(&ByteRansEncSymbol::new(0, 4, 4));
+    /// encoder.put(&ByteRansEncSymbol::new(4, 8, 4));
+    /// assert_eq!(encoder.stats().ideal_bits_per_symbol(), 1.5);
+    /// ```
+    #[must_use]
+    pub fn ideal_bits_per_symbol(&self) -> f64 {
+        self.ideal_bits / self.num_symbols() as f64
+    }
+}
+
+/// Encoder wrapper collecting statistics of the encoded data.
+///
+/// The wrapper forwards everything to the wrapped encoder, counting the
+/// symbols put into each channel along with their information content, so
+/// that the efficiency of the model can be monitored on real data. Use
+/// [`Self::stats()`] to get the statistics.
+///
+/// # Examples
+/// ```
+/// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoderMulti};
+/// use rans::model::{EncModel, Model};
+/// use rans::stats::StatsEncoder;
+/// use rans::RansEncoderMulti;
+///
+/// let model = Model::from_counts(&[1, 2, 5], 12);
+/// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+///
+/// let mut encoder = StatsEncoder::new(ByteRansEncoderMulti::<2>::new(1 << 16));
+/// for i in 0..10000 {
+///     encoder.put_at(i % 2, enc_model.symbol([2, 2, 1, 2, 0, 2, 1, 2][i % 8]));
+/// }
+/// encoder.flush_all();
+///
+/// let stats = encoder.stats();
+/// assert_eq!(stats.num_symbols(), 10000);
+/// assert!((stats.ideal_bits_per_symbol() - model.entropy()).abs() < 0.01);
+/// assert!(stats.bits_per_symbol() < stats.ideal_bits_per_symbol() + 0.01);
+/// ```
+#[derive(Debug)]
+pub struct StatsEncoder<E, const N: usize> {
+    encoder: E,
+    channel_symbols: [u64; N],
+    ideal_bits: f64,
+    raw_bits: f64,
+}
+
+impl<E: RansEncoderMulti<N>, const N: usize> StatsEncoder<E, N> {
+    /// Wraps given encoder. The data it already contains is only taken into
+    /// account in the output length.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::stats::StatsEncoder;
+    ///
+    /// let encoder = StatsEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// assert_eq!(encoder.stats().num_symbols(), 0);
+    /// ```
+    #[must_use]
+    pub fn new(encoder: E) -> Self {
+        Self {
+            encoder,
+            channel_symbols: [0; N],
+            ideal_bits: 0.0,
+            raw_bits: 0.0,
+        }
+    }
+
+    /// Returns the statistics of the data put into the encoder since it was
+    /// created or last [reset](RansEncoderMulti::reset).
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::stats::StatsEncoder;
+    /// use rans::{RansEncSymbol, RansEncoder, RansEncoderMulti};
+    ///
+    /// let mut encoder = StatsEncoder::new(ByteRansEncoder::new(1024));
+    /// encoder.put(&ByteRansEncSymbol::new(0, 1, 4));
+    /// encoder.reset();
+    /// assert_eq!(encoder.stats().num_symbols(), 0);
+    /// ```
+    #[must_use]
+    pub fn stats(&self) -> EncoderStats {
+        EncoderStats {
+            channel_symbols: self.channel_symbols.to_vec(),
+            ideal_bits: self.ideal_bits,
+            raw_bits: self.raw_bits,
+            output_len: self.encoder.len(),
+        }
+    }
+
+    /// Returns a reference to the wrapped encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::stats::StatsEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let encoder = StatsEncoder::new(ByteRansEncoderMulti::<2>::new(1024));
+    /// assert_eq!(encoder.get_ref().capacity(), 1024);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn get_ref(&self) -> &E {
+        &self.encoder
+    }
+
+    /// Consumes this wrapper, returning the wrapped encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoder;
+    /// use rans::stats::StatsEncoder;
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let mut encoder = StatsEncoder::new(ByteRansEncoder::new(1024));
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.into_inner().data(), [0, 0, 128, 0]);
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> E {
+        self.encoder
+    }
+
+    fn record(&mut self, channel: usize, symbol: &E::Symbol) {
+        self.channel_symbols[channel] += 1;
+        self.ideal_bits += f64::from(symbol.scale_bits()) - f64::from(symbol.freq()).log2();
+    }
+}
+
+impl<E: RansEncoderMulti<N>, const N: usize> RansEncoderMulti<N> for StatsEncoder<E, N> {
+    type Symbol = E::Symbol;
+
+    fn reset(&mut self) {
+        self.encoder.reset();
+        self.channel_symbols = [0; N];
+        self.ideal_bits = 0.0;
+        self.raw_bits = 0.0;
+    }
+
+    fn clear(&mut self) {
+        self.encoder.clear();
+    }
+
+    #[inline]
+    fn put_at(&mut self, channel: usize, symbol: &Self::Symbol) {
+        self.encoder.put_at(channel, symbol);
+        self.record(channel, symbol);
+    }
+
+    fn try_put_at(&mut self, channel: usize, symbol: &Self::Symbol) -> Result<(), RansError> {
+        self.encoder.try_put_at(channel, symbol)?;
+        self.record(channel, symbol);
+        Ok(())
+    }
+
+    fn put_bits_at(&mut self, channel: usize, value: u32, num_bits: u32) {
+        self.encoder.put_bits_at(channel, value, num_bits);
+        self.raw_bits += f64::from(num_bits);
+    }
+
+    fn put_uniform_at(&mut self, channel: usize, value: u32, n: u32) {
+        self.encoder.put_uniform_at(channel, value, n);
+        self.raw_bits += f64::from(n).log2();
+    }
+
+    #[inline]
+    fn flush_at(&mut self, channel: usize) {
+        self.encoder.flush_at(channel);
+    }
+
+    fn try_flush_at(&mut self, channel: usize) -> Result<(), RansError> {
+        self.encoder.try_flush_at(channel)
+    }
+
+    fn flush_section(&mut self) -> Range<usize> {
+        self.encoder.flush_section()
+    }
+
+    #[inline]
+    fn data(&self) -> &[u8] {
+        self.encoder.data()
+    }
+
+    fn capacity(&self) -> usize {
+        self.encoder.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.encoder.reserve(additional);
+    }
+}
+
+impl<E: RansEncoderMulti<1>> RansEncoder for StatsEncoder<E, 1> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoderMulti};
+    use crate::model::{EncModel, Model};
+    use crate::stats::StatsEncoder;
+    use crate::RansEncoderMulti;
+
+    #[test]
+    fn test_stats() {
+        let model = Model::from_counts(&[10, 1, 50, 7, 3, 100], 20);
+        let enc_model = EncModel::<B64RansEncSymbol>::new(&model);
+        let counts = [100, 10, 500, 70, 30, 1000];
+        let symbols: Vec<usize> = (0..6)
+            .flat_map(|symbol| std::iter::repeat(symbol).take(counts[symbol]))
+            .collect();
+
+        let mut encoder = StatsEncoder::new(B64RansEncoderMulti::<3>::new(1 << 16));
+        for (i, &symbol) in symbols.iter().enumerate() {
+            encoder.put_at(i % 3, enc_model.symbol(symbol));
+        }
+        encoder.put_bits_at(0, 12345, 32);
+        encoder.flush_all();
+
+        let stats = encoder.stats();
+        assert_eq!(stats.num_symbols(), symbols.len() as u64);
+        assert_eq!(stats.num_symbols_at(2), symbols.len() as u64 / 3);
+        assert_eq!(stats.output_len(), encoder.len());
+        assert_eq!(stats.raw_bits(), 32.0);
+
+        let counts: Vec<u32> = counts.iter().map(|&count| count as u32).collect();
+        assert!((stats.ideal_bits() - model.estimate_size(&counts)).abs() < 1e-6);
+        assert!(stats.bits_per_symbol() >= stats.ideal_bits_per_symbol());
+        assert!(stats.bits_per_symbol() < stats.ideal_bits_per_symbol() + 0.2);
+    }
+}
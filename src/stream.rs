@@ -0,0 +1,1025 @@
+use std::io;
+use std::io::{Read, Write};
+
+use crate::frame::{FrameDecoder, FrameEncoder, RunLengthMode};
+use crate::model::Model;
+use crate::simple::{read_model, scale_counts, write_model, SCALE_BITS};
+use crate::varint;
+
+/// Default number of symbols in a single block.
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 16;
+
+/// Streaming rANS compressor writing to any [`Write`] sink.
+///
+/// Since rANS decodes the symbols in the reverse order of encoding, the
+/// symbols are buffered into blocks of fixed size, and each block is encoded
+/// (in reverse) as a separate [frame](crate::frame) when it's full. Every
+/// block is written to the sink as its length (LEB128-encoded) followed by
+/// the data, so that [`RansReader`] can read the
+/// stream back block by block.
+///
+/// A writer created with [`Self::new()`] compresses bytes and stores an
+/// order-0 model of each block inside of it; one created with
+/// [`Self::with_model()`] codes arbitrary symbols using a fixed model that
+/// the reader has to be given as well.
+///
+/// The model can also be changed mid-stream with [`Self::set_model()`], so
+/// that long streams with changing statistics can adapt at block granularity.
+/// The new model is written to the stream as an empty block (which is never
+/// written otherwise) followed by the length-prefixed
+/// [serialized model](Model::to_bytes), and [`RansReader`] switches to it
+/// when it reaches it.
+///
+/// The pending block is written when the writer is flushed, finished with
+/// [`Self::finish()`] or dropped; errors that happen when dropping are
+/// ignored, so calling [`Self::finish()`] is preferred.
+#[derive(Debug)]
+pub struct RansWriter<W: Write> {
+    inner: Option<W>,
+    model: Option<Model>,
+    block_size: usize,
+    run_length: RunLengthMode,
+    symbols: Vec<usize>,
+}
+
+impl<W: Write> RansWriter<W> {
+    /// Creates a new `RansWriter` instance compressing bytes written into it
+    /// with an order-0 model computed for each block and stored along with
+    /// it.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use rans::stream::RansWriter;
+    ///
+    /// let mut writer = RansWriter::new(Vec::new());
+    /// writer.write_all(b"hello, world").unwrap();
+    /// let compressed = writer.finish().unwrap();
+    /// assert!(!compressed.is_empty());
+    /// ```
+    #[must_use]
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner: Some(inner),
+            model: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+            run_length: RunLengthMode::Never,
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Creates a new `RansWriter` instance encoding symbols using given
+    /// fixed `model`, which is not stored in the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::stream::RansWriter;
+    ///
+    /// let model = Model::from_counts(&[3, 1, 1000], 12);
+    /// let mut writer = RansWriter::with_model(Vec::new(), model);
+    /// writer.write_symbols(&[0, 2, 2, 1]).unwrap();
+    /// let compressed = writer.finish().unwrap();
+    /// assert!(!compressed.is_empty());
+    /// ```
+    #[must_use]
+    pub fn with_model(inner: W, model: Model) -> Self {
+        let mut writer = Self::new(inner);
+        writer.model = Some(model);
+        writer
+    }
+
+    /// Sets the number of symbols in a single block. Larger blocks make the
+    /// per-block overhead smaller, at the cost of memory usage.
+    ///
+    /// # Panics
+    /// Panics if `block_size` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use rans::stream::RansWriter;
+    ///
+    /// let mut writer = RansWriter::new(Vec::new()).with_block_size(4);
+    /// writer.write_all(b"abcdefgh").unwrap();
+    /// // Both blocks have already been written
+    /// assert!(!writer.get_ref().is_empty());
+    /// ```
+    #[must_use]
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        assert!(block_size > 0, "block_size must not be zero");
+        self.block_size = block_size;
+        self
+    }
+
+    /// Sets whether runs of repeated symbols are coded as (symbol, run
+    /// length) pairs; see [`RunLengthMode`]. With [`RunLengthMode::Auto`],
+    /// the choice is made (and recorded) separately for every block.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::{Read, Write};
+    ///
+    /// use rans::frame::RunLengthMode;
+    /// use rans::stream::{RansReader, RansWriter};
+    ///
+    /// let data: Vec<u8> = (0..100_000).map(|i| (i / 1000 % 2) as u8).collect();
+    ///
+    /// let mut writer = RansWriter::new(Vec::new()).with_run_length(RunLengthMode::Auto);
+    /// writer.write_all(&data).unwrap();
+    /// let compressed = writer.finish().unwrap();
+    /// assert!(compressed.len() < 500);
+    ///
+    /// let mut decompressed = Vec::new();
+    /// RansReader::new(compressed.as_slice())
+    ///     .read_to_end(&mut decompressed)
+    ///     .unwrap();
+    /// assert_eq!(decompressed, data);
+    /// ```
+    #[must_use]
+    pub fn with_run_length(mut self, run_length: RunLengthMode) -> Self {
+        self.run_length = run_length;
+        self
+    }
+
+    /// Returns a reference to the underlying sink.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::stream::RansWriter;
+    ///
+    /// let writer = RansWriter::new(Vec::new());
+    /// assert!(writer.get_ref().is_empty());
+    /// ```
+    #[must_use]
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("writer already finished")
+    }
+
+    /// Returns a mutable reference to the underlying sink.
+    ///
+    /// Writing to the sink directly corrupts the stream, but this can be used
+    /// e.g. to take the data compressed so far out of an in-memory buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use rans::stream::RansWriter;
+    ///
+    /// let mut writer = RansWriter::new(Vec::new());
+    /// writer.write_all(b"hello").unwrap();
+    /// writer.flush().unwrap();
+    /// let compressed = std::mem::take(writer.get_mut());
+    /// assert!(!compressed.is_empty());
+    /// assert!(writer.finish().unwrap().is_empty());
+    /// ```
+    #[must_use]
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().expect("writer already finished")
+    }
+
+    /// Writes the pending block and switches to coding the following symbols
+    /// with `model`, storing it in the stream so that [`RansReader`] switches
+    /// to it as well. This works regardless of how the writer was created.
+    ///
+    /// # Errors
+    /// Returns any error returned by the underlying sink.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use rans::model::Model;
+    /// use rans::stream::{RansReader, RansWriter};
+    ///
+    /// let mut writer = RansWriter::new(Vec::new());
+    /// writer.write_all(b"text").unwrap();
+    /// let model = Model::from_counts(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1000, 1], 12);
+    /// writer.set_model(model.clone()).unwrap();
+    /// writer.write_symbols(&[12, 12, 13, 12]).unwrap();
+    /// let compressed = writer.finish().unwrap();
+    ///
+    /// let mut reader = RansReader::new(compressed.as_slice());
+    /// let symbols: Vec<usize> = reader.symbols().collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(symbols, [116, 101, 120, 116, 12, 12, 13, 12]);
+    /// assert_eq!(reader.model(), Some(&model));
+    /// ```
+    pub fn set_model(&mut self, model: Model) -> io::Result<()> {
+        self.write_block()?;
+
+        let mut record = Vec::new();
+        write_model_record(&model, &mut record);
+        let inner = self.inner.as_mut().expect("writer already finished");
+        inner.write_all(&record)?;
+        self.model = Some(model);
+        Ok(())
+    }
+
+    /// Adds a single symbol to the stream.
+    ///
+    /// # Errors
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] if the
+    /// symbol can't be coded with the writer's model (i.e. it's out of range
+    /// or has zero frequency, or is not a byte value if the model is
+    /// computed for each block), or any error returned by the underlying
+    /// sink.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::stream::RansWriter;
+    ///
+    /// let model = Model::from_counts(&[1, 0, 1], 8);
+    /// let mut writer = RansWriter::with_model(Vec::new(), model);
+    /// assert!(writer.write_symbol(2).is_ok());
+    /// assert!(writer.write_symbol(1).is_err());
+    /// ```
+    pub fn write_symbol(&mut self, symbol: usize) -> io::Result<()> {
+        check_symbol(self.model.as_ref(), symbol)?;
+
+        self.symbols.push(symbol);
+        if self.symbols.len() >= self.block_size {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    /// Adds all the `symbols` to the stream.
+    ///
+    /// # Errors
+    /// Same as in the case of [`Self::write_symbol()`]. The symbols preceding
+    /// the invalid one are added to the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::stream::RansWriter;
+    ///
+    /// let model = Model::from_counts(&[1, 1, 1], 8);
+    /// let mut writer = RansWriter::with_model(Vec::new(), model);
+    /// writer.write_symbols(&[0, 1, 2]).unwrap();
+    /// ```
+    pub fn write_symbols(&mut self, symbols: &[usize]) -> io::Result<()> {
+        for &symbol in symbols {
+            self.write_symbol(symbol)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending block, flushes the underlying sink and returns it.
+    ///
+    /// # Errors
+    /// Returns any error returned by the underlying sink.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use rans::stream::RansWriter;
+    ///
+    /// let mut writer = RansWriter::new(Vec::new());
+    /// writer.write_all(b"hello").unwrap();
+    /// let compressed: Vec<u8> = writer.finish().unwrap();
+    /// assert!(!compressed.is_empty());
+    /// ```
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.inner.take().expect("writer already finished"))
+    }
+
+    /// Encodes the buffered symbols as a single block and writes it.
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.symbols.is_empty() {
+            return Ok(());
+        }
+
+        let mut block = Vec::new();
+        encode_block(
+            self.model.as_ref(),
+            self.run_length,
+            &self.symbols,
+            &mut block,
+        );
+        let inner = self.inner.as_mut().expect("writer already finished");
+        inner.write_all(&block)?;
+        self.symbols.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for RansWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.write_symbol(byte as usize)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Writes the pending block, even if it's not full, and flushes the
+    /// underlying sink.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner
+            .as_mut()
+            .expect("writer already finished")
+            .flush()
+    }
+}
+
+impl<W: Write> Drop for RansWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.flush();
+        }
+    }
+}
+
+/// Streaming rANS decompressor reading from any [`Read`] source.
+///
+/// Reads the blocks written by [`RansWriter`] one at a time, so only a single
+/// decoded block is kept in memory. The decoded data can be read either
+/// through the [`Read`] implementation (if the symbols are bytes), or symbol
+/// by symbol with [`Self::read_symbol()`] or [`Self::symbols()`].
+///
+/// The model switches written with [`RansWriter::set_model()`] are handled
+/// transparently; the model currently in use can be checked with
+/// [`Self::model()`].
+///
+/// The block headers and checksums are validated, but a checksum only
+/// protects against accidental corruption, so this should not be used for
+/// data coming from untrusted sources.
+#[derive(Debug)]
+pub struct RansReader<R: Read> {
+    inner: R,
+    model: Option<Model>,
+    symbols: Vec<usize>,
+    pos: usize,
+}
+
+impl<R: Read> RansReader<R> {
+    /// Creates a new `RansReader` instance reading a stream written by a
+    /// writer created with [`RansWriter::new()`], i.e. with the models
+    /// stored in the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::{Read, Write};
+    ///
+    /// use rans::stream::{RansReader, RansWriter};
+    ///
+    /// let mut writer = RansWriter::new(Vec::new());
+    /// writer.write_all(b"hello, world").unwrap();
+    /// let compressed = writer.finish().unwrap();
+    ///
+    /// let mut reader = RansReader::new(compressed.as_slice());
+    /// let mut decompressed = Vec::new();
+    /// reader.read_to_end(&mut decompressed).unwrap();
+    /// assert_eq!(decompressed, b"hello, world");
+    /// ```
+    #[must_use]
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            model: None,
+            symbols: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Creates a new `RansReader` instance reading a stream written by a
+    /// writer created with [`RansWriter::with_model()`]. `model` has to be
+    /// the same as the one used by the writer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::stream::{RansReader, RansWriter};
+    ///
+    /// let model = Model::from_counts(&[3, 1, 1000], 12);
+    /// let mut writer = RansWriter::with_model(Vec::new(), model.clone());
+    /// writer.write_symbols(&[0, 2, 2, 1]).unwrap();
+    /// let compressed = writer.finish().unwrap();
+    ///
+    /// let mut reader = RansReader::with_model(compressed.as_slice(), model);
+    /// let symbols: Vec<usize> = reader.symbols().collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(symbols, [0, 2, 2, 1]);
+    /// ```
+    #[must_use]
+    pub fn with_model(inner: R, model: Model) -> Self {
+        Self {
+            model: Some(model),
+            ..Self::new(inner)
+        }
+    }
+
+    /// Returns a reference to the underlying source.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::stream::RansReader;
+    ///
+    /// let reader = RansReader::new([1, 2, 3].as_slice());
+    /// assert_eq!(reader.get_ref(), &[1, 2, 3]);
+    /// ```
+    #[must_use]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the fixed model the following blocks are decoded with, i.e.
+    /// the one given to [`Self::with_model()`] or the last one switched to by
+    /// the stream, or `None` if the blocks store their own models.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::stream::{RansReader, RansWriter};
+    ///
+    /// let model = Model::from_counts(&[1, 2, 3], 8);
+    /// let mut writer = RansWriter::new(Vec::new());
+    /// writer.set_model(model.clone()).unwrap();
+    /// writer.write_symbol(2).unwrap();
+    /// let compressed = writer.finish().unwrap();
+    ///
+    /// let mut reader = RansReader::new(compressed.as_slice());
+    /// assert_eq!(reader.model(), None);
+    /// assert_eq!(reader.read_symbol().unwrap(), Some(2));
+    /// assert_eq!(reader.model(), Some(&model));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn model(&self) -> Option<&Model> {
+        self.model.as_ref()
+    }
+
+    /// Unwraps this `RansReader`, returning the underlying source. Any
+    /// decoded data that hasn't been read yet is lost.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::stream::RansReader;
+    ///
+    /// let reader = RansReader::new([1, 2, 3].as_slice());
+    /// assert_eq!(reader.into_inner(), &[1, 2, 3]);
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads a single symbol. Returns `None` at the end of the stream.
+    ///
+    /// # Errors
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if a block is
+    /// malformed, [`io::ErrorKind::UnexpectedEof`] if the stream ends in the
+    /// middle of a block, or any error returned by the underlying source.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use rans::stream::{RansReader, RansWriter};
+    ///
+    /// let mut writer = RansWriter::new(Vec::new());
+    /// writer.write_all(b"a").unwrap();
+    /// let compressed = writer.finish().unwrap();
+    ///
+    /// let mut reader = RansReader::new(compressed.as_slice());
+    /// assert_eq!(reader.read_symbol().unwrap(), Some(b'a' as usize));
+    /// assert_eq!(reader.read_symbol().unwrap(), None);
+    /// ```
+    pub fn read_symbol(&mut self) -> io::Result<Option<usize>> {
+        if self.pos >= self.symbols.len() && !self.read_block()? {
+            return Ok(None);
+        }
+
+        let symbol = self.symbols[self.pos];
+        self.pos += 1;
+        Ok(Some(symbol))
+    }
+
+    /// Returns an iterator over the remaining symbols of the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use rans::stream::{RansReader, RansWriter};
+    ///
+    /// let mut writer = RansWriter::new(Vec::new());
+    /// writer.write_all(b"ab").unwrap();
+    /// let compressed = writer.finish().unwrap();
+    ///
+    /// let mut reader = RansReader::new(compressed.as_slice());
+    /// let mut symbols = reader.symbols();
+    /// assert_eq!(symbols.next().unwrap().unwrap(), b'a' as usize);
+    /// assert_eq!(symbols.next().unwrap().unwrap(), b'b' as usize);
+    /// assert!(symbols.next().is_none());
+    /// ```
+    pub fn symbols(&mut self) -> Symbols<'_, R> {
+        Symbols { reader: self }
+    }
+
+    /// Reads and decodes the next block, switching the models on the way.
+    /// Returns `false` at the end of the stream.
+    fn read_block(&mut self) -> io::Result<bool> {
+        let mut model_record = false;
+        loop {
+            let len = match read_block_len(&mut self.inner)? {
+                Some(len) => len,
+                None if model_record => return Err(io::ErrorKind::UnexpectedEof.into()),
+                None => return Ok(false),
+            };
+            if len == 0 && !model_record {
+                model_record = true;
+                continue;
+            }
+
+            // Don't trust the length for the allocation size
+            let mut block = Vec::new();
+            (&mut self.inner).take(len).read_to_end(&mut block)?;
+            if (block.len() as u64) < len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            if model_record {
+                self.model = Some(read_model_record(&block)?);
+                model_record = false;
+            } else {
+                self.symbols = decode_block(self.model.as_ref(), &block)?;
+                self.pos = 0;
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for RansReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            if self.pos >= self.symbols.len() && (len > 0 || !self.read_block()?) {
+                break;
+            }
+
+            let available = &self.symbols[self.pos..];
+            let count = available.len().min(buf.len() - len);
+            for (dst, &symbol) in buf[len..len + count].iter_mut().zip(available) {
+                *dst = symbol_to_byte(symbol)?;
+            }
+            self.pos += count;
+            len += count;
+        }
+        Ok(len)
+    }
+}
+
+/// Iterator over the symbols of a [`RansReader`], returned by
+/// [`RansReader::symbols()`].
+#[derive(Debug)]
+pub struct Symbols<'r, R: Read> {
+    reader: &'r mut RansReader<R>,
+}
+
+impl<R: Read> Iterator for Symbols<'_, R> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.read_symbol().transpose()
+    }
+}
+
+/// Checks that a symbol can be coded with given model, or is a byte if the
+/// model is computed for each block.
+pub(crate) fn check_symbol(model: Option<&Model>, symbol: usize) -> io::Result<()> {
+    let valid = match model {
+        Some(model) => symbol < model.len() && model.freq(symbol) > 0,
+        None => symbol < 256,
+    };
+    if !valid {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "symbol can't be coded with the model",
+        ));
+    }
+    Ok(())
+}
+
+/// Encodes a block of symbols with given model, or with an order-0 model
+/// stored in the block if it's `None`, and appends it to `output` prefixed
+/// with its length.
+pub(crate) fn encode_block(
+    model: Option<&Model>,
+    run_length: RunLengthMode,
+    symbols: &[usize],
+    output: &mut Vec<u8>,
+) {
+    let frame_encoder = FrameEncoder::new().with_run_length(run_length);
+    let mut block = Vec::new();
+    let frame = match model {
+        Some(model) => frame_encoder.encode(model, symbols),
+        None => {
+            let mut counts = [0_u64; 256];
+            for &symbol in symbols {
+                counts[symbol] += 1;
+            }
+            let model = Model::from_counts(&scale_counts(&counts), SCALE_BITS);
+            write_model(&mut block, &model);
+            frame_encoder.encode(&model, symbols)
+        }
+    };
+    block.extend_from_slice(&frame);
+
+    varint::write_u64(output, block.len() as u64);
+    output.extend_from_slice(&block);
+}
+
+/// Decodes the data of a block (without the length prefix) encoded by
+/// [`encode_block()`] with the same `model`.
+pub(crate) fn decode_block(model: Option<&Model>, block: &[u8]) -> io::Result<Vec<usize>> {
+    let mut pos = 0;
+    let embedded_model;
+    let model = match model {
+        Some(model) => model,
+        None => {
+            embedded_model = read_model(block, &mut pos).map_err(invalid_data)?;
+            &embedded_model
+        }
+    };
+    let frame = FrameDecoder::new(&block[pos..]).map_err(invalid_data)?;
+    if frame.frame_len() != block.len() - pos {
+        return Err(invalid_data("trailing data after the frame"));
+    }
+
+    frame.decode(model).map_err(invalid_data)
+}
+
+/// Appends a record switching to `model` to the stream: an empty block
+/// followed by the serialized model, prefixed with its length.
+pub(crate) fn write_model_record(model: &Model, output: &mut Vec<u8>) {
+    let data = model.to_bytes();
+    varint::write_u64(output, 0);
+    varint::write_u64(output, data.len() as u64);
+    output.extend_from_slice(&data);
+}
+
+/// Parses the serialized model of a record written by
+/// [`write_model_record()`] (without the length prefix).
+pub(crate) fn read_model_record(data: &[u8]) -> io::Result<Model> {
+    Model::from_bytes(data).map_err(invalid_data)
+}
+
+/// Converts a decoded symbol to a byte, for reading the symbols as bytes.
+pub(crate) fn symbol_to_byte(symbol: usize) -> io::Result<u8> {
+    u8::try_from(symbol).map_err(|_| invalid_data("symbol is not a byte"))
+}
+
+/// Reads the LEB128-encoded length of a block. Returns `None` if the source
+/// ends right at the block boundary.
+fn read_block_len<R: Read>(reader: &mut R) -> io::Result<Option<u64>> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        if reader.read(&mut byte)? == 0 {
+            return if shift == 0 {
+                Ok(None)
+            } else {
+                Err(io::ErrorKind::UnexpectedEof.into())
+            };
+        }
+
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+
+    Err(invalid_data("block length too long"))
+}
+
+pub(crate) fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind, Read, Write};
+
+    use crate::frame::{FrameDecoder, RunLengthMode};
+    use crate::model::Model;
+    use crate::simple::read_model;
+    use crate::stream::{RansReader, RansWriter};
+    use crate::varint;
+
+    /// Splits the stream into blocks, returning the data of each block.
+    fn blocks(data: &[u8]) -> Vec<&[u8]> {
+        let mut blocks = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let len = varint::read_u64(data, &mut pos).unwrap() as usize;
+            blocks.push(&data[pos..pos + len]);
+            pos += len;
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_write_bytes() {
+        let data: Vec<u8> = (0..10000_u32).map(|x| (x * x % 251) as u8).collect();
+        let mut writer = RansWriter::new(Vec::new()).with_block_size(4096);
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let blocks = blocks(&compressed);
+        assert_eq!(blocks.len(), 3);
+        let mut decoded = Vec::new();
+        for block in blocks {
+            let mut pos = 0;
+            let model = read_model(block, &mut pos).unwrap();
+            let frame = FrameDecoder::new(&block[pos..]).unwrap();
+            assert_eq!(frame.frame_len(), block.len() - pos);
+            decoded.extend(frame.decode(&model).unwrap().iter().map(|&x| x as u8));
+        }
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_write_symbols_with_model() {
+        let model = Model::from_counts(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10], 10);
+        let symbols: Vec<usize> = (0..1000).map(|x| x * 7 % 10).collect();
+        let mut writer = RansWriter::with_model(Vec::new(), model.clone()).with_block_size(300);
+        writer.write_symbols(&symbols).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let decoded: Vec<usize> = blocks(&compressed)
+            .iter()
+            .flat_map(|block| FrameDecoder::new(block).unwrap().decode(&model).unwrap())
+            .collect();
+        assert_eq!(decoded, symbols);
+    }
+
+    #[test]
+    fn test_flush_writes_partial_block() {
+        let mut writer = RansWriter::new(Vec::new());
+        writer.write_all(b"abc").unwrap();
+        assert!(writer.get_ref().is_empty());
+
+        writer.flush().unwrap();
+        assert_eq!(blocks(writer.get_ref()).len(), 1);
+        // Nothing new to write
+        writer.flush().unwrap();
+        assert_eq!(blocks(writer.get_ref()).len(), 1);
+    }
+
+    #[test]
+    fn test_drop_writes_pending_block() {
+        let mut compressed = Vec::new();
+        {
+            let mut writer = RansWriter::new(&mut compressed);
+            writer.write_all(b"abc").unwrap();
+        }
+
+        assert_eq!(blocks(&compressed).len(), 1);
+    }
+
+    #[test]
+    fn test_empty_stream() {
+        let writer = RansWriter::new(Vec::new());
+
+        assert!(writer.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_symbol() {
+        let mut writer = RansWriter::new(Vec::new());
+
+        assert_eq!(
+            writer.write_symbol(256).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn test_round_trip_bytes() {
+        let data: Vec<u8> = (0..20000_u32).map(|x| (x * x % 251) as u8).collect();
+        for block_size in [1, 1000, 65536] {
+            let mut writer = RansWriter::new(Vec::new()).with_block_size(block_size);
+            writer.write_all(&data).unwrap();
+            let compressed = writer.finish().unwrap();
+
+            let mut reader = RansReader::new(compressed.as_slice());
+            let mut decompressed = Vec::new();
+            reader.read_to_end(&mut decompressed).unwrap();
+            assert_eq!(decompressed, data);
+        }
+    }
+
+    #[test]
+    fn test_run_length_selected_per_block() {
+        // A block of long runs followed by a block of noise
+        let mut data: Vec<u8> = (0..4096).map(|x| (x / 512 % 2) as u8).collect();
+        data.extend((0..4096_u32).map(|x| (x * x % 251) as u8));
+        let mut writer = RansWriter::new(Vec::new())
+            .with_block_size(4096)
+            .with_run_length(RunLengthMode::Auto);
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let run_length: Vec<bool> = blocks(&compressed)
+            .iter()
+            .map(|block| {
+                let mut pos = 0;
+                read_model(block, &mut pos).unwrap();
+                FrameDecoder::new(&block[pos..])
+                    .unwrap()
+                    .header()
+                    .run_length()
+            })
+            .collect();
+        assert_eq!(run_length, [true, false]);
+
+        let mut reader = RansReader::new(compressed.as_slice());
+        let mut decompressed = Vec::new();
+        reader.read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_read_small_buffer() {
+        let data: Vec<u8> = (0..1000_u32).map(|x| (x % 7) as u8).collect();
+        let mut writer = RansWriter::new(Vec::new()).with_block_size(100);
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut reader = RansReader::new(compressed.as_slice());
+        let mut decompressed = Vec::new();
+        let mut buf = [0; 3];
+        loop {
+            let len = reader.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            decompressed.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_round_trip_symbols_with_model() {
+        let model = Model::from_counts(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 0, 300], 10);
+        let symbols: Vec<usize> = (0..1000)
+            .map(|x| if x % 3 == 0 { 11 } else { x * 7 % 10 })
+            .collect();
+        let mut writer = RansWriter::with_model(Vec::new(), model.clone()).with_block_size(128);
+        writer.write_symbols(&symbols).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut reader = RansReader::with_model(compressed.as_slice(), model);
+        let decoded: Vec<usize> = reader.symbols().collect::<Result<_, _>>().unwrap();
+        assert_eq!(decoded, symbols);
+    }
+
+    #[test]
+    fn test_read_non_byte_symbols() {
+        let model =
+            Model::from_counts(&[0; 300].iter().chain(&[1]).copied().collect::<Vec<_>>(), 4);
+        let mut writer = RansWriter::with_model(Vec::new(), model.clone());
+        writer.write_symbol(300).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut reader = RansReader::with_model(compressed.as_slice(), model);
+        let mut decompressed = Vec::new();
+        assert_eq!(
+            reader.read_to_end(&mut decompressed).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_model_switch() {
+        let data: Vec<u8> = (0..3000_u32).map(|x| (x * x % 251) as u8).collect();
+        let first = Model::from_counts(&[5, 0, 1], 8);
+        let second = Model::from_counts(&[1; 1000], 12);
+        let symbols: Vec<usize> = (0..2000).map(|x| x * 7 % 1000).collect();
+
+        let mut writer = RansWriter::new(Vec::new()).with_block_size(1024);
+        writer.write_all(&data).unwrap();
+        writer.set_model(first.clone()).unwrap();
+        // Switching again before writing any symbols is allowed
+        writer.set_model(first.clone()).unwrap();
+        writer.write_symbols(&[0, 2, 0]).unwrap();
+        assert_eq!(
+            writer.write_symbol(1).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        writer.set_model(second.clone()).unwrap();
+        writer.write_symbols(&symbols).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        // 3 byte blocks, 3 model records, 1 block with the first model and
+        // 2 blocks with the second one
+        assert_eq!(blocks(&compressed).len(), 3 + 3 * 2 + 1 + 2);
+
+        let mut reader = RansReader::new(compressed.as_slice());
+        let mut decoded = Vec::new();
+        for _ in 0..data.len() {
+            decoded.push(reader.read_symbol().unwrap().unwrap());
+        }
+        assert_eq!(reader.model(), None);
+        assert_eq!(
+            decoded,
+            data.iter().map(|&x| x as usize).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            (0..3)
+                .map(|_| reader.read_symbol().unwrap().unwrap())
+                .collect::<Vec<_>>(),
+            [0, 2, 0]
+        );
+        assert_eq!(reader.model(), Some(&first));
+        let decoded: Vec<usize> = reader.symbols().collect::<Result<_, _>>().unwrap();
+        assert_eq!(decoded, symbols);
+        assert_eq!(reader.model(), Some(&second));
+    }
+
+    #[test]
+    fn test_model_switch_at_end() {
+        let model = Model::from_counts(&[1, 1], 4);
+        let mut writer = RansWriter::new(Vec::new());
+        writer.write_all(b"abc").unwrap();
+        writer.set_model(model.clone()).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut reader = RansReader::new(compressed.as_slice());
+        let mut decompressed = Vec::new();
+        reader.read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, b"abc");
+        assert_eq!(reader.model(), Some(&model));
+    }
+
+    #[test]
+    fn test_read_invalid_model_record() {
+        let mut writer = RansWriter::new(Vec::new());
+        writer.set_model(Model::from_counts(&[1, 1], 4)).unwrap();
+        writer.write_symbol(1).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        // Truncated in the middle of the record
+        for len in 1..3 {
+            let mut reader = RansReader::new(&compressed[..len]);
+            assert_eq!(
+                reader.read_symbol().unwrap_err().kind(),
+                ErrorKind::UnexpectedEof
+            );
+        }
+
+        // Empty model
+        let mut reader = RansReader::new([0, 0].as_slice());
+        assert_eq!(
+            reader.read_symbol().unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+
+        // Model with a wrong scale
+        let mut corrupted = compressed.clone();
+        corrupted[2] += 1;
+        let mut reader = RansReader::new(corrupted.as_slice());
+        assert_eq!(
+            reader.read_symbol().unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_read_truncated() {
+        let mut writer = RansWriter::new(Vec::new());
+        writer.write_all(b"hello, world").unwrap();
+        let compressed = writer.finish().unwrap();
+
+        for len in 1..compressed.len() {
+            let mut reader = RansReader::new(&compressed[..len]);
+            let mut decompressed = Vec::new();
+            assert!(reader.read_to_end(&mut decompressed).is_err());
+        }
+    }
+
+    #[test]
+    fn test_read_corrupted() {
+        let mut writer = RansWriter::new(Vec::new());
+        writer.write_all(b"hello, world").unwrap();
+        let mut compressed = writer.finish().unwrap();
+        *compressed.last_mut().unwrap() ^= 1;
+
+        let mut reader = RansReader::new(compressed.as_slice());
+        assert_eq!(
+            reader.read_symbol().unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,539 @@
+use crate::decoder_input::DecoderInput;
+use crate::model::Model;
+use crate::RansError;
+
+/// Encoding and decoding tables of table-based ANS (tANS), built from a
+/// normalized frequency table.
+///
+/// The symbols are spread over `1 << scale_bits` states using the same
+/// algorithm as FSE, so that decoding a symbol takes a single table lookup
+/// and reading a few bits. Since the tables grow with the scale bits, this is
+/// mostly useful for small alphabets and scale bits of about 12 or less.
+#[derive(Debug, Clone)]
+pub struct TansTable {
+    scale_bits: u32,
+    /// Decoding table, indexed by the decoder state
+    dec_entries: Vec<TansDecEntry>,
+    /// Encoder states sorted by symbol, indexed by `cum_freq` + sub-state
+    enc_states: Vec<u32>,
+    /// Encoding transforms of all the symbols
+    enc_symbols: Vec<TansEncSymbol>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TansDecEntry {
+    symbol: u32,
+    num_bits: u32,
+    new_state: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TansEncSymbol {
+    /// `(max_bits << 16) - (freq << max_bits)`; adding the state to this and
+    /// shifting right by 16 gives the number of bits to output
+    delta_num_bits: u32,
+    /// `cum_freq - freq`, as a wrapping offset into `enc_states`
+    delta_find_state: u32,
+}
+
+impl TansTable {
+    /// Builds the tANS tables of given `model`.
+    ///
+    /// # Panics
+    /// Panics if the model's scale bits are larger than 16.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::TansTable;
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// assert_eq!(table.scale_bits(), 4);
+    /// assert_eq!(table.len(), 3);
+    /// ```
+    #[must_use]
+    pub fn new(model: &Model) -> Self {
+        let scale_bits = model.scale_bits();
+        assert!(scale_bits <= 16, "scale_bits must not be larger than 16");
+        let size = 1_u32 << scale_bits;
+        let mask = size - 1;
+
+        // Spread the symbols over the states; the step is odd, so every
+        // state gets visited exactly once
+        let step = ((size >> 1) + (size >> 3) + 3) | 1;
+        let mut spread = vec![0; size as usize];
+        let mut pos = 0;
+        for (symbol, &freq) in model.freqs().iter().enumerate() {
+            for _ in 0..freq {
+                spread[pos as usize] = symbol as u32;
+                pos = (pos + step) & mask;
+            }
+        }
+
+        let mut next_sub_state = model.freqs().to_vec();
+        let mut next_enc_index = model.cum_freqs().to_vec();
+        let mut dec_entries = Vec::with_capacity(size as usize);
+        let mut enc_states = vec![0; size as usize];
+        for (state, &symbol) in spread.iter().enumerate() {
+            let sub_state = next_sub_state[symbol as usize];
+            next_sub_state[symbol as usize] += 1;
+            let num_bits = scale_bits - high_bit(sub_state);
+            dec_entries.push(TansDecEntry {
+                symbol,
+                num_bits,
+                new_state: (sub_state << num_bits) - size,
+            });
+
+            let enc_index = &mut next_enc_index[symbol as usize];
+            enc_states[*enc_index as usize] = size + state as u32;
+            *enc_index += 1;
+        }
+
+        let enc_symbols = model
+            .freqs()
+            .iter()
+            .zip(model.cum_freqs())
+            .map(|(&freq, &cum_freq)| {
+                let max_bits = match freq {
+                    0 => {
+                        return TansEncSymbol {
+                            delta_num_bits: 0,
+                            delta_find_state: 0,
+                        };
+                    }
+                    1 => scale_bits,
+                    _ => scale_bits - high_bit(freq - 1),
+                };
+                TansEncSymbol {
+                    delta_num_bits: (max_bits << 16).wrapping_sub(freq << max_bits),
+                    delta_find_state: cum_freq.wrapping_sub(freq),
+                }
+            })
+            .collect();
+
+        Self {
+            scale_bits,
+            dec_entries,
+            enc_states,
+            enc_symbols,
+        }
+    }
+
+    /// Returns the number of bits used for frequency quantization, which is
+    /// also the number of bits of the coder state.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::TansTable;
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 1], 8));
+    /// assert_eq!(table.scale_bits(), 8);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
+
+    /// Returns the number of symbols in the alphabet, including the ones
+    /// with zero frequency.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::TansTable;
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 0, 1], 8));
+    /// assert_eq!(table.len(), 3);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.enc_symbols.len()
+    }
+
+    /// Returns `true` if the alphabet is empty. This is never the case, as
+    /// models of empty alphabets can't be created.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::TansTable;
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1], 8));
+    /// assert!(!table.is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.enc_symbols.is_empty()
+    }
+}
+
+/// Returns the index of the highest set bit of a non-zero `value`.
+#[inline]
+fn high_bit(value: u32) -> u32 {
+    31 - value.leading_zeros()
+}
+
+/// tANS encoder.
+///
+/// Just like the rANS encoders, it works in reverse: the decoder returns the
+/// symbols in the reverse order of encoding.
+#[derive(Debug)]
+pub struct TansEncoder<'t> {
+    table: &'t TansTable,
+    state: u32,
+    dst: Vec<u8>,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl<'t> TansEncoder<'t> {
+    /// Creates a new `TansEncoder` instance using given `table`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::{TansEncoder, TansTable};
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// let encoder = TansEncoder::new(&table);
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    #[must_use]
+    pub fn new(table: &'t TansTable) -> Self {
+        Self {
+            table,
+            state: 1 << table.scale_bits,
+            dst: Vec::new(),
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    /// Resets the encoder, discarding all the data encoded so far.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::{TansEncoder, TansTable};
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// let mut encoder = TansEncoder::new(&table);
+    /// encoder.put(2);
+    /// encoder.flush();
+    /// encoder.reset();
+    /// assert_eq!(encoder.data(), []);
+    /// ```
+    pub fn reset(&mut self) {
+        self.state = 1 << self.table.scale_bits;
+        self.dst.clear();
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+
+    /// Encodes a single symbol.
+    ///
+    /// # Panics
+    /// Panics if `symbol` is out of range of the alphabet. Encoding a symbol
+    /// with zero frequency produces data that can't be decoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::{TansEncoder, TansTable};
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// let mut encoder = TansEncoder::new(&table);
+    /// encoder.put(2);
+    /// ```
+    #[inline]
+    pub fn put(&mut self, symbol: usize) {
+        let enc_symbol = self.table.enc_symbols[symbol];
+        let num_bits = self.state.wrapping_add(enc_symbol.delta_num_bits) >> 16;
+        self.write_bits(self.state, num_bits);
+
+        let index = (self.state >> num_bits).wrapping_add(enc_symbol.delta_find_state);
+        self.state = self.table.enc_states[index as usize];
+    }
+
+    /// Flushes the state of the encoder, after which [`Self::data()`]
+    /// returns the complete encoded data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::{TansEncoder, TansTable};
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// let mut encoder = TansEncoder::new(&table);
+    /// encoder.flush();
+    /// assert_eq!(encoder.data(), [0b1_0000]);
+    /// ```
+    pub fn flush(&mut self) {
+        self.write_bits(self.state, self.table.scale_bits);
+        // End mark, so that the decoder can find the last bit
+        self.write_bits(1, 1);
+        while self.bit_count > 0 {
+            self.dst.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count = self.bit_count.saturating_sub(8);
+        }
+    }
+
+    /// Returns the encoded data. It's only complete after [`Self::flush()`]
+    /// is called.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::{TansEncoder, TansTable};
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// let mut encoder = TansEncoder::new(&table);
+    /// encoder.put(0);
+    /// encoder.flush();
+    /// assert!(!encoder.data().is_empty());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.dst
+    }
+
+    #[inline]
+    fn write_bits(&mut self, value: u32, num_bits: u32) {
+        let mask = (1_u64 << num_bits) - 1;
+        self.bit_buffer |= (u64::from(value) & mask) << self.bit_count;
+        self.bit_count += num_bits;
+        while self.bit_count >= 8 {
+            self.dst.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+}
+
+/// tANS decoder.
+///
+/// The data is read from the end towards the beginning.
+#[derive(Debug)]
+pub struct TansDecoder<'a, 't> {
+    table: &'t TansTable,
+    state: u32,
+    data: DecoderInput<'a>,
+    /// Number of bits that haven't been read yet
+    bits_left: usize,
+}
+
+impl<'a, 't> TansDecoder<'a, 't> {
+    /// Creates a new `TansDecoder` instance with given `data`, using given
+    /// `table`, which has to be built from the same model as the one used by
+    /// the encoder.
+    ///
+    /// # Panics
+    /// Panics if the data is not valid tANS encoded data. Use
+    /// [`Self::try_new()`] to handle this gracefully.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::{TansDecoder, TansTable};
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// let decoder = TansDecoder::new([0b1_0000], &table);
+    /// ```
+    #[must_use]
+    pub fn new<T: Into<DecoderInput<'a>>>(data: T, table: &'t TansTable) -> Self {
+        Self::try_new(data, table).expect("invalid tANS data")
+    }
+
+    /// Creates a new `TansDecoder` instance like [`Self::new()`], checking
+    /// that the data contains the end mark and the initial state.
+    ///
+    /// # Errors
+    /// Returns [`RansError::EmptyInput`] if `data` is empty,
+    /// [`RansError::InvalidHeader`] if the last byte doesn't contain the end
+    /// mark, or [`RansError::UnexpectedEof`] if the data is too short to
+    /// contain the initial state.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::{TansDecoder, TansTable};
+    /// use rans::RansError;
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// assert_eq!(
+    ///     TansDecoder::try_new([0], &table).unwrap_err(),
+    ///     RansError::InvalidHeader
+    /// );
+    /// assert_eq!(
+    ///     TansDecoder::try_new([0b1], &table).unwrap_err(),
+    ///     RansError::UnexpectedEof
+    /// );
+    /// ```
+    pub fn try_new<T: Into<DecoderInput<'a>>>(
+        data: T,
+        table: &'t TansTable,
+    ) -> Result<Self, RansError> {
+        let data = data.into();
+        let &last = data.last().ok_or(RansError::EmptyInput)?;
+        if last == 0 {
+            return Err(RansError::InvalidHeader);
+        }
+
+        let bits_left = data.len() * 8 - 8 + high_bit(u32::from(last)) as usize;
+        if bits_left < table.scale_bits as usize {
+            return Err(RansError::UnexpectedEof);
+        }
+
+        let mut decoder = Self {
+            table,
+            state: 0,
+            data,
+            bits_left,
+        };
+        decoder.state = decoder.read_bits(table.scale_bits);
+        Ok(decoder)
+    }
+
+    /// Decodes a single symbol.
+    ///
+    /// Decoding more symbols than were encoded returns garbage, but doesn't
+    /// panic.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::tans::{TansDecoder, TansEncoder, TansTable};
+    ///
+    /// let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+    /// let mut encoder = TansEncoder::new(&table);
+    /// encoder.put(0);
+    /// encoder.put(2);
+    /// encoder.flush();
+    ///
+    /// let mut decoder = TansDecoder::new(encoder.data(), &table);
+    /// assert_eq!(decoder.decode(), 2);
+    /// assert_eq!(decoder.decode(), 0);
+    /// ```
+    #[inline]
+    pub fn decode(&mut self) -> usize {
+        let entry = self.table.dec_entries[self.state as usize];
+        self.state = entry.new_state + self.read_bits(entry.num_bits);
+        entry.symbol as usize
+    }
+
+    /// Reads the last `num_bits` unread bits. Missing bits are read as
+    /// zeros.
+    #[inline]
+    fn read_bits(&mut self, num_bits: u32) -> u32 {
+        let (start, num_bits) = match self.bits_left.checked_sub(num_bits as usize) {
+            Some(start) => (start, num_bits),
+            None => (0, self.bits_left as u32),
+        };
+        self.bits_left = start;
+
+        let mut word = [0; 8];
+        let bytes = &self.data[start / 8..];
+        let len = bytes.len().min(8);
+        word[..len].copy_from_slice(&bytes[..len]);
+        let value = u64::from_le_bytes(word) >> (start % 8);
+        (value & ((1 << num_bits) - 1)) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::model::Model;
+    use crate::tans::{TansDecoder, TansEncoder, TansTable};
+
+    fn round_trip(model: &Model, symbols: &[usize]) -> Vec<u8> {
+        let table = TansTable::new(model);
+        let mut encoder = TansEncoder::new(&table);
+        for &symbol in symbols.iter().rev() {
+            encoder.put(symbol);
+        }
+        encoder.flush();
+
+        let mut decoder = TansDecoder::new(encoder.data(), &table);
+        for &symbol in symbols {
+            assert_eq!(decoder.decode(), symbol);
+        }
+        encoder.data().to_vec()
+    }
+
+    #[test]
+    fn test_table_visits_all_states() {
+        let model = Model::from_counts(&[3, 1, 7, 0, 5], 6);
+        let table = TansTable::new(&model);
+
+        let mut states = table.enc_states.clone();
+        states.sort_unstable();
+        assert_eq!(states, (64..128).collect::<Vec<_>>());
+        for (symbol, &freq) in model.freqs().iter().enumerate() {
+            let count = table
+                .dec_entries
+                .iter()
+                .filter(|entry| entry.symbol as usize == symbol)
+                .count();
+            assert_eq!(count, freq as usize);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_random() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let counts: Vec<u32> = (0..16).map(|i| 1 + i * i).collect();
+        let model = Model::from_counts(&counts, 11);
+        let symbols: Vec<usize> = (0..10000)
+            .map(|_| loop {
+                let symbol = rng.gen_range(0..16);
+                if rng.gen_range(0..256) < counts[symbol] {
+                    break symbol;
+                }
+            })
+            .collect();
+
+        let data = round_trip(&model, &symbols);
+        // Close to the entropy of the data (about 3.36 bits per symbol)
+        assert!(data.len() < 10000 * 35 / 80);
+    }
+
+    #[test]
+    fn test_round_trip_single_symbol() {
+        round_trip(&Model::from_counts(&[0, 1], 0), &[1; 100]);
+        round_trip(&Model::from_counts(&[1], 4), &[0; 100]);
+    }
+
+    #[test]
+    fn test_round_trip_all_scale_bits() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        for scale_bits in 1..=16 {
+            let model = Model::from_counts(&[1, 3], scale_bits);
+            let symbols: Vec<usize> = (0..1000).map(|_| rng.gen_range(0..2)).collect();
+
+            round_trip(&model, &symbols);
+        }
+    }
+
+    #[test]
+    fn test_decode_past_end() {
+        let table = TansTable::new(&Model::from_counts(&[1, 2, 5], 4));
+        let mut encoder = TansEncoder::new(&table);
+        encoder.put(1);
+        encoder.flush();
+
+        let mut decoder = TansDecoder::new(encoder.data(), &table);
+        assert_eq!(decoder.decode(), 1);
+        for _ in 0..10 {
+            decoder.decode();
+        }
+    }
+}
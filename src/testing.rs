@@ -0,0 +1,341 @@
+use std::fmt::Debug;
+
+use crate::codec::RansCodec;
+use crate::model::Model;
+use crate::RansError;
+
+/// Codec whose output can be checked with [`assert_round_trip()`].
+///
+/// Implement this for the encoding and decoding code of your integration to
+/// property-test it with the models and symbols generated by this module.
+///
+/// # Examples
+/// ```
+/// use rans::model::Model;
+/// use rans::simple::{compress, decompress};
+/// use rans::testing::{assert_round_trip, RoundTrip};
+/// use rans::RansError;
+///
+/// struct Simple;
+///
+/// impl RoundTrip for Simple {
+///     type Symbol = u8;
+///     type Error = RansError;
+///
+///     fn encode(&self, symbols: &[u8]) -> Vec<u8> {
+///         compress(symbols)
+///     }
+///
+///     fn decode(&self, data: &[u8], _len: usize) -> Result<Vec<u8>, RansError> {
+///         decompress(data)
+///     }
+/// }
+///
+/// assert_round_trip(&Simple, b"abracadabra");
+/// ```
+pub trait RoundTrip {
+    /// Type of the symbols being encoded.
+    type Symbol: Debug + PartialEq;
+    /// Type of the error returned by [`Self::decode()`].
+    type Error: Debug;
+
+    /// Encodes given symbols.
+    fn encode(&self, symbols: &[Self::Symbol]) -> Vec<u8>;
+
+    /// Decodes `len` symbols from data produced by [`Self::encode()`].
+    ///
+    /// # Errors
+    /// Returns an error if the data can't be decoded.
+    fn decode(&self, data: &[u8], len: usize) -> Result<Vec<Self::Symbol>, Self::Error>;
+}
+
+impl RoundTrip for RansCodec {
+    type Error = RansError;
+    type Symbol = u16;
+
+    fn encode(&self, symbols: &[u16]) -> Vec<u8> {
+        self.encode_symbols(symbols)
+    }
+
+    fn decode(&self, data: &[u8], len: usize) -> Result<Vec<u16>, RansError> {
+        self.decode_symbols(data, len)
+    }
+}
+
+/// Encodes `symbols` with given codec, decodes them back and checks that the
+/// result is the same as the input.
+///
+/// # Panics
+/// Panics if decoding fails or if the decoded symbols differ from the input,
+/// describing the first mismatch.
+///
+/// # Examples
+/// ```
+/// use rans::codec::RansCodec;
+/// use rans::testing::{assert_round_trip, random_model, random_symbols};
+///
+/// for seed in 0..10 {
+///     let model = random_model(seed, 300, 12);
+///     let symbols: Vec<u16> = random_symbols(seed, &model, 1000)
+///         .into_iter()
+///         .map(|symbol| symbol as u16)
+///         .collect();
+///     assert_round_trip(&RansCodec::new(&model), &symbols);
+/// }
+/// ```
+pub fn assert_round_trip<C: RoundTrip>(codec: &C, symbols: &[C::Symbol]) {
+    let encoded = codec.encode(symbols);
+    let decoded = match codec.decode(&encoded, symbols.len()) {
+        Ok(decoded) => decoded,
+        Err(error) => panic!(
+            "decoding {} symbols from {} bytes failed: {:?}",
+            symbols.len(),
+            encoded.len(),
+            error
+        ),
+    };
+
+    if let Some(index) = symbols
+        .iter()
+        .zip(&decoded)
+        .position(|(symbol, decoded)| symbol != decoded)
+    {
+        panic!(
+            "symbol {} decoded as {:?} instead of {:?}",
+            index, decoded[index], symbols[index]
+        );
+    }
+    assert_eq!(
+        decoded.len(),
+        symbols.len(),
+        "wrong number of decoded symbols"
+    );
+}
+
+/// Generates a random valid model with 1 to `max_symbols` symbols, with some
+/// of the symbols having zero frequency.
+///
+/// The result depends only on the arguments, so failing cases can be
+/// reproduced from the seed.
+///
+/// # Panics
+/// Panics if `max_symbols` is 0 or if `scale_bits` is larger than 31.
+///
+/// # Examples
+/// ```
+/// use rans::testing::random_model;
+///
+/// let model = random_model(42, 100, 12);
+/// assert!((1..=100).contains(&model.len()));
+/// assert_eq!(model.freqs().iter().sum::<u32>(), 1 << 12);
+/// assert_eq!(random_model(42, 100, 12), model);
+/// ```
+#[must_use]
+pub fn random_model(seed: u64, max_symbols: usize, scale_bits: u32) -> Model {
+    assert!(max_symbols > 0, "max_symbols must not be 0");
+    assert!(scale_bits <= 31, "scale_bits must not be larger than 31");
+
+    let mut rng = SplitMix64(seed);
+    let max_symbols = max_symbols.min(1 << scale_bits);
+    let len = 1 + rng.below(max_symbols as u64) as usize;
+    // Mix flat and very skewed distributions
+    let max_count = 1 << rng.below(24);
+    let mut counts: Vec<u32> = (0..len)
+        .map(|_| {
+            if rng.below(4) == 0 {
+                0
+            } else {
+                1 + rng.below(max_count) as u32
+            }
+        })
+        .collect();
+    if counts.iter().all(|&count| count == 0) {
+        counts[rng.below(len as u64) as usize] = 1;
+    }
+
+    Model::from_counts(&counts, scale_bits)
+}
+
+/// Generates `len` random symbols distributed according to given model. Only
+/// the symbols with non-zero frequency are generated, so all of them can be
+/// encoded with the model.
+///
+/// # Examples
+/// ```
+/// use rans::model::Model;
+/// use rans::testing::random_symbols;
+///
+/// let model = Model::from_counts(&[1, 0, 1000], 12);
+/// let symbols = random_symbols(7, &model, 1000);
+/// assert!(symbols.iter().all(|&symbol| symbol != 1));
+/// assert!(symbols.iter().filter(|&&symbol| symbol == 2).count() > 900);
+/// ```
+#[must_use]
+pub fn random_symbols(seed: u64, model: &Model, len: usize) -> Vec<usize> {
+    let mut rng = SplitMix64(seed);
+    (0..len)
+        .map(|_| model.symbol_for(rng.below(1 << model.scale_bits()) as u32))
+        .collect()
+}
+
+/// Returns models exercising the edge cases of the coders at given
+/// `scale_bits`: a single symbol taking the whole range, every slot of the
+/// range taken by a different symbol, one symbol taking all but one slot,
+/// and zero-frequency symbols at both ends of the alphabet.
+///
+/// The single-symbol model is left out at 16 bits, since the byte-aligned
+/// decoder symbols store frequencies as 16-bit integers and can't represent
+/// a frequency of 65536.
+///
+/// # Panics
+/// Panics if `scale_bits` is 0 or larger than 16.
+///
+/// # Examples
+/// ```
+/// use rans::codec::RansCodec;
+/// use rans::testing::{assert_round_trip, edge_case_models, random_symbols};
+///
+/// for model in edge_case_models(8) {
+///     let symbols: Vec<u16> = random_symbols(1, &model, 100)
+///         .into_iter()
+///         .map(|symbol| symbol as u16)
+///         .collect();
+///     assert_round_trip(&RansCodec::new(&model), &symbols);
+/// }
+/// ```
+#[must_use]
+pub fn edge_case_models(scale_bits: u32) -> Vec<Model> {
+    assert!(
+        (1..=16).contains(&scale_bits),
+        "scale_bits must be in the 1..=16 range"
+    );
+
+    let total = 1 << scale_bits;
+    let mut models = vec![
+        Model::from_freqs(vec![1; total as usize], scale_bits),
+        Model::from_freqs(vec![total - 1, 1], scale_bits),
+        Model::from_freqs(vec![1, total - 1], scale_bits),
+        Model::from_freqs(vec![0, total / 2, 0, total / 2, 0], scale_bits),
+    ];
+    if scale_bits < 16 {
+        models.push(Model::from_freqs(vec![total], scale_bits));
+    }
+
+    models
+}
+
+/// Small, fast pseudorandom number generator, so that the generated data
+/// doesn't depend on the version of any external crate.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in the `0..n` range.
+    fn below(&mut self, n: u64) -> u64 {
+        ((u128::from(self.next()) * u128::from(n)) >> 64) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::RansCodec;
+    use crate::simple::{compress_u16, decompress_u16};
+    use crate::testing::{
+        assert_round_trip, edge_case_models, random_model, random_symbols, RoundTrip,
+    };
+    use crate::RansError;
+
+    struct U16;
+
+    impl RoundTrip for U16 {
+        type Error = RansError;
+        type Symbol = u16;
+
+        fn encode(&self, symbols: &[u16]) -> Vec<u8> {
+            compress_u16(symbols)
+        }
+
+        fn decode(&self, data: &[u8], _len: usize) -> Result<Vec<u16>, RansError> {
+            decompress_u16(data)
+        }
+    }
+
+    /// Codec dropping the last symbol
+    struct Lossy;
+
+    impl RoundTrip for Lossy {
+        type Error = RansError;
+        type Symbol = u8;
+
+        fn encode(&self, symbols: &[u8]) -> Vec<u8> {
+            symbols[..symbols.len() - 1].to_vec()
+        }
+
+        fn decode(&self, data: &[u8], _len: usize) -> Result<Vec<u8>, RansError> {
+            Ok(data.to_vec())
+        }
+    }
+
+    fn to_u16(symbols: Vec<usize>) -> Vec<u16> {
+        symbols.into_iter().map(|symbol| symbol as u16).collect()
+    }
+
+    #[test]
+    fn test_random_models_valid() {
+        for seed in 0..200 {
+            let model = random_model(seed, 1000, 10);
+
+            assert!((1..=1000).contains(&model.len()));
+            assert_eq!(
+                model
+                    .freqs()
+                    .iter()
+                    .map(|&freq| u64::from(freq))
+                    .sum::<u64>(),
+                1 << 10
+            );
+        }
+    }
+
+    #[test]
+    fn test_random_model_capped_by_scale_bits() {
+        for seed in 0..50 {
+            assert!(random_model(seed, 1000, 4).len() <= 16);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_random() {
+        for seed in 0..50 {
+            let model = random_model(seed, 5000, 16);
+            let symbols = to_u16(random_symbols(seed, &model, 2000));
+
+            assert_round_trip(&RansCodec::new(&model), &symbols);
+            assert_round_trip(&U16, &symbols);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_edge_cases() {
+        for scale_bits in 1..=16 {
+            for model in edge_case_models(scale_bits) {
+                let symbols = to_u16(random_symbols(u64::from(scale_bits), &model, 500));
+
+                assert_round_trip(&RansCodec::new(&model), &symbols);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong number of decoded symbols")]
+    fn test_assert_round_trip_detects_mismatch() {
+        assert_round_trip(&Lossy, b"abc");
+    }
+}
@@ -0,0 +1,388 @@
+use crate::RansError;
+
+/// Reversible byte transform applied to the data before entropy coding.
+///
+/// Transforms don't compress the data themselves, but they make its
+/// statistics more skewed, which order-0 models can exploit. Several
+/// transforms can be chained with [`apply_all()`] and reverted with
+/// [`reverse_all()`]; [`FrameEncoder`](crate::frame::FrameEncoder) can also
+/// record them in the frame header.
+///
+/// # Examples
+/// ```
+/// use rans::transform::Transform;
+///
+/// let data = [10, 11, 12, 13, 13, 13];
+/// let transformed = Transform::Delta.apply(&data);
+/// assert_eq!(transformed, [10, 1, 1, 1, 0, 0]);
+/// assert_eq!(Transform::Delta.reverse(&transformed).unwrap(), data);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Transform {
+    /// Replaces every byte with its (wrapping) difference from the previous
+    /// one. Useful for slowly changing signals, such as samples or pixels.
+    Delta,
+    /// Maps bytes interpreted as signed values to unsigned ones, so that
+    /// small negative values become small positive ones (0, -1, 1, -2, ...
+    /// become 0, 1, 2, 3, ...). Usually applied after [`Transform::Delta`].
+    Zigzag,
+    /// Replaces every byte with its position in a list of recently used
+    /// bytes, then moves it to the front of the list. Useful for data with
+    /// strong local correlation, such as the output of the Burrows-Wheeler
+    /// transform.
+    MoveToFront,
+    /// Replaces runs of the same byte with two copies of the byte followed
+    /// by the number of the remaining repetitions (up to 255).
+    RunLength,
+}
+
+impl Transform {
+    /// Applies the transform to given data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::transform::Transform;
+    ///
+    /// assert_eq!(Transform::Zigzag.apply(&[0, 255, 1, 254]), [0, 1, 2, 3]);
+    /// assert_eq!(Transform::MoveToFront.apply(b"bbaa"), [98, 0, 98, 0]);
+    /// assert_eq!(Transform::RunLength.apply(b"aaaaab"), b"aa\x03b");
+    /// ```
+    #[must_use]
+    pub fn apply(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Transform::Delta => {
+                let mut prev = 0;
+                data.iter()
+                    .map(|&byte| {
+                        let delta = byte.wrapping_sub(prev);
+                        prev = byte;
+                        delta
+                    })
+                    .collect()
+            }
+            Transform::Zigzag => data
+                .iter()
+                .map(|&byte| zigzag_encode(i32::from(byte as i8)) as u8)
+                .collect(),
+            Transform::MoveToFront => {
+                let mut list = mtf_list();
+                data.iter()
+                    .map(|&byte| {
+                        let index = list
+                            .iter()
+                            .position(|&b| b == byte)
+                            .expect("the list contains all the bytes");
+                        list.copy_within(..index, 1);
+                        list[0] = byte;
+                        index as u8
+                    })
+                    .collect()
+            }
+            Transform::RunLength => rle_encode(data),
+        }
+    }
+
+    /// Reverses the transform, returning the original data.
+    ///
+    /// # Errors
+    /// Returns [`RansError::UnexpectedEof`] if the data is a truncated
+    /// output of [`Transform::RunLength`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::transform::Transform;
+    /// use rans::RansError;
+    ///
+    /// assert_eq!(Transform::RunLength.reverse(b"aa\x03b").unwrap(), b"aaaaab");
+    /// assert_eq!(Transform::RunLength.reverse(b"aa"), Err(RansError::UnexpectedEof));
+    /// ```
+    pub fn reverse(&self, data: &[u8]) -> Result<Vec<u8>, RansError> {
+        Ok(match self {
+            Transform::Delta => {
+                let mut prev = 0_u8;
+                data.iter()
+                    .map(|&delta| {
+                        prev = prev.wrapping_add(delta);
+                        prev
+                    })
+                    .collect()
+            }
+            Transform::Zigzag => data
+                .iter()
+                .map(|&byte| zigzag_decode(u32::from(byte)) as u8)
+                .collect(),
+            Transform::MoveToFront => {
+                let mut list = mtf_list();
+                data.iter()
+                    .map(|&index| {
+                        let index = usize::from(index);
+                        let byte = list[index];
+                        list.copy_within(..index, 1);
+                        list[0] = byte;
+                        byte
+                    })
+                    .collect()
+            }
+            Transform::RunLength => rle_decode(data)?,
+        })
+    }
+
+    /// Returns the identifier of the transform stored in frame headers.
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Transform::Delta => 0,
+            Transform::Zigzag => 1,
+            Transform::MoveToFront => 2,
+            Transform::RunLength => 3,
+        }
+    }
+
+    /// Returns the transform with given identifier, as returned by
+    /// [`Self::id()`].
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Transform::Delta),
+            1 => Some(Transform::Zigzag),
+            2 => Some(Transform::MoveToFront),
+            3 => Some(Transform::RunLength),
+            _ => None,
+        }
+    }
+}
+
+/// Applies given transforms to the data, in order.
+///
+/// # Examples
+/// ```
+/// use rans::transform::{apply_all, reverse_all, Transform};
+///
+/// let transforms = [Transform::Delta, Transform::Zigzag];
+/// let data = [100, 98, 96, 97, 99];
+/// let transformed = apply_all(&transforms, &data);
+/// assert_eq!(transformed, [200, 3, 3, 2, 4]);
+/// assert_eq!(reverse_all(&transforms, &transformed).unwrap(), data);
+/// ```
+#[must_use]
+pub fn apply_all(transforms: &[Transform], data: &[u8]) -> Vec<u8> {
+    transforms
+        .iter()
+        .fold(data.to_vec(), |data, transform| transform.apply(&data))
+}
+
+/// Reverses given transforms, in reverse order, undoing [`apply_all()`].
+///
+/// # Errors
+/// Returns an error if reversing any of the transforms fails; see
+/// [`Transform::reverse()`].
+///
+/// # Examples
+/// ```
+/// use rans::transform::{apply_all, reverse_all, Transform};
+///
+/// let transforms = [Transform::MoveToFront, Transform::RunLength];
+/// let data = b"abababbbbbbbbbba";
+/// assert_eq!(reverse_all(&transforms, &apply_all(&transforms, data)).unwrap(), data);
+/// ```
+pub fn reverse_all(transforms: &[Transform], data: &[u8]) -> Result<Vec<u8>, RansError> {
+    transforms
+        .iter()
+        .rev()
+        .try_fold(data.to_vec(), |data, transform| transform.reverse(&data))
+}
+
+/// Maps a signed integer to an unsigned one, interleaving the negative and
+/// positive values: 0, -1, 1, -2, 2, ... become 0, 1, 2, 3, 4, ...
+///
+/// # Examples
+/// ```
+/// use rans::transform::zigzag_encode;
+///
+/// assert_eq!(zigzag_encode(0), 0);
+/// assert_eq!(zigzag_encode(-1), 1);
+/// assert_eq!(zigzag_encode(1), 2);
+/// assert_eq!(zigzag_encode(i32::MIN), u32::MAX);
+/// ```
+#[must_use]
+#[inline]
+pub fn zigzag_encode(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+/// Reverses [`zigzag_encode()`].
+///
+/// # Examples
+/// ```
+/// use rans::transform::zigzag_decode;
+///
+/// assert_eq!(zigzag_decode(3), -2);
+/// assert_eq!(zigzag_decode(u32::MAX), i32::MIN);
+/// ```
+#[must_use]
+#[inline]
+pub fn zigzag_decode(value: u32) -> i32 {
+    (value >> 1) as i32 ^ -((value & 1) as i32)
+}
+
+/// Replaces every integer with its (wrapping) difference from the previous
+/// one and zigzag encodes the result, so that the output can be coded with
+/// [`uint_coder`](crate::uint_coder).
+///
+/// # Examples
+/// ```
+/// use rans::transform::{delta_decode_ints, delta_encode_ints};
+///
+/// let values = [1000, 1002, 1001, 1001, -5];
+/// let encoded = delta_encode_ints(&values);
+/// assert_eq!(encoded, [2000, 4, 1, 0, 2011]);
+/// assert_eq!(delta_decode_ints(&encoded), values);
+/// ```
+#[must_use]
+pub fn delta_encode_ints(values: &[i32]) -> Vec<u32> {
+    let mut prev = 0_i32;
+    values
+        .iter()
+        .map(|&value| {
+            let delta = value.wrapping_sub(prev);
+            prev = value;
+            zigzag_encode(delta)
+        })
+        .collect()
+}
+
+/// Reverses [`delta_encode_ints()`].
+///
+/// # Examples
+/// ```
+/// use rans::transform::delta_decode_ints;
+///
+/// assert_eq!(delta_decode_ints(&[20, 1, 1]), [10, 9, 8]);
+/// ```
+#[must_use]
+pub fn delta_decode_ints(values: &[u32]) -> Vec<i32> {
+    let mut prev = 0_i32;
+    values
+        .iter()
+        .map(|&value| {
+            prev = prev.wrapping_add(zigzag_decode(value));
+            prev
+        })
+        .collect()
+}
+
+/// Returns the initial list of the move-to-front transform.
+fn mtf_list() -> [u8; 256] {
+    let mut list = [0; 256];
+    for (i, byte) in list.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    list
+}
+
+fn rle_encode(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut pos = 0;
+    while pos < data.len() {
+        let byte = data[pos];
+        let run = data[pos..]
+            .iter()
+            .take(2 + usize::from(u8::MAX))
+            .take_while(|&&b| b == byte)
+            .count();
+
+        output.push(byte);
+        if run >= 2 {
+            output.push(byte);
+            output.push((run - 2) as u8);
+        }
+        pos += run;
+    }
+    output
+}
+
+fn rle_decode(data: &[u8]) -> Result<Vec<u8>, RansError> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut pos = 0;
+    while pos < data.len() {
+        let byte = data[pos];
+        if data.get(pos + 1) == Some(&byte) {
+            let extra = *data.get(pos + 2).ok_or(RansError::UnexpectedEof)?;
+            output.resize(output.len() + 2 + usize::from(extra), byte);
+            pos += 3;
+        } else {
+            output.push(byte);
+            pos += 1;
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::transform::{
+        apply_all, delta_decode_ints, delta_encode_ints, reverse_all, zigzag_decode, zigzag_encode,
+        Transform,
+    };
+
+    const TRANSFORMS: [Transform; 4] = [
+        Transform::Delta,
+        Transform::Zigzag,
+        Transform::MoveToFront,
+        Transform::RunLength,
+    ];
+
+    fn test_data() -> Vec<u8> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut data: Vec<u8> = (0..=255).collect();
+        data.extend((0..1000).map(|_| rng.gen::<u8>()));
+        for len in [1, 2, 3, 256, 257, 258, 259, 1000] {
+            data.extend(std::iter::repeat(b'x').take(len));
+            data.push(b'y');
+        }
+        data.extend((0..1000).map(|i| (i / 7) as u8));
+        data
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = test_data();
+        for transform in TRANSFORMS {
+            assert_eq!(transform.reverse(&transform.apply(&data)).unwrap(), data);
+            assert_eq!(Transform::from_id(transform.id()), Some(transform));
+            assert!(transform.apply(&[]).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_chains() {
+        let data = test_data();
+        for first in TRANSFORMS {
+            for second in TRANSFORMS {
+                let transforms = [first, second, Transform::RunLength];
+                let transformed = apply_all(&transforms, &data);
+                assert_eq!(reverse_all(&transforms, &transformed).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_length() {
+        let data = vec![7; 1000];
+        let transformed = Transform::RunLength.apply(&data);
+        assert_eq!(transformed.len(), 12);
+        assert_eq!(Transform::RunLength.reverse(&transformed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_ints() {
+        for value in [0, 1, -1, 1000, -1000, i32::MAX, i32::MIN] {
+            assert_eq!(zigzag_decode(zigzag_encode(value)), value);
+        }
+
+        let values = [i32::MIN, i32::MAX, 0, -1, 5, i32::MIN];
+        assert_eq!(delta_decode_ints(&delta_encode_ints(&values)), values);
+    }
+}
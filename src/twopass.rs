@@ -0,0 +1,218 @@
+use crate::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+use crate::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+use crate::model::{DecModel, EncModel, Model};
+use crate::simple::{decode_symbols_checked, histogram, read_model_with_scale, write_model};
+use crate::{varint, RansEncoder, RansEncoderMulti, RansError};
+
+/// Largest number of scale bits supported by the byte-aligned coder.
+const MAX_SCALE_BITS: u32 = 16;
+
+/// Compresses a byte buffer with an order-0 model built from the buffer
+/// itself, returning the compressed data along with the model.
+///
+/// The first pass counts the bytes and builds a model with `scale_bits` bits
+/// of frequency quantization; the second one encodes the data. The output
+/// contains the original length, the serialized model and the rANS
+/// bitstream. Use [`decode()`] to get the original data back. The returned
+/// model can be used, e.g., to estimate the cost of similar data without
+/// parsing the output. For empty input, the returned model contains a single
+/// symbol.
+///
+/// Use [`TwoPassEncoder`] to reuse the internal buffers when encoding many
+/// buffers.
+///
+/// # Panics
+/// Panics if `scale_bits` is 0 or larger than 16, or if there are more
+/// distinct bytes in `data` than `1 << scale_bits`.
+///
+/// # Examples
+/// ```
+/// use rans::twopass::{decode, encode};
+///
+/// let (compressed, model) = encode(b"abracadabra", 10);
+/// assert_eq!(model.freq(usize::from(b'a')), 466);
+/// assert_eq!(decode(&compressed).unwrap(), b"abracadabra");
+/// ```
+#[must_use]
+pub fn encode(data: &[u8], scale_bits: u32) -> (Vec<u8>, Model) {
+    TwoPassEncoder::new().encode(data, scale_bits)
+}
+
+/// Decompresses data produced by [`encode()`] or [`TwoPassEncoder`].
+///
+/// Malformed data results in an error, but the output length is only limited
+/// by the header.
+///
+/// # Errors
+/// Returns [`RansError::UnexpectedEof`] if the input is truncated,
+/// [`RansError::InvalidHeader`]/[`RansError::InvalidModel`] if the header is
+/// malformed, or [`RansError::InputExhausted`] if the bitstream ends before
+/// all the bytes are decoded.
+///
+/// # Examples
+/// ```
+/// use rans::twopass::{decode, encode};
+/// use rans::RansError;
+///
+/// let (compressed, _) = encode(b"hello", 12);
+/// assert_eq!(decode(&compressed).unwrap(), b"hello");
+/// assert_eq!(decode(&compressed[..3]), Err(RansError::UnexpectedEof));
+/// ```
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, RansError> {
+    let mut pos = 0;
+    let len =
+        usize::try_from(varint::read_u64(data, &mut pos)?).map_err(|_| RansError::InvalidHeader)?;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    let model = read_model_with_scale(data, &mut pos, 1..=MAX_SCALE_BITS)?;
+    let payload = &data[pos..];
+    if payload.len() < 4 {
+        return Err(RansError::UnexpectedEof);
+    }
+
+    let dec_model = DecModel::<ByteRansDecSymbol>::new(&model);
+    decode_symbols_checked(&mut ByteRansDecoder::new(payload), &dec_model, len)
+}
+
+/// Two-pass order-0 compressor keeping its scratch buffer between calls.
+///
+/// The output is the same as the one of [`encode()`].
+///
+/// # Examples
+/// ```
+/// use rans::twopass::{decode, TwoPassEncoder};
+///
+/// let mut encoder = TwoPassEncoder::new();
+/// for message in [&b"first message"[..], b"second message"] {
+///     let (compressed, _) = encoder.encode(message, 12);
+///     assert_eq!(decode(&compressed).unwrap(), message);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct TwoPassEncoder {
+    buffer: Vec<u8>,
+}
+
+impl TwoPassEncoder {
+    /// Creates a new `TwoPassEncoder` instance with an empty scratch buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::twopass::TwoPassEncoder;
+    ///
+    /// let mut encoder = TwoPassEncoder::new();
+    /// let (compressed, _) = encoder.encode(b"", 12);
+    /// assert_eq!(compressed, [0]);
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compresses a byte buffer, returning the compressed data along with the
+    /// model used. See [`encode()`].
+    ///
+    /// # Panics
+    /// Panics if `scale_bits` is 0 or larger than 16, or if there are more
+    /// distinct bytes in `data` than `1 << scale_bits`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::twopass::{decode, TwoPassEncoder};
+    ///
+    /// let mut encoder = TwoPassEncoder::new();
+    /// let (compressed, model) = encoder.encode(b"aaaaaaab", 3);
+    /// assert_eq!(model.freqs()[usize::from(b'a')..=usize::from(b'b')], [7, 1]);
+    /// assert_eq!(decode(&compressed).unwrap(), b"aaaaaaab");
+    /// ```
+    pub fn encode(&mut self, data: &[u8], scale_bits: u32) -> (Vec<u8>, Model) {
+        assert!(
+            (1..=MAX_SCALE_BITS).contains(&scale_bits),
+            "scale_bits must be between 1 and 16"
+        );
+
+        let mut output = Vec::new();
+        varint::write_u64(&mut output, data.len() as u64);
+        if data.is_empty() {
+            return (output, Model::from_counts(&[1], scale_bits));
+        }
+
+        let model = Model::from_counts(&histogram(data), scale_bits);
+        write_model(&mut output, &model);
+
+        // With at most 16-bit frequencies, a single symbol emits at most
+        // 2 bytes.
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        buffer.reserve(data.len() * 2 + 4);
+        let mut encoder = ByteRansEncoder::with_buffer(buffer);
+        let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+        for &byte in data.iter().rev() {
+            encoder.put_index(byte as usize, &enc_model);
+        }
+        encoder.flush();
+
+        output.extend_from_slice(encoder.data());
+        self.buffer = encoder.into_buffer();
+        (output, model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::twopass::{decode, encode, TwoPassEncoder};
+    use crate::{varint, RansError};
+
+    #[test]
+    fn test_round_trip_scale_bits() {
+        let data: Vec<u8> = (0..10000_u32).map(|x| (x * x % 251) as u8).collect();
+
+        for scale_bits in 8..=16 {
+            let (compressed, model) = encode(&data, scale_bits);
+
+            assert_eq!(model.scale_bits(), scale_bits);
+            assert_eq!(decode(&compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_reuse_buffer() {
+        let mut encoder = TwoPassEncoder::new();
+        let long: Vec<u8> = (0..5000_u32).map(|x| (x % 7) as u8).collect();
+
+        let (first, _) = encoder.encode(&long, 12);
+        let (second, _) = encoder.encode(b"short", 12);
+        let (third, _) = encoder.encode(&long, 12);
+
+        assert_eq!(first, third);
+        assert_eq!(decode(&second).unwrap(), b"short");
+        assert_eq!(decode(&third).unwrap(), long);
+    }
+
+    #[test]
+    fn test_invalid_scale_bits() {
+        let (mut compressed, _) = encode(b"abc", 12);
+        // scale_bits of the model
+        compressed[1] = 17;
+
+        assert_eq!(decode(&compressed), Err(RansError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_declared_len_too_large() {
+        let (compressed, _) = encode(b"abc", 12);
+        let mut corrupted = Vec::new();
+        varint::write_u64(&mut corrupted, (1 << 46) - 1);
+        corrupted.extend_from_slice(&compressed[1..]);
+
+        assert_eq!(decode(&corrupted), Err(RansError::InputExhausted));
+    }
+
+    #[test]
+    #[should_panic(expected = "too many distinct symbols")]
+    fn test_too_many_symbols() {
+        let _ = encode(b"abcde", 2);
+    }
+}
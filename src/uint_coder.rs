@@ -0,0 +1,522 @@
+use crate::model::{DecModel, EncModel, Model};
+use crate::{RansDecSymbol, RansDecoderMulti, RansEncSymbol, RansEncoderMulti, RansError};
+
+/// Scheme mapping integers to tokens coded with a model, followed by raw
+/// residual bits, as used by JPEG XL's hybrid integer coding (and, with
+/// different parameters, Deflate's length and distance codes).
+///
+/// Values smaller than `1 << split_exponent` are coded directly as tokens.
+/// Larger values are split into their position of the most significant bit,
+/// `msb_in_token` bits following it and the `lsb_in_token` least significant
+/// bits, all of which are stored in the token, and the remaining bits in
+/// between, which are stored as raw bits. This keeps the alphabet small while
+/// still capturing the magnitude (and optionally the lowest bits) of the
+/// values with the model.
+///
+/// # Examples
+/// ```
+/// use rans::uint_coder::TokenScheme;
+///
+/// let scheme = TokenScheme::new(4, 2, 0);
+/// assert_eq!(scheme.tokenize(7), (7, 0, 0));
+/// assert_eq!(scheme.tokenize(100), (26, 4, 4));
+/// assert_eq!(scheme.detokenize(26, 4), 100);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenScheme {
+    split_exponent: u32,
+    msb_in_token: u32,
+    lsb_in_token: u32,
+}
+
+impl TokenScheme {
+    /// Scheme coding every value as its bit length followed by the bits
+    /// after the most significant one, like Exp-Golomb codes.
+    pub const EXP_GOLOMB: Self = Self::new(0, 0, 0);
+
+    /// Creates a new token scheme.
+    ///
+    /// # Panics
+    /// Panics if `split_exponent` is larger than 31, or if `msb_in_token +
+    /// lsb_in_token` is larger than `split_exponent`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::uint_coder::TokenScheme;
+    ///
+    /// let scheme = TokenScheme::new(4, 1, 1);
+    /// assert_eq!(scheme.split_exponent(), 4);
+    /// ```
+    #[must_use]
+    pub const fn new(split_exponent: u32, msb_in_token: u32, lsb_in_token: u32) -> Self {
+        assert!(
+            split_exponent <= 31,
+            "split_exponent must not be larger than 31"
+        );
+        assert!(
+            msb_in_token + lsb_in_token <= split_exponent,
+            "msb_in_token + lsb_in_token must not be larger than split_exponent"
+        );
+
+        Self {
+            split_exponent,
+            msb_in_token,
+            lsb_in_token,
+        }
+    }
+
+    /// Returns the binary logarithm of the number of values coded directly
+    /// as tokens.
+    #[must_use]
+    #[inline]
+    pub fn split_exponent(&self) -> u32 {
+        self.split_exponent
+    }
+
+    /// Returns the number of bits following the most significant one that
+    /// are stored in the token.
+    #[must_use]
+    #[inline]
+    pub fn msb_in_token(&self) -> u32 {
+        self.msb_in_token
+    }
+
+    /// Returns the number of least significant bits stored in the token.
+    #[must_use]
+    #[inline]
+    pub fn lsb_in_token(&self) -> u32 {
+        self.lsb_in_token
+    }
+
+    /// Returns the number of distinct tokens, i.e. the size of the alphabet
+    /// of the models used with this scheme.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::uint_coder::TokenScheme;
+    ///
+    /// assert_eq!(TokenScheme::EXP_GOLOMB.num_tokens(), 33);
+    /// assert_eq!(TokenScheme::default().num_tokens(), 128);
+    /// ```
+    #[must_use]
+    pub fn num_tokens(&self) -> usize {
+        self.tokenize(u32::MAX).0 as usize + 1
+    }
+
+    /// Maps given value to a `(token, num_bits, residual)` tuple, where the
+    /// residual is `num_bits` bits wide.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::uint_coder::TokenScheme;
+    ///
+    /// assert_eq!(TokenScheme::EXP_GOLOMB.tokenize(0), (0, 0, 0));
+    /// assert_eq!(TokenScheme::EXP_GOLOMB.tokenize(13), (4, 3, 0b101));
+    /// ```
+    #[must_use]
+    pub fn tokenize(&self, value: u32) -> (u32, u32, u32) {
+        let split = 1 << self.split_exponent;
+        if value < split {
+            return (value, 0, 0);
+        }
+
+        let in_token = self.msb_in_token + self.lsb_in_token;
+        let n = u32::BITS - 1 - value.leading_zeros();
+        let m = value - (1 << n);
+        let token = split
+            + ((n - self.split_exponent) << in_token)
+            + ((m >> (n - self.msb_in_token)) << self.lsb_in_token)
+            + (m & ((1 << self.lsb_in_token) - 1));
+        let num_bits = n - in_token;
+        let residual = (value >> self.lsb_in_token) & mask(num_bits);
+
+        (token, num_bits, residual)
+    }
+
+    /// Returns the number of residual bits stored along with given token.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::uint_coder::TokenScheme;
+    ///
+    /// let scheme = TokenScheme::new(4, 2, 0);
+    /// assert_eq!(scheme.residual_bits(15), 0);
+    /// assert_eq!(scheme.residual_bits(26), 4);
+    /// ```
+    #[must_use]
+    pub fn residual_bits(&self, token: u32) -> u32 {
+        let split = 1 << self.split_exponent;
+        if token < split {
+            return 0;
+        }
+
+        let in_token = self.msb_in_token + self.lsb_in_token;
+        self.split_exponent - in_token + ((token - split) >> in_token)
+    }
+
+    /// Reverses [`Self::tokenize()`], returning the value given token and
+    /// residual were created from.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::uint_coder::TokenScheme;
+    ///
+    /// assert_eq!(TokenScheme::EXP_GOLOMB.detokenize(4, 0b101), 13);
+    /// ```
+    #[must_use]
+    pub fn detokenize(&self, token: u32, residual: u32) -> u32 {
+        let split = 1 << self.split_exponent;
+        if token < split {
+            return token;
+        }
+
+        let num_bits = self.residual_bits(token);
+        let low = token & mask(self.lsb_in_token);
+        let msb = (token >> self.lsb_in_token) & mask(self.msb_in_token);
+        let high = (1 << self.msb_in_token) | msb;
+        (((high << num_bits) | residual) << self.lsb_in_token) | low
+    }
+
+    /// Counts the tokens of given values, returning a histogram with
+    /// [`Self::num_tokens()`] entries that can be normalized with
+    /// [`Model::from_counts()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::uint_coder::TokenScheme;
+    ///
+    /// let counts = TokenScheme::EXP_GOLOMB.histogram(&[0, 1, 2, 3, 1000]);
+    /// assert_eq!(counts[..4], [1, 1, 2, 0]);
+    /// assert_eq!(counts[10], 1);
+    /// ```
+    #[must_use]
+    pub fn histogram(&self, values: &[u32]) -> Vec<u32> {
+        let mut counts = vec![0; self.num_tokens()];
+        for &value in values {
+            counts[self.tokenize(value).0 as usize] += 1;
+        }
+        counts
+    }
+}
+
+impl Default for TokenScheme {
+    /// Returns the scheme with `split_exponent` of 4 and `msb_in_token` of 2,
+    /// which is a good general-purpose choice.
+    fn default() -> Self {
+        Self::new(4, 2, 0)
+    }
+}
+
+#[inline]
+fn mask(bits: u32) -> u32 {
+    if bits >= 32 {
+        u32::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// Encoder side of the hybrid integer coder: encoder symbols of the tokens
+/// along with the [`TokenScheme`] mapping the values to them.
+///
+/// # Examples
+/// ```
+/// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+/// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+/// use rans::model::Model;
+/// use rans::uint_coder::{TokenScheme, UintDecModel, UintEncModel};
+/// use rans::RansEncoderMulti;
+///
+/// let values = [3, 1, 70000, 12, 0, 5, 1];
+/// let scheme = TokenScheme::default();
+/// let model = Model::from_counts(&scheme.histogram(&values), 12);
+/// let enc_model = UintEncModel::<ByteRansEncSymbol>::new(&model, scheme);
+/// let dec_model = UintDecModel::<ByteRansDecSymbol>::new(&model, scheme);
+///
+/// let mut encoder = ByteRansEncoder::new(1024);
+/// for &value in values.iter().rev() {
+///     enc_model.put_at(&mut encoder, 0, value);
+/// }
+/// encoder.flush_all();
+///
+/// let mut decoder = ByteRansDecoder::new(encoder.data());
+/// for &value in &values {
+///     assert_eq!(dec_model.decode_at(&mut decoder, 0), value);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UintEncModel<S> {
+    enc_model: EncModel<S>,
+    scheme: TokenScheme,
+}
+
+impl<S: RansEncSymbol> UintEncModel<S> {
+    /// Creates encoder symbols for the tokens of given model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncSymbol;
+    /// use rans::model::Model;
+    /// use rans::uint_coder::{TokenScheme, UintEncModel};
+    ///
+    /// let model = Model::from_counts(&[1; 33], 8);
+    /// let enc_model = UintEncModel::<ByteRansEncSymbol>::new(&model, TokenScheme::EXP_GOLOMB);
+    /// assert_eq!(enc_model.scheme(), TokenScheme::EXP_GOLOMB);
+    /// ```
+    #[must_use]
+    pub fn new(model: &Model, scheme: TokenScheme) -> Self {
+        Self {
+            enc_model: EncModel::new(model),
+            scheme,
+        }
+    }
+
+    /// Puts given value into the specified channel as its token, followed by
+    /// the residual bits.
+    ///
+    /// Since the token has to be decoded before the residual, the residual
+    /// is put first.
+    ///
+    /// # Panics
+    /// Panics if the value's token is outside of the model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::model::Model;
+    /// use rans::uint_coder::{TokenScheme, UintEncModel};
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let model = Model::from_counts(&[1; 33], 8);
+    /// let enc_model = UintEncModel::<ByteRansEncSymbol>::new(&model, TokenScheme::EXP_GOLOMB);
+    ///
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// enc_model.put_at(&mut encoder, 0, 1 << 20);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.len(), 7);
+    /// ```
+    pub fn put_at<E, const N: usize>(&self, encoder: &mut E, channel: usize, value: u32)
+    where
+        E: RansEncoderMulti<N, Symbol = S>,
+    {
+        let (token, num_bits, residual) = self.scheme.tokenize(value);
+        assert!(
+            (token as usize) < self.enc_model.len(),
+            "token {token} is outside of the model"
+        );
+
+        encoder.put_bits_at(channel, residual, num_bits);
+        encoder.put_index_at(channel, token as usize, &self.enc_model);
+    }
+
+    /// Returns the token scheme used by this model.
+    #[must_use]
+    #[inline]
+    pub fn scheme(&self) -> TokenScheme {
+        self.scheme
+    }
+
+    /// Returns the encoder symbols of the tokens.
+    #[must_use]
+    #[inline]
+    pub fn enc_model(&self) -> &EncModel<S> {
+        &self.enc_model
+    }
+}
+
+/// Decoder side of the hybrid integer coder. See [`UintEncModel`].
+#[derive(Debug, Clone)]
+pub struct UintDecModel<S> {
+    dec_model: DecModel<S>,
+    scheme: TokenScheme,
+}
+
+impl<S: RansDecSymbol> UintDecModel<S> {
+    /// Creates decoder symbols and a lookup table for the tokens of given
+    /// model.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::ByteRansDecSymbol;
+    /// use rans::model::Model;
+    /// use rans::uint_coder::{TokenScheme, UintDecModel};
+    ///
+    /// let model = Model::from_counts(&[1; 128], 8);
+    /// let dec_model = UintDecModel::<ByteRansDecSymbol>::new(&model, TokenScheme::default());
+    /// assert_eq!(dec_model.dec_model().len(), 128);
+    /// ```
+    #[must_use]
+    pub fn new(model: &Model, scheme: TokenScheme) -> Self {
+        Self {
+            dec_model: DecModel::new(model),
+            scheme,
+        }
+    }
+
+    /// Decodes a single value at given channel, reading its token and then
+    /// the residual bits.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::model::Model;
+    /// use rans::uint_coder::{TokenScheme, UintDecModel, UintEncModel};
+    /// use rans::RansEncoderMulti;
+    ///
+    /// let model = Model::from_counts(&[1; 33], 8);
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// UintEncModel::<ByteRansEncSymbol>::new(&model, TokenScheme::EXP_GOLOMB)
+    ///     .put_at(&mut encoder, 0, 12345);
+    /// encoder.flush_all();
+    ///
+    /// let dec_model = UintDecModel::<ByteRansDecSymbol>::new(&model, TokenScheme::EXP_GOLOMB);
+    /// let mut decoder = ByteRansDecoder::new(encoder.data());
+    /// assert_eq!(dec_model.decode_at(&mut decoder, 0), 12345);
+    /// ```
+    pub fn decode_at<D, const N: usize>(&self, decoder: &mut D, channel: usize) -> u32
+    where
+        D: RansDecoderMulti<N, Symbol = S>,
+    {
+        let token = decoder.decode_symbol_at(channel, &self.dec_model) as u32;
+        let residual = decoder.get_bits_at(channel, self.scheme.residual_bits(token));
+        self.scheme.detokenize(token, residual)
+    }
+
+    /// Decodes a single value at given channel like [`Self::decode_at()`],
+    /// but checks that the data doesn't end first, so that malformed or
+    /// truncated data never makes the decoder read past the end of it.
+    ///
+    /// # Errors
+    /// Returns [`RansError::InputExhausted`] if there is not enough data left
+    /// to decode the value, in which case the decoder shouldn't be used
+    /// anymore.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_decoder::{ByteRansDecSymbol, ByteRansDecoder};
+    /// use rans::byte_encoder::{ByteRansEncSymbol, ByteRansEncoder};
+    /// use rans::model::Model;
+    /// use rans::uint_coder::{TokenScheme, UintDecModel, UintEncModel};
+    /// use rans::{RansEncoderMulti, RansError};
+    ///
+    /// let model = Model::from_counts(&[1; 33], 8);
+    /// let mut encoder = ByteRansEncoder::new(1024);
+    /// UintEncModel::<ByteRansEncSymbol>::new(&model, TokenScheme::EXP_GOLOMB)
+    ///     .put_at(&mut encoder, 0, 12345);
+    /// encoder.flush_all();
+    ///
+    /// let dec_model = UintDecModel::<ByteRansDecSymbol>::new(&model, TokenScheme::EXP_GOLOMB);
+    /// let mut decoder = ByteRansDecoder::new(encoder.data());
+    /// assert_eq!(dec_model.try_decode_at(&mut decoder, 0), Ok(12345));
+    /// assert_eq!(
+    ///     dec_model.try_decode_at(&mut decoder, 0),
+    ///     Err(RansError::InputExhausted)
+    /// );
+    /// ```
+    pub fn try_decode_at<D, const N: usize>(
+        &self,
+        decoder: &mut D,
+        channel: usize,
+    ) -> Result<u32, RansError>
+    where
+        D: RansDecoderMulti<N, Symbol = S>,
+    {
+        let scale_bits = self.dec_model.scale_bits();
+        let (token, symbol) = self.dec_model.lookup(decoder.get_at(channel, scale_bits));
+        decoder.try_advance_at(channel, symbol, scale_bits)?;
+        let token = token as u32;
+        let residual = decoder.try_get_bits_at(channel, self.scheme.residual_bits(token))?;
+        Ok(self.scheme.detokenize(token, residual))
+    }
+
+    /// Returns the token scheme used by this model.
+    #[must_use]
+    #[inline]
+    pub fn scheme(&self) -> TokenScheme {
+        self.scheme
+    }
+
+    /// Returns the decoder symbols of the tokens.
+    #[must_use]
+    #[inline]
+    pub fn dec_model(&self) -> &DecModel<S> {
+        &self.dec_model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::b64_decoder::{B64RansDecSymbol, B64RansDecoderMulti};
+    use crate::b64_encoder::{B64RansEncSymbol, B64RansEncoderMulti};
+    use crate::model::Model;
+    use crate::uint_coder::{TokenScheme, UintDecModel, UintEncModel};
+    use crate::RansEncoderMulti;
+
+    const SCHEMES: [TokenScheme; 5] = [
+        TokenScheme::EXP_GOLOMB,
+        TokenScheme::new(4, 2, 0),
+        TokenScheme::new(4, 1, 1),
+        TokenScheme::new(8, 0, 8),
+        TokenScheme::new(31, 0, 0),
+    ];
+
+    fn test_values() -> Vec<u32> {
+        let mut values: Vec<u32> = (0..300).collect();
+        for shift in 0..32 {
+            let power = 1_u32 << shift;
+            values.extend([power - 1, power, power | 1, power | (power >> 1)]);
+        }
+        values.push(u32::MAX);
+        values
+    }
+
+    #[test]
+    fn test_tokenize_round_trip() {
+        for scheme in SCHEMES {
+            let num_tokens = scheme.num_tokens();
+            for value in test_values() {
+                let (token, num_bits, residual) = scheme.tokenize(value);
+                assert!((token as usize) < num_tokens);
+                assert_eq!(scheme.residual_bits(token), num_bits);
+                assert!(num_bits == 32 || residual >> num_bits == 0);
+                assert_eq!(scheme.detokenize(token, residual), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let values = test_values();
+
+        for scheme in SCHEMES.into_iter().filter(|s| s.num_tokens() <= 1 << 12) {
+            let model = Model::from_counts(&scheme.histogram(&values), 16);
+            let enc_model = UintEncModel::<B64RansEncSymbol>::new(&model, scheme);
+            let dec_model = UintDecModel::<B64RansDecSymbol>::new(&model, scheme);
+
+            let mut encoder = B64RansEncoderMulti::<2>::new(1 << 16);
+            for (i, &value) in values.iter().enumerate().rev() {
+                enc_model.put_at(&mut encoder, 1 - i % 2, value);
+            }
+            encoder.flush_all();
+
+            let mut decoder = B64RansDecoderMulti::<2>::new(encoder.data());
+            for (i, &value) in values.iter().enumerate() {
+                assert_eq!(dec_model.decode_at(&mut decoder, i % 2), value);
+            }
+            assert!(decoder.is_exhausted());
+
+            let mut decoder = B64RansDecoderMulti::<2>::new(encoder.data());
+            for (i, &value) in values.iter().enumerate() {
+                assert_eq!(dec_model.try_decode_at(&mut decoder, i % 2), Ok(value));
+            }
+            assert!(decoder.is_exhausted());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "must not be larger than split_exponent")]
+    fn test_invalid_scheme() {
+        let _ = TokenScheme::new(2, 2, 1);
+    }
+}
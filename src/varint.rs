@@ -0,0 +1,53 @@
+use crate::RansError;
+
+/// Appends `value` to `buf` as an unsigned LEB128 integer.
+pub(crate) fn write_u64(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Reads an unsigned LEB128 integer from `data` starting at `*pos` and
+/// advances `*pos` past it.
+pub(crate) fn read_u64(data: &[u8], pos: &mut usize) -> Result<u64, RansError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos).ok_or(RansError::UnexpectedEof)?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(RansError::InvalidHeader)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::varint::{read_u64, write_u64};
+    use crate::RansError;
+
+    #[test]
+    fn test_round_trip() {
+        let values = [0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX];
+        let mut buf = Vec::new();
+        for &value in &values {
+            write_u64(&mut buf, value);
+        }
+
+        let mut pos = 0;
+        for &value in &values {
+            assert_eq!(read_u64(&buf, &mut pos), Ok(value));
+        }
+        assert_eq!(pos, buf.len());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut pos = 0;
+        assert_eq!(read_u64(&[0x80], &mut pos), Err(RansError::UnexpectedEof));
+    }
+}
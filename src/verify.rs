@@ -0,0 +1,439 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::ops::Range;
+
+use crate::model::{DecModel, Model};
+use crate::{
+    RansBackend, RansDecSymbol, RansDecoderMulti, RansEncSymbol, RansEncoderMulti, RansError,
+};
+
+/// Value put into a [`VerifyingEncoder`], recorded to check that it can be
+/// decoded back.
+#[derive(Debug, Clone, Copy)]
+enum Put {
+    Symbol {
+        channel: usize,
+        cum_freq: u32,
+        freq: u32,
+        scale_bits: u32,
+    },
+    Bits {
+        channel: usize,
+        value: u32,
+        num_bits: u32,
+    },
+    Uniform {
+        channel: usize,
+        value: u32,
+        n: u32,
+    },
+}
+
+impl Put {
+    fn channel(&self) -> usize {
+        match *self {
+            Put::Symbol { channel, .. }
+            | Put::Bits { channel, .. }
+            | Put::Uniform { channel, .. } => channel,
+        }
+    }
+}
+
+/// Encoder wrapper that decodes every flushed block back with the model
+/// and checks that it yields the same symbols.
+///
+/// Once all the channels have been flushed, the data encoded since the last
+/// verified block is decoded with the backend's decoder and a
+/// [`DecModel`] built from given [`Model`], the same way the actual decoder
+/// would do it. Every symbol put into the encoder has to be one of the
+/// model's symbols, while raw bits and uniform values (put with
+/// [`RansEncoderMulti::put_bits_at()`] and
+/// [`RansEncoderMulti::put_uniform_at()`]) are decoded with the
+/// corresponding decoder methods. This catches encoder symbols built from a
+/// different frequency table or with different `scale_bits` than the
+/// decoder's model, as well as channel and flushing order mistakes, which
+/// would otherwise only surface as garbage on the decoder side.
+///
+/// Verification makes encoding considerably slower and keeps all the symbols
+/// of the current block in memory, so it's meant for testing and debugging.
+/// The flushing methods panic if the verification fails, while the fallible
+/// ones return [`RansError::VerificationFailed`].
+///
+/// # Examples
+/// ```
+/// use rans::byte_encoder::ByteRansEncSymbol;
+/// use rans::model::{EncModel, Model};
+/// use rans::verify::VerifyingEncoder;
+/// use rans::{ByteBackend, RansEncoderMulti, RansError};
+///
+/// let model = Model::from_counts(&[1, 3], 4);
+/// let mut encoder = VerifyingEncoder::<ByteBackend, 1>::new(1024, &model);
+/// let enc_model = EncModel::<ByteRansEncSymbol>::new(&model);
+/// encoder.put_at(0, enc_model.symbol(1));
+/// encoder.put_bits_at(0, 0x1234, 16);
+/// assert_eq!(encoder.try_flush_all(), Ok(()));
+///
+/// // Symbols from a different model can't be decoded with this one
+/// let other_model = Model::from_counts(&[1, 1], 4);
+/// let enc_model = EncModel::<ByteRansEncSymbol>::new(&other_model);
+/// encoder.reset();
+/// encoder.put_at(0, enc_model.symbol(1));
+/// assert_eq!(encoder.try_flush_all(), Err(RansError::VerificationFailed));
+/// ```
+pub struct VerifyingEncoder<B: RansBackend, const N: usize> {
+    encoder: B::Encoder<N>,
+    dec_model: DecModel<B::DecSymbol>,
+    puts: Vec<Put>,
+    /// Channels flushed in the current block, in order
+    flushed: Vec<usize>,
+    /// Length of the data when the current block started
+    block_start: usize,
+}
+
+impl<B: RansBackend, const N: usize> VerifyingEncoder<B, N> {
+    /// Creates a new `VerifyingEncoder` instance that can contain `max_len`
+    /// bytes in the internal buffer and verifies the data using given model.
+    ///
+    /// # Panics
+    /// Panics if the model's `scale_bits` is larger than the backend's
+    /// [`RansBackend::MAX_SCALE_BITS`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::verify::VerifyingEncoder;
+    /// use rans::{B64Backend, RansEncoderMulti};
+    ///
+    /// let model = Model::from_counts(&[1, 3], 20);
+    /// let encoder = VerifyingEncoder::<B64Backend, 2>::new(1024, &model);
+    /// assert!(encoder.is_empty());
+    /// ```
+    #[must_use]
+    pub fn new(max_len: usize, model: &Model) -> Self {
+        Self::from_encoder(B::new_encoder(max_len), model)
+    }
+
+    /// Wraps given encoder, verifying the data using given model. The data
+    /// the encoder already contains is not verified.
+    ///
+    /// # Panics
+    /// Panics if the model's `scale_bits` is larger than the backend's
+    /// [`RansBackend::MAX_SCALE_BITS`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::byte_encoder::ByteRansEncoderMulti;
+    /// use rans::model::Model;
+    /// use rans::verify::VerifyingEncoder;
+    /// use rans::{ByteBackend, RansEncoderMulti};
+    ///
+    /// let model = Model::from_counts(&[1, 3], 8);
+    /// let encoder = ByteRansEncoderMulti::<2>::with_growable_buffer();
+    /// let mut encoder = VerifyingEncoder::<ByteBackend, 2>::from_encoder(encoder, &model);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.len(), 8);
+    /// ```
+    #[must_use]
+    pub fn from_encoder(encoder: B::Encoder<N>, model: &Model) -> Self {
+        let block_start = encoder.len();
+        Self {
+            encoder,
+            dec_model: DecModel::new(model),
+            puts: Vec::new(),
+            flushed: Vec::with_capacity(N),
+            block_start,
+        }
+    }
+
+    /// Returns a reference to the wrapped encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::verify::VerifyingEncoder;
+    /// use rans::{ByteBackend, RansEncoderMulti};
+    ///
+    /// let model = Model::from_counts(&[1, 3], 8);
+    /// let encoder = VerifyingEncoder::<ByteBackend, 2>::new(1024, &model);
+    /// assert_eq!(encoder.get_ref().capacity(), 1024);
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn get_ref(&self) -> &B::Encoder<N> {
+        &self.encoder
+    }
+
+    /// Consumes this wrapper, returning the wrapped encoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::model::Model;
+    /// use rans::verify::VerifyingEncoder;
+    /// use rans::{ByteBackend, RansEncoderMulti};
+    ///
+    /// let model = Model::from_counts(&[1, 3], 8);
+    /// let mut encoder = VerifyingEncoder::<ByteBackend, 1>::new(1024, &model);
+    /// encoder.flush_all();
+    /// assert_eq!(encoder.into_inner().data(), [0, 0, 128, 0]);
+    /// ```
+    #[must_use]
+    pub fn into_inner(self) -> B::Encoder<N> {
+        self.encoder
+    }
+
+    /// Records that given channel has been flushed, verifying the block if
+    /// it was the last one.
+    fn on_flush(&mut self, channel: usize) -> Result<(), RansError> {
+        self.flushed.push(channel);
+        if self.flushed.len() < N {
+            return Ok(());
+        }
+
+        let result = self.verify_block();
+        self.start_block();
+        result
+    }
+
+    fn start_block(&mut self) {
+        self.puts.clear();
+        self.flushed.clear();
+        self.block_start = self.encoder.len();
+    }
+
+    /// Decodes the current block and checks that it contains the recorded
+    /// values.
+    fn verify_block(&self) -> Result<(), RansError> {
+        // The channel flushed last is initialized first by the decoder
+        let mut channels = [None; N];
+        for (i, &channel) in self.flushed.iter().enumerate() {
+            let slot = channels
+                .get_mut(channel)
+                .ok_or(RansError::VerificationFailed)?;
+            if slot.replace(N - 1 - i).is_some() {
+                return Err(RansError::VerificationFailed);
+            }
+        }
+
+        let data = self.encoder.data();
+        let data = &data[..data.len() - self.block_start];
+        let mut decoder =
+            B::try_new_decoder::<N, _>(data).map_err(|_| RansError::VerificationFailed)?;
+        for put in self.puts.iter().rev() {
+            let channel = channels
+                .get(put.channel())
+                .copied()
+                .flatten()
+                .ok_or(RansError::VerificationFailed)?;
+            let matches = match *put {
+                Put::Symbol {
+                    cum_freq,
+                    freq,
+                    scale_bits,
+                    ..
+                } => {
+                    scale_bits == self.dec_model.scale_bits() && {
+                        let index = decoder.decode_symbol_at(channel, &self.dec_model);
+                        let symbol = self.dec_model.symbol(index);
+                        (symbol.cum_freq(), symbol.freq()) == (cum_freq, freq)
+                    }
+                }
+                Put::Bits {
+                    value, num_bits, ..
+                } => {
+                    let mask = u32::MAX >> (32 - num_bits.max(1));
+                    num_bits == 0 || decoder.get_bits_at(channel, num_bits) == value & mask
+                }
+                Put::Uniform { value, n, .. } => decoder.get_uniform_at(channel, n) == value,
+            };
+            if !matches {
+                return Err(RansError::VerificationFailed);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<B: RansBackend, const N: usize> RansEncoderMulti<N> for VerifyingEncoder<B, N> {
+    type Symbol = B::EncSymbol;
+
+    fn reset(&mut self) {
+        self.encoder.reset();
+        self.start_block();
+    }
+
+    fn clear(&mut self) {
+        self.encoder.clear();
+        self.start_block();
+    }
+
+    fn put_at(&mut self, channel: usize, symbol: &Self::Symbol) {
+        self.encoder.put_at(channel, symbol);
+        self.puts.push(Put::Symbol {
+            channel,
+            cum_freq: symbol.cum_freq(),
+            freq: symbol.freq(),
+            scale_bits: symbol.scale_bits(),
+        });
+    }
+
+    fn try_put_at(&mut self, channel: usize, symbol: &Self::Symbol) -> Result<(), RansError> {
+        self.encoder.try_put_at(channel, symbol)?;
+        self.puts.push(Put::Symbol {
+            channel,
+            cum_freq: symbol.cum_freq(),
+            freq: symbol.freq(),
+            scale_bits: symbol.scale_bits(),
+        });
+        Ok(())
+    }
+
+    fn put_bits_at(&mut self, channel: usize, value: u32, num_bits: u32) {
+        self.encoder.put_bits_at(channel, value, num_bits);
+        self.puts.push(Put::Bits {
+            channel,
+            value,
+            num_bits,
+        });
+    }
+
+    fn put_uniform_at(&mut self, channel: usize, value: u32, n: u32) {
+        self.encoder.put_uniform_at(channel, value, n);
+        self.puts.push(Put::Uniform { channel, value, n });
+    }
+
+    fn flush_at(&mut self, channel: usize) {
+        self.encoder.flush_at(channel);
+        assert!(
+            self.on_flush(channel).is_ok(),
+            "encoded data doesn't decode back to the same symbols"
+        );
+    }
+
+    fn try_flush_at(&mut self, channel: usize) -> Result<(), RansError> {
+        self.encoder.try_flush_at(channel)?;
+        self.on_flush(channel)
+    }
+
+    fn flush_section(&mut self) -> Range<usize> {
+        let start = self.block_start;
+        self.flush_all();
+        self.encoder.clear();
+        start..self.encoder.len()
+    }
+
+    fn data(&self) -> &[u8] {
+        self.encoder.data()
+    }
+
+    fn capacity(&self) -> usize {
+        self.encoder.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.encoder.reserve(additional);
+    }
+}
+
+impl<B: RansBackend, const N: usize> Debug for VerifyingEncoder<B, N>
+where
+    B::Encoder<N>: Debug,
+    B::DecSymbol: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerifyingEncoder")
+            .field("encoder", &self.encoder)
+            .field("dec_model", &self.dec_model)
+            .field("puts", &self.puts.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{EncModel, Model};
+    use crate::verify::VerifyingEncoder;
+    use crate::{
+        B64Backend, ByteBackend, RansBackend, RansEncSymbol, RansEncoderMulti, RansError,
+        SimdBackend,
+    };
+
+    fn check_valid<B: RansBackend>() {
+        let model = Model::from_counts(&[7, 1, 30, 2, 5, 11], 12);
+        let enc_model = EncModel::<B::EncSymbol>::new(&model);
+        let mut encoder = VerifyingEncoder::<B, 3>::new(1 << 16, &model);
+
+        for block in 0..5 {
+            for i in 0..1000 {
+                encoder.put_at(i % 3, enc_model.symbol((i * block) % 6));
+                if i % 100 == 0 {
+                    encoder.put_bits_at(i % 3, i as u32, 20);
+                }
+            }
+            encoder.put_uniform_at(2, 12345, 100_000);
+            encoder.put_bits_at(1, 0, 0);
+            if block % 2 == 0 {
+                encoder.flush_all();
+            } else {
+                for channel in [2, 0, 1] {
+                    assert_eq!(encoder.try_flush_at(channel), Ok(()));
+                }
+            }
+            encoder.clear();
+        }
+    }
+
+    fn check_invalid<B: RansBackend>() {
+        let model = Model::from_counts(&[7, 1, 30, 2, 5, 11], 12);
+        let other_scale_bits = Model::from_counts(&[7, 1, 30, 2, 5, 11], 10);
+        let other_freqs = Model::from_counts(&[7, 2, 30, 2, 5, 11], 12);
+
+        for other_model in [other_scale_bits, other_freqs] {
+            let enc_model = EncModel::<B::EncSymbol>::new(&other_model);
+            let mut encoder = VerifyingEncoder::<B, 2>::new(1 << 16, &model);
+            for i in 0..100 {
+                encoder.put_at(i % 2, enc_model.symbol(i % 6));
+            }
+            assert_eq!(encoder.try_flush_all(), Err(RansError::VerificationFailed));
+        }
+    }
+
+    #[test]
+    fn test_valid() {
+        check_valid::<ByteBackend>();
+        check_valid::<B64Backend>();
+        check_valid::<SimdBackend>();
+    }
+
+    #[test]
+    fn test_invalid() {
+        check_invalid::<ByteBackend>();
+        check_invalid::<B64Backend>();
+        check_invalid::<SimdBackend>();
+    }
+
+    #[test]
+    fn test_sections() {
+        let model = Model::from_counts(&[3, 4, 9], 4);
+        let mut encoder = VerifyingEncoder::<ByteBackend, 2>::new(1024, &model);
+        let symbol = <ByteBackend as RansBackend>::EncSymbol::new(3, 4, 4);
+
+        encoder.put_all(&[&symbol; 10]);
+        let first = encoder.flush_section();
+        encoder.put_all(&[&symbol; 20]);
+        let second = encoder.flush_section();
+        assert_eq!(first.end, second.start);
+        assert_eq!(second.end, encoder.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't decode back to the same symbols")]
+    fn test_mixed_up_channels() {
+        let model = Model::from_counts(&[3, 4, 9], 4);
+        let mut encoder = VerifyingEncoder::<ByteBackend, 2>::new(1024, &model);
+
+        encoder.put_bits_at(0, 0xffff, 16);
+        encoder.flush_at(0);
+        encoder.flush_at(0);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::simd_decoder::{SimdRansDecSymbol, SimdRansDecoder};
+
+/// Multi-stream interleaved rANS decoder - 16-bit word version.
+///
+/// This is the same type as
+/// [`SimdRansDecoder`], named
+/// consistently with the byte-aligned and 64-bit coders. The SIMD code paths
+/// are only used if the respective crate features are enabled; otherwise, it
+/// is a plain scalar decoder.
+///
+/// # Examples
+/// ```
+/// use rans::w16_decoder::W16RansDecoderMulti;
+/// use rans::RansDecoderMulti;
+///
+/// let mut decoder = W16RansDecoderMulti::<2>::new([108, 0, 0, 1, 0, 0, 0, 1]);
+/// assert_eq!(decoder.get_at(0, 4), 12);
+/// assert_eq!(decoder.get_at(1, 4), 0);
+/// ```
+pub type W16RansDecoderMulti<'a, const N: usize> = SimdRansDecoder<'a, N>;
+
+/// Single-stream rANS decoder - 16-bit word version.
+pub type W16RansDecoder<'a> = SimdRansDecoder<'a, 1>;
+
+/// rANS decoder symbol - 16-bit word version.
+pub type W16RansDecSymbol = SimdRansDecSymbol;
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{DecModel, EncModel, Model};
+    use crate::w16_decoder::{W16RansDecSymbol, W16RansDecoder};
+    use crate::w16_encoder::{W16RansEncSymbol, W16RansEncoder};
+    use crate::{RansDecoder, RansEncoder, RansEncoderMulti};
+
+    #[test]
+    fn test_round_trip() {
+        let model = Model::from_counts(&[40, 1, 7, 300, 2, 19], 16);
+        let enc_model = EncModel::<W16RansEncSymbol>::new(&model);
+        let dec_model = DecModel::<W16RansDecSymbol>::new(&model);
+        let symbols: Vec<usize> = (0..1000).map(|i| (i * i + 3 * i) % 6).collect();
+
+        let mut encoder = W16RansEncoder::new(4096);
+        for &symbol in symbols.iter().rev() {
+            encoder.put(enc_model.symbol(symbol));
+        }
+        encoder.flush();
+        // The output consists of whole 16-bit words
+        assert_eq!(encoder.len() % 2, 0);
+
+        let mut decoder = W16RansDecoder::new(encoder.data());
+        for &symbol in &symbols {
+            assert_eq!(decoder.decode_symbol(&dec_model), symbol);
+        }
+        assert!(decoder.is_exhausted());
+    }
+}
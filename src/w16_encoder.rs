@@ -0,0 +1,32 @@
+use crate::simd_encoder::{SimdRansEncSymbol, SimdRansEncoder};
+
+/// Multi-stream interleaved rANS encoder - 16-bit word version.
+///
+/// The states are 32 bits wide and are renormalized by emitting 16-bit
+/// little-endian words; the scale bits can't be larger than 16. This is the
+/// same bitstream (and the same type) as
+/// [`SimdRansEncoder`], named
+/// consistently with the byte-aligned and 64-bit coders.
+///
+/// # Examples
+/// ```
+/// use rans::w16_decoder::W16RansDecoderMulti;
+/// use rans::w16_encoder::{W16RansEncSymbol, W16RansEncoderMulti};
+/// use rans::{RansDecoderMulti, RansEncSymbol, RansEncoderMulti};
+///
+/// let mut encoder = W16RansEncoderMulti::<2>::new(1024);
+/// encoder.put_at(0, &W16RansEncSymbol::new(3, 1, 4));
+/// encoder.put_at(1, &W16RansEncSymbol::new(9, 1, 4));
+/// encoder.flush_all();
+///
+/// let mut decoder = W16RansDecoderMulti::<2>::new(encoder.data());
+/// assert_eq!(decoder.get_at(0, 4), 9);
+/// assert_eq!(decoder.get_at(1, 4), 3);
+/// ```
+pub type W16RansEncoderMulti<const N: usize> = SimdRansEncoder<N>;
+
+/// Single-stream rANS encoder - 16-bit word version.
+pub type W16RansEncoder = SimdRansEncoder<1>;
+
+/// rANS encoder symbol - 16-bit word version.
+pub type W16RansEncSymbol = SimdRansEncSymbol;
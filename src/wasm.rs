@@ -0,0 +1,259 @@
+use std::io::{Read, Write};
+
+use wasm_bindgen::prelude::*;
+
+use crate::stream::{RansReader, RansWriter};
+use crate::{simple, varint, RansError};
+
+/// Compresses a byte buffer with [`simple::compress()`].
+///
+/// # Examples
+/// ```
+/// use rans::wasm::{compress, decompress};
+///
+/// let compressed = compress(b"abracadabra");
+/// assert_eq!(decompress(&compressed).unwrap(), b"abracadabra");
+/// ```
+#[wasm_bindgen]
+#[must_use]
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    simple::compress(data)
+}
+
+/// Decompresses data produced by [`compress()`]. Throws an `Error` if the
+/// data is malformed.
+///
+/// # Errors
+/// Returns any of the errors of [`simple::decompress()`].
+///
+/// # Examples
+/// ```
+/// use rans::wasm::{compress, decompress};
+///
+/// let compressed = compress(b"hello");
+/// assert_eq!(decompress(&compressed).unwrap(), b"hello");
+/// ```
+#[wasm_bindgen]
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, JsError> {
+    Ok(simple::decompress(data)?)
+}
+
+/// Streaming compressor producing the format of
+/// [`RansWriter`], fed with chunks of data.
+///
+/// # Examples
+/// ```
+/// use rans::wasm::{StreamCompressor, StreamDecompressor};
+///
+/// let mut compressor = StreamCompressor::new();
+/// let mut compressed = compressor.push(b"hello, ");
+/// compressed.extend(compressor.push(b"world"));
+/// compressed.extend(compressor.finish());
+///
+/// let mut decompressor = StreamDecompressor::new();
+/// assert_eq!(decompressor.push(&compressed).unwrap(), b"hello, world");
+/// decompressor.finish().unwrap();
+/// ```
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct StreamCompressor {
+    writer: RansWriter<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl StreamCompressor {
+    /// Creates a new `StreamCompressor` instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::wasm::StreamCompressor;
+    ///
+    /// let compressor = StreamCompressor::new();
+    /// assert!(compressor.finish().is_empty());
+    /// ```
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            writer: RansWriter::new(Vec::new()),
+        }
+    }
+
+    /// Adds a chunk of data to the stream, returning the compressed data
+    /// produced so far. Data is only produced once a whole block is
+    /// collected, so the result is usually empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::stream::DEFAULT_BLOCK_SIZE;
+    /// use rans::wasm::StreamCompressor;
+    ///
+    /// let mut compressor = StreamCompressor::new();
+    /// assert!(compressor.push(b"hello").is_empty());
+    /// assert!(!compressor.push(&vec![0; DEFAULT_BLOCK_SIZE]).is_empty());
+    /// ```
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.writer
+            .write_all(chunk)
+            .expect("writing to a Vec never fails");
+        std::mem::take(self.writer.get_mut())
+    }
+
+    /// Finishes the stream, returning the rest of the compressed data.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::wasm::StreamCompressor;
+    ///
+    /// let mut compressor = StreamCompressor::new();
+    /// compressor.push(b"hello");
+    /// assert!(!compressor.finish().is_empty());
+    /// ```
+    #[must_use]
+    pub fn finish(self) -> Vec<u8> {
+        self.writer.finish().expect("writing to a Vec never fails")
+    }
+}
+
+impl Default for StreamCompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming decompressor of the format produced by [`StreamCompressor`] and
+/// [`RansWriter`], fed with chunks of compressed
+/// data as they arrive, e.g. from a `fetch()` response body.
+///
+/// Each block is decoded as soon as all its data is available, so only a
+/// single block of compressed data is buffered. As with
+/// [`RansReader`], the block checksums only
+/// protect against accidental corruption, so this should not be used for
+/// data coming from untrusted sources.
+#[wasm_bindgen]
+#[derive(Debug, Default)]
+pub struct StreamDecompressor {
+    /// Compressed data of the blocks that haven't been received fully yet
+    pending: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl StreamDecompressor {
+    /// Creates a new `StreamDecompressor` instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::wasm::StreamDecompressor;
+    ///
+    /// let decompressor = StreamDecompressor::new();
+    /// decompressor.finish().unwrap();
+    /// ```
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chunk of compressed data, returning the data of all the blocks
+    /// completed by it. Throws an `Error` if the data is malformed.
+    ///
+    /// # Errors
+    /// Returns an error if any of the completed blocks is malformed.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::wasm::{StreamCompressor, StreamDecompressor};
+    ///
+    /// let mut compressor = StreamCompressor::new();
+    /// compressor.push(b"hello");
+    /// let compressed = compressor.finish();
+    ///
+    /// let mut decompressor = StreamDecompressor::new();
+    /// let (first, second) = compressed.split_at(3);
+    /// assert_eq!(decompressor.push(first).unwrap(), b"");
+    /// assert_eq!(decompressor.push(second).unwrap(), b"hello");
+    /// ```
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.pending.extend_from_slice(chunk);
+
+        let mut output = Vec::new();
+        let mut start = 0;
+        loop {
+            let mut pos = start;
+            let len = match varint::read_u64(&self.pending, &mut pos) {
+                Ok(len) => len,
+                Err(RansError::UnexpectedEof) => break,
+                Err(error) => return Err(error.into()),
+            };
+            let end = usize::try_from(len)
+                .ok()
+                .and_then(|len| pos.checked_add(len))
+                .ok_or(RansError::InvalidHeader)?;
+            if end > self.pending.len() {
+                break;
+            }
+
+            RansReader::new(&self.pending[start..end]).read_to_end(&mut output)?;
+            start = end;
+        }
+
+        self.pending.drain(..start);
+        Ok(output)
+    }
+
+    /// Finishes the stream. Throws an `Error` if the last block is
+    /// incomplete.
+    ///
+    /// # Errors
+    /// Returns an error if the stream is truncated.
+    ///
+    /// # Examples
+    /// ```
+    /// use rans::wasm::{StreamCompressor, StreamDecompressor};
+    ///
+    /// let mut compressor = StreamCompressor::new();
+    /// compressor.push(b"hello");
+    /// let compressed = compressor.finish();
+    ///
+    /// let mut decompressor = StreamDecompressor::new();
+    /// decompressor.push(&compressed).unwrap();
+    /// decompressor.finish().unwrap();
+    /// ```
+    pub fn finish(self) -> Result<(), JsError> {
+        if !self.pending.is_empty() {
+            return Err(RansError::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stream::DEFAULT_BLOCK_SIZE;
+    use crate::wasm::{StreamCompressor, StreamDecompressor};
+
+    #[test]
+    fn test_stream_chunks() {
+        let data: Vec<u8> = (0..3 * DEFAULT_BLOCK_SIZE + 123)
+            .map(|i| (i % 251 * i % 7) as u8)
+            .collect();
+
+        let mut compressor = StreamCompressor::new();
+        let mut compressed = Vec::new();
+        for chunk in data.chunks(10000) {
+            compressed.extend(compressor.push(chunk));
+        }
+        compressed.extend(compressor.finish());
+
+        for chunk_size in [1, 7, 1000, compressed.len()] {
+            let mut decompressor = StreamDecompressor::new();
+            let mut decompressed = Vec::new();
+            for chunk in compressed.chunks(chunk_size) {
+                decompressed.extend(decompressor.push(chunk).unwrap());
+                assert!(decompressor.pending.len() < DEFAULT_BLOCK_SIZE);
+            }
+            decompressor.finish().unwrap();
+            assert_eq!(decompressed, data);
+        }
+    }
+}